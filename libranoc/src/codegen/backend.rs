@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{core::Types, semantic::Resolution, thir};
+
+use super::{component::Interface, *};
+
+/// A module checked and lowered to the typed IR, which is all a backend
+/// needs of it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Typed {
+    pub module: thir::Module,
+    pub resolution: Resolution,
+    pub types: Types,
+}
+
+/// What compiles the typed IR of a module into the artifacts of a target.
+pub trait Backend {
+    fn compile(&self, typed: Typed, options: &CompileOptions) -> (Artifacts, Vec<Error>);
+}
+
+/// The backend walking the typed IR into a WASM module, which is the one
+/// `compile_artifacts` uses.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Wasm;
+
+impl Backend for Wasm {
+    fn compile(&self, typed: Typed, options: &CompileOptions) -> (Artifacts, Vec<Error>) {
+        // the glue is made of the types of the exported functions, which
+        // the walk consumes.
+        let bindings = if options.bindings {
+            Some(js::bindings(&typed, options))
+        } else {
+            None
+        };
+        let interface = match &options.component {
+            Some(world) => match Interface::of(&typed, world, options) {
+                Ok(interface) => Some(interface),
+                Err(errors) => return (Artifacts::default(), errors),
+            },
+            None => None,
+        };
+        let context = walk_typed(typed, options, Placement::default());
+        let (mut artifacts, mut errors) = finish_artifacts(context, options);
+        artifacts.bindings = bindings;
+        if let (Some(interface), true) = (interface, errors.is_empty()) {
+            match interface.wrap(&artifacts.wasm) {
+                Ok(component) => artifacts.component = Some(component),
+                Err(mut wrapping) => errors.append(&mut wrapping),
+            }
+        }
+        (artifacts, errors)
+    }
+}
@@ -0,0 +1,712 @@
+//! The backend emitting portable C from the typed IR, for the targets no
+//! WASM runtime runs on, and to test the WASM backend against: the same
+//! functions compiled by both give the same results.
+//!
+//! It compiles what the `Cranelift` backend does: the functions at the top
+//! of a module working on numbers and booleans. Every function is named
+//! `rano_fn_` and its name, so one like `div` or `free` doesn't clash with
+//! the C library. A `pub fn` is exported under that name and declared by
+//! the header made along with the source, the others are `static`, and an
+//! `extern fn` is declared for the program to define, named after the
+//! `name` of its `#[link(...)]` if it has one. The integer arithmetic goes through the helpers at the top
+//! of the source, which do what the WASM instructions do where C leaves it
+//! undefined, like overflowing or shifting by the width of the type, and
+//! trap by `abort`.
+
+use std::{collections::HashMap, convert::TryFrom, fmt::Write};
+
+use crate::{
+    core::{
+        arena::Idx,
+        ast::{Literal, Name, Pattern},
+        NodeId, TypeId,
+    },
+    semantic::{Definition, DefinitionKind, Resolution},
+    syntax::Span,
+    thir::{
+        Binary, BinaryOperator, Block, Call, Expression, ExpressionKind, Function, If, Item, Let,
+        Statement, Unary, UnaryOperator,
+    },
+};
+
+use super::*;
+
+/// The backend emitting C source.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct C;
+
+impl Backend for C {
+    fn compile(&self, typed: Typed, options: &CompileOptions) -> (Artifacts, Vec<Error>) {
+        match emit_source(typed, options) {
+            Ok((source, header)) => (
+                Artifacts {
+                    c: Some(source),
+                    c_header: Some(header),
+                    ..Artifacts::default()
+                },
+                Vec::new(),
+            ),
+            Err(errors) => (Artifacts::default(), errors),
+        }
+    }
+}
+
+/// The source and the header declaring the `pub fn`s of it.
+fn emit_source(typed: Typed, options: &CompileOptions) -> Result<(String, String), Vec<Error>> {
+    let Typed {
+        module, resolution, ..
+    } = typed;
+    let mut errors = Vec::new();
+    let mut functions = Vec::new();
+    for statement in module.statements {
+        match statement {
+            Statement::Item(Item::Function(function)) => functions.push(function),
+            Statement::Item(Item::Use(_)) => {}
+            statement => errors.push(Error::unimplemented(statement)),
+        }
+    }
+
+    // every function is declared first, so the ones before it can call it.
+    let mut source = prelude();
+    let mut header = String::from(
+        "#ifndef RANO_H\n#define RANO_H\n\n#include <stdbool.h>\n#include <stdint.h>\n\n",
+    );
+    let mut names = HashMap::new();
+    for function in &functions {
+        match prototype(function) {
+            Ok((name, prototype)) => {
+                writeln!(source, "{};", prototype).unwrap();
+                if function.is_pub && !function.is_extern {
+                    writeln!(header, "{};", prototype).unwrap();
+                }
+                names.insert(function.id, name);
+            }
+            Err(error) => errors.push(error),
+        }
+    }
+    for function in functions {
+        if function.is_extern || !names.contains_key(&function.id) {
+            continue;
+        }
+        let overflow = match function.attribute("overflow") {
+            Some(attribute) => match Overflow::from_attribute(&attribute.value) {
+                Ok(overflow) => overflow,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            },
+            None => options.overflow,
+        };
+        let mut emitter = Emitter {
+            resolution: &resolution,
+            functions: &names,
+            overflow,
+            body: String::new(),
+            indent: 1,
+            temporaries: 0,
+            variables: HashMap::new(),
+        };
+        match emitter.function(function) {
+            Ok(function) => {
+                source.push('\n');
+                source.push_str(&function);
+            }
+            Err(error) => errors.push(error),
+        }
+    }
+    header.push_str("\n#endif\n");
+    if errors.is_empty() {
+        Ok((source, header))
+    } else {
+        Err(errors)
+    }
+}
+
+/// The includes and the helpers of the integer arithmetic, for both widths
+/// and each of the `Overflow` policies.
+/// The trap is `abort` itself, so the compiler knows it never returns.
+fn prelude() -> String {
+    let mut prelude = String::from(
+        "#include <stdbool.h>\n#include <stdint.h>\n#include <stdlib.h>\n\n\
+         #define rano_trap() abort()\n",
+    );
+    for bits in [32, 64] {
+        let (int, uint, min, max) = (
+            format!("int{}_t", bits),
+            format!("uint{}_t", bits),
+            format!("INT{}_MIN", bits),
+            format!("INT{}_MAX", bits),
+        );
+        let signature = |name: &str, result: &str| {
+            format!(
+                "static inline {} rano_{}_i{}({} a, {} b)",
+                result, name, bits, int, int
+            )
+        };
+        // the unsigned arithmetic wraps, where the signed one is undefined.
+        for (name, operator) in [("add", "+"), ("sub", "-"), ("mul", "*")] {
+            writeln!(
+                prelude,
+                "{} {{ return ({})(({})a {} ({})b); }}",
+                signature(&format!("{}_wrap", name), &int),
+                int,
+                uint,
+                operator,
+                uint
+            )
+            .unwrap();
+        }
+        writeln!(
+            prelude,
+            "{} {{ {} r = rano_add_wrap_i{}(a, b); return ((a ^ r) & (b ^ r)) < 0; }}",
+            signature("add_overflows", "bool"),
+            int,
+            bits
+        )
+        .unwrap();
+        writeln!(
+            prelude,
+            "{} {{ {} r = rano_sub_wrap_i{}(a, b); return ((a ^ b) & (a ^ r)) < 0; }}",
+            signature("sub_overflows", "bool"),
+            int,
+            bits
+        )
+        .unwrap();
+        writeln!(
+            prelude,
+            "{} {{ return a != 0 && ((a == -1 && b == {}) || rano_mul_wrap_i{}(a, b) / a != b); }}",
+            signature("mul_overflows", "bool"),
+            min,
+            bits
+        )
+        .unwrap();
+        for name in ["add", "sub", "mul"] {
+            writeln!(
+                prelude,
+                "{} {{ if (rano_{}_overflows_i{}(a, b)) rano_trap(); return rano_{}_wrap_i{}(a, b); }}",
+                signature(&format!("{}_trap", name), &int),
+                name,
+                bits,
+                name,
+                bits
+            )
+            .unwrap();
+            // the bound is the one of the sign of the left operand for the
+            // sum and the difference, and of the product otherwise.
+            let negative = if name == "mul" {
+                "(a < 0) != (b < 0)"
+            } else {
+                "a < 0"
+            };
+            writeln!(
+                prelude,
+                "{} {{ if (rano_{}_overflows_i{}(a, b)) return {} ? {} : {}; return rano_{}_wrap_i{}(a, b); }}",
+                signature(&format!("{}_saturate", name), &int),
+                name,
+                bits,
+                negative,
+                min,
+                max,
+                name,
+                bits
+            )
+            .unwrap();
+        }
+        // `/` is undefined for the minimum and `-1` like for a divisor of
+        // zero, so dividing by `-1` is negating instead.
+        writeln!(
+            prelude,
+            "{} {{ if (b == 0 || (a == {} && b == -1)) rano_trap(); return a / b; }}",
+            signature("div_trap", &int),
+            min
+        )
+        .unwrap();
+        writeln!(
+            prelude,
+            "{} {{ if (b == 0) rano_trap(); return b == -1 ? rano_sub_wrap_i{}(0, a) : a / b; }}",
+            signature("div_wrap", &int),
+            bits
+        )
+        .unwrap();
+        writeln!(
+            prelude,
+            "{} {{ if (b == 0) rano_trap(); return b == -1 ? (a == {} ? {} : -a) : a / b; }}",
+            signature("div_saturate", &int),
+            min,
+            max
+        )
+        .unwrap();
+        writeln!(
+            prelude,
+            "{} {{ if (b == 0 || (a == {} && b == -1)) rano_trap(); return a % b; }}",
+            signature("rem_trap", &int),
+            min
+        )
+        .unwrap();
+        for name in ["rem_wrap", "rem_saturate"] {
+            writeln!(
+                prelude,
+                "{} {{ if (b == 0) rano_trap(); return b == -1 ? 0 : a % b; }}",
+                signature(name, &int)
+            )
+            .unwrap();
+        }
+        writeln!(
+            prelude,
+            "{} {{ return ({})(({})a << (b & {})); }}",
+            signature("shl", &int),
+            int,
+            uint,
+            bits - 1
+        )
+        .unwrap();
+        writeln!(
+            prelude,
+            "{} {{ return a >> (b & {}); }}",
+            signature("shr", &int),
+            bits - 1
+        )
+        .unwrap();
+    }
+    prelude.push('\n');
+    prelude
+}
+
+/// The C type of the values of `ty`, unless the backend doesn't compile
+/// them yet or they are the unit.
+fn c_type(ty: TypeId) -> Option<&'static str> {
+    Some(match ty {
+        TypeId::I32 => "int32_t",
+        TypeId::I64 => "int64_t",
+        TypeId::F32 => "float",
+        TypeId::F64 => "double",
+        TypeId::BOOL => "bool",
+        _ => return None,
+    })
+}
+
+/// The name `function` is called by in C, with its prototype.
+fn prototype(function: &Function) -> Result<(String, String), Error> {
+    if let Some(parameter) = function.generic_parameters.first() {
+        return Err(Error::unimplemented(parameter));
+    }
+    let result = match function.return_type {
+        TypeId::UNIT | TypeId::NEVER => "void",
+        ty => c_type(ty).ok_or_else(|| Error::unimplemented(&function.name))?,
+    };
+    let mut parameters = Vec::new();
+    for (index, (pattern, ty)) in function.parameters.iter().enumerate() {
+        let ty = c_type(*ty).ok_or_else(|| Error::unimplemented(pattern))?;
+        parameters.push(format!("{} {}", ty, parameter_name(pattern, index)));
+    }
+    if parameters.is_empty() {
+        parameters.push("void".to_owned());
+    }
+    let name = match function.attribute("link") {
+        Some(link) if function.is_extern => link_of(&link.value, &function.name)?.1,
+        _ if function.is_extern => function.name.content.clone(),
+        _ => format!("rano_fn_{}", function.name.content),
+    };
+    let storage = if function.is_extern {
+        "extern "
+    } else if function.is_pub {
+        ""
+    } else {
+        "static "
+    };
+    let prototype = format!("{}{} {}({})", storage, result, name, parameters.join(", "));
+    Ok((name, prototype))
+}
+
+/// The name of the `index`th parameter, after the one it is given, which
+/// can't be a keyword of C.
+fn parameter_name(pattern: &Pattern, index: usize) -> String {
+    match pattern {
+        Pattern::Slot(Name::Ident(name)) => format!("{}_p{}", name.content, index),
+        _ => format!("p{}", index),
+    }
+}
+
+/// What a variable of the function holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Binding {
+    Parameter(u32),
+    Local(Idx<Definition>),
+}
+
+/// Emits the body of a function. Every value but a literal is kept in a
+/// temporary as it is computed, so the operands are evaluated from left to
+/// right, as the WASM backend does, where C doesn't tell in which order.
+struct Emitter<'t> {
+    resolution: &'t Resolution,
+    /// The name of each function declared, by its id.
+    functions: &'t HashMap<NodeId, String>,
+    /// What the integer arithmetic of the function does on overflow.
+    overflow: Overflow,
+    body: String,
+    indent: usize,
+    temporaries: u32,
+    variables: HashMap<Binding, String>,
+}
+
+impl<'t> Emitter<'t> {
+    fn function(&mut self, function: Function) -> Result<String, Error> {
+        let (_, prototype) = prototype(&function)?;
+        for (index, (pattern, _)) in function.parameters.iter().enumerate() {
+            self.variables.insert(
+                Binding::Parameter(index as u32),
+                parameter_name(pattern, index),
+            );
+        }
+        if let Some(block) = function.body {
+            if let Some(value) = self.block(block)? {
+                self.line(format!("return {};", value));
+            }
+        }
+        Ok(format!("{} {{\n{}}}\n", prototype, self.body))
+    }
+
+    fn line(&mut self, line: impl AsRef<str>) {
+        for _ in 0..self.indent {
+            self.body.push_str("    ");
+        }
+        self.body.push_str(line.as_ref());
+        self.body.push('\n');
+    }
+
+    /// A new temporary of the C type `ty`, holding `value` if there is one.
+    fn temporary(&mut self, ty: &str, value: Option<String>) -> String {
+        let name = format!("t{}", self.temporaries);
+        self.temporaries += 1;
+        match value {
+            Some(value) => self.line(format!("{} {} = {};", ty, name, value)),
+            None => self.line(format!("{} {};", ty, name)),
+        }
+        name
+    }
+
+    /// Emits the statements of `block`, giving its value.
+    fn block(&mut self, block: Block) -> Result<Option<String>, Error> {
+        for statement in block.statements {
+            match statement {
+                Statement::Let(r#let) => self.r#let(r#let)?,
+                Statement::Expression(expression) => {
+                    self.expression(expression)?;
+                }
+                Statement::Item(item) => return Err(Error::unimplemented(item)),
+            }
+        }
+        match block.value {
+            Some(value) => self.expression(*value),
+            None => Ok(None),
+        }
+    }
+
+    fn r#let(&mut self, r#let: Let) -> Result<(), Error> {
+        let span = r#let.span.clone();
+        let value = match r#let.value {
+            Some(value) => self.expression(value)?,
+            None => None,
+        };
+        match (&r#let.pattern, self.resolution.lets.get(r#let.id)) {
+            (Pattern::Slot(Name::Placeholder(_)), _) => Ok(()),
+            (Pattern::Slot(Name::Ident(name)), Some(definition)) => {
+                let ty = c_type(r#let.ty).ok_or_else(|| Error::unimplemented(&span))?;
+                let variable = format!("{}_{}", name.content, definition.index());
+                match value {
+                    Some(value) => self.line(format!("{} {} = {};", ty, variable, value)),
+                    None => self.line(format!("{} {};", ty, variable)),
+                }
+                self.variables.insert(Binding::Local(*definition), variable);
+                Ok(())
+            }
+            _ => Err(Error::unimplemented(&span)),
+        }
+    }
+
+    /// The C expression giving the value of `expression`, which is a
+    /// literal or a temporary, unless it is the unit or never finishes.
+    fn expression(&mut self, expression: Expression) -> Result<Option<String>, Error> {
+        let Expression { kind, ty, span, id } = expression;
+        match kind {
+            ExpressionKind::Literal(literal) => self.literal(ty, literal, span).map(Some),
+            ExpressionKind::Name(Name::Ident(_)) => {
+                let variable = self
+                    .binding(id)
+                    .and_then(|binding| self.variables.get(&binding).cloned())
+                    .ok_or_else(|| Error::unimplemented(&span))?;
+                let ty = c_type(ty).ok_or_else(|| Error::unimplemented(&span))?;
+                Ok(Some(self.temporary(ty, Some(variable))))
+            }
+            ExpressionKind::Tuple(elements) if elements.is_empty() => Ok(None),
+            ExpressionKind::Unary(unary) => self.unary(ty, unary).map(Some),
+            ExpressionKind::Binary(binary) => self.binary(ty, binary).map(Some),
+            ExpressionKind::Assign(assign) => {
+                let variable = match &assign.target.kind {
+                    ExpressionKind::Name(Name::Ident(_)) => self
+                        .binding(assign.target.id)
+                        .and_then(|binding| self.variables.get(&binding).cloned()),
+                    _ => None,
+                }
+                .ok_or_else(|| Error::unimplemented(&*assign.target))?;
+                let value = self.operand(*assign.value)?;
+                self.line(format!("{} = {};", variable, value));
+                Ok(None)
+            }
+            ExpressionKind::Call(call) => self.call(ty, call),
+            ExpressionKind::If(r#if) => self.r#if(ty, r#if),
+            ExpressionKind::Block(block) => self.scoped(ty, block),
+            ExpressionKind::Unsafe(r#unsafe) => self.scoped(ty, r#unsafe.block),
+            ExpressionKind::Todo(_) => {
+                self.line("rano_trap();");
+                Ok(None)
+            }
+            _ => Err(Error::unimplemented(&span)),
+        }
+    }
+
+    /// The value of an operand, which has to have one.
+    fn operand(&mut self, expression: Expression) -> Result<String, Error> {
+        let span = expression.span.clone();
+        self.expression(expression)?
+            .ok_or_else(|| Error::unimplemented(&span))
+    }
+
+    /// What the name expression `id` refers to, if it is a parameter or a
+    /// local.
+    fn binding(&self, id: NodeId) -> Option<Binding> {
+        let definition = self.resolution.target(*self.resolution.references.get(id)?);
+        match self.resolution.definitions[definition].kind {
+            DefinitionKind::Parameter { index, .. } => Some(Binding::Parameter(index)),
+            DefinitionKind::Local => Some(Binding::Local(definition)),
+            _ => None,
+        }
+    }
+
+    fn literal(&mut self, ty: TypeId, literal: Literal, span: Span) -> Result<String, Error> {
+        Ok(match literal {
+            // the least value is written as an expression, as its negation
+            // isn't a literal of the type in C.
+            Literal::Integer(integer) => {
+                let value = integer.value();
+                let span = integer.0.span;
+                if ty == TypeId::I64 {
+                    match value.and_then(|value| i64::try_from(value).ok()) {
+                        Some(i64::MIN) => "INT64_MIN".to_owned(),
+                        Some(value) => format!("INT64_C({})", value),
+                        None => return Err(Error::literal_out_of_range(span, "i64")),
+                    }
+                } else {
+                    match value.and_then(|value| i32::try_from(value).ok()) {
+                        Some(i32::MIN) => "INT32_MIN".to_owned(),
+                        Some(value) => format!("INT32_C({})", value),
+                        None => return Err(Error::literal_out_of_range(span, "i32")),
+                    }
+                }
+            }
+            // the token is digits around a point, which both parse, and
+            // written back as they parsed so C reads the same value.
+            Literal::Decimal(token) => {
+                if ty == TypeId::F32 {
+                    let value: f32 = token.content.parse().expect("a decimal parses");
+                    format!("{:?}f", value)
+                } else {
+                    let value: f64 = token.content.parse().expect("a decimal parses");
+                    format!("{:?}", value)
+                }
+            }
+            Literal::Boolean(token) => token.content,
+            Literal::String(_) | Literal::Character(_) => return Err(Error::unimplemented(&span)),
+        })
+    }
+
+    /// The helper doing the integer `operation` of the type `ty`.
+    fn helper(ty: TypeId, operation: &str) -> String {
+        let bits = if ty == TypeId::I64 { 64 } else { 32 };
+        format!("rano_{}_i{}", operation, bits)
+    }
+
+    /// The helper doing `+`, `-`, `*`, `/` or `%`, which does what the
+    /// function does on overflow.
+    fn arithmetic(&self, ty: TypeId, operation: &str) -> String {
+        let policy = match self.overflow {
+            Overflow::Trap => "trap",
+            Overflow::Wrap => "wrap",
+            Overflow::Saturate => "saturate",
+        };
+        Emitter::helper(ty, &format!("{}_{}", operation, policy))
+    }
+
+    fn unary(&mut self, ty: TypeId, unary: Unary) -> Result<String, Error> {
+        let operand_type = unary.operand.ty;
+        let span = unary.operator_span.clone();
+        let operand = self.operand(*unary.operand)?;
+        let value = match (unary.operator, operand_type) {
+            (UnaryOperator::Plus, _) => operand,
+            (UnaryOperator::Minus, TypeId::I32 | TypeId::I64) => {
+                format!("{}(0, {})", self.arithmetic(operand_type, "sub"), operand)
+            }
+            (UnaryOperator::Minus, TypeId::F32 | TypeId::F64) => format!("-{}", operand),
+            (UnaryOperator::Not, TypeId::BOOL) => format!("!{}", operand),
+            (UnaryOperator::Not, TypeId::I32 | TypeId::I64) => format!("~{}", operand),
+            _ => return Err(Error::unimplemented(&span)),
+        };
+        let ty = c_type(ty).ok_or_else(|| Error::unimplemented(&span))?;
+        Ok(self.temporary(ty, Some(value)))
+    }
+
+    fn binary(&mut self, ty: TypeId, binary: Binary) -> Result<String, Error> {
+        let Binary {
+            operator,
+            operator_span,
+            lhs,
+            rhs,
+        } = binary;
+        let operand_type = lhs.ty;
+        if rhs.ty != operand_type {
+            return Err(Error::unimplemented(&operator_span));
+        }
+        // both operands are evaluated, as the WASM backend does.
+        let lhs = self.operand(*lhs)?;
+        let rhs = self.operand(*rhs)?;
+        let call = |helper: String| format!("{}({}, {})", helper, lhs, rhs);
+        let infix = |operator: &str| format!("{} {} {}", lhs, operator, rhs);
+        let value = match (operand_type, operator) {
+            (TypeId::I32 | TypeId::I64, BinaryOperator::Add) => {
+                call(self.arithmetic(operand_type, "add"))
+            }
+            (TypeId::I32 | TypeId::I64, BinaryOperator::Subtract) => {
+                call(self.arithmetic(operand_type, "sub"))
+            }
+            (TypeId::I32 | TypeId::I64, BinaryOperator::Multiply) => {
+                call(self.arithmetic(operand_type, "mul"))
+            }
+            (TypeId::I32 | TypeId::I64, BinaryOperator::Divide) => {
+                call(self.arithmetic(operand_type, "div"))
+            }
+            (TypeId::I32 | TypeId::I64, BinaryOperator::Remainder) => {
+                call(self.arithmetic(operand_type, "rem"))
+            }
+            (TypeId::I32 | TypeId::I64, BinaryOperator::ShiftLeft) => {
+                call(Emitter::helper(operand_type, "shl"))
+            }
+            (TypeId::I32 | TypeId::I64, BinaryOperator::ShiftRight) => {
+                call(Emitter::helper(operand_type, "shr"))
+            }
+            (TypeId::I32 | TypeId::I64 | TypeId::BOOL, BinaryOperator::BitAnd)
+            | (TypeId::BOOL, BinaryOperator::LogicalAnd) => infix("&"),
+            (TypeId::I32 | TypeId::I64 | TypeId::BOOL, BinaryOperator::BitOr)
+            | (TypeId::BOOL, BinaryOperator::LogicalOr) => infix("|"),
+            (TypeId::I32 | TypeId::I64 | TypeId::BOOL, BinaryOperator::BitXor) => infix("^"),
+            (TypeId::F32 | TypeId::F64, BinaryOperator::Add) => infix("+"),
+            (TypeId::F32 | TypeId::F64, BinaryOperator::Subtract) => infix("-"),
+            (TypeId::F32 | TypeId::F64, BinaryOperator::Multiply) => infix("*"),
+            (TypeId::F32 | TypeId::F64, BinaryOperator::Divide) => infix("/"),
+            (
+                TypeId::I32 | TypeId::I64 | TypeId::BOOL | TypeId::F32 | TypeId::F64,
+                BinaryOperator::EqualTo,
+            ) => infix("=="),
+            (
+                TypeId::I32 | TypeId::I64 | TypeId::BOOL | TypeId::F32 | TypeId::F64,
+                BinaryOperator::NotEqualTo,
+            ) => infix("!="),
+            (
+                TypeId::I32 | TypeId::I64 | TypeId::F32 | TypeId::F64,
+                BinaryOperator::GreaterThan,
+            ) => infix(">"),
+            (TypeId::I32 | TypeId::I64 | TypeId::F32 | TypeId::F64, BinaryOperator::LessThan) => {
+                infix("<")
+            }
+            (
+                TypeId::I32 | TypeId::I64 | TypeId::F32 | TypeId::F64,
+                BinaryOperator::GreaterThanOrEqualTo,
+            ) => infix(">="),
+            (
+                TypeId::I32 | TypeId::I64 | TypeId::F32 | TypeId::F64,
+                BinaryOperator::LessThanOrEqualTo,
+            ) => infix("<="),
+            _ => return Err(Error::unimplemented(&operator_span)),
+        };
+        let ty = c_type(ty).ok_or_else(|| Error::unimplemented(&operator_span))?;
+        Ok(self.temporary(ty, Some(value)))
+    }
+
+    fn call(&mut self, ty: TypeId, call: Call) -> Result<Option<String>, Error> {
+        let span = call.span.clone();
+        let function = match &call.callee.kind {
+            ExpressionKind::Name(Name::Ident(_)) => self
+                .resolution
+                .references
+                .get(call.callee.id)
+                .map(|definition| {
+                    &self.resolution.definitions[self.resolution.target(*definition)].kind
+                }),
+            _ => None,
+        };
+        let name = match function {
+            Some(DefinitionKind::Function { id, .. }) => self.functions.get(id),
+            _ => None,
+        }
+        .cloned()
+        .ok_or_else(|| Error::unimplemented(&span))?;
+        let mut arguments = Vec::new();
+        for argument in call.arguments {
+            arguments.push(self.operand(argument)?);
+        }
+        let call = format!("{}({})", name, arguments.join(", "));
+        match (ty, c_type(ty)) {
+            (_, Some(ty)) => Ok(Some(self.temporary(ty, Some(call)))),
+            (TypeId::UNIT | TypeId::NEVER, None) => {
+                self.line(format!("{};", call));
+                Ok(None)
+            }
+            _ => Err(Error::unimplemented(&span)),
+        }
+    }
+
+    /// The temporary a block with a value of the type `ty` keeps it in,
+    /// unless it is the unit or never finishes.
+    fn result(&mut self, ty: TypeId, span: &Span) -> Result<Option<String>, Error> {
+        match ty {
+            TypeId::UNIT | TypeId::NEVER => Ok(None),
+            ty => {
+                let ty = c_type(ty).ok_or_else(|| Error::unimplemented(span))?;
+                Ok(Some(self.temporary(ty, None)))
+            }
+        }
+    }
+
+    /// Emits `block` in a block of C, so its locals are its own, and the
+    /// value of it to `result`.
+    fn enclosed(&mut self, block: Block, result: &Option<String>) -> Result<(), Error> {
+        self.indent += 1;
+        let value = self.block(block)?;
+        if let (Some(result), Some(value)) = (result, value) {
+            self.line(format!("{} = {};", result, value));
+        }
+        self.indent -= 1;
+        Ok(())
+    }
+
+    fn scoped(&mut self, ty: TypeId, block: Block) -> Result<Option<String>, Error> {
+        let result = self.result(ty, &block.span)?;
+        self.line("{");
+        self.enclosed(block, &result)?;
+        self.line("}");
+        Ok(result)
+    }
+
+    fn r#if(&mut self, ty: TypeId, r#if: If) -> Result<Option<String>, Error> {
+        let result = self.result(ty, &r#if.body.span)?;
+        let condition = self.operand(*r#if.condition)?;
+        self.line(format!("if ({}) {{", condition));
+        self.enclosed(r#if.body, &result)?;
+        if let Some(block) = r#if.else_block {
+            self.line("} else {");
+            self.enclosed(block, &result)?;
+        }
+        self.line("}");
+        Ok(result)
+    }
+}
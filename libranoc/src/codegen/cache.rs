@@ -0,0 +1,67 @@
+use std::{fs, path::PathBuf};
+
+use crate::core::{
+    ast::Module,
+    ir::{read_ir, write_ir, IrKind, FORMAT_VERSION},
+};
+
+use super::*;
+
+/// The typed IR of the modules compiled before, kept on disk by a hash of
+/// their configured AST, so a module which didn't change since isn't
+/// resolved and checked again. Only the ones without errors are kept.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    directory: PathBuf,
+}
+
+impl Cache {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Cache {
+            directory: directory.into(),
+        }
+    }
+
+    /// The typed IR of the configured `module`, from the cache if it is
+    /// there, or checked and put in it if it isn't.
+    pub(super) fn check(
+        &self,
+        module: Module,
+        options: &CompileOptions,
+    ) -> Result<Typed, Vec<Error>> {
+        let path = match write_ir(IrKind::Ast, &module) {
+            Ok(mut bytes) => {
+                // folding changes the IR, so the levels which fold and the
+                // ones which don't keep their own.
+                bytes.push(options.opt_level.fold_constants() as u8);
+                // and so may another build of the compiler, even if the
+                // format of what it writes stays the same.
+                bytes.extend_from_slice(env!("CARGO_PKG_VERSION").as_bytes());
+                bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+                self.directory
+                    .join(format!("{:016x}.ir", content_hash(&bytes)))
+            }
+            Err(_) => return check_module(module, options),
+        };
+        if let Some(typed) = fs::read(&path)
+            .ok()
+            .and_then(|bytes| read_ir(IrKind::Typed, &bytes).ok())
+        {
+            return Ok(typed);
+        }
+        let typed = check_module(module, options)?;
+        // a cache which can't be written only makes the next build slower.
+        if let Ok(bytes) = write_ir(IrKind::Typed, &typed) {
+            let _ = fs::create_dir_all(&self.directory).and_then(|_| fs::write(&path, bytes));
+        }
+        Ok(typed)
+    }
+}
+
+/// The FNV-1a hash of `bytes`, which is the same in every build of the
+/// compiler, unlike the hasher of `std`.
+fn content_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
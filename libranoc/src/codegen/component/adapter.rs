@@ -0,0 +1,303 @@
+use wasm_encoder::{
+    CodeSection, EntityType, Export, ExportSection, Function, FunctionSection, ImportSection,
+    Instruction, Limits, MemArg, MemoryType, Module, TypeSection, ValType,
+};
+use wasmparser::{FuncType, Type as CoreType};
+
+use super::{InterfaceFunction, ValueType};
+
+/// The core type of the values of `ty`, as the module passes them.
+fn value_type(ty: &CoreType) -> ValType {
+    match ty {
+        CoreType::I64 => ValType::I64,
+        CoreType::F32 => ValType::F32,
+        CoreType::F64 => ValType::F64,
+        CoreType::V128 => ValType::V128,
+        _ => ValType::I32,
+    }
+}
+
+fn value_types(types: &[CoreType]) -> Vec<ValType> {
+    types.iter().map(value_type).collect()
+}
+
+/// Adds `instructions` to the body of `function`, in order.
+fn emit(function: &mut Function, instructions: Vec<Instruction>) {
+    for instruction in instructions {
+        function.instruction(instruction);
+    }
+}
+
+/// The words the adapter reads and writes are only aligned to 4 bytes.
+fn word(offset: u32) -> MemArg {
+    MemArg {
+        offset,
+        align: 2,
+        memory_index: 0,
+    }
+}
+
+/// The module giving the module its imports, in the order of `shims`,
+/// exporting each by its index. It imports the functions of the component,
+/// lowered, from `host` by their names.
+pub(super) fn shim_module(imports: &[&InterfaceFunction], shims: &[(usize, FuncType)]) -> Module {
+    let mut types = TypeSection::new();
+    let mut import_section = ImportSection::new();
+    for (index, function) in imports.iter().enumerate() {
+        let parameters: Vec<_> = function
+            .parameters
+            .iter()
+            .flat_map(|(_, ty)| ty.flatten())
+            .collect();
+        let results: Vec<_> = function
+            .result
+            .iter()
+            .flat_map(ValueType::flatten)
+            .collect();
+        types.function(parameters, results);
+        import_section.import(
+            "host",
+            Some(&function.label),
+            EntityType::Function(index as u32),
+        );
+    }
+    let mut functions = FunctionSection::new();
+    let mut exports = ExportSection::new();
+    let mut code = CodeSection::new();
+    for (index, (imported, ty)) in shims.iter().enumerate() {
+        let ty_index = (imports.len() + index) as u32;
+        types.function(value_types(&ty.params), value_types(&ty.returns));
+        functions.function(ty_index);
+        let function_index = (imports.len() + index) as u32;
+        exports.export(&index.to_string(), Export::Function(function_index));
+        let mut function = Function::new(vec![]);
+        for parameter in 0..ty.params.len() as u32 {
+            function.instruction(Instruction::LocalGet(parameter));
+        }
+        function.instruction(Instruction::Call(*imported as u32));
+        // the module takes the unit as an `i32`, which the component
+        // doesn't give.
+        if imports[*imported].result.is_none() {
+            for _ in ty.returns.iter() {
+                function.instruction(Instruction::I32Const(0));
+            }
+        }
+        function.instruction(Instruction::End);
+        code.function(&function);
+    }
+    let mut module = Module::new();
+    module.section(&types);
+    module.section(&import_section);
+    module.section(&functions);
+    module.section(&exports);
+    module.section(&code);
+    module
+}
+
+/// The functions the adapter imports from the module, which its own come
+/// after.
+const MALLOC: u32 = 0;
+const REALLOC: u32 = 1;
+const FREE: u32 = 2;
+const RELEASE: u32 = 3;
+
+/// The module adapting the `exports` of the module, which it imports from
+/// `main` along with its memory and runtime if `memory`, to the canonical
+/// ABI. It exports a function of each name, the post-return `post_{name}`
+/// of the ones giving back a string, and `cabi_realloc`.
+pub(super) fn adapter_module(exports: &[(&InterfaceFunction, FuncType)], memory: bool) -> Module {
+    let mut types = TypeSection::new();
+    let mut import_section = ImportSection::new();
+    let mut type_count = 0;
+    let mut function_type = |params: Vec<ValType>, results: Vec<ValType>| {
+        types.function(params, results);
+        type_count += 1;
+        type_count - 1
+    };
+    let mut imported = 0;
+    if memory {
+        import_section.import(
+            "main",
+            Some("memory"),
+            EntityType::Memory(MemoryType {
+                limits: Limits { min: 0, max: None },
+            }),
+        );
+        let malloc = function_type(vec![ValType::I32], vec![ValType::I32]);
+        let realloc = function_type(vec![ValType::I32, ValType::I32], vec![ValType::I32]);
+        let free = function_type(vec![ValType::I32], vec![]);
+        import_section.import("main", Some("rano_malloc"), EntityType::Function(malloc));
+        import_section.import("main", Some("rano_realloc"), EntityType::Function(realloc));
+        import_section.import("main", Some("rano_free"), EntityType::Function(free));
+        import_section.import("main", Some("rano_release"), EntityType::Function(free));
+        imported = 4;
+    }
+    let mut adapted = Vec::new();
+    for (function, ty) in exports {
+        let ty = function_type(value_types(&ty.params), value_types(&ty.returns));
+        import_section.import("main", Some(&function.name), EntityType::Function(ty));
+        adapted.push(imported);
+        imported += 1;
+    }
+
+    let mut functions = FunctionSection::new();
+    let mut export_section = ExportSection::new();
+    let mut code = CodeSection::new();
+    let mut defined = imported;
+    let mut define = |name: &str, ty: u32, function: Function| {
+        functions.function(ty);
+        export_section.export(name, Export::Function(defined));
+        code.function(&function);
+        defined += 1;
+    };
+    for ((function, ty), main) in exports.iter().zip(adapted) {
+        let parameters: Vec<_> = function
+            .parameters
+            .iter()
+            .flat_map(|(_, ty)| ty.flatten())
+            .collect();
+        let gives_string = function.result == Some(ValueType::String);
+        let results = match &function.result {
+            Some(ValueType::String) => vec![ValType::I32],
+            result => result.iter().flat_map(ValueType::flatten).collect(),
+        };
+        let scratch = parameters.len() as u32;
+        let adapter_type = function_type(parameters, results);
+        let mut adapter = Function::new(if gives_string {
+            vec![(2, ValType::I32)]
+        } else {
+            vec![]
+        });
+        let mut local = 0;
+        for leaf in function.parameters.iter().flat_map(|(_, ty)| ty.leaves()) {
+            match leaf {
+                // the length goes in the word `cabi_realloc` left in front
+                // of the bytes, which makes them a string.
+                ValueType::String => {
+                    emit(
+                        &mut adapter,
+                        vec![
+                            Instruction::LocalGet(local),
+                            Instruction::I32Const(4),
+                            Instruction::I32Sub,
+                            Instruction::LocalGet(local + 1),
+                            Instruction::I32Store(word(0)),
+                            Instruction::LocalGet(local),
+                            Instruction::I32Const(4),
+                            Instruction::I32Sub,
+                        ],
+                    );
+                    local += 2;
+                }
+                _ => {
+                    adapter.instruction(Instruction::LocalGet(local));
+                    local += 1;
+                }
+            }
+        }
+        adapter.instruction(Instruction::Call(main));
+        match &function.result {
+            None => {
+                for _ in ty.returns.iter() {
+                    adapter.instruction(Instruction::Drop);
+                }
+            }
+            // the address and the length of the bytes are passed back in a
+            // block, which the post-return frees along with the string.
+            Some(ValueType::String) => {
+                let (string, area) = (scratch, scratch + 1);
+                emit(
+                    &mut adapter,
+                    vec![
+                        Instruction::LocalSet(string),
+                        Instruction::I32Const(8),
+                        Instruction::Call(MALLOC),
+                        Instruction::LocalTee(area),
+                        Instruction::I32Eqz,
+                        Instruction::If(wasm_encoder::BlockType::Empty),
+                        Instruction::Unreachable,
+                        Instruction::End,
+                        Instruction::LocalGet(area),
+                        Instruction::LocalGet(string),
+                        Instruction::I32Const(4),
+                        Instruction::I32Add,
+                        Instruction::I32Store(word(0)),
+                        Instruction::LocalGet(area),
+                        Instruction::LocalGet(string),
+                        Instruction::I32Load(word(0)),
+                        Instruction::I32Store(word(4)),
+                        Instruction::LocalGet(area),
+                    ],
+                );
+            }
+            Some(_) => {}
+        }
+        adapter.instruction(Instruction::End);
+        define(&function.name, adapter_type, adapter);
+
+        if gives_string {
+            let post_type = function_type(vec![ValType::I32], vec![]);
+            let mut post = Function::new(vec![]);
+            emit(
+                &mut post,
+                vec![
+                    Instruction::LocalGet(0),
+                    Instruction::I32Load(word(0)),
+                    Instruction::I32Const(4),
+                    Instruction::I32Sub,
+                    Instruction::Call(RELEASE),
+                    Instruction::LocalGet(0),
+                    Instruction::Call(FREE),
+                    Instruction::End,
+                ],
+            );
+            define(&format!("post_{}", function.name), post_type, post);
+        }
+    }
+
+    if memory {
+        // `(old, old_size, align, new_size)`, allocating a word more in
+        // front of what is asked for, which is only ever bytes.
+        let realloc_type = function_type(vec![ValType::I32; 4], vec![ValType::I32]);
+        let mut realloc = Function::new(vec![(1, ValType::I32)]);
+        emit(
+            &mut realloc,
+            vec![
+                Instruction::LocalGet(0),
+                Instruction::I32Eqz,
+                Instruction::If(wasm_encoder::BlockType::Result(ValType::I32)),
+                Instruction::LocalGet(3),
+                Instruction::I32Const(4),
+                Instruction::I32Add,
+                Instruction::Call(MALLOC),
+                Instruction::Else,
+                Instruction::LocalGet(0),
+                Instruction::I32Const(4),
+                Instruction::I32Sub,
+                Instruction::LocalGet(3),
+                Instruction::I32Const(4),
+                Instruction::I32Add,
+                Instruction::Call(REALLOC),
+                Instruction::End,
+                Instruction::LocalTee(4),
+                Instruction::I32Eqz,
+                Instruction::If(wasm_encoder::BlockType::Empty),
+                Instruction::Unreachable,
+                Instruction::End,
+                Instruction::LocalGet(4),
+                Instruction::I32Const(4),
+                Instruction::I32Add,
+                Instruction::End,
+            ],
+        );
+        define("cabi_realloc", realloc_type, realloc);
+    }
+
+    let mut module = Module::new();
+    module.section(&types);
+    module.section(&import_section);
+    module.section(&functions);
+    module.section(&export_section);
+    module.section(&code);
+    module
+}
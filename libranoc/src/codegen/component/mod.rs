@@ -0,0 +1,688 @@
+//! Wraps a compiled module as a component of the component model, whose
+//! interface is the world of its `pub fn`s, exported, and its `extern fn`s,
+//! imported, as the WIT made along with it declares it.
+//!
+//! The module isn't changed: the component instantiates it with two core
+//! modules around it. The shim gives the module its imports, calling the
+//! functions the component imports, which only pass numbers and booleans,
+//! and trapping for `todo`. The adapter exports a function for each of the
+//! ones the module does, taking and giving the values as the canonical ABI
+//! lays them out. A string given to the component is allocated by the
+//! adapter's `cabi_realloc` behind a word, which the adapter writes its
+//! length to, so it is a string of the runtime where the host copies it,
+//! and one given back is passed in a block the post-return releases along
+//! with the string. Arrays, optionals and the types declared in the program
+//! aren't in the interface yet, nor is a tuple given back.
+
+use std::collections::HashMap;
+
+use wasm_encoder::encoders;
+use wasmparser::{ExternalKind, FuncType, ImportSectionEntryType, Parser, Payload, TypeDef};
+
+use crate::{
+    core::{
+        ast::{Name, Pattern},
+        Type, TypeId, Types,
+    },
+    thir::{Function, Item, Statement},
+};
+
+use super::*;
+
+mod adapter;
+mod wit;
+
+use adapter::{adapter_module, shim_module};
+
+/// The most values the parameters of a function are passed as, without
+/// going through the memory, in the canonical ABI.
+const MAX_FLAT_PARAMS: usize = 16;
+
+/// A component and the WIT of its world.
+#[derive(Debug, Clone, Default)]
+pub struct Component {
+    pub wasm: Vec<u8>,
+    pub wit: String,
+}
+
+/// A type of the values the component passes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ValueType {
+    Bool,
+    S32,
+    S64,
+    F32,
+    F64,
+    String,
+    Tuple(Vec<ValueType>),
+}
+
+impl ValueType {
+    /// The type a value of `ty` is passed as, if the component can pass it,
+    /// with strings and tuples of them only if `memory` can.
+    fn of(types: &Types, ty: TypeId, memory: bool) -> Option<ValueType> {
+        Some(match types.get(ty) {
+            Type::Bool => ValueType::Bool,
+            Type::I32 => ValueType::S32,
+            Type::I64 => ValueType::S64,
+            Type::F32 => ValueType::F32,
+            Type::F64 => ValueType::F64,
+            Type::String if memory => ValueType::String,
+            Type::Tuple(elements) if memory && !elements.is_empty() => ValueType::Tuple(
+                elements
+                    .iter()
+                    .map(|element| ValueType::of(types, *element, memory))
+                    .collect::<Option<_>>()?,
+            ),
+            _ => return None,
+        })
+    }
+
+    /// The types of the values a value is made of, in order, which are the
+    /// ones of its elements for a tuple.
+    fn leaves(&self) -> Vec<&ValueType> {
+        match self {
+            ValueType::Tuple(elements) => elements.iter().flat_map(ValueType::leaves).collect(),
+            ty => vec![ty],
+        }
+    }
+
+    /// The core types the canonical ABI passes a value as, a string being
+    /// the address and the length of its bytes.
+    fn flatten(&self) -> Vec<wasm_encoder::ValType> {
+        use wasm_encoder::ValType;
+        self.leaves()
+            .into_iter()
+            .flat_map(|leaf| match leaf {
+                ValueType::S64 => vec![ValType::I64],
+                ValueType::F32 => vec![ValType::F32],
+                ValueType::F64 => vec![ValType::F64],
+                ValueType::String => vec![ValType::I32, ValType::I32],
+                _ => vec![ValType::I32],
+            })
+            .collect()
+    }
+
+    fn uses_memory(&self) -> bool {
+        self.leaves()
+            .into_iter()
+            .any(|leaf| *leaf == ValueType::String)
+    }
+}
+
+/// A function of the interface of the component.
+#[derive(Debug, Clone)]
+struct InterfaceFunction {
+    /// The name of the function in the module.
+    name: String,
+    /// The name of the function in the component.
+    label: String,
+    parameters: Vec<(String, ValueType)>,
+    result: Option<ValueType>,
+}
+
+impl InterfaceFunction {
+    /// The function of the interface `function` is, if the component can
+    /// pass what it takes and gives, with strings and tuples only if
+    /// `memory` can.
+    fn of(types: &Types, function: &Function, memory: bool) -> Result<Self, Error> {
+        let unsupported = |ty: TypeId| Error::not_in_interface(&function.name, &types.display(ty));
+        let function_label =
+            label(&function.name.content).ok_or_else(|| Error::invalid_label(&function.name))?;
+        let mut parameters = Vec::new();
+        for (index, (pattern, ty)) in function.parameters.iter().enumerate() {
+            let name = match pattern {
+                Pattern::Slot(Name::Ident(name)) => {
+                    label(&name.content).ok_or_else(|| Error::invalid_label(name))?
+                }
+                _ => format!("arg{}", index),
+            };
+            let ty = ValueType::of(types, *ty, memory).ok_or_else(|| unsupported(*ty))?;
+            parameters.push((name, ty));
+        }
+        let result = match function.return_type {
+            TypeId::UNIT | TypeId::NEVER => None,
+            ty => match ValueType::of(types, ty, memory) {
+                Some(ValueType::Tuple(_)) | None => return Err(unsupported(ty)),
+                result => result,
+            },
+        };
+        let flat: usize = parameters.iter().map(|(_, ty)| ty.flatten().len()).sum();
+        if flat > MAX_FLAT_PARAMS {
+            let parameters: Vec<_> = function
+                .parameters
+                .iter()
+                .map(|(_, ty)| types.display(*ty))
+                .collect();
+            return Err(Error::not_in_interface(
+                &function.name,
+                &format!("({})", parameters.join(", ")),
+            ));
+        }
+        Ok(InterfaceFunction {
+            name: function.name.content.clone(),
+            label: function_label,
+            parameters,
+            result,
+        })
+    }
+
+    fn uses_memory(&self) -> bool {
+        self.parameters.iter().any(|(_, ty)| ty.uses_memory())
+            || matches!(&self.result, Some(ty) if ty.uses_memory())
+    }
+}
+
+/// An import of the module, as the functions of the component imported.
+#[derive(Debug, Clone)]
+struct Import {
+    module: String,
+    field: String,
+    function: InterfaceFunction,
+}
+
+/// The interface of the component a module is wrapped as.
+#[derive(Debug, Clone)]
+pub(super) struct Interface {
+    world: String,
+    imports: Vec<Import>,
+    exports: Vec<InterfaceFunction>,
+}
+
+impl Interface {
+    /// The interface of the component `typed` is wrapped as, whose world is
+    /// called `world`.
+    pub fn of(typed: &Typed, world: &str, options: &CompileOptions) -> Result<Self, Vec<Error>> {
+        let mut errors = Vec::new();
+        if options.target.wasi {
+            errors.push(Error::unsupported_target(
+                "wasi",
+                "a component imports WASI by its interfaces",
+            ));
+        }
+        let mut interface = Interface {
+            world: world.to_owned(),
+            imports: Vec::new(),
+            exports: Vec::new(),
+        };
+        for statement in &typed.module.statements {
+            let function = match statement {
+                Statement::Item(Item::Function(function)) => function,
+                _ => continue,
+            };
+            if function.is_extern {
+                // the shim is instantiated before the memory is, so it can
+                // only pass numbers and booleans.
+                let import = InterfaceFunction::of(&typed.types, function, false)
+                    .and_then(|imported| Ok((import_of(function)?, imported)));
+                match import {
+                    Ok(((module, field), function)) => interface.imports.push(Import {
+                        module,
+                        field,
+                        function,
+                    }),
+                    Err(error) => errors.push(error),
+                }
+            } else if function.is_pub && function.generic_parameters.is_empty() {
+                match InterfaceFunction::of(&typed.types, function, true) {
+                    Ok(function) => interface.exports.push(function),
+                    Err(error) => errors.push(error),
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(interface)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn uses_memory(&self) -> bool {
+        self.exports.iter().any(InterfaceFunction::uses_memory)
+    }
+
+    /// The component of the module `wasm` compiled with this interface.
+    pub fn wrap(&self, wasm: &[u8]) -> Result<Component, Vec<Error>> {
+        let module = ModuleInterface::read(wasm).map_err(|error| vec![error])?;
+        // what gives each import of the module, in order: the index of the
+        // function of the component imported.
+        let mut errors = Vec::new();
+        let mut shims = Vec::new();
+        for (module_name, field, ty) in &module.imports {
+            let imported = self
+                .imports
+                .iter()
+                .position(|import| import.module == *module_name && import.field == *field);
+            match imported {
+                Some(index) => shims.push((index, ty.clone())),
+                None => errors.push(Error::component_import(module_name, field)),
+            }
+        }
+        let mut exports = Vec::new();
+        for function in &self.exports {
+            match module.exports.get(&function.name) {
+                Some(ty) => exports.push((function, ty.clone())),
+                None => errors.push(Error::invalid_module(format!(
+                    "`{}` isn't exported",
+                    function.name
+                ))),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let memory = self.uses_memory();
+        let mut component = Encoder::default();
+        let imports: Vec<_> = self.imports.iter().map(|import| &import.function).collect();
+        let import_types: Vec<_> = imports
+            .iter()
+            .map(|function| component.function_type(function))
+            .collect();
+        let export_types: Vec<_> = exports
+            .iter()
+            .map(|(function, _)| component.function_type(function))
+            .collect();
+        component.finish_types();
+
+        // the functions imported, lowered to core functions the shim
+        // imports as `host`.
+        let mut section = Vec::new();
+        vector(
+            &mut section,
+            imports.iter().zip(&import_types),
+            |bytes, (function, ty)| {
+                bytes.push(0x00);
+                name(bytes, &function.label);
+                bytes.push(0x01);
+                bytes.extend(encoders::u32(*ty));
+            },
+        );
+        component.section(IMPORT_SECTION, section);
+        let mut section = Vec::new();
+        vector(&mut section, 0..imports.len() as u32, |bytes, function| {
+            bytes.extend(&[0x01, 0x00]);
+            bytes.extend(encoders::u32(function));
+            bytes.push(0x00);
+        });
+        component.section(CANON_SECTION, section);
+        let mut core_functions = imports.len() as u32;
+        let host = component.inline_instance(
+            imports
+                .iter()
+                .enumerate()
+                .map(|(index, function)| (function.label.clone(), CORE_FUNC, index as u32))
+                .collect(),
+        );
+
+        // the shim, giving the module the functions it imports from each of
+        // its modules.
+        component.module(&shim_module(&imports, &shims));
+        let host = if imports.is_empty() {
+            vec![]
+        } else {
+            vec![("host".to_owned(), host)]
+        };
+        let shim = component.instantiate(0, host);
+        let shim_functions = core_functions;
+        core_functions += shims.len() as u32;
+        component.alias_core_exports(
+            shim,
+            CORE_FUNC,
+            (0..shims.len()).map(|index| index.to_string()).collect(),
+        );
+        let mut modules: Vec<(&str, Vec<InlineExport>)> = Vec::new();
+        for (index, (module_name, field, _)) in module.imports.iter().enumerate() {
+            let export = (field.clone(), CORE_FUNC, shim_functions + index as u32);
+            match modules.iter_mut().find(|(name, _)| name == module_name) {
+                Some((_, exports)) => exports.push(export),
+                None => modules.push((module_name, vec![export])),
+            }
+        }
+        let arguments = modules
+            .into_iter()
+            .map(|(name, exports)| (name.to_owned(), component.inline_instance(exports)))
+            .collect();
+
+        // the module, and the adapter of its exports.
+        component.section(CORE_MODULE_SECTION, wasm.to_vec());
+        let main = component.instantiate(1, arguments);
+        component.module(&adapter_module(&exports, memory));
+        let adapter = component.instantiate(2, vec![("main".to_owned(), main)]);
+
+        let mut names = Vec::new();
+        for (function, _) in &exports {
+            names.push(function.name.clone());
+            if matches!(&function.result, Some(ty) if ty.uses_memory()) {
+                names.push(format!("post_{}", function.name));
+            }
+        }
+        if memory {
+            names.push("cabi_realloc".to_owned());
+        }
+        let adapted: HashMap<_, _> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (name.clone(), core_functions + index as u32))
+            .collect();
+        component.alias_core_exports(adapter, CORE_FUNC, names);
+        if memory {
+            component.alias_core_exports(main, CORE_MEMORY, vec!["memory".to_owned()]);
+        }
+
+        // the exports, lifted from the adapter.
+        let mut section = Vec::new();
+        vector(
+            &mut section,
+            exports.iter().zip(&export_types),
+            |bytes, ((function, _), ty)| {
+                bytes.extend(&[0x00, 0x00]);
+                bytes.extend(encoders::u32(adapted[&function.name]));
+                let mut options = Vec::new();
+                if function.uses_memory() {
+                    options.push(vec![0x00]);
+                    let mut memory = vec![0x03];
+                    memory.extend(encoders::u32(0));
+                    options.push(memory);
+                }
+                // only the strings passed in are allocated by the component.
+                if function.parameters.iter().any(|(_, ty)| ty.uses_memory()) {
+                    let mut realloc = vec![0x04];
+                    realloc.extend(encoders::u32(adapted["cabi_realloc"]));
+                    options.push(realloc);
+                }
+                if let Some(post) = adapted.get(&format!("post_{}", function.name)) {
+                    let mut post_return = vec![0x05];
+                    post_return.extend(encoders::u32(*post));
+                    options.push(post_return);
+                }
+                vector(bytes, options, |bytes, option| bytes.extend(option));
+                bytes.extend(encoders::u32(*ty));
+            },
+        );
+        component.section(CANON_SECTION, section);
+        let lifted = imports.len() as u32;
+        let mut section = Vec::new();
+        vector(
+            &mut section,
+            exports.iter().enumerate(),
+            |bytes, (index, (function, _))| {
+                bytes.push(0x00);
+                name(bytes, &function.label);
+                bytes.push(FUNC);
+                bytes.extend(encoders::u32(lifted + index as u32));
+                bytes.push(0x00);
+            },
+        );
+        component.section(EXPORT_SECTION, section);
+
+        Ok(Component {
+            wasm: component.finish(),
+            wit: wit::world(self),
+        })
+    }
+}
+
+/// The module and the name `function`, an `extern fn`, is imported from.
+fn import_of(function: &Function) -> Result<(String, String), Error> {
+    match function.attribute("link") {
+        Some(link) => link_of(&link.value, &function.name),
+        None => Ok(("extern".to_owned(), function.name.content.clone())),
+    }
+}
+
+/// `name` as a name of WIT, which is kebab-case: words of letters and
+/// digits, each starting with a letter and all lowercase or all uppercase,
+/// separated by `-` where the source has `_`.
+fn label(name: &str) -> Option<String> {
+    let words: Vec<_> = name.split('_').collect();
+    let valid = words.iter().all(|word| {
+        let mut chars = word.chars();
+        matches!(chars.next(), Some(first) if first.is_ascii_alphabetic())
+            && (word
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+                || word
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()))
+    });
+    if valid {
+        Some(words.join("-"))
+    } else {
+        None
+    }
+}
+
+/// The imports and exports of a compiled module, with their types.
+struct ModuleInterface {
+    imports: Vec<(String, String, FuncType)>,
+    exports: HashMap<String, FuncType>,
+}
+
+impl ModuleInterface {
+    fn read(wasm: &[u8]) -> Result<Self, Error> {
+        let mut types = Vec::new();
+        let mut functions = Vec::new();
+        let mut imports = Vec::new();
+        let mut exports = HashMap::new();
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload.map_err(Error::invalid_module)? {
+                Payload::TypeSection(reader) => {
+                    for ty in reader {
+                        if let TypeDef::Func(ty) = ty.map_err(Error::invalid_module)? {
+                            types.push(ty);
+                        }
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        let import = import.map_err(Error::invalid_module)?;
+                        if let ImportSectionEntryType::Function(ty) = import.ty {
+                            functions.push(ty);
+                            imports.push((
+                                import.module.to_owned(),
+                                import.field.unwrap_or_default().to_owned(),
+                                types[ty as usize].clone(),
+                            ));
+                        }
+                    }
+                }
+                Payload::FunctionSection(reader) => {
+                    for ty in reader {
+                        functions.push(ty.map_err(Error::invalid_module)?);
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export.map_err(Error::invalid_module)?;
+                        if let ExternalKind::Function = export.kind {
+                            let ty = functions[export.index as usize];
+                            exports.insert(export.field.to_owned(), types[ty as usize].clone());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(ModuleInterface { imports, exports })
+    }
+}
+
+const CORE_MODULE_SECTION: u8 = 1;
+const CORE_INSTANCE_SECTION: u8 = 2;
+const ALIAS_SECTION: u8 = 6;
+const TYPE_SECTION: u8 = 7;
+const CANON_SECTION: u8 = 8;
+const IMPORT_SECTION: u8 = 10;
+const EXPORT_SECTION: u8 = 11;
+
+const CORE_FUNC: u8 = 0x00;
+const CORE_MEMORY: u8 = 0x02;
+const CORE_INSTANCE: u8 = 0x12;
+const FUNC: u8 = 0x01;
+
+/// Writes `items` as a vector, by `write` for each.
+fn vector<T>(
+    bytes: &mut Vec<u8>,
+    items: impl IntoIterator<Item = T>,
+    mut write: impl FnMut(&mut Vec<u8>, T),
+) {
+    let mut contents = Vec::new();
+    let mut count = 0;
+    for item in items {
+        write(&mut contents, item);
+        count += 1;
+    }
+    bytes.extend(encoders::u32(count));
+    bytes.extend(contents);
+}
+
+fn name(bytes: &mut Vec<u8>, name: &str) {
+    bytes.extend(encoders::str(name));
+}
+
+/// An export of an inline core instance: its name, core sort and index.
+type InlineExport = (String, u8, u32);
+
+/// Writes the sections of a component, keeping count of the core modules
+/// and instances defined so far.
+#[derive(Default)]
+struct Encoder {
+    bytes: Vec<u8>,
+    /// The types defined, in order, which the type section is made of.
+    types: Vec<Vec<u8>>,
+    type_indices: HashMap<Vec<u8>, u32>,
+    core_instances: u32,
+}
+
+impl Encoder {
+    fn section(&mut self, id: u8, contents: Vec<u8>) {
+        self.bytes.push(id);
+        self.bytes.extend(encoders::u32(contents.len() as u32));
+        self.bytes.extend(contents);
+    }
+
+    fn module(&mut self, module: &wasm_encoder::Module) {
+        self.section(CORE_MODULE_SECTION, module.as_slice().to_vec());
+    }
+
+    /// The index of the type `ty` encodes, which is defined the first time.
+    fn define_type(&mut self, ty: Vec<u8>) -> u32 {
+        if let Some(index) = self.type_indices.get(&ty) {
+            return *index;
+        }
+        let index = self.types.len() as u32;
+        self.types.push(ty.clone());
+        self.type_indices.insert(ty, index);
+        index
+    }
+
+    /// The encoding of `ty` as the type of a value, which refers to a tuple
+    /// by the index of its type, as a signed number like the primitives.
+    fn value_type(&mut self, ty: &ValueType) -> Vec<u8> {
+        match ty {
+            ValueType::Bool => vec![0x7f],
+            ValueType::S32 => vec![0x7a],
+            ValueType::S64 => vec![0x78],
+            ValueType::F32 => vec![0x76],
+            ValueType::F64 => vec![0x75],
+            ValueType::String => vec![0x73],
+            ValueType::Tuple(elements) => {
+                let mut tuple = vec![0x6f];
+                let elements: Vec<_> = elements
+                    .iter()
+                    .map(|element| self.value_type(element))
+                    .collect();
+                vector(&mut tuple, elements, |bytes, element| bytes.extend(element));
+                encoders::s33(self.define_type(tuple) as i64).collect()
+            }
+        }
+    }
+
+    fn function_type(&mut self, function: &InterfaceFunction) -> u32 {
+        let mut ty = vec![0x40];
+        let parameters: Vec<_> = function
+            .parameters
+            .iter()
+            .map(|(label, ty)| (label.clone(), self.value_type(ty)))
+            .collect();
+        vector(&mut ty, parameters, |bytes, (label, ty)| {
+            name(bytes, &label);
+            bytes.extend(ty);
+        });
+        match &function.result {
+            Some(result) => {
+                ty.push(0x00);
+                let result = self.value_type(result);
+                ty.extend(result);
+            }
+            None => ty.extend(&[0x01, 0x00]),
+        }
+        self.define_type(ty)
+    }
+
+    /// Writes the type section of the types defined so far.
+    fn finish_types(&mut self) {
+        let mut section = Vec::new();
+        vector(
+            &mut section,
+            std::mem::take(&mut self.types),
+            |bytes, ty| bytes.extend(ty),
+        );
+        self.section(TYPE_SECTION, section);
+    }
+
+    /// Defines a core instance, giving its index.
+    fn core_instance(&mut self, instance: Vec<u8>) -> u32 {
+        let mut section = Vec::new();
+        vector(&mut section, Some(instance), |bytes, instance| {
+            bytes.extend(instance)
+        });
+        self.section(CORE_INSTANCE_SECTION, section);
+        self.core_instances += 1;
+        self.core_instances - 1
+    }
+
+    /// Instantiates the core module `module` with the core instances
+    /// `arguments`, by the names of the modules it imports from.
+    fn instantiate(&mut self, module: u32, arguments: Vec<(String, u32)>) -> u32 {
+        let mut instance = vec![0x00];
+        instance.extend(encoders::u32(module));
+        vector(&mut instance, arguments, |bytes, (argument, index)| {
+            name(bytes, &argument);
+            bytes.push(CORE_INSTANCE);
+            bytes.extend(encoders::u32(index));
+        });
+        self.core_instance(instance)
+    }
+
+    /// A core instance exporting the core definitions `exports`, by their
+    /// name, sort and index.
+    fn inline_instance(&mut self, exports: Vec<InlineExport>) -> u32 {
+        let mut instance = vec![0x01];
+        vector(&mut instance, exports, |bytes, (export, sort, index)| {
+            name(bytes, &export);
+            bytes.push(sort);
+            bytes.extend(encoders::u32(index));
+        });
+        self.core_instance(instance)
+    }
+
+    /// Aliases the exports `names` of the core instance `instance`, which
+    /// are of the core sort `sort`, in order.
+    fn alias_core_exports(&mut self, instance: u32, sort: u8, names: Vec<String>) {
+        let mut section = Vec::new();
+        vector(&mut section, names, |bytes, export| {
+            bytes.extend(&[0x00, sort, 0x01]);
+            bytes.extend(encoders::u32(instance));
+            name(bytes, &export);
+        });
+        self.section(ALIAS_SECTION, section);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        let mut component = vec![0x00, 0x61, 0x73, 0x6d, 0x0d, 0x00, 0x01, 0x00];
+        component.extend(self.bytes);
+        component
+    }
+}
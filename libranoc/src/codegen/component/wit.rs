@@ -0,0 +1,106 @@
+use std::fmt::Write;
+
+use super::{Interface, InterfaceFunction, ValueType};
+
+/// The words of WIT, which a name has to be escaped by `%` to be.
+const KEYWORDS: &[&str] = &[
+    "as",
+    "async",
+    "bool",
+    "borrow",
+    "char",
+    "constructor",
+    "enum",
+    "export",
+    "f32",
+    "f64",
+    "flags",
+    "from",
+    "func",
+    "future",
+    "import",
+    "include",
+    "interface",
+    "list",
+    "option",
+    "own",
+    "package",
+    "record",
+    "resource",
+    "result",
+    "s16",
+    "s32",
+    "s64",
+    "s8",
+    "static",
+    "stream",
+    "string",
+    "tuple",
+    "type",
+    "u16",
+    "u32",
+    "u64",
+    "u8",
+    "use",
+    "variant",
+    "with",
+    "world",
+];
+
+/// The WIT of the world of `interface`, in the package `rano` of its name.
+pub(super) fn world(interface: &Interface) -> String {
+    let world = identifier(&interface.world);
+    let mut wit = format!("package rano:{};\n\nworld {} {{\n", world, world);
+    for import in &interface.imports {
+        writeln!(wit, "  import {}", function(&import.function)).unwrap();
+    }
+    if !interface.imports.is_empty() && !interface.exports.is_empty() {
+        wit.push('\n');
+    }
+    for export in &interface.exports {
+        writeln!(wit, "  export {}", function(export)).unwrap();
+    }
+    wit.push_str("}\n");
+    wit
+}
+
+fn function(function: &InterfaceFunction) -> String {
+    let parameters: Vec<_> = function
+        .parameters
+        .iter()
+        .map(|(name, ty)| format!("{}: {}", identifier(name), value_type(ty)))
+        .collect();
+    let result = match &function.result {
+        Some(ty) => format!(" -> {}", value_type(ty)),
+        None => String::new(),
+    };
+    format!(
+        "{}: func({}){};",
+        identifier(&function.label),
+        parameters.join(", "),
+        result
+    )
+}
+
+fn identifier(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("%{}", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+fn value_type(ty: &ValueType) -> String {
+    match ty {
+        ValueType::Bool => "bool".to_owned(),
+        ValueType::S32 => "s32".to_owned(),
+        ValueType::S64 => "s64".to_owned(),
+        ValueType::F32 => "f32".to_owned(),
+        ValueType::F64 => "f64".to_owned(),
+        ValueType::String => "string".to_owned(),
+        ValueType::Tuple(elements) => {
+            let elements: Vec<_> = elements.iter().map(value_type).collect();
+            format!("tuple<{}>", elements.join(", "))
+        }
+    }
+}
@@ -1,18 +1,83 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet};
 
 use wasm_encoder::{
-    CodeSection, DataSection, EntityType, Export, ExportSection, Function, FunctionSection,
-    GlobalType, ImportSection, Instruction, Limits, MemoryType, Module, TableType, TypeSection,
-    ValType,
+    encoders, BlockType, CodeSection, CustomSection, DataCountSection, DataSection, ElementMode,
+    ElementSection, ElementSegment, Elements, EntityType, Export, ExportSection, Function,
+    FunctionSection, GlobalSection, GlobalType, ImportSection, Instruction, Limits, MemArg,
+    MemorySection, MemoryType, Module, RawSection, SectionId, StartSection, TableSection,
+    TableType, TypeSection, ValType,
 };
 
 use crate::{
-    core::{ast::Type, Error},
+    core::{
+        arena::Idx,
+        ast::{Name, Pattern},
+        ir::{write_ir, IrKind},
+        Error, NodeId, Type, TypeId, Types,
+    },
+    semantic::{Definition, DefinitionKind, Resolution},
     syntax::{Span, Token},
+    thir::{self, Statement},
 };
 
+use super::{
+    code_offset, dwarf_sections, encode_instruction, export_panic_tag,
+    frame::{PAGE_SIZE, STACK_POINTER, STACK_SIZE},
+    import_shared_memory, initialize_shared_memory, inline_calls,
+    results::value_size,
+    runtime::{HEAP_BASE, HEAP_TOP, INITIALIZED, RESERVED, STACK_BASE},
+    simplify, source_map, tag_section, Artifacts, Atomic, Callee, Channels, CompileOptions, Floats,
+    Inline, Lines, Locals, Meta, Names, OptLevel, Overflow, Owned, Place, Runtime, Signature, Simd,
+    TailCalls, TargetOptions, Throw, FEATURES, META_SECTION, PANIC_TAG, TAG_SECTION,
+};
+
+/// Where the functions the module defines are numbered from while walking.
+/// They come after the imported ones, which are only all known once the
+/// whole module is walked, so they are renumbered by `finish`.
+const DEFINED_FUNCTIONS: u32 = 1 << 31;
+
+/// A function the module defines, with its type, the types of its locals
+/// besides the parameters and its body, once it is walked.
+type DefinedFunction<'a> = (u32, Option<(Vec<ValType>, Vec<Instruction<'a>>)>);
+
+/// An instruction of a proposal wasm-encoder has no variant for yet, which
+/// a `nop` stands for in the body it is in until `finish` writes it by
+/// hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Handwritten {
+    Atomic(Atomic),
+    Simd(Simd),
+    Throw(Throw),
+}
+
+impl Handwritten {
+    fn encode(self, code: &mut Vec<u8>) {
+        match self {
+            Handwritten::Atomic(atomic) => atomic.encode(code),
+            Handwritten::Simd(simd) => simd.encode(code),
+            Handwritten::Throw(throw) => throw.encode(code),
+        }
+    }
+}
+
+/// Where a module compiled on its own is placed in the module it is linked
+/// into, so the addresses its code has are right there as they are.
+#[derive(Debug, Clone, Copy)]
+pub struct Placement {
+    /// The address its data is placed from.
+    pub data: i32,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Placement { data: RESERVED }
+    }
+}
+
 pub struct Context<'a> {
-    import_section: ImportSection,
+    /// The imports by the index they are given, which `finish` declares
+    /// once it knows the functions used.
+    imported: Vec<(String, String, EntityType)>,
     import_index_function: u32,
     import_index_table: u32,
     import_index_memory: u32,
@@ -20,22 +85,130 @@ pub struct Context<'a> {
     import_index_instance: u32,
     import_index_module: u32,
     import_extern_type_map: HashMap<String, HashMap<String, (Span, EntityType)>>,
+    /// Where an `extern fn` given `#[link(...)]` is imported from, as the
+    /// module and the name there.
+    extern_links: HashMap<String, (String, String)>,
+    /// The `extern fn`s taking their strings and arrays as the address and
+    /// the length of their contents, by `#[link(pass = "ptr_len")]`.
+    ptr_len_externs: HashSet<String>,
 
     imports: HashMap<String, u32>,
-    locals: HashMap<String, VecDeque<u32>>,
+    /// What the names refer to, as found by the resolver.
+    resolution: Resolution,
+    /// The types the THIR refers to.
+    types: Types,
+    /// The index of each function implemented so far.
+    functions: HashMap<Idx<Definition>, u32>,
 
     type_section: TypeSection,
-    type_section_last_id: u32,
+    /// The function types in the type section, each of which is declared
+    /// once however many functions have it.
+    function_types: Vec<(Vec<ValType>, Vec<ValType>)>,
+
+    defined_functions: Vec<DefinedFunction<'a>>,
+    /// The parameter and return types of each function declared so far,
+    /// extern or not.
+    function_signatures: HashMap<Idx<Definition>, (Vec<TypeId>, TypeId)>,
 
-    function_section: FunctionSection,
+    table_functions: Vec<u32>,
+    /// The `i32.const`s giving the slot of a function in the table, as the
+    /// function they are in and their position in its body, which are
+    /// renumbered once the slots nothing takes are left out.
+    table_slots: HashSet<(u32, usize)>,
+    /// Whether a function value is called, which needs the module to define
+    /// the table even if no slot in it is taken.
+    indirect_calls: bool,
+    /// The functions calling each function declared in the program with
+    /// the environment of a function value, by the index of the one called.
+    thunks: HashMap<u32, u32>,
 
-    code_section: CodeSection,
+    /// How many `unsafe` blocks the code being walked is inside of.
+    unsafe_depth: u32,
+    /// What the integer arithmetic being walked does on overflow.
+    overflow: Overflow,
+    /// Which of the optimizations `finish` makes, and whether every local
+    /// is named.
+    opt_level: OptLevel,
+    /// Whether the runtime is exported even if the code kept doesn't use
+    /// it, as the adapters of a component allocate the strings passed in
+    /// and out with it.
+    export_runtime: bool,
+    /// Whether the calls of each function are inlined, for the ones given
+    /// `#[inline]` or `#[inline(never)]`.
+    inline_hints: HashMap<u32, Inline>,
+    /// The locals of the function being walked.
+    locals: Locals,
+    /// The calls the function being walked makes of itself in a tail
+    /// position.
+    tail_calls: TailCalls,
+    /// The calls which are `return_call`s, as the function making them and
+    /// their position in its body.
+    return_calls: HashSet<(u32, usize)>,
+    /// How many calls of the functions which can call themselves may be
+    /// running at once before they panic, if they are counted.
+    max_call_depth: Option<u32>,
+    /// The items of the functions the calls of which are counted.
+    counted_functions: HashSet<NodeId>,
+    /// Whether the calls of the function being walked are counted, which
+    /// its `return_call`s leave the count of before.
+    call_depth_counted: bool,
+    /// The `nop`s standing for the instructions written by hand, as the
+    /// function they are in and their position in its body.
+    handwritten: HashMap<(u32, usize), Handwritten>,
+    /// The definitions of the `#[thread_safe]` functions, extern or not.
+    thread_safe_functions: HashSet<Idx<Definition>>,
+    /// Whether the function being walked is `#[thread_safe]`, which the
+    /// atomics can only be called in.
+    thread_safe: bool,
+    /// The modules the items being walked are declared in, outermost first,
+    /// by their name and whether they are `pub`.
+    modules: Vec<(String, bool)>,
+    /// Whether the raw memory operations were used, which needs the module
+    /// to define a memory.
+    memory_used: bool,
+    /// Whether a function has a frame on the shadow stack, which needs the
+    /// module to place the stack and define its pointer.
+    stack_used: bool,
+    /// The functions of the runtime, which are defined before any other.
+    runtime: Runtime,
+    /// The routines formatting and parsing floats, once they are needed.
+    floats: Option<Floats>,
+    /// The routines of the channels, once they are needed.
+    channels: Option<Channels>,
+    target: TargetOptions,
+    /// The names of the functions and locals, for the `name` section.
+    names: Names,
+    /// The spans of the instructions, if DWARF or a source map is emitted.
+    lines: Option<Lines>,
+    dwarf: bool,
+    /// The URL of the source map, if one is made.
+    source_map: Option<String>,
+    /// The names of the source files by their `FileId`.
+    file_names: Vec<String>,
 
     export_section: ExportSection,
+    exported_functions: Vec<(String, u32)>,
+    /// The signatures of the `pub fn`s, by their index, for the `rano.meta`
+    /// section.
+    exported_signatures: Vec<(u32, Signature)>,
 
-    data_section: DataSection,
-    data_segment_last_id: u32,
+    /// The data placed in the memory, by its address.
+    data_segments: Vec<(i32, Vec<u8>)>,
     data_segment_last_offset: i32,
+    /// The addresses of the string and array literals already in the data
+    /// segments, by their bytes.
+    literals: HashMap<Vec<u8>, i32>,
+    /// The literals in the data segments, by their address, with the
+    /// addresses of the literals each holds, which are used as long as it
+    /// is.
+    literal_references: HashMap<i32, Vec<i32>>,
+    /// Where the values of a tuple of each type, besides the first, are
+    /// passed back through by a function or an `if` giving it, when the
+    /// target can't give them all.
+    result_areas: HashMap<TypeId, i32>,
+    /// The functions comparing two tuples or two arrays of each type, once
+    /// `==` needed them.
+    aggregate_eqs: HashMap<TypeId, u32>,
 
     pub instructions: Vec<Instruction<'a>>,
 
@@ -43,9 +216,14 @@ pub struct Context<'a> {
 }
 
 impl<'a> Context<'a> {
-    pub fn new() -> Self {
-        Context {
-            import_section: ImportSection::new(),
+    pub fn new(
+        resolution: Resolution,
+        types: Types,
+        options: &CompileOptions,
+        placement: Placement,
+    ) -> Self {
+        let mut context = Context {
+            imported: Vec::new(),
             import_index_function: 0,
             import_index_table: 0,
             import_index_memory: 0,
@@ -53,42 +231,847 @@ impl<'a> Context<'a> {
             import_index_instance: 0,
             import_index_module: 0,
             import_extern_type_map: HashMap::new(),
+            extern_links: HashMap::new(),
+            ptr_len_externs: HashSet::new(),
 
             imports: HashMap::new(),
-            locals: HashMap::new(),
+            resolution,
+            types,
+            functions: HashMap::new(),
 
             type_section: TypeSection::new(),
-            type_section_last_id: 0,
+            function_types: Vec::new(),
 
-            function_section: FunctionSection::new(),
+            defined_functions: Vec::new(),
+            function_signatures: HashMap::new(),
 
-            export_section: ExportSection::new(),
+            table_functions: Vec::new(),
+            table_slots: HashSet::new(),
+            indirect_calls: false,
+            thunks: HashMap::new(),
+
+            unsafe_depth: 0,
+            overflow: options.overflow,
+            opt_level: options.opt_level,
+            export_runtime: options.component.is_some(),
+            inline_hints: HashMap::new(),
+            locals: Locals::default(),
+            tail_calls: TailCalls::default(),
+            return_calls: HashSet::new(),
+            max_call_depth: options.max_call_depth,
+            counted_functions: HashSet::new(),
+            call_depth_counted: false,
+            handwritten: HashMap::new(),
+            thread_safe_functions: HashSet::new(),
+            thread_safe: false,
+            modules: Vec::new(),
+            memory_used: false,
+            stack_used: false,
+            runtime: Runtime::default(),
+            floats: None,
+            channels: None,
+            target: options.target,
+            names: Names::default(),
+            lines: if options.dwarf || options.source_map.is_some() {
+                Some(Lines::default())
+            } else {
+                None
+            },
+            dwarf: options.dwarf,
+            source_map: options.source_map.clone(),
+            file_names: options.file_names.clone(),
 
-            code_section: CodeSection::new(),
+            export_section: ExportSection::new(),
+            exported_functions: Vec::new(),
+            exported_signatures: Vec::new(),
 
-            data_section: DataSection::new(),
-            data_segment_last_id: 0,
-            data_segment_last_offset: 0,
+            data_segments: Vec::new(),
+            data_segment_last_offset: placement.data,
+            literals: HashMap::new(),
+            literal_references: HashMap::new(),
+            result_areas: HashMap::new(),
+            aggregate_eqs: HashMap::new(),
 
             instructions: Vec::new(),
 
             compilation_errors: Vec::new(),
-        }
+        };
+        context.runtime = context.emit_runtime();
+        context
     }
 
-    pub fn finish(self) -> (Vec<u8>, Vec<Error>) {
+    pub fn finish(mut self) -> (Artifacts, Vec<Error>) {
+        if self.target.exception_handling && self.stack_used {
+            self.reset_stack_on_panic();
+        }
+        if self.opt_level.inline() {
+            self.inline_functions();
+        }
+        if self.opt_level.peephole() {
+            self.simplify_functions();
+        }
+        let kept = self.kept_functions();
+        // the functions kept are numbered in order, the imported ones
+        // first, and the others by none.
+        let mut numbered = 0;
+        let mut number = |id: u32| {
+            kept.contains(&id).then(|| {
+                numbered += 1;
+                numbered - 1
+            })
+        };
+        let imported_indices: Vec<_> = (0..self.import_index_function).map(&mut number).collect();
+        let defined_indices: Vec<_> = (0..self.defined_functions.len() as u32)
+            .map(|position| number(DEFINED_FUNCTIONS + position))
+            .collect();
+        let index = |id: u32| match id.checked_sub(DEFINED_FUNCTIONS) {
+            Some(defined) => defined_indices[defined as usize],
+            None => imported_indices[id as usize],
+        };
+        let called = |id: u32| index(id).expect("the functions kept only call kept ones");
+
+        // the slots the code kept takes, renumbered in order.
+        let taken: HashSet<_> = self
+            .table_slots
+            .iter()
+            .filter(|(function, _)| kept.contains(function))
+            .filter_map(|(function, at)| {
+                let (_, body) = self.defined_functions[(function - DEFINED_FUNCTIONS) as usize]
+                    .1
+                    .as_ref()?;
+                match body[*at] {
+                    Instruction::I32Const(slot) => Some(slot),
+                    _ => None,
+                }
+            })
+            .collect();
+        let mut slots = vec![0; self.table_functions.len()];
+        let mut table_functions = Vec::new();
+        for (slot, id) in self.table_functions.iter().enumerate() {
+            if taken.contains(&(slot as i32)) {
+                slots[slot] = table_functions.len() as i32;
+                table_functions.push(called(*id));
+            }
+        }
+
+        let mut import_section = ImportSection::new();
+        let mut imports = 0;
+        let mut functions = 0;
+        for (module, name, ty) in &self.imported {
+            if let EntityType::Function(_) = ty {
+                functions += 1;
+                if imported_indices[functions - 1].is_none() {
+                    continue;
+                }
+            }
+            import_section.import(module, Some(name), *ty);
+            imports += 1;
+        }
+        // a shared memory is imported, and the data copied into it by the
+        // start function, which is defined behind the others.
+        let shared = self.target.threads && self.memory_used;
+        let start = (imported_indices.iter().flatten().count()
+            + defined_indices.iter().flatten().count()) as u32;
+
+        let mut function_section = FunctionSection::new();
+        let mut code_section = CodeSection::new();
+        let defined_functions = std::mem::take(&mut self.defined_functions);
+        // the constants of the code kept, which the literals used are
+        // among.
+        let mut constants = HashSet::new();
+        // the offset into the payload of the code section, which the DWARF
+        // addresses are, behind the number of functions.
+        let kept_defined = defined_indices.iter().flatten().count() + shared as usize;
+        let mut offset = encoders::u32(kept_defined as u32).len();
+        for (position, (type_id, implementation)) in defined_functions.into_iter().enumerate() {
+            let id = DEFINED_FUNCTIONS + position as u32;
+            if !kept.contains(&id) {
+                continue;
+            }
+            function_section.function(type_id);
+            let (locals, body) = match implementation {
+                Some(implementation) => implementation,
+                // it failed to compile, which is reported already.
+                None => (Vec::new(), vec![Instruction::Unreachable, Instruction::End]),
+            };
+            let mut code = Vec::new();
+            let mut offsets = Vec::with_capacity(body.len());
+            for (at, instruction) in body.into_iter().enumerate() {
+                offsets.push(code.len());
+                match instruction {
+                    // wasm-encoder has no `return_call` yet, so it is
+                    // written by hand.
+                    Instruction::Call(callee) if self.return_calls.contains(&(id, at)) => {
+                        code.push(0x12);
+                        code.extend(encoders::u32(called(callee)));
+                    }
+                    Instruction::Call(callee) => {
+                        encode_instruction(Instruction::Call(called(callee)), &mut code)
+                    }
+                    Instruction::Nop if self.handwritten.contains_key(&(id, at)) => {
+                        self.handwritten[&(id, at)].encode(&mut code)
+                    }
+                    Instruction::I32Const(slot) if self.table_slots.contains(&(id, at)) => {
+                        encode_instruction(Instruction::I32Const(slots[slot as usize]), &mut code)
+                    }
+                    Instruction::I32Const(value) => {
+                        constants.insert(value);
+                        encode_instruction(instruction, &mut code)
+                    }
+                    instruction => encode_instruction(instruction, &mut code),
+                }
+            }
+            // every local is declared on its own, as a count of 1 and its
+            // type.
+            let locals_size = encoders::u32(locals.len() as u32).len() + 2 * locals.len();
+            let size = locals_size + code.len();
+            let start = offset + encoders::u32(size as u32).len();
+            offset = start + size;
+            if let Some(lines) = &mut self.lines {
+                lines.place_function(id, start, start + locals_size, &offsets, offset);
+            }
+            let mut function = Function::new(locals.into_iter().map(|ty| (1, ty)));
+            function.raw(code);
+            code_section.function(&function);
+        }
+        for (name, id) in &self.exported_functions {
+            if let Some(index) = index(*id) {
+                self.export_section.export(name, Export::Function(index));
+            }
+        }
+
+        let mut table_section = TableSection::new();
+        let mut element_section = ElementSection::new();
+        if !table_functions.is_empty() || self.indirect_calls {
+            table_section.table(TableType {
+                element_type: ValType::FuncRef,
+                limits: Limits {
+                    min: table_functions.len() as u32,
+                    max: None,
+                },
+            });
+            element_section.segment(ElementSegment {
+                mode: ElementMode::Active {
+                    table: None,
+                    offset: Instruction::I32Const(0),
+                },
+                element_type: ValType::FuncRef,
+                elements: Elements::Functions(&table_functions),
+            });
+            self.export_section
+                .export("__indirect_function_table", Export::Table(0));
+        }
+
+        // the heap starts behind the data, aligned like its blocks are.
+        let mut heap = (self.data_segment_last_offset + 7) & !7;
+        let mut state = vec![0; RESERVED as usize];
+        let mut global_section = GlobalSection::new();
+        if self.stack_used {
+            // the shadow stack grows down towards the data from the heap,
+            // which is moved behind it.
+            state[STACK_BASE as usize..][..4].copy_from_slice(&heap.to_le_bytes());
+            heap += STACK_SIZE;
+            global_section.global(
+                GlobalType {
+                    val_type: ValType::I32,
+                    mutable: true,
+                },
+                Instruction::I32Const(heap),
+            );
+        }
+        state[HEAP_TOP as usize..][..4].copy_from_slice(&heap.to_le_bytes());
+        state[HEAP_BASE as usize..][..4].copy_from_slice(&heap.to_le_bytes());
+        if shared {
+            state[INITIALIZED as usize..][..4].copy_from_slice(&1i32.to_le_bytes());
+        }
+        let mut data_section = DataSection::new();
+        // the addresses and the lengths of the segments the start function
+        // copies into the shared memory.
+        let mut passive = Vec::new();
+        let mut segment = |address: i32, data: Vec<u8>| match shared {
+            true => {
+                passive.push((address, data.len() as u32));
+                data_section.passive(data);
+            }
+            false => {
+                data_section.active(0, Instruction::I32Const(address), data);
+            }
+        };
+        segment(0, state);
+        let literals: HashSet<_> = self.literal_references.keys().copied().collect();
+        // a literal is always given by an `i32.const` of its address, or
+        // held by another literal, so one none of the code kept has and no
+        // literal used holds is unused. The other data is reached by
+        // addresses computed at runtime too.
+        let mut used = HashSet::new();
+        let mut pending: Vec<i32> = constants.intersection(&literals).copied().collect();
+        while let Some(address) = pending.pop() {
+            if used.insert(address) {
+                pending.extend(self.literal_references.get(&address).into_iter().flatten());
+            }
+        }
+        for (address, data) in std::mem::take(&mut self.data_segments) {
+            if literals.contains(&address) && !used.contains(&address) {
+                continue;
+            }
+            segment(address, data);
+        }
+
+        // the memory holds the stack from the start.
+        let pages = (heap as u32).div_ceil(PAGE_SIZE).max(1);
+        let mut memory_section = MemorySection::new();
+        if self.memory_used {
+            if !shared {
+                memory_section.memory(MemoryType {
+                    limits: Limits {
+                        min: pages,
+                        max: None,
+                    },
+                });
+            }
+            self.export_section.export("memory", Export::Memory(0));
+        }
+        let shared_imports = shared.then(|| import_shared_memory(&import_section, imports, pages));
+        if shared {
+            let type_id = self.declare_function_type(vec![], vec![]);
+            function_section.function(type_id);
+            let mut function = Function::new(std::iter::empty());
+            for instruction in initialize_shared_memory(&passive) {
+                function.instruction(instruction);
+            }
+            code_section.function(&function);
+        }
+
         let mut module = Module::new();
         module.section(&self.type_section);
-        module.section(&self.import_section);
-        module.section(&self.function_section);
-        module.section(&self.export_section);
-        module.section(&self.code_section);
-        module.section(&self.data_section);
-        (module.finish(), self.compilation_errors)
+        match &shared_imports {
+            Some(data) => module.section(&RawSection {
+                id: SectionId::Import.into(),
+                data,
+            }),
+            None => module.section(&import_section),
+        };
+        module.section(&function_section);
+        module.section(&table_section);
+        module.section(&memory_section);
+        let exceptions = self.target.exception_handling;
+        if exceptions {
+            let type_id = self.declare_function_type(vec![ValType::I32; 2], vec![]);
+            module.section(&RawSection {
+                id: TAG_SECTION,
+                data: &tag_section(type_id),
+            });
+        }
+        if self.stack_used {
+            module.section(&global_section);
+        }
+        match exceptions {
+            true => module.section(&RawSection {
+                id: SectionId::Export.into(),
+                data: &export_panic_tag(&self.export_section),
+            }),
+            false => module.section(&self.export_section),
+        };
+        if shared {
+            module.section(&StartSection {
+                function_index: start,
+            });
+        }
+        module.section(&element_section);
+        if shared {
+            module.section(&DataCountSection {
+                count: passive.len() as u32,
+            });
+        }
+        module.section(&code_section);
+        module.section(&data_section);
+        let meta = Meta {
+            compiler: env!("CARGO_PKG_VERSION").to_owned(),
+            features: ["wasi"]
+                .iter()
+                .chain(FEATURES)
+                .filter(|feature| self.target.enables(feature))
+                .map(|feature| feature.to_string())
+                .collect(),
+            exports: std::mem::take(&mut self.exported_signatures)
+                .into_iter()
+                .map(|(_, signature)| signature)
+                .collect(),
+        };
+        match write_ir(IrKind::Meta, &meta) {
+            Ok(data) => {
+                module.section(&CustomSection {
+                    name: META_SECTION,
+                    data: &data,
+                });
+            }
+            Err(error) => self.compilation_errors.push(error),
+        }
+        module.section(&CustomSection {
+            name: "name",
+            data: &self.names.encode(index),
+        });
+        if let Some(lines) = self.lines.as_ref().filter(|_| self.dwarf) {
+            for (name, data) in dwarf_sections(lines, &self.names, &self.file_names, offset) {
+                module.section(&CustomSection { name, data: &data });
+            }
+        }
+        if let Some(url) = &self.source_map {
+            let mut data = encoders::u32(url.len() as u32).collect::<Vec<_>>();
+            data.extend(url.as_bytes());
+            module.section(&CustomSection {
+                name: "sourceMappingURL",
+                data: &data,
+            });
+        }
+        let wasm = module.finish();
+        // the map is made last, as its offsets are into the whole module.
+        let source_map = match (&self.lines, &self.source_map) {
+            (Some(lines), Some(_)) => Some(source_map(lines, &self.file_names, code_offset(&wasm))),
+            _ => None,
+        };
+        (
+            Artifacts {
+                wasm,
+                source_map,
+                ..Artifacts::default()
+            },
+            self.compilation_errors,
+        )
     }
 
-    pub fn convert_type(&mut self, _ty: Type) -> Vec<ValType> {
-        vec![ValType::I32]
+    /// Links the modules compiled on their own into this one, each named by
+    /// its name and placed behind the ones before it, and this one behind
+    /// the runtime. Their runtimes are left out for the one of this module,
+    /// and an import of a function another one exports, by the name of the
+    /// module and of the function, calls it instead.
+    pub fn link(&mut self, objects: Vec<(String, Context<'a>)>) {
+        let runtime = self.runtime.functions();
+        // the id each function of each object has here, the ones of its
+        // runtime being the ones of this module.
+        let mut next = self.defined_functions.len() as u32;
+        let defined: Vec<Vec<u32>> = objects
+            .iter()
+            .map(|(_, object)| {
+                let object_runtime = object.runtime.functions();
+                (0..object.defined_functions.len() as u32)
+                    .map(|position| {
+                        let id = DEFINED_FUNCTIONS + position;
+                        match object_runtime.iter().position(|function| *function == id) {
+                            Some(index) => runtime[index],
+                            None => {
+                                next += 1;
+                                DEFINED_FUNCTIONS + next - 1
+                            }
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        // the functions each object exports, by the name of the object and
+        // of the function, with their ids here and their types.
+        let mut exports = HashMap::new();
+        let mut exporters = HashMap::new();
+        for ((name, object), ids) in objects.iter().zip(&defined) {
+            let object_runtime = object.runtime.functions();
+            for (export, id) in &object.exported_functions {
+                if object_runtime.contains(id) || *id < DEFINED_FUNCTIONS {
+                    continue;
+                }
+                let (type_id, _) = object.defined_functions[(id - DEFINED_FUNCTIONS) as usize];
+                let ty = object.function_types[type_id as usize].clone();
+                exports.insert(
+                    (name.clone(), export.clone()),
+                    (ids[(id - DEFINED_FUNCTIONS) as usize], ty),
+                );
+                if let Some(first) = exporters.insert(export.clone(), name.clone()) {
+                    self.compilation_errors
+                        .push(Error::exported_twice(export, &first, name));
+                }
+            }
+        }
+
+        for ((name, mut object), ids) in objects.into_iter().zip(defined) {
+            let types: Vec<u32> = object
+                .function_types
+                .iter()
+                .map(|(parameters, results)| {
+                    self.declare_function_type(parameters.clone(), results.clone())
+                })
+                .collect();
+            let mut imported = Vec::new();
+            for (module, field, ty) in &object.imported {
+                // only functions are imported, so their ordinals are
+                // their positions here.
+                let type_id = match ty {
+                    EntityType::Function(type_id) => *type_id as usize,
+                    _ => continue,
+                };
+                let (parameters, results) = object.function_types[type_id].clone();
+                match exports.get(&(module.clone(), field.clone())) {
+                    Some((id, ty)) if *ty == (parameters.clone(), results.clone()) => {
+                        imported.push(*id)
+                    }
+                    linked => {
+                        if linked.is_some() {
+                            self.compilation_errors
+                                .push(Error::mismatched_link(&name, module, field));
+                        }
+                        imported.push(self.import_builtin(module, field, parameters, results));
+                    }
+                }
+            }
+            let id = |id: u32| match id.checked_sub(DEFINED_FUNCTIONS) {
+                Some(position) => ids[position as usize],
+                None => imported[id as usize],
+            };
+            let object_runtime = object.runtime.functions();
+            let first_slot = self.table_functions.len() as i32;
+            self.table_functions
+                .extend(object.table_functions.iter().map(|function| id(*function)));
+            for (position, (type_id, implementation)) in
+                std::mem::take(&mut object.defined_functions)
+                    .into_iter()
+                    .enumerate()
+            {
+                let old = DEFINED_FUNCTIONS + position as u32;
+                if object_runtime.contains(&old) {
+                    continue;
+                }
+                let new = id(old);
+                let implementation = implementation.map(|(locals, body)| {
+                    let body = body
+                        .into_iter()
+                        .enumerate()
+                        .map(|(at, instruction)| match instruction {
+                            Instruction::Call(callee) => Instruction::Call(id(callee)),
+                            Instruction::I32Const(slot)
+                                if object.table_slots.contains(&(old, at)) =>
+                            {
+                                Instruction::I32Const(first_slot + slot)
+                            }
+                            Instruction::CallIndirect { ty, table } => Instruction::CallIndirect {
+                                ty: types[ty as usize],
+                                table,
+                            },
+                            Instruction::Block(BlockType::FunctionType(ty)) => {
+                                Instruction::Block(BlockType::FunctionType(types[ty as usize]))
+                            }
+                            Instruction::Loop(BlockType::FunctionType(ty)) => {
+                                Instruction::Loop(BlockType::FunctionType(types[ty as usize]))
+                            }
+                            Instruction::If(BlockType::FunctionType(ty)) => {
+                                Instruction::If(BlockType::FunctionType(types[ty as usize]))
+                            }
+                            instruction => instruction,
+                        })
+                        .collect();
+                    (locals, body)
+                });
+                self.defined_functions
+                    .push((types[type_id as usize], implementation));
+                debug_assert_eq!(
+                    DEFINED_FUNCTIONS + self.defined_functions.len() as u32 - 1,
+                    new
+                );
+            }
+            let kept = |function: u32| {
+                (function >= DEFINED_FUNCTIONS && !object_runtime.contains(&function))
+                    .then(|| id(function))
+            };
+            self.table_slots.extend(
+                object
+                    .table_slots
+                    .iter()
+                    .filter_map(|(function, at)| Some((kept(*function)?, *at))),
+            );
+            self.return_calls.extend(
+                object
+                    .return_calls
+                    .iter()
+                    .filter_map(|(function, at)| Some((kept(*function)?, *at))),
+            );
+            self.handwritten
+                .extend(
+                    object
+                        .handwritten
+                        .iter()
+                        .filter_map(|((function, at), instruction)| {
+                            Some(((kept(*function)?, *at), *instruction))
+                        }),
+                );
+            self.inline_hints.extend(
+                object
+                    .inline_hints
+                    .iter()
+                    .filter_map(|(function, inline)| Some((kept(*function)?, *inline))),
+            );
+            for (export, function) in &object.exported_functions {
+                if let Some(function) = kept(*function) {
+                    self.exported_functions.push((export.clone(), function));
+                }
+            }
+            for (function, signature) in std::mem::take(&mut object.exported_signatures) {
+                if let Some(function) = kept(function) {
+                    self.exported_signatures.push((function, signature));
+                }
+            }
+            self.names.absorb(std::mem::take(&mut object.names), kept);
+            if let (Some(lines), Some(object_lines)) = (&mut self.lines, object.lines.take()) {
+                lines.absorb(object_lines, kept);
+            }
+            self.data_segments.append(&mut object.data_segments);
+            self.data_segment_last_offset = object.data_segment_last_offset;
+            self.literal_references
+                .extend(std::mem::take(&mut object.literal_references));
+            self.memory_used |= object.memory_used;
+            self.stack_used |= object.stack_used;
+            self.compilation_errors
+                .append(&mut object.compilation_errors);
+        }
+    }
+
+    /// The functions the module keeps: all of them, unless
+    /// `CompileOptions::opt_level` leaves out the ones none of the
+    /// exported functions calls or takes the slot of, however indirectly.
+    /// The runtime is only exported once the code kept uses it, as the
+    /// host only needs it for the values on the heap passed in and out,
+    /// unless the module is made a component.
+    fn kept_functions(&self) -> HashSet<u32> {
+        let defined =
+            (0..self.defined_functions.len() as u32).map(|position| DEFINED_FUNCTIONS + position);
+        if !self.opt_level.eliminate_dead_code() {
+            return (0..self.import_index_function).chain(defined).collect();
+        }
+        let runtime = self.runtime.functions();
+        let exported = self.exported_functions.iter().map(|(_, id)| *id);
+        let kept = self.reach(
+            exported.clone().filter(|id| !runtime.contains(id)),
+            HashSet::new(),
+        );
+        if self.export_runtime || runtime.iter().any(|id| kept.contains(id)) {
+            return self.reach(exported, kept);
+        }
+        kept
+    }
+
+    /// Adds the functions `roots` to `reached`, with the ones they call and
+    /// take the slots of, however indirectly.
+    fn reach(
+        &self,
+        roots: impl IntoIterator<Item = u32>,
+        mut reached: HashSet<u32>,
+    ) -> HashSet<u32> {
+        let mut queue: Vec<_> = roots.into_iter().collect();
+        while let Some(id) = queue.pop() {
+            if !reached.insert(id) {
+                continue;
+            }
+            let body = match id.checked_sub(DEFINED_FUNCTIONS) {
+                Some(position) => match &self.defined_functions[position as usize].1 {
+                    Some((_, body)) => body,
+                    None => continue,
+                },
+                None => continue,
+            };
+            for (at, instruction) in body.iter().enumerate() {
+                match *instruction {
+                    Instruction::Call(callee) => queue.push(callee),
+                    Instruction::I32Const(slot) if self.table_slots.contains(&(id, at)) => {
+                        queue.push(self.table_functions[slot as usize])
+                    }
+                    _ => {}
+                }
+            }
+        }
+        reached
+    }
+
+    /// Inlines the calls of the functions which can be into the functions
+    /// calling them, as they were emitted. A function making a
+    /// `return_call` is left called, as it would return from the function
+    /// it was inlined into, and so is one taking a slot in the table, which
+    /// is renumbered where it was taken, or having an instruction written by
+    /// hand, which is only written once it is encoded.
+    fn inline_functions(&mut self) {
+        let mut callees = HashMap::new();
+        for (position, (type_id, implementation)) in self.defined_functions.iter().enumerate() {
+            let id = DEFINED_FUNCTIONS + position as u32;
+            let (locals, body) = match implementation {
+                Some(implementation) => implementation,
+                None => continue,
+            };
+            if self
+                .return_calls
+                .iter()
+                .chain(&self.table_slots)
+                .chain(self.handwritten.keys())
+                .any(|(function, _)| *function == id)
+            {
+                continue;
+            }
+            let inline = self.inline_hints.get(&id).copied().unwrap_or_default();
+            let (parameters, results) = &self.function_types[*type_id as usize];
+            if let Some(callee) = Callee::new(inline, parameters, results, locals, body) {
+                callees.insert(id, callee);
+            }
+        }
+        for position in 0..self.defined_functions.len() {
+            let id = DEFINED_FUNCTIONS + position as u32;
+            let (type_id, implementation) = &mut self.defined_functions[position];
+            let (locals, body) = match implementation {
+                Some(implementation) => implementation,
+                None => continue,
+            };
+            let parameters = self.function_types[*type_id as usize].0.len();
+            let return_calls = &self.return_calls;
+            let positions = inline_calls(id, parameters, locals, body, &callees, |at| {
+                return_calls.contains(&(id, at))
+            });
+            self.move_instructions(id, &positions);
+        }
+    }
+
+    /// Simplifies the obvious sequences of instructions in the functions,
+    /// leaving the `return_call`s, the slots and the instructions written by
+    /// hand as they are.
+    fn simplify_functions(&mut self) {
+        for position in 0..self.defined_functions.len() {
+            let id = DEFINED_FUNCTIONS + position as u32;
+            let body = match &mut self.defined_functions[position].1 {
+                Some((_, body)) => body,
+                None => continue,
+            };
+            let (return_calls, table_slots) = (&self.return_calls, &self.table_slots);
+            let handwritten = &self.handwritten;
+            let positions = simplify(body, |at| {
+                return_calls.contains(&(id, at))
+                    || table_slots.contains(&(id, at))
+                    || handwritten.contains_key(&(id, at))
+            });
+            self.move_instructions(id, &positions);
+        }
+    }
+
+    /// Moves the `return_call`s, the slots, the instructions written by hand
+    /// and the spans of the function `id` along with its instructions, the
+    /// one at each index of which is at `positions[index]` now.
+    fn move_instructions(&mut self, id: u32, positions: &[usize]) {
+        let moved = |instructions: HashSet<(u32, usize)>| {
+            instructions
+                .into_iter()
+                .map(|(function, at)| match function == id {
+                    true => (function, positions[at]),
+                    false => (function, at),
+                })
+                .collect()
+        };
+        self.return_calls = moved(std::mem::take(&mut self.return_calls));
+        self.table_slots = moved(std::mem::take(&mut self.table_slots));
+        self.handwritten = std::mem::take(&mut self.handwritten)
+            .into_iter()
+            .map(|((function, at), instruction)| match function == id {
+                true => ((function, positions[at]), instruction),
+                false => ((function, at), instruction),
+            })
+            .collect();
+        if let Some(lines) = &mut self.lines {
+            lines.move_marks(id, positions);
+        }
+    }
+
+    /// The value types `ty` is passed around as, one for each of its
+    /// `leaves`. The unit is an `i32` like any other value for now.
+    pub fn convert_type(&self, ty: TypeId) -> Vec<ValType> {
+        self.leaves(ty)
+            .into_iter()
+            .map(|ty| match ty {
+                TypeId::I64 => ValType::I64,
+                TypeId::F32 => ValType::F32,
+                TypeId::F64 => ValType::F64,
+                ty if ty.lanes().is_some() => ValType::V128,
+                _ => ValType::I32,
+            })
+            .collect()
+    }
+
+    /// The types of the values a value of `ty` is made of, which is the
+    /// values of its elements, in order, for a tuple, whether it isn't
+    /// `null` and the values of its value for an optional of a value which
+    /// isn't a pointer, and itself otherwise.
+    pub fn leaves(&self, ty: TypeId) -> Vec<TypeId> {
+        match self.types.get(ty) {
+            Type::Tuple(elements) if !elements.is_empty() => elements
+                .iter()
+                .flat_map(|element| self.leaves(*element))
+                .collect(),
+            Type::Optional(inner) if !self.is_pointer(*inner) => {
+                let mut leaves = vec![TypeId::BOOL];
+                leaves.extend(self.leaves(*inner));
+                leaves
+            }
+            _ => vec![ty],
+        }
+    }
+
+    /// The number of bytes a value of `ty` takes in memory, which is the
+    /// sizes of the value types it is passed as.
+    pub fn size_of(&self, ty: TypeId) -> u32 {
+        self.convert_type(ty).into_iter().map(value_size).sum()
+    }
+
+    /// Whether a value of `ty` is the pointer to a block on the heap, or to
+    /// a literal in the data segments. A function value is the one to its
+    /// environment.
+    pub fn is_pointer(&self, ty: TypeId) -> bool {
+        matches!(
+            self.types.get(ty),
+            Type::String
+                | Type::Array(_)
+                | Type::Adt { .. }
+                | Type::Dynamic(_)
+                | Type::Function { .. }
+        )
+    }
+
+    /// Whether a value of `ty` is a string or an array, which an `extern fn`
+    /// can take as the address and the length of its contents.
+    pub fn is_sequence(&self, ty: TypeId) -> bool {
+        matches!(self.types.get(ty), Type::String | Type::Array(_))
+    }
+
+    /// The value types an `extern fn` taking its strings and arrays as the
+    /// address and the length of their contents takes a value of `ty` as.
+    pub fn ptr_len_types(&self, ty: TypeId) -> Vec<ValType> {
+        self.leaves(ty)
+            .into_iter()
+            .flat_map(|leaf| {
+                if self.is_sequence(leaf) {
+                    vec![ValType::I32, ValType::I32]
+                } else {
+                    self.convert_type(leaf)
+                }
+            })
+            .collect()
+    }
+
+    /// Whether the references to a value of `ty` are counted, which is when
+    /// it is a pointer, or an optional one.
+    pub fn is_counted(&self, ty: TypeId) -> bool {
+        match self.types.get(ty) {
+            Type::Optional(inner) => self.is_pointer(*inner),
+            _ => self.is_pointer(ty),
+        }
+    }
+
+    pub fn types(&self) -> &Types {
+        &self.types
+    }
+
+    /// How `ty` is named in the functions implementing the operators, like
+    /// `Add__i32_i32`.
+    pub fn type_name(&self, ty: TypeId) -> String {
+        self.types.display(ty)
     }
 
     pub fn declare_extern_type(
@@ -114,62 +1097,334 @@ impl<'a> Context<'a> {
         Ok(())
     }
 
+    pub fn declare_extern_link(
+        &mut self,
+        name: impl Into<String>,
+        module: impl Into<String>,
+        field: impl Into<String>,
+    ) {
+        self.extern_links
+            .insert(name.into(), (module.into(), field.into()));
+    }
+
+    pub fn declare_ptr_len_extern(&mut self, name: impl Into<String>) {
+        self.ptr_len_externs.insert(name.into());
+    }
+
+    /// Whether the name expression `id` refers to an `extern fn` taking its
+    /// strings and arrays as the address and the length of their contents.
+    pub fn takes_ptr_len(&self, id: NodeId) -> bool {
+        match self.resolution.references.get(id) {
+            Some(definition) => {
+                let definition = self.resolution.target(*definition);
+                let definition = &self.resolution.definitions[definition];
+                matches!(
+                    definition.kind,
+                    DefinitionKind::Function {
+                        is_extern: true,
+                        ..
+                    }
+                ) && self.ptr_len_externs.contains(&definition.name.content)
+            }
+            None => false,
+        }
+    }
+
     pub fn declare_function_type(
         &mut self,
         parameters_type: Vec<ValType>,
         return_type: Vec<ValType>,
     ) -> u32 {
-        self.type_section.function(parameters_type, return_type);
-        let result = self.type_section_last_id;
-        self.type_section_last_id += 1;
+        let signature = (parameters_type, return_type);
+        if let Some(id) = self.function_types.iter().position(|ty| *ty == signature) {
+            return id as u32;
+        }
+        self.type_section
+            .function(signature.0.iter().copied(), signature.1.iter().copied());
+        self.function_types.push(signature);
+        (self.function_types.len() - 1) as u32
+    }
 
-        result
+    /// Remembers the signature of the function declared by the item `item`.
+    pub fn declare_function_signature(
+        &mut self,
+        item: NodeId,
+        parameters: Vec<TypeId>,
+        result: TypeId,
+    ) {
+        if let Some(definition) = self.resolution.items.get(item) {
+            self.function_signatures
+                .insert(*definition, (parameters, result));
+        }
     }
 
-    pub fn implement_function(&mut self, function_type_id: u32, body: Vec<Instruction>) {
-        self.function_section.function(function_type_id);
-        // TODO: local variables....
-        let locals = vec![];
-        let mut function = Function::new(locals);
-        for instruction in body {
-            function.instruction(instruction);
+    /// The signature of the function the name expression `id` refers to,
+    /// once it is declared.
+    pub fn function_signature(&self, id: NodeId) -> Option<&(Vec<TypeId>, TypeId)> {
+        let definition = self.resolution.references.get(id)?;
+        self.function_signatures
+            .get(&self.resolution.target(*definition))
+    }
+
+    /// Calls the function value of the function type `ty` the slot `value`
+    /// holds through the table, with the arguments on the stack, leaving
+    /// what it gives. The function in the slot of its environment takes the
+    /// environment behind them.
+    pub fn call_indirect(&mut self, value: u32, ty: u32) {
+        self.indirect_calls = true;
+        self.instructions.extend(vec![
+            Instruction::LocalGet(value),
+            Instruction::LocalGet(value),
+            Instruction::I32Load(MemArg {
+                offset: 0,
+                align: 2,
+                memory_index: 0,
+            }),
+            Instruction::CallIndirect { ty, table: 0 },
+        ]);
+    }
+
+    /// The function calling the function `id`, declared in the program as
+    /// `name`, with the values of `parameters` and the environment of a
+    /// function value, which it leaves alone, giving a value of
+    /// `return_type`. It is made the first time it is asked for.
+    pub fn thunk(
+        &mut self,
+        id: u32,
+        name: &str,
+        parameters: &[TypeId],
+        return_type: TypeId,
+    ) -> u32 {
+        if let Some(thunk) = self.thunks.get(&id) {
+            return *thunk;
         }
-        self.code_section.function(&function);
+        let mut parameters_type: Vec<_> = parameters
+            .iter()
+            .flat_map(|ty| self.convert_type(*ty))
+            .collect();
+        let width = parameters_type.len() as u32;
+        parameters_type.push(ValType::I32);
+        let results = self.result_types(return_type);
+        let type_id = self.declare_function_type(parameters_type, results);
+        let thunk = self.declare_function(type_id);
+        self.name_function(thunk, format!("{}::{{thunk}}", name));
+        let mut body: Vec<_> = (0..width).map(Instruction::LocalGet).collect();
+        body.extend(vec![Instruction::Call(id), Instruction::End]);
+        self.implement_function(thunk, vec![], body);
+        self.thunks.insert(id, thunk);
+        thunk
     }
 
-    pub fn export_function<S: AsRef<str>>(&mut self, name: S, id: u32) {
-        self.export_section
-            .export(name.as_ref(), Export::Function(id));
+    /// Places the function in the exported function table so the host can
+    /// call it back through its slot, which is pushed.
+    pub fn push_table_slot(&mut self, id: u32) {
+        let slot = match self
+            .table_functions
+            .iter()
+            .position(|&function| function == id)
+        {
+            Some(slot) => slot,
+            None => {
+                self.table_functions.push(id);
+                self.table_functions.len() - 1
+            }
+        };
+        self.table_slots
+            .insert((self.tail_calls.function, self.instructions.len()));
+        self.instructions.push(Instruction::I32Const(slot as i32));
     }
 
-    pub fn set_local(&mut self, name: String, id: u32) {
-        self.locals
-            .entry(name)
-            .or_insert(VecDeque::new())
-            .push_back(id);
+    /// Numbers a function of the type `function_type_id` the module
+    /// defines, which can be called before it is implemented.
+    pub fn declare_function(&mut self, function_type_id: u32) -> u32 {
+        self.defined_functions.push((function_type_id, None));
+        DEFINED_FUNCTIONS + (self.defined_functions.len() - 1) as u32
     }
 
-    pub fn get_local(&mut self, name: &String, span: Span) -> Result<u32, Error> {
+    /// Names the function `id` in the `name` section, and the DWARF.
+    pub fn name_function(&mut self, id: u32, name: impl Into<String>) {
+        self.names.function(id, name);
+    }
+
+    /// Names the locals of the function `id`, parameters included, which is
+    /// being walked. At `-O0` the ones `names` leaves out are named too.
+    pub fn name_locals(&mut self, id: u32, mut names: Vec<(u32, String)>) {
+        if self.opt_level.name_every_local() {
+            self.locals.name_the_rest(&mut names);
+        }
+        self.names.locals(id, names);
+    }
+
+    /// The names of the `let` locals of the function being walked.
+    pub fn let_names(&self) -> Vec<(u32, String)> {
         self.locals
-            .get(name)
-            .and_then(|deque| deque.back())
-            .cloned()
-            .ok_or_else(|| Error::undefined_symbol(name, span))
+            .bindings()
+            .map(|(definition, slot)| {
+                let name = &self.resolution.definitions[definition].name;
+                (slot, name.content.clone())
+            })
+            .collect()
+    }
+
+    /// Starts recording the spans of the instructions of a function.
+    pub fn enter_function_spans(&mut self) {
+        if let Some(lines) = &mut self.lines {
+            lines.enter_function();
+        }
+    }
+
+    /// Stops recording the spans of the instructions of the function `id`.
+    pub fn leave_function_spans(&mut self, id: u32) {
+        if let Some(lines) = &mut self.lines {
+            lines.leave_function(id);
+        }
+    }
+
+    /// Starts walking an expression, which the instructions emitted from
+    /// now on are lowered from.
+    pub fn enter_span(&mut self, span: &Span) {
+        if let Some(lines) = &mut self.lines {
+            lines.enter_span(self.instructions.len(), span);
+        }
+    }
+
+    pub fn leave_span(&mut self) {
+        if let Some(lines) = &mut self.lines {
+            lines.leave_span(self.instructions.len());
+        }
+    }
+
+    pub fn implement_function(
+        &mut self,
+        id: u32,
+        locals: Vec<ValType>,
+        body: Vec<Instruction<'a>>,
+    ) {
+        self.defined_functions[(id - DEFINED_FUNCTIONS) as usize].1 = Some((locals, body));
     }
 
-    pub fn remove_local(&mut self, name: &String) {
-        if let Some(deque) = self.locals.get_mut(name) {
-            deque.pop_back();
+    /// Sets whether the calls of the function `id` are inlined.
+    pub fn hint_inline(&mut self, id: u32, inline: Inline) {
+        self.inline_hints.insert(id, inline);
+    }
+
+    pub fn export_function<S: AsRef<str>>(&mut self, name: S, id: u32) {
+        self.exported_functions.push((name.as_ref().to_owned(), id));
+    }
+
+    /// Describes `function`, of the index `id`, in the `rano.meta` section
+    /// as one of the functions the module exports.
+    pub fn describe_export(&mut self, id: u32, function: &thir::Function) {
+        let parameters = function
+            .parameters
+            .iter()
+            .map(|(pattern, ty)| {
+                let name = match pattern {
+                    Pattern::Slot(Name::Ident(name)) => name.content.clone(),
+                    _ => "_".to_owned(),
+                };
+                (name, self.type_name(*ty))
+            })
+            .collect();
+        let signature = Signature {
+            name: self.qualified_name(&function.name.content),
+            parameters,
+            return_type: self.type_name(function.return_type),
+        };
+        self.exported_signatures.push((id, signature));
+    }
+
+    /// Remembers the index of the function declared by the item `item`, so
+    /// the names referring to it can be resolved from now on.
+    pub fn define_function(&mut self, item: NodeId, id: u32) {
+        if let Some(definition) = self.resolution.items.get(item) {
+            self.functions.insert(*definition, id);
+        }
+    }
+
+    /// The index of the function declared by the item `item`, once it is.
+    pub fn function_index(&self, item: NodeId) -> Option<u32> {
+        let definition = self.resolution.items.get(item)?;
+        self.functions.get(definition).copied()
+    }
+
+    /// The definition of the function or the module declared by the item
+    /// `item`.
+    pub fn item_definition(&self, item: NodeId) -> Option<Idx<Definition>> {
+        self.resolution.items.get(item).copied()
+    }
+
+    /// The definition the name expression `id` refers to, behind the
+    /// imports leading to it.
+    pub fn referred_definition(&self, id: NodeId) -> Option<Idx<Definition>> {
+        let definition = self.resolution.references.get(id)?;
+        Some(self.resolution.target(*definition))
+    }
+
+    /// Whether the name expression `id` refers to the function declared by
+    /// the item `item`.
+    pub fn refers_to_item(&self, id: NodeId, item: NodeId) -> bool {
+        match (
+            self.resolution.references.get(id),
+            self.resolution.items.get(item),
+        ) {
+            (Some(definition), Some(item)) => self.resolution.target(*definition) == *item,
+            _ => false,
         }
     }
 
-    pub fn resolve(&mut self, name: &String, span: Span) -> Result<u32, Error> {
-        self.get_local(name, span.clone())
-            .or(self.import("extern", name, span.clone()))
+    /// Whether the name expression `id` refers to one of the functions
+    /// codegen lowers by itself.
+    pub fn is_builtin(&self, id: NodeId) -> bool {
+        matches!(
+            self.resolution.reference(id),
+            Some(Definition {
+                kind: DefinitionKind::Builtin,
+                ..
+            })
+        )
+    }
+
+    /// The index the name expression `id` refers to, which is a local for a
+    /// parameter or a `let` and a function otherwise.
+    pub fn resolve(&mut self, id: NodeId, name: &Token) -> Result<u32, Error> {
+        let undefined = || Error::undefined_symbol(&name.content, name.span.clone());
+        let definition = self
+            .resolution
+            .references
+            .get(id)
+            .map(|definition| self.resolution.target(*definition))
+            .ok_or_else(undefined)?;
+        match &self.resolution.definitions[definition].kind {
+            DefinitionKind::Parameter { index, .. } => {
+                self.locals.parameter(*index).ok_or_else(undefined)
+            }
+            DefinitionKind::Local => self.locals.get(definition).ok_or_else(undefined),
+            // one which isn't declared failed to be, like a generic one, and
+            // its calls can't be compiled either.
+            DefinitionKind::Function {
+                is_extern: false, ..
+            } => self
+                .functions
+                .get(&definition)
+                .copied()
+                .ok_or_else(|| Error::unimplemented(name)),
+            DefinitionKind::Function {
+                is_extern: true, ..
+            } => {
+                let function = self.resolution.definitions[definition].name.content.clone();
+                match self.extern_links.get(&function).cloned() {
+                    Some((module, field)) => self.import(&module, &field, name.span.clone()),
+                    None => self.import("extern", &function, name.span.clone()),
+                }
+            }
+            _ => Err(Error::unimplemented(name)),
+        }
     }
 
     pub fn import(&mut self, module: &str, name: &String, span: Span) -> Result<u32, Error> {
-        if let Some(id) = self.imports.get(name) {
+        let key = format!("{}.{}", module, name);
+        if let Some(id) = self.imports.get(&key) {
             return Ok(*id);
         }
         let (_, ty) = self
@@ -221,25 +1476,584 @@ impl<'a> Context<'a> {
         let result = *counter;
         *counter += 1;
 
-        self.import_section.import(module, Some(&name), ty);
+        if matches!(ty, EntityType::Function(_)) {
+            self.names.function(result, key.clone());
+        }
+        self.imported.push((module.to_owned(), name.clone(), ty));
 
-        self.imports.insert(name.clone(), result);
+        self.imports.insert(key, result);
 
         Ok(result)
     }
 
-    pub fn create_data<D>(&mut self, data: D)
+    /// Imports a function the compiler itself relies on, rather than one the
+    /// source declared with `extern fn`.
+    pub fn import_builtin(
+        &mut self,
+        module: &str,
+        name: &str,
+        parameters_type: Vec<ValType>,
+        return_type: Vec<ValType>,
+    ) -> u32 {
+        let key = format!("{}.{}", module, name);
+        if let Some(id) = self.imports.get(&key) {
+            return *id;
+        }
+        let type_id = self.declare_function_type(parameters_type, return_type);
+        let result = self.import_index_function;
+        self.import_index_function += 1;
+        self.imported.push((
+            module.to_owned(),
+            name.to_owned(),
+            EntityType::Function(type_id),
+        ));
+        self.names.function(result, key.clone());
+        self.imports.insert(key, result);
+
+        result
+    }
+
+    /// Where a module compiled on its own is placed behind this one.
+    pub fn placement_behind(&self) -> Placement {
+        Placement {
+            data: self.data_segment_last_offset,
+        }
+    }
+
+    /// Where `span` is in the source, as `file:line:column`.
+    pub fn location(&self, span: &Span) -> String {
+        let file = self
+            .file_names
+            .get(span.file.0 as usize)
+            .map(String::as_str)
+            .unwrap_or("<unknown>");
+        format!("{}:{}:{}", file, span.line + 1, span.column + 1)
+    }
+
+    /// Panics with the message on the stack, at `span`.
+    pub fn panic(&mut self, span: &Span) {
+        let location = self.location(span);
+        let location = self.string_literal(&location);
+        let panic = self.runtime().panic;
+        self.instructions.push(Instruction::I32Const(location));
+        self.instructions.push(Instruction::Call(panic));
+        // `unreachable` is valid whatever the stack is expected to hold.
+        self.instructions.push(Instruction::Unreachable);
+    }
+
+    /// Starts walking the body of a function taking values of the types
+    /// `parameters`, returning the locals of the one it is in, to leave it
+    /// with.
+    pub fn enter_function(&mut self, parameters: &[TypeId]) -> Locals {
+        let widths = parameters
+            .iter()
+            .map(|ty| self.leaves(*ty).len() as u32)
+            .collect::<Vec<_>>();
+        std::mem::replace(&mut self.locals, Locals::new(widths))
+    }
+
+    /// Counts the calls of the functions among `statements` which can call
+    /// themselves, if `CompileOptions::max_call_depth` is set.
+    pub fn count_recursive_calls(&mut self, statements: &[Statement]) {
+        if self.max_call_depth.is_some() {
+            self.counted_functions = self.find_recursive_functions(statements);
+        }
+    }
+
+    /// How many calls of the function `item` may be running at once, if
+    /// they are counted.
+    pub fn call_depth_limit(&self, item: NodeId) -> Option<u32> {
+        self.max_call_depth
+            .filter(|_| self.counted_functions.contains(&item))
+    }
+
+    /// Sets whether the calls of the function being walked are counted,
+    /// returning whether the ones of the function it is in were.
+    pub fn set_call_depth_counted(&mut self, counted: bool) -> bool {
+        std::mem::replace(&mut self.call_depth_counted, counted)
+    }
+
+    /// Starts walking the body of a function which makes the calls
+    /// `tail_calls` of itself, returning the ones of the function it is in.
+    pub fn enter_tail_calls(&mut self, tail_calls: TailCalls) -> TailCalls {
+        std::mem::replace(&mut self.tail_calls, tail_calls)
+    }
+
+    /// How many `if`s the call of the function being walked by the name
+    /// expression `callee` is inside of, if it is in a tail position.
+    pub fn tail_call_depth(&self, callee: NodeId) -> Option<u32> {
+        self.tail_calls.calls.get(&callee).copied()
+    }
+
+    /// Calls the function `id` by `return_call`, which the function being
+    /// walked returns what it gives from.
+    pub fn return_call(&mut self, id: u32) {
+        self.leave_frame();
+        if self.call_depth_counted {
+            self.leave_call_depth();
+        }
+        self.return_calls
+            .insert((self.tail_calls.function, self.instructions.len()));
+        self.instructions.push(Instruction::Call(id));
+    }
+
+    /// Pushes the atomic instruction `atomic`, standing in for which is a
+    /// `nop` until it is encoded.
+    pub fn push_atomic(&mut self, atomic: Atomic) {
+        self.use_memory();
+        self.push_handwritten(Handwritten::Atomic(atomic));
+    }
+
+    /// Pushes the SIMD instruction `simd`, like an atomic one.
+    pub fn push_simd(&mut self, simd: Simd) {
+        if simd.uses_memory() {
+            self.use_memory();
+        }
+        self.push_handwritten(Handwritten::Simd(simd));
+    }
+
+    /// Marks the `nop` at `at` in the body of the function `id` as the
+    /// atomic instruction `atomic`.
+    pub fn atomic_at(&mut self, id: u32, at: usize, atomic: Atomic) {
+        self.handwritten
+            .insert((id, at), Handwritten::Atomic(atomic));
+    }
+
+    /// Marks the `nop` at `at` in the body of the function `id` as the
+    /// `throw` of a panic.
+    pub fn throw_panic_at(&mut self, id: u32, at: usize) {
+        self.handwritten
+            .insert((id, at), Handwritten::Throw(Throw(PANIC_TAG)));
+    }
+
+    /// Makes a panic empty the shadow stack, as the functions it unwinds
+    /// can't give their frames back. It is only caught by the host, which
+    /// none of them is running anymore once it is.
+    fn reset_stack_on_panic(&mut self) {
+        use Instruction::*;
+        let panic = self.runtime.panic;
+        let reset = vec![
+            I32Const(STACK_BASE),
+            I32Load(MemArg {
+                offset: 0,
+                align: 2,
+                memory_index: 0,
+            }),
+            I32Const(STACK_SIZE),
+            I32Add,
+            GlobalSet(STACK_POINTER),
+        ];
+        let shift = reset.len();
+        let position = (panic - DEFINED_FUNCTIONS) as usize;
+        if let Some((_, body)) = &mut self.defined_functions[position].1 {
+            body.splice(0..0, reset);
+        }
+        self.handwritten = std::mem::take(&mut self.handwritten)
+            .into_iter()
+            .map(|((id, at), instruction)| match id == panic {
+                true => ((id, at + shift), instruction),
+                false => ((id, at), instruction),
+            })
+            .collect();
+    }
+
+    fn push_handwritten(&mut self, instruction: Handwritten) {
+        self.handwritten.insert(
+            (self.tail_calls.function, self.instructions.len()),
+            instruction,
+        );
+        self.instructions.push(Instruction::Nop);
+    }
+
+    /// Remembers the function declared by the item `item` is
+    /// `#[thread_safe]`.
+    pub fn declare_thread_safe(&mut self, item: NodeId) {
+        self.thread_safe_functions
+            .extend(self.item_definition(item));
+    }
+
+    /// Whether the name expression `id` refers to a `#[thread_safe]`
+    /// function.
+    pub fn is_thread_safe_function(&self, id: NodeId) -> bool {
+        matches!(self.referred_definition(id), Some(definition) if self.thread_safe_functions.contains(&definition))
+    }
+
+    /// Whether the function being walked is `#[thread_safe]`.
+    pub fn is_thread_safe(&self) -> bool {
+        self.thread_safe
+    }
+
+    /// Walks the items of the module `name` from now on, until
+    /// `leave_module`.
+    pub fn enter_module(&mut self, name: &str, is_pub: bool) {
+        self.modules.push((name.to_owned(), is_pub));
+    }
+
+    pub fn leave_module(&mut self) {
+        self.modules.pop();
+    }
+
+    /// `name` behind the modules the item being walked is in, the way a
+    /// `use` declaration names it.
+    pub fn qualified_name(&self, name: &str) -> String {
+        self.modules
+            .iter()
+            .map(|(module, _)| module.as_str())
+            .chain(std::iter::once(name))
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+
+    /// Whether a `pub` item where it is walked can be used from outside of
+    /// the module, which it can if the modules it is in are `pub` too.
+    pub fn is_visible(&self) -> bool {
+        self.modules.iter().all(|(_, is_pub)| *is_pub)
+    }
+
+    /// Sets whether the function being walked is `#[thread_safe]`,
+    /// returning whether the one it is in was.
+    pub fn set_thread_safe(&mut self, thread_safe: bool) -> bool {
+        std::mem::replace(&mut self.thread_safe, thread_safe)
+    }
+
+    /// The first slot of the parameter `index` of the function being
+    /// walked, the others of which follow it.
+    pub fn parameter_slot(&self, index: usize) -> u32 {
+        self.locals.parameter(index as u32).unwrap_or_default()
+    }
+
+    /// Stops walking the body of a function, returning the types of the
+    /// locals it needs besides the parameters.
+    pub fn leave_function(&mut self, outer: Locals) -> Vec<ValType> {
+        std::mem::replace(&mut self.locals, outer).into_types()
+    }
+
+    /// The local the `let` statement `id` binds, unless its pattern binds
+    /// none.
+    pub fn let_binding(&self, id: NodeId) -> Option<Idx<Definition>> {
+        self.resolution.lets.get(id).copied()
+    }
+
+    /// The first slot of the local `definition`, the value of which is of
+    /// the type `ty`. A tuple takes a slot for each of its leaves.
+    pub fn bind_local(&mut self, definition: Idx<Definition>, ty: TypeId) -> u32 {
+        let types = self.convert_type(ty);
+        self.locals.bind(definition, &types)
+    }
+
+    /// `count` locals of the type `ty` for the instructions to keep values
+    /// in, which are the same ones every time.
+    pub fn scratch_locals(&mut self, ty: ValType, count: usize) -> Vec<u32> {
+        self.locals.scratch(ty, count)
+    }
+
+    /// Gives the function being walked a frame on the shadow stack for the
+    /// locals `address_taken`, returning the slot holding its address.
+    pub fn allocate_frame(&mut self, address_taken: HashSet<Idx<Definition>>) -> u32 {
+        self.locals.enter_frame(address_taken)
+    }
+
+    /// The slot holding the address of the frame of the function being
+    /// walked, if it has one.
+    pub fn frame_pointer(&self) -> Option<u32> {
+        self.locals.frame_pointer()
+    }
+
+    /// Notes that the instruction at `at` is the `i32.const` of the size of
+    /// the frame, which `finish_frame` sets.
+    pub fn note_frame_size(&mut self, at: usize) {
+        self.locals.note_frame_size(at);
+    }
+
+    /// The size of the frame of the function being walked, if it has one,
+    /// with the positions of its `i32.const`s.
+    pub fn frame_size(&self) -> Option<(u32, Vec<usize>)> {
+        let (size, positions) = self.locals.frame_size()?;
+        Some((size, positions.to_vec()))
+    }
+
+    /// The offset of the local `definition` in the frame, which is placed
+    /// there taking `size` bytes, if its address is taken.
+    pub fn place_in_frame(&mut self, definition: Idx<Definition>, size: u32) -> Option<u32> {
+        self.locals.place(definition, size)
+    }
+
+    /// The place of the local `definition`, once it is placed in the frame,
+    /// or if the closure being walked captures it.
+    pub fn local_place(&self, definition: Idx<Definition>) -> Option<Place> {
+        self.locals.place_of(definition)
+    }
+
+    /// The first slot of the parameter or the local `definition`, unless it
+    /// lives in the memory or has none yet.
+    pub fn local_slot(&self, definition: Idx<Definition>) -> Option<u32> {
+        match self.resolution.definitions[definition].kind {
+            DefinitionKind::Parameter { index, .. } => self.locals.parameter(index),
+            DefinitionKind::Local => self.locals.get(definition),
+            _ => None,
+        }
+    }
+
+    /// The locals the parameters of the closure `id` bind.
+    pub fn closure_parameters(&self, id: NodeId) -> Vec<Option<Idx<Definition>>> {
+        self.resolution
+            .closure_parameters
+            .get(id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Binds the local `definition` to the slots of the parameter `index` of
+    /// the closure being walked, which binds it.
+    pub fn bind_closure_parameter(&mut self, definition: Idx<Definition>, index: usize) {
+        self.locals.bind_parameter(definition, index as u32);
+    }
+
+    /// Has the closure being walked find the local `definition` it captures
+    /// at `place`.
+    pub fn capture_local(&mut self, definition: Idx<Definition>, place: Place) {
+        self.locals.capture(definition, place);
+    }
+
+    /// A slot of the type `ty` of its own, which the value kept in it stays
+    /// in while anything else is walked.
+    pub fn temporary_local(&mut self, ty: ValType) -> u32 {
+        self.locals.temporary(ty)
+    }
+
+    pub fn overflow(&self) -> Overflow {
+        self.overflow
+    }
+
+    /// Sets what the integer arithmetic does on overflow, returning what it
+    /// did before.
+    pub fn set_overflow(&mut self, overflow: Overflow) -> Overflow {
+        std::mem::replace(&mut self.overflow, overflow)
+    }
+
+    /// Whether the name expression `id` refers to a parameter or a local.
+    pub fn is_local(&self, id: NodeId) -> bool {
+        let definition = match self.resolution.references.get(id) {
+            Some(definition) => self.resolution.target(*definition),
+            None => return false,
+        };
+        matches!(
+            self.resolution.definitions[definition].kind,
+            DefinitionKind::Parameter { .. } | DefinitionKind::Local
+        )
+    }
+
+    pub fn enter_unsafe(&mut self) {
+        self.unsafe_depth += 1;
+    }
+
+    pub fn leave_unsafe(&mut self) {
+        self.unsafe_depth -= 1;
+    }
+
+    pub fn is_unsafe(&self) -> bool {
+        self.unsafe_depth > 0
+    }
+
+    pub fn runtime(&self) -> Runtime {
+        self.runtime
+    }
+
+    /// The routines formatting and parsing floats, emitted the first time
+    /// they are asked for.
+    pub fn floats(&mut self) -> Floats {
+        match self.floats {
+            Some(floats) => floats,
+            None => {
+                let floats = self.emit_floats();
+                self.floats = Some(floats);
+                floats
+            }
+        }
+    }
+
+    /// The routines of the channels, emitted the first time they are asked
+    /// for.
+    pub fn channels(&mut self) -> Channels {
+        match self.channels {
+            Some(channels) => channels,
+            None => {
+                let channels = self.emit_channels();
+                self.channels = Some(channels);
+                channels
+            }
+        }
+    }
+
+    pub fn target(&self) -> TargetOptions {
+        self.target
+    }
+
+    /// Counts one more reference to the value of the type `ty` on the stack,
+    /// if its references are counted, and leaves it there.
+    pub fn retain(&mut self, ty: TypeId) {
+        if self.is_counted(ty) {
+            self.instructions
+                .push(Instruction::Call(self.runtime.retain));
+        }
+    }
+
+    /// Takes the value of the type `ty` off the stack, releasing it if its
+    /// references are counted. A tuple is taken off from its last value.
+    pub fn release(&mut self, ty: TypeId) {
+        for leaf in self.leaves(ty).into_iter().rev() {
+            if self.is_counted(leaf) {
+                self.instructions
+                    .push(Instruction::Call(self.runtime.release));
+            } else {
+                self.instructions.push(Instruction::Drop);
+            }
+        }
+    }
+
+    /// Releases the reference in the slot `slot`.
+    pub fn release_local(&mut self, slot: u32) {
+        self.instructions.push(Instruction::LocalGet(slot));
+        self.instructions
+            .push(Instruction::Call(self.runtime.release));
+    }
+
+    /// Releases the references the parameters of the types `parameters`
+    /// hold, which the function owns.
+    pub fn release_parameters(&mut self, parameters: &[TypeId]) {
+        for (index, ty) in parameters.iter().enumerate() {
+            let slot = self.parameter_slot(index);
+            for (offset, leaf) in self.leaves(*ty).into_iter().enumerate() {
+                if self.is_counted(leaf) {
+                    self.release_local(slot + offset as u32);
+                }
+            }
+        }
+    }
+
+    /// Releases the references the locals of every block being walked
+    /// hold, before a call which none of them is used after.
+    pub fn release_owned(&mut self) {
+        for owned in self.locals.owned() {
+            self.release_owned_reference(owned);
+        }
+    }
+
+    fn release_owned_reference(&mut self, owned: Owned) {
+        match owned {
+            Owned::Slot(slot) => self.release_local(slot),
+            Owned::Frame(offset) => self.release_framed(self.in_frame(offset)),
+        }
+    }
+
+    /// Starts walking a block, which releases the references its locals
+    /// hold at its end.
+    pub fn enter_scope(&mut self) {
+        self.locals.enter_scope();
+    }
+
+    /// Has the block being walked release the reference in `slot` at its
+    /// end.
+    pub fn own_local(&mut self, slot: u32) {
+        self.locals.own(Owned::Slot(slot));
+    }
+
+    /// Has the block being walked release the reference at `offset` in the
+    /// frame at its end.
+    pub fn own_framed(&mut self, offset: u32) {
+        self.locals.own(Owned::Frame(offset));
+    }
+
+    /// Releases the references the locals of the block being walked hold,
+    /// behind the value it gives.
+    pub fn leave_scope(&mut self) {
+        for owned in self.locals.leave_scope() {
+            self.release_owned_reference(owned);
+        }
+    }
+
+    /// Marks the memory as used, so `finish` defines and exports it.
+    pub fn use_memory(&mut self) {
+        self.memory_used = true;
+    }
+
+    /// Marks the shadow stack as used, so `finish` places it in the memory.
+    pub fn use_stack(&mut self) {
+        self.memory_used = true;
+        self.stack_used = true;
+    }
+
+    /// The address a tuple of the type `ty` is passed back through, laid
+    /// out like in memory, though its first value is given rather than
+    /// stored there.
+    pub fn result_area(&mut self, ty: TypeId) -> i32 {
+        if let Some(address) = self.result_areas.get(&ty) {
+            return *address;
+        }
+        let size = self.size_of(ty);
+        let address = self.create_data(vec![0; size as usize]);
+        self.result_areas.insert(ty, address);
+        address
+    }
+
+    /// The function comparing two tuples or two arrays of the type `ty`,
+    /// emitted the first time it is asked for, or `None` if they can't be
+    /// compared.
+    pub fn aggregate_eq(&mut self, ty: TypeId) -> Option<u32> {
+        if let Some(id) = self.aggregate_eqs.get(&ty) {
+            return Some(*id);
+        }
+        let id = self.emit_aggregate_eq(ty)?;
+        self.aggregate_eqs.insert(ty, id);
+        Some(id)
+    }
+
+    /// Places `data` in the memory, aligned to 4 bytes, and returns its
+    /// address.
+    pub fn create_data<D>(&mut self, data: D) -> i32
     where
         D: IntoIterator<Item = u8>,
         D::IntoIter: ExactSizeIterator,
     {
         let data = data.into_iter();
-        let id = self.data_segment_last_id;
-        self.data_segment_last_id += 1;
-        let offset = Instruction::I32Const(self.data_segment_last_offset);
-        self.data_segment_last_offset += data.len() as i32;
+        let address = (self.data_segment_last_offset + 3) & !3;
+        self.data_segment_last_offset = address + data.len() as i32;
+
+        self.use_memory();
+        self.data_segments.push((address, data.collect()));
+        address
+    }
+
+    /// The address of a literal made of the bytes `data`, which are only
+    /// placed in the data segments once, however many literals are made of
+    /// them.
+    pub fn literal_data(&mut self, data: Vec<u8>) -> i32 {
+        if let Some(&address) = self.literals.get(&data) {
+            return address;
+        }
+        let address = self.create_data(data.iter().copied());
+        self.literals.insert(data, address);
+        self.literal_references.insert(address, Vec::new());
+        address
+    }
+
+    /// The address of a string with the text `text`, laid out the way the
+    /// runtime expects.
+    pub fn string_literal(&mut self, text: &str) -> i32 {
+        let mut data = (text.len() as u32).to_le_bytes().to_vec();
+        data.extend_from_slice(text.as_bytes());
+        self.literal_data(data)
+    }
 
-        self.data_section.active(id, offset, data);
+    /// The address of an array of `length` elements, laid out the way the
+    /// runtime expects, the bytes of which are `elements`, holding the
+    /// addresses of the literals `references`.
+    pub fn array_literal(&mut self, length: u32, elements: &[u8], references: Vec<i32>) -> i32 {
+        let mut data = length.to_le_bytes().to_vec();
+        data.extend_from_slice(elements);
+        let address = self.literal_data(data);
+        self.literal_references.insert(address, references);
+        address
     }
 
     pub fn add_compilation_error(&mut self, error: Error) {
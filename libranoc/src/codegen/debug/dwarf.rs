@@ -0,0 +1,193 @@
+//! DWARF 4, as it is laid out for WASM: its sections are custom sections,
+//! and its addresses are offsets into the payload of the code section.
+//! Only a compile unit with a subprogram per function and the line table
+//! are written, which is what a debugger needs to show where it is.
+
+use wasm_encoder::encoders;
+
+use super::{Lines, Names};
+
+const DW_TAG_COMPILE_UNIT: u8 = 0x11;
+const DW_TAG_SUBPROGRAM: u8 = 0x2e;
+const DW_CHILDREN_NO: u8 = 0;
+const DW_CHILDREN_YES: u8 = 1;
+const DW_AT_NAME: u8 = 0x03;
+const DW_AT_STMT_LIST: u8 = 0x10;
+const DW_AT_LOW_PC: u8 = 0x11;
+const DW_AT_HIGH_PC: u8 = 0x12;
+const DW_AT_PRODUCER: u8 = 0x25;
+const DW_FORM_ADDR: u8 = 0x01;
+const DW_FORM_DATA4: u8 = 0x06;
+const DW_FORM_STRING: u8 = 0x08;
+const DW_FORM_SEC_OFFSET: u8 = 0x17;
+
+const DW_LNS_COPY: u8 = 1;
+const DW_LNS_ADVANCE_PC: u8 = 2;
+const DW_LNS_ADVANCE_LINE: u8 = 3;
+const DW_LNS_SET_FILE: u8 = 4;
+const DW_LNS_SET_COLUMN: u8 = 5;
+const DW_LNE_END_SEQUENCE: u8 = 1;
+const DW_LNE_SET_ADDRESS: u8 = 2;
+/// The number of operands of the standard opcodes, the special opcodes
+/// coming after them, which are never used.
+const STANDARD_OPCODE_LENGTHS: [u8; 12] = [0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1];
+
+const ABBREV_COMPILE_UNIT: u8 = 1;
+const ABBREV_SUBPROGRAM: u8 = 2;
+
+/// The DWARF sections by their names, for the code section of the size
+/// `code_size` with the rows of `lines`, the functions named by `names`
+/// and the source files named by `file_names`.
+pub fn dwarf_sections(
+    lines: &Lines,
+    names: &Names,
+    file_names: &[String],
+    code_size: usize,
+) -> Vec<(&'static str, Vec<u8>)> {
+    let unit_name = file_names
+        .first()
+        .map(String::as_str)
+        .unwrap_or("<unknown>");
+
+    let abbrev = vec![
+        ABBREV_COMPILE_UNIT,
+        DW_TAG_COMPILE_UNIT,
+        DW_CHILDREN_YES,
+        DW_AT_PRODUCER,
+        DW_FORM_STRING,
+        DW_AT_NAME,
+        DW_FORM_STRING,
+        DW_AT_STMT_LIST,
+        DW_FORM_SEC_OFFSET,
+        DW_AT_LOW_PC,
+        DW_FORM_ADDR,
+        DW_AT_HIGH_PC,
+        DW_FORM_DATA4,
+        0,
+        0,
+        ABBREV_SUBPROGRAM,
+        DW_TAG_SUBPROGRAM,
+        DW_CHILDREN_NO,
+        DW_AT_NAME,
+        DW_FORM_STRING,
+        DW_AT_LOW_PC,
+        DW_FORM_ADDR,
+        DW_AT_HIGH_PC,
+        DW_FORM_DATA4,
+        0,
+        0,
+        // the end of the abbreviations.
+        0,
+    ];
+
+    let mut dies = vec![ABBREV_COMPILE_UNIT];
+    string(&mut dies, &format!("ranoc {}", env!("CARGO_PKG_VERSION")));
+    string(&mut dies, unit_name);
+    dies.extend(0u32.to_le_bytes());
+    dies.extend(0u32.to_le_bytes());
+    dies.extend((code_size as u32).to_le_bytes());
+    for (id, start, size) in lines.ranges() {
+        if let Some(name) = names.function_name(*id) {
+            dies.push(ABBREV_SUBPROGRAM);
+            string(&mut dies, name);
+            dies.extend((*start as u32).to_le_bytes());
+            dies.extend((*size as u32).to_le_bytes());
+        }
+    }
+    dies.push(0);
+    let mut info = Vec::new();
+    // the version, the offset of the abbreviations and the size of an
+    // address come before the entries.
+    info.extend((2 + 4 + 1 + dies.len() as u32).to_le_bytes());
+    info.extend(4u16.to_le_bytes());
+    info.extend(0u32.to_le_bytes());
+    info.push(4);
+    info.extend(dies);
+
+    vec![
+        (".debug_abbrev", abbrev),
+        (".debug_info", info),
+        (".debug_line", line_table(lines, file_names, code_size)),
+    ]
+}
+
+fn line_table(lines: &Lines, file_names: &[String], code_size: usize) -> Vec<u8> {
+    let files = lines.file_count(file_names.len()).max(1);
+    let mut header = vec![
+        // the minimum size of an instruction, the most operations one
+        // has, whether a row is a statement, and the line base, line
+        // range and opcode base of the special opcodes.
+        1,
+        1,
+        1,
+        -5i8 as u8,
+        14,
+        STANDARD_OPCODE_LENGTHS.len() as u8 + 1,
+    ];
+    header.extend(STANDARD_OPCODE_LENGTHS.iter());
+    // no include directories.
+    header.push(0);
+    for file in 0..files {
+        let name = file_names
+            .get(file)
+            .map(String::as_str)
+            .unwrap_or("<unknown>");
+        string(&mut header, name);
+        // the directory, modification time and size, all unknown.
+        header.extend([0, 0, 0].iter());
+    }
+    header.push(0);
+
+    let mut program = vec![0, 5, DW_LNE_SET_ADDRESS];
+    program.extend(0u32.to_le_bytes());
+    let (mut address, mut file, mut line, mut column) = (0, 1, 1, 0);
+    for (row_address, span) in lines.rows() {
+        let (row_file, row_line, row_column) = match span {
+            Some(span) => (
+                span.file.0 + 1,
+                span.line as i32 + 1,
+                span.column as u32 + 1,
+            ),
+            None => (file, 0, 0),
+        };
+        if row_file != file {
+            program.push(DW_LNS_SET_FILE);
+            program.extend(encoders::u32(row_file));
+            file = row_file;
+        }
+        if row_line != line {
+            program.push(DW_LNS_ADVANCE_LINE);
+            program.extend(encoders::s32(row_line - line));
+            line = row_line;
+        }
+        if row_column != column {
+            program.push(DW_LNS_SET_COLUMN);
+            program.extend(encoders::u32(row_column));
+            column = row_column;
+        }
+        if row_address != address {
+            program.push(DW_LNS_ADVANCE_PC);
+            program.extend(encoders::u32((row_address - address) as u32));
+            address = row_address;
+        }
+        program.push(DW_LNS_COPY);
+    }
+    if code_size > address {
+        program.push(DW_LNS_ADVANCE_PC);
+        program.extend(encoders::u32((code_size - address) as u32));
+    }
+    program.extend([0, 1, DW_LNE_END_SEQUENCE].iter());
+
+    let mut table = Vec::new();
+    table.extend((2 + 4 + header.len() as u32 + program.len() as u32).to_le_bytes());
+    table.extend(4u16.to_le_bytes());
+    table.extend((header.len() as u32).to_le_bytes());
+    table.extend(header);
+    table.extend(program);
+    table
+}
+
+fn string(sink: &mut Vec<u8>, string: &str) {
+    sink.extend(string.as_bytes());
+    sink.push(0);
+}
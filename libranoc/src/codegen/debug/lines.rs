@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use crate::syntax::Span;
+
+/// The spans of the instructions, recorded while they are emitted: from
+/// each mark on, the instructions of a function are lowered from the
+/// expression it has the span of. Once the functions are placed in the
+/// code section, they are rows of a line table, which the DWARF and the
+/// source map are both written from.
+#[derive(Debug, Default)]
+pub struct Lines {
+    /// The spans of the expressions being walked, innermost last.
+    spans: Vec<Span>,
+    /// The marks of the functions being walked, innermost last, by the
+    /// index of the instruction they start at.
+    marks: Vec<Vec<(usize, Span)>>,
+    /// The marks of each function walked, by its id.
+    functions: HashMap<u32, Vec<(usize, Span)>>,
+    /// The rows of the line table, by their offset into the payload of the
+    /// code section, and `None` for code lowered from no expression.
+    rows: Vec<(usize, Option<Span>)>,
+    /// The id, start and size of each function in the code section.
+    ranges: Vec<(u32, usize, usize)>,
+}
+
+impl Lines {
+    pub fn enter_function(&mut self) {
+        self.marks.push(Vec::new());
+    }
+
+    /// Stops recording the marks of the function `id`.
+    pub fn leave_function(&mut self, id: u32) {
+        if let Some(marks) = self.marks.pop() {
+            self.functions.insert(id, marks);
+        }
+    }
+
+    /// Starts walking an expression at the instruction `index`.
+    pub fn enter_span(&mut self, index: usize, span: &Span) {
+        self.spans.push(span.clone());
+        self.mark(index, span.clone());
+    }
+
+    /// Stops walking the innermost expression at the instruction `index`,
+    /// the ones after it belonging to the expression around it again.
+    pub fn leave_span(&mut self, index: usize) {
+        self.spans.pop();
+        if let Some(span) = self.spans.last().cloned() {
+            self.mark(index, span);
+        }
+    }
+
+    /// Moves the marks of the function `id` along with its instructions,
+    /// the one at each index of which is at `positions[index]` now.
+    pub fn move_marks(&mut self, id: u32, positions: &[usize]) {
+        if let Some(marks) = self.functions.get_mut(&id) {
+            for (index, _) in marks {
+                if let Some(position) = positions.get(*index) {
+                    *index = *position;
+                }
+            }
+        }
+    }
+
+    /// Takes the marks of the functions `other` walked, which are
+    /// renumbered by `id`, which gives none for the ones left out.
+    pub fn absorb(&mut self, other: Lines, id: impl Fn(u32) -> Option<u32>) {
+        for (function, marks) in other.functions {
+            if let Some(function) = id(function) {
+                self.functions.insert(function, marks);
+            }
+        }
+    }
+
+    fn mark(&mut self, index: usize, span: Span) {
+        if let Some(marks) = self.marks.last_mut() {
+            match marks.last_mut() {
+                Some(last) if last.0 == index => last.1 = span,
+                _ => marks.push((index, span)),
+            }
+        }
+    }
+
+    /// Places the function `id` in the code section, its locals starting
+    /// at `start` and its instructions at `code`, at the `offsets` from
+    /// there, and its body ending at `end`.
+    pub fn place_function(
+        &mut self,
+        id: u32,
+        start: usize,
+        code: usize,
+        offsets: &[usize],
+        end: usize,
+    ) {
+        self.ranges.push((id, start, end - start));
+        match self.functions.remove(&id) {
+            Some(marks) => {
+                // a mark behind the last instruction marks nothing.
+                for (index, span) in marks {
+                    if let Some(offset) = offsets.get(index) {
+                        self.rows.push((code + offset, Some(span)));
+                    }
+                }
+            }
+            None => self.rows.push((start, None)),
+        }
+    }
+
+    /// The rows of the line table, by their offsets.
+    pub fn rows(&self) -> Vec<(usize, Option<&Span>)> {
+        let mut rows: Vec<_> = self
+            .rows
+            .iter()
+            .map(|(offset, span)| (*offset, span.as_ref()))
+            .collect();
+        rows.sort_by_key(|(offset, _)| *offset);
+        rows
+    }
+
+    /// The id, start and size of each function in the code section.
+    pub fn ranges(&self) -> &[(u32, usize, usize)] {
+        &self.ranges
+    }
+
+    /// The number of source files the rows refer to, which is more than
+    /// `known` if some aren't named.
+    pub fn file_count(&self, known: usize) -> usize {
+        self.rows
+            .iter()
+            .filter_map(|(_, span)| span.as_ref())
+            .map(|span| span.file.0 as usize + 1)
+            .max()
+            .unwrap_or(0)
+            .max(known)
+    }
+}
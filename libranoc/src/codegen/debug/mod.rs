@@ -0,0 +1,129 @@
+//! The debug info of the module. The `name` section names its functions
+//! and locals, and with `CompileOptions::dwarf`, the DWARF sections map its
+//! instructions back to the expressions they were lowered from, so a
+//! debugger shows Rano names and lines. With `CompileOptions::source_map`,
+//! the same mapping is made a source map, for browsers.
+//!
+//! wasm-encoder writes none of them, so they are all encoded here.
+
+use std::collections::BTreeMap;
+
+use wasm_encoder::{encoders, CodeSection, Function, Instruction, Section};
+
+mod dwarf;
+mod lines;
+mod source_map;
+
+pub use dwarf::*;
+pub use lines::*;
+pub use source_map::*;
+
+/// The id of the subsection naming the functions.
+const FUNCTION_NAMES: u8 = 1;
+/// The id of the subsection naming the locals of each function.
+const LOCAL_NAMES: u8 = 2;
+
+/// The names of the functions and of their locals, by the ids codegen
+/// gives the functions, which `finish` turns into indices.
+#[derive(Debug, Default)]
+pub struct Names {
+    functions: BTreeMap<u32, String>,
+    locals: BTreeMap<u32, Vec<(u32, String)>>,
+}
+
+impl Names {
+    pub fn function(&mut self, id: u32, name: impl Into<String>) {
+        self.functions.insert(id, name.into());
+    }
+
+    pub fn function_name(&self, id: u32) -> Option<&str> {
+        self.functions.get(&id).map(String::as_str)
+    }
+
+    /// Names the locals of the function `id`, parameters included.
+    pub fn locals(&mut self, id: u32, mut names: Vec<(u32, String)>) {
+        if !names.is_empty() {
+            names.sort_by_key(|(local, _)| *local);
+            self.locals.insert(id, names);
+        }
+    }
+
+    /// Takes the names of `other`, the functions of which are renumbered by
+    /// `id`, which gives none for the ones left out.
+    pub fn absorb(&mut self, other: Names, id: impl Fn(u32) -> Option<u32>) {
+        for (function, name) in other.functions {
+            if let Some(function) = id(function) {
+                self.functions.insert(function, name);
+            }
+        }
+        for (function, names) in other.locals {
+            if let Some(function) = id(function) {
+                self.locals.insert(function, names);
+            }
+        }
+    }
+
+    /// The `name` section, with the ids of the functions turned into their
+    /// indices by `index`, which gives none for the functions left out.
+    pub fn encode(&self, index: impl Fn(u32) -> Option<u32>) -> Vec<u8> {
+        let mut functions: Vec<_> = self
+            .functions
+            .iter()
+            .filter_map(|(id, name)| Some((index(*id)?, name)))
+            .collect();
+        functions.sort_by_key(|(index, _)| *index);
+        let mut payload = Vec::new();
+        payload.extend(encoders::u32(functions.len() as u32));
+        for (index, name) in functions {
+            payload.extend(encoders::u32(index));
+            payload.extend(encoders::str(name));
+        }
+        let mut section = Vec::new();
+        subsection(&mut section, FUNCTION_NAMES, &payload);
+
+        let mut locals: Vec<_> = self
+            .locals
+            .iter()
+            .filter_map(|(id, names)| Some((index(*id)?, names)))
+            .collect();
+        locals.sort_by_key(|(index, _)| *index);
+        let mut payload = Vec::new();
+        payload.extend(encoders::u32(locals.len() as u32));
+        for (index, names) in locals {
+            payload.extend(encoders::u32(index));
+            payload.extend(encoders::u32(names.len() as u32));
+            for (local, name) in names {
+                payload.extend(encoders::u32(*local));
+                payload.extend(encoders::str(name));
+            }
+        }
+        subsection(&mut section, LOCAL_NAMES, &payload);
+        section
+    }
+}
+
+fn subsection(section: &mut Vec<u8>, id: u8, payload: &[u8]) {
+    section.push(id);
+    section.extend(encoders::u32(payload.len() as u32));
+    section.extend_from_slice(payload);
+}
+
+/// Appends the encoding of `instruction` to `sink`. wasm-encoder only
+/// encodes an instruction into a function, so it is read back out of a
+/// code section holding one with nothing else.
+pub fn encode_instruction(instruction: Instruction, sink: &mut Vec<u8>) {
+    let mut function = Function::new(Vec::new());
+    function.instruction(instruction);
+    let mut code = CodeSection::new();
+    code.function(&function);
+    let mut bytes = Vec::new();
+    code.encode(&mut bytes);
+    // the size of the section, the number of functions and the size of the
+    // function come first, then the number of its locals, which is 0.
+    let mut rest = &bytes[..];
+    for _ in 0..3 {
+        let length = rest.iter().position(|byte| byte & 0x80 == 0).unwrap() + 1;
+        rest = &rest[length..];
+    }
+    sink.extend_from_slice(&rest[1..]);
+}
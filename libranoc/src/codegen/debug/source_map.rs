@@ -0,0 +1,119 @@
+//! Source maps, version 3, as browsers read them for WASM: there is one
+//! generated line, and a column of it is an offset into the module, so
+//! the rows of the line table are shifted by where the payload of the code
+//! section starts.
+
+use super::Lines;
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The source map of a module whose code section has its payload at
+/// `code`, with the rows of `lines` and the source files named by
+/// `file_names`.
+pub fn source_map(lines: &Lines, file_names: &[String], code: usize) -> String {
+    let mut sources = Vec::new();
+    for file in 0..lines.file_count(file_names.len()) {
+        let name = file_names
+            .get(file)
+            .map(String::as_str)
+            .unwrap_or("<unknown>");
+        sources.push(json_string(name));
+    }
+
+    let mut mappings = String::new();
+    // every field but the generated column is relative to the last segment
+    // that has it.
+    let (mut offset, mut file, mut line, mut column) = (0, 0, 0, 0);
+    for (row_offset, span) in lines.rows() {
+        if !mappings.is_empty() {
+            mappings.push(',');
+        }
+        let row_offset = (code + row_offset) as i64;
+        vlq(&mut mappings, row_offset - offset);
+        offset = row_offset;
+        // code lowered from no expression maps to nothing.
+        if let Some(span) = span {
+            let (row_file, row_line, row_column) =
+                (span.file.0 as i64, span.line as i64, span.column as i64);
+            vlq(&mut mappings, row_file - file);
+            vlq(&mut mappings, row_line - line);
+            vlq(&mut mappings, row_column - column);
+            file = row_file;
+            line = row_line;
+            column = row_column;
+        }
+    }
+
+    format!(
+        r#"{{"version":3,"sources":[{}],"names":[],"mappings":"{}"}}"#,
+        sources.join(","),
+        mappings
+    )
+}
+
+/// Appends `value` as a base64 VLQ: five bits a digit, least significant
+/// first, with the sign in the lowest bit of the first.
+fn vlq(sink: &mut String, value: i64) {
+    let mut value = if value < 0 {
+        ((-value as u64) << 1) | 1
+    } else {
+        (value as u64) << 1
+    };
+    loop {
+        let mut digit = (value & 0b11111) as usize;
+        value >>= 5;
+        if value != 0 {
+            digit |= 0b100000;
+        }
+        sink.push(BASE64[digit] as char);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+fn json_string(string: &str) -> String {
+    let mut json = String::from("\"");
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Where the payload of the code section of the encoded `module` starts,
+/// skipping the sections before it by their sizes.
+pub fn code_offset(module: &[u8]) -> usize {
+    const CODE: u8 = 10;
+    // behind the magic number and the version.
+    let mut offset = 8;
+    while let Some(&id) = module.get(offset) {
+        let (size, read) = leb128(&module[offset + 1..]);
+        offset += 1 + read;
+        if id == CODE {
+            return offset;
+        }
+        offset += size;
+    }
+    offset
+}
+
+/// An unsigned LEB128 and the number of bytes it took.
+fn leb128(bytes: &[u8]) -> (usize, usize) {
+    let mut value = 0;
+    for (index, byte) in bytes.iter().enumerate() {
+        value |= ((byte & 0x7f) as usize) << (7 * index);
+        if byte & 0x80 == 0 {
+            return (value, index + 1);
+        }
+    }
+    (value, bytes.len())
+}
@@ -0,0 +1,148 @@
+use wasm_encoder::{BlockType, Instruction, ValType};
+
+use crate::core::{Type, TypeId};
+
+use super::{
+    results::{load, value_size},
+    Context,
+};
+
+impl<'a> Context<'a> {
+    /// The instruction comparing two values of the type `leaf`, or `None`
+    /// if codegen can't compare them by itself. A string is compared by
+    /// the runtime, and an array by the function comparing arrays of its
+    /// type, which both release the two values.
+    fn leaf_eq(&mut self, leaf: TypeId) -> Option<Instruction<'a>> {
+        Some(match leaf {
+            TypeId::I32 | TypeId::BOOL | TypeId::UNIT => Instruction::I32Eq,
+            TypeId::I64 => Instruction::I64Eq,
+            TypeId::F32 => Instruction::F32Eq,
+            TypeId::F64 => Instruction::F64Eq,
+            TypeId::STRING => Instruction::Call(self.runtime().string_eq),
+            leaf if matches!(self.types().get(leaf), Type::Array(_)) => {
+                Instruction::Call(self.aggregate_eq(leaf)?)
+            }
+            _ => return None,
+        })
+    }
+
+    /// Declares and implements the function comparing two tuples or two
+    /// arrays of the type `ty`, or gives `None` if it is neither or one of
+    /// its elements can't be compared.
+    pub(super) fn emit_aggregate_eq(&mut self, ty: TypeId) -> Option<u32> {
+        match self.types().get(ty) {
+            Type::Tuple(_) => self.emit_tuple_eq(ty),
+            Type::Array(element) => self.emit_array_eq(ty, *element),
+            _ => None,
+        }
+    }
+
+    /// The function comparing two tuples element by element, nested tuples
+    /// included. It takes the values of both tuples and gives whether they
+    /// are all equal, comparing every one of them so the references the
+    /// tuples own are all released.
+    fn emit_tuple_eq(&mut self, ty: TypeId) -> Option<u32> {
+        let leaves = self.leaves(ty);
+        let comparisons = leaves
+            .iter()
+            .map(|leaf| self.leaf_eq(*leaf))
+            .collect::<Option<Vec<_>>>()?;
+        let value_types = self.convert_type(ty);
+        let parameters = value_types.iter().chain(&value_types).copied().collect();
+        let function_type = self.declare_function_type(parameters, vec![ValType::I32]);
+        let id = self.declare_function(function_type);
+
+        let count = leaves.len() as u32;
+        let mut body = vec![Instruction::I32Const(1)];
+        for (index, comparison) in comparisons.into_iter().enumerate() {
+            let index = index as u32;
+            body.extend(vec![
+                Instruction::LocalGet(index),
+                Instruction::LocalGet(count + index),
+                comparison,
+                Instruction::I32And,
+            ]);
+        }
+        body.push(Instruction::End);
+        self.implement_function(id, vec![], body);
+        self.name_function(id, format!("rano_eq__{}", self.type_name(ty)));
+        Some(id)
+    }
+
+    /// The function comparing two arrays of `element`s: they are equal if
+    /// they are as long and their elements are, which are compared in
+    /// order until two differ. It releases both arrays, and counts a new
+    /// reference to the strings and the arrays it reads out of them before
+    /// comparing them, as comparing them releases them too.
+    fn emit_array_eq(&mut self, ty: TypeId, element: TypeId) -> Option<u32> {
+        use Instruction::*;
+        let leaves = self.leaves(element);
+        let comparisons = leaves
+            .iter()
+            .map(|leaf| self.leaf_eq(*leaf))
+            .collect::<Option<Vec<_>>>()?;
+        let function_type =
+            self.declare_function_type(vec![ValType::I32, ValType::I32], vec![ValType::I32]);
+        let id = self.declare_function(function_type);
+        let runtime = self.runtime();
+
+        let (lhs, rhs, index, equal) = (0, 1, 2, 3);
+        let size = self.size_of(element) as i32;
+        let mut body = vec![
+            LocalGet(lhs),
+            load(ValType::I32, 0),
+            LocalGet(rhs),
+            load(ValType::I32, 0),
+            I32Eq,
+            LocalSet(equal),
+            Block(BlockType::Empty),
+            Loop(BlockType::Empty),
+            LocalGet(equal),
+            I32Eqz,
+            LocalGet(index),
+            LocalGet(lhs),
+            load(ValType::I32, 0),
+            I32GeU,
+            I32Or,
+            BrIf(1),
+        ];
+        // the elements are behind the length.
+        let mut offset = 4;
+        for (leaf, comparison) in leaves.into_iter().zip(comparisons) {
+            let value_type = self.convert_type(leaf)[0];
+            for array in [lhs, rhs] {
+                body.extend(vec![
+                    LocalGet(array),
+                    LocalGet(index),
+                    I32Const(size),
+                    I32Mul,
+                    I32Add,
+                    load(value_type, offset),
+                ]);
+                if self.is_counted(leaf) {
+                    body.push(Call(runtime.retain));
+                }
+            }
+            body.extend(vec![comparison, LocalGet(equal), I32And, LocalSet(equal)]);
+            offset += value_size(value_type);
+        }
+        body.extend(vec![
+            LocalGet(index),
+            I32Const(1),
+            I32Add,
+            LocalSet(index),
+            Br(0),
+            End,
+            End,
+            LocalGet(lhs),
+            Call(runtime.release),
+            LocalGet(rhs),
+            Call(runtime.release),
+            LocalGet(equal),
+            End,
+        ]);
+        self.implement_function(id, vec![ValType::I32; 2], body);
+        self.name_function(id, format!("rano_eq__{}", self.type_name(ty)));
+        Some(id)
+    }
+}
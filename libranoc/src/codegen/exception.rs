@@ -0,0 +1,61 @@
+use wasm_encoder::{encoders, ExportSection, Section};
+
+/// The tag panics are thrown with, the only one a module defines. It is
+/// exported as `rano_panic_tag`, so the host can tell them from the other
+/// exceptions, and its values are the message and the location, like the
+/// arguments of `rano_panic`.
+pub(super) const PANIC_TAG: u32 = 0;
+
+/// The id of the tag section, between the memory and the global ones.
+pub(super) const TAG_SECTION: u8 = 13;
+
+/// `throw` of the exception-handling proposal, by its tag, which
+/// wasm-encoder has no variant for yet, so a `nop` stands for it in the body
+/// it is in until `finish` writes it by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throw(pub u32);
+
+impl Throw {
+    pub fn encode(self, code: &mut Vec<u8>) {
+        code.push(0x08);
+        code.extend(encoders::u32(self.0));
+    }
+}
+
+/// The payload of the tag section defining the panic tag, of the function
+/// type `type_id` taking its values.
+pub fn tag_section(type_id: u32) -> Vec<u8> {
+    let mut data: Vec<u8> = encoders::u32(1).collect();
+    // an exception, the only attribute there is.
+    data.push(0x00);
+    data.extend(encoders::u32(type_id));
+    data
+}
+
+/// The payload of `exports` with the panic tag exported too, which
+/// wasm-encoder has no kind of export for yet.
+pub fn export_panic_tag(exports: &ExportSection) -> Vec<u8> {
+    let mut section = Vec::new();
+    exports.encode(&mut section);
+    // the exports are behind the size of the section and their count.
+    let (_, size) = read_u32(&section);
+    let (count, length) = read_u32(&section[size..]);
+    let mut data: Vec<u8> = encoders::u32(count + 1).collect();
+    data.extend(&section[size + length..]);
+    data.extend(encoders::str("rano_panic_tag"));
+    data.push(0x04);
+    data.extend(encoders::u32(PANIC_TAG));
+    data
+}
+
+/// The LEB128 number `bytes` start with, and how many bytes it takes.
+fn read_u32(bytes: &[u8]) -> (u32, usize) {
+    let mut value = 0;
+    for (at, byte) in bytes.iter().enumerate() {
+        value |= ((byte & 0x7f) as u32) << (7 * at);
+        if byte & 0x80 == 0 {
+            return (value, at + 1);
+        }
+    }
+    (value, bytes.len())
+}
@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+
+use wasm_encoder::{BlockType, Instruction, ValType};
+
+use crate::{
+    core::{arena::Idx, ast::Name, IntrinsicKind, NodeId, TypeId},
+    semantic::Definition,
+    syntax::Span,
+    thir::{Block, Expression, ExpressionKind, Function, Statement},
+    typeck::captures::CaptureMode,
+};
+
+use super::{
+    locals::Place,
+    results::{load, store, value_size},
+    runtime::STACK_BASE,
+    Context, Error,
+};
+
+/// The bytes the shadow stack takes, which it can't grow past.
+pub(super) const STACK_SIZE: i32 = 64 * 1024;
+/// The bytes of a page of the memory.
+pub(super) const PAGE_SIZE: u32 = 64 * 1024;
+/// The global holding the lowest address of the shadow stack used so far,
+/// the only one the module defines, and none are imported.
+pub(super) const STACK_POINTER: u32 = 0;
+
+impl<'a> Context<'a> {
+    /// The locals `function` takes the address of by `@address_of`, or
+    /// which a closure made in it captures by reference, leaving out the
+    /// functions and the closures declared in it, which have frames of
+    /// their own.
+    pub fn find_address_taken(&self, function: &Function) -> HashSet<Idx<Definition>> {
+        let mut locals = HashSet::new();
+        if let Some(body) = &function.body {
+            self.find_address_taken_in(body, &mut locals);
+        }
+        locals
+    }
+
+    /// The locals the closure of the body `body` takes the address of, like
+    /// a function does.
+    pub fn find_address_taken_by_closure(&self, body: &Expression) -> HashSet<Idx<Definition>> {
+        let mut locals = HashSet::new();
+        self.find_address_taken_by(body, &mut locals);
+        locals
+    }
+
+    fn find_address_taken_in(&self, block: &Block, locals: &mut HashSet<Idx<Definition>>) {
+        for statement in &block.statements {
+            match statement {
+                Statement::Item(_) => {}
+                Statement::Let(r#let) => {
+                    if let Some(value) = &r#let.value {
+                        self.find_address_taken_by(value, locals);
+                    }
+                }
+                Statement::Expression(expression) => self.find_address_taken_by(expression, locals),
+            }
+        }
+        if let Some(value) = &block.value {
+            self.find_address_taken_by(value, locals);
+        }
+    }
+
+    fn find_address_taken_by(
+        &self,
+        expression: &Expression,
+        locals: &mut HashSet<Idx<Definition>>,
+    ) {
+        if let ExpressionKind::Closure(closure) = &expression.kind {
+            locals.extend(
+                closure
+                    .captures
+                    .iter()
+                    .filter(|capture| capture.mode == CaptureMode::Reference)
+                    .map(|capture| capture.local),
+            );
+            return;
+        }
+        if let ExpressionKind::Call(call) = &expression.kind {
+            if let (ExpressionKind::Intrinsic(intrinsic), [argument]) =
+                (&call.callee.kind, call.arguments.as_slice())
+            {
+                if intrinsic.kind == IntrinsicKind::AddressOf {
+                    locals.extend(self.referred_definition(argument.id));
+                }
+            }
+        }
+        for block in expression.blocks() {
+            self.find_address_taken_in(block, locals);
+        }
+        for operand in expression.operands() {
+            self.find_address_taken_by(operand, locals);
+        }
+    }
+
+    /// Starts the frame of the function being walked on the shadow stack,
+    /// if it takes the address of any of `address_taken`, panicking with a
+    /// stack overflow at `span` if the stack has no room left for it.
+    pub fn enter_frame(&mut self, address_taken: HashSet<Idx<Definition>>, span: &Span) {
+        use Instruction::*;
+        if address_taken.is_empty() {
+            return;
+        }
+        self.use_stack();
+        let pointer = self.allocate_frame(address_taken);
+        self.instructions.push(GlobalGet(STACK_POINTER));
+        self.push_frame_size();
+        self.instructions.extend(vec![
+            I32Sub,
+            LocalTee(pointer),
+            I32Const(STACK_BASE),
+            load(ValType::I32, 0),
+            I32LtU,
+            If(BlockType::Empty),
+        ]);
+        let message = self.string_literal("stack overflow");
+        self.instructions.push(I32Const(message));
+        self.panic(span);
+        self.instructions
+            .extend(vec![End, LocalGet(pointer), GlobalSet(STACK_POINTER)]);
+    }
+
+    /// Gives the frame of the function being walked back to the shadow
+    /// stack, if it has one.
+    pub fn leave_frame(&mut self) {
+        use Instruction::*;
+        if let Some(pointer) = self.frame_pointer() {
+            self.instructions.push(LocalGet(pointer));
+            self.push_frame_size();
+            self.instructions
+                .extend(vec![I32Add, GlobalSet(STACK_POINTER)]);
+        }
+    }
+
+    /// Sets the size of the frame of the function being walked, once its
+    /// whole body is, which only then is known.
+    pub fn finish_frame(&mut self) {
+        if let Some((size, positions)) = self.frame_size() {
+            for at in positions {
+                self.instructions[at] = Instruction::I32Const(size as i32);
+            }
+        }
+    }
+
+    fn push_frame_size(&mut self) {
+        self.note_frame_size(self.instructions.len());
+        self.instructions.push(Instruction::I32Const(0));
+    }
+
+    /// The place of the local `definition` of the type `ty` in the frame, if
+    /// its address is taken, which places it there when it is bound.
+    pub fn place_local(&mut self, definition: Idx<Definition>, ty: TypeId) -> Option<Place> {
+        let size = self.size_of(ty);
+        let offset = self.place_in_frame(definition, size)?;
+        Some(self.in_frame(offset))
+    }
+
+    /// The place `offset` bytes into the frame of the function being
+    /// walked.
+    pub fn in_frame(&self, offset: u32) -> Place {
+        Place {
+            pointer: self.frame_pointer().unwrap_or_default(),
+            offset,
+        }
+    }
+
+    /// The place of the local the name expression `id` refers to, if it
+    /// lives in the frame or is captured by the closure being walked.
+    pub fn framed_local(&self, id: NodeId) -> Option<Place> {
+        self.local_place(self.referred_definition(id)?)
+    }
+
+    /// The places of the leaves of a value of the type `ty` at `place`,
+    /// with their value types.
+    fn framed_leaves(&self, place: Place, ty: TypeId) -> Vec<(Place, ValType)> {
+        let mut at = place.offset;
+        self.convert_type(ty)
+            .into_iter()
+            .map(|ty| {
+                let leaf = (
+                    Place {
+                        offset: at,
+                        ..place
+                    },
+                    ty,
+                );
+                at += value_size(ty);
+                leaf
+            })
+            .collect()
+    }
+
+    /// Pushes the value of the type `ty` at `place`, which is a reference
+    /// of its own, like one read from a slot.
+    pub fn load_framed(&mut self, place: Place, ty: TypeId) {
+        let leaves = self
+            .framed_leaves(place, ty)
+            .into_iter()
+            .zip(self.leaves(ty));
+        for ((at, value_type), leaf) in leaves {
+            self.instructions.push(Instruction::LocalGet(at.pointer));
+            self.push_load(value_type, at.offset);
+            self.retain(leaf);
+        }
+    }
+
+    /// Takes the value of the type `ty` off the stack into `place`, from its
+    /// last leaf.
+    pub fn store_framed(&mut self, place: Place, ty: TypeId) {
+        for (at, ty) in self.framed_leaves(place, ty).into_iter().rev() {
+            let value = self.scratch_locals(ty, 1)[0];
+            self.instructions.extend(vec![
+                Instruction::LocalSet(value),
+                Instruction::LocalGet(at.pointer),
+                Instruction::LocalGet(value),
+            ]);
+            self.push_store(ty, at.offset);
+        }
+    }
+
+    /// The places of the leaves of a value of the type `ty` at `place`
+    /// which hold references.
+    pub fn framed_references(&self, place: Place, ty: TypeId) -> Vec<Place> {
+        self.framed_leaves(place, ty)
+            .into_iter()
+            .zip(self.leaves(ty))
+            .filter(|(_, leaf)| self.is_counted(*leaf))
+            .map(|((at, _), _)| at)
+            .collect()
+    }
+
+    /// Clears the reference at `place`, so releasing it does nothing.
+    pub fn clear_framed(&mut self, place: Place) {
+        self.instructions.extend(vec![
+            Instruction::LocalGet(place.pointer),
+            Instruction::I32Const(0),
+            store(ValType::I32, place.offset),
+        ]);
+    }
+
+    /// Releases the reference at `place`.
+    pub fn release_framed(&mut self, place: Place) {
+        let release = self.runtime().release;
+        self.instructions.extend(vec![
+            Instruction::LocalGet(place.pointer),
+            load(ValType::I32, place.offset),
+            Instruction::Call(release),
+        ]);
+    }
+
+    /// Pushes the address of `place`.
+    pub fn push_address(&mut self, place: Place) {
+        self.instructions.extend(vec![
+            Instruction::LocalGet(place.pointer),
+            Instruction::I32Const(place.offset as i32),
+            Instruction::I32Add,
+        ]);
+    }
+
+    /// Walks `@address_of(local)`, the address of the place of `local` in
+    /// the frame, or in the environment of the closure capturing it. Only a
+    /// `let` local has one.
+    pub fn walk_address_of(&mut self, arguments: &[Expression]) -> Result<(), Error> {
+        let argument = match arguments {
+            [argument] => argument,
+            _ => return Ok(()),
+        };
+        let place = match &argument.kind {
+            ExpressionKind::Name(Name::Ident(_)) => self.framed_local(argument.id),
+            _ => None,
+        };
+        match place {
+            Some(place) => {
+                self.push_address(place);
+                Ok(())
+            }
+            None => Err(Error::unimplemented(argument)),
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use wasm_encoder::{BlockType, Instruction, ValType};
+
+use crate::core::{ast::AttributeValue, Error};
+
+/// The number of instructions a function has at most to be inlined without
+/// being given `#[inline]`.
+const INLINE_SIZE: usize = 12;
+/// The number of instructions inlining may add to a function, however many
+/// of the functions it calls are given `#[inline]`.
+const INLINE_BUDGET: usize = 256;
+
+/// Whether the calls of a function are inlined, which is set for it by
+/// `#[inline]` or `#[inline(never)]` in front of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Inline {
+    /// Inlined if it is small.
+    #[default]
+    Auto,
+    /// Inlined whatever its size, while the function calling it has budget
+    /// left.
+    Hint,
+    /// Never inlined.
+    Never,
+}
+
+impl Inline {
+    /// Reads `inline` or `inline(never)`.
+    pub fn from_attribute(value: &AttributeValue) -> Result<Inline, Error> {
+        const EXPECTED: &str = "`inline` or `inline(never)`";
+        match value {
+            AttributeValue::Word(_) => Ok(Inline::Hint),
+            AttributeValue::List { items, .. } if items.len() == 1 => match &items[0] {
+                AttributeValue::Word(name) if name.content == "never" => Ok(Inline::Never),
+                item => Err(Error::invalid_attribute(item, EXPECTED)),
+            },
+            _ => Err(Error::invalid_attribute(value, EXPECTED)),
+        }
+    }
+}
+
+/// A function the calls of which can be inlined, with its body as it was
+/// emitted, so what is inlined into it isn't inlined again.
+#[derive(Debug, Clone)]
+pub struct Callee<'a> {
+    parameters: Vec<ValType>,
+    result: BlockType,
+    locals: Vec<ValType>,
+    body: Vec<Instruction<'a>>,
+}
+
+impl<'a> Callee<'a> {
+    /// The function taking `parameters` and giving `results`, with the
+    /// locals `locals` and the body `body`, if `inline` lets it be inlined.
+    /// A function giving several values is left alone, as a block giving
+    /// them would need a type of its own.
+    pub fn new(
+        inline: Inline,
+        parameters: &[ValType],
+        results: &[ValType],
+        locals: &[ValType],
+        body: &[Instruction<'a>],
+    ) -> Option<Self> {
+        let result = match results {
+            [] => BlockType::Empty,
+            [result] => BlockType::Result(*result),
+            _ => return None,
+        };
+        // behind the body is the `end` of the function.
+        let inlined = match inline {
+            Inline::Auto => body.len() <= INLINE_SIZE + 1,
+            Inline::Hint => true,
+            Inline::Never => false,
+        };
+        if !inlined
+            || parameters
+                .iter()
+                .chain(locals)
+                .any(|ty| zero(*ty).is_none())
+        {
+            return None;
+        }
+        Some(Callee {
+            parameters: parameters.to_vec(),
+            result,
+            locals: locals.to_vec(),
+            body: body.to_vec(),
+        })
+    }
+
+    /// The number of instructions inlining a call adds: the body in a
+    /// block, setting the parameters and zeroing the locals, without the
+    /// call.
+    fn size(&self) -> usize {
+        self.body.len() + self.parameters.len() + 2 * self.locals.len()
+    }
+
+    /// Pushes the body to `code`, with the arguments on the stack and its
+    /// locals from the slot `base` on. A `return` leaves the block instead.
+    fn inline(&self, base: u32, code: &mut Vec<Instruction<'a>>) {
+        let parameters = self.parameters.len() as u32;
+        for slot in (0..parameters).rev() {
+            code.push(Instruction::LocalSet(base + slot));
+        }
+        // the locals start at zero in every call, as they would in the
+        // function.
+        for (offset, ty) in self.locals.iter().enumerate() {
+            code.extend(zero(*ty));
+            code.push(Instruction::LocalSet(base + parameters + offset as u32));
+        }
+        code.push(Instruction::Block(self.result));
+        let mut depth: u32 = 0;
+        for instruction in &self.body {
+            code.push(match *instruction {
+                Instruction::LocalGet(slot) => Instruction::LocalGet(base + slot),
+                Instruction::LocalSet(slot) => Instruction::LocalSet(base + slot),
+                Instruction::LocalTee(slot) => Instruction::LocalTee(base + slot),
+                Instruction::Block(_) | Instruction::Loop(_) | Instruction::If(_) => {
+                    depth += 1;
+                    *instruction
+                }
+                // the last one ends the block.
+                Instruction::End => {
+                    depth = depth.saturating_sub(1);
+                    Instruction::End
+                }
+                Instruction::Return => Instruction::Br(depth),
+                instruction => instruction,
+            });
+        }
+    }
+}
+
+/// Inlines the calls of `callees` in `body`, the body of the function
+/// `caller`, which takes `parameters` values and has `locals` besides them,
+/// while it has budget left. A call `keep` tells the position of is left
+/// alone, and so are the calls of itself. Gives where each instruction of
+/// the body is now, and the end of the body behind the last one.
+pub fn inline_calls<'a>(
+    caller: u32,
+    parameters: usize,
+    locals: &mut Vec<ValType>,
+    body: &mut Vec<Instruction<'a>>,
+    callees: &HashMap<u32, Callee<'a>>,
+    keep: impl Fn(usize) -> bool,
+) -> Vec<usize> {
+    let mut budget = INLINE_BUDGET;
+    // the calls of the same function share its locals, which it starts over
+    // each time.
+    let mut bases = HashMap::new();
+    let mut code = Vec::with_capacity(body.len());
+    let mut positions = Vec::with_capacity(body.len() + 1);
+    for (at, instruction) in std::mem::take(body).into_iter().enumerate() {
+        positions.push(code.len());
+        let callee = match instruction {
+            Instruction::Call(id) if id != caller && !keep(at) => callees
+                .get(&id)
+                .filter(|callee| callee.size() <= budget)
+                .map(|callee| (id, callee)),
+            _ => None,
+        };
+        match callee {
+            Some((id, callee)) => {
+                budget -= callee.size();
+                let base = *bases.entry(id).or_insert_with(|| {
+                    let base = (parameters + locals.len()) as u32;
+                    locals.extend(&callee.parameters);
+                    locals.extend(&callee.locals);
+                    base
+                });
+                callee.inline(base, &mut code);
+            }
+            None => code.push(instruction),
+        }
+    }
+    positions.push(code.len());
+    *body = code;
+    positions
+}
+
+/// The instruction giving the zero of `ty`, if it has one.
+fn zero(ty: ValType) -> Option<Instruction<'static>> {
+    match ty {
+        ValType::I32 => Some(Instruction::I32Const(0)),
+        ValType::I64 => Some(Instruction::I64Const(0)),
+        ValType::F32 => Some(Instruction::F32Const(0.0)),
+        ValType::F64 => Some(Instruction::F64Const(0.0)),
+        ValType::V128 => Some(Instruction::V128Const(0)),
+        _ => None,
+    }
+}
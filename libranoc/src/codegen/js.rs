@@ -0,0 +1,438 @@
+//! The JavaScript glue of a module, which wraps the functions it exports so
+//! they take and give the values of JavaScript, encoding the strings and
+//! arrays they are passed into the memory and decoding the ones they give
+//! back, with the `.d.ts` declaring them.
+//!
+//! A value is passed as the runtime lays it out: a string or an array is
+//! allocated by `rano_malloc` and owned by the function it is given to, and
+//! one given back is read and released. An `i64` is a `bigint`, a boolean
+//! is given back as one, a tuple is an array of its elements and the unit
+//! is `undefined`. The functions taking or giving values the glue can't
+//! pass, like optionals, the types declared in the program, or a tuple the
+//! function gives back through the memory, aren't wrapped, and are left in
+//! `instance.exports`.
+
+use std::fmt::Write;
+
+use crate::{
+    core::{
+        ast::{Name, Pattern},
+        Type, TypeId, Types,
+    },
+    thir::{Function, Item, Statement},
+};
+
+use super::{results::MAX_RESULTS, *};
+
+/// The helpers every glue defines in `instantiate`, which the wrappers call.
+const HELPERS: &str = r#"  const raw = instance.exports;
+  // the memory grows while the values are written, which detaches the
+  // buffers taken before.
+  const view = () => new DataView(raw.memory.buffer);
+  // the runtime is left out of a module which doesn't use it, and with it
+  // the values to release.
+  const release = (pointer) => {
+    if (raw.rano_release) raw.rano_release(pointer);
+  };
+  const allocate = (size) => {
+    const pointer = raw.rano_malloc(size);
+    if (pointer === 0) throw new RangeError("the memory can't grow");
+    return pointer;
+  };
+  const writeString = (value) => {
+    const bytes = encoder.encode(value);
+    const pointer = allocate(4 + bytes.length);
+    view().setUint32(pointer, bytes.length, true);
+    new Uint8Array(raw.memory.buffer, pointer + 4, bytes.length).set(bytes);
+    return pointer;
+  };
+  const readString = (pointer) => {
+    const length = view().getUint32(pointer, true);
+    return decoder.decode(new Uint8Array(raw.memory.buffer, pointer + 4, length));
+  };
+  const writeArray = (values, size, write) => {
+    const pointer = allocate(4 + values.length * size);
+    view().setUint32(pointer, values.length, true);
+    values.forEach((value, index) => write(pointer + 4 + index * size, value));
+    return pointer;
+  };
+  const readArray = (pointer, size, read) => {
+    const length = view().getUint32(pointer, true);
+    return Array.from({ length }, (_, index) => read(pointer + 4 + index * size));
+  };
+"#;
+
+/// The glue of a module, as the JavaScript module and its declarations.
+#[derive(Debug, Clone, Default)]
+pub struct Bindings {
+    pub js: String,
+    pub dts: String,
+}
+
+/// The glue of the functions `typed` exports, for the module it is compiled
+/// to with `options`.
+pub(super) fn bindings(typed: &Typed, options: &CompileOptions) -> Bindings {
+    let glue = Glue {
+        types: &typed.types,
+        options,
+    };
+    let mut wrappers = String::new();
+    let mut declarations = String::new();
+    let mut skipped = String::new();
+    for statement in &typed.module.statements {
+        let function = match statement {
+            Statement::Item(Item::Function(function))
+                if function.is_pub
+                    && !function.is_extern
+                    && function.generic_parameters.is_empty() =>
+            {
+                function
+            }
+            _ => continue,
+        };
+        match glue.wrapper(function) {
+            Ok((wrapper, declaration)) => {
+                wrappers.push_str(&wrapper);
+                declarations.push_str(&declaration);
+            }
+            Err(ty) => writeln!(
+                skipped,
+                "// `{}` isn't wrapped, as the glue can't pass a `{}`.",
+                function.name.content,
+                typed.types.display(ty)
+            )
+            .unwrap(),
+        }
+    }
+
+    let js = format!(
+        "// The glue of a module compiled by ranoc, passing the strings and\n\
+         // arrays its exported functions take and give through its memory.\n\
+         {}\n\
+         const encoder = new TextEncoder();\n\
+         const decoder = new TextDecoder();\n\n\
+         export async function instantiate(source, imports = {{}}) {{\n  \
+         const instance =\n    \
+         source instanceof WebAssembly.Module\n      \
+         ? await WebAssembly.instantiate(source, imports)\n      \
+         : (await WebAssembly.instantiate(source, imports)).instance;\n\
+         {}  return {{\n    instance,\n{}  }};\n}}\n",
+        skipped, HELPERS, wrappers
+    );
+    let dts = format!(
+        "export interface Exports {{\n  \
+         readonly instance: WebAssembly.Instance;\n\
+         {}}}\n\n\
+         export function instantiate(\n  \
+         source: BufferSource | WebAssembly.Module,\n  \
+         imports?: WebAssembly.Imports,\n\
+         ): Promise<Exports>;\n",
+        declarations
+    );
+    Bindings { js, dts }
+}
+
+/// Writes the code passing the values of a module.
+struct Glue<'t> {
+    types: &'t Types,
+    options: &'t CompileOptions,
+}
+
+/// The type the glue can't pass a value of, which keeps the function from
+/// being wrapped.
+type Unsupported = TypeId;
+
+impl<'t> Glue<'t> {
+    /// The wrapper of `function` in the object `instantiate` gives, with its
+    /// declaration in `Exports`.
+    fn wrapper(&self, function: &Function) -> Result<(String, String), Unsupported> {
+        let mut names = Vec::new();
+        let mut declared = Vec::new();
+        let mut arguments = Vec::new();
+        for (index, (pattern, ty)) in function.parameters.iter().enumerate() {
+            // the names of the source are only declared, so they can't be
+            // the ones of the glue.
+            let name = format!("arg{}", index);
+            let declared_name = match pattern {
+                Pattern::Slot(Name::Ident(name)) if !is_reserved(&name.content) => {
+                    name.content.clone()
+                }
+                _ => name.clone(),
+            };
+            arguments.extend(self.lower(*ty, &name)?);
+            declared.push(format!("{}: {}", declared_name, self.ts_type(*ty)?));
+            names.push(name);
+        }
+        let name = &function.name.content;
+        let call = format!("raw.{}({})", name, arguments.join(", "));
+        let (body, result) = match function.return_type {
+            TypeId::UNIT | TypeId::NEVER => (format!("      {};\n", call), "void".to_owned()),
+            ty => {
+                let leaves = leaves(self.types, ty);
+                if leaves.len() > 1
+                    && (!self.options.target.multi_value || leaves.len() > MAX_RESULTS)
+                {
+                    return Err(ty);
+                }
+                let results: Vec<_> = match leaves.len() {
+                    1 => vec!["result".to_owned()],
+                    count => (0..count)
+                        .map(|index| format!("result[{}]", index))
+                        .collect(),
+                };
+                let mut releases = Vec::new();
+                let value = self.lift(ty, &mut results.into_iter(), &mut releases)?;
+                let mut body = format!("      const result = {};\n", call);
+                if releases.is_empty() {
+                    writeln!(body, "      return {};", value).unwrap();
+                } else {
+                    writeln!(body, "      const value = {};", value).unwrap();
+                    for pointer in releases {
+                        writeln!(body, "      release({});", pointer).unwrap();
+                    }
+                    body.push_str("      return value;\n");
+                }
+                (body, self.ts_type(ty)?)
+            }
+        };
+        let wrapper = format!("    {}({}) {{\n{}    }},\n", name, names.join(", "), body);
+        let declaration = format!("  {}({}): {};\n", name, declared.join(", "), result);
+        Ok((wrapper, declaration))
+    }
+
+    /// The arguments `value` of the type `ty` is passed as, one for each of
+    /// its leaves.
+    fn lower(&self, ty: TypeId, value: &str) -> Result<Vec<String>, Unsupported> {
+        Ok(match self.types.get(ty) {
+            Type::I32 | Type::I64 | Type::F32 | Type::F64 => vec![value.to_owned()],
+            Type::Bool => vec![format!("{} ? 1 : 0", value)],
+            Type::Tuple(elements) if elements.is_empty() => vec!["0".to_owned()],
+            Type::Tuple(elements) => {
+                let mut arguments = Vec::new();
+                for (index, element) in elements.iter().enumerate() {
+                    arguments.extend(self.lower(*element, &format!("{}[{}]", value, index))?);
+                }
+                arguments
+            }
+            Type::String => vec![format!("writeString({})", value)],
+            Type::Array(element) => vec![format!(
+                "writeArray({}, {}, {})",
+                value,
+                size_of(self.types, *element),
+                self.writer(*element)?
+            )],
+            _ => return Err(ty),
+        })
+    }
+
+    /// The value of the type `ty` given back as the `results`, adding the
+    /// ones to release once it is read to `releases`.
+    fn lift(
+        &self,
+        ty: TypeId,
+        results: &mut impl Iterator<Item = String>,
+        releases: &mut Vec<String>,
+    ) -> Result<String, Unsupported> {
+        if let Type::Tuple(elements) = self.types.get(ty) {
+            if !elements.is_empty() {
+                let mut values = Vec::new();
+                for element in elements {
+                    values.push(self.lift(*element, results, releases)?);
+                }
+                return Ok(format!("[{}]", values.join(", ")));
+            }
+        }
+        let result = results.next().expect("a result for each leaf");
+        Ok(match self.types.get(ty) {
+            Type::I32 | Type::I64 | Type::F32 | Type::F64 => result,
+            Type::Bool => format!("{} !== 0", result),
+            Type::Tuple(_) => "undefined".to_owned(),
+            Type::String => {
+                releases.push(result.clone());
+                format!("readString({})", result)
+            }
+            Type::Array(element) => {
+                releases.push(result.clone());
+                format!(
+                    "readArray({}, {}, {})",
+                    result,
+                    size_of(self.types, *element),
+                    self.reader(*element)?
+                )
+            }
+            _ => return Err(ty),
+        })
+    }
+
+    /// The function writing an element of the type `ty` of an array to an
+    /// address.
+    fn writer(&self, ty: TypeId) -> Result<String, Unsupported> {
+        let mut statements = Vec::new();
+        self.store(ty, "value", 0, &mut statements)?;
+        Ok(format!(
+            "(address, value) => {{ {} }}",
+            statements.join(" ")
+        ))
+    }
+
+    /// The statements storing `value` of the type `ty` at `offset` from the
+    /// address, each leaf behind the one before.
+    fn store(
+        &self,
+        ty: TypeId,
+        value: &str,
+        offset: u32,
+        statements: &mut Vec<String>,
+    ) -> Result<(), Unsupported> {
+        let set = |setter: &str, value: &str| {
+            format!("view().{}(address + {}, {}, true);", setter, offset, value)
+        };
+        match self.types.get(ty) {
+            Type::I32 => statements.push(set("setInt32", value)),
+            Type::I64 => statements.push(set("setBigInt64", value)),
+            Type::F32 => statements.push(set("setFloat32", value)),
+            Type::F64 => statements.push(set("setFloat64", value)),
+            Type::Bool => statements.push(set("setInt32", &format!("{} ? 1 : 0", value))),
+            Type::Tuple(elements) if elements.is_empty() => statements.push(set("setInt32", "0")),
+            Type::Tuple(elements) => {
+                let mut offset = offset;
+                for (index, element) in elements.iter().enumerate() {
+                    self.store(
+                        *element,
+                        &format!("{}[{}]", value, index),
+                        offset,
+                        statements,
+                    )?;
+                    offset += size_of(self.types, *element);
+                }
+            }
+            // the value is written before the view is taken, as writing it
+            // may grow the memory.
+            Type::String | Type::Array(_) => {
+                let pointer = self.lower(ty, value)?.remove(0);
+                let name = format!("pointer{}", offset);
+                statements.push(format!("const {} = {};", name, pointer));
+                statements.push(set("setUint32", &name));
+            }
+            _ => return Err(ty),
+        }
+        Ok(())
+    }
+
+    /// The function reading an element of the type `ty` of an array from an
+    /// address.
+    fn reader(&self, ty: TypeId) -> Result<String, Unsupported> {
+        Ok(format!("(address) => {}", self.load(ty, 0)?))
+    }
+
+    /// The expression loading a value of the type `ty` at `offset` from the
+    /// address.
+    fn load(&self, ty: TypeId, offset: u32) -> Result<String, Unsupported> {
+        let get = |getter: &str| format!("view().{}(address + {}, true)", getter, offset);
+        Ok(match self.types.get(ty) {
+            Type::I32 => get("getInt32"),
+            Type::I64 => get("getBigInt64"),
+            Type::F32 => get("getFloat32"),
+            Type::F64 => get("getFloat64"),
+            Type::Bool => format!("{} !== 0", get("getInt32")),
+            Type::Tuple(elements) if elements.is_empty() => "undefined".to_owned(),
+            Type::Tuple(elements) => {
+                let mut offset = offset;
+                let mut values = Vec::new();
+                for element in elements {
+                    values.push(self.load(*element, offset)?);
+                    offset += size_of(self.types, *element);
+                }
+                format!("[{}]", values.join(", "))
+            }
+            Type::String => format!("readString({})", get("getUint32")),
+            Type::Array(element) => format!(
+                "readArray({}, {}, {})",
+                get("getUint32"),
+                size_of(self.types, *element),
+                self.reader(*element)?
+            ),
+            _ => return Err(ty),
+        })
+    }
+
+    /// The type of TypeScript a value of `ty` is passed as.
+    fn ts_type(&self, ty: TypeId) -> Result<String, Unsupported> {
+        Ok(match self.types.get(ty) {
+            Type::I32 | Type::F32 | Type::F64 => "number".to_owned(),
+            Type::I64 => "bigint".to_owned(),
+            Type::Bool => "boolean".to_owned(),
+            Type::Tuple(elements) if elements.is_empty() => "undefined".to_owned(),
+            Type::Tuple(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| self.ts_type(*element))
+                    .collect::<Result<Vec<_>, _>>()?;
+                format!("[{}]", elements.join(", "))
+            }
+            Type::String => "string".to_owned(),
+            Type::Array(element) => {
+                format!("Array<{}>", self.ts_type(*element)?)
+            }
+            _ => return Err(ty),
+        })
+    }
+}
+
+/// The types of the values a value of `ty` is passed as, like
+/// `Context::leaves`.
+fn leaves(types: &Types, ty: TypeId) -> Vec<TypeId> {
+    match types.get(ty) {
+        Type::Tuple(elements) if !elements.is_empty() => elements
+            .iter()
+            .flat_map(|element| leaves(types, *element))
+            .collect(),
+        _ => vec![ty],
+    }
+}
+
+/// The bytes a value of `ty` takes in memory, like `Context::size_of`.
+fn size_of(types: &Types, ty: TypeId) -> u32 {
+    leaves(types, ty)
+        .into_iter()
+        .map(|ty| match ty {
+            TypeId::I64 | TypeId::F64 => 8,
+            _ => 4,
+        })
+        .sum()
+}
+
+/// Whether `name` can't name a parameter in JavaScript.
+fn is_reserved(name: &str) -> bool {
+    matches!(
+        name,
+        "arguments"
+            | "await"
+            | "case"
+            | "catch"
+            | "class"
+            | "const"
+            | "debugger"
+            | "default"
+            | "delete"
+            | "do"
+            | "eval"
+            | "export"
+            | "extends"
+            | "finally"
+            | "function"
+            | "import"
+            | "instanceof"
+            | "new"
+            | "null"
+            | "switch"
+            | "this"
+            | "throw"
+            | "try"
+            | "typeof"
+            | "var"
+            | "void"
+            | "with"
+            | "yield"
+    )
+}
@@ -0,0 +1,110 @@
+use crate::{
+    core::ast::{AttributeValue, Module},
+    syntax::Token,
+};
+
+use super::*;
+
+/// Compiles modules on their own and links them into one WASM module, in
+/// which a function one of them imports by `#[link("name", "function")]`
+/// calls the function the module compiled as `name` exports by that name.
+/// They share one runtime, and their data is placed one behind the other,
+/// so the code of each is linked as it was compiled.
+pub struct Linker<'a> {
+    options: CompileOptions,
+    /// The module the others are linked into, which only has the runtime.
+    context: Context<'a>,
+    objects: Vec<(String, Context<'a>)>,
+    placement: Placement,
+}
+
+impl<'a> Linker<'a> {
+    pub fn new(options: &CompileOptions) -> Self {
+        let context = match walk_module(Module { nodes: Vec::new() }, options, Placement::default())
+        {
+            Ok(context) => context,
+            Err(_) => unreachable!("an empty module is always compiled"),
+        };
+        let placement = context.placement_behind();
+        Linker {
+            options: options.clone(),
+            context,
+            objects: Vec::new(),
+            placement,
+        }
+    }
+
+    /// Compiles `module` on its own as `name`, with the errors checking it
+    /// finds. The ones walking it finds are reported by `link`.
+    pub fn compile(&mut self, name: &str, module: Module) -> Vec<Error> {
+        match walk_module(module, &self.options, self.placement) {
+            Ok(context) => {
+                self.placement = context.placement_behind();
+                self.objects.push((name.to_owned(), context));
+                Vec::new()
+            }
+            Err(errors) => errors,
+        }
+    }
+
+    /// Links the modules compiled so far into one, in the order they were
+    /// compiled.
+    pub fn link(mut self) -> (Artifacts, Vec<Error>) {
+        self.context.link(self.objects);
+        finish_artifacts(self.context, &self.options)
+    }
+}
+
+/// Reads `link(module = "env", name = "log")`, where `name` defaults to the
+/// name of the function.
+pub(crate) fn link_of(value: &AttributeValue, function: &Token) -> Result<(String, String), Error> {
+    const EXPECTED: &str = "`link(module = \"...\", name = \"...\", pass = \"...\")`";
+    if !matches!(value, AttributeValue::List { .. }) {
+        return Err(Error::invalid_attribute(value, EXPECTED));
+    }
+    for item in value.items() {
+        if !matches!(item, AttributeValue::KeyValue { key, .. } if ["module", "name", "pass"].contains(&key.content.as_str()))
+        {
+            return Err(Error::invalid_attribute(item, EXPECTED));
+        }
+    }
+    let string = |key: &str| match value.value_of(key) {
+        Some(value) => value
+            .as_str()
+            .map(|s| Some(s.to_owned()))
+            .ok_or_else(|| Error::invalid_attribute(value, "a string")),
+        None => Ok(None),
+    };
+    let module = string("module")?.ok_or_else(|| Error::invalid_attribute(value, EXPECTED))?;
+    let field = string("name")?.unwrap_or_else(|| function.content.clone());
+    Ok((module, field))
+}
+
+/// How an `extern fn` takes its strings and arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Pass {
+    /// As the pointer to their length followed by their contents, like the
+    /// functions of a module take them.
+    Pointer,
+    /// As the address of their contents and their length, which is the
+    /// number of bytes of a string and of elements of an array. The caller
+    /// keeps them, so the host doesn't release them.
+    PtrLen,
+}
+
+/// Reads `pass = "pointer"` or `pass = "ptr_len"` from the `link` of an
+/// `extern fn`, which is `Pass::Pointer` without it. What the function gives
+/// back is passed like it always is.
+pub(crate) fn pass_of(value: &AttributeValue) -> Result<Pass, Error> {
+    match value.value_of("pass") {
+        Some(pass) => match pass.as_str() {
+            Some("pointer") => Ok(Pass::Pointer),
+            Some("ptr_len") => Ok(Pass::PtrLen),
+            _ => Err(Error::invalid_attribute(
+                pass,
+                "`\"pointer\"` or `\"ptr_len\"`",
+            )),
+        },
+        None => Ok(Pass::Pointer),
+    }
+}
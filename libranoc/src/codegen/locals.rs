@@ -0,0 +1,279 @@
+use std::collections::{HashMap, HashSet};
+
+use wasm_encoder::ValType;
+
+use crate::{core::arena::Idx, semantic::Definition};
+
+/// Where a reference a block owns is held, which it releases at its end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Owned {
+    Slot(u32),
+    /// The offset of a word of the frame.
+    Frame(u32),
+}
+
+/// Where in the memory a local lives, `offset` bytes past the address in
+/// the slot `pointer`: the frame, or the environment of a closure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Place {
+    pub pointer: u32,
+    pub offset: u32,
+}
+
+/// The frame of the function being walked on the shadow stack, which the
+/// locals whose address is taken live in, each at an offset of its own.
+#[derive(Debug, Default)]
+struct Frame {
+    /// The slot holding the address of the frame.
+    pointer: u32,
+    address_taken: HashSet<Idx<Definition>>,
+    offsets: HashMap<Idx<Definition>, u32>,
+    size: u32,
+    /// The positions of the `i32.const`s of its size in the body, which is
+    /// only known once the whole body is walked.
+    sizes: Vec<usize>,
+}
+
+/// The slots of the function being walked besides its parameters, which
+/// come first. Each `let` gets a slot of its own, or a run of them for a
+/// tuple, and the scratch locals the instructions keep values in are
+/// shared, but never with a `let`. The locals whose address is taken get a
+/// place in the frame instead.
+#[derive(Debug, Default)]
+pub struct Locals {
+    /// The first slot of each parameter.
+    parameters: Vec<u32>,
+    /// The index of the first slot which isn't a parameter.
+    first: u32,
+    types: Vec<ValType>,
+    bindings: HashMap<Idx<Definition>, u32>,
+    scratch: Vec<u32>,
+    /// The references each block being walked holds, innermost last,
+    /// which are released at its end.
+    scopes: Vec<Vec<Owned>>,
+    frame: Option<Frame>,
+    /// The places of the locals the closure being walked captures.
+    captured: HashMap<Idx<Definition>, Place>,
+}
+
+impl Locals {
+    /// The locals of a function whose parameters take `widths` slots each.
+    pub fn new(widths: impl IntoIterator<Item = u32>) -> Self {
+        let mut first = 0;
+        let parameters = widths
+            .into_iter()
+            .map(|width| {
+                first += width;
+                first - width
+            })
+            .collect();
+        Locals {
+            parameters,
+            first,
+            ..Locals::default()
+        }
+    }
+
+    /// The first slot of the parameter `index`.
+    pub fn parameter(&self, index: u32) -> Option<u32> {
+        self.parameters.get(index as usize).copied()
+    }
+
+    fn allocate(&mut self, ty: ValType) -> u32 {
+        self.types.push(ty);
+        self.first + (self.types.len() - 1) as u32
+    }
+
+    /// The first slot of the local `definition`, which is allocated the
+    /// first time it is asked for, followed by one for each of `types`
+    /// but the first.
+    pub fn bind(&mut self, definition: Idx<Definition>, types: &[ValType]) -> u32 {
+        if let Some(index) = self.bindings.get(&definition) {
+            return *index;
+        }
+        let index = self.first + self.types.len() as u32;
+        for ty in types {
+            self.allocate(*ty);
+        }
+        self.bindings.insert(definition, index);
+        index
+    }
+
+    /// Binds the local `definition` to the slots of the parameter `index`,
+    /// which a closure parameter is.
+    pub fn bind_parameter(&mut self, definition: Idx<Definition>, index: u32) {
+        if let Some(slot) = self.parameter(index) {
+            self.bindings.insert(definition, slot);
+        }
+    }
+
+    /// A slot of the type `ty` of its own, to keep a value in while other
+    /// instructions are walked.
+    pub fn temporary(&mut self, ty: ValType) -> u32 {
+        self.allocate(ty)
+    }
+
+    /// Has the closure being walked find the local `definition` it
+    /// captures at `place`.
+    pub fn capture(&mut self, definition: Idx<Definition>, place: Place) {
+        self.captured.insert(definition, place);
+    }
+
+    /// The place of the local `definition`, if it lives in the frame or is
+    /// captured by the closure being walked.
+    pub fn place_of(&self, definition: Idx<Definition>) -> Option<Place> {
+        if let Some(place) = self.captured.get(&definition) {
+            return Some(*place);
+        }
+        let frame = self.frame.as_ref()?;
+        Some(Place {
+            pointer: frame.pointer,
+            offset: *frame.offsets.get(&definition)?,
+        })
+    }
+
+    pub fn get(&self, definition: Idx<Definition>) -> Option<u32> {
+        self.bindings.get(&definition).copied()
+    }
+
+    /// The locals bound so far, with their slots.
+    pub fn bindings(&self) -> impl Iterator<Item = (Idx<Definition>, u32)> + '_ {
+        self.bindings
+            .iter()
+            .map(|(definition, slot)| (*definition, *slot))
+    }
+
+    /// `count` scratch slots of the type `ty`, which are the same ones every
+    /// time. Codegen never walks anything while using them, so they are
+    /// never used twice at once.
+    pub fn scratch(&mut self, ty: ValType, count: usize) -> Vec<u32> {
+        let mut indices: Vec<u32> = self
+            .scratch
+            .iter()
+            .copied()
+            .filter(|index| self.types[(index - self.first) as usize] == ty)
+            .take(count)
+            .collect();
+        while indices.len() < count {
+            let index = self.allocate(ty);
+            self.scratch.push(index);
+            indices.push(index);
+        }
+        indices
+    }
+
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    /// Has the innermost block release the reference `owned` at its end.
+    pub fn own(&mut self, owned: Owned) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if !scope.contains(&owned) {
+                scope.push(owned);
+            }
+        }
+    }
+
+    /// The references every block being walked owns, the innermost last.
+    pub fn owned(&self) -> Vec<Owned> {
+        self.scopes.iter().flatten().copied().collect()
+    }
+
+    /// The references the innermost block owns, which it is leaving.
+    pub fn leave_scope(&mut self) -> Vec<Owned> {
+        self.scopes.pop().unwrap_or_default()
+    }
+
+    /// Gives the function a frame for the locals `address_taken`, returning
+    /// the slot holding its address.
+    pub fn enter_frame(&mut self, address_taken: HashSet<Idx<Definition>>) -> u32 {
+        let pointer = self.allocate(ValType::I32);
+        self.frame = Some(Frame {
+            pointer,
+            address_taken,
+            ..Frame::default()
+        });
+        pointer
+    }
+
+    /// The slot holding the address of the frame, if the function has one.
+    pub fn frame_pointer(&self) -> Option<u32> {
+        self.frame.as_ref().map(|frame| frame.pointer)
+    }
+
+    /// The offset of the local `definition` in the frame, which is placed
+    /// there the first time it is asked for, taking `size` bytes, if its
+    /// address is taken.
+    pub fn place(&mut self, definition: Idx<Definition>, size: u32) -> Option<u32> {
+        let frame = self.frame.as_mut()?;
+        if !frame.address_taken.contains(&definition) {
+            return None;
+        }
+        let offset = match frame.offsets.get(&definition) {
+            Some(offset) => *offset,
+            None => {
+                // every local is aligned like the `i64`s it may hold.
+                let offset = (frame.size + 7) & !7;
+                frame.size = offset + size;
+                frame.offsets.insert(definition, offset);
+                offset
+            }
+        };
+        Some(offset)
+    }
+
+    /// Notes that the instruction at `at` is the `i32.const` of the size of
+    /// the frame.
+    pub fn note_frame_size(&mut self, at: usize) {
+        if let Some(frame) = &mut self.frame {
+            frame.sizes.push(at);
+        }
+    }
+
+    /// The size of the frame, which keeps the stack aligned to 8 bytes,
+    /// with the positions of its `i32.const`s.
+    pub fn frame_size(&self) -> Option<(u32, &[usize])> {
+        let frame = self.frame.as_ref()?;
+        Some(((frame.size + 7) & !7, &frame.sizes))
+    }
+
+    /// Names the slots `names` leaves without one, parameters included. The
+    /// slots of a tuple after its first are named after it with their
+    /// position in it, the scratch ones after their slot, and the others,
+    /// like the parameters taken apart by a pattern, after their slot too.
+    /// The one holding the address of the frame is `frame`.
+    pub fn name_the_rest(&self, names: &mut Vec<(u32, String)>) {
+        let named: HashMap<u32, String> = names.iter().cloned().collect();
+        let starts: Vec<u32> = self
+            .parameters
+            .iter()
+            .copied()
+            .chain(self.bindings.values().copied())
+            .collect();
+        // the local the slots since the last start are the leaves of.
+        let mut current: Option<(&str, u32)> = None;
+        for slot in 0..self.first + self.types.len() as u32 {
+            if let Some(name) = named.get(&slot) {
+                current = Some((name, slot));
+                continue;
+            }
+            let scratch = self.scratch.contains(&slot);
+            let frame = self.frame_pointer() == Some(slot);
+            if scratch || frame || starts.contains(&slot) {
+                current = None;
+            }
+            names.push(match current {
+                _ if scratch => (slot, format!("scratch{}", slot)),
+                _ if frame => (slot, "frame".to_string()),
+                Some((name, start)) => (slot, format!("{}.{}", name, slot - start)),
+                None => (slot, format!("local{}", slot)),
+            });
+        }
+    }
+
+    /// The types of the slots, as the function declares them.
+    pub fn into_types(self) -> Vec<ValType> {
+        self.types
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    ir::{read_ir, IrKind},
+    Error,
+};
+
+/// The custom section describing how a module was compiled and the
+/// functions it exports, for the tools looking into a compiled module.
+pub const META_SECTION: &str = "rano.meta";
+
+/// The content of the `rano.meta` custom section.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Meta {
+    /// The version of the compiler which compiled the module.
+    pub compiler: String,
+    /// The target and the proposals the module was compiled for, as
+    /// `TargetOptions::enables` names them.
+    pub features: Vec<String>,
+    /// The `pub fn`s of the module, in the order they were compiled.
+    pub exports: Vec<Signature>,
+}
+
+/// The signature of an exported function, with its types as the source
+/// spells them, like `i32` or `(string, Array<i64>)`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Signature {
+    /// The name the function is exported by.
+    pub name: String,
+    /// The names of the parameters, or `_` for one a pattern takes apart,
+    /// with their types.
+    pub parameters: Vec<(String, String)>,
+    pub return_type: String,
+}
+
+/// Decodes the content of the `rano.meta` custom section.
+pub fn read_meta(section: &[u8]) -> Result<Meta, Error> {
+    read_ir(IrKind::Meta, section)
+}
@@ -1,21 +1,232 @@
-use crate::core::ast::Module;
+use crate::{
+    core::ast::{
+        cfg::{configure, Cfg},
+        Module,
+    },
+    hir,
+    semantic::{check_initialized, resolve},
+    thir, typeck,
+};
 
+mod backend;
+mod c;
+mod cache;
+mod component;
 mod context;
+mod debug;
+mod equality;
+mod exception;
+mod frame;
+mod inline;
+mod js;
+mod link;
+mod locals;
+mod meta;
+mod native;
+mod opt_level;
+mod overflow;
+mod peephole;
+mod results;
+mod runtime;
+mod simd;
+mod stack_depth;
+mod tail_call;
+mod target;
+mod text;
+mod threads;
 mod walker;
+mod wasi;
 
 pub(super) use crate::core::Error;
+pub use backend::*;
+pub use c::C;
+pub use cache::Cache;
+pub use component::Component;
 pub(super) use context::*;
+pub(super) use debug::*;
+pub(super) use exception::*;
+pub use inline::Inline;
+pub(super) use inline::{inline_calls, Callee};
+pub use js::Bindings;
+pub use link::*;
+pub(super) use locals::*;
+pub use meta::*;
+pub use native::Cranelift;
+pub use opt_level::*;
+pub use overflow::*;
+pub(super) use peephole::simplify;
+pub use runtime::{read_panic, Panic};
+pub(super) use runtime::{Channels, Floats, Runtime};
+pub(super) use simd::*;
+pub(super) use tail_call::*;
+pub use target::*;
+pub use text::*;
+pub(super) use threads::*;
 pub(super) use walker::*;
+pub use wasi::*;
+
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    /// What the `#[cfg(...)]` attributes are evaluated against.
+    pub cfg: Cfg,
+    /// What the integer arithmetic does on overflow, unless a function says
+    /// otherwise.
+    pub overflow: Overflow,
+    /// Which optimizations to make.
+    pub opt_level: OptLevel,
+    pub target: TargetOptions,
+    /// Whether to emit DWARF mapping the instructions back to the source,
+    /// besides the names of the functions and locals.
+    pub dwarf: bool,
+    /// The names of the source files by their `FileId`, which the DWARF
+    /// and the source map refer to them by.
+    pub file_names: Vec<String>,
+    /// The URL the source map will be served at, if one is made. The module
+    /// refers to it by its `sourceMappingURL` section.
+    pub source_map: Option<String>,
+    /// Whether to print the module in the text format too.
+    pub text: bool,
+    /// Where the typed IR of the modules is cached, if it is, so the ones
+    /// which didn't change since aren't checked again.
+    pub cache: Option<Cache>,
+    /// Whether to make the JavaScript glue of the module, wrapping its
+    /// exported functions so they take and give strings and arrays.
+    pub bindings: bool,
+    /// The name of the world to wrap the module as a component of, with
+    /// its WIT, if it is.
+    pub component: Option<String>,
+    /// How many calls of the functions which can call themselves may be
+    /// running at once before they panic with a stack overflow, if the
+    /// depth of the calls is checked.
+    pub max_call_depth: Option<u32>,
+}
+
+/// What compiling a module makes.
+#[derive(Debug, Clone, Default)]
+pub struct Artifacts {
+    pub wasm: Vec<u8>,
+    /// The source map, with `CompileOptions::source_map`.
+    pub source_map: Option<String>,
+    /// The module in the text format, with `CompileOptions::text`.
+    pub text: Option<String>,
+    /// The native object file, which the `Cranelift` backend makes instead
+    /// of the WASM module.
+    pub object: Option<Vec<u8>>,
+    /// The C source, which the `C` backend makes instead of the WASM
+    /// module.
+    pub c: Option<String>,
+    /// The header declaring the `pub fn`s of the C source.
+    pub c_header: Option<String>,
+    /// The JavaScript glue of the module, with `CompileOptions::bindings`.
+    pub bindings: Option<Bindings>,
+    /// The module wrapped as a component, with `CompileOptions::component`.
+    pub component: Option<Component>,
+}
 
 pub fn compile_wasm(module: Module) -> (Vec<u8>, Vec<Error>) {
-    let mut context = Context::new();
+    compile_wasm_with(module, &CompileOptions::default())
+}
+
+pub fn compile_wasm_with(module: Module, options: &CompileOptions) -> (Vec<u8>, Vec<Error>) {
+    let (artifacts, errors) = compile_artifacts(module, options);
+    (artifacts.wasm, errors)
+}
+
+pub fn compile_artifacts(module: Module, options: &CompileOptions) -> (Artifacts, Vec<Error>) {
+    compile_with(module, options, &Wasm)
+}
+
+/// Compiles `module` with `backend`, which is given its typed IR.
+pub fn compile_with(
+    module: Module,
+    options: &CompileOptions,
+    backend: &dyn Backend,
+) -> (Artifacts, Vec<Error>) {
+    match typed_module(module, options) {
+        Ok(typed) => backend.compile(typed, options),
+        Err(errors) => (Artifacts::default(), errors),
+    }
+}
+
+/// Checks `module` and walks it into a context placed at `placement`,
+/// which is left to be linked or finished.
+fn walk_module<'a>(
+    module: Module,
+    options: &CompileOptions,
+    placement: Placement,
+) -> Result<Context<'a>, Vec<Error>> {
+    Ok(walk_typed(
+        typed_module(module, options)?,
+        options,
+        placement,
+    ))
+}
 
-    match context.walk(module) {
+fn walk_typed<'a>(typed: Typed, options: &CompileOptions, placement: Placement) -> Context<'a> {
+    let mut context = Context::new(typed.resolution, typed.types, options, placement);
+
+    match context.walk(typed.module) {
         Ok(()) => {}
         Err(error) => {
             context.add_compilation_error(error);
         }
     }
+    context
+}
+
+/// Configures `module` and lowers it to the typed IR, from the cache if
+/// there is one.
+pub(crate) fn typed_module(
+    mut module: Module,
+    options: &CompileOptions,
+) -> Result<Typed, Vec<Error>> {
+    let errors = configure(&mut module, &options.cfg);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    match &options.cache {
+        Some(cache) => cache.check(module, options),
+        None => check_module(module, options),
+    }
+}
+
+/// Resolves, checks and folds the configured `module`, lowering it to the
+/// typed IR.
+fn check_module(module: Module, options: &CompileOptions) -> Result<Typed, Vec<Error>> {
+    let mut module = hir::lower(module);
+    let resolution = resolve(&module);
+    if !resolution.errors.is_empty() {
+        return Err(resolution.errors);
+    }
+    let errors = check_initialized(&module, &resolution);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let typeck = typeck::check(&module, &resolution);
+    if !typeck.errors.is_empty() {
+        return Err(typeck.errors);
+    }
+    // after the checker, which decides which integers are `i32`, and keeping
+    // the ids of the expressions it typed.
+    let errors = hir::fold_constants(&mut module, &typeck, options.opt_level.fold_constants());
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let module = thir::build(module, &resolution, &typeck);
+    Ok(Typed {
+        module,
+        resolution,
+        types: typeck.types,
+    })
+}
 
-    context.finish()
+fn finish_artifacts(context: Context, options: &CompileOptions) -> (Artifacts, Vec<Error>) {
+    let (mut artifacts, mut errors) = context.finish();
+    if options.text && errors.is_empty() {
+        match print_text(&artifacts.wasm, options.target) {
+            Ok(text) => artifacts.text = Some(text),
+            Err(error) => errors.push(error),
+        }
+    }
+    (artifacts, errors)
 }
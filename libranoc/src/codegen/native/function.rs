@@ -0,0 +1,526 @@
+use std::{collections::HashMap, convert::TryFrom};
+
+use cranelift_codegen::ir::{
+    self,
+    condcodes::{FloatCC, IntCC},
+    types, Block as ClifBlock, ExtFuncData, ExternalName, FuncRef, InstBuilder, TrapCode, Value,
+};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+
+use crate::{
+    core::{
+        arena::Idx,
+        ast::{Literal, Name, Pattern},
+        NodeId, TypeId,
+    },
+    semantic::{Definition, DefinitionKind, Resolution},
+    syntax::Span,
+    thir::{
+        Binary, BinaryOperator, Block, Call, Expression, ExpressionKind, Function, If, Let,
+        Statement, Unary, UnaryOperator,
+    },
+};
+
+use super::{value_type, Declared, Error, Overflow};
+
+/// Translates the functions of a module to Cranelift IR.
+pub(super) struct Translator<'t> {
+    pub resolution: &'t Resolution,
+    /// The index of each function declared, by its id.
+    pub functions: &'t HashMap<NodeId, u32>,
+    pub declared: &'t [Declared],
+    /// What the integer arithmetic of the function does on overflow.
+    pub overflow: Overflow,
+}
+
+impl<'t> Translator<'t> {
+    /// The IR of `function`, the `index`th declared.
+    pub fn translate(&self, index: u32, function: Function) -> Result<ir::Function, Error> {
+        let mut func = ir::Function::with_name_signature(
+            ExternalName::user(0, index),
+            self.declared[index as usize].signature.clone(),
+        );
+        let mut context = FunctionBuilderContext::new();
+        let mut body = Body {
+            translator: self,
+            builder: FunctionBuilder::new(&mut func, &mut context),
+            variables: HashMap::new(),
+            callees: HashMap::new(),
+        };
+        let entry = body.builder.create_block();
+        body.builder.append_block_params_for_function_params(entry);
+        body.builder.switch_to_block(entry);
+        body.builder.seal_block(entry);
+        let parameters = body.builder.block_params(entry).to_vec();
+        // the parameters are bound by the pattern of each, which the
+        // resolver gave their index.
+        for (index, (pattern, ty)) in function.parameters.iter().enumerate() {
+            let ty = value_type(*ty).ok_or_else(|| Error::unimplemented(pattern))?;
+            let variable = body.variable(Binding::Parameter(index as u32), ty);
+            body.builder.def_var(variable, parameters[index]);
+        }
+        if let Some(block) = function.body {
+            let value = body.block(block)?;
+            if !body.builder.is_unreachable() {
+                let values: Vec<Value> = value.into_iter().collect();
+                body.builder.ins().return_(&values);
+            }
+        }
+        body.builder.finalize();
+        Ok(func)
+    }
+}
+
+/// What a variable of the function holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Binding {
+    Parameter(u32),
+    Local(Idx<Definition>),
+}
+
+/// The function being translated.
+struct Body<'t, 'b> {
+    translator: &'t Translator<'t>,
+    builder: FunctionBuilder<'b>,
+    variables: HashMap<Binding, Variable>,
+    /// The functions called so far, by their index.
+    callees: HashMap<u32, FuncRef>,
+}
+
+impl<'t, 'b> Body<'t, 'b> {
+    /// The variable of `binding`, which is declared the first time it is
+    /// asked for.
+    fn variable(&mut self, binding: Binding, ty: ir::Type) -> Variable {
+        if let Some(variable) = self.variables.get(&binding) {
+            return *variable;
+        }
+        let variable = Variable::with_u32(self.variables.len() as u32);
+        self.builder.declare_var(variable, ty);
+        self.variables.insert(binding, variable);
+        variable
+    }
+
+    /// Continues in a block nothing jumps to, after an instruction which
+    /// never finishes, so the code after it has somewhere to go.
+    fn diverge(&mut self) {
+        let block = self.builder.create_block();
+        self.builder.switch_to_block(block);
+        self.builder.seal_block(block);
+    }
+
+    fn block(&mut self, block: Block) -> Result<Option<Value>, Error> {
+        for statement in block.statements {
+            match statement {
+                Statement::Let(r#let) => self.r#let(r#let)?,
+                Statement::Expression(expression) => {
+                    self.expression(expression)?;
+                }
+                Statement::Item(item) => return Err(Error::unimplemented(item)),
+            }
+        }
+        match block.value {
+            Some(value) => self.expression(*value),
+            None => Ok(None),
+        }
+    }
+
+    fn r#let(&mut self, r#let: Let) -> Result<(), Error> {
+        let span = r#let.span.clone();
+        let value = match r#let.value {
+            Some(value) => self.expression(value)?,
+            None => None,
+        };
+        match (
+            &r#let.pattern,
+            self.translator.resolution.lets.get(r#let.id),
+        ) {
+            (Pattern::Slot(Name::Placeholder(_)), _) => Ok(()),
+            (Pattern::Slot(Name::Ident(_)), Some(definition)) => {
+                let ty = value_type(r#let.ty).ok_or_else(|| Error::unimplemented(&span))?;
+                let variable = self.variable(Binding::Local(*definition), ty);
+                if let Some(value) = value {
+                    self.builder.def_var(variable, value);
+                }
+                Ok(())
+            }
+            _ => Err(Error::unimplemented(&span)),
+        }
+    }
+
+    /// The value of `expression`, unless it is the unit or never finishes.
+    fn expression(&mut self, expression: Expression) -> Result<Option<Value>, Error> {
+        let Expression { kind, ty, span, id } = expression;
+        match kind {
+            ExpressionKind::Literal(literal) => self.literal(ty, literal, span).map(Some),
+            ExpressionKind::Name(Name::Ident(_)) => {
+                let binding = self
+                    .binding(id)
+                    .ok_or_else(|| Error::unimplemented(&span))?;
+                let ty = value_type(ty).ok_or_else(|| Error::unimplemented(&span))?;
+                let variable = self.variable(binding, ty);
+                Ok(Some(self.builder.use_var(variable)))
+            }
+            ExpressionKind::Tuple(elements) if elements.is_empty() => Ok(None),
+            ExpressionKind::Unary(unary) => self.unary(unary).map(Some),
+            ExpressionKind::Binary(binary) => self.binary(binary).map(Some),
+            ExpressionKind::Assign(assign) => {
+                let binding = match &assign.target.kind {
+                    ExpressionKind::Name(Name::Ident(_)) => self.binding(assign.target.id),
+                    _ => None,
+                }
+                .ok_or_else(|| Error::unimplemented(&*assign.target))?;
+                let ty = value_type(assign.value.ty)
+                    .ok_or_else(|| Error::unimplemented(&*assign.value))?;
+                let value = self.operand(*assign.value)?;
+                let variable = self.variable(binding, ty);
+                self.builder.def_var(variable, value);
+                Ok(None)
+            }
+            ExpressionKind::Call(call) => self.call(call),
+            ExpressionKind::If(r#if) => self.r#if(ty, r#if),
+            ExpressionKind::Block(block) => self.block(block),
+            ExpressionKind::Unsafe(r#unsafe) => self.block(r#unsafe.block),
+            ExpressionKind::Todo(_) => {
+                self.builder.ins().trap(TrapCode::User(0));
+                self.diverge();
+                Ok(None)
+            }
+            _ => Err(Error::unimplemented(&span)),
+        }
+    }
+
+    /// The value of an operand, which has to have one.
+    fn operand(&mut self, expression: Expression) -> Result<Value, Error> {
+        let span = expression.span.clone();
+        self.expression(expression)?
+            .ok_or_else(|| Error::unimplemented(&span))
+    }
+
+    /// What the name expression `id` refers to, if it is a parameter or a
+    /// local.
+    fn binding(&self, id: NodeId) -> Option<Binding> {
+        let resolution = self.translator.resolution;
+        let definition = resolution.target(*resolution.references.get(id)?);
+        match resolution.definitions[definition].kind {
+            DefinitionKind::Parameter { index, .. } => Some(Binding::Parameter(index)),
+            DefinitionKind::Local => Some(Binding::Local(definition)),
+            _ => None,
+        }
+    }
+
+    fn literal(&mut self, ty: TypeId, literal: Literal, span: Span) -> Result<Value, Error> {
+        Ok(match literal {
+            Literal::Integer(integer) => {
+                let value = integer.value();
+                let span = integer.0.span;
+                if ty == TypeId::I64 {
+                    let value = value
+                        .and_then(|value| i64::try_from(value).ok())
+                        .ok_or_else(|| Error::literal_out_of_range(span, "i64"))?;
+                    self.builder.ins().iconst(types::I64, value)
+                } else {
+                    let value = value
+                        .and_then(|value| i32::try_from(value).ok())
+                        .ok_or_else(|| Error::literal_out_of_range(span, "i32"))?;
+                    self.builder.ins().iconst(types::I32, value as i64)
+                }
+            }
+            // the token is digits around a point, which both parse.
+            Literal::Decimal(token) => {
+                if ty == TypeId::F32 {
+                    let value: f32 = token.content.parse().expect("a decimal parses");
+                    self.builder.ins().f32const(value)
+                } else {
+                    let value: f64 = token.content.parse().expect("a decimal parses");
+                    self.builder.ins().f64const(value)
+                }
+            }
+            Literal::Boolean(token) => self
+                .builder
+                .ins()
+                .iconst(types::I8, (token.content == "true") as i64),
+            Literal::String(_) | Literal::Character(_) => return Err(Error::unimplemented(&span)),
+        })
+    }
+
+    fn unary(&mut self, unary: Unary) -> Result<Value, Error> {
+        let ty = unary.operand.ty;
+        let span = unary.operator_span.clone();
+        let operand = self.operand(*unary.operand)?;
+        Ok(match (unary.operator, ty) {
+            (UnaryOperator::Plus, _) => operand,
+            (UnaryOperator::Minus, TypeId::I32 | TypeId::I64) => {
+                let zero = self.builder.ins().iconst(value_type(ty).unwrap(), 0);
+                self.integer_arithmetic(BinaryOperator::Subtract, zero, operand)
+            }
+            (UnaryOperator::Minus, TypeId::F32 | TypeId::F64) => self.builder.ins().fneg(operand),
+            (UnaryOperator::Not, TypeId::BOOL) => self.builder.ins().bxor_imm(operand, 1),
+            (UnaryOperator::Not, TypeId::I32 | TypeId::I64) => self.builder.ins().bnot(operand),
+            _ => return Err(Error::unimplemented(&span)),
+        })
+    }
+
+    fn binary(&mut self, binary: Binary) -> Result<Value, Error> {
+        let Binary {
+            operator,
+            operator_span,
+            lhs,
+            rhs,
+        } = binary;
+        let ty = lhs.ty;
+        if rhs.ty != ty {
+            return Err(Error::unimplemented(&operator_span));
+        }
+        // both operands are evaluated, as the WASM backend does.
+        let lhs = self.operand(*lhs)?;
+        let rhs = self.operand(*rhs)?;
+        let ins = self.builder.ins();
+        Ok(match (ty, operator) {
+            (
+                TypeId::I32 | TypeId::I64,
+                BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply,
+            ) => self.integer_arithmetic(operator, lhs, rhs),
+            (TypeId::I32 | TypeId::I64, BinaryOperator::Divide | BinaryOperator::Remainder) => {
+                self.integer_division(operator, lhs, rhs)
+            }
+            (TypeId::I32 | TypeId::I64 | TypeId::BOOL, BinaryOperator::BitAnd)
+            | (TypeId::BOOL, BinaryOperator::LogicalAnd) => ins.band(lhs, rhs),
+            (TypeId::I32 | TypeId::I64 | TypeId::BOOL, BinaryOperator::BitOr)
+            | (TypeId::BOOL, BinaryOperator::LogicalOr) => ins.bor(lhs, rhs),
+            (TypeId::I32 | TypeId::I64 | TypeId::BOOL, BinaryOperator::BitXor) => {
+                ins.bxor(lhs, rhs)
+            }
+            (TypeId::I32 | TypeId::I64, BinaryOperator::ShiftLeft) => ins.ishl(lhs, rhs),
+            (TypeId::I32 | TypeId::I64, BinaryOperator::ShiftRight) => ins.sshr(lhs, rhs),
+            (TypeId::F32 | TypeId::F64, BinaryOperator::Add) => ins.fadd(lhs, rhs),
+            (TypeId::F32 | TypeId::F64, BinaryOperator::Subtract) => ins.fsub(lhs, rhs),
+            (TypeId::F32 | TypeId::F64, BinaryOperator::Multiply) => ins.fmul(lhs, rhs),
+            (TypeId::F32 | TypeId::F64, BinaryOperator::Divide) => ins.fdiv(lhs, rhs),
+            (TypeId::I32 | TypeId::I64 | TypeId::BOOL, operator) => {
+                let condition = match operator {
+                    BinaryOperator::EqualTo => IntCC::Equal,
+                    BinaryOperator::NotEqualTo => IntCC::NotEqual,
+                    BinaryOperator::GreaterThan => IntCC::SignedGreaterThan,
+                    BinaryOperator::LessThan => IntCC::SignedLessThan,
+                    BinaryOperator::GreaterThanOrEqualTo => IntCC::SignedGreaterThanOrEqual,
+                    BinaryOperator::LessThanOrEqualTo => IntCC::SignedLessThanOrEqual,
+                    _ => return Err(Error::unimplemented(&operator_span)),
+                };
+                let result = ins.icmp(condition, lhs, rhs);
+                self.builder.ins().bint(types::I8, result)
+            }
+            (TypeId::F32 | TypeId::F64, operator) => {
+                let condition = match operator {
+                    BinaryOperator::EqualTo => FloatCC::Equal,
+                    BinaryOperator::NotEqualTo => FloatCC::NotEqual,
+                    BinaryOperator::GreaterThan => FloatCC::GreaterThan,
+                    BinaryOperator::LessThan => FloatCC::LessThan,
+                    BinaryOperator::GreaterThanOrEqualTo => FloatCC::GreaterThanOrEqual,
+                    BinaryOperator::LessThanOrEqualTo => FloatCC::LessThanOrEqual,
+                    _ => return Err(Error::unimplemented(&operator_span)),
+                };
+                let result = ins.fcmp(condition, lhs, rhs);
+                self.builder.ins().bint(types::I8, result)
+            }
+            _ => return Err(Error::unimplemented(&operator_span)),
+        })
+    }
+
+    /// `+`, `-` or `*` of integers, which does what the function does on
+    /// overflow.
+    fn integer_arithmetic(&mut self, operator: BinaryOperator, lhs: Value, rhs: Value) -> Value {
+        let ins = self.builder.ins();
+        let result = match operator {
+            BinaryOperator::Add => ins.iadd(lhs, rhs),
+            BinaryOperator::Subtract => ins.isub(lhs, rhs),
+            _ => ins.imul(lhs, rhs),
+        };
+        if self.translator.overflow == Overflow::Wrap {
+            return result;
+        }
+        let ty = self.builder.func.dfg.value_type(result);
+        let bits = ty.bits() as i64;
+        // the sum overflowed if both operands have the other sign than it,
+        // the difference if the left one has the other sign than both the
+        // right one and it, and the product if its high half isn't the sign
+        // of its low one.
+        let ins = self.builder.ins();
+        let overflowed = match operator {
+            BinaryOperator::Add => {
+                let lhs_sign = ins.bxor(lhs, result);
+                let rhs_sign = self.builder.ins().bxor(rhs, result);
+                let both = self.builder.ins().band(lhs_sign, rhs_sign);
+                self.builder.ins().icmp_imm(IntCC::SignedLessThan, both, 0)
+            }
+            BinaryOperator::Subtract => {
+                let operands = ins.bxor(lhs, rhs);
+                let lhs_sign = self.builder.ins().bxor(lhs, result);
+                let both = self.builder.ins().band(operands, lhs_sign);
+                self.builder.ins().icmp_imm(IntCC::SignedLessThan, both, 0)
+            }
+            _ => {
+                let high = ins.smulhi(lhs, rhs);
+                let sign = self.builder.ins().sshr_imm(result, bits - 1);
+                self.builder.ins().icmp(IntCC::NotEqual, high, sign)
+            }
+        };
+        match self.translator.overflow {
+            Overflow::Saturate => {
+                // the bound is the one of the sign of the left operand for
+                // the sum and the difference, and of the product otherwise.
+                let sign_of = match operator {
+                    BinaryOperator::Multiply => self.builder.ins().bxor(lhs, rhs),
+                    _ => lhs,
+                };
+                let negative = self
+                    .builder
+                    .ins()
+                    .icmp_imm(IntCC::SignedLessThan, sign_of, 0);
+                let (min, max) = if bits == 32 {
+                    (i32::MIN as i64, i32::MAX as i64)
+                } else {
+                    (i64::MIN, i64::MAX)
+                };
+                let min = self.builder.ins().iconst(ty, min);
+                let max = self.builder.ins().iconst(ty, max);
+                let bound = self.builder.ins().select(negative, min, max);
+                self.builder.ins().select(overflowed, bound, result)
+            }
+            _ => {
+                self.builder
+                    .ins()
+                    .trapnz(overflowed, TrapCode::IntegerOverflow);
+                result
+            }
+        }
+    }
+
+    /// `/` or `%` of integers. Dividing by zero traps, and dividing the
+    /// least value by `-1` does what the function does on overflow, like in
+    /// the WASM backend: the quotient wraps to the least value or saturates
+    /// to the greatest, and the remainder is `0`.
+    fn integer_division(&mut self, operator: BinaryOperator, lhs: Value, rhs: Value) -> Value {
+        let ty = self.builder.func.dfg.value_type(lhs);
+        let (min, max) = if ty.bits() == 32 {
+            (i32::MIN as i64, i32::MAX as i64)
+        } else {
+            (i64::MIN, i64::MAX)
+        };
+        self.builder
+            .ins()
+            .trapz(rhs, TrapCode::IntegerDivisionByZero);
+        let minus_one = self.builder.ins().icmp_imm(IntCC::Equal, rhs, -1);
+        if self.translator.overflow == Overflow::Trap {
+            let least = self.builder.ins().icmp_imm(IntCC::Equal, lhs, min);
+            let overflowed = self.builder.ins().band(least, minus_one);
+            self.builder
+                .ins()
+                .trapnz(overflowed, TrapCode::IntegerOverflow);
+            return match operator {
+                BinaryOperator::Divide => self.builder.ins().sdiv(lhs, rhs),
+                _ => self.builder.ins().srem(lhs, rhs),
+            };
+        }
+        // the instructions trap on the least value and `-1` whatever the
+        // function does, so it is divided by `1` instead, which leaves the
+        // remainder `0` and the quotient to be negated.
+        let one = self.builder.ins().iconst(ty, 1);
+        let divisor = self.builder.ins().select(minus_one, one, rhs);
+        if operator == BinaryOperator::Remainder {
+            return self.builder.ins().srem(lhs, divisor);
+        }
+        let quotient = self.builder.ins().sdiv(lhs, divisor);
+        let mut negated = self.builder.ins().ineg(lhs);
+        if self.translator.overflow == Overflow::Saturate {
+            let least = self.builder.ins().icmp_imm(IntCC::Equal, lhs, min);
+            let max = self.builder.ins().iconst(ty, max);
+            negated = self.builder.ins().select(least, max, negated);
+        }
+        self.builder.ins().select(minus_one, negated, quotient)
+    }
+
+    fn call(&mut self, call: Call) -> Result<Option<Value>, Error> {
+        let span = call.span.clone();
+        let resolution = self.translator.resolution;
+        let function = match &call.callee.kind {
+            ExpressionKind::Name(Name::Ident(_)) => resolution
+                .references
+                .get(call.callee.id)
+                .map(|definition| &resolution.definitions[resolution.target(*definition)].kind),
+            _ => None,
+        };
+        let index = match function {
+            Some(DefinitionKind::Function { id, .. }) => self.translator.functions.get(id),
+            _ => None,
+        }
+        .copied()
+        .ok_or_else(|| Error::unimplemented(&span))?;
+        let mut arguments = Vec::new();
+        for argument in call.arguments {
+            arguments.push(self.operand(argument)?);
+        }
+        let callee = self.callee(index);
+        let call = self.builder.ins().call(callee, &arguments);
+        Ok(self.builder.inst_results(call).first().copied())
+    }
+
+    /// The reference of the function `index` in this one, which is imported
+    /// the first time it is called.
+    fn callee(&mut self, index: u32) -> FuncRef {
+        if let Some(callee) = self.callees.get(&index) {
+            return *callee;
+        }
+        let declared = &self.translator.declared[index as usize];
+        let signature = self.builder.import_signature(declared.signature.clone());
+        let callee = self.builder.import_function(ExtFuncData {
+            name: ExternalName::user(0, index),
+            signature,
+            // the functions of the object are in its text, and the others
+            // wherever the linker puts them.
+            colocated: declared.defined,
+        });
+        self.callees.insert(index, callee);
+        callee
+    }
+
+    fn r#if(&mut self, ty: TypeId, r#if: If) -> Result<Option<Value>, Error> {
+        let result = match ty {
+            TypeId::UNIT | TypeId::NEVER => None,
+            ty => Some(value_type(ty).ok_or_else(|| Error::unimplemented(&r#if.body))?),
+        };
+        let condition = self.operand(*r#if.condition)?;
+        let then_block = self.builder.create_block();
+        let else_block = self.builder.create_block();
+        let merge = self.builder.create_block();
+        if let Some(result) = result {
+            self.builder.append_block_param(merge, result);
+        }
+        self.builder.ins().brz(condition, else_block, &[]);
+        self.builder.ins().jump(then_block, &[]);
+
+        self.builder.switch_to_block(then_block);
+        self.builder.seal_block(then_block);
+        let value = self.block(r#if.body)?;
+        self.jump(merge, result.and(value));
+
+        self.builder.switch_to_block(else_block);
+        self.builder.seal_block(else_block);
+        let value = match r#if.else_block {
+            Some(block) => self.block(block)?,
+            None => None,
+        };
+        self.jump(merge, result.and(value));
+
+        self.builder.switch_to_block(merge);
+        self.builder.seal_block(merge);
+        Ok(result.map(|_| self.builder.block_params(merge)[0]))
+    }
+
+    /// Jumps to `block` with `value`, unless the code before never finishes.
+    fn jump(&mut self, block: ClifBlock, value: Option<Value>) {
+        if !self.builder.is_unreachable() {
+            let arguments: Vec<Value> = value.into_iter().collect();
+            self.builder.ins().jump(block, &arguments);
+        }
+    }
+}
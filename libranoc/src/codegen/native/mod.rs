@@ -0,0 +1,349 @@
+//! The backend compiling the typed IR to native object files through
+//! Cranelift, which a linker makes an executable or a library of.
+//!
+//! Only the functions at the top of a module working on numbers and
+//! booleans are compiled for now: the values the WASM backend keeps on the
+//! heap, like strings, tuples, arrays and closures, need a runtime the
+//! native code doesn't have yet, and are reported as unimplemented. A
+//! `pub fn` is a global symbol of its name, with the calling convention of
+//! the target, and an `extern fn` one the object leaves undefined, named
+//! after the `name` of its `#[link(...)]` if it has one. A boolean is
+//! passed as a byte, and the unit as nothing.
+
+use std::collections::HashMap;
+
+use cranelift_codegen::{
+    binemit::{Addend, CodeOffset, NullStackMapSink, NullTrapSink, Reloc, RelocSink},
+    ir::{self, types, AbiParam, ConstantOffset, ExternalName, JumpTable, Signature, SourceLoc},
+    isa::{self, TargetIsa},
+    settings::{self, Configurable},
+};
+use object::{
+    write::{Object, Relocation, StandardSection, Symbol, SymbolSection},
+    RelocationEncoding, RelocationKind, SectionKind, SymbolFlags, SymbolKind, SymbolScope,
+};
+use target_lexicon::{Architecture, BinaryFormat, Endianness, Triple};
+
+use crate::{
+    core::{NodeId, TypeId},
+    thir::{Function, Item, Statement},
+};
+
+use super::*;
+
+mod function;
+
+use function::Translator;
+
+/// The backend compiling to native object files through Cranelift.
+#[derive(Debug, Clone)]
+pub struct Cranelift {
+    triple: Triple,
+}
+
+impl Cranelift {
+    pub fn new(triple: Triple) -> Self {
+        Cranelift { triple }
+    }
+
+    /// The backend compiling for the machine the compiler runs on.
+    pub fn host() -> Self {
+        Cranelift::new(Triple::host())
+    }
+}
+
+impl Backend for Cranelift {
+    fn compile(&self, typed: Typed, options: &CompileOptions) -> (Artifacts, Vec<Error>) {
+        match self.compile_object(typed, options) {
+            Ok(object) => (
+                Artifacts {
+                    object: Some(object),
+                    ..Artifacts::default()
+                },
+                Vec::new(),
+            ),
+            Err(errors) => (Artifacts::default(), errors),
+        }
+    }
+}
+
+/// A function the object defines or leaves to the linker, which the code
+/// calls by its index.
+struct Declared {
+    symbol: String,
+    signature: Signature,
+    scope: SymbolScope,
+    defined: bool,
+}
+
+impl Cranelift {
+    fn isa(&self, options: &CompileOptions) -> Result<Box<dyn TargetIsa>, Error> {
+        let unsupported =
+            |reason: &str| Error::unsupported_target(&self.triple.to_string(), reason);
+        let mut flags = settings::builder();
+        let opt_level = if options.opt_level >= OptLevel::O1 {
+            "speed"
+        } else {
+            "none"
+        };
+        flags
+            .set("opt_level", opt_level)
+            .map_err(Error::native_codegen)?;
+        // the calls of the `extern fn`s go through the PLT.
+        flags.set("is_pic", "true").map_err(Error::native_codegen)?;
+        let builder = isa::lookup(self.triple.clone())
+            .map_err(|_| unsupported("Cranelift doesn't compile for it"))?;
+        Ok(builder.finish(settings::Flags::new(flags)))
+    }
+
+    fn object(&self) -> Result<Object, Error> {
+        let unsupported =
+            |reason: &str| Error::unsupported_target(&self.triple.to_string(), reason);
+        let format = match self.triple.binary_format {
+            BinaryFormat::Elf => object::BinaryFormat::Elf,
+            BinaryFormat::Macho => object::BinaryFormat::MachO,
+            BinaryFormat::Coff => object::BinaryFormat::Coff,
+            _ => return Err(unsupported("only ELF, Mach-O and COFF objects are written")),
+        };
+        let architecture = match self.triple.architecture {
+            Architecture::X86_64 => object::Architecture::X86_64,
+            _ => return Err(unsupported("only x86-64 objects are written")),
+        };
+        let endianness = match self.triple.endianness() {
+            Ok(Endianness::Little) => object::Endianness::Little,
+            Ok(Endianness::Big) => object::Endianness::Big,
+            Err(()) => return Err(unsupported("its endianness isn't known")),
+        };
+        let mut object = Object::new(format, architecture, endianness);
+        if format == object::BinaryFormat::Elf {
+            // without it, the linkers of ELF give the executable a stack
+            // which can be run.
+            object.add_section(Vec::new(), b".note.GNU-stack".to_vec(), SectionKind::Other);
+        }
+        Ok(object)
+    }
+
+    fn compile_object(
+        &self,
+        typed: Typed,
+        options: &CompileOptions,
+    ) -> Result<Vec<u8>, Vec<Error>> {
+        let isa = self.isa(options).map_err(|error| vec![error])?;
+        let mut object = self.object().map_err(|error| vec![error])?;
+        let Typed {
+            module, resolution, ..
+        } = typed;
+
+        let mut errors = Vec::new();
+        let mut functions = Vec::new();
+        for statement in module.statements {
+            match statement {
+                Statement::Item(Item::Function(function)) => functions.push(function),
+                Statement::Item(Item::Use(_)) => {}
+                statement => errors.push(Error::unimplemented(statement)),
+            }
+        }
+        // every function is declared first, so the ones before it can call
+        // it.
+        let mut declared = Vec::new();
+        let mut indices: HashMap<NodeId, u32> = HashMap::new();
+        for function in &functions {
+            match declare(isa.as_ref(), function) {
+                Ok(declaration) => {
+                    indices.insert(function.id, declared.len() as u32);
+                    declared.push(declaration);
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+
+        let mut bodies = Vec::new();
+        for function in functions {
+            let index = match indices.get(&function.id) {
+                Some(index) if !function.is_extern => *index,
+                _ => continue,
+            };
+            let overflow = match function.attribute("overflow") {
+                Some(attribute) => match Overflow::from_attribute(&attribute.value) {
+                    Ok(overflow) => overflow,
+                    Err(error) => {
+                        errors.push(error);
+                        continue;
+                    }
+                },
+                None => options.overflow,
+            };
+            let translator = Translator {
+                resolution: &resolution,
+                functions: &indices,
+                declared: &declared,
+                overflow,
+            };
+            let function = match translator.translate(index, function) {
+                Ok(function) => function,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+            let mut context = cranelift_codegen::Context::for_function(function);
+            let mut code = Vec::new();
+            let mut relocations = Relocations::default();
+            match context.compile_and_emit(
+                isa.as_ref(),
+                &mut code,
+                &mut relocations,
+                &mut NullTrapSink {},
+                &mut NullStackMapSink {},
+            ) {
+                Ok(_) => bodies.push((index, code, relocations.0)),
+                Err(error) => errors.push(Error::native_codegen(error)),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let symbols: Vec<_> = declared
+            .iter()
+            .map(|declaration| {
+                object.add_symbol(Symbol {
+                    name: declaration.symbol.as_bytes().to_vec(),
+                    value: 0,
+                    size: 0,
+                    kind: SymbolKind::Text,
+                    scope: declaration.scope,
+                    weak: false,
+                    section: SymbolSection::Undefined,
+                    flags: SymbolFlags::None,
+                })
+            })
+            .collect();
+        let text = object.section_id(StandardSection::Text);
+        let mut offsets = HashMap::new();
+        for (index, code, _) in &bodies {
+            let offset = object.add_symbol_data(symbols[*index as usize], text, code, 16);
+            offsets.insert(*index, offset);
+        }
+        for (index, _, relocations) in bodies {
+            for (offset, reloc, callee, addend) in relocations {
+                let (kind, encoding, size) = match reloc {
+                    Reloc::X86CallPCRel4 | Reloc::X86PCRel4 => {
+                        (RelocationKind::Relative, RelocationEncoding::X86Branch, 32)
+                    }
+                    Reloc::X86CallPLTRel4 => (
+                        RelocationKind::PltRelative,
+                        RelocationEncoding::X86Branch,
+                        32,
+                    ),
+                    Reloc::X86GOTPCRel4 => {
+                        (RelocationKind::GotRelative, RelocationEncoding::Generic, 32)
+                    }
+                    Reloc::Abs8 => (RelocationKind::Absolute, RelocationEncoding::Generic, 64),
+                    reloc => {
+                        errors.push(Error::native_codegen(format!(
+                            "the relocation {:?} isn't written",
+                            reloc
+                        )));
+                        continue;
+                    }
+                };
+                let relocation = Relocation {
+                    offset: offsets[&index] + offset as u64,
+                    size,
+                    kind,
+                    encoding,
+                    symbol: symbols[callee as usize],
+                    addend,
+                };
+                if let Err(error) = object.add_relocation(text, relocation) {
+                    errors.push(Error::native_codegen(error));
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        object
+            .write()
+            .map_err(|error| vec![Error::native_codegen(error)])
+    }
+}
+
+/// The symbol and the signature of `function`, which are the ones of a C
+/// function of the same name.
+fn declare(isa: &dyn TargetIsa, function: &Function) -> Result<Declared, Error> {
+    if let Some(parameter) = function.generic_parameters.first() {
+        return Err(Error::unimplemented(parameter));
+    }
+    let mut signature = Signature::new(isa.default_call_conv());
+    for (pattern, ty) in &function.parameters {
+        match value_type(*ty) {
+            Some(ty) => signature.params.push(AbiParam::new(ty)),
+            None => return Err(Error::unimplemented(pattern)),
+        }
+    }
+    match function.return_type {
+        TypeId::UNIT | TypeId::NEVER => {}
+        ty => match value_type(ty) {
+            Some(ty) => signature.returns.push(AbiParam::new(ty)),
+            None => return Err(Error::unimplemented(&function.name)),
+        },
+    }
+    let symbol = match function.attribute("link") {
+        Some(link) if function.is_extern => link_of(&link.value, &function.name)?.1,
+        _ => function.name.content.clone(),
+    };
+    let scope = if function.is_extern {
+        SymbolScope::Unknown
+    } else if function.is_pub {
+        SymbolScope::Dynamic
+    } else {
+        SymbolScope::Compilation
+    };
+    Ok(Declared {
+        symbol,
+        signature,
+        scope,
+        defined: !function.is_extern,
+    })
+}
+
+/// The type of the values of `ty` in the native code, unless the backend
+/// doesn't compile them yet or they are the unit.
+fn value_type(ty: TypeId) -> Option<ir::Type> {
+    Some(match ty {
+        TypeId::I32 => types::I32,
+        TypeId::I64 => types::I64,
+        TypeId::F32 => types::F32,
+        TypeId::F64 => types::F64,
+        TypeId::BOOL => types::I8,
+        _ => return None,
+    })
+}
+
+/// The relocations of the calls of a function, as the offset of each, its
+/// kind, the index of the callee and the addend.
+#[derive(Default)]
+struct Relocations(Vec<(CodeOffset, Reloc, u32, Addend)>);
+
+impl RelocSink for Relocations {
+    fn reloc_block(&mut self, _: CodeOffset, _: Reloc, _: CodeOffset) {}
+
+    fn reloc_external(
+        &mut self,
+        offset: CodeOffset,
+        _: SourceLoc,
+        reloc: Reloc,
+        name: &ExternalName,
+        addend: Addend,
+    ) {
+        if let ExternalName::User { index, .. } = name {
+            self.0.push((offset, reloc, *index, addend));
+        }
+    }
+
+    fn reloc_constant(&mut self, _: CodeOffset, _: Reloc, _: ConstantOffset) {}
+
+    fn reloc_jt(&mut self, _: CodeOffset, _: Reloc, _: JumpTable) {}
+}
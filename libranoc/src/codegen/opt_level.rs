@@ -0,0 +1,58 @@
+/// How much a build optimizes the module, set by `-O0`, `-O1` or `-O2` on
+/// the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum OptLevel {
+    /// Emits the code as it is written, with the operations on constants
+    /// left to run and every local named, so a debugger steps through it
+    /// the way it reads. What debug builds do.
+    #[default]
+    O0,
+    /// Folds the constants, simplifies the obvious sequences of
+    /// instructions and leaves out the code nothing exported uses.
+    O1,
+    /// Inlines the calls of the small functions, and of the ones given
+    /// `#[inline]`, besides. What release builds do.
+    O2,
+}
+
+impl OptLevel {
+    /// The level called `name` on the command line, its digit.
+    pub fn from_name(name: &str) -> Option<OptLevel> {
+        Some(match name {
+            "0" => OptLevel::O0,
+            "1" => OptLevel::O1,
+            "2" => OptLevel::O2,
+            _ => return None,
+        })
+    }
+
+    /// Whether the operations on constants are replaced by their values.
+    /// They are checked for overflow whether they are or not.
+    pub fn fold_constants(self) -> bool {
+        self >= OptLevel::O1
+    }
+
+    /// Whether to simplify the obvious sequences of instructions, like
+    /// operators on constants.
+    pub fn peephole(self) -> bool {
+        self >= OptLevel::O1
+    }
+
+    /// Whether to leave out the functions, the slots in the table and the
+    /// string literals none of the exported functions uses.
+    pub fn eliminate_dead_code(self) -> bool {
+        self >= OptLevel::O1
+    }
+
+    /// Whether to inline the calls of the small functions, and of the ones
+    /// given `#[inline]`.
+    pub fn inline(self) -> bool {
+        self >= OptLevel::O2
+    }
+
+    /// Whether to name every local in the `name` section and the DWARF,
+    /// the scratch ones and the slots of the tuples too.
+    pub fn name_every_local(self) -> bool {
+        self == OptLevel::O0
+    }
+}
@@ -0,0 +1,42 @@
+use crate::core::{ast::AttributeValue, Error};
+
+/// What the integer `+`, `-`, `*` and `/` do when the result doesn't fit
+/// in their type. It is set for a build by `CompileOptions`, and for the
+/// operations of a single function by `#[overflow(trap)]`, `#[overflow(wrap)]`
+/// or `#[overflow(saturate)]` in front of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Panics with the operation, like a `todo` does. What debug builds
+    /// do.
+    #[default]
+    Trap,
+    /// Keeps the low bits of the result, like the WASM instructions do.
+    Wrap,
+    /// Gives the bound of the type the result is past.
+    Saturate,
+}
+
+impl Overflow {
+    /// The policy called `name` in the attribute and on the command line.
+    pub fn from_name(name: &str) -> Option<Overflow> {
+        Some(match name {
+            "trap" => Overflow::Trap,
+            "wrap" => Overflow::Wrap,
+            "saturate" => Overflow::Saturate,
+            _ => return None,
+        })
+    }
+
+    /// Reads `overflow(policy)`.
+    pub fn from_attribute(value: &AttributeValue) -> Result<Overflow, Error> {
+        const EXPECTED: &str = "`overflow(trap)`, `overflow(wrap)` or `overflow(saturate)`";
+        match value {
+            AttributeValue::List { items, .. } if items.len() == 1 => match &items[0] {
+                AttributeValue::Word(name) => Overflow::from_name(&name.content)
+                    .ok_or_else(|| Error::invalid_attribute(&items[0], EXPECTED)),
+                item => Err(Error::invalid_attribute(item, EXPECTED)),
+            },
+            _ => Err(Error::invalid_attribute(value, EXPECTED)),
+        }
+    }
+}
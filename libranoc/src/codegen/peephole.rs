@@ -0,0 +1,136 @@
+use wasm_encoder::Instruction;
+
+/// Simplifies the obvious sequences of instructions in `body`: operators on
+/// constants are folded, operands which change nothing are dropped, a
+/// `local.set` followed by a `local.get` of the same local is a
+/// `local.tee`, two `i32.eqz` in front of a branch or two negations cancel
+/// out, and what is pushed only to be dropped isn't pushed. An instruction
+/// `keep` tells the position of is left as it is, and so is everything in
+/// front of it. Gives where each instruction of the body is now, or the one
+/// behind it if it was removed, and the end of the body behind the last one.
+pub fn simplify<'a>(body: &mut Vec<Instruction<'a>>, keep: impl Fn(usize) -> bool) -> Vec<usize> {
+    let mut code = Vec::with_capacity(body.len());
+    let mut positions: Vec<usize> = Vec::with_capacity(body.len() + 1);
+    // the instructions before it are left as they are.
+    let mut fixed = 0;
+    for (at, instruction) in std::mem::take(body).into_iter().enumerate() {
+        positions.push(code.len());
+        code.push(instruction);
+        if keep(at) {
+            fixed = code.len();
+            continue;
+        }
+        // what is simplified may make what is in front of it simplify too.
+        while let Some((replaced, with)) = simplify_end(&code[fixed..]) {
+            code.truncate(code.len() - replaced);
+            code.extend(with);
+        }
+        for position in positions.iter_mut().rev() {
+            if *position <= code.len() {
+                break;
+            }
+            *position = code.len();
+        }
+    }
+    positions.push(code.len());
+    *body = code;
+    positions
+}
+
+/// The number of instructions at the end of `code` which simplify, with
+/// what they simplify to, if they do.
+fn simplify_end<'a>(code: &[Instruction<'a>]) -> Option<(usize, Vec<Instruction<'a>>)> {
+    use Instruction::*;
+    if let [.., I32Const(a), I32Const(b), operator] = code {
+        if let Some(folded) = fold_i32(*a, *b, operator) {
+            return Some((3, vec![folded]));
+        }
+    }
+    if let [.., I64Const(a), I64Const(b), operator] = code {
+        if let Some(folded) = fold_i64(*a, *b, operator) {
+            return Some((3, vec![folded]));
+        }
+    }
+    Some(match code {
+        [.., I32Const(a), I32Eqz] => (2, vec![I32Const((*a == 0) as i32)]),
+        [.., I64Const(a), I64Eqz] => (2, vec![I32Const((*a == 0) as i32)]),
+        [.., I32Const(0), I32Add | I32Sub | I32Or | I32Xor | I32Shl | I32ShrS | I32ShrU]
+        | [.., I64Const(0), I64Add | I64Sub | I64Or | I64Xor | I64Shl | I64ShrS | I64ShrU]
+        | [.., I32Const(1), I32Mul]
+        | [.., I64Const(1), I64Mul] => (2, vec![]),
+        [.., LocalSet(set), LocalGet(get)] if set == get => (2, vec![LocalTee(*set)]),
+        [.., LocalTee(local), Drop] => (2, vec![LocalSet(*local)]),
+        [.., I32Const(_) | I64Const(_) | F32Const(_) | F64Const(_) | LocalGet(_) | GlobalGet(_), Drop] => {
+            (2, vec![])
+        }
+        [.., I32Eqz, I32Eqz, If(ty)] => (3, vec![If(*ty)]),
+        [.., I32Eqz, I32Eqz, BrIf(label)] => (3, vec![BrIf(*label)]),
+        [.., F32Neg, F32Neg] | [.., F64Neg, F64Neg] => (2, vec![]),
+        [.., Nop] => (1, vec![]),
+        _ => return None,
+    })
+}
+
+/// `a operator b` for the `i32` operators which can't trap.
+fn fold_i32<'a>(a: i32, b: i32, operator: &Instruction) -> Option<Instruction<'a>> {
+    use Instruction::*;
+    let (unsigned_a, unsigned_b) = (a as u32, b as u32);
+    let value = match operator {
+        I32Add => a.wrapping_add(b),
+        I32Sub => a.wrapping_sub(b),
+        I32Mul => a.wrapping_mul(b),
+        I32And => a & b,
+        I32Or => a | b,
+        I32Xor => a ^ b,
+        // the shifts and rotations only take the low bits of `b`.
+        I32Shl => a.wrapping_shl(unsigned_b),
+        I32ShrS => a.wrapping_shr(unsigned_b),
+        I32ShrU => unsigned_a.wrapping_shr(unsigned_b) as i32,
+        I32Rotl => unsigned_a.rotate_left(unsigned_b % 32) as i32,
+        I32Rotr => unsigned_a.rotate_right(unsigned_b % 32) as i32,
+        I32Eq => (a == b) as i32,
+        I32Neq => (a != b) as i32,
+        I32LtS => (a < b) as i32,
+        I32LtU => (unsigned_a < unsigned_b) as i32,
+        I32GtS => (a > b) as i32,
+        I32GtU => (unsigned_a > unsigned_b) as i32,
+        I32LeS => (a <= b) as i32,
+        I32LeU => (unsigned_a <= unsigned_b) as i32,
+        I32GeS => (a >= b) as i32,
+        I32GeU => (unsigned_a >= unsigned_b) as i32,
+        _ => return None,
+    };
+    Some(I32Const(value))
+}
+
+/// `a operator b` for the `i64` operators which can't trap, the
+/// comparisons of which give an `i32`.
+fn fold_i64<'a>(a: i64, b: i64, operator: &Instruction) -> Option<Instruction<'a>> {
+    use Instruction::*;
+    let (unsigned_a, unsigned_b) = (a as u64, b as u64);
+    let value = match operator {
+        I64Add => a.wrapping_add(b),
+        I64Sub => a.wrapping_sub(b),
+        I64Mul => a.wrapping_mul(b),
+        I64And => a & b,
+        I64Or => a | b,
+        I64Xor => a ^ b,
+        I64Shl => a.wrapping_shl(unsigned_b as u32),
+        I64ShrS => a.wrapping_shr(unsigned_b as u32),
+        I64ShrU => unsigned_a.wrapping_shr(unsigned_b as u32) as i64,
+        I64Rotl => unsigned_a.rotate_left((unsigned_b % 64) as u32) as i64,
+        I64Rotr => unsigned_a.rotate_right((unsigned_b % 64) as u32) as i64,
+        I64Eq => return Some(I32Const((a == b) as i32)),
+        I64Neq => return Some(I32Const((a != b) as i32)),
+        I64LtS => return Some(I32Const((a < b) as i32)),
+        I64LtU => return Some(I32Const((unsigned_a < unsigned_b) as i32)),
+        I64GtS => return Some(I32Const((a > b) as i32)),
+        I64GtU => return Some(I32Const((unsigned_a > unsigned_b) as i32)),
+        I64LeS => return Some(I32Const((a <= b) as i32)),
+        I64LeU => return Some(I32Const((unsigned_a <= unsigned_b) as i32)),
+        I64GeS => return Some(I32Const((a >= b) as i32)),
+        I64GeU => return Some(I32Const((unsigned_a >= unsigned_b) as i32)),
+        _ => return None,
+    };
+    Some(I64Const(value))
+}
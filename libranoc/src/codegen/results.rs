@@ -0,0 +1,142 @@
+use wasm_encoder::{BlockType, Instruction, MemArg, ValType};
+
+use crate::core::TypeId;
+
+use super::{Context, Simd};
+
+/// The most values a tuple is given as with the `multi_value` target. A
+/// bigger one is passed back through the memory like without it.
+pub(super) const MAX_RESULTS: usize = 8;
+
+impl<'a> Context<'a> {
+    /// The value types a function or an `if` giving a value of `ty` gives,
+    /// which is all the ones of a small tuple with the `multi_value` target
+    /// and only the first otherwise.
+    pub fn result_types(&self, ty: TypeId) -> Vec<ValType> {
+        let mut types = self.convert_type(ty);
+        if !self.target().multi_value || types.len() > MAX_RESULTS {
+            types.truncate(1);
+        }
+        types
+    }
+
+    /// Whether some of the values of `ty` are passed back through the
+    /// memory rather than given.
+    pub fn spills_results(&self, ty: TypeId) -> bool {
+        self.result_types(ty).len() < self.convert_type(ty).len()
+    }
+
+    /// The type of the block of an `if` giving a value of `ty`.
+    pub fn block_type(&mut self, ty: TypeId) -> BlockType {
+        match self.result_types(ty).as_slice() {
+            [] => BlockType::Empty,
+            [ty] => BlockType::Result(*ty),
+            types => BlockType::FunctionType(self.declare_function_type(vec![], types.to_vec())),
+        }
+    }
+
+    /// Stores the values of `ty` on the stack which aren't given, from the
+    /// last one, where the function or the branch of the `if` ending next
+    /// passes them back.
+    pub fn spill_results(&mut self, ty: TypeId) {
+        if !self.spills_results(ty) {
+            return;
+        }
+        let area = self.result_area(ty);
+        for (value_type, offset) in self.spilled(ty).into_iter().rev() {
+            let scratch = self.scratch_locals(value_type, 1)[0];
+            self.instructions.extend(vec![
+                Instruction::LocalSet(scratch),
+                Instruction::I32Const(area),
+                Instruction::LocalGet(scratch),
+            ]);
+            self.push_store(value_type, offset);
+        }
+    }
+
+    /// Loads the values of `ty` which weren't given behind the one which
+    /// was, right after the call or the `if` giving them.
+    pub fn reload_results(&mut self, ty: TypeId) {
+        if !self.spills_results(ty) {
+            return;
+        }
+        let area = self.result_area(ty);
+        for (value_type, offset) in self.spilled(ty) {
+            self.instructions.push(Instruction::I32Const(area));
+            self.push_load(value_type, offset);
+        }
+    }
+
+    /// The value types of `ty` which aren't given, with their offsets into
+    /// its `result_area`.
+    fn spilled(&self, ty: TypeId) -> Vec<(ValType, u32)> {
+        let given = self.result_types(ty).len();
+        let mut offset = 0;
+        self.convert_type(ty)
+            .into_iter()
+            .map(|value_type| {
+                let at = offset;
+                offset += value_size(value_type);
+                (value_type, at)
+            })
+            .skip(given)
+            .collect()
+    }
+
+    /// Stores the value of the type `ty` on the stack at `offset` from the
+    /// address under it, which a vector needs an instruction written by
+    /// hand for.
+    pub fn push_store(&mut self, ty: ValType, offset: u32) {
+        match ty {
+            ValType::V128 => self.push_simd(Simd::Store(offset)),
+            ty => self.instructions.push(store(ty, offset)),
+        }
+    }
+
+    /// Loads a value of the type `ty` at `offset` from the address on the
+    /// stack.
+    pub fn push_load(&mut self, ty: ValType, offset: u32) {
+        match ty {
+            ValType::V128 => self.push_simd(Simd::Load(offset)),
+            ty => self.instructions.push(load(ty, offset)),
+        }
+    }
+}
+
+/// The number of bytes a value of the type `ty` takes in memory.
+pub(super) fn value_size(ty: ValType) -> u32 {
+    match ty {
+        ValType::I64 | ValType::F64 => 8,
+        ValType::V128 => 16,
+        _ => 4,
+    }
+}
+
+/// The area is only aligned to 4 bytes, like any data.
+fn memory_argument(offset: u32) -> MemArg {
+    MemArg {
+        offset,
+        align: 2,
+        memory_index: 0,
+    }
+}
+
+pub(super) fn store<'a>(ty: ValType, offset: u32) -> Instruction<'a> {
+    let memory_argument = memory_argument(offset);
+    match ty {
+        ValType::I64 => Instruction::I64Store(memory_argument),
+        ValType::F32 => Instruction::F32Store(memory_argument),
+        ValType::F64 => Instruction::F64Store(memory_argument),
+        _ => Instruction::I32Store(memory_argument),
+    }
+}
+
+pub(super) fn load<'a>(ty: ValType, offset: u32) -> Instruction<'a> {
+    let memory_argument = memory_argument(offset);
+    match ty {
+        ValType::I64 => Instruction::I64Load(memory_argument),
+        ValType::F32 => Instruction::F32Load(memory_argument),
+        ValType::F64 => Instruction::F64Load(memory_argument),
+        _ => Instruction::I32Load(memory_argument),
+    }
+}
@@ -0,0 +1,298 @@
+use wasm_encoder::{BlockType, Instruction};
+
+use super::{word, Runtime, FREE_LIST, HEADER, HEAP_BASE, HEAP_TOP};
+
+/// The locals are the `size`, then the `previous` and `current` blocks of
+/// the free list, and the `block` bumped off the heap and its `end`.
+pub(super) fn malloc<'a>() -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (size, previous, current, block, end) = (0, 1, 2, 3, 4);
+    vec![
+        // a size this close to 4GiB would wrap around when rounded up.
+        LocalGet(size),
+        I32Const(-16),
+        I32GtU,
+        If(BlockType::Empty),
+        I32Const(0),
+        Return,
+        End,
+        LocalGet(size),
+        I32Const(7),
+        I32Add,
+        I32Const(-8),
+        I32And,
+        LocalTee(size),
+        I32Eqz,
+        If(BlockType::Empty),
+        I32Const(8),
+        LocalSet(size),
+        End,
+        // the first freed block large enough.
+        I32Const(0),
+        LocalSet(previous),
+        I32Const(FREE_LIST),
+        I32Load(word(0)),
+        LocalSet(current),
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(current),
+        I32Eqz,
+        BrIf(1),
+        LocalGet(current),
+        I32Load(word(0)),
+        LocalGet(size),
+        I32GeU,
+        If(BlockType::Empty),
+        LocalGet(previous),
+        I32Eqz,
+        If(BlockType::Empty),
+        I32Const(FREE_LIST),
+        LocalGet(current),
+        I32Load(word(4)),
+        I32Store(word(0)),
+        Else,
+        LocalGet(previous),
+        LocalGet(current),
+        I32Load(word(4)),
+        I32Store(word(4)),
+        End,
+        // the block is referenced once, by the value it is given to.
+        LocalGet(current),
+        I32Const(1),
+        I32Store(word(4)),
+        LocalGet(current),
+        I32Const(HEADER),
+        I32Add,
+        Return,
+        End,
+        LocalGet(current),
+        LocalSet(previous),
+        LocalGet(current),
+        I32Load(word(4)),
+        LocalSet(current),
+        Br(0),
+        End,
+        End,
+        // or a new one at the top of the heap, growing the memory by the
+        // pages it is past its end.
+        I32Const(HEAP_TOP),
+        I32Load(word(0)),
+        LocalTee(block),
+        I32Const(HEADER),
+        I32Add,
+        LocalGet(size),
+        I32Add,
+        LocalTee(end),
+        LocalGet(block),
+        I32LtU,
+        If(BlockType::Empty),
+        I32Const(0),
+        Return,
+        End,
+        LocalGet(end),
+        MemorySize(0),
+        I32Const(16),
+        I32Shl,
+        I32GtU,
+        If(BlockType::Empty),
+        LocalGet(end),
+        MemorySize(0),
+        I32Const(16),
+        I32Shl,
+        I32Sub,
+        I32Const(0xffff),
+        I32Add,
+        I32Const(16),
+        I32ShrU,
+        MemoryGrow(0),
+        I32Const(-1),
+        I32Eq,
+        If(BlockType::Empty),
+        I32Const(0),
+        Return,
+        End,
+        End,
+        LocalGet(block),
+        LocalGet(size),
+        I32Store(word(0)),
+        LocalGet(block),
+        I32Const(1),
+        I32Store(word(4)),
+        I32Const(HEAP_TOP),
+        LocalGet(end),
+        I32Store(word(0)),
+        LocalGet(block),
+        I32Const(HEADER),
+        I32Add,
+        End,
+    ]
+}
+
+/// The locals are the `pointer` and its `block`.
+pub(super) fn free<'a>() -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (pointer, block) = (0, 1);
+    vec![
+        LocalGet(pointer),
+        I32Eqz,
+        If(BlockType::Empty),
+        Return,
+        End,
+        LocalGet(pointer),
+        I32Const(HEADER),
+        I32Sub,
+        LocalTee(block),
+        I32Const(FREE_LIST),
+        I32Load(word(0)),
+        I32Store(word(4)),
+        I32Const(FREE_LIST),
+        LocalGet(block),
+        I32Store(word(0)),
+        End,
+    ]
+}
+
+/// The locals are the `pointer` and its `block`.
+pub(super) fn retain<'a>() -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (pointer, block) = (0, 1);
+    vec![
+        // `0` and the literals are below the heap.
+        LocalGet(pointer),
+        I32Const(HEAP_BASE),
+        I32Load(word(0)),
+        I32GeU,
+        If(BlockType::Empty),
+        LocalGet(pointer),
+        I32Const(HEADER),
+        I32Sub,
+        LocalTee(block),
+        LocalGet(block),
+        I32Load(word(4)),
+        I32Const(1),
+        I32Add,
+        I32Store(word(4)),
+        End,
+        LocalGet(pointer),
+        End,
+    ]
+}
+
+/// The locals are the `pointer`, its `block` and the `count` of the
+/// references left.
+pub(super) fn release<'a>(runtime: Runtime) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (pointer, block, count) = (0, 1, 2);
+    vec![
+        LocalGet(pointer),
+        I32Const(HEAP_BASE),
+        I32Load(word(0)),
+        I32LtU,
+        If(BlockType::Empty),
+        Return,
+        End,
+        LocalGet(pointer),
+        I32Const(HEADER),
+        I32Sub,
+        LocalTee(block),
+        LocalGet(block),
+        I32Load(word(4)),
+        I32Const(1),
+        I32Sub,
+        LocalTee(count),
+        I32Store(word(4)),
+        LocalGet(count),
+        I32Eqz,
+        If(BlockType::Empty),
+        LocalGet(pointer),
+        Call(runtime.free),
+        End,
+        End,
+    ]
+}
+
+/// The locals are the `pointer` and the `size`, then the size of the `old`
+/// block, the `new` one and the `offset` of the word being copied, unless
+/// `memory.copy` copies it with `bulk_memory`.
+pub(super) fn realloc<'a>(runtime: Runtime, bulk_memory: bool) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (pointer, size, old, new, offset) = (0, 1, 2, 3, 4);
+    let mut instructions = vec![
+        LocalGet(pointer),
+        I32Eqz,
+        If(BlockType::Empty),
+        LocalGet(size),
+        Call(runtime.malloc),
+        Return,
+        End,
+        LocalGet(pointer),
+        I32Const(HEADER),
+        I32Sub,
+        I32Load(word(0)),
+        LocalTee(old),
+        LocalGet(size),
+        I32GeU,
+        If(BlockType::Empty),
+        LocalGet(pointer),
+        Return,
+        End,
+        LocalGet(size),
+        Call(runtime.malloc),
+        LocalTee(new),
+        I32Eqz,
+        If(BlockType::Empty),
+        I32Const(0),
+        Return,
+        End,
+    ];
+    let copy = if bulk_memory {
+        vec![
+            LocalGet(new),
+            LocalGet(pointer),
+            LocalGet(old),
+            MemoryCopy { src: 0, dst: 0 },
+        ]
+    } else {
+        copy_words(pointer, new, old, offset)
+    };
+    instructions.extend(copy);
+    instructions.extend(vec![
+        LocalGet(pointer),
+        Call(runtime.free),
+        LocalGet(new),
+        End,
+    ]);
+    instructions
+}
+
+/// Copies the `size` bytes at `from` to `to` by words, with `offset` for
+/// the word being copied.
+fn copy_words<'a>(from: u32, to: u32, size: u32, offset: u32) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    vec![
+        // the size of a block is a multiple of 8, so it is copied by words.
+        I32Const(0),
+        LocalSet(offset),
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(offset),
+        LocalGet(size),
+        I32GeU,
+        BrIf(1),
+        LocalGet(to),
+        LocalGet(offset),
+        I32Add,
+        LocalGet(from),
+        LocalGet(offset),
+        I32Add,
+        I32Load(word(0)),
+        I32Store(word(0)),
+        LocalGet(offset),
+        I32Const(4),
+        I32Add,
+        LocalSet(offset),
+        Br(0),
+        End,
+        End,
+    ]
+}
@@ -0,0 +1,393 @@
+use wasm_encoder::{BlockType, Instruction, ValType};
+
+use super::{lock::Body, word, Runtime, HEADER};
+use crate::codegen::{Atomic, Context};
+
+/// The indices of the routines of the runtime passing messages between
+/// threads, which only a module using them defines. A channel is the
+/// address of the number of slots of its ring, as a word, with the index
+/// of its oldest value, the number of values it holds, the address of the
+/// ring and its capacity, or `0` if it is unbounded, behind it. The ring of
+/// an unbounded channel is reallocated twice as large when a value is sent
+/// to it while it is full.
+///
+/// The threads holding a channel count their references to it in the
+/// header of its block, while holding the lock, and the last one frees it
+/// with its ring.
+///
+/// Every routine takes the lock of all the channels before it looks at
+/// one, and a change of any of them bumps a counter the routines waiting
+/// for one wait on, like a condition variable, so `select` can wait for
+/// either of its channels.
+#[derive(Debug, Clone, Copy)]
+pub struct Channels {
+    /// `rano_channel(capacity: i32): i32`, which gives an unbounded channel
+    /// for `0`, and traps unless `capacity` is small enough to allocate.
+    pub channel: u32,
+    /// `rano_send(channel: i32, value: i32): i32`, which waits while the
+    /// channel is full and gives `0`.
+    pub send: u32,
+    /// `rano_receive(channel: i32): i32`, which waits until the channel
+    /// holds a value and takes the oldest.
+    pub receive: u32,
+    /// `rano_select(first: i32, second: i32): i32`, which waits until
+    /// either channel holds a value and gives the one which does, the
+    /// first if both do.
+    pub select: u32,
+    /// `rano_share(channel: i32): i32`, which counts one more reference to
+    /// the channel and gives it back.
+    pub share: u32,
+    /// `rano_close(channel: i32): i32`, which counts one less, frees the
+    /// channel if that was the last and gives `0`.
+    pub close: u32,
+}
+
+/// The slots the ring of an unbounded channel starts with.
+const UNBOUNDED_SLOTS: i32 = 4;
+
+/// The words all the channels share, in the data segments.
+#[derive(Debug, Clone, Copy)]
+struct Shared {
+    /// `1` while a routine holds the lock, `0` otherwise.
+    lock: i32,
+    /// Bumped by every change of a channel, while holding the lock.
+    changes: i32,
+}
+
+impl<'a> Body<'a> {
+    /// Counts a change of a channel, lets go of the lock and wakes every
+    /// routine waiting for one.
+    fn changed(&mut self, shared: Shared) {
+        use Instruction::*;
+        self.extend(vec![I32Const(shared.changes), I32Const(1)]);
+        self.atomic(Atomic::Add);
+        self.instructions.push(Drop);
+        self.unlock(shared.lock);
+        self.extend(vec![I32Const(shared.changes), I32Const(-1)]);
+        self.atomic(Atomic::Notify);
+        self.instructions.push(Drop);
+    }
+
+    /// Lets go of the lock and waits for the next change of a channel, then
+    /// starts the loop the body is in over. The local `seen` is where the
+    /// number of changes is kept, read before letting go, so a change made
+    /// after it isn't missed.
+    fn wait(&mut self, shared: Shared, seen: u32) {
+        use Instruction::*;
+        self.instructions.push(I32Const(shared.changes));
+        self.atomic(Atomic::Load);
+        self.instructions.push(LocalSet(seen));
+        self.unlock(shared.lock);
+        self.extend(vec![I32Const(shared.changes), LocalGet(seen), I64Const(-1)]);
+        self.atomic(Atomic::Wait);
+        self.extend(vec![Drop, Br(0)]);
+    }
+}
+
+impl<'a> Context<'a> {
+    /// Declares and implements the routines of the channels, and the words
+    /// they share.
+    pub(in crate::codegen) fn emit_channels(&mut self) -> Channels {
+        let runtime = self.runtime();
+        let shared = Shared {
+            lock: self.create_data(vec![0; 4]),
+            changes: self.create_data(vec![0; 4]),
+        };
+        let unary_type = self.declare_function_type(vec![ValType::I32], vec![ValType::I32]);
+        let binary_type =
+            self.declare_function_type(vec![ValType::I32, ValType::I32], vec![ValType::I32]);
+        let channels = Channels {
+            channel: self.declare_function(unary_type),
+            send: self.declare_function(binary_type),
+            receive: self.declare_function(unary_type),
+            select: self.declare_function(binary_type),
+            share: self.declare_function(unary_type),
+            close: self.declare_function(unary_type),
+        };
+        let routines = vec![
+            (channels.channel, "rano_channel", 3, channel(runtime)),
+            (channels.send, "rano_send", 2, send(runtime, shared)),
+            (channels.receive, "rano_receive", 2, receive(shared)),
+            (channels.select, "rano_select", 1, select(shared)),
+            (channels.share, "rano_share", 0, share(shared)),
+            (channels.close, "rano_close", 1, close(runtime, shared)),
+        ];
+        for (id, name, locals, body) in routines {
+            self.implement_body(id, vec![ValType::I32; locals], body);
+            self.name_function(id, name);
+        }
+        channels
+    }
+}
+
+/// The locals are the number of `slots` of the ring, the `ring` and the
+/// address of the `channel`.
+fn channel<'a>(runtime: Runtime) -> Body<'a> {
+    use Instruction::*;
+    let (capacity, slots, ring, channel) = (0, 1, 2, 3);
+    let mut body = Body::default();
+    body.extend(vec![
+        LocalGet(capacity),
+        I32Const(0x1000_0000),
+        I32GeU,
+        If(BlockType::Empty),
+        Unreachable,
+        End,
+        LocalGet(capacity),
+        I32Const(UNBOUNDED_SLOTS),
+        LocalGet(capacity),
+        Select,
+        LocalTee(slots),
+        I32Const(2),
+        I32Shl,
+        Call(runtime.malloc),
+        LocalTee(ring),
+        I32Eqz,
+        If(BlockType::Empty),
+        Unreachable,
+        End,
+        I32Const(20),
+        Call(runtime.malloc),
+        LocalTee(channel),
+        I32Eqz,
+        If(BlockType::Empty),
+        Unreachable,
+        End,
+        LocalGet(channel),
+        LocalGet(slots),
+        I32Store(word(0)),
+        LocalGet(channel),
+        I32Const(0),
+        I32Store(word(4)),
+        LocalGet(channel),
+        I32Const(0),
+        I32Store(word(8)),
+        LocalGet(channel),
+        LocalGet(ring),
+        I32Store(word(12)),
+        LocalGet(channel),
+        LocalGet(capacity),
+        I32Store(word(16)),
+        LocalGet(channel),
+        End,
+    ]);
+    body
+}
+
+/// The locals are the number of changes seen before waiting and the `ring`
+/// an unbounded channel grows into.
+fn send<'a>(runtime: Runtime, shared: Shared) -> Body<'a> {
+    use Instruction::*;
+    let (channel, value, seen, ring) = (0, 1, 2, 3);
+    let mut body = Body::default();
+    body.instructions.push(Loop(BlockType::Empty));
+    body.lock(shared.lock);
+    body.extend(vec![
+        // an unbounded channel which is full grows, with the values in the
+        // slots before the oldest moved behind the ones it had.
+        LocalGet(channel),
+        I32Load(word(8)),
+        LocalGet(channel),
+        I32Load(word(0)),
+        I32GeU,
+        LocalGet(channel),
+        I32Load(word(16)),
+        I32Eqz,
+        I32And,
+        If(BlockType::Empty),
+        LocalGet(channel),
+        I32Load(word(12)),
+        LocalGet(channel),
+        I32Load(word(0)),
+        I32Const(3),
+        I32Shl,
+        Call(runtime.realloc),
+        LocalTee(ring),
+        I32Eqz,
+        If(BlockType::Empty),
+    ]);
+    body.unlock(shared.lock);
+    body.extend(vec![
+        Unreachable,
+        End,
+        LocalGet(ring),
+        LocalGet(channel),
+        I32Load(word(0)),
+        I32Const(2),
+        I32Shl,
+        I32Add,
+        LocalGet(ring),
+        LocalGet(channel),
+        I32Load(word(4)),
+        I32Const(2),
+        I32Shl,
+        Call(runtime.copy),
+        LocalGet(channel),
+        LocalGet(ring),
+        I32Store(word(12)),
+        LocalGet(channel),
+        LocalGet(channel),
+        I32Load(word(0)),
+        I32Const(1),
+        I32Shl,
+        I32Store(word(0)),
+        End,
+        LocalGet(channel),
+        I32Load(word(8)),
+        LocalGet(channel),
+        I32Load(word(0)),
+        I32LtU,
+        If(BlockType::Empty),
+        // the slot behind the newest value.
+        LocalGet(channel),
+        I32Load(word(12)),
+        LocalGet(channel),
+        I32Load(word(4)),
+        LocalGet(channel),
+        I32Load(word(8)),
+        I32Add,
+        LocalGet(channel),
+        I32Load(word(0)),
+        I32RemU,
+        I32Const(2),
+        I32Shl,
+        I32Add,
+        LocalGet(value),
+        I32Store(word(0)),
+        LocalGet(channel),
+        LocalGet(channel),
+        I32Load(word(8)),
+        I32Const(1),
+        I32Add,
+        I32Store(word(8)),
+    ]);
+    body.changed(shared);
+    body.extend(vec![I32Const(0), Return, End]);
+    body.wait(shared, seen);
+    body.extend(vec![End, Unreachable, End]);
+    body
+}
+
+/// The locals are the number of changes seen before waiting and the value
+/// taken.
+fn receive<'a>(shared: Shared) -> Body<'a> {
+    use Instruction::*;
+    let (channel, seen, value) = (0, 1, 2);
+    let mut body = Body::default();
+    body.instructions.push(Loop(BlockType::Empty));
+    body.lock(shared.lock);
+    body.extend(vec![
+        LocalGet(channel),
+        I32Load(word(8)),
+        If(BlockType::Empty),
+        LocalGet(channel),
+        I32Load(word(12)),
+        LocalGet(channel),
+        I32Load(word(4)),
+        I32Const(2),
+        I32Shl,
+        I32Add,
+        I32Load(word(0)),
+        LocalSet(value),
+        LocalGet(channel),
+        LocalGet(channel),
+        I32Load(word(4)),
+        I32Const(1),
+        I32Add,
+        LocalGet(channel),
+        I32Load(word(0)),
+        I32RemU,
+        I32Store(word(4)),
+        LocalGet(channel),
+        LocalGet(channel),
+        I32Load(word(8)),
+        I32Const(1),
+        I32Sub,
+        I32Store(word(8)),
+    ]);
+    body.changed(shared);
+    body.extend(vec![LocalGet(value), Return, End]);
+    body.wait(shared, seen);
+    body.extend(vec![End, Unreachable, End]);
+    body
+}
+
+/// The local is the number of changes seen before waiting.
+fn select<'a>(shared: Shared) -> Body<'a> {
+    use Instruction::*;
+    let (first, second, seen) = (0, 1, 2);
+    let mut body = Body::default();
+    body.instructions.push(Loop(BlockType::Empty));
+    body.lock(shared.lock);
+    for channel in [first, second] {
+        body.extend(vec![
+            LocalGet(channel),
+            I32Load(word(8)),
+            If(BlockType::Empty),
+        ]);
+        body.unlock(shared.lock);
+        body.extend(vec![LocalGet(channel), Return, End]);
+    }
+    body.wait(shared, seen);
+    body.extend(vec![End, Unreachable, End]);
+    body
+}
+
+fn share<'a>(shared: Shared) -> Body<'a> {
+    use Instruction::*;
+    let channel = 0;
+    let mut body = Body::default();
+    body.lock(shared.lock);
+    body.extend(vec![
+        LocalGet(channel),
+        I32Const(HEADER),
+        I32Sub,
+        LocalGet(channel),
+        I32Const(HEADER),
+        I32Sub,
+        I32Load(word(4)),
+        I32Const(1),
+        I32Add,
+        I32Store(word(4)),
+    ]);
+    body.unlock(shared.lock);
+    body.extend(vec![LocalGet(channel), End]);
+    body
+}
+
+/// The local is the `count` of the references left. No other thread holds
+/// the channel once it is `0`, so it is freed without the lock.
+fn close<'a>(runtime: Runtime, shared: Shared) -> Body<'a> {
+    use Instruction::*;
+    let (channel, count) = (0, 1);
+    let mut body = Body::default();
+    body.lock(shared.lock);
+    body.extend(vec![
+        LocalGet(channel),
+        I32Const(HEADER),
+        I32Sub,
+        LocalGet(channel),
+        I32Const(HEADER),
+        I32Sub,
+        I32Load(word(4)),
+        I32Const(1),
+        I32Sub,
+        LocalTee(count),
+        I32Store(word(4)),
+    ]);
+    body.unlock(shared.lock);
+    body.extend(vec![
+        LocalGet(count),
+        I32Eqz,
+        If(BlockType::Empty),
+        LocalGet(channel),
+        I32Load(word(12)),
+        Call(runtime.free),
+        LocalGet(channel),
+        Call(runtime.free),
+        End,
+        I32Const(0),
+        End,
+    ]);
+    body
+}
@@ -0,0 +1,831 @@
+//! A decimal is a number kept as its decimal digits, which the floats are
+//! converted to and from exactly by shifting it by powers of two. It is a
+//! block of the memory with the number of digits `nd`, the position of the
+//! decimal point `dp` relative to the first digit, whether digits past the
+//! `CAPACITY` were dropped, and the digits, one to a byte, most significant
+//! first: `0.d0 d1 ... d(nd - 1) * 10^dp`. Only the significant digits are
+//! kept, so an `nd` of `0` is zero.
+
+use wasm_encoder::{BlockType, Instruction, ValType};
+
+use super::{byte, word};
+
+const ND: u32 = 0;
+const DP: u32 = 4;
+const TRUNCATED: u32 = 8;
+const DIGITS: u32 = 12;
+/// The most digits a decimal keeps, which is more than a float needs to
+/// tell it apart from the floats next to it.
+const CAPACITY: i32 = 800;
+/// The bytes a decimal takes.
+pub(super) const DECIMAL_SIZE: usize = DIGITS as usize + CAPACITY as usize;
+/// The most bits a decimal is shifted by at once, which keeps what is
+/// carried between the digits in 64 bits.
+const MAX_SHIFT: i32 = 60;
+
+/// The indices of the routines of the decimals.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Decimal {
+    /// `trim(decimal)`, which drops the trailing zeros.
+    pub trim: u32,
+    /// `assign(decimal, value: i64)`, which sets a decimal to an unsigned
+    /// integer.
+    pub assign: u32,
+    /// `left_shift(decimal, bits)`, which multiplies a decimal by
+    /// `2^bits`, `bits` being at most `MAX_SHIFT`.
+    pub left_shift: u32,
+    /// `right_shift(decimal, bits)`, which divides a decimal by `2^bits`.
+    pub right_shift: u32,
+    /// `shift(decimal, bits)`, which multiplies a decimal by `2^bits` for
+    /// any `bits`, negative or not.
+    pub shift: u32,
+    /// `should_round_up(decimal, digits): i32`, whether a decimal is
+    /// rounded up to its first `digits` digits, to the even one if it is
+    /// halfway.
+    pub should_round_up: u32,
+    /// `round_up(decimal, digits)`.
+    pub round_up: u32,
+    /// `round_down(decimal, digits)`.
+    pub round_down: u32,
+    /// `rounded_integer(decimal): i64`, a decimal rounded to an integer,
+    /// or all ones if it doesn't fit in 64 bits.
+    pub rounded_integer: u32,
+}
+
+/// The digit of the decimal in the local `decimal` at the index on the
+/// stack.
+fn digit<'a>(decimal: u32) -> Vec<Instruction<'a>> {
+    vec![
+        Instruction::LocalGet(decimal),
+        Instruction::I32Add,
+        Instruction::I32Load8_U(byte(DIGITS)),
+    ]
+}
+
+/// Sets the digit at the index in the local `index` to the `i64` in the
+/// local `value`, or notes the decimal is truncated if it is past the
+/// `CAPACITY` and not `0`.
+fn put_digit<'a>(decimal: u32, index: u32, value: u32) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    vec![
+        LocalGet(index),
+        I32Const(CAPACITY),
+        I32LtS,
+        If(BlockType::Empty),
+        LocalGet(decimal),
+        LocalGet(index),
+        I32Add,
+        LocalGet(value),
+        I32WrapI64,
+        I32Store8(byte(DIGITS)),
+        Else,
+        LocalGet(value),
+        I64Eqz,
+        I32Eqz,
+        If(BlockType::Empty),
+        LocalGet(decimal),
+        I32Const(1),
+        I32Store(word(TRUNCATED)),
+        End,
+        End,
+    ]
+}
+
+/// Returns from a rounding routine if the local `digits` isn't one of the
+/// digits of the decimal, which leaves it as it is.
+fn return_outside<'a>(decimal: u32, digits: u32) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    vec![
+        LocalGet(digits),
+        I32Const(0),
+        I32LtS,
+        LocalGet(digits),
+        LocalGet(decimal),
+        I32Load(word(ND)),
+        I32GeS,
+        I32Or,
+        If(BlockType::Empty),
+        Return,
+        End,
+    ]
+}
+
+/// The local `local` plus `step`.
+fn step<'a>(local: u32, step: i32) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    vec![LocalGet(local), I32Const(step), I32Add, LocalSet(local)]
+}
+
+/// Declares and implements the routines of the decimals.
+pub(super) fn emit_decimal(context: &mut super::Context) -> Decimal {
+    let unary = context.declare_function_type(vec![ValType::I32], vec![]);
+    let binary = context.declare_function_type(vec![ValType::I32; 2], vec![]);
+    let assign_type = context.declare_function_type(vec![ValType::I32, ValType::I64], vec![]);
+    let test_type = context.declare_function_type(vec![ValType::I32; 2], vec![ValType::I32]);
+    let integer_type = context.declare_function_type(vec![ValType::I32], vec![ValType::I64]);
+    let decimal = Decimal {
+        trim: context.declare_function(unary),
+        assign: context.declare_function(assign_type),
+        left_shift: context.declare_function(binary),
+        right_shift: context.declare_function(binary),
+        shift: context.declare_function(binary),
+        should_round_up: context.declare_function(test_type),
+        round_up: context.declare_function(binary),
+        round_down: context.declare_function(binary),
+        rounded_integer: context.declare_function(integer_type),
+    };
+    let (i32, i64) = (ValType::I32, ValType::I64);
+    context.implement_function(decimal.trim, vec![i32], trim());
+    context.implement_function(decimal.assign, vec![i32, i64], assign(decimal));
+    context.implement_function(
+        decimal.left_shift,
+        vec![i32, i32, i32, i64, i64, i64, i64],
+        left_shift(decimal),
+    );
+    context.implement_function(
+        decimal.right_shift,
+        vec![i32, i32, i32, i64, i64, i64, i64],
+        right_shift(decimal),
+    );
+    context.implement_function(decimal.shift, vec![], shift(decimal));
+    context.implement_function(decimal.should_round_up, vec![i32], should_round_up());
+    context.implement_function(decimal.round_up, vec![i32], round_up());
+    context.implement_function(decimal.round_down, vec![], round_down(decimal));
+    context.implement_function(
+        decimal.rounded_integer,
+        vec![i32, i32, i64],
+        rounded_integer(decimal),
+    );
+    for (name, id) in &[
+        ("rano_decimal_trim", decimal.trim),
+        ("rano_decimal_assign", decimal.assign),
+        ("rano_decimal_left_shift", decimal.left_shift),
+        ("rano_decimal_right_shift", decimal.right_shift),
+        ("rano_decimal_shift", decimal.shift),
+        ("rano_decimal_should_round_up", decimal.should_round_up),
+        ("rano_decimal_round_up", decimal.round_up),
+        ("rano_decimal_round_down", decimal.round_down),
+        ("rano_decimal_rounded_integer", decimal.rounded_integer),
+    ] {
+        context.name_function(*id, *name);
+    }
+    decimal
+}
+
+/// The locals are the `decimal` and its number of digits `nd`.
+fn trim<'a>() -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (decimal, nd) = (0, 1);
+    vec![
+        LocalGet(decimal),
+        I32Load(word(ND)),
+        LocalSet(nd),
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(nd),
+        I32Eqz,
+        BrIf(1),
+        LocalGet(decimal),
+        LocalGet(nd),
+        I32Add,
+        I32Load8_U(byte(DIGITS - 1)),
+        BrIf(1),
+        LocalGet(nd),
+        I32Const(1),
+        I32Sub,
+        LocalSet(nd),
+        Br(0),
+        End,
+        End,
+        LocalGet(decimal),
+        LocalGet(nd),
+        I32Store(word(ND)),
+        LocalGet(nd),
+        I32Eqz,
+        If(BlockType::Empty),
+        LocalGet(decimal),
+        I32Const(0),
+        I32Store(word(DP)),
+        End,
+        End,
+    ]
+}
+
+/// The locals are the `decimal`, the `value`, its number of digits `nd`
+/// and what is `left` of it while they are counted. The digits are written
+/// from the last one.
+fn assign<'a>(decimal: Decimal) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (this, value, nd, left) = (0, 1, 2, 3);
+    let mut instructions = vec![
+        LocalGet(value),
+        LocalSet(left),
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(left),
+        I64Eqz,
+        BrIf(1),
+        LocalGet(left),
+        I64Const(10),
+        I64DivU,
+        LocalSet(left),
+    ];
+    instructions.extend(step(nd, 1));
+    instructions.extend(vec![
+        Br(0),
+        End,
+        End,
+        LocalGet(this),
+        LocalGet(nd),
+        I32Store(word(ND)),
+        LocalGet(this),
+        LocalGet(nd),
+        I32Store(word(DP)),
+        LocalGet(this),
+        I32Const(0),
+        I32Store(word(TRUNCATED)),
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(nd),
+        I32Eqz,
+        BrIf(1),
+    ]);
+    instructions.extend(step(nd, -1));
+    instructions.extend(vec![
+        LocalGet(this),
+        LocalGet(nd),
+        I32Add,
+        LocalGet(value),
+        I64Const(10),
+        I64RemU,
+        I32WrapI64,
+        I32Store8(byte(DIGITS)),
+        LocalGet(value),
+        I64Const(10),
+        I64DivU,
+        LocalSet(value),
+        Br(0),
+        End,
+        End,
+        LocalGet(this),
+        Call(decimal.trim),
+        End,
+    ]);
+    instructions
+}
+
+/// The locals are the `decimal`, the `bits` it is shifted by, the index
+/// `read` from and the one `written` to, its number of digits `nd`, the
+/// number `carried` between the digits, the `quotient` of that by ten, the
+/// `value` of a digit and the bits as an `i64`, `shift`.
+///
+/// The digits are read from the last one, and written where they end up,
+/// which is as many places further as the carry out of the first digit
+/// has digits. That is found first by doing the shift without writing.
+fn left_shift<'a>(decimal: Decimal) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (this, bits, read, written, nd) = (0, 1, 2, 3, 4);
+    let (carried, quotient, value, shift) = (5, 6, 7, 8);
+    // `carried` plus the digit at `read`, shifted.
+    let add_digit = |instructions: &mut Vec<Instruction<'a>>| {
+        instructions.extend(vec![LocalGet(carried), LocalGet(read)]);
+        instructions.extend(digit(this));
+        instructions.extend(vec![
+            I64ExtendI32U,
+            LocalGet(shift),
+            I64Shl,
+            I64Add,
+            LocalSet(carried),
+        ]);
+    };
+    // writes `carried` modulo ten before `written`, keeping the rest.
+    let put_remainder = |instructions: &mut Vec<Instruction<'a>>| {
+        instructions.extend(vec![
+            LocalGet(carried),
+            I64Const(10),
+            I64DivU,
+            LocalSet(quotient),
+            LocalGet(carried),
+            LocalGet(quotient),
+            I64Const(10),
+            I64Mul,
+            I64Sub,
+            LocalSet(value),
+        ]);
+        instructions.extend(step(written, -1));
+        instructions.extend(put_digit(this, written, value));
+        instructions.extend(vec![LocalGet(quotient), LocalSet(carried)]);
+    };
+
+    let mut instructions = vec![
+        LocalGet(bits),
+        I64ExtendI32U,
+        LocalSet(shift),
+        LocalGet(this),
+        I32Load(word(ND)),
+        LocalTee(nd),
+        LocalSet(read),
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(read),
+        I32Eqz,
+        BrIf(1),
+    ];
+    instructions.extend(step(read, -1));
+    add_digit(&mut instructions);
+    instructions.extend(vec![
+        LocalGet(carried),
+        I64Const(10),
+        I64DivU,
+        LocalSet(carried),
+        Br(0),
+        End,
+        End,
+        // the digits the carry out of the first digit has.
+        LocalGet(nd),
+        LocalSet(written),
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(carried),
+        I64Eqz,
+        BrIf(1),
+        LocalGet(carried),
+        I64Const(10),
+        I64DivU,
+        LocalSet(carried),
+    ]);
+    instructions.extend(step(written, 1));
+    instructions.extend(vec![
+        Br(0),
+        End,
+        End,
+        // the decimal point moves as far as the digits do.
+        LocalGet(this),
+        LocalGet(this),
+        I32Load(word(DP)),
+        LocalGet(written),
+        LocalGet(nd),
+        I32Sub,
+        I32Add,
+        I32Store(word(DP)),
+        LocalGet(nd),
+        LocalSet(read),
+        // the number of digits, which is no more than the capacity.
+        LocalGet(this),
+        LocalGet(written),
+        I32Const(CAPACITY),
+        LocalGet(written),
+        I32Const(CAPACITY),
+        I32LtS,
+        Select,
+        I32Store(word(ND)),
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(read),
+        I32Eqz,
+        BrIf(1),
+    ]);
+    instructions.extend(step(read, -1));
+    add_digit(&mut instructions);
+    put_remainder(&mut instructions);
+    instructions.extend(vec![
+        Br(0),
+        End,
+        End,
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(carried),
+        I64Eqz,
+        BrIf(1),
+    ]);
+    put_remainder(&mut instructions);
+    instructions.extend(vec![
+        Br(0),
+        End,
+        End,
+        LocalGet(this),
+        Call(decimal.trim),
+        End,
+    ]);
+    instructions
+}
+
+/// The locals are the `decimal`, the `bits` it is shifted by, the index
+/// `read` from and the one `written` to, its number of digits `nd`, the
+/// number `carried` between the digits, the `mask` of the bits shifted
+/// out, the `value` of a digit and the bits as an `i64`, `shift`.
+///
+/// Enough of the first digits are read for the first digit of the result,
+/// and from then on one digit is written for each digit read, and then
+/// for each digit left in what is carried.
+fn right_shift<'a>(decimal: Decimal) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (this, bits, read, written, nd) = (0, 1, 2, 3, 4);
+    let (carried, mask, value, shift) = (5, 6, 7, 8);
+    // leaves if the first digit of the result is in what is carried.
+    let leave_if_shifted = |depth: u32| {
+        vec![
+            LocalGet(carried),
+            LocalGet(shift),
+            I64ShrU,
+            I64Eqz,
+            I32Eqz,
+            BrIf(depth),
+        ]
+    };
+    // writes the digit shifted out of what is carried.
+    let put_shifted = |instructions: &mut Vec<Instruction<'a>>| {
+        instructions.extend(vec![
+            LocalGet(carried),
+            LocalGet(shift),
+            I64ShrU,
+            LocalSet(value),
+        ]);
+        instructions.extend(put_digit(this, written, value));
+        instructions.extend(step(written, 1));
+    };
+
+    let mut instructions = vec![
+        LocalGet(bits),
+        I64ExtendI32U,
+        LocalSet(shift),
+        LocalGet(this),
+        I32Load(word(ND)),
+        LocalSet(nd),
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+    ];
+    instructions.extend(leave_if_shifted(1));
+    instructions.extend(vec![
+        LocalGet(read),
+        LocalGet(nd),
+        I32GeS,
+        If(BlockType::Empty),
+        // out of digits, the rest of them are zeros.
+        LocalGet(carried),
+        I64Eqz,
+        If(BlockType::Empty),
+        LocalGet(this),
+        I32Const(0),
+        I32Store(word(ND)),
+        Return,
+        End,
+        Loop(BlockType::Empty),
+    ]);
+    instructions.extend(leave_if_shifted(3));
+    instructions.extend(vec![
+        LocalGet(carried),
+        I64Const(10),
+        I64Mul,
+        LocalSet(carried),
+    ]);
+    instructions.extend(step(read, 1));
+    instructions.extend(vec![
+        Br(0),
+        End,
+        End,
+        LocalGet(carried),
+        I64Const(10),
+        I64Mul,
+        LocalGet(read),
+    ]);
+    instructions.extend(digit(this));
+    instructions.extend(vec![I64ExtendI32U, I64Add, LocalSet(carried)]);
+    instructions.extend(step(read, 1));
+    instructions.extend(vec![
+        Br(0),
+        End,
+        End,
+        LocalGet(this),
+        LocalGet(this),
+        I32Load(word(DP)),
+        LocalGet(read),
+        I32Sub,
+        I32Const(1),
+        I32Add,
+        I32Store(word(DP)),
+        I64Const(1),
+        LocalGet(shift),
+        I64Shl,
+        I64Const(1),
+        I64Sub,
+        LocalSet(mask),
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(read),
+        LocalGet(nd),
+        I32GeS,
+        BrIf(1),
+    ]);
+    // `written` is behind `read`, so the digit is read after one is
+    // written.
+    put_shifted(&mut instructions);
+    instructions.extend(vec![
+        LocalGet(carried),
+        LocalGet(mask),
+        I64And,
+        I64Const(10),
+        I64Mul,
+        LocalGet(read),
+    ]);
+    instructions.extend(digit(this));
+    instructions.extend(vec![I64ExtendI32U, I64Add, LocalSet(carried)]);
+    instructions.extend(step(read, 1));
+    instructions.extend(vec![
+        Br(0),
+        End,
+        End,
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(carried),
+        I64Eqz,
+        BrIf(1),
+    ]);
+    put_shifted(&mut instructions);
+    instructions.extend(vec![
+        LocalGet(carried),
+        LocalGet(mask),
+        I64And,
+        I64Const(10),
+        I64Mul,
+        LocalSet(carried),
+        Br(0),
+        End,
+        End,
+        LocalGet(this),
+        LocalGet(written),
+        I32Const(CAPACITY),
+        LocalGet(written),
+        I32Const(CAPACITY),
+        I32LtS,
+        Select,
+        I32Store(word(ND)),
+        LocalGet(this),
+        Call(decimal.trim),
+        End,
+    ]);
+    instructions
+}
+
+/// The locals are the `decimal` and the `bits` it is shifted by, which it
+/// is shifted by at most `MAX_SHIFT` at a time.
+fn shift<'a>(decimal: Decimal) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (this, bits) = (0, 1);
+    vec![
+        LocalGet(this),
+        I32Load(word(ND)),
+        I32Eqz,
+        If(BlockType::Empty),
+        Return,
+        End,
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(bits),
+        I32Const(MAX_SHIFT),
+        I32LeS,
+        BrIf(1),
+        LocalGet(this),
+        I32Const(MAX_SHIFT),
+        Call(decimal.left_shift),
+        LocalGet(bits),
+        I32Const(MAX_SHIFT),
+        I32Sub,
+        LocalSet(bits),
+        Br(0),
+        End,
+        End,
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(bits),
+        I32Const(-MAX_SHIFT),
+        I32GeS,
+        BrIf(1),
+        LocalGet(this),
+        I32Const(MAX_SHIFT),
+        Call(decimal.right_shift),
+        LocalGet(bits),
+        I32Const(MAX_SHIFT),
+        I32Add,
+        LocalSet(bits),
+        Br(0),
+        End,
+        End,
+        LocalGet(bits),
+        I32Const(0),
+        I32GtS,
+        If(BlockType::Empty),
+        LocalGet(this),
+        LocalGet(bits),
+        Call(decimal.left_shift),
+        Else,
+        LocalGet(bits),
+        I32Const(0),
+        I32LtS,
+        If(BlockType::Empty),
+        LocalGet(this),
+        I32Const(0),
+        LocalGet(bits),
+        I32Sub,
+        Call(decimal.right_shift),
+        End,
+        End,
+        End,
+    ]
+}
+
+/// The locals are the `decimal`, the number of `digits` it is rounded to
+/// and the `value` of the first digit dropped.
+fn should_round_up<'a>() -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (this, digits, value) = (0, 1, 2);
+    let mut instructions = vec![
+        LocalGet(digits),
+        I32Const(0),
+        I32LtS,
+        LocalGet(digits),
+        LocalGet(this),
+        I32Load(word(ND)),
+        I32GeS,
+        I32Or,
+        If(BlockType::Empty),
+        I32Const(0),
+        Return,
+        End,
+        LocalGet(digits),
+    ];
+    instructions.extend(digit(this));
+    instructions.extend(vec![
+        LocalTee(value),
+        I32Const(5),
+        I32Eq,
+        LocalGet(digits),
+        I32Const(1),
+        I32Add,
+        LocalGet(this),
+        I32Load(word(ND)),
+        I32Eq,
+        I32And,
+        If(BlockType::Empty),
+        // exactly halfway, unless digits were dropped, which rounds to the
+        // even one.
+        LocalGet(this),
+        I32Load(word(TRUNCATED)),
+        If(BlockType::Empty),
+        I32Const(1),
+        Return,
+        End,
+        LocalGet(digits),
+        I32Const(0),
+        I32GtS,
+        LocalGet(this),
+        LocalGet(digits),
+        I32Add,
+        I32Load8_U(byte(DIGITS - 1)),
+        I32Const(1),
+        I32And,
+        I32And,
+        Return,
+        End,
+        LocalGet(value),
+        I32Const(5),
+        I32GeU,
+        End,
+    ]);
+    instructions
+}
+
+/// The locals are the `decimal`, the number of `digits` it is rounded to
+/// and the `index` of the digit incremented, which is the last one below
+/// `9`.
+fn round_up<'a>() -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (this, digits, index) = (0, 1, 2);
+    let mut instructions = return_outside(this, digits);
+    instructions.extend(vec![
+        LocalGet(digits),
+        LocalSet(index),
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(index),
+        I32Eqz,
+        BrIf(1),
+    ]);
+    instructions.extend(step(index, -1));
+    instructions.push(LocalGet(index));
+    instructions.extend(digit(this));
+    instructions.extend(vec![
+        I32Const(9),
+        I32LtU,
+        If(BlockType::Empty),
+        LocalGet(this),
+        LocalGet(index),
+        I32Add,
+        LocalGet(index),
+    ]);
+    instructions.extend(digit(this));
+    instructions.extend(vec![
+        I32Const(1),
+        I32Add,
+        I32Store8(byte(DIGITS)),
+        LocalGet(this),
+        LocalGet(index),
+        I32Const(1),
+        I32Add,
+        I32Store(word(ND)),
+        Return,
+        End,
+        Br(0),
+        End,
+        End,
+        // all nines, which round up to a `1` a place further.
+        LocalGet(this),
+        I32Const(1),
+        I32Store8(byte(DIGITS)),
+        LocalGet(this),
+        I32Const(1),
+        I32Store(word(ND)),
+        LocalGet(this),
+        LocalGet(this),
+        I32Load(word(DP)),
+        I32Const(1),
+        I32Add,
+        I32Store(word(DP)),
+        End,
+    ]);
+    instructions
+}
+
+/// The locals are the `decimal` and the number of `digits` it is rounded
+/// to.
+fn round_down<'a>(decimal: Decimal) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (this, digits) = (0, 1);
+    let mut instructions = return_outside(this, digits);
+    instructions.extend(vec![
+        LocalGet(this),
+        LocalGet(digits),
+        I32Store(word(ND)),
+        LocalGet(this),
+        Call(decimal.trim),
+        End,
+    ]);
+    instructions
+}
+
+/// The locals are the `decimal`, the `index` of the digit added, its
+/// decimal point `dp` and the `integer`.
+fn rounded_integer<'a>(decimal: Decimal) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (this, index, dp, integer) = (0, 1, 2, 3);
+    let mut instructions = vec![
+        LocalGet(this),
+        I32Load(word(DP)),
+        LocalTee(dp),
+        I32Const(20),
+        I32GtS,
+        If(BlockType::Empty),
+        I64Const(-1),
+        Return,
+        End,
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(index),
+        LocalGet(dp),
+        I32GeS,
+        BrIf(1),
+        LocalGet(integer),
+        I64Const(10),
+        I64Mul,
+        // the digits past the last one are zeros.
+        LocalGet(index),
+        LocalGet(this),
+        I32Load(word(ND)),
+        I32LtS,
+        If(BlockType::Result(ValType::I64)),
+        LocalGet(index),
+    ];
+    instructions.extend(digit(this));
+    instructions.extend(vec![
+        I64ExtendI32U,
+        Else,
+        I64Const(0),
+        End,
+        I64Add,
+        LocalSet(integer),
+    ]);
+    instructions.extend(step(index, 1));
+    instructions.extend(vec![
+        Br(0),
+        End,
+        End,
+        LocalGet(integer),
+        LocalGet(this),
+        LocalGet(dp),
+        Call(decimal.should_round_up),
+        I64ExtendI32U,
+        I64Add,
+        End,
+    ]);
+    instructions
+}
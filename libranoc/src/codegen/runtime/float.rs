@@ -0,0 +1,1367 @@
+use wasm_encoder::{BlockType, Instruction, ValType};
+
+use super::{
+    byte,
+    decimal::{emit_decimal, Decimal, DECIMAL_SIZE},
+    word, Runtime,
+};
+use crate::codegen::Context;
+
+/// The indices of the routines of the runtime converting floats to strings
+/// and back, which only a module using them defines. A float is given to
+/// them as its bits, with the number of bits of its mantissa and of its
+/// exponent, which tell an `f32` from an `f64`.
+#[derive(Debug, Clone, Copy)]
+pub struct Floats {
+    /// `rano_float_to_string(bits: i64, mantissa_bits: i32, exponent_bits:
+    /// i32): i32`, the shortest decimal which reads back as the float, the
+    /// way Rust debug formats it: `1.0`, `0.25`, `1e16` and `1.5e-7`, or
+    /// `inf`, `-inf` and `NaN`.
+    pub to_string: u32,
+    /// `rano_string_to_float(string: i32, mantissa_bits: i32,
+    /// exponent_bits: i32): i64`, the float nearest to the decimal, like
+    /// `-1.5`, `.5`, `2.` and `1e-7`, or `inf`, `infinity` and `nan`,
+    /// which gives NaN for a string which isn't one.
+    pub parse: u32,
+}
+
+/// The decimals the routines work on, which they are only called with.
+#[derive(Debug, Clone, Copy)]
+struct Decimals {
+    value: i32,
+    upper: i32,
+    lower: i32,
+}
+
+impl<'a> Context<'a> {
+    /// Declares and implements the routines converting floats, and the
+    /// decimals they are done on.
+    pub(in crate::codegen) fn emit_floats(&mut self) -> Floats {
+        let runtime = self.runtime();
+        let decimal = emit_decimal(self);
+        let decimals = Decimals {
+            value: self.create_data(vec![0; DECIMAL_SIZE]),
+            upper: self.create_data(vec![0; DECIMAL_SIZE]),
+            lower: self.create_data(vec![0; DECIMAL_SIZE]),
+        };
+        let to_string_type = self.declare_function_type(
+            vec![ValType::I64, ValType::I32, ValType::I32],
+            vec![ValType::I32],
+        );
+        let parse_type = self.declare_function_type(vec![ValType::I32; 3], vec![ValType::I64]);
+        let round_shortest_type = self.declare_function_type(
+            vec![
+                ValType::I32,
+                ValType::I64,
+                ValType::I32,
+                ValType::I32,
+                ValType::I32,
+            ],
+            vec![],
+        );
+        let matches_type = self.declare_function_type(vec![ValType::I32; 3], vec![ValType::I32]);
+        let floats = Floats {
+            to_string: self.declare_function(to_string_type),
+            parse: self.declare_function(parse_type),
+        };
+        let round_shortest = self.declare_function(round_shortest_type);
+        let to_bits = self.declare_function(parse_type);
+        let matches = self.declare_function(matches_type);
+        let words = Words {
+            inf: self.string_literal("inf"),
+            infinity: self.string_literal("infinity"),
+            nan: self.string_literal("nan"),
+        };
+
+        let (i32, i64) = (ValType::I32, ValType::I64);
+        self.implement_function(
+            floats.to_string,
+            vec![i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i64],
+            to_string(runtime, decimal, decimals, round_shortest),
+        );
+        self.implement_function(
+            round_shortest,
+            vec![i32; 11].into_iter().chain(vec![i64]).collect(),
+            self::round_shortest(decimal, decimals),
+        );
+        self.implement_function(
+            to_bits,
+            vec![i32, i32, i32, i32, i64],
+            to_float_bits(decimal),
+        );
+        self.implement_function(matches, vec![i32, i32], matches_word());
+        self.implement_function(
+            floats.parse,
+            vec![i32, i32, i32, i32, i32, i32, i32, i32, i32, i64],
+            parse(runtime, decimals, to_bits, matches, words),
+        );
+        for (name, id) in &[
+            ("rano_float_to_string", floats.to_string),
+            ("rano_string_to_float", floats.parse),
+            ("rano_round_shortest", round_shortest),
+            ("rano_decimal_to_float", to_bits),
+            ("rano_matches_word", matches),
+        ] {
+            self.name_function(*id, *name);
+        }
+        floats
+    }
+}
+
+/// The words the special floats are read from, in lower case.
+#[derive(Debug, Clone, Copy)]
+struct Words {
+    inf: i32,
+    infinity: i32,
+    nan: i32,
+}
+
+/// The bits of the infinity of the float with the mantissa bits in the
+/// local `mantissa_bits`, and the largest exponent in `top`.
+fn infinity<'a>(mantissa_bits: u32, top: u32) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    vec![
+        LocalGet(top),
+        I64ExtendI32U,
+        LocalGet(mantissa_bits),
+        I64ExtendI32U,
+        I64Shl,
+    ]
+}
+
+/// Sets the locals `top` and `bias` of the float with the exponent bits in
+/// the local `exponent_bits`: the largest exponent, which the infinities
+/// and the NaNs have, and what is added to the exponent of the others.
+fn exponent_range<'a>(exponent_bits: u32, top: u32, bias: u32) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    vec![
+        I32Const(1),
+        LocalGet(exponent_bits),
+        I32Shl,
+        I32Const(1),
+        I32Sub,
+        LocalSet(top),
+        I32Const(1),
+        I32Const(1),
+        LocalGet(exponent_bits),
+        I32Const(1),
+        I32Sub,
+        I32Shl,
+        I32Sub,
+        LocalSet(bias),
+    ]
+}
+
+/// The digit of the decimal at `decimal` at the index on the stack.
+fn digit<'a>(decimal: i32) -> Vec<Instruction<'a>> {
+    vec![
+        Instruction::I32Const(decimal),
+        Instruction::I32Add,
+        Instruction::I32Load8_U(byte(12)),
+    ]
+}
+
+/// The locals are the `bits` of the float and the number of bits of its
+/// mantissa and exponent, its biased `exponent`, the largest one `top`,
+/// the `bias`, whether it is `negative`, the `string` written, the address
+/// written `at`, the number of digits `nd` and the decimal point `dp` of
+/// its decimal, its `exponent10` in scientific notation, the `index` of a
+/// digit written and the `mantissa`.
+fn to_string<'a>(
+    runtime: Runtime,
+    decimal: Decimal,
+    decimals: Decimals,
+    round_shortest: u32,
+) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (bits, mantissa_bits, exponent_bits) = (0, 1, 2);
+    let (exponent, top, bias, negative, string, at) = (3, 4, 5, 6, 7, 8);
+    let (nd, dp, exponent10, index, mantissa) = (9, 10, 11, 12, 13);
+    let value = decimals.value;
+    let put = |byte: Vec<Instruction<'a>>| {
+        let mut instructions = vec![LocalGet(at)];
+        instructions.extend(byte);
+        instructions.extend(vec![
+            I32Store8(super::byte(0)),
+            LocalGet(at),
+            I32Const(1),
+            I32Add,
+            LocalSet(at),
+        ]);
+        instructions
+    };
+    let put_text = |text: &str| -> Vec<Instruction<'a>> {
+        text.bytes()
+            .flat_map(|byte| put(vec![I32Const(byte as i32)]))
+            .collect()
+    };
+    // the digits from the index `from` gives up to the local `to`.
+    let put_digits = |from: Vec<Instruction<'a>>, to: u32| {
+        let mut instructions = from;
+        instructions.extend(vec![
+            LocalSet(index),
+            Block(BlockType::Empty),
+            Loop(BlockType::Empty),
+            LocalGet(index),
+            LocalGet(to),
+            I32GeS,
+            BrIf(1),
+        ]);
+        let mut character = vec![LocalGet(index)];
+        character.extend(digit(value));
+        character.extend(vec![I32Const(b'0' as i32), I32Add]);
+        instructions.extend(put(character));
+        instructions.extend(vec![
+            LocalGet(index),
+            I32Const(1),
+            I32Add,
+            LocalSet(index),
+            Br(0),
+            End,
+            End,
+        ]);
+        instructions
+    };
+    // as many zeros as the number on the stack.
+    let put_zeros = || {
+        let mut instructions = vec![
+            LocalSet(index),
+            Block(BlockType::Empty),
+            Loop(BlockType::Empty),
+            LocalGet(index),
+            I32Const(0),
+            I32LeS,
+            BrIf(1),
+        ];
+        instructions.extend(put_text("0"));
+        instructions.extend(vec![
+            LocalGet(index),
+            I32Const(1),
+            I32Sub,
+            LocalSet(index),
+            Br(0),
+            End,
+            End,
+        ]);
+        instructions
+    };
+    // the digit of the exponent `divisor` gives.
+    let put_exponent_digit = |divisor: i32| {
+        put(vec![
+            LocalGet(exponent10),
+            I32Const(divisor),
+            I32DivU,
+            I32Const(10),
+            I32RemU,
+            I32Const(b'0' as i32),
+            I32Add,
+        ])
+    };
+
+    let mut instructions = exponent_range(exponent_bits, top, bias);
+    instructions.extend(vec![
+        LocalGet(bits),
+        LocalGet(mantissa_bits),
+        LocalGet(exponent_bits),
+        I32Add,
+        I64ExtendI32U,
+        I64ShrU,
+        I32WrapI64,
+        I32Const(1),
+        I32And,
+        LocalSet(negative),
+        LocalGet(bits),
+        LocalGet(mantissa_bits),
+        I64ExtendI32U,
+        I64ShrU,
+        I32WrapI64,
+        LocalGet(top),
+        I32And,
+        LocalSet(exponent),
+        LocalGet(bits),
+        I64Const(1),
+        LocalGet(mantissa_bits),
+        I64ExtendI32U,
+        I64Shl,
+        I64Const(1),
+        I64Sub,
+        I64And,
+        LocalSet(mantissa),
+        // more than the longest a float is written as, `-1.2345678901234567e-308`.
+        I32Const(4 + 32),
+        Call(runtime.malloc),
+        LocalTee(string),
+        I32Eqz,
+        If(BlockType::Empty),
+        Unreachable,
+        End,
+        LocalGet(string),
+        I32Const(4),
+        I32Add,
+        LocalSet(at),
+        Block(BlockType::Empty),
+        LocalGet(exponent),
+        LocalGet(top),
+        I32Eq,
+        If(BlockType::Empty),
+        LocalGet(mantissa),
+        I64Eqz,
+        I32Eqz,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(put_text("NaN"));
+    instructions.extend(vec![Br(2), End, LocalGet(negative), If(BlockType::Empty)]);
+    instructions.extend(put_text("-"));
+    instructions.push(End);
+    instructions.extend(put_text("inf"));
+    instructions.extend(vec![Br(1), End, LocalGet(negative), If(BlockType::Empty)]);
+    instructions.extend(put_text("-"));
+    instructions.extend(vec![
+        End,
+        LocalGet(exponent),
+        LocalGet(mantissa),
+        I64Eqz,
+        I32Eqz,
+        I32Or,
+        I32Eqz,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(put_text("0.0"));
+    instructions.extend(vec![
+        Br(1),
+        End,
+        // a subnormal float has the exponent of the smallest normal one,
+        // and the others an implicit bit above their mantissa.
+        LocalGet(exponent),
+        I32Eqz,
+        If(BlockType::Empty),
+        I32Const(1),
+        LocalSet(exponent),
+        Else,
+        LocalGet(mantissa),
+        I64Const(1),
+        LocalGet(mantissa_bits),
+        I64ExtendI32U,
+        I64Shl,
+        I64Or,
+        LocalSet(mantissa),
+        End,
+        LocalGet(exponent),
+        LocalGet(bias),
+        I32Add,
+        LocalSet(exponent),
+        // the float is `mantissa * 2^(exponent - mantissa_bits)` exactly.
+        I32Const(value),
+        LocalGet(mantissa),
+        Call(decimal.assign),
+        I32Const(value),
+        LocalGet(exponent),
+        LocalGet(mantissa_bits),
+        I32Sub,
+        Call(decimal.shift),
+        I32Const(value),
+        LocalGet(mantissa),
+        LocalGet(exponent),
+        LocalGet(mantissa_bits),
+        LocalGet(bias),
+        Call(round_shortest),
+        I32Const(value),
+        I32Load(word(0)),
+        LocalSet(nd),
+        I32Const(value),
+        I32Load(word(4)),
+        LocalTee(dp),
+        I32Const(1),
+        I32Sub,
+        LocalTee(exponent10),
+        I32Const(-4),
+        I32GeS,
+        LocalGet(exponent10),
+        I32Const(16),
+        I32LtS,
+        I32And,
+        If(BlockType::Empty),
+        LocalGet(dp),
+        I32Const(0),
+        I32LeS,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(put_text("0."));
+    instructions.extend(vec![I32Const(0), LocalGet(dp), I32Sub]);
+    instructions.extend(put_zeros());
+    instructions.extend(put_digits(vec![I32Const(0)], nd));
+    instructions.extend(vec![
+        Else,
+        LocalGet(dp),
+        LocalGet(nd),
+        I32GeS,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(put_digits(vec![I32Const(0)], nd));
+    instructions.extend(vec![LocalGet(dp), LocalGet(nd), I32Sub]);
+    instructions.extend(put_zeros());
+    instructions.extend(put_text(".0"));
+    instructions.push(Else);
+    instructions.extend(put_digits(vec![I32Const(0)], dp));
+    instructions.extend(put_text("."));
+    instructions.extend(put_digits(vec![LocalGet(dp)], nd));
+    instructions.extend(vec![End, End, Br(1), End]);
+    // in scientific notation otherwise.
+    let mut first = vec![I32Const(0)];
+    first.extend(digit(value));
+    first.extend(vec![I32Const(b'0' as i32), I32Add]);
+    instructions.extend(put(first));
+    instructions.extend(vec![
+        LocalGet(nd),
+        I32Const(1),
+        I32GtS,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(put_text("."));
+    instructions.extend(put_digits(vec![I32Const(1)], nd));
+    instructions.push(End);
+    instructions.extend(put_text("e"));
+    instructions.extend(vec![
+        LocalGet(exponent10),
+        I32Const(0),
+        I32LtS,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(put_text("-"));
+    instructions.extend(vec![
+        I32Const(0),
+        LocalGet(exponent10),
+        I32Sub,
+        LocalSet(exponent10),
+        End,
+        LocalGet(exponent10),
+        I32Const(100),
+        I32GeU,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(put_exponent_digit(100));
+    instructions.extend(vec![
+        End,
+        LocalGet(exponent10),
+        I32Const(10),
+        I32GeU,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(put_exponent_digit(10));
+    instructions.push(End);
+    instructions.extend(put_exponent_digit(1));
+    instructions.extend(vec![
+        End,
+        LocalGet(string),
+        LocalGet(at),
+        LocalGet(string),
+        I32Sub,
+        I32Const(4),
+        I32Sub,
+        I32Store(word(0)),
+        LocalGet(string),
+        End,
+    ]);
+    instructions
+}
+
+/// The locals are the `decimal` of the float, its `mantissa`, `exponent`,
+/// the number of bits of its mantissa and its `bias`, the index `upper_index`
+/// into the decimal halfway to the next float up and the ones `index` and
+/// `lower_index` into the float and the one halfway down, the digits `l`,
+/// `m` and `u` there, whether these bounds are `inclusive`, how far the
+/// float is below the upper one as `upper_delta`, whether rounding down or
+/// up, `ok_down` and `ok_up`, stays between them, the `lower_exponent` and
+/// the `lower_mantissa` of the float below.
+///
+/// The digits of the three are walked until the float can be rounded to
+/// one of them which stays between the bounds: below the upper one when
+/// rounding up, and above the lower one when rounding down. `upper_delta`
+/// is `0` while the float has the same digits as the upper bound, `1` if
+/// it is one below on a digit and only `9`s above the `0`s of the upper
+/// bound since, and `2` if it is further below, so rounding it up stays
+/// below the bound. Halfway between the two roundings, it rounds up.
+fn round_shortest<'a>(decimal: Decimal, decimals: Decimals) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (value, mantissa, exponent, mantissa_bits, bias) = (0, 1, 2, 3, 4);
+    let (upper_index, index, lower_index, l, m, u) = (5, 6, 7, 8, 9, 10);
+    let (inclusive, upper_delta, ok_down, ok_up, lower_exponent) = (11, 12, 13, 14, 15);
+    let lower_mantissa = 16;
+    let (upper, lower) = (decimals.upper, decimals.lower);
+    // the digit of the decimal at `decimal` at the local `at`, or `0` past
+    // its digits.
+    let digit_or_zero = |decimal: i32, at: u32| {
+        let mut instructions = vec![
+            LocalGet(at),
+            I32Const(0),
+            I32GeS,
+            LocalGet(at),
+            I32Const(decimal),
+            I32Load(word(0)),
+            I32LtS,
+            I32And,
+            If(BlockType::Result(ValType::I32)),
+            LocalGet(at),
+        ];
+        instructions.extend(digit(decimal));
+        instructions.extend(vec![Else, I32Const(0), End]);
+        instructions
+    };
+    // the index into `decimal` of the digit at `upper_index` into the upper
+    // bound, the decimal points of the two being apart.
+    let aligned = |decimal: Vec<Instruction<'a>>| {
+        let mut instructions = vec![
+            LocalGet(upper_index),
+            I32Const(upper),
+            I32Load(word(4)),
+            I32Sub,
+        ];
+        instructions.extend(decimal);
+        instructions.extend(vec![I32Load(word(4)), I32Add]);
+        instructions
+    };
+    let round_with = |routine: u32| {
+        vec![
+            LocalGet(value),
+            LocalGet(index),
+            I32Const(1),
+            I32Add,
+            Call(routine),
+            Return,
+        ]
+    };
+
+    let mut instructions = vec![
+        LocalGet(mantissa),
+        I64Eqz,
+        If(BlockType::Empty),
+        LocalGet(value),
+        I32Const(0),
+        I32Store(word(0)),
+        Return,
+        End,
+        // halfway to the next float up, `(2 * mantissa + 1) * 2^(exponent -
+        // mantissa_bits - 1)`.
+        I32Const(upper),
+        LocalGet(mantissa),
+        I64Const(2),
+        I64Mul,
+        I64Const(1),
+        I64Add,
+        Call(decimal.assign),
+        I32Const(upper),
+        LocalGet(exponent),
+        LocalGet(mantissa_bits),
+        I32Sub,
+        I32Const(1),
+        I32Sub,
+        Call(decimal.shift),
+        // the next float down has the same exponent, unless the mantissa is
+        // the smallest of a normal float, below which the exponent is one
+        // less and there are twice as many floats.
+        LocalGet(mantissa),
+        I64Const(1),
+        LocalGet(mantissa_bits),
+        I64ExtendI32U,
+        I64Shl,
+        I64GtU,
+        LocalGet(exponent),
+        LocalGet(bias),
+        I32Const(1),
+        I32Add,
+        I32Eq,
+        I32Or,
+        If(BlockType::Empty),
+        LocalGet(mantissa),
+        I64Const(1),
+        I64Sub,
+        LocalSet(lower_mantissa),
+        LocalGet(exponent),
+        LocalSet(lower_exponent),
+        Else,
+        LocalGet(mantissa),
+        I64Const(2),
+        I64Mul,
+        I64Const(1),
+        I64Sub,
+        LocalSet(lower_mantissa),
+        LocalGet(exponent),
+        I32Const(1),
+        I32Sub,
+        LocalSet(lower_exponent),
+        End,
+        I32Const(lower),
+        LocalGet(lower_mantissa),
+        I64Const(2),
+        I64Mul,
+        I64Const(1),
+        I64Add,
+        Call(decimal.assign),
+        I32Const(lower),
+        LocalGet(lower_exponent),
+        LocalGet(mantissa_bits),
+        I32Sub,
+        I32Const(1),
+        I32Sub,
+        Call(decimal.shift),
+        // a float with an even mantissa is what its bounds are read as.
+        LocalGet(mantissa),
+        I64Const(1),
+        I64And,
+        I64Eqz,
+        LocalSet(inclusive),
+        Loop(BlockType::Empty),
+    ];
+    instructions.extend(aligned(vec![LocalGet(value)]));
+    instructions.extend(vec![
+        LocalTee(index),
+        LocalGet(value),
+        I32Load(word(0)),
+        I32GeS,
+        If(BlockType::Empty),
+        Return,
+        End,
+    ]);
+    instructions.extend(aligned(vec![I32Const(lower)]));
+    instructions.push(LocalSet(lower_index));
+    instructions.extend(digit_or_zero(lower, lower_index));
+    instructions.push(LocalSet(l));
+    instructions.extend(vec![
+        LocalGet(index),
+        I32Const(0),
+        I32GeS,
+        If(BlockType::Result(ValType::I32)),
+        LocalGet(index),
+        LocalGet(value),
+        I32Add,
+        I32Load8_U(byte(12)),
+        Else,
+        I32Const(0),
+        End,
+        LocalSet(m),
+    ]);
+    instructions.extend(digit_or_zero(upper, upper_index));
+    instructions.extend(vec![
+        LocalSet(u),
+        // rounding down stays above the lower bound if it has another digit
+        // here, or if it is inclusive and ends here.
+        LocalGet(l),
+        LocalGet(m),
+        I32Neq,
+        LocalGet(inclusive),
+        LocalGet(lower_index),
+        I32Const(1),
+        I32Add,
+        I32Const(lower),
+        I32Load(word(0)),
+        I32Eq,
+        I32And,
+        I32Or,
+        LocalSet(ok_down),
+        LocalGet(upper_delta),
+        I32Eqz,
+        If(BlockType::Empty),
+        LocalGet(m),
+        I32Const(1),
+        I32Add,
+        LocalGet(u),
+        I32LtU,
+        If(BlockType::Empty),
+        I32Const(2),
+        LocalSet(upper_delta),
+        Else,
+        LocalGet(m),
+        LocalGet(u),
+        I32Neq,
+        LocalSet(upper_delta),
+        End,
+        Else,
+        LocalGet(upper_delta),
+        I32Const(1),
+        I32Eq,
+        LocalGet(m),
+        I32Const(9),
+        I32Neq,
+        LocalGet(u),
+        I32Const(0),
+        I32Neq,
+        I32Or,
+        I32And,
+        If(BlockType::Empty),
+        I32Const(2),
+        LocalSet(upper_delta),
+        End,
+        End,
+        // rounding up stays below the upper bound if it is further than
+        // this digit, or inclusive, or has more digits.
+        LocalGet(upper_delta),
+        I32Const(0),
+        I32GtU,
+        LocalGet(inclusive),
+        LocalGet(upper_delta),
+        I32Const(1),
+        I32GtU,
+        I32Or,
+        LocalGet(upper_index),
+        I32Const(1),
+        I32Add,
+        I32Const(upper),
+        I32Load(word(0)),
+        I32LtS,
+        I32Or,
+        I32And,
+        LocalSet(ok_up),
+        LocalGet(ok_down),
+        LocalGet(ok_up),
+        I32And,
+        If(BlockType::Empty),
+    ]);
+    // between the two the nearest is taken, like Rust does.
+    instructions.extend(vec![
+        LocalGet(index),
+        I32Const(1),
+        I32Add,
+        LocalTee(m),
+        LocalGet(value),
+        I32Load(word(0)),
+        I32LtS,
+        If(BlockType::Result(ValType::I32)),
+        LocalGet(m),
+        LocalGet(value),
+        I32Add,
+        I32Load8_U(byte(12)),
+        Else,
+        I32Const(0),
+        End,
+        I32Const(5),
+        I32GeU,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(round_with(decimal.round_up));
+    instructions.extend(vec![End]);
+    instructions.extend(round_with(decimal.round_down));
+    instructions.extend(vec![End, LocalGet(ok_down), If(BlockType::Empty)]);
+    instructions.extend(round_with(decimal.round_down));
+    instructions.extend(vec![End, LocalGet(ok_up), If(BlockType::Empty)]);
+    instructions.extend(round_with(decimal.round_up));
+    instructions.extend(vec![
+        End,
+        LocalGet(upper_index),
+        I32Const(1),
+        I32Add,
+        LocalSet(upper_index),
+        Br(0),
+        End,
+        End,
+    ]);
+    instructions
+}
+
+/// The locals are the `decimal`, the number of bits of the mantissa and the
+/// exponent of the float, its `exponent`, the `bits` the decimal is
+/// shifted by, the `bias`, the largest exponent `top` and the `mantissa`.
+///
+/// The decimal is scaled by powers of two to between `0.5` and `1`, which
+/// is how far the exponent is from `-1`, and then by the bits of the
+/// mantissa, which it is rounded to.
+fn to_float_bits<'a>(decimal: Decimal) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (this, mantissa_bits, exponent_bits) = (0, 1, 2);
+    let (exponent, bits, bias, top, mantissa) = (3, 4, 5, 6, 7);
+    // the bits a decimal with the decimal point on the stack is shifted by,
+    // which keep it above `0.1` if it is above `1`, and below `1` if it is
+    // below `0.1`: `1` for `0`, and about `dp * log2(10)` otherwise, though
+    // no more than 27.
+    let shift_for = || {
+        vec![
+            I32Const(3321),
+            I32Mul,
+            I32Const(1000),
+            I32DivU,
+            LocalTee(bits),
+            I32Const(27),
+            LocalGet(bits),
+            I32Const(27),
+            I32LtU,
+            Select,
+            LocalTee(bits),
+            I32Const(1),
+            LocalGet(bits),
+            I32Const(1),
+            I32GtU,
+            Select,
+            LocalSet(bits),
+        ]
+    };
+    let return_infinity = || {
+        let mut instructions = vec![
+            LocalGet(exponent),
+            LocalGet(bias),
+            I32Sub,
+            LocalGet(top),
+            I32GeS,
+            If(BlockType::Empty),
+        ];
+        instructions.extend(infinity(mantissa_bits, top));
+        instructions.extend(vec![Return, End]);
+        instructions
+    };
+
+    let mut instructions = exponent_range(exponent_bits, top, bias);
+    instructions.extend(vec![
+        LocalGet(this),
+        I32Load(word(0)),
+        I32Eqz,
+        If(BlockType::Empty),
+        I64Const(0),
+        Return,
+        End,
+        LocalGet(this),
+        I32Load(word(4)),
+        I32Const(310),
+        I32GtS,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(infinity(mantissa_bits, top));
+    instructions.extend(vec![
+        Return,
+        End,
+        LocalGet(this),
+        I32Load(word(4)),
+        I32Const(-330),
+        I32LtS,
+        If(BlockType::Empty),
+        I64Const(0),
+        Return,
+        End,
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(this),
+        I32Load(word(4)),
+        I32Const(0),
+        I32LeS,
+        BrIf(1),
+        LocalGet(this),
+        I32Load(word(4)),
+    ]);
+    instructions.extend(shift_for());
+    instructions.extend(vec![
+        LocalGet(this),
+        I32Const(0),
+        LocalGet(bits),
+        I32Sub,
+        Call(decimal.shift),
+        LocalGet(exponent),
+        LocalGet(bits),
+        I32Add,
+        LocalSet(exponent),
+        Br(0),
+        End,
+        End,
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(this),
+        I32Load(word(4)),
+        I32Const(0),
+        I32GtS,
+        BrIf(1),
+        LocalGet(this),
+        I32Load(word(4)),
+        I32Eqz,
+        LocalGet(this),
+        I32Load8_U(byte(12)),
+        I32Const(5),
+        I32GeU,
+        I32And,
+        BrIf(1),
+        I32Const(0),
+        LocalGet(this),
+        I32Load(word(4)),
+        I32Sub,
+    ]);
+    instructions.extend(shift_for());
+    instructions.extend(vec![
+        LocalGet(this),
+        LocalGet(bits),
+        Call(decimal.shift),
+        LocalGet(exponent),
+        LocalGet(bits),
+        I32Sub,
+        LocalSet(exponent),
+        Br(0),
+        End,
+        End,
+        // a float is between `1` and `2`.
+        LocalGet(exponent),
+        I32Const(1),
+        I32Sub,
+        LocalSet(exponent),
+        // below the smallest exponent the float is subnormal, and has less
+        // bits.
+        LocalGet(exponent),
+        LocalGet(bias),
+        I32Const(1),
+        I32Add,
+        I32LtS,
+        If(BlockType::Empty),
+        LocalGet(bias),
+        I32Const(1),
+        I32Add,
+        LocalGet(exponent),
+        I32Sub,
+        LocalSet(bits),
+        LocalGet(this),
+        I32Const(0),
+        LocalGet(bits),
+        I32Sub,
+        Call(decimal.shift),
+        LocalGet(exponent),
+        LocalGet(bits),
+        I32Add,
+        LocalSet(exponent),
+        End,
+    ]);
+    instructions.extend(return_infinity());
+    instructions.extend(vec![
+        LocalGet(this),
+        LocalGet(mantissa_bits),
+        I32Const(1),
+        I32Add,
+        Call(decimal.shift),
+        LocalGet(this),
+        Call(decimal.rounded_integer),
+        LocalTee(mantissa),
+        // rounding up may carry into a bit more.
+        I64Const(2),
+        LocalGet(mantissa_bits),
+        I64ExtendI32U,
+        I64Shl,
+        I64Eq,
+        If(BlockType::Empty),
+        LocalGet(mantissa),
+        I64Const(1),
+        I64ShrU,
+        LocalSet(mantissa),
+        LocalGet(exponent),
+        I32Const(1),
+        I32Add,
+        LocalSet(exponent),
+    ]);
+    instructions.extend(return_infinity());
+    instructions.extend(vec![
+        End,
+        // without the implicit bit, the float is subnormal.
+        LocalGet(mantissa),
+        I64Const(1),
+        LocalGet(mantissa_bits),
+        I64ExtendI32U,
+        I64Shl,
+        I64And,
+        I64Eqz,
+        If(BlockType::Empty),
+        LocalGet(bias),
+        LocalSet(exponent),
+        End,
+        LocalGet(mantissa),
+        I64Const(1),
+        LocalGet(mantissa_bits),
+        I64ExtendI32U,
+        I64Shl,
+        I64Const(1),
+        I64Sub,
+        I64And,
+        LocalGet(exponent),
+        LocalGet(bias),
+        I32Sub,
+        LocalGet(top),
+        I32And,
+        I64ExtendI32U,
+        LocalGet(mantissa_bits),
+        I64ExtendI32U,
+        I64Shl,
+        I64Or,
+        End,
+    ]);
+    instructions
+}
+
+/// The locals are the `string`, the `index` into it the rest of which is
+/// compared, the `word` compared with, the `offset` into it and its
+/// `length`. The rest of the string matches a word in lower case in any
+/// case.
+fn matches_word<'a>() -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (string, index, this, offset, length) = (0, 1, 2, 3, 4);
+    vec![
+        LocalGet(string),
+        I32Load(word(0)),
+        LocalGet(index),
+        I32Sub,
+        LocalGet(this),
+        I32Load(word(0)),
+        LocalTee(length),
+        I32Neq,
+        If(BlockType::Empty),
+        I32Const(0),
+        Return,
+        End,
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(offset),
+        LocalGet(length),
+        I32GeU,
+        BrIf(1),
+        LocalGet(string),
+        LocalGet(index),
+        I32Add,
+        LocalGet(offset),
+        I32Add,
+        I32Load8_U(byte(4)),
+        I32Const(0x20),
+        I32Or,
+        LocalGet(this),
+        LocalGet(offset),
+        I32Add,
+        I32Load8_U(byte(4)),
+        I32Neq,
+        If(BlockType::Empty),
+        I32Const(0),
+        Return,
+        End,
+        LocalGet(offset),
+        I32Const(1),
+        I32Add,
+        LocalSet(offset),
+        Br(0),
+        End,
+        End,
+        I32Const(1),
+        End,
+    ]
+}
+
+/// The locals are the `string`, the number of bits of the mantissa and the
+/// exponent of the float, the `index` of the byte read, the `length` of the
+/// string, the `byte`, whether the float is `negative`, whether a decimal
+/// `point` and `digits` were read, the `exponent` read and whether it is
+/// `exponent_negative`, the number of digits `nd` and the `bits` of the
+/// float.
+///
+/// The digits are read into a decimal, the leading zeros only moving its
+/// decimal point, which is converted to the float.
+fn parse<'a>(
+    runtime: Runtime,
+    decimals: Decimals,
+    to_bits: u32,
+    matches: u32,
+    words: Words,
+) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (string, mantissa_bits, exponent_bits, index, length) = (0, 1, 2, 3, 4);
+    let (byte, negative, point, digits, exponent) = (5, 6, 7, 8, 9);
+    let (exponent_negative, nd, bits) = (10, 11, 12);
+    let decimal = decimals.value;
+    let read = || {
+        vec![
+            LocalGet(string),
+            LocalGet(index),
+            I32Add,
+            I32Load8_U(super::byte(4)),
+        ]
+    };
+    let next = || vec![LocalGet(index), I32Const(1), I32Add, LocalSet(index)];
+    let word_at = |word: i32| {
+        vec![
+            LocalGet(string),
+            LocalGet(index),
+            I32Const(word),
+            Call(matches),
+        ]
+    };
+    // the bits of the infinity, and of a NaN with the top bit of the
+    // mantissa set.
+    let mut infinity = vec![
+        I32Const(1),
+        LocalGet(exponent_bits),
+        I32Shl,
+        I32Const(1),
+        I32Sub,
+        I64ExtendI32U,
+        LocalGet(mantissa_bits),
+        I64ExtendI32U,
+        I64Shl,
+    ];
+    let mut nan = infinity.clone();
+    nan.extend(vec![
+        I64Const(1),
+        LocalGet(mantissa_bits),
+        I32Const(1),
+        I32Sub,
+        I64ExtendI32U,
+        I64Shl,
+        I64Or,
+    ]);
+
+    let mut instructions = vec![
+        LocalGet(string),
+        I32Load(word(0)),
+        LocalSet(length),
+        I32Const(decimal),
+        I32Const(0),
+        I32Store(word(0)),
+        I32Const(decimal),
+        I32Const(0),
+        I32Store(word(4)),
+        I32Const(decimal),
+        I32Const(0),
+        I32Store(word(8)),
+        Block(BlockType::Empty),
+        Block(BlockType::Empty),
+        LocalGet(length),
+        I32Eqz,
+        BrIf(0),
+    ];
+    instructions.extend(read());
+    instructions.extend(vec![
+        LocalTee(byte),
+        I32Const(b'-' as i32),
+        I32Eq,
+        LocalTee(negative),
+        LocalGet(byte),
+        I32Const(b'+' as i32),
+        I32Eq,
+        I32Or,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(next());
+    instructions.push(End);
+    instructions.extend(word_at(words.inf));
+    instructions.extend(word_at(words.infinity));
+    instructions.extend(vec![I32Or, If(BlockType::Empty)]);
+    instructions.append(&mut infinity);
+    instructions.extend(vec![LocalSet(bits), Br(2), End]);
+    instructions.extend(word_at(words.nan));
+    instructions.push(If(BlockType::Empty));
+    instructions.extend(nan.clone());
+    instructions.extend(vec![
+        LocalSet(bits),
+        Br(2),
+        End,
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(index),
+        LocalGet(length),
+        I32GeU,
+        BrIf(1),
+    ]);
+    instructions.extend(read());
+    instructions.extend(vec![
+        LocalTee(byte),
+        I32Const(b'.' as i32),
+        I32Eq,
+        If(BlockType::Empty),
+        // a second point is invalid.
+        LocalGet(point),
+        BrIf(3),
+        I32Const(1),
+        LocalSet(point),
+        I32Const(decimal),
+        LocalGet(nd),
+        I32Store(word(4)),
+    ]);
+    instructions.extend(next());
+    instructions.extend(vec![
+        Br(1),
+        End,
+        LocalGet(byte),
+        I32Const(b'0' as i32),
+        I32Sub,
+        LocalTee(byte),
+        I32Const(9),
+        I32GtU,
+        BrIf(1),
+        I32Const(1),
+        LocalSet(digits),
+        LocalGet(byte),
+        I32Eqz,
+        LocalGet(nd),
+        I32Eqz,
+        I32And,
+        If(BlockType::Empty),
+        // a leading zero only moves the decimal point.
+        I32Const(decimal),
+        I32Const(decimal),
+        I32Load(word(4)),
+        I32Const(1),
+        I32Sub,
+        I32Store(word(4)),
+        Else,
+        LocalGet(nd),
+        I32Const(DECIMAL_SIZE as i32 - 12),
+        I32LtS,
+        If(BlockType::Empty),
+        LocalGet(nd),
+        I32Const(decimal),
+        I32Add,
+        LocalGet(byte),
+        I32Store8(super::byte(12)),
+        LocalGet(nd),
+        I32Const(1),
+        I32Add,
+        LocalSet(nd),
+        Else,
+        LocalGet(byte),
+        If(BlockType::Empty),
+        I32Const(decimal),
+        I32Const(1),
+        I32Store(word(8)),
+        End,
+        End,
+        End,
+    ]);
+    instructions.extend(next());
+    instructions.extend(vec![
+        Br(0),
+        End,
+        End,
+        LocalGet(digits),
+        I32Eqz,
+        BrIf(0),
+        I32Const(decimal),
+        LocalGet(nd),
+        I32Store(word(0)),
+        LocalGet(point),
+        I32Eqz,
+        If(BlockType::Empty),
+        I32Const(decimal),
+        LocalGet(nd),
+        I32Store(word(4)),
+        End,
+        LocalGet(index),
+        LocalGet(length),
+        I32LtU,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(read());
+    instructions.extend(vec![
+        I32Const(0x20),
+        I32Or,
+        I32Const(b'e' as i32),
+        I32Neq,
+        BrIf(1),
+    ]);
+    instructions.extend(next());
+    instructions.extend(vec![
+        LocalGet(index),
+        LocalGet(length),
+        I32LtU,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(read());
+    instructions.extend(vec![
+        LocalTee(byte),
+        I32Const(b'-' as i32),
+        I32Eq,
+        LocalTee(exponent_negative),
+        LocalGet(byte),
+        I32Const(b'+' as i32),
+        I32Eq,
+        I32Or,
+        If(BlockType::Empty),
+    ]);
+    instructions.extend(next());
+    instructions.extend(vec![
+        End,
+        End,
+        // the exponent has a digit at least.
+        LocalGet(index),
+        LocalGet(length),
+        I32GeU,
+        BrIf(1),
+    ]);
+    instructions.extend(read());
+    instructions.extend(vec![
+        I32Const(b'0' as i32),
+        I32Sub,
+        I32Const(9),
+        I32GtU,
+        BrIf(1),
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(index),
+        LocalGet(length),
+        I32GeU,
+        BrIf(1),
+    ]);
+    instructions.extend(read());
+    instructions.extend(vec![
+        I32Const(b'0' as i32),
+        I32Sub,
+        LocalTee(byte),
+        I32Const(9),
+        I32GtU,
+        BrIf(1),
+        // one this large is past any float already.
+        LocalGet(exponent),
+        I32Const(10000),
+        I32LtS,
+        If(BlockType::Empty),
+        LocalGet(exponent),
+        I32Const(10),
+        I32Mul,
+        LocalGet(byte),
+        I32Add,
+        LocalSet(exponent),
+        End,
+    ]);
+    instructions.extend(next());
+    instructions.extend(vec![
+        Br(0),
+        End,
+        End,
+        I32Const(decimal),
+        I32Const(decimal),
+        I32Load(word(4)),
+        I32Const(0),
+        LocalGet(exponent),
+        I32Sub,
+        LocalGet(exponent),
+        LocalGet(exponent_negative),
+        Select,
+        I32Add,
+        I32Store(word(4)),
+        End,
+        // anything left is invalid.
+        LocalGet(index),
+        LocalGet(length),
+        I32Neq,
+        BrIf(0),
+        I32Const(decimal),
+        LocalGet(mantissa_bits),
+        LocalGet(exponent_bits),
+        Call(to_bits),
+        LocalSet(bits),
+        Br(1),
+        End,
+    ]);
+    instructions.extend(nan);
+    instructions.extend(vec![
+        LocalSet(bits),
+        I32Const(0),
+        LocalSet(negative),
+        End,
+        LocalGet(string),
+        Call(runtime.release),
+        LocalGet(bits),
+        LocalGet(negative),
+        I64ExtendI32U,
+        LocalGet(mantissa_bits),
+        LocalGet(exponent_bits),
+        I32Add,
+        I64ExtendI32U,
+        I64Shl,
+        I64Or,
+        End,
+    ]);
+    instructions
+}
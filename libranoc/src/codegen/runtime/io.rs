@@ -0,0 +1,44 @@
+use wasm_encoder::Instruction;
+
+use super::{word, Runtime};
+
+/// The locals are the `string`, the `fd` it is written to and whether a
+/// `newline` follows it. The two pieces are written by one `fd_write`,
+/// from the vectors at `iovecs`, the second of which points to the byte
+/// at `newline_byte`; the number of bytes written lands behind them, and
+/// a short write isn't retried.
+pub(super) fn write<'a>(
+    runtime: Runtime,
+    fd_write: u32,
+    iovecs: i32,
+    newline_byte: i32,
+) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (string, fd, newline) = (0, 1, 2);
+    vec![
+        I32Const(iovecs),
+        LocalGet(string),
+        I32Const(4),
+        I32Add,
+        I32Store(word(0)),
+        I32Const(iovecs),
+        LocalGet(string),
+        I32Load(word(0)),
+        I32Store(word(4)),
+        I32Const(iovecs),
+        I32Const(newline_byte),
+        I32Store(word(8)),
+        I32Const(iovecs),
+        LocalGet(newline),
+        I32Store(word(12)),
+        LocalGet(fd),
+        I32Const(iovecs),
+        I32Const(2),
+        I32Const(iovecs + 16),
+        Call(fd_write),
+        Drop,
+        LocalGet(string),
+        Call(runtime.release),
+        End,
+    ]
+}
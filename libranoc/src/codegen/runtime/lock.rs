@@ -0,0 +1,66 @@
+use wasm_encoder::{BlockType, Instruction, ValType};
+
+use crate::codegen::{Atomic, Context};
+
+/// A body of a routine, with the atomic instructions the `nop`s at their
+/// indices stand for.
+#[derive(Default)]
+pub(super) struct Body<'a> {
+    pub(super) instructions: Vec<Instruction<'a>>,
+    pub(super) atomics: Vec<(usize, Atomic)>,
+}
+
+impl<'a> Body<'a> {
+    pub(super) fn extend(&mut self, instructions: Vec<Instruction<'a>>) {
+        self.instructions.extend(instructions);
+    }
+
+    pub(super) fn atomic(&mut self, atomic: Atomic) {
+        self.atomics.push((self.instructions.len(), atomic));
+        self.instructions.push(Instruction::Nop);
+    }
+
+    /// Spins until the word at `lock` is taken, which is `1` while it is
+    /// and `0` otherwise.
+    pub(super) fn lock(&mut self, lock: i32) {
+        use Instruction::*;
+        self.extend(vec![
+            Block(BlockType::Empty),
+            Loop(BlockType::Empty),
+            I32Const(lock),
+            I32Const(0),
+            I32Const(1),
+        ]);
+        self.atomic(Atomic::CompareExchange);
+        self.extend(vec![I32Eqz, BrIf(1), Br(0), End, End]);
+    }
+
+    pub(super) fn unlock(&mut self, lock: i32) {
+        use Instruction::*;
+        self.extend(vec![I32Const(lock), I32Const(0)]);
+        self.atomic(Atomic::Store);
+    }
+}
+
+impl<'a> Context<'a> {
+    /// Implements the function `id` with `body`, writing its atomics by
+    /// hand.
+    pub(super) fn implement_body(&mut self, id: u32, locals: Vec<ValType>, body: Body<'a>) {
+        for (at, atomic) in body.atomics {
+            self.atomic_at(id, at, atomic);
+        }
+        self.implement_function(id, locals, body.instructions);
+    }
+
+    /// Implements the function `id`, taking `parameters` `i32`s, as a call
+    /// of `routine` with them, holding the word at `lock` while it runs.
+    pub(super) fn implement_locked(&mut self, id: u32, lock: i32, routine: u32, parameters: u32) {
+        let mut body = Body::default();
+        body.lock(lock);
+        body.extend((0..parameters).map(Instruction::LocalGet).collect());
+        body.instructions.push(Instruction::Call(routine));
+        body.unlock(lock);
+        body.instructions.push(Instruction::End);
+        self.implement_body(id, vec![], body);
+    }
+}
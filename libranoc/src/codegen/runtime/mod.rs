@@ -0,0 +1,304 @@
+//! The runtime every module is given, written in WASM by hand, which the
+//! code of strings, arrays, closures and structs calls to allocate.
+//!
+//! The allocator keeps its state in the first bytes of the memory, which
+//! are never handed out, so a pointer is never `0`:
+//!
+//! - `HEAP_TOP` holds the end of the heap, set to behind the data segments
+//!   by `finish`, which the heap grows from by bumping it.
+//! - `FREE_LIST` holds the first freed block, each of which holds the next.
+//! - `HEAP_BASE` holds the start of the heap, which the values in the data
+//!   segments are below.
+//!
+//! Every block starts with a header of 8 bytes, with the size of the block
+//! behind it, rounded up to 8 bytes, and the next block of the free list
+//! while it is in there, or the number of references to it while it isn't.
+//! A block is reused by the first allocation it is large enough for, as it
+//! is, and the memory grows when the heap reaches its end.
+//!
+//! A value on the heap is freed once the last reference to it is released.
+//! Every expression gives a reference it owns, which goes to whatever
+//! consumes the value: a local releases it at the end of its block, a
+//! function the arguments it is given once it returns, and the routines
+//! of strings their operands. Reading a local counts a new reference.
+//! Literals are never freed, so counting the references to a value below
+//! `HEAP_BASE` does nothing. Only the environment of a closure on the heap
+//! points to other values yet, which aren't released with it; when arrays
+//! and structs do too, releasing one will have to release its fields, and
+//! the values in a cycle will never be freed.
+//!
+//! A function value is the address of its environment, which holds the
+//! slot of the function it calls in the table, then what a closure
+//! captures. That function takes the address behind its arguments.
+//!
+//! A string is the address of its length in bytes, as a word, with its
+//! UTF-8 bytes right behind it. Strings are never changed in place, so a
+//! literal is left in the data segments and concatenating or slicing
+//! allocates a new one.
+//!
+//! An array is the address of its length in elements, as a word, with its
+//! elements right behind it, each taking the bytes of the values it is
+//! passed as, in order. An array literal of constants is left in the data
+//! segments too, where the literals made of the same bytes are placed once.
+//!
+//! With the `wasi` target, the runtime writes strings to the standard
+//! streams too, through the `fd_write` of WASI.
+//!
+//! A panic keeps its message and where it happened in the two words after
+//! the state of the allocator, `PANIC_MESSAGE` and `PANIC_LOCATION`, before
+//! it traps, so the host can tell what happened from the memory alone.
+//! With the `exception-handling` target, it throws them as the values of
+//! the tag exported as `rano_panic_tag` instead, after counting no call as
+//! running and emptying the shadow stack, so the instance can be called
+//! again once the host caught it.
+//!
+//! With `CompileOptions::max_call_depth`, the functions which can call
+//! themselves count how many of their calls are running in the word after
+//! those, `CALL_DEPTH`, and panic with a stack overflow once there are too
+//! many, before the stack of the engine runs out.
+//!
+//! A module with a function keeping locals in a frame on the shadow stack
+//! has the stack placed between the data segments and the heap, with the
+//! lowest address it may grow down to in the next word, `STACK_BASE`.
+//!
+//! With the `threads` target, the memory is shared by the instances of the
+//! module on other threads, so the data is only copied into it while the
+//! next word, `INITIALIZED`, is `0`, which the data sets. The allocator
+//! then holds the word behind it, `ALLOCATOR_LOCK`, while it allocates or
+//! frees, as the channels do so on any thread.
+//!
+//! Floats are formatted and parsed by routines only a module which does so
+//! is given, working on exact decimals kept in the data segments, so no
+//! host is needed for either.
+//!
+//! So are the channels threads pass messages through, which live on the
+//! heap and are only looked at with a lock in the data segments held,
+//! waiting on the shared memory, so no host is needed for them either.
+
+use wasm_encoder::{MemArg, ValType};
+
+use super::Context;
+
+mod alloc;
+mod channel;
+mod decimal;
+mod float;
+mod io;
+mod lock;
+mod panic;
+mod string;
+
+use alloc::*;
+pub use channel::Channels;
+pub use float::Floats;
+pub use panic::{read_panic, Panic};
+use string::*;
+
+pub(super) const HEAP_TOP: i32 = 0;
+pub(super) const FREE_LIST: i32 = 4;
+pub(super) const HEAP_BASE: i32 = 8;
+pub(super) const PANIC_MESSAGE: i32 = 12;
+pub(super) const PANIC_LOCATION: i32 = 16;
+pub(super) const CALL_DEPTH: i32 = 20;
+pub(super) const STACK_BASE: i32 = 24;
+pub(super) const INITIALIZED: i32 = 28;
+pub(super) const ALLOCATOR_LOCK: i32 = 32;
+/// The bytes the allocator, the panics, the guard of the call depth, the
+/// shadow stack and the shared memory keep their state in, which the data
+/// segments come after.
+pub(super) const RESERVED: i32 = 40;
+
+/// The size of the header of a block.
+const HEADER: i32 = 8;
+
+/// The indices of the functions of the runtime.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Runtime {
+    /// `rano_malloc(size: i32): i32`, which gives `0` if the memory can't
+    /// grow.
+    pub malloc: u32,
+    /// `rano_free(pointer: i32)`, which ignores `0`.
+    pub free: u32,
+    /// `rano_realloc(pointer: i32, size: i32): i32`, which moves the block
+    /// if it isn't large enough, and allocates one for `0`.
+    pub realloc: u32,
+    /// `rano_retain(pointer: i32): i32`, which counts one more reference to
+    /// a value and gives it back.
+    pub retain: u32,
+    /// `rano_release(pointer: i32)`, which counts one less and frees the
+    /// value if that was the last.
+    pub release: u32,
+    /// `copy(destination: i32, source: i32, length: i32)`, which copies
+    /// bytes front to back.
+    pub copy: u32,
+    /// `rano_string_concat(lhs: i32, rhs: i32): i32`. Like the other
+    /// routines of strings, it releases the strings it is given.
+    pub string_concat: u32,
+    /// `rano_string_eq(lhs: i32, rhs: i32): i32`, comparing the bytes.
+    pub string_eq: u32,
+    /// `rano_string_slice(string: i32, start: i32, end: i32): i32`, with
+    /// the byte offsets `start` and `end`, which traps unless `start <= end
+    /// <= length`.
+    pub string_slice: u32,
+    /// `rano_write(string: i32, fd: i32, newline: i32)`, which writes a
+    /// string to a stream, and a newline after it unless `newline` is `0`.
+    /// Only with the `wasi` target.
+    pub write: Option<u32>,
+    /// `rano_panic(message: i32, location: i32)`, which keeps the strings
+    /// where the host finds them, writes them to the standard error with
+    /// the `wasi` target, and traps.
+    pub panic: u32,
+}
+
+impl Runtime {
+    /// The functions of the runtime.
+    pub fn functions(&self) -> Vec<u32> {
+        let mut functions = vec![
+            self.malloc,
+            self.free,
+            self.realloc,
+            self.retain,
+            self.release,
+            self.copy,
+            self.string_concat,
+            self.string_eq,
+            self.string_slice,
+            self.panic,
+        ];
+        functions.extend(self.write);
+        functions
+    }
+}
+
+/// The word at `offset` bytes past the address on the stack.
+fn word(offset: u32) -> MemArg {
+    MemArg {
+        offset,
+        align: 2,
+        memory_index: 0,
+    }
+}
+
+/// The byte `offset` bytes past the address on the stack.
+fn byte(offset: u32) -> MemArg {
+    MemArg {
+        offset,
+        align: 0,
+        memory_index: 0,
+    }
+}
+
+impl<'a> Context<'a> {
+    /// Defines the runtime in the module and exports it, so the host can
+    /// allocate the values it passes in too, and keep or release the ones
+    /// it is given.
+    pub(super) fn emit_runtime(&mut self) -> Runtime {
+        let bulk_memory = self.target().bulk_memory;
+        let unary_type = self.declare_function_type(vec![ValType::I32], vec![ValType::I32]);
+        let free_type = self.declare_function_type(vec![ValType::I32], vec![]);
+        let binary_type =
+            self.declare_function_type(vec![ValType::I32, ValType::I32], vec![ValType::I32]);
+        let copy_type = self.declare_function_type(vec![ValType::I32; 3], vec![]);
+        let slice_type = self.declare_function_type(vec![ValType::I32; 3], vec![ValType::I32]);
+        let panic_type = self.declare_function_type(vec![ValType::I32; 2], vec![]);
+        let mut runtime = Runtime {
+            malloc: self.declare_function(unary_type),
+            free: self.declare_function(free_type),
+            realloc: self.declare_function(binary_type),
+            retain: self.declare_function(unary_type),
+            release: self.declare_function(free_type),
+            copy: self.declare_function(copy_type),
+            string_concat: self.declare_function(binary_type),
+            string_eq: self.declare_function(binary_type),
+            string_slice: self.declare_function(slice_type),
+            write: None,
+            panic: self.declare_function(panic_type),
+        };
+        if self.target().threads {
+            let (unlocked_malloc, unlocked_free) = (
+                self.declare_function(unary_type),
+                self.declare_function(free_type),
+            );
+            self.implement_function(unlocked_malloc, vec![ValType::I32; 4], malloc());
+            self.implement_function(unlocked_free, vec![ValType::I32], free());
+            self.name_function(unlocked_malloc, "rano_unlocked_malloc");
+            self.name_function(unlocked_free, "rano_unlocked_free");
+            self.implement_locked(runtime.malloc, ALLOCATOR_LOCK, unlocked_malloc, 1);
+            self.implement_locked(runtime.free, ALLOCATOR_LOCK, unlocked_free, 1);
+        } else {
+            self.implement_function(runtime.malloc, vec![ValType::I32; 4], malloc());
+            self.implement_function(runtime.free, vec![ValType::I32], free());
+        }
+        self.implement_function(
+            runtime.realloc,
+            vec![ValType::I32; 3],
+            realloc(runtime, bulk_memory),
+        );
+        self.implement_function(runtime.retain, vec![ValType::I32], retain());
+        self.implement_function(runtime.release, vec![ValType::I32; 2], release(runtime));
+        self.implement_function(runtime.copy, vec![], copy(bulk_memory));
+        self.implement_function(
+            runtime.string_concat,
+            vec![ValType::I32; 3],
+            string_concat(runtime),
+        );
+        self.implement_function(runtime.string_eq, vec![ValType::I32; 3], string_eq(runtime));
+        self.implement_function(
+            runtime.string_slice,
+            vec![ValType::I32; 2],
+            string_slice(runtime),
+        );
+        if self.target().wasi {
+            let fd_write = self.import_fd_write();
+            // two vectors of a pointer and a length, and the length written.
+            let iovecs = self.create_data(vec![0; 20]);
+            let newline_byte = self.create_data(vec![b'\n']);
+            let write_type = self.declare_function_type(vec![ValType::I32; 3], vec![]);
+            let write = self.declare_function(write_type);
+            self.implement_function(
+                write,
+                vec![],
+                io::write(runtime, fd_write, iovecs, newline_byte),
+            );
+            self.name_function(write, "rano_write");
+            runtime.write = Some(write);
+        }
+        let written = runtime.write.map(|write| {
+            (
+                write,
+                self.string_literal("panicked at "),
+                self.string_literal(": "),
+            )
+        });
+        let throws = self.target().exception_handling;
+        let body = panic::panic(runtime, written, throws);
+        if throws {
+            self.throw_panic_at(runtime.panic, body.len() - 2);
+        }
+        self.implement_function(runtime.panic, vec![], body);
+        for (name, id) in &[
+            ("rano_malloc", runtime.malloc),
+            ("rano_free", runtime.free),
+            ("rano_realloc", runtime.realloc),
+            ("rano_retain", runtime.retain),
+            ("rano_release", runtime.release),
+            ("rano_copy", runtime.copy),
+            ("rano_string_concat", runtime.string_concat),
+            ("rano_string_eq", runtime.string_eq),
+            ("rano_string_slice", runtime.string_slice),
+            ("rano_panic", runtime.panic),
+        ] {
+            self.name_function(*id, *name);
+        }
+        self.export_function("rano_malloc", runtime.malloc);
+        self.export_function("rano_free", runtime.free);
+        self.export_function("rano_realloc", runtime.realloc);
+        self.export_function("rano_retain", runtime.retain);
+        self.export_function("rano_release", runtime.release);
+        self.export_function("rano_string_concat", runtime.string_concat);
+        self.export_function("rano_string_eq", runtime.string_eq);
+        self.export_function("rano_string_slice", runtime.string_slice);
+        self.use_memory();
+        runtime
+    }
+}
@@ -0,0 +1,94 @@
+use std::convert::TryInto;
+
+use wasm_encoder::Instruction;
+
+use super::{word, Runtime, CALL_DEPTH, PANIC_LOCATION, PANIC_MESSAGE};
+
+/// What a module panicked with, read back from its memory.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Panic {
+    pub message: String,
+    /// Where the panic is in the source, as `file:line:column`.
+    pub location: String,
+}
+
+/// Reads the panic the module of the memory `memory` trapped with, if it
+/// panicked rather than trapping otherwise.
+pub fn read_panic(memory: &[u8]) -> Option<Panic> {
+    let word = |address: usize| -> Option<usize> {
+        let bytes = memory.get(address..address + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?) as usize)
+    };
+    let string = |address: usize| -> Option<String> {
+        let length = word(address)?;
+        let bytes = memory.get(address + 4..address + 4 + length)?;
+        Some(String::from_utf8_lossy(bytes).into_owned())
+    };
+    match word(PANIC_MESSAGE as usize)? {
+        0 => None,
+        message => Some(Panic {
+            message: string(message)?,
+            location: string(word(PANIC_LOCATION as usize)?)?,
+        }),
+    }
+}
+
+/// The locals are the `message` and its `location`, which are kept where
+/// the host finds them; the message is kept alive for it. With the `wasi`
+/// target, `written` is `rano_write` and the literals written around the
+/// location, `panicked at ` and `: `. With `throws`, the panic is thrown
+/// rather than trapping, by the `nop` before the `end`, having counted no
+/// call as running anymore.
+pub(super) fn panic<'a>(
+    runtime: Runtime,
+    written: Option<(u32, i32, i32)>,
+    throws: bool,
+) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (message, location) = (0, 1);
+    let mut instructions = vec![
+        I32Const(PANIC_LOCATION),
+        LocalGet(location),
+        I32Store(word(0)),
+        I32Const(PANIC_MESSAGE),
+        LocalGet(message),
+        I32Store(word(0)),
+    ];
+    if let Some((write, prefix, separator)) = written {
+        // the standard error.
+        let fd = 2;
+        instructions.extend(vec![
+            I32Const(prefix),
+            I32Const(fd),
+            I32Const(0),
+            Call(write),
+            LocalGet(location),
+            I32Const(fd),
+            I32Const(0),
+            Call(write),
+            I32Const(separator),
+            I32Const(fd),
+            I32Const(0),
+            Call(write),
+            // `rano_write` releases what it writes.
+            LocalGet(message),
+            Call(runtime.retain),
+            I32Const(fd),
+            I32Const(1),
+            Call(write),
+        ]);
+    }
+    match throws {
+        true => instructions.extend(vec![
+            I32Const(CALL_DEPTH),
+            I32Const(0),
+            I32Store(word(0)),
+            LocalGet(message),
+            LocalGet(location),
+            Nop,
+            End,
+        ]),
+        false => instructions.extend(vec![Unreachable, End]),
+    }
+    instructions
+}
@@ -0,0 +1,207 @@
+use wasm_encoder::{BlockType, Instruction};
+
+use super::{byte, word, Runtime};
+
+/// The locals are the `destination`, the `source` and the `length` left,
+/// which `memory.copy` copies all at once if there is `bulk_memory`.
+pub(super) fn copy<'a>(bulk_memory: bool) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (destination, source, length) = (0, 1, 2);
+    if bulk_memory {
+        return vec![
+            LocalGet(destination),
+            LocalGet(source),
+            LocalGet(length),
+            MemoryCopy { src: 0, dst: 0 },
+            End,
+        ];
+    }
+    vec![
+        Block(BlockType::Empty),
+        Loop(BlockType::Empty),
+        LocalGet(length),
+        I32Eqz,
+        BrIf(1),
+        LocalGet(destination),
+        LocalGet(source),
+        I32Load8_U(byte(0)),
+        I32Store8(byte(0)),
+        LocalGet(destination),
+        I32Const(1),
+        I32Add,
+        LocalSet(destination),
+        LocalGet(source),
+        I32Const(1),
+        I32Add,
+        LocalSet(source),
+        LocalGet(length),
+        I32Const(1),
+        I32Sub,
+        LocalSet(length),
+        Br(0),
+        End,
+        End,
+        End,
+    ]
+}
+
+/// The locals are the `lhs` and `rhs`, their lengths and the `string` they
+/// are copied into.
+pub(super) fn string_concat<'a>(runtime: Runtime) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (lhs, rhs, lhs_length, rhs_length, string) = (0, 1, 2, 3, 4);
+    vec![
+        LocalGet(lhs),
+        I32Load(word(0)),
+        LocalSet(lhs_length),
+        LocalGet(rhs),
+        I32Load(word(0)),
+        LocalSet(rhs_length),
+        I32Const(4),
+        LocalGet(lhs_length),
+        I32Add,
+        LocalGet(rhs_length),
+        I32Add,
+        Call(runtime.malloc),
+        LocalTee(string),
+        // a string can't be given back as `0`, so running out of memory
+        // traps.
+        I32Eqz,
+        If(BlockType::Empty),
+        Unreachable,
+        End,
+        LocalGet(string),
+        LocalGet(lhs_length),
+        LocalGet(rhs_length),
+        I32Add,
+        I32Store(word(0)),
+        LocalGet(string),
+        I32Const(4),
+        I32Add,
+        LocalGet(lhs),
+        I32Const(4),
+        I32Add,
+        LocalGet(lhs_length),
+        Call(runtime.copy),
+        LocalGet(string),
+        I32Const(4),
+        I32Add,
+        LocalGet(lhs_length),
+        I32Add,
+        LocalGet(rhs),
+        I32Const(4),
+        I32Add,
+        LocalGet(rhs_length),
+        Call(runtime.copy),
+        LocalGet(lhs),
+        Call(runtime.release),
+        LocalGet(rhs),
+        Call(runtime.release),
+        LocalGet(string),
+        End,
+    ]
+}
+
+/// The locals are the `lhs` and `rhs`, their `length`, the `index` of the
+/// byte compared and whether they are `equal`.
+pub(super) fn string_eq<'a>(runtime: Runtime) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (lhs, rhs, length, index, equal) = (0, 1, 2, 3, 4);
+    vec![
+        Block(BlockType::Empty),
+        LocalGet(lhs),
+        LocalGet(rhs),
+        I32Eq,
+        LocalTee(equal),
+        BrIf(0),
+        LocalGet(lhs),
+        I32Load(word(0)),
+        LocalTee(length),
+        LocalGet(rhs),
+        I32Load(word(0)),
+        I32Neq,
+        BrIf(0),
+        Loop(BlockType::Empty),
+        LocalGet(index),
+        LocalGet(length),
+        I32GeU,
+        If(BlockType::Empty),
+        I32Const(1),
+        LocalSet(equal),
+        Br(2),
+        End,
+        LocalGet(lhs),
+        LocalGet(index),
+        I32Add,
+        I32Load8_U(byte(4)),
+        LocalGet(rhs),
+        LocalGet(index),
+        I32Add,
+        I32Load8_U(byte(4)),
+        I32Neq,
+        BrIf(1),
+        LocalGet(index),
+        I32Const(1),
+        I32Add,
+        LocalSet(index),
+        Br(0),
+        End,
+        End,
+        LocalGet(lhs),
+        Call(runtime.release),
+        LocalGet(rhs),
+        Call(runtime.release),
+        LocalGet(equal),
+        End,
+    ]
+}
+
+/// The locals are the `string`, the `start` and `end` of the slice, its
+/// `length` and the `slice` it is copied into.
+pub(super) fn string_slice<'a>(runtime: Runtime) -> Vec<Instruction<'a>> {
+    use Instruction::*;
+    let (string, start, end, length, slice) = (0, 1, 2, 3, 4);
+    vec![
+        // compared unsigned, so a negative offset is out of bounds too.
+        LocalGet(start),
+        LocalGet(end),
+        I32GtU,
+        LocalGet(end),
+        LocalGet(string),
+        I32Load(word(0)),
+        I32GtU,
+        I32Or,
+        If(BlockType::Empty),
+        Unreachable,
+        End,
+        LocalGet(end),
+        LocalGet(start),
+        I32Sub,
+        LocalTee(length),
+        I32Const(4),
+        I32Add,
+        Call(runtime.malloc),
+        LocalTee(slice),
+        I32Eqz,
+        If(BlockType::Empty),
+        Unreachable,
+        End,
+        LocalGet(slice),
+        LocalGet(length),
+        I32Store(word(0)),
+        LocalGet(slice),
+        I32Const(4),
+        I32Add,
+        LocalGet(string),
+        I32Const(4),
+        I32Add,
+        LocalGet(start),
+        I32Add,
+        LocalGet(length),
+        Call(runtime.copy),
+        LocalGet(string),
+        Call(runtime.release),
+        LocalGet(slice),
+        End,
+    ]
+}
@@ -0,0 +1,208 @@
+use wasm_encoder::encoders;
+
+use crate::{
+    core::{ast::Literal, IntrinsicKind, TypeId},
+    syntax::{Span, Spanned},
+    thir::{BinaryOperator, Expression, ExpressionKind, Intrinsic},
+};
+
+use super::{Context, Error, Walker};
+
+/// An instruction of the SIMD proposal, which wasm-encoder has none of but
+/// `v128.const` yet, so a `nop` stands for it in the body it is in until
+/// `finish` writes it by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Simd {
+    /// One without immediates, by its opcode.
+    Plain(u32),
+    /// `v128.load` at the offset.
+    Load(u32),
+    /// `v128.store` at the offset.
+    Store(u32),
+    /// An instruction on the lane, by its opcode.
+    Lane(u32, u8),
+    /// `i8x16.shuffle`, by the byte of the two vectors each byte is taken
+    /// from.
+    Shuffle([u8; 16]),
+}
+
+impl Simd {
+    /// `i32x4.all_true`, whether no lane is zero, which is what comparing
+    /// the lanes gives for vectors of any type if they are all equal.
+    pub const ALL_TRUE: Simd = Simd::Plain(0xa3);
+
+    /// The vector of the type `vector` with every lane set to the one on
+    /// the stack.
+    pub fn splat(vector: TypeId) -> Simd {
+        Simd::Plain(pick(vector, [0x11, 0x12, 0x13, 0x14]))
+    }
+
+    /// The lane `lane` of the vector of the type `vector` on the stack.
+    pub fn extract_lane(vector: TypeId, lane: u8) -> Simd {
+        Simd::Lane(pick(vector, [0x1b, 0x1d, 0x1f, 0x21]), lane)
+    }
+
+    /// The vector of the type `vector` with the lane `lane` replaced by the
+    /// value on top of it.
+    pub fn replace_lane(vector: TypeId, lane: u8) -> Simd {
+        Simd::Lane(pick(vector, [0x1c, 0x1e, 0x20, 0x22]), lane)
+    }
+
+    /// The vector of the lanes `indices` of two vectors with as many lanes
+    /// as there are indices, the lanes of the second one following those of
+    /// the first.
+    pub fn shuffle(indices: &[u8]) -> Simd {
+        let width = 16 / indices.len().max(1);
+        let mut bytes = [0; 16];
+        for (byte, at) in bytes.iter_mut().enumerate() {
+            *at = (indices[byte / width] as usize * width + byte % width) as u8;
+        }
+        Simd::Shuffle(bytes)
+    }
+
+    /// The operation `operator` does on the lanes of two vectors of the type
+    /// `vector`, if the checker lets it.
+    pub fn lanewise(operator: BinaryOperator, vector: TypeId) -> Option<Simd> {
+        Some(Simd::Plain(match operator {
+            BinaryOperator::Add => pick(vector, [0xae, 0xce, 0xe4, 0xf0]),
+            BinaryOperator::Subtract => pick(vector, [0xb1, 0xd1, 0xe5, 0xf1]),
+            BinaryOperator::Multiply => pick(vector, [0xb5, 0xd5, 0xe6, 0xf2]),
+            BinaryOperator::Divide => match vector {
+                TypeId::F32X4 => 0xe7,
+                TypeId::F64X2 => 0xf3,
+                _ => return None,
+            },
+            BinaryOperator::BitAnd => 0x4e,
+            BinaryOperator::BitOr => 0x50,
+            BinaryOperator::BitXor => 0x51,
+            _ => return None,
+        }))
+    }
+
+    /// The lanes of two vectors of the type `vector` compared, each of all
+    /// ones if they are equal. The lanes of integers are equal if their
+    /// bits are, so `i32x4.eq` compares the ones of `i64x2` too.
+    pub fn equal(vector: TypeId) -> Simd {
+        Simd::Plain(pick(vector, [0x37, 0x37, 0x41, 0x47]))
+    }
+
+    /// Whether the instruction accesses the memory.
+    pub fn uses_memory(self) -> bool {
+        matches!(self, Simd::Load(_) | Simd::Store(_))
+    }
+
+    /// Writes the instruction to `code`. A load or a store is only aligned
+    /// to 4 bytes, like any value in the memory.
+    pub fn encode(self, code: &mut Vec<u8>) {
+        code.push(0xfd);
+        match self {
+            Simd::Plain(opcode) => code.extend(encoders::u32(opcode)),
+            Simd::Load(offset) => {
+                code.extend(encoders::u32(0x00));
+                code.extend(memory_argument(offset));
+            }
+            Simd::Store(offset) => {
+                code.extend(encoders::u32(0x0b));
+                code.extend(memory_argument(offset));
+            }
+            Simd::Lane(opcode, lane) => {
+                code.extend(encoders::u32(opcode));
+                code.push(lane);
+            }
+            Simd::Shuffle(bytes) => {
+                code.extend(encoders::u32(0x0d));
+                code.extend(bytes);
+            }
+        }
+    }
+}
+
+impl<'a> Context<'a> {
+    /// Lowers the call of a SIMD intrinsic on vectors of the type its type
+    /// argument is. The lanes it is given are immediates of the instruction
+    /// rather than values, so they are read off the literals.
+    pub fn walk_simd(
+        &mut self,
+        intrinsic: &Intrinsic,
+        arguments: &[Expression],
+    ) -> Result<(), Error> {
+        let vector = intrinsic.type_arguments[0];
+        let lanes = vector.lanes().map_or(0, |(_, lanes)| lanes);
+        match (intrinsic.kind, arguments) {
+            (IntrinsicKind::Splat, [lane]) => {
+                self.walk(lane)?;
+                self.push_simd(Simd::splat(vector));
+            }
+            (IntrinsicKind::ExtractLane, [operand, index]) => {
+                let lane = lane_index(index, lanes)?;
+                self.walk(operand)?;
+                self.push_simd(Simd::extract_lane(vector, lane));
+            }
+            (IntrinsicKind::ReplaceLane, [operand, index, value]) => {
+                let lane = lane_index(index, lanes)?;
+                self.walk(operand)?;
+                self.walk(value)?;
+                self.push_simd(Simd::replace_lane(vector, lane));
+            }
+            (IntrinsicKind::Shuffle, [a, b, indices]) => {
+                // an index picks one of the lanes of both vectors.
+                let indices = match &indices.kind {
+                    ExpressionKind::Tuple(elements) => elements
+                        .iter()
+                        .map(|index| lane_index(index, lanes * 2))
+                        .collect::<Result<Vec<_>, _>>()?,
+                    _ => return Err(Error::invalid_lane(indices.span(), lanes * 2)),
+                };
+                self.walk(a)?;
+                self.walk(b)?;
+                self.push_simd(Simd::shuffle(&indices));
+            }
+            // the checker checked the arguments against the signature.
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Checks a value of `ty` can be passed to or given by a function, which
+    /// one holding a vector can only be with the `simd` target.
+    pub fn check_vector_target(&self, ty: TypeId, location: Span) -> Result<(), Error> {
+        let vector = self
+            .leaves(ty)
+            .into_iter()
+            .find(|leaf| leaf.lanes().is_some());
+        match vector {
+            Some(vector) if !self.target().simd => Err(Error::type_needs_target(
+                self.types().display(vector),
+                "simd",
+                location,
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The lane `index` is, if it is an integer literal below `lanes`.
+fn lane_index(index: &Expression, lanes: u32) -> Result<u8, Error> {
+    let lane = match &index.kind {
+        ExpressionKind::Literal(Literal::Integer(integer)) => integer.value(),
+        _ => None,
+    };
+    match lane {
+        Some(lane) if lane < lanes as u64 => Ok(lane as u8),
+        _ => Err(Error::invalid_lane(index.span(), lanes)),
+    }
+}
+
+fn memory_argument(offset: u32) -> impl Iterator<Item = u8> {
+    encoders::u32(2).chain(encoders::u32(offset))
+}
+
+/// The one of `opcodes` for `i32x4`, `i64x2`, `f32x4` or `f64x2`.
+fn pick(vector: TypeId, opcodes: [u32; 4]) -> u32 {
+    match vector {
+        TypeId::I32X4 => opcodes[0],
+        TypeId::I64X2 => opcodes[1],
+        TypeId::F32X4 => opcodes[2],
+        _ => opcodes[3],
+    }
+}
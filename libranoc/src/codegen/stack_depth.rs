@@ -0,0 +1,162 @@
+use std::collections::{HashMap, HashSet};
+
+use wasm_encoder::{BlockType, Instruction, MemArg};
+
+use crate::{
+    core::{arena::Idx, NodeId},
+    semantic::Definition,
+    syntax::Span,
+    thir::{Block, Expression, ExpressionKind, Item, ModuleDeclaration, Statement},
+};
+
+use super::{runtime::CALL_DEPTH, Context};
+
+/// The functions of a module by their definition, with the ones each
+/// refers to, called or not, as it may call the ones it passes around.
+type CallGraph = HashMap<Idx<Definition>, (NodeId, HashSet<Idx<Definition>>)>;
+
+impl<'a> Context<'a> {
+    /// The items of the functions among `statements`, nested ones too,
+    /// which can call themselves, directly or through the others. Only
+    /// those can nest their calls deeper than the module has functions, so
+    /// they are the ones the depth of the calls is checked by.
+    pub fn find_recursive_functions(&self, statements: &[Statement]) -> HashSet<NodeId> {
+        let mut graph = CallGraph::new();
+        self.add_functions(statements, &mut graph);
+        graph
+            .iter()
+            .filter(|(definition, (_, callees))| {
+                let mut seen = HashSet::new();
+                let mut pending: Vec<_> = callees.iter().copied().collect();
+                while let Some(callee) = pending.pop() {
+                    if callee == **definition {
+                        return true;
+                    }
+                    if seen.insert(callee) {
+                        if let Some((_, callees)) = graph.get(&callee) {
+                            pending.extend(callees);
+                        }
+                    }
+                }
+                false
+            })
+            .map(|(_, (item, _))| *item)
+            .collect()
+    }
+
+    fn add_functions(&self, statements: &[Statement], graph: &mut CallGraph) {
+        for statement in statements {
+            match statement {
+                Statement::Item(Item::Function(function)) => {
+                    let mut callees = HashSet::new();
+                    if let Some(body) = &function.body {
+                        self.add_functions(&body.statements, graph);
+                        self.find_references_in(body, &mut callees);
+                    }
+                    if let Some(definition) = self.item_definition(function.id) {
+                        graph.insert(definition, (function.id, callees));
+                    }
+                }
+                Statement::Item(Item::Module(ModuleDeclaration {
+                    body: Some(body), ..
+                })) => self.add_functions(&body.statements, graph),
+                Statement::Item(_) => {}
+                Statement::Let(r#let) => {
+                    if let Some(value) = &r#let.value {
+                        self.add_functions_in(value, graph);
+                    }
+                }
+                Statement::Expression(expression) => self.add_functions_in(expression, graph),
+            }
+        }
+    }
+
+    /// Adds the functions declared in the blocks of `expression`.
+    fn add_functions_in(&self, expression: &Expression, graph: &mut CallGraph) {
+        for block in expression.blocks() {
+            self.add_functions(&block.statements, graph);
+            if let Some(value) = &block.value {
+                self.add_functions_in(value, graph);
+            }
+        }
+        for operand in expression.operands() {
+            self.add_functions_in(operand, graph);
+        }
+    }
+
+    /// The definitions the names in `block` refer to, leaving out the
+    /// functions declared in it, which refer to theirs.
+    fn find_references_in(&self, block: &Block, references: &mut HashSet<Idx<Definition>>) {
+        for statement in &block.statements {
+            match statement {
+                Statement::Item(_) => {}
+                Statement::Let(r#let) => {
+                    if let Some(value) = &r#let.value {
+                        self.find_references(value, references);
+                    }
+                }
+                Statement::Expression(expression) => self.find_references(expression, references),
+            }
+        }
+        if let Some(value) = &block.value {
+            self.find_references(value, references);
+        }
+    }
+
+    fn find_references(&self, expression: &Expression, references: &mut HashSet<Idx<Definition>>) {
+        if let ExpressionKind::Name(_) | ExpressionKind::Path(_) = &expression.kind {
+            references.extend(self.referred_definition(expression.id));
+        }
+        for block in expression.blocks() {
+            self.find_references_in(block, references);
+        }
+        for operand in expression.operands() {
+            self.find_references(operand, references);
+        }
+    }
+
+    /// Starts a function the calls of which are counted, panicking with a
+    /// stack overflow at `span` if `limit` of them are running already.
+    pub fn enter_call_depth(&mut self, limit: u32, span: &Span) {
+        use Instruction::*;
+        self.use_memory();
+        self.instructions.extend(vec![
+            I32Const(CALL_DEPTH),
+            I32Load(word(0)),
+            I32Const(limit as i32),
+            I32GeU,
+            If(BlockType::Empty),
+        ]);
+        let message = self.string_literal("stack overflow");
+        self.instructions.push(I32Const(message));
+        self.panic(span);
+        self.instructions.push(End);
+        self.add_call_depth(1);
+    }
+
+    /// Leaves a function the calls of which are counted.
+    pub fn leave_call_depth(&mut self) {
+        self.add_call_depth(-1);
+    }
+
+    fn add_call_depth(&mut self, delta: i32) {
+        use Instruction::*;
+        self.instructions.extend(vec![
+            I32Const(CALL_DEPTH),
+            I32Const(CALL_DEPTH),
+            I32Load(word(0)),
+            I32Const(delta),
+            I32Add,
+            I32Store(word(0)),
+        ]);
+    }
+}
+
+/// The counter is a word of the state of the runtime.
+fn word(offset: u32) -> MemArg {
+    MemArg {
+        offset,
+        align: 2,
+        memory_index: 0,
+    }
+}
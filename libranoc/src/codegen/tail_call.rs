@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use crate::{
+    core::{ast::Name, NodeId},
+    thir::{Block, ExpressionKind, Function},
+};
+
+use super::Context;
+
+/// The calls a function makes of itself in a tail position, which don't
+/// need to return to it.
+#[derive(Debug, Default)]
+pub struct TailCalls {
+    /// The index of the function.
+    pub function: u32,
+    /// The calls by the name expression of their callee, with how many
+    /// `if`s they are inside of.
+    pub calls: HashMap<NodeId, u32>,
+}
+
+impl<'a> Context<'a> {
+    /// The calls the function `function` of the index `id` makes of itself
+    /// in a tail position, which is the value of its body, or of a block or
+    /// a branch of an `if` in one.
+    pub fn find_tail_calls(&self, id: u32, function: &Function) -> TailCalls {
+        let mut calls = HashMap::new();
+        if let Some(body) = &function.body {
+            self.find_tail_calls_in(function.id, body, 0, &mut calls);
+        }
+        TailCalls {
+            function: id,
+            calls,
+        }
+    }
+
+    fn find_tail_calls_in(
+        &self,
+        item: NodeId,
+        block: &Block,
+        depth: u32,
+        calls: &mut HashMap<NodeId, u32>,
+    ) {
+        let mut value = match &block.value {
+            Some(value) => &**value,
+            None => return,
+        };
+        // `(a)` is `a` in parentheses.
+        while let ExpressionKind::Tuple(elements) = &value.kind {
+            match elements.as_slice() {
+                [element] => value = element,
+                _ => return,
+            }
+        }
+        match &value.kind {
+            ExpressionKind::Call(call)
+                if matches!(call.callee.kind, ExpressionKind::Name(Name::Ident(_)))
+                    && self.refers_to_item(call.callee.id, item) =>
+            {
+                calls.insert(call.callee.id, depth);
+            }
+            ExpressionKind::Block(block) => self.find_tail_calls_in(item, block, depth, calls),
+            ExpressionKind::Unsafe(r#unsafe) => {
+                self.find_tail_calls_in(item, &r#unsafe.block, depth, calls)
+            }
+            ExpressionKind::If(r#if) => {
+                if let Some(else_block) = &r#if.else_block {
+                    self.find_tail_calls_in(item, &r#if.body, depth + 1, calls);
+                    self.find_tail_calls_in(item, else_block, depth + 1, calls);
+                }
+            }
+            _ => {}
+        }
+    }
+}
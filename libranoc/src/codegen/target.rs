@@ -0,0 +1,82 @@
+/// What the module is compiled for, which is the WASM proposals the engine
+/// running it is expected to support. It is set for a build by
+/// `CompileOptions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TargetOptions {
+    /// Whether the module is a WASI command: an `extern fn` named after a
+    /// function of WASI imports it, `@print` and the like write to the
+    /// standard streams, and `_start` runs `main`.
+    pub wasi: bool,
+    /// Whether a function or an `if` can give more values than one, which
+    /// the values of a small tuple are then given as. Otherwise the first
+    /// is given, and the others are passed back through the memory.
+    pub multi_value: bool,
+    /// Whether the `v128` type and its instructions can be used.
+    pub simd: bool,
+    /// Whether `memory.copy` and `memory.fill` can be used, which the
+    /// runtime copies with rather than by loops, and `@memory_copy` and
+    /// `@memory_fill` need.
+    pub bulk_memory: bool,
+    /// Whether `return_call` can be used, so a call in a tail position
+    /// doesn't grow the stack.
+    pub tail_call: bool,
+    /// Whether the memory can be shared between threads, and the atomic
+    /// instructions used on it. The memory is then imported as `env.memory`,
+    /// shared, so the host can give it to the instances running on other
+    /// threads, and the data is copied into it once, by the first instance.
+    /// It needs `bulk_memory`, which enabling it enables too.
+    pub threads: bool,
+    /// Whether a panic throws an exception of the tag the module exports as
+    /// `rano_panic_tag`, with its message and location, rather than
+    /// trapping, so the host can catch it and keep calling the instance.
+    pub exception_handling: bool,
+}
+
+/// The names of the proposals which can be enabled, as the command line
+/// spells them.
+pub const FEATURES: &[&str] = &[
+    "multi-value",
+    "simd",
+    "bulk-memory",
+    "tail-call",
+    "threads",
+    "exception-handling",
+];
+
+impl TargetOptions {
+    /// Enables the proposal `name`, one of `FEATURES`, returning whether
+    /// there is one of the name, and `bulk-memory` with `threads`.
+    pub fn enable(&mut self, name: &str) -> bool {
+        if name == "threads" {
+            self.bulk_memory = true;
+        }
+        match self.feature(name) {
+            Some(enabled) => {
+                *enabled = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the proposal `name` is enabled, or the target `name` for
+    /// `wasi`.
+    pub fn enables(mut self, name: &str) -> bool {
+        match name {
+            "wasi" => self.wasi,
+            _ => matches!(self.feature(name), Some(&mut true)),
+        }
+    }
+
+    fn feature(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "multi-value" => Some(&mut self.multi_value),
+            "simd" => Some(&mut self.simd),
+            "bulk-memory" => Some(&mut self.bulk_memory),
+            "tail-call" => Some(&mut self.tail_call),
+            "threads" => Some(&mut self.threads),
+            "exception-handling" => Some(&mut self.exception_handling),
+            _ => None,
+        }
+    }
+}
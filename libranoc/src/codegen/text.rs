@@ -0,0 +1,759 @@
+//! The text format of a compiled module. It is printed from the binary, so
+//! it can't diverge from what is run, after validating it, so what is
+//! printed is known to be a module.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Write},
+};
+
+use wasmparser::{
+    BinaryReaderError, DataKind, ElementItem, ElementKind, ExternalKind, FuncType,
+    ImportSectionEntryType, InitExpr, MemoryImmediate, MemoryType, Name, NameSectionReader,
+    Operator, Parser, Payload, ResizableLimits, Type, TypeDef, TypeOrFuncType, Validator,
+    WasmFeatures,
+};
+
+use crate::core::Error;
+
+use super::TargetOptions;
+
+/// The WASM text of the module `wasm`, with the functions and locals named
+/// by its `name` section. It is validated against the proposals `target`
+/// enables.
+pub fn print_text(wasm: &[u8], target: TargetOptions) -> Result<String, Error> {
+    if target.exception_handling {
+        return Err(Error::unsupported_target(
+            "exception-handling",
+            "the text format can't be printed with the tags of the proposal",
+        ));
+    }
+    let mut validator = Validator::new();
+    validator.wasm_features(WasmFeatures {
+        multi_value: target.multi_value,
+        simd: target.simd,
+        bulk_memory: target.bulk_memory,
+        tail_call: target.tail_call,
+        threads: target.threads,
+        ..WasmFeatures::default()
+    });
+    validator
+        .validate_all(wasm)
+        .map_err(Error::invalid_module)?;
+    let mut printer = Printer::default();
+    printer.read_names(wasm).map_err(Error::invalid_module)?;
+    printer.print(wasm).map_err(Error::invalid_module)?;
+    Ok(printer.text)
+}
+
+/// What goes wrong while printing: the module was already validated, so
+/// it can only be reading it again, or writing to the string.
+#[derive(Debug)]
+enum PrintError {
+    Read(BinaryReaderError),
+    Write(fmt::Error),
+}
+
+impl From<BinaryReaderError> for PrintError {
+    fn from(error: BinaryReaderError) -> Self {
+        PrintError::Read(error)
+    }
+}
+
+impl From<fmt::Error> for PrintError {
+    fn from(error: fmt::Error) -> Self {
+        PrintError::Write(error)
+    }
+}
+
+impl fmt::Display for PrintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrintError::Read(error) => write!(f, "{}", error),
+            PrintError::Write(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Printer {
+    text: String,
+    types: Vec<FuncType>,
+    /// The type of each function, imported ones first.
+    functions: Vec<u32>,
+    imported_functions: u32,
+    /// The names of the functions, which are only kept if they are valid
+    /// identifiers and the first of their spelling.
+    function_names: HashMap<u32, String>,
+    local_names: HashMap<u32, HashMap<u32, String>>,
+}
+
+impl Printer {
+    /// Reads the types of the functions and their names ahead, as the
+    /// `name` section comes after the code.
+    fn read_names(&mut self, wasm: &[u8]) -> Result<(), PrintError> {
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::TypeSection(reader) => {
+                    for ty in reader {
+                        match ty? {
+                            TypeDef::Func(ty) => self.types.push(ty),
+                            // not valid without the module linking
+                            // proposal, which isn't enabled.
+                            _ => self.types.push(FuncType {
+                                params: Box::new([]),
+                                returns: Box::new([]),
+                            }),
+                        }
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        if let ImportSectionEntryType::Function(ty) = import?.ty {
+                            self.functions.push(ty);
+                            self.imported_functions += 1;
+                        }
+                    }
+                }
+                Payload::FunctionSection(reader) => {
+                    for ty in reader {
+                        self.functions.push(ty?);
+                    }
+                }
+                Payload::CustomSection {
+                    name: "name",
+                    data,
+                    data_offset,
+                } => self.read_name_section(data, data_offset)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn read_name_section(&mut self, data: &[u8], offset: usize) -> Result<(), PrintError> {
+        for name in NameSectionReader::new(data, offset)? {
+            match name? {
+                Name::Function(names) => {
+                    let mut map = names.get_map()?;
+                    let mut seen = HashSet::new();
+                    for _ in 0..map.get_count() {
+                        let naming = map.read()?;
+                        if is_identifier(naming.name) && seen.insert(naming.name) {
+                            self.function_names
+                                .insert(naming.index, naming.name.to_owned());
+                        }
+                    }
+                }
+                Name::Local(names) => {
+                    let mut reader = names.get_function_local_reader()?;
+                    for _ in 0..reader.get_count() {
+                        let function = reader.read()?;
+                        let mut map = function.get_map()?;
+                        let mut seen = HashSet::new();
+                        let locals = self.local_names.entry(function.func_index).or_default();
+                        for _ in 0..map.get_count() {
+                            let naming = map.read()?;
+                            if is_identifier(naming.name) && seen.insert(naming.name) {
+                                locals.insert(naming.index, naming.name.to_owned());
+                            }
+                        }
+                    }
+                }
+                Name::Module(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn print(&mut self, wasm: &[u8]) -> Result<(), PrintError> {
+        let (mut tables, mut memories, mut globals) = (0, 0, 0);
+        let (mut elements, mut data) = (0, 0);
+        let mut function = self.imported_functions;
+        let mut imported_function = 0;
+        writeln!(self.text, "(module")?;
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload? {
+                Payload::TypeSection(_) => {
+                    for (index, ty) in self.types.iter().enumerate() {
+                        writeln!(self.text, "  (type (;{};) (func{}))", index, signature(ty))?;
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        let import = import?;
+                        write!(
+                            self.text,
+                            "  (import {} {} ",
+                            string(import.module.as_bytes()),
+                            string(import.field.unwrap_or_default().as_bytes())
+                        )?;
+                        match import.ty {
+                            ImportSectionEntryType::Function(ty) => {
+                                write!(
+                                    self.text,
+                                    "(func{} (;{};) (type {}))",
+                                    self.id(imported_function),
+                                    imported_function,
+                                    ty
+                                )?;
+                                imported_function += 1;
+                            }
+                            ImportSectionEntryType::Table(ty) => {
+                                write!(
+                                    self.text,
+                                    "(table (;{};) {} {})",
+                                    tables,
+                                    limits(ty.limits),
+                                    value_type(ty.element_type)
+                                )?;
+                                tables += 1;
+                            }
+                            ImportSectionEntryType::Memory(ty) => {
+                                write!(self.text, "(memory (;{};) {})", memories, memory(ty))?;
+                                memories += 1;
+                            }
+                            ImportSectionEntryType::Global(ty) => {
+                                write!(
+                                    self.text,
+                                    "(global (;{};) {})",
+                                    globals,
+                                    global_type(ty.content_type, ty.mutable)
+                                )?;
+                                globals += 1;
+                            }
+                            ImportSectionEntryType::Module(_)
+                            | ImportSectionEntryType::Instance(_) => {}
+                        }
+                        writeln!(self.text, ")")?;
+                    }
+                }
+                Payload::TableSection(reader) => {
+                    for ty in reader {
+                        let ty = ty?;
+                        writeln!(
+                            self.text,
+                            "  (table (;{};) {} {})",
+                            tables,
+                            limits(ty.limits),
+                            value_type(ty.element_type)
+                        )?;
+                        tables += 1;
+                    }
+                }
+                Payload::MemorySection(reader) => {
+                    for ty in reader {
+                        writeln!(self.text, "  (memory (;{};) {})", memories, memory(ty?))?;
+                        memories += 1;
+                    }
+                }
+                Payload::GlobalSection(reader) => {
+                    for global in reader {
+                        let global = global?;
+                        writeln!(
+                            self.text,
+                            "  (global (;{};) {} {})",
+                            globals,
+                            global_type(global.ty.content_type, global.ty.mutable),
+                            self.init_expr(global.init_expr)?
+                        )?;
+                        globals += 1;
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export?;
+                        let item = match export.kind {
+                            ExternalKind::Function => {
+                                format!("func {}", self.function(export.index))
+                            }
+                            ExternalKind::Table => format!("table {}", export.index),
+                            ExternalKind::Memory => format!("memory {}", export.index),
+                            ExternalKind::Global => format!("global {}", export.index),
+                            ExternalKind::Type => format!("type {}", export.index),
+                            ExternalKind::Module => format!("module {}", export.index),
+                            ExternalKind::Instance => format!("instance {}", export.index),
+                        };
+                        writeln!(
+                            self.text,
+                            "  (export {} ({}))",
+                            string(export.field.as_bytes()),
+                            item
+                        )?;
+                    }
+                }
+                Payload::StartSection { func, .. } => {
+                    writeln!(self.text, "  (start {})", self.function(func))?;
+                }
+                Payload::ElementSection(reader) => {
+                    for element in reader {
+                        let element = element?;
+                        write!(self.text, "  (elem (;{};)", elements)?;
+                        match element.kind {
+                            ElementKind::Active {
+                                table_index,
+                                init_expr,
+                            } => {
+                                if table_index != 0 {
+                                    write!(self.text, " (table {})", table_index)?;
+                                }
+                                write!(self.text, " {}", self.init_expr(init_expr)?)?;
+                            }
+                            ElementKind::Passive => {}
+                            ElementKind::Declared => write!(self.text, " declare")?,
+                        }
+                        let mut items = Vec::new();
+                        for item in element.items.get_items_reader()? {
+                            items.push(item?);
+                        }
+                        // a list of functions has a shorter form.
+                        if items
+                            .iter()
+                            .all(|item| matches!(item, ElementItem::Func(_)))
+                        {
+                            write!(self.text, " func")?;
+                        } else {
+                            write!(self.text, " {}", value_type(element.ty))?;
+                        }
+                        for item in items {
+                            let item = match item {
+                                ElementItem::Func(index) if element.ty == Type::FuncRef => {
+                                    self.function(index)
+                                }
+                                ElementItem::Func(index) => {
+                                    format!("(ref.func {})", self.function(index))
+                                }
+                                ElementItem::Null(ty) => format!("(ref.null {})", heap_type(ty)),
+                            };
+                            write!(self.text, " {}", item)?;
+                        }
+                        writeln!(self.text, ")")?;
+                        elements += 1;
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let ty = &self.types[self.functions[function as usize] as usize];
+                    let names = self.local_names.get(&function);
+                    let name = |local: u32| match names.and_then(|names| names.get(&local)) {
+                        Some(name) => format!(" ${}", name),
+                        None => String::new(),
+                    };
+                    let mut header = format!(
+                        "  (func{} (;{};) (type {})",
+                        self.id(function),
+                        function,
+                        self.functions[function as usize]
+                    );
+                    let mut local = 0;
+                    for param in ty.params.iter() {
+                        write!(header, " (param{} {})", name(local), value_type(*param))?;
+                        local += 1;
+                    }
+                    if !ty.returns.is_empty() {
+                        write!(header, " (result{})", types(&ty.returns))?;
+                    }
+                    writeln!(self.text, "{}", header)?;
+                    let mut locals = Vec::new();
+                    for group in body.get_locals_reader()? {
+                        let (count, ty) = group?;
+                        for _ in 0..count {
+                            locals.push(format!("(local{} {})", name(local), value_type(ty)));
+                            local += 1;
+                        }
+                    }
+                    if !locals.is_empty() {
+                        writeln!(self.text, "    {}", locals.join(" "))?;
+                    }
+                    let mut depth = 0;
+                    for operator in body.get_operators_reader()? {
+                        let operator = operator?;
+                        match operator {
+                            // the end of the body closes the function.
+                            Operator::End if depth == 0 => break,
+                            Operator::End => depth -= 1,
+                            Operator::Else => depth -= 1,
+                            _ => {}
+                        }
+                        let text = self.operator(&operator, names)?;
+                        writeln!(self.text, "    {}{}", "  ".repeat(depth), text)?;
+                        if let Operator::Block { .. }
+                        | Operator::Loop { .. }
+                        | Operator::If { .. }
+                        | Operator::Else = operator
+                        {
+                            depth += 1;
+                        }
+                    }
+                    writeln!(self.text, "  )")?;
+                    function += 1;
+                }
+                Payload::DataSection(reader) => {
+                    for segment in reader {
+                        let segment = segment?;
+                        write!(self.text, "  (data (;{};)", data)?;
+                        if let DataKind::Active {
+                            memory_index,
+                            init_expr,
+                        } = segment.kind
+                        {
+                            if memory_index != 0 {
+                                write!(self.text, " (memory {})", memory_index)?;
+                            }
+                            write!(self.text, " {}", self.init_expr(init_expr)?)?;
+                        }
+                        writeln!(self.text, " {})", string(segment.data))?;
+                        data += 1;
+                    }
+                }
+                // the text format has no custom sections, so they are only
+                // noted.
+                Payload::CustomSection { name, data, .. } => {
+                    writeln!(
+                        self.text,
+                        "  ;; custom section {}, {} bytes",
+                        string(name.as_bytes()),
+                        data.len()
+                    )?;
+                }
+                _ => {}
+            }
+        }
+        writeln!(self.text, ")")?;
+        Ok(())
+    }
+
+    /// The identifier of the function `index` to define it with, if it is
+    /// named.
+    fn id(&self, index: u32) -> String {
+        match self.function_names.get(&index) {
+            Some(name) => format!(" ${}", name),
+            None => String::new(),
+        }
+    }
+
+    /// The function `index` as it is referred to.
+    fn function(&self, index: u32) -> String {
+        match self.function_names.get(&index) {
+            Some(name) => format!("${}", name),
+            None => index.to_string(),
+        }
+    }
+
+    fn init_expr(&self, init_expr: InitExpr) -> Result<String, PrintError> {
+        let mut operators = Vec::new();
+        for operator in init_expr.get_operators_reader() {
+            match operator? {
+                Operator::End => break,
+                operator => operators.push(self.operator(&operator, None)?),
+            }
+        }
+        Ok(format!("({})", operators.join(" ")))
+    }
+
+    /// The text of `operator`, in a function whose locals are named by
+    /// `locals`.
+    fn operator(
+        &self,
+        operator: &Operator,
+        locals: Option<&HashMap<u32, String>>,
+    ) -> Result<String, PrintError> {
+        use Operator::*;
+        let local = |index: &u32| match locals.and_then(|locals| locals.get(index)) {
+            Some(name) => format!("${}", name),
+            None => index.to_string(),
+        };
+        let text = match operator {
+            Block { ty } => format!("block{}", block_type(*ty)),
+            Loop { ty } => format!("loop{}", block_type(*ty)),
+            If { ty } => format!("if{}", block_type(*ty)),
+            Br { relative_depth } => format!("br {}", relative_depth),
+            BrIf { relative_depth } => format!("br_if {}", relative_depth),
+            BrTable { table } => {
+                let mut text = String::from("br_table");
+                for target in table.targets() {
+                    write!(text, " {}", target?.0)?;
+                }
+                text
+            }
+            Call { function_index } => format!("call {}", self.function(*function_index)),
+            CallIndirect { index, table_index } => {
+                format!("call_indirect {} (type {})", table_index, index)
+            }
+            ReturnCall { function_index } => {
+                format!("return_call {}", self.function(*function_index))
+            }
+            ReturnCallIndirect { index, table_index } => {
+                format!("return_call_indirect {} (type {})", table_index, index)
+            }
+            TypedSelect { ty } => format!("select (result {})", value_type(*ty)),
+            LocalGet { local_index } => format!("local.get {}", local(local_index)),
+            LocalSet { local_index } => format!("local.set {}", local(local_index)),
+            LocalTee { local_index } => format!("local.tee {}", local(local_index)),
+            GlobalGet { global_index } => format!("global.get {}", global_index),
+            GlobalSet { global_index } => format!("global.set {}", global_index),
+            I32Load { memarg }
+            | I64Load { memarg }
+            | F32Load { memarg }
+            | F64Load { memarg }
+            | I32Load8S { memarg }
+            | I32Load8U { memarg }
+            | I32Load16S { memarg }
+            | I32Load16U { memarg }
+            | I64Load8S { memarg }
+            | I64Load8U { memarg }
+            | I64Load16S { memarg }
+            | I64Load16U { memarg }
+            | I64Load32S { memarg }
+            | I64Load32U { memarg }
+            | I32Store { memarg }
+            | I64Store { memarg }
+            | F32Store { memarg }
+            | F64Store { memarg }
+            | I32Store8 { memarg }
+            | I32Store16 { memarg }
+            | I64Store8 { memarg }
+            | I64Store16 { memarg }
+            | I64Store32 { memarg } => {
+                let name = mnemonic(operator);
+                format!("{}{}", name, memory_immediate(&name, *memarg))
+            }
+            MemorySize { .. } => String::from("memory.size"),
+            MemoryGrow { .. } => String::from("memory.grow"),
+            I32Const { value } => format!("i32.const {}", value),
+            I64Const { value } => format!("i64.const {}", value),
+            F32Const { value } => format!("f32.const {}", f32_text(value.bits())),
+            F64Const { value } => format!("f64.const {}", f64_text(value.bits())),
+            RefNull { ty } => format!("ref.null {}", heap_type(*ty)),
+            RefFunc { function_index } => format!("ref.func {}", self.function(*function_index)),
+            MemoryInit { segment, .. } => format!("memory.init {}", segment),
+            DataDrop { segment } => format!("data.drop {}", segment),
+            MemoryCopy { .. } => String::from("memory.copy"),
+            MemoryFill { .. } => String::from("memory.fill"),
+            MemoryAtomicNotify { memarg } => {
+                format!("memory.atomic.notify{}", memory_immediate("i32", *memarg))
+            }
+            MemoryAtomicWait32 { memarg } => {
+                format!("memory.atomic.wait32{}", memory_immediate("i32", *memarg))
+            }
+            AtomicFence { .. } => String::from("atomic.fence"),
+            I32AtomicLoad { memarg }
+            | I32AtomicStore { memarg }
+            | I32AtomicRmwAdd { memarg }
+            | I32AtomicRmwSub { memarg }
+            | I32AtomicRmwAnd { memarg }
+            | I32AtomicRmwOr { memarg }
+            | I32AtomicRmwXor { memarg }
+            | I32AtomicRmwXchg { memarg }
+            | I32AtomicRmwCmpxchg { memarg } => {
+                // the words of the name are separated by dots past the type.
+                let name = mnemonic(operator).replace('_', ".");
+                format!("{}{}", name, memory_immediate(&name, *memarg))
+            }
+            V128Load { memarg } | V128Store { memarg } => {
+                let name = mnemonic(operator);
+                format!("{}{}", name, memory_immediate(&name, *memarg))
+            }
+            V128Const { value } => {
+                let words: Vec<_> = value
+                    .bytes()
+                    .chunks(4)
+                    .map(|word| {
+                        format!(
+                            "0x{:08x}",
+                            u32::from_le_bytes([word[0], word[1], word[2], word[3]])
+                        )
+                    })
+                    .collect();
+                format!("v128.const i32x4 {}", words.join(" "))
+            }
+            I32x4ExtractLane { lane }
+            | I32x4ReplaceLane { lane }
+            | I64x2ExtractLane { lane }
+            | I64x2ReplaceLane { lane }
+            | F32x4ExtractLane { lane }
+            | F32x4ReplaceLane { lane }
+            | F64x2ExtractLane { lane }
+            | F64x2ReplaceLane { lane } => format!("{} {}", mnemonic(operator), lane),
+            I8x16Shuffle { lanes } => {
+                let lanes: Vec<_> = lanes.iter().map(u8::to_string).collect();
+                format!("i8x16.shuffle {}", lanes.join(" "))
+            }
+            // the rest have no immediates.
+            _ => mnemonic(operator),
+        };
+        Ok(text)
+    }
+}
+
+/// The name of the instruction `operator`, made from the name of its
+/// variant: `I32TruncSatF32S` is `i32.trunc_sat_f32_s`.
+fn mnemonic(operator: &Operator) -> String {
+    let variant = format!("{:?}", operator);
+    let variant = variant
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    let mut words: Vec<String> = Vec::new();
+    for c in variant.chars() {
+        match words.last_mut() {
+            Some(word) if !c.is_ascii_uppercase() => word.push(c),
+            _ => words.push(c.to_ascii_lowercase().to_string()),
+        }
+    }
+    match words.split_first() {
+        Some((first, [])) => first.clone(),
+        Some((first, rest)) => format!("{}.{}", first, rest.join("_")),
+        None => String::new(),
+    }
+}
+
+/// The offset and alignment of a load or store, unless they are the
+/// default, which is the natural alignment of its width.
+fn memory_immediate(name: &str, memarg: MemoryImmediate) -> String {
+    let operation = name.split('.').nth(1).unwrap_or_default();
+    let width: String = operation
+        .trim_start_matches(|c: char| c.is_ascii_alphabetic())
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    let natural = match width.as_str() {
+        "8" => 0,
+        "16" => 1,
+        "32" => 2,
+        _ if name.starts_with("i64") || name.starts_with("f64") => 3,
+        _ if name.starts_with("v128") => 4,
+        _ => 2,
+    };
+    let mut text = String::new();
+    if memarg.offset != 0 {
+        text.push_str(&format!(" offset={}", memarg.offset));
+    }
+    if memarg.align != natural {
+        text.push_str(&format!(" align={}", 1u32 << memarg.align));
+    }
+    text
+}
+
+fn signature(ty: &FuncType) -> String {
+    let mut text = String::new();
+    if !ty.params.is_empty() {
+        text.push_str(&format!(" (param{})", types(&ty.params)));
+    }
+    if !ty.returns.is_empty() {
+        text.push_str(&format!(" (result{})", types(&ty.returns)));
+    }
+    text
+}
+
+fn types(types: &[Type]) -> String {
+    types
+        .iter()
+        .map(|ty| format!(" {}", value_type(*ty)))
+        .collect()
+}
+
+fn value_type(ty: Type) -> &'static str {
+    match ty {
+        Type::I32 => "i32",
+        Type::I64 => "i64",
+        Type::F32 => "f32",
+        Type::F64 => "f64",
+        Type::V128 => "v128",
+        Type::FuncRef => "funcref",
+        Type::ExternRef => "externref",
+        Type::Func => "func",
+        Type::EmptyBlockType => "",
+    }
+}
+
+/// A reference type as the `ref.null` instruction spells it.
+fn heap_type(ty: Type) -> &'static str {
+    match ty {
+        Type::ExternRef => "extern",
+        _ => "func",
+    }
+}
+
+fn global_type(ty: Type, mutable: bool) -> String {
+    if mutable {
+        format!("(mut {})", value_type(ty))
+    } else {
+        value_type(ty).to_owned()
+    }
+}
+
+fn block_type(ty: TypeOrFuncType) -> String {
+    match ty {
+        TypeOrFuncType::Type(Type::EmptyBlockType) => String::new(),
+        TypeOrFuncType::Type(ty) => format!(" (result {})", value_type(ty)),
+        TypeOrFuncType::FuncType(index) => format!(" (type {})", index),
+    }
+}
+
+fn limits(limits: ResizableLimits) -> String {
+    match limits.maximum {
+        Some(maximum) => format!("{} {}", limits.initial, maximum),
+        None => limits.initial.to_string(),
+    }
+}
+
+fn memory(ty: MemoryType) -> String {
+    match ty {
+        MemoryType::M32 { limits: l, shared } => {
+            format!("{}{}", limits(l), if shared { " shared" } else { "" })
+        }
+        MemoryType::M64 { limits } => match limits.maximum {
+            Some(maximum) => format!("i64 {} {}", limits.initial, maximum),
+            None => format!("i64 {}", limits.initial),
+        },
+    }
+}
+
+/// A float, exact either way: the shortest decimal that reads back as
+/// it, or the payload of a NaN.
+fn f32_text(bits: u32) -> String {
+    let value = f32::from_bits(bits);
+    let sign = if value.is_sign_negative() { "-" } else { "" };
+    if value.is_nan() {
+        format!("{}nan:0x{:x}", sign, bits & 0x7f_ffff)
+    } else if value.is_infinite() {
+        format!("{}inf", sign)
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+fn f64_text(bits: u64) -> String {
+    let value = f64::from_bits(bits);
+    let sign = if value.is_sign_negative() { "-" } else { "" };
+    if value.is_nan() {
+        format!("{}nan:0x{:x}", sign, bits & 0xf_ffff_ffff_ffff)
+    } else if value.is_infinite() {
+        format!("{}inf", sign)
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+/// A string literal of the bytes, escaping what isn't printable ASCII.
+fn string(bytes: &[u8]) -> String {
+    let mut text = String::from("\"");
+    for byte in bytes {
+        match byte {
+            b'"' => text.push_str("\\\""),
+            b'\\' => text.push_str("\\\\"),
+            0x20..=0x7e => text.push(*byte as char),
+            _ => text.push_str(&format!("\\{:02x}", byte)),
+        }
+    }
+    text.push('"');
+    text
+}
+
+/// Whether `name` can be written as an identifier, after a `$`.
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-./:<=>?@\\^_`|~".contains(c))
+}
@@ -0,0 +1,249 @@
+use wasm_encoder::{encoders, ImportSection, Instruction, MemArg, Section};
+
+use crate::{
+    core::{ast::Name, IntrinsicKind, Type, TypeId, Types},
+    syntax::{Span, Spanned},
+    thir::{Block, Expression, ExpressionKind, Function, Statement},
+};
+
+use super::{runtime::INITIALIZED, Context, Error};
+
+/// The pages the shared memory can grow to, which it must have a limit of.
+pub const MAX_SHARED_PAGES: u32 = 16 * 1024;
+
+/// Whether a value of `ty` is held without pointing into the heap, which
+/// is every value a `#[thread_safe]` function may have.
+pub fn is_plain_data(types: &Types, ty: TypeId) -> bool {
+    match types.get(ty) {
+        Type::I32 | Type::I64 | Type::F32 | Type::F64 | Type::Bool => true,
+        Type::Tuple(elements) => elements.iter().all(|ty| is_plain_data(types, *ty)),
+        _ => false,
+    }
+}
+
+/// An instruction of the threads proposal, which wasm-encoder has none of
+/// yet, so a `nop` stands for it in the body it is in until `finish` writes
+/// it by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Atomic {
+    Notify,
+    Wait,
+    Fence,
+    Load,
+    Store,
+    Add,
+    Sub,
+    And,
+    Or,
+    Xor,
+    Exchange,
+    CompareExchange,
+}
+
+impl Atomic {
+    /// The instruction the intrinsic `kind` lowers to, if it is an atomic.
+    pub fn of(kind: IntrinsicKind) -> Option<Atomic> {
+        Some(match kind {
+            IntrinsicKind::AtomicNotify => Atomic::Notify,
+            IntrinsicKind::AtomicWait => Atomic::Wait,
+            IntrinsicKind::AtomicFence => Atomic::Fence,
+            IntrinsicKind::AtomicLoad => Atomic::Load,
+            IntrinsicKind::AtomicStore => Atomic::Store,
+            IntrinsicKind::AtomicAdd => Atomic::Add,
+            IntrinsicKind::AtomicSub => Atomic::Sub,
+            IntrinsicKind::AtomicAnd => Atomic::And,
+            IntrinsicKind::AtomicOr => Atomic::Or,
+            IntrinsicKind::AtomicXor => Atomic::Xor,
+            IntrinsicKind::AtomicExchange => Atomic::Exchange,
+            IntrinsicKind::AtomicCompareExchange => Atomic::CompareExchange,
+            _ => return None,
+        })
+    }
+
+    /// Writes the instruction to `code`, on a word at no offset for all but
+    /// the fence, which accesses none.
+    pub fn encode(self, code: &mut Vec<u8>) {
+        code.push(0xfe);
+        code.extend(encoders::u32(self.opcode()));
+        match self {
+            Atomic::Fence => code.push(0x00),
+            _ => {
+                code.extend(encoders::u32(2));
+                code.extend(encoders::u32(0));
+            }
+        }
+    }
+
+    fn opcode(self) -> u32 {
+        match self {
+            Atomic::Notify => 0x00,
+            Atomic::Wait => 0x01,
+            Atomic::Fence => 0x03,
+            Atomic::Load => 0x10,
+            Atomic::Store => 0x17,
+            Atomic::Add => 0x1e,
+            Atomic::Sub => 0x25,
+            Atomic::And => 0x2c,
+            Atomic::Or => 0x33,
+            Atomic::Xor => 0x3a,
+            Atomic::Exchange => 0x41,
+            Atomic::CompareExchange => 0x48,
+        }
+    }
+}
+
+/// The content of the import section, with the `count` imports of
+/// `imports` and the shared memory of `pages` pages behind them, which
+/// wasm-encoder has no flag for yet.
+pub fn import_shared_memory(imports: &ImportSection, count: u32, pages: u32) -> Vec<u8> {
+    let mut section = Vec::new();
+    imports.encode(&mut section);
+    // the imports are behind the size of the section and their count.
+    let size = section
+        .iter()
+        .position(|byte| byte & 0x80 == 0)
+        .unwrap_or_default()
+        + 1;
+    let entries = &section[size + encoders::u32(count).len()..];
+    let mut data: Vec<u8> = encoders::u32(count + 1).collect();
+    data.extend(entries);
+    data.extend(encoders::str("env"));
+    data.extend(encoders::str("memory"));
+    // a memory, shared, with a maximum.
+    data.extend([0x02, 0x03]);
+    data.extend(encoders::u32(pages));
+    data.extend(encoders::u32(MAX_SHARED_PAGES.max(pages)));
+    data
+}
+
+/// The body of the start function of a module sharing its memory, which
+/// copies the passive data segments at `segments`, as their addresses and
+/// lengths, into it unless an instance did already, and drops them. The
+/// first instance finishes starting before any thread is spawned, so
+/// `INITIALIZED` needs no atomic access.
+pub fn initialize_shared_memory(segments: &[(i32, u32)]) -> Vec<Instruction<'static>> {
+    use Instruction::*;
+    let mut body = vec![
+        I32Const(INITIALIZED),
+        I32Load(MemArg {
+            offset: 0,
+            align: 2,
+            memory_index: 0,
+        }),
+        I32Eqz,
+        If(wasm_encoder::BlockType::Empty),
+    ];
+    for (segment, (address, length)) in segments.iter().enumerate() {
+        body.extend(vec![
+            I32Const(*address),
+            I32Const(0),
+            I32Const(*length as i32),
+            MemoryInit {
+                mem: 0,
+                data: segment as u32,
+            },
+        ]);
+    }
+    body.push(End);
+    body.extend((0..segments.len()).map(|segment| DataDrop(segment as u32)));
+    body.push(End);
+    body
+}
+
+impl<'a> Context<'a> {
+    /// Checks that the `#[thread_safe]` function `function` can run while
+    /// other threads share the memory with it: it only has values which
+    /// don't point into the heap, none of which is passed back through the
+    /// memory, and only uses the functions and the intrinsics which are
+    /// thread safe too, so it never touches the heap, the references to
+    /// which aren't counted atomically, or the shadow stack.
+    pub fn check_thread_safe(&self, function: &Function) -> Result<(), Error> {
+        for (pattern, ty) in &function.parameters {
+            self.check_thread_safe_type(*ty, pattern.span())?;
+        }
+        self.check_thread_safe_type(function.return_type, function.name.span.clone())?;
+        match &function.body {
+            Some(body) => self.check_thread_safe_block(body),
+            None => Ok(()),
+        }
+    }
+
+    fn check_thread_safe_block(&self, block: &Block) -> Result<(), Error> {
+        for statement in &block.statements {
+            match statement {
+                Statement::Item(_) => {}
+                Statement::Let(r#let) => {
+                    self.check_thread_safe_type(r#let.ty, r#let.span.clone())?;
+                    if let Some(value) = &r#let.value {
+                        self.check_thread_safe_expression(value)?;
+                    }
+                }
+                Statement::Expression(expression) => {
+                    self.check_thread_safe_expression(expression)?
+                }
+            }
+        }
+        match &block.value {
+            Some(value) => self.check_thread_safe_expression(value),
+            None => Ok(()),
+        }
+    }
+
+    fn check_thread_safe_expression(&self, expression: &Expression) -> Result<(), Error> {
+        let name = match &expression.kind {
+            ExpressionKind::Name(Name::Ident(name)) => Some(name.content.clone()),
+            ExpressionKind::Path(path) => Some(path.to_string()),
+            ExpressionKind::Intrinsic(intrinsic) => {
+                return match intrinsic.kind.is_thread_safe() {
+                    true => Ok(()),
+                    false => Err(Error::not_thread_safe(
+                        format!("@{}", intrinsic.name.content),
+                        intrinsic.name.span.clone(),
+                    )),
+                };
+            }
+            _ => None,
+        };
+        // a function is passed around as its slot in the table, and the
+        // builtins are lowered to calls of the host or the runtime, all but
+        // `channel`, which allocates the channel, touching nothing else.
+        if let Some(name) = name {
+            if self.is_builtin(expression.id) {
+                return match name.as_str() {
+                    "channel" => Err(Error::not_thread_safe(name, expression.span())),
+                    _ => Ok(()),
+                };
+            }
+            if self.function_signature(expression.id).is_some() {
+                return match self.is_thread_safe_function(expression.id) {
+                    true => Ok(()),
+                    false => Err(Error::not_thread_safe(name, expression.span())),
+                };
+            }
+        }
+        self.check_thread_safe_type(expression.ty, expression.span())?;
+        for block in expression.blocks() {
+            self.check_thread_safe_block(block)?;
+        }
+        for operand in expression.operands() {
+            self.check_thread_safe_expression(operand)?;
+        }
+        Ok(())
+    }
+
+    fn check_thread_safe_type(&self, ty: TypeId, location: Span) -> Result<(), Error> {
+        if ty == TypeId::UNIT || ty == TypeId::NEVER {
+            return Ok(());
+        }
+        if !is_plain_data(self.types(), ty) {
+            return Err(Error::not_plain_data(self.types().display(ty), location));
+        }
+        if self.spills_results(ty) {
+            return Err(Error::spilled_across_threads(
+                self.types().display(ty),
+                location,
+            ));
+        }
+        Ok(())
+    }
+}
@@ -1,20 +1,34 @@
 use crate::{
     codegen::*,
-    core::ast::{Module, Node},
+    thir::{Item, Module, Statement},
 };
 
 impl<'a> Walker<Module> for Context<'a> {
     fn walk(&mut self, module: Module) -> Result<(), Error> {
-        for node in module.nodes {
-            match node {
-                Node::Directive => {
-                    todo!("directive is not implemented now")
-                }
-                Node::Statement(statement) => {
-                    if let Err(error) = self.walk(statement) {
-                        self.add_compilation_error(error);
-                    }
+        for directive in &module.directives {
+            self.add_compilation_error(Error::unimplemented(directive));
+        }
+        self.declare_functions(&module.statements);
+        self.count_recursive_calls(&module.statements);
+        let main = module
+            .statements
+            .iter()
+            .find_map(|statement| match statement {
+                Statement::Item(Item::Function(function))
+                    if function.name.content == "main" && !function.is_extern =>
+                {
+                    Some((self.function_index(function.id)?, function.return_type))
                 }
+                _ => None,
+            });
+        for statement in module.statements {
+            if let Err(error) = self.walk(statement) {
+                self.add_compilation_error(error);
+            }
+        }
+        if self.target().wasi {
+            if let Some((main, return_type)) = main {
+                self.emit_start(main, return_type);
             }
         }
         Ok(())
@@ -1,80 +1,221 @@
 use wasm_encoder::{EntityType, Instruction};
 
-use crate::{codegen::*, core::ast::*};
+use crate::{
+    codegen::*,
+    core::{
+        ast::{AttributeValue, Name, Pattern},
+        TypeId,
+    },
+    syntax::{Spanned, Token},
+    thir::{Function, Item, ModuleDeclaration, Statement},
+};
 
-impl<'a> Walker<FunctionDeclaration> for Context<'a> {
-    fn walk(&mut self, function_declaration: FunctionDeclaration) -> Result<(), Error> {
-        let parameters_type: Vec<_> = function_declaration
+impl<'a> Context<'a> {
+    /// Declares the functions among `statements`, so they can be called
+    /// before they are implemented, by the functions above them and by
+    /// themselves. A function failing to be declared is reported here, and
+    /// left out of the module.
+    pub fn declare_functions(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::Item(Item::Function(function)) => {
+                    if let Err(error) = self.declare_function_item(function) {
+                        self.add_compilation_error(error);
+                    }
+                }
+                Statement::Item(Item::Module(ModuleDeclaration {
+                    name,
+                    is_pub,
+                    body: Some(body),
+                    ..
+                })) => {
+                    self.enter_module(&name.content, *is_pub);
+                    self.declare_functions(&body.statements);
+                    self.leave_module();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn declare_function_item(&mut self, function: &Function) -> Result<(), Error> {
+        // the checker gives a generic function no type, so there is nothing
+        // to monomorphize its calls with yet.
+        if let Some(parameter) = function.generic_parameters.first() {
+            return Err(Error::unimplemented(parameter));
+        }
+        for (pattern, ty) in &function.parameters {
+            self.check_vector_target(*ty, pattern.span())?;
+        }
+        self.check_vector_target(function.return_type, function.name.span.clone())?;
+        let pass = match function.attribute("link") {
+            Some(link) if function.is_extern => pass_of(&link.value)?,
+            _ => Pass::Pointer,
+        };
+        let parameters_type = function
             .parameters
             .iter()
-            .cloned()
-            .flat_map(|(_, ty)| self.convert_type(ty))
+            .flat_map(|(_, ty)| match pass {
+                Pass::Pointer => self.convert_type(*ty),
+                Pass::PtrLen => self.ptr_len_types(*ty),
+            })
             .collect();
-        let return_type = self.convert_type(function_declaration.return_type.clone());
+        // the host has nowhere to pass back what doesn't fit in the results,
+        // though the function is still declared to report its calls once.
+        if function.is_extern && self.spills_results(function.return_type) {
+            self.add_compilation_error(Error::extern_results_need_target(
+                &function.name,
+                "multi-value",
+            ));
+        }
+        let return_type = self.result_types(function.return_type);
         let id = self.declare_function_type(parameters_type, return_type);
+        if function.attribute("thread_safe").is_some() {
+            self.declare_thread_safe(function.id);
+        }
+        self.declare_function_signature(
+            function.id,
+            function.parameters.iter().map(|(_, ty)| *ty).collect(),
+            function.return_type,
+        );
 
-        if function_declaration.is_extern {
-            self.declare_extern_type(
-                "extern",
-                function_declaration.name.clone(),
-                EntityType::Function(id),
-            )?;
-        } else {
-            self.set_local(function_declaration.name.content.clone(), id);
-
-            let mut param_id = 0;
-            for (pat, _ty) in &function_declaration.parameters {
-                match pat {
-                    Pattern::Slot(name) => match name {
-                        Name::Ident(token) => {
-                            self.set_local(token.content.clone(), param_id);
-                        }
-                        Name::Placeholder => {}
-                    },
+        if function.is_extern {
+            match function.attribute("link") {
+                Some(link) => {
+                    let (module, field) = link_of(&link.value, &function.name)?;
+                    self.declare_extern_type(
+                        module.clone(),
+                        Token {
+                            content: field.clone(),
+                            ..function.name.clone()
+                        },
+                        EntityType::Function(id),
+                    )?;
+                    self.declare_extern_link(function.name.content.clone(), module, field);
+                    if pass == Pass::PtrLen {
+                        self.declare_ptr_len_extern(function.name.content.clone());
+                    }
                 }
-                param_id += 1;
-            }
-
-            let mut body = Vec::new();
-            std::mem::swap(&mut self.instructions, &mut body);
-            if let Some(body) = function_declaration.body.clone() {
-                self.walk(body)?;
-            }
-            std::mem::swap(&mut self.instructions, &mut body);
-            if function_declaration
-                .body
-                .and_then(|block| block.last_expression)
-                .is_none()
-            {
-                if matches!(&function_declaration.return_type, Type::Tuple(v) if v.is_empty()) {
-                    body.push(Instruction::I32Const(0));
-                } else {
-                    return Err(Error::mismatched_type(
-                        function_declaration.return_type,
-                        Type::Tuple(Vec::new()),
-                        function_declaration.name.span,
-                    ));
+                None if self.target().wasi && is_wasi_function(&function.name.content) => {
+                    self.declare_extern_type(
+                        WASI_MODULE,
+                        function.name.clone(),
+                        EntityType::Function(id),
+                    )?;
+                    let name = function.name.content.clone();
+                    self.declare_extern_link(name.clone(), WASI_MODULE, name);
                 }
+                None => self.declare_extern_type(
+                    "extern",
+                    function.name.clone(),
+                    EntityType::Function(id),
+                )?,
             }
-            body.push(Instruction::End);
+        } else {
+            let id = self.declare_function(id);
+            self.name_function(id, self.qualified_name(&function.name.content));
+            self.define_function(function.id, id);
+        }
+        Ok(())
+    }
+}
 
-            dbg!(&body);
+impl<'a> Walker<Function> for Context<'a> {
+    fn walk(&mut self, function: Function) -> Result<(), Error> {
+        // an extern function has nothing to implement, and a function which
+        // wasn't declared failed to, which is reported already.
+        let id = match self.function_index(function.id) {
+            Some(id) if !function.is_extern => id,
+            _ => return Ok(()),
+        };
 
-            for (pat, _ty) in &function_declaration.parameters {
-                match pat {
-                    Pattern::Slot(name) => match name {
-                        Name::Ident(token) => {
-                            self.remove_local(&token.content);
-                        }
-                        Name::Placeholder => {}
-                    },
-                }
+        let overflow = match function.attribute("overflow") {
+            Some(attribute) => Overflow::from_attribute(&attribute.value)?,
+            None => self.overflow(),
+        };
+        if let Some(attribute) = function.attribute("inline") {
+            self.hint_inline(id, Inline::from_attribute(&attribute.value)?);
+        }
+        let thread_safe = match function.attribute("thread_safe") {
+            Some(attribute) => match &attribute.value {
+                AttributeValue::Word(_) => true,
+                value => return Err(Error::invalid_attribute(value, "`thread_safe`")),
+            },
+            None => false,
+        };
+        if thread_safe {
+            self.check_thread_safe(&function)?;
+        }
+        let overflow = self.set_overflow(overflow);
+        let outer_thread_safe = self.set_thread_safe(thread_safe);
+        // the parameters are the first locals, which the resolver numbered
+        // already.
+        let parameters: Vec<_> = function.parameters.iter().map(|(_, ty)| *ty).collect();
+        let outer = self.enter_function(&parameters);
+        let tail_calls = self.find_tail_calls(id, &function);
+        // without `return_call`, a call of itself in a tail position runs
+        // the body again instead.
+        let looped = !tail_calls.calls.is_empty() && !self.target().tail_call;
+        let outer_tail_calls = self.enter_tail_calls(tail_calls);
+        // the count is a word of the memory, which the threads sharing it
+        // would race to change.
+        let call_depth_limit = self.call_depth_limit(function.id).filter(|_| !thread_safe);
+        let outer_call_depth_counted = self.set_call_depth_counted(call_depth_limit.is_some());
+        self.enter_function_spans();
+        let mut body = Vec::new();
+        std::mem::swap(&mut self.instructions, &mut body);
+        // counted before the loop, as a call of itself looping back to it
+        // doesn't nest.
+        if let Some(limit) = call_depth_limit {
+            self.enter_call_depth(limit, &function.name.span);
+        }
+        let address_taken = self.find_address_taken(&function);
+        self.enter_frame(address_taken, &function.name.span);
+        if looped {
+            let block_type = self.block_type(function.return_type);
+            self.instructions.push(Instruction::Loop(block_type));
+        }
+        let walked = match function.body.clone() {
+            Some(body) => self.walk(body),
+            None => Ok(()),
+        };
+        // a body without a value gives the unit, unless it never finishes.
+        if matches!(&function.body, Some(block) if block.value.is_none() && block.ty != TypeId::NEVER)
+        {
+            self.instructions.push(Instruction::I32Const(0));
+        }
+        self.spill_results(function.return_type);
+        if looped {
+            self.instructions.push(Instruction::End);
+        }
+        self.leave_frame();
+        if call_depth_limit.is_some() {
+            self.leave_call_depth();
+        }
+        // the function owns its arguments.
+        self.release_parameters(&parameters);
+        self.finish_frame();
+        std::mem::swap(&mut self.instructions, &mut body);
+        self.leave_function_spans(id);
+        let mut names = self.let_names();
+        for (index, (pattern, _)) in function.parameters.iter().enumerate() {
+            if let Pattern::Slot(Name::Ident(name)) = pattern {
+                names.push((self.parameter_slot(index), name.content.clone()));
             }
-
-            self.implement_function(id, body);
         }
-        if function_declaration.is_pub {
-            self.export_function(function_declaration.name.content, id)
+        self.name_locals(id, names);
+        let locals = self.leave_function(outer);
+        self.enter_tail_calls(outer_tail_calls);
+        self.set_call_depth_counted(outer_call_depth_counted);
+        self.set_overflow(overflow);
+        self.set_thread_safe(outer_thread_safe);
+        walked?;
+        body.push(Instruction::End);
+
+        self.implement_function(id, locals, body);
+        if function.is_pub && self.is_visible() {
+            self.describe_export(id, &function);
+            self.export_function(self.qualified_name(&function.name.content), id)
         }
 
         Ok(())
@@ -1,30 +1,28 @@
-use crate::{codegen::*, core::ast::Declaration};
+use crate::{codegen::*, thir::Item};
 
 mod function_declaration;
 
-impl<'a> Walker<Declaration> for Context<'a> {
-    fn walk(&mut self, declaration: Declaration) -> Result<(), Error> {
-        match declaration {
-            Declaration::FunctionDeclaration(function_declaration) => {
-                self.walk(function_declaration)
-            }
-            Declaration::VariableDeclaration => {
-                todo!("variable declaration is not implemented now")
-            }
-            Declaration::StructDeclaration => {
-                todo!("struct declaration is not implemented now")
-            }
-            Declaration::UnionDeclaration => {
-                todo!("union declaration is not implemented now")
-            }
-            Declaration::TypeDeclaration => {
-                todo!("type declaration is not implemented now")
-            }
-            Declaration::TraitDeclaration => {
-                todo!("trait declaration is not implemented now")
-            }
-            Declaration::ImplDeclaration => {
-                todo!("impl declaration is not implemented now")
+impl<'a> Walker<Item> for Context<'a> {
+    fn walk(&mut self, item: Item) -> Result<(), Error> {
+        match item {
+            Item::Function(function) => self.walk(function),
+            // the resolver made the names it imports refer to the items they
+            // name, so there is nothing left of it to compile.
+            Item::Use(_) => Ok(()),
+            Item::Module(declaration) => {
+                // the loader reports a `mod name;` it didn't read.
+                let body = match declaration.body {
+                    Some(body) => body,
+                    None => return Ok(()),
+                };
+                self.enter_module(&declaration.name.content, declaration.is_pub);
+                for statement in body.statements {
+                    if let Err(error) = self.walk(statement) {
+                        self.add_compilation_error(error);
+                    }
+                }
+                self.leave_module();
+                Ok(())
             }
         }
     }
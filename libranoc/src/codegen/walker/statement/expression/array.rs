@@ -0,0 +1,69 @@
+use wasm_encoder::Instruction;
+
+use crate::{
+    codegen::*,
+    core::ast::Literal,
+    syntax::Span,
+    thir::{Expression, ExpressionKind},
+};
+
+impl<'a> Context<'a> {
+    /// Walks an array literal at `span`, which gives the address of the
+    /// array. Only one made of constants, numbers, booleans, strings or
+    /// tuples of them, is implemented for now, which is placed in the data
+    /// segments as it is, like a string literal, with no code storing it.
+    pub(super) fn walk_array(
+        &mut self,
+        span: Span,
+        elements: Vec<Expression>,
+    ) -> Result<(), Error> {
+        let mut bytes = Vec::new();
+        let mut references = Vec::new();
+        for element in &elements {
+            if !self.constant_bytes(element, &mut bytes, &mut references)? {
+                return Err(Error::unimplemented(span));
+            }
+        }
+        let address = self.array_literal(elements.len() as u32, &bytes, references);
+        self.instructions.push(Instruction::I32Const(address));
+        Ok(())
+    }
+
+    /// Pushes to `bytes` the value of `expression` as it is laid out in
+    /// memory, if it is a constant, and to `references` the addresses of
+    /// the string literals in it.
+    fn constant_bytes(
+        &mut self,
+        expression: &Expression,
+        bytes: &mut Vec<u8>,
+        references: &mut Vec<i32>,
+    ) -> Result<bool, Error> {
+        let instruction = match &expression.kind {
+            ExpressionKind::Literal(literal) => {
+                let instruction = self.literal_constant(expression.ty, literal.clone())?;
+                if let (Literal::String(_), Instruction::I32Const(address)) = (literal, instruction)
+                {
+                    references.push(address);
+                }
+                instruction
+            }
+            ExpressionKind::Tuple(elements) => {
+                for element in elements {
+                    if !self.constant_bytes(element, bytes, references)? {
+                        return Ok(false);
+                    }
+                }
+                return Ok(true);
+            }
+            _ => return Ok(false),
+        };
+        match instruction {
+            Instruction::I32Const(value) => bytes.extend(value.to_le_bytes()),
+            Instruction::I64Const(value) => bytes.extend(value.to_le_bytes()),
+            Instruction::F32Const(value) => bytes.extend(value.to_bits().to_le_bytes()),
+            Instruction::F64Const(value) => bytes.extend(value.to_bits().to_le_bytes()),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,44 @@
+use wasm_encoder::Instruction;
+
+use crate::{
+    codegen::*,
+    core::ast::Name,
+    thir::{Assign, ExpressionKind},
+};
+
+impl<'a> Context<'a> {
+    /// Walks `target = value`, which gives the unit and releases the value
+    /// assigned over. Only a parameter or a local can be assigned to for
+    /// now.
+    pub(super) fn walk_assign(&mut self, assign: Assign) -> Result<(), Error> {
+        if let Some(place) = self.framed_local(assign.target.id) {
+            let ty = assign.target.ty;
+            self.walk(*assign.value)?;
+            for at in self.framed_references(place, ty) {
+                self.release_framed(at);
+            }
+            self.store_framed(place, ty);
+            self.instructions.push(Instruction::I32Const(0));
+            return Ok(());
+        }
+        let slot = match &assign.target.kind {
+            ExpressionKind::Name(Name::Ident(name)) if self.is_local(assign.target.id) => {
+                self.resolve(assign.target.id, name)?
+            }
+            _ => return Err(Error::unimplemented(&*assign.target)),
+        };
+        let leaves = self.leaves(assign.target.ty);
+        self.walk(*assign.value)?;
+        for (offset, leaf) in leaves.iter().enumerate() {
+            if self.is_counted(*leaf) {
+                self.release_local(slot + offset as u32);
+            }
+        }
+        for offset in (0..leaves.len()).rev() {
+            self.instructions
+                .push(Instruction::LocalSet(slot + offset as u32));
+        }
+        self.instructions.push(Instruction::I32Const(0));
+        Ok(())
+    }
+}
@@ -1,13 +1,23 @@
-use crate::{codegen::*, core::ast::Block};
+use crate::{codegen::*, thir::Block};
 
-impl<'a> Walker<Block> for Context<'a> {
-    fn walk(&mut self, block: Block) -> Result<(), Error> {
-        for statement in block.body {
+impl<'a> Context<'a> {
+    fn walk_block_body(&mut self, block: Block) -> Result<(), Error> {
+        for statement in block.statements {
             self.walk(statement)?;
         }
-        if let Some(last_expression) = block.last_expression {
-            self.walk(last_expression)?;
+        if let Some(value) = block.value {
+            self.walk(value)?;
         }
         Ok(())
     }
 }
+
+impl<'a> Walker<Block> for Context<'a> {
+    fn walk(&mut self, block: Block) -> Result<(), Error> {
+        self.declare_functions(&block.statements);
+        self.enter_scope();
+        let walked = self.walk_block_body(block);
+        self.leave_scope();
+        walked
+    }
+}
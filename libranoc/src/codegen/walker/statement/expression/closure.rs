@@ -0,0 +1,218 @@
+use wasm_encoder::{BlockType, Instruction, ValType};
+
+use crate::{
+    codegen::*,
+    core::{arena::Idx, NodeId, Type, TypeId},
+    semantic::Definition,
+    syntax::Span,
+    thir::{Closure, ExpressionKind},
+    typeck::captures::{Capture, CaptureMode},
+};
+
+impl<'a> Context<'a> {
+    /// Walks the closure `id` of the type `ty` at `span`, which gives the
+    /// address of its environment: a block on the heap holding the slot in
+    /// the table of the function implementing it, then each local it
+    /// captures, every one aligned like the locals of a frame. One captured
+    /// by value is copied there, and one captured by reference stays where
+    /// it is, in the frame of the function making the closure or in the
+    /// environment of the closure it is in, and its address is kept
+    /// instead. The references the values copied there hold aren't
+    /// released with the block yet.
+    pub(super) fn walk_closure(
+        &mut self,
+        id: NodeId,
+        ty: TypeId,
+        span: Span,
+        closure: Closure,
+    ) -> Result<(), Error> {
+        let (parameters, return_type) = match self.types().get(ty) {
+            Type::Function {
+                parameters,
+                return_type,
+            } => (parameters.clone(), *return_type),
+            _ => return Err(Error::unimplemented(span)),
+        };
+        let (offsets, size) = self.environment_layout(&closure.captures);
+        let captures = closure.captures.clone();
+        let function =
+            self.implement_closure(id, &span, closure, &parameters, return_type, &offsets)?;
+        let environment = self.allocate_environment(function, size, &span);
+        for (capture, offset) in captures.iter().zip(offsets) {
+            let place = Place {
+                pointer: environment,
+                offset,
+            };
+            match capture.mode {
+                CaptureMode::Value => {
+                    self.load_local(capture)?;
+                    self.store_framed(place, capture.ty);
+                }
+                // only a `let` local can be placed in the frame.
+                CaptureMode::Reference => {
+                    let local = self
+                        .local_place(capture.local)
+                        .ok_or_else(|| Error::unimplemented(&capture.span))?;
+                    self.instructions.push(Instruction::LocalGet(environment));
+                    self.push_address(local);
+                    self.push_store(ValType::I32, offset);
+                }
+            }
+        }
+        self.instructions.push(Instruction::LocalGet(environment));
+        Ok(())
+    }
+
+    /// The offsets of `captures` in the environment of a closure, and its
+    /// size.
+    fn environment_layout(&self, captures: &[Capture]) -> (Vec<u32>, u32) {
+        let mut size = 4;
+        let offsets = captures
+            .iter()
+            .map(|capture| {
+                let offset = (size + 7) & !7;
+                size = offset
+                    + match capture.mode {
+                        CaptureMode::Value => self.size_of(capture.ty),
+                        CaptureMode::Reference => 4,
+                    };
+                offset
+            })
+            .collect();
+        (offsets, size)
+    }
+
+    /// Allocates the environment of a function value of `size` bytes,
+    /// holding the slot of the function `function` and nothing else yet,
+    /// returning the slot its address is kept in. Running out of memory
+    /// panics at `span`.
+    pub(super) fn allocate_environment(&mut self, function: u32, size: u32, span: &Span) -> u32 {
+        use Instruction::*;
+        self.use_memory();
+        let malloc = self.runtime().malloc;
+        let environment = self.temporary_local(ValType::I32);
+        self.instructions.extend(vec![
+            I32Const(size as i32),
+            Call(malloc),
+            LocalTee(environment),
+            I32Eqz,
+            If(BlockType::Empty),
+        ]);
+        let message = self.string_literal("out of memory");
+        self.instructions.push(I32Const(message));
+        self.panic(span);
+        self.instructions.extend(vec![End, LocalGet(environment)]);
+        self.push_table_slot(function);
+        self.push_store(ValType::I32, 0);
+        environment
+    }
+
+    /// Pushes the value of the local `capture` captures, a reference of its
+    /// own, from wherever it lives.
+    fn load_local(&mut self, capture: &Capture) -> Result<(), Error> {
+        if let Some(place) = self.local_place(capture.local) {
+            self.load_framed(place, capture.ty);
+            return Ok(());
+        }
+        let slot = self
+            .local_slot(capture.local)
+            .ok_or_else(|| Error::unimplemented(&capture.span))?;
+        for (offset, leaf) in self.leaves(capture.ty).into_iter().enumerate() {
+            self.instructions
+                .push(Instruction::LocalGet(slot + offset as u32));
+            self.retain(leaf);
+        }
+        Ok(())
+    }
+
+    /// Implements the closure `id` at `span` as a function taking the
+    /// values of `parameters` and the address of its environment, in which
+    /// its `captures` are at `offsets`, returning its index. The address of
+    /// a local captured by reference is read into a slot of its own when it
+    /// is called. Like a function, it owns its arguments, but not its
+    /// environment.
+    fn implement_closure(
+        &mut self,
+        id: NodeId,
+        span: &Span,
+        closure: Closure,
+        parameters: &[TypeId],
+        return_type: TypeId,
+        offsets: &[u32],
+    ) -> Result<u32, Error> {
+        use Instruction::*;
+        let mut parameters_type: Vec<_> = parameters
+            .iter()
+            .flat_map(|ty| self.convert_type(*ty))
+            .collect();
+        parameters_type.push(ValType::I32);
+        let results = self.result_types(return_type);
+        let type_id = self.declare_function_type(parameters_type, results);
+        let function = self.declare_function(type_id);
+        self.name_function(function, self.qualified_name("{closure}"));
+
+        let mut slots = parameters.to_vec();
+        slots.push(TypeId::I32);
+        let outer = self.enter_function(&slots);
+        let outer_tail_calls = self.enter_tail_calls(TailCalls {
+            function,
+            ..TailCalls::default()
+        });
+        let outer_call_depth_counted = self.set_call_depth_counted(false);
+        self.enter_function_spans();
+        let mut body = Vec::new();
+        std::mem::swap(&mut self.instructions, &mut body);
+        let environment = self.parameter_slot(parameters.len());
+        for (index, definition) in self.closure_parameters(id).into_iter().enumerate() {
+            if let Some(definition) = definition {
+                self.bind_closure_parameter(definition, index);
+            }
+        }
+        let mut captured: Vec<Idx<Definition>> = Vec::new();
+        for (capture, offset) in closure.captures.iter().zip(offsets) {
+            let place = match capture.mode {
+                CaptureMode::Value => Place {
+                    pointer: environment,
+                    offset: *offset,
+                },
+                CaptureMode::Reference => {
+                    let address = self.temporary_local(ValType::I32);
+                    self.instructions.push(LocalGet(environment));
+                    self.push_load(ValType::I32, *offset);
+                    self.instructions.push(LocalSet(address));
+                    Place {
+                        pointer: address,
+                        offset: 0,
+                    }
+                }
+            };
+            self.capture_local(capture.local, place);
+            captured.push(capture.local);
+        }
+        // the captured locals live in the environment, or behind it, already.
+        let mut address_taken = self.find_address_taken_by_closure(&closure.body);
+        address_taken.retain(|local| !captured.contains(local));
+        self.enter_frame(address_taken, span);
+        let gives_unit = matches!(&closure.body.kind, ExpressionKind::Block(block) if block.value.is_none() && block.ty != TypeId::NEVER);
+        let walked = self.walk(*closure.body);
+        if gives_unit {
+            self.instructions.push(I32Const(0));
+        }
+        self.spill_results(return_type);
+        self.leave_frame();
+        self.release_parameters(parameters);
+        self.finish_frame();
+        std::mem::swap(&mut self.instructions, &mut body);
+        self.leave_function_spans(function);
+        let mut names = self.let_names();
+        names.push((environment, "environment".to_owned()));
+        self.name_locals(function, names);
+        let locals = self.leave_function(outer);
+        self.enter_tail_calls(outer_tail_calls);
+        self.set_call_depth_counted(outer_call_depth_counted);
+        walked?;
+        body.push(End);
+        self.implement_function(function, locals, body);
+        Ok(function)
+    }
+}
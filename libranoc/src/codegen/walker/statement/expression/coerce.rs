@@ -0,0 +1,44 @@
+use wasm_encoder::Instruction;
+
+use crate::{
+    codegen::*,
+    core::ast::Name,
+    thir::{Coerce, ExpressionKind},
+    typeck::coercion::Coercion,
+};
+
+impl<'a> Context<'a> {
+    /// Emits the conversion of the operand of `coerce` the checker found at
+    /// a coercion site.
+    pub(super) fn walk_coerce(&mut self, coerce: Coerce) -> Result<(), Error> {
+        let operand = *coerce.operand;
+        match coerce.coercion {
+            // an optional of a value on the heap is the pointer to it, which
+            // is 0 for `null`.
+            Coercion::Optional if self.is_pointer(operand.ty) => self.walk(operand),
+            // and one of any other value is whether it isn't `null`, in
+            // front of the value.
+            Coercion::Optional => {
+                self.instructions.push(Instruction::I32Const(1));
+                self.walk(operand)
+            }
+            // a function value is the address of an environment, which for
+            // a function declared in the program holds the slot of the one
+            // calling it and nothing else.
+            Coercion::FunctionPointer => match &operand.kind {
+                ExpressionKind::Name(Name::Ident(name)) => {
+                    let id = self.resolve(operand.id, name)?;
+                    let (parameters, return_type) = self
+                        .function_signature(operand.id)
+                        .cloned()
+                        .ok_or_else(|| Error::unimplemented(&operand))?;
+                    let thunk = self.thunk(id, &name.content, &parameters, return_type);
+                    let environment = self.allocate_environment(thunk, 4, &operand.span);
+                    self.instructions.push(Instruction::LocalGet(environment));
+                    Ok(())
+                }
+                _ => Err(Error::unimplemented(&operand)),
+            },
+        }
+    }
+}
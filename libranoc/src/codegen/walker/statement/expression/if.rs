@@ -1,31 +1,24 @@
-use wasm_encoder::{BlockType, Instruction, ValType};
+use wasm_encoder::Instruction;
 
-use crate::{codegen::*, core::ast::*};
+use crate::{codegen::*, core::TypeId, thir::If};
 
-impl<'a> Walker<Else> for Context<'a> {
-    fn walk(&mut self, r#else: Else) -> Result<(), Error> {
-        self.instructions.push(Instruction::Else);
-        match r#else {
-            Else::If(_, r#if) => self.walk(r#if),
-            Else::Block(_, block) => self.walk(block),
-        }
-    }
-}
-
-impl<'a> Walker<If> for Context<'a> {
-    fn walk(&mut self, r#if: If) -> Result<(), Error> {
+impl<'a> Context<'a> {
+    /// Walks an `if` expression of the type `ty`, which is the one of its
+    /// blocks. The values of a tuple it doesn't give are passed back like
+    /// the ones a function doesn't.
+    pub(super) fn walk_if(&mut self, ty: TypeId, r#if: If) -> Result<(), Error> {
         self.walk(r#if.condition)?;
-        // TODO
-        // we all pretend to be the i32 on the good side.
-        // but what if we're not the i32 on the other?
-        let expression_type = ValType::I32;
-        self.instructions
-            .push(Instruction::If(BlockType::Result(expression_type)));
+        let block_type = self.block_type(ty);
+        self.instructions.push(Instruction::If(block_type));
         self.walk(r#if.body)?;
-        if let Some(r#else) = r#if.else_part {
-            self.walk(r#else)?;
+        self.spill_results(ty);
+        if let Some(else_block) = r#if.else_block {
+            self.instructions.push(Instruction::Else);
+            self.walk(else_block)?;
+            self.spill_results(ty);
         }
         self.instructions.push(Instruction::End);
+        self.reload_results(ty);
 
         Ok(())
     }
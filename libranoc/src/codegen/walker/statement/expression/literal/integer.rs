@@ -1,11 +1,32 @@
+use std::convert::TryFrom;
+
 use wasm_encoder::Instruction;
 
-use crate::{codegen::*, core::ast::Integer};
+use crate::{
+    codegen::*,
+    core::{ast::Integer, TypeId},
+};
 
-impl<'a> Walker<Integer> for Context<'a> {
-    fn walk(&mut self, Integer(s): Integer) -> Result<(), Error> {
-        let v: i32 = s.parse().unwrap();
-        self.instructions.push(Instruction::I32Const(v));
-        Ok(())
+impl<'a> Context<'a> {
+    /// The instruction giving an integer literal of the type `ty`, which is
+    /// `i32` or `i64`.
+    pub(super) fn integer_constant(
+        &self,
+        ty: TypeId,
+        integer: Integer,
+    ) -> Result<Instruction<'static>, Error> {
+        let value = integer.value();
+        let span = integer.0.span;
+        if ty == TypeId::I64 {
+            value
+                .and_then(|value| i64::try_from(value).ok())
+                .map(Instruction::I64Const)
+                .ok_or_else(|| Error::literal_out_of_range(span, "i64"))
+        } else {
+            value
+                .and_then(|value| i32::try_from(value).ok())
+                .map(Instruction::I32Const)
+                .ok_or_else(|| Error::literal_out_of_range(span, "i32"))
+        }
     }
 }
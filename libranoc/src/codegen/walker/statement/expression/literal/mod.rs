@@ -1,23 +1,41 @@
-use crate::{codegen::*, core::ast::Literal};
+use wasm_encoder::Instruction;
+
+use crate::{codegen::*, core::ast::Literal, core::TypeId};
 
 mod integer;
 
-impl<'a> Walker<Literal> for Context<'a> {
-    fn walk(&mut self, literal: Literal) -> Result<(), Error> {
-        match literal {
+impl<'a> Context<'a> {
+    /// Walks a literal of the type `ty`, which the literal alone doesn't
+    /// tell for an integer or a decimal.
+    pub(super) fn walk_literal(&mut self, ty: TypeId, literal: Literal) -> Result<(), Error> {
+        let instruction = self.literal_constant(ty, literal)?;
+        self.instructions.push(instruction);
+        Ok(())
+    }
+
+    /// The instruction giving the value of a literal of the type `ty`, which
+    /// is the address of a string.
+    pub(super) fn literal_constant(
+        &mut self,
+        ty: TypeId,
+        literal: Literal,
+    ) -> Result<Instruction<'static>, Error> {
+        Ok(match literal {
             Literal::String(_) => {
-                todo!()
-            }
-            Literal::Character(_) => {
-                todo!()
-            }
-            Literal::Integer(integer) => self.walk(integer),
-            Literal::Decimal(_) => {
-                todo!()
+                let text = literal.text().expect("a string literal has a text");
+                Instruction::I32Const(self.string_literal(&text))
             }
-            Literal::Boolean(_) => {
-                todo!()
+            Literal::Character(token) => return Err(Error::unimplemented(token)),
+            Literal::Integer(integer) => self.integer_constant(ty, integer)?,
+            // the token is digits around a point, which both parse.
+            Literal::Decimal(token) => {
+                if ty == TypeId::F32 {
+                    Instruction::F32Const(token.content.parse().expect("a decimal parses"))
+                } else {
+                    Instruction::F64Const(token.content.parse().expect("a decimal parses"))
+                }
             }
-        }
+            Literal::Boolean(token) => Instruction::I32Const((token.content == "true") as i32),
+        })
     }
 }
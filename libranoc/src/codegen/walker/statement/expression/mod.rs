@@ -1,35 +1,59 @@
-use crate::{codegen::*, core::ast::Expression};
+use crate::{
+    codegen::*,
+    thir::{Expression, ExpressionKind},
+};
 
+mod array;
+mod assign;
+mod block;
+mod closure;
+mod coerce;
+mod r#if;
 mod literal;
 mod name;
 mod operator;
+mod todo;
 mod tuple;
-mod r#if;
-mod block;
+mod r#unsafe;
 
 impl<'a> Walker<Expression> for Context<'a> {
     fn walk(&mut self, expression: Expression) -> Result<(), Error> {
-        match expression {
-            Expression::Match => {
-                todo!("match is not implemented now")
-            }
-            Expression::Closure => {
-                todo!("closure is not implemented now")
-            }
-            Expression::Literal(literal) => self.walk(literal),
-            Expression::Path => {
-                todo!("path is not implemented now")
-            }
-            Expression::Array => {
-                todo!("array is not implemented now")
-            }
-            Expression::Tuple(expressions) => self.walk(expressions),
-            Expression::Init => {
-                todo!("struct/union init is not implemented now")
+        self.enter_span(&expression.span);
+        let walked = self.walk_expression(expression);
+        self.leave_span();
+        walked
+    }
+}
+
+impl<'a> Context<'a> {
+    fn walk_expression(&mut self, expression: Expression) -> Result<(), Error> {
+        match expression.kind {
+            ExpressionKind::Literal(literal) => self.walk_literal(expression.ty, literal),
+            ExpressionKind::Array(elements) => self.walk_array(expression.span, elements),
+            ExpressionKind::Tuple(expressions) => self.walk(expressions),
+            ExpressionKind::Unary(unary) => self.walk(unary),
+            ExpressionKind::Binary(binary) => self.walk(binary),
+            ExpressionKind::Assign(assign) => self.walk_assign(assign),
+            ExpressionKind::Field(field) => self.walk(field),
+            ExpressionKind::Call(call) => self.walk(call),
+            ExpressionKind::Name(name) => self.walk_name(expression.id, expression.ty, name),
+            ExpressionKind::If(r#if) => self.walk_if(expression.ty, r#if),
+            ExpressionKind::Block(block) => self.walk(block),
+            ExpressionKind::Unsafe(r#unsafe) => self.walk(r#unsafe),
+            ExpressionKind::Todo(todo) => self.walk(todo),
+            // the checker only lets an intrinsic be called.
+            ExpressionKind::Intrinsic(intrinsic) => Err(Error::unimplemented(&intrinsic.name)),
+            ExpressionKind::Coerce(coerce) => self.walk_coerce(coerce),
+            // the checker knows these but codegen doesn't yet, so they are
+            // reported rather than compiled.
+            ExpressionKind::Match(_)
+            | ExpressionKind::Path(_)
+            | ExpressionKind::Init(_)
+            | ExpressionKind::Index(_)
+            | ExpressionKind::Null => Err(Error::unimplemented(&expression.span)),
+            ExpressionKind::Closure(closure) => {
+                self.walk_closure(expression.id, expression.ty, expression.span, closure)
             }
-            Expression::Operator(operator) => self.walk(operator),
-            Expression::Name(name) => self.walk(name),
-            Expression::If(r#if) => self.walk(r#if),
         }
     }
 }
@@ -1,12 +1,27 @@
 use wasm_encoder::Instruction;
 
-use crate::{codegen::*, core::ast::Name};
+use crate::{
+    codegen::*,
+    core::{ast::Name, NodeId, TypeId},
+};
 
-impl<'a> Walker<Name> for Context<'a> {
-    fn walk(&mut self, name: Name) -> Result<(), Error> {
+impl<'a> Context<'a> {
+    /// Walks the name expression `id` of the type `ty`, whose `Name` is all
+    /// the HIR has. The value read is a reference of its own, or a tuple of
+    /// them read from a slot each, or from the frame or the environment of
+    /// the closure being walked.
+    pub(super) fn walk_name(&mut self, id: NodeId, ty: TypeId, name: Name) -> Result<(), Error> {
+        if let Some(place) = self.framed_local(id) {
+            self.load_framed(place, ty);
+            return Ok(());
+        }
         if let Name::Ident(ident) = name {
-            let resolved = self.resolve(&ident.content, ident.span)?;
-            self.instructions.push(Instruction::LocalGet(resolved));
+            let resolved = self.resolve(id, &ident)?;
+            for (offset, leaf) in self.leaves(ty).into_iter().enumerate() {
+                self.instructions
+                    .push(Instruction::LocalGet(resolved + offset as u32));
+                self.retain(leaf);
+            }
         }
         Ok(())
     }
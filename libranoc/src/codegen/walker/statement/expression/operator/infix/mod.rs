@@ -1,98 +1,481 @@
-use wasm_encoder::Instruction;
-
-use crate::{codegen::*, core::ast::InfixOperator};
-
-impl<'a> Walker<InfixOperator> for Context<'a> {
-    fn walk(&mut self, operator: InfixOperator) -> Result<(), Error> {
-        match &operator {
-            InfixOperator::LogicalOr(lhs, operator_span, rhs)
-            | InfixOperator::LogicalAnd(lhs, operator_span, rhs)
-            | InfixOperator::Add(lhs, operator_span, rhs)
-            | InfixOperator::Subtract(lhs, operator_span, rhs)
-            | InfixOperator::Multiply(lhs, operator_span, rhs)
-            | InfixOperator::Divide(lhs, operator_span, rhs)
-            | InfixOperator::Remainder(lhs, operator_span, rhs)
-            | InfixOperator::RangeRightExclusive(lhs, operator_span, rhs)
-            | InfixOperator::RangeRightInclusive(lhs, operator_span, rhs) => {
+use wasm_encoder::{BlockType, Instruction, ValType};
+
+use crate::{
+    codegen::*,
+    core::TypeId,
+    syntax::Span,
+    thir::{Binary, BinaryOperator, Expression},
+};
+
+/// The integer types codegen does the arithmetic of by itself.
+#[derive(Debug, Clone, Copy)]
+enum Width {
+    I32,
+    I64,
+}
+
+impl Width {
+    fn of(ty: TypeId) -> Option<Width> {
+        match ty {
+            TypeId::I32 => Some(Width::I32),
+            TypeId::I64 => Some(Width::I64),
+            _ => None,
+        }
+    }
+
+    fn value_type(self) -> ValType {
+        match self {
+            Width::I32 => ValType::I32,
+            Width::I64 => ValType::I64,
+        }
+    }
+
+    /// The instruction of the two for this type.
+    fn pick(self, i32: Instruction<'static>, i64: Instruction<'static>) -> Instruction<'static> {
+        match self {
+            Width::I32 => i32,
+            Width::I64 => i64,
+        }
+    }
+
+    fn constant(self, value: i64) -> Instruction<'static> {
+        match self {
+            Width::I32 => Instruction::I32Const(value as i32),
+            Width::I64 => Instruction::I64Const(value),
+        }
+    }
+
+    fn min(self) -> i64 {
+        match self {
+            Width::I32 => i32::MIN as i64,
+            Width::I64 => i64::MIN,
+        }
+    }
+
+    fn max(self) -> i64 {
+        match self {
+            Width::I32 => i32::MAX as i64,
+            Width::I64 => i64::MAX,
+        }
+    }
+}
+
+impl<'a> Walker<Binary> for Context<'a> {
+    fn walk(&mut self, binary: Binary) -> Result<(), Error> {
+        let Binary {
+            operator,
+            operator_span,
+            lhs,
+            rhs,
+        } = binary;
+        if lhs.ty.lanes().is_some() {
+            return self.walk_vector_operator(operator, *lhs, *rhs);
+        }
+        if let (
+            BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply,
+            Some(width),
+        ) = (operator, Width::of(lhs.ty))
+        {
+            return self.walk_integer_arithmetic(operator, width, operator_span, *lhs, *rhs);
+        }
+        if let (BinaryOperator::Divide | BinaryOperator::Remainder, Some(width)) =
+            (operator, Width::of(lhs.ty))
+        {
+            return self.walk_integer_division(operator, width, operator_span, *lhs, *rhs);
+        }
+        if let (
+            BinaryOperator::Add | BinaryOperator::EqualTo | BinaryOperator::NotEqualTo,
+            TypeId::STRING,
+        ) = (operator, lhs.ty)
+        {
+            return self.walk_string_operator(operator, *lhs, *rhs);
+        }
+        if let BinaryOperator::EqualTo | BinaryOperator::NotEqualTo = operator {
+            if lhs.ty == rhs.ty {
+                if let Some(aggregate_eq) = self.aggregate_eq(lhs.ty) {
+                    self.walk(*lhs)?;
+                    self.walk(*rhs)?;
+                    self.instructions.push(Instruction::Call(aggregate_eq));
+                    if operator == BinaryOperator::NotEqualTo {
+                        self.instructions.push(Instruction::I32Eqz);
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        match operator {
+            BinaryOperator::LogicalOr
+            | BinaryOperator::LogicalAnd
+            | BinaryOperator::Add
+            | BinaryOperator::Subtract
+            | BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::Remainder
+            | BinaryOperator::RangeRightExclusive
+            | BinaryOperator::RangeRightInclusive
+            | BinaryOperator::BitAnd
+            | BinaryOperator::BitOr
+            | BinaryOperator::BitXor
+            | BinaryOperator::ShiftLeft
+            | BinaryOperator::ShiftRight => {
                 let trait_name = operator.trait_name();
-                let lhs_type = "i32";
-                let rhs_type = "i32";
+                let lhs_type = self.type_name(lhs.ty);
+                let rhs_type = self.type_name(rhs.ty);
                 let function_id = self.import(
                     "extern",
                     &format!("{}__{}_{}", trait_name, lhs_type, rhs_type),
-                    operator_span.clone(),
+                    operator_span,
                 )?;
                 self.walk(lhs)?;
                 self.walk(rhs)?;
                 self.instructions.push(Instruction::Call(function_id));
                 Ok(())
             }
-            InfixOperator::EqualTo(lhs, operator_span, rhs)
-            | InfixOperator::NotEqualTo(lhs, operator_span, rhs) => {
-                let to_negate = matches!(operator, InfixOperator::NotEqualTo(..));
-                let lhs_type = "i32";
-                let rhs_type = "i32";
+            BinaryOperator::EqualTo | BinaryOperator::NotEqualTo => {
+                let to_negate = operator == BinaryOperator::NotEqualTo;
+                let lhs_type = self.type_name(lhs.ty);
+                let rhs_type = self.type_name(rhs.ty);
                 let partial_eq = self.import(
                     "extern",
                     &format!("PartialEq__{}_{}", lhs_type, rhs_type),
                     operator_span.clone(),
                 )?;
-                let result_type = "i32";
+                let result_type = "bool";
                 self.walk(lhs)?;
                 self.walk(rhs)?;
                 self.instructions.push(Instruction::Call(partial_eq));
                 if to_negate {
-                    let not = self.import(
-                        "extern",
-                        &format!("Not__{}", result_type),
-                        operator_span.clone(),
-                    )?;
+                    let not =
+                        self.import("extern", &format!("Not__{}", result_type), operator_span)?;
                     self.instructions.push(Instruction::Call(not));
                 }
                 Ok(())
             }
-            InfixOperator::GreaterThan(lhs, operator_span, rhs)
-            | InfixOperator::LessThan(lhs, operator_span, rhs)
-            | InfixOperator::GreaterThanOrEqualTo(lhs, operator_span, rhs)
-            | InfixOperator::LessThanOrEqualTo(lhs, operator_span, rhs) => {
-                let to_negate = matches!(operator, InfixOperator::NotEqualTo(..));
-                let lhs_type = "i32";
-                let rhs_type = "i32";
+            BinaryOperator::GreaterThan
+            | BinaryOperator::LessThan
+            | BinaryOperator::GreaterThanOrEqualTo
+            | BinaryOperator::LessThanOrEqualTo => {
+                let lhs_type = self.type_name(lhs.ty);
+                let rhs_type = self.type_name(rhs.ty);
                 let partial_eq = self.import(
                     "extern",
                     &format!("PartialOrd__{}_{}", lhs_type, rhs_type),
-                    operator_span.clone(),
+                    operator_span,
                 )?;
                 self.walk(lhs)?;
                 self.walk(rhs)?;
                 self.instructions.push(Instruction::Call(partial_eq));
+                self.instructions.push(Instruction::I32Const(0));
                 match operator {
-                    InfixOperator::GreaterThan(..) => {
-                        self.instructions.push(Instruction::I32Const(0));
-                        self.instructions.push(Instruction::I32GtS);
+                    BinaryOperator::GreaterThan => self.instructions.push(Instruction::I32GtS),
+                    BinaryOperator::LessThan => self.instructions.push(Instruction::I32LtS),
+                    BinaryOperator::GreaterThanOrEqualTo => {
+                        self.instructions.push(Instruction::I32GeS)
                     }
-                    InfixOperator::LessThan(..) => {
-                        self.instructions.push(Instruction::I32Const(0));
-                        self.instructions.push(Instruction::I32LtS);
-                    }
-                    InfixOperator::GreaterThanOrEqualTo(..) => {
-                        self.instructions.push(Instruction::I32Const(0));
-                        self.instructions.push(Instruction::I32GeS);
-                    }
-                    InfixOperator::LessThanOrEqualTo(..) => {
-                        self.instructions.push(Instruction::I32Const(0));
-                        self.instructions.push(Instruction::I32LeS);
-                    }
-                    _ => {}
+                    _ => self.instructions.push(Instruction::I32LeS),
                 }
                 Ok(())
             }
-            InfixOperator::GetField(_) => {
-                todo!()
+        }
+    }
+}
+
+impl<'a> Context<'a> {
+    /// An operator on two vectors, which operates on their lanes one by one,
+    /// wrapping the ones of integers whatever the overflow policy says. Two
+    /// are equal if all their lanes are.
+    fn walk_vector_operator(
+        &mut self,
+        operator: BinaryOperator,
+        lhs: Expression,
+        rhs: Expression,
+    ) -> Result<(), Error> {
+        let ty = lhs.ty;
+        let span = lhs.span.clone();
+        self.walk(lhs)?;
+        self.walk(rhs)?;
+        match operator {
+            BinaryOperator::EqualTo | BinaryOperator::NotEqualTo => {
+                self.push_simd(Simd::equal(ty));
+                self.push_simd(Simd::ALL_TRUE);
+                if operator == BinaryOperator::NotEqualTo {
+                    self.instructions.push(Instruction::I32Eqz);
+                }
+            }
+            // the checker lets no other operator through.
+            _ => match Simd::lanewise(operator, ty) {
+                Some(simd) => self.push_simd(simd),
+                None => return Err(Error::unimplemented(span)),
+            },
+        }
+        Ok(())
+    }
+
+    /// `+`, `==` or `!=` with a string on the left, which the runtime does.
+    /// Adding anything but a string to one is left to the host for now.
+    fn walk_string_operator(
+        &mut self,
+        operator: BinaryOperator,
+        lhs: Expression,
+        rhs: Expression,
+    ) -> Result<(), Error> {
+        if rhs.ty != TypeId::STRING {
+            return Err(Error::unimplemented(rhs.span));
+        }
+        let runtime = self.runtime();
+        self.walk(lhs)?;
+        self.walk(rhs)?;
+        match operator {
+            BinaryOperator::Add => self
+                .instructions
+                .push(Instruction::Call(runtime.string_concat)),
+            BinaryOperator::EqualTo => self.instructions.push(Instruction::Call(runtime.string_eq)),
+            _ => self.instructions.extend(vec![
+                Instruction::Call(runtime.string_eq),
+                Instruction::I32Eqz,
+            ]),
+        }
+        Ok(())
+    }
+
+    /// `+`, `-` or `*` of two integers of the type `width`, doing what the
+    /// overflow policy in effect says when the result doesn't fit in it.
+    fn walk_integer_arithmetic(
+        &mut self,
+        operator: BinaryOperator,
+        width: Width,
+        operator_span: Span,
+        lhs: Expression,
+        rhs: Expression,
+    ) -> Result<(), Error> {
+        self.walk(lhs)?;
+        self.walk(rhs)?;
+        self.push_integer_arithmetic(operator, width, operator_span);
+        Ok(())
+    }
+
+    /// `-x` of an integer `operand`, which is `0 - x`, so negating the
+    /// minimum overflows like subtracting it does. Anything else is left to
+    /// the caller, which gets it back.
+    pub(super) fn walk_integer_negation(
+        &mut self,
+        operator_span: Span,
+        operand: Expression,
+    ) -> Result<Option<Expression>, Error> {
+        let width = match Width::of(operand.ty) {
+            Some(width) => width,
+            None => return Ok(Some(operand)),
+        };
+        self.instructions.push(width.constant(0));
+        self.walk(operand)?;
+        self.push_integer_arithmetic(BinaryOperator::Subtract, width, operator_span);
+        Ok(None)
+    }
+
+    /// The arithmetic of `walk_integer_arithmetic` on the operands on the
+    /// stack.
+    fn push_integer_arithmetic(
+        &mut self,
+        operator: BinaryOperator,
+        width: Width,
+        operator_span: Span,
+    ) {
+        use Instruction::*;
+        let (operation, verb) = match operator {
+            BinaryOperator::Add => (width.pick(I32Add, I64Add), "add"),
+            BinaryOperator::Subtract => (width.pick(I32Sub, I64Sub), "subtract"),
+            _ => (width.pick(I32Mul, I64Mul), "multiply"),
+        };
+        let overflow = self.overflow();
+        if overflow == Overflow::Wrap {
+            self.instructions.push(operation);
+            return;
+        }
+
+        let locals = self.scratch_locals(width.value_type(), 3);
+        let (a, b, result) = (locals[0], locals[1], locals[2]);
+        self.instructions.extend(vec![
+            LocalSet(b),
+            LocalSet(a),
+            LocalGet(a),
+            LocalGet(b),
+            operation,
+            LocalSet(result),
+        ]);
+        // whether the wrapped result is wrong, as an `i32`: a sum has the
+        // sign of neither operand, a difference the sign of neither the lhs
+        // nor the negated rhs, and a product divided by the lhs doesn't give
+        // the rhs back.
+        let overflowed = match operator {
+            BinaryOperator::Add => vec![
+                LocalGet(a),
+                LocalGet(result),
+                width.pick(I32Xor, I64Xor),
+                LocalGet(b),
+                LocalGet(result),
+                width.pick(I32Xor, I64Xor),
+                width.pick(I32And, I64And),
+                width.constant(0),
+                width.pick(I32LtS, I64LtS),
+            ],
+            BinaryOperator::Subtract => vec![
+                LocalGet(a),
+                LocalGet(b),
+                width.pick(I32Xor, I64Xor),
+                LocalGet(a),
+                LocalGet(result),
+                width.pick(I32Xor, I64Xor),
+                width.pick(I32And, I64And),
+                width.constant(0),
+                width.pick(I32LtS, I64LtS),
+            ],
+            // dividing the minimum by `-1` traps, so that case is told
+            // apart first.
+            _ => vec![
+                LocalGet(a),
+                width.constant(-1),
+                width.pick(I32Eq, I64Eq),
+                If(BlockType::Result(ValType::I32)),
+                LocalGet(b),
+                width.constant(width.min()),
+                width.pick(I32Eq, I64Eq),
+                Else,
+                LocalGet(a),
+                width.pick(I32Eqz, I64Eqz),
+                If(BlockType::Result(ValType::I32)),
+                I32Const(0),
+                Else,
+                LocalGet(result),
+                LocalGet(a),
+                width.pick(I32DivS, I64DivS),
+                LocalGet(b),
+                width.pick(I32Neq, I64Neq),
+                End,
+                End,
+            ],
+        };
+        self.instructions.extend(overflowed);
+
+        match overflow {
+            Overflow::Trap => {
+                self.instructions.push(If(BlockType::Empty));
+                let message = self.string_literal(&format!("attempt to {} with overflow", verb));
+                self.instructions.push(I32Const(message));
+                self.panic(&operator_span);
+                self.instructions.extend(vec![End, LocalGet(result)]);
+            }
+            _ => {
+                // past the minimum if the exact result is negative, which
+                // is the sign of the lhs for a sum or a difference, and of
+                // the operands told apart for a product.
+                let mut negative = vec![LocalGet(a)];
+                if operator == BinaryOperator::Multiply {
+                    negative.extend(vec![LocalGet(b), width.pick(I32Xor, I64Xor)]);
+                }
+                negative.extend(vec![width.constant(0), width.pick(I32LtS, I64LtS)]);
+                self.instructions.extend(vec![
+                    If(BlockType::Result(width.value_type())),
+                    width.constant(width.min()),
+                    width.constant(width.max()),
+                ]);
+                self.instructions.extend(negative);
+                self.instructions
+                    .extend(vec![Select, Else, LocalGet(result), End]);
+            }
+        }
+    }
+
+    /// `/` or `%` of two integers of the type `width`. Dividing by zero
+    /// panics whatever the overflow policy, which only says what dividing
+    /// the minimum by `-1` does, the quotient being one past the maximum.
+    /// The remainder is `0` then, which only the `trap` policy reports.
+    fn walk_integer_division(
+        &mut self,
+        operator: BinaryOperator,
+        width: Width,
+        operator_span: Span,
+        lhs: Expression,
+        rhs: Expression,
+    ) -> Result<(), Error> {
+        use Instruction::*;
+        let (operation, zero, overflow_message) = match operator {
+            BinaryOperator::Divide => (
+                width.pick(I32DivS, I64DivS),
+                "attempt to divide by zero",
+                "attempt to divide with overflow",
+            ),
+            _ => (
+                width.pick(I32RemS, I64RemS),
+                "attempt to calculate the remainder with a divisor of zero",
+                "attempt to calculate the remainder with overflow",
+            ),
+        };
+        self.walk(lhs)?;
+        self.walk(rhs)?;
+        let locals = self.scratch_locals(width.value_type(), 2);
+        let (a, b) = (locals[0], locals[1]);
+        self.instructions.extend(vec![
+            LocalSet(b),
+            LocalSet(a),
+            LocalGet(b),
+            width.pick(I32Eqz, I64Eqz),
+            If(BlockType::Empty),
+        ]);
+        let message = self.string_literal(zero);
+        self.instructions.push(I32Const(message));
+        self.panic(&operator_span);
+        self.instructions.push(End);
+
+        match (self.overflow(), operator) {
+            (Overflow::Trap, _) => {
+                self.instructions.extend(vec![
+                    LocalGet(a),
+                    width.constant(width.min()),
+                    width.pick(I32Eq, I64Eq),
+                    LocalGet(b),
+                    width.constant(-1),
+                    width.pick(I32Eq, I64Eq),
+                    I32And,
+                    If(BlockType::Empty),
+                ]);
+                let message = self.string_literal(overflow_message);
+                self.instructions.push(I32Const(message));
+                self.panic(&operator_span);
+                self.instructions
+                    .extend(vec![End, LocalGet(a), LocalGet(b), operation]);
             }
-            InfixOperator::GetFieldNullable(_) => {
-                todo!()
+            // `rem_s` gives `0` for the minimum and `-1` rather than
+            // trapping.
+            (_, BinaryOperator::Remainder) => {
+                self.instructions
+                    .extend(vec![LocalGet(a), LocalGet(b), operation]);
+            }
+            // `div_s` traps on the minimum and `-1`, so dividing by `-1` is
+            // negating instead, which wraps the minimum to itself.
+            (overflow, _) => {
+                let negated = vec![width.constant(0), LocalGet(a), width.pick(I32Sub, I64Sub)];
+                self.instructions.extend(vec![
+                    LocalGet(b),
+                    width.constant(-1),
+                    width.pick(I32Eq, I64Eq),
+                    If(BlockType::Result(width.value_type())),
+                ]);
+                if overflow == Overflow::Saturate {
+                    self.instructions.push(width.constant(width.max()));
+                    self.instructions.extend(negated);
+                    self.instructions.extend(vec![
+                        LocalGet(a),
+                        width.constant(width.min()),
+                        width.pick(I32Eq, I64Eq),
+                        Select,
+                    ]);
+                } else {
+                    self.instructions.extend(negated);
+                }
+                self.instructions
+                    .extend(vec![Else, LocalGet(a), LocalGet(b), operation, End]);
             }
         }
+        Ok(())
     }
 }
@@ -0,0 +1,68 @@
+use wasm_encoder::Instruction;
+
+use crate::{codegen::*, syntax::Token, thir::Expression};
+
+/// The channel operations with their arity, all of them taking and returning
+/// `i32`s:
+///
+/// - `channel(capacity)` creates a channel holding up to `capacity` values,
+///   or as many as are sent to it if `capacity` is `0`.
+/// - `send(channel, value)` blocks while the channel is full.
+/// - `receive(channel)` blocks until a value arrives.
+/// - `select(first, second)` blocks until either channel has a value and
+///   returns the one to `receive` from.
+/// - `share(channel)` counts another reference to the channel, for a thread
+///   to hold, and returns it.
+/// - `close(channel)` lets go of a reference to the channel, which is freed
+///   once the last one is.
+const CHANNEL_BUILTINS: &[(&str, usize)] = &[
+    ("channel", 1),
+    ("send", 2),
+    ("receive", 1),
+    ("select", 2),
+    ("share", 1),
+    ("close", 1),
+];
+
+impl<'a> Context<'a> {
+    /// Lowers the channel operations into calls to the routines of the
+    /// runtime. Returns `None` if `name` isn't one of them.
+    pub(super) fn walk_channel_builtin(
+        &mut self,
+        name: &Token,
+        arguments: &[Expression],
+    ) -> Option<Result<(), Error>> {
+        let &(builtin, arity) = CHANNEL_BUILTINS
+            .iter()
+            .find(|(builtin, _)| *builtin == name.content)?;
+        if arguments.len() != arity {
+            return Some(Err(Error::mismatched_arity(
+                builtin,
+                arity,
+                arguments.len(),
+                name.span.clone(),
+            )));
+        }
+
+        // a channel is waited on in the memory the threads share.
+        if !self.target().threads {
+            return Some(Err(Error::builtin_needs_target(name, "threads")));
+        }
+        let channels = self.channels();
+        let id = match builtin {
+            "channel" => channels.channel,
+            "send" => channels.send,
+            "receive" => channels.receive,
+            "select" => channels.select,
+            "share" => channels.share,
+            _ => channels.close,
+        };
+        for argument in arguments {
+            if let Err(error) = self.walk(argument) {
+                return Some(Err(error));
+            }
+        }
+        self.instructions.push(Instruction::Call(id));
+        Some(Ok(()))
+    }
+}
@@ -0,0 +1,66 @@
+use wasm_encoder::Instruction;
+
+use crate::{
+    codegen::*,
+    core::{ast::Literal, Type, TypeId},
+    thir::{ExpressionKind, Field},
+};
+
+impl<'a> Walker<Field> for Context<'a> {
+    /// Walks `t.0`, the element of a tuple, which is the only field known
+    /// for now. The whole tuple is on the stack first, and the values of
+    /// the other elements are released.
+    fn walk(&mut self, field: Field) -> Result<(), Error> {
+        let index = match &field.field.kind {
+            ExpressionKind::Literal(Literal::Integer(index)) => index.value(),
+            _ => None,
+        };
+        let elements = match self.types().get(field.operand.ty) {
+            Type::Tuple(elements) => elements.clone(),
+            _ => Vec::new(),
+        };
+        let index = match index {
+            Some(index) if (index as usize) < elements.len() => index as usize,
+            _ => return Err(Error::unimplemented(&*field.field)),
+        };
+        let leaves = |context: &Self, elements: &[TypeId]| -> Vec<TypeId> {
+            elements
+                .iter()
+                .flat_map(|element| context.leaves(*element))
+                .collect()
+        };
+        let before = leaves(self, &elements[..index]);
+        let after = leaves(self, &elements[index + 1..]);
+        let types = self.convert_type(elements[index]);
+
+        self.walk(*field.operand)?;
+        for leaf in after.into_iter().rev() {
+            self.release(leaf);
+        }
+        if before.is_empty() {
+            return Ok(());
+        }
+        // the element is kept aside while the values under it are released.
+        let slots: Vec<_> = types
+            .iter()
+            .enumerate()
+            .map(|(position, ty)| {
+                let count = types[..=position]
+                    .iter()
+                    .filter(|other| *other == ty)
+                    .count();
+                self.scratch_locals(*ty, count)[count - 1]
+            })
+            .collect();
+        for slot in slots.iter().rev() {
+            self.instructions.push(Instruction::LocalSet(*slot));
+        }
+        for leaf in before.into_iter().rev() {
+            self.release(leaf);
+        }
+        for slot in slots {
+            self.instructions.push(Instruction::LocalGet(slot));
+        }
+        Ok(())
+    }
+}
@@ -1,22 +1,205 @@
-use wasm_encoder::Instruction;
+use wasm_encoder::{Instruction, MemArg, ValType};
 
 use crate::{
     codegen::*,
-    core::ast::{Expression, FunctionCall, Name},
+    core::{ast::Name, Type, TypeId},
+    thir::{Call, Expression, ExpressionKind},
 };
 
-impl<'a> Walker<FunctionCall> for Context<'a> {
-    fn walk(&mut self, operator: FunctionCall) -> Result<(), Error> {
-        for params in &operator.1 {
-            self.walk(params.clone())?;
-        }
-        if let Expression::Name(Name::Ident(name)) = operator.0.as_ref() {
-            let id = self.resolve(&name.content, name.span.clone())?;
-            self.instructions.push(Instruction::Call(id));
-        } else {
-            return Err(Error::unimplemented(&operator));
-        }
-        // TODO
+impl<'a> Walker<Call> for Context<'a> {
+    fn walk(&mut self, call: Call) -> Result<(), Error> {
+        if let ExpressionKind::Intrinsic(intrinsic) = &call.callee.kind {
+            return self.walk_intrinsic(intrinsic, &call.arguments);
+        }
+        let name = match &call.callee.kind {
+            ExpressionKind::Name(Name::Ident(name)) if !self.is_local(call.callee.id) => name,
+            _ => return self.walk_indirect_call(call),
+        };
+        if self.is_builtin(call.callee.id) {
+            if let Some(result) = self
+                .walk_thread_builtin(name, &call.arguments)
+                .or_else(|| self.walk_channel_builtin(name, &call.arguments))
+                .or_else(|| self.walk_memory_builtin(name, &call.arguments))
+                .or_else(|| self.walk_panic_builtin(name, &call.arguments))
+            {
+                return result;
+            }
+        }
+        let id = self.resolve(call.callee.id, name)?;
+        let signature = self.function_signature(call.callee.id).cloned();
+        if let Some((parameters, _)) = &signature {
+            self.check_arguments(&name.content, parameters, &call)?;
+            if let Some(depth) = self.tail_call_depth(call.callee.id) {
+                return self.walk_tail_call(id, depth, parameters, call.arguments);
+            }
+        }
+        if let Some((parameters, result)) = &signature {
+            if self.takes_ptr_len(call.callee.id) {
+                self.walk_ptr_len_call(id, parameters, call.arguments)?;
+                self.reload_results(*result);
+                return Ok(());
+            }
+        }
+        for argument in call.arguments {
+            self.walk(argument)?;
+        }
+        self.instructions.push(Instruction::Call(id));
+        if let Some((_, result)) = signature {
+            self.reload_results(result);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Context<'a> {
+    /// Walks a call of a function value, which is kept in a slot of its own
+    /// while the arguments are walked, and released once it returns.
+    fn walk_indirect_call(&mut self, call: Call) -> Result<(), Error> {
+        let (parameters, return_type) = match self.types().get(call.callee.ty) {
+            Type::Function {
+                parameters,
+                return_type,
+            } => (parameters.clone(), *return_type),
+            _ => return Err(Error::unimplemented(&call)),
+        };
+        self.check_arguments(&self.type_name(call.callee.ty), &parameters, &call)?;
+        let mut parameters_type: Vec<_> = parameters
+            .iter()
+            .flat_map(|ty| self.convert_type(*ty))
+            .collect();
+        parameters_type.push(ValType::I32);
+        let results = self.result_types(return_type);
+        let ty = self.declare_function_type(parameters_type, results);
+        let value = self.temporary_local(ValType::I32);
+        self.walk(*call.callee)?;
+        self.instructions.push(Instruction::LocalSet(value));
+        for argument in call.arguments {
+            self.walk(argument)?;
+        }
+        self.call_indirect(value, ty);
+        self.release_local(value);
+        self.reload_results(return_type);
+        Ok(())
+    }
+
+    /// Walks a call the function being walked makes of itself in a tail
+    /// position, inside of `depth` `if`s, which never returns to it. The
+    /// references it holds are released before, as nothing is run after.
+    /// It is a `return_call` with the `tail-call` target, and otherwise the
+    /// arguments are set to the parameters and the loop around the body is
+    /// run again, so a recursion this deep doesn't grow the stack.
+    fn walk_tail_call(
+        &mut self,
+        id: u32,
+        depth: u32,
+        parameters: &[TypeId],
+        arguments: Vec<Expression>,
+    ) -> Result<(), Error> {
+        for argument in arguments {
+            self.walk(argument)?;
+        }
+        self.release_owned();
+        self.release_parameters(parameters);
+        if self.target().tail_call {
+            self.return_call(id);
+            return Ok(());
+        }
+        let width: usize = parameters.iter().map(|ty| self.leaves(*ty).len()).sum();
+        for slot in (0..width as u32).rev() {
+            self.instructions.push(Instruction::LocalSet(slot));
+        }
+        self.instructions.push(Instruction::Br(depth));
+        Ok(())
+    }
+
+    /// Walks a call of an `extern fn` taking its strings and arrays as the
+    /// address and the length of their contents. The arguments are kept in
+    /// scratch slots once they are all walked to be passed so, and the
+    /// strings and arrays are released after the call, as the host doesn't
+    /// own them.
+    fn walk_ptr_len_call(
+        &mut self,
+        id: u32,
+        parameters: &[TypeId],
+        arguments: Vec<Expression>,
+    ) -> Result<(), Error> {
+        for argument in arguments {
+            self.walk(argument)?;
+        }
+        let leaves: Vec<_> = parameters.iter().flat_map(|ty| self.leaves(*ty)).collect();
+        let types: Vec<_> = leaves
+            .iter()
+            .flat_map(|leaf| self.convert_type(*leaf))
+            .collect();
+        // the values of each value type take its scratch slots in turn.
+        let mut slots = Vec::new();
+        for (index, ty) in types.iter().enumerate() {
+            let before = types[..index].iter().filter(|other| *other == ty).count();
+            slots.push(self.scratch_locals(*ty, before + 1)[before]);
+        }
+        for slot in slots.iter().rev() {
+            self.instructions.push(Instruction::LocalSet(*slot));
+        }
+        let mut passed = Vec::new();
+        let mut slots = slots.into_iter();
+        for leaf in leaves {
+            let width = self.convert_type(leaf).len();
+            if self.is_sequence(leaf) {
+                let slot = slots.next().unwrap();
+                self.instructions.extend(vec![
+                    Instruction::LocalGet(slot),
+                    Instruction::I32Const(4),
+                    Instruction::I32Add,
+                    Instruction::LocalGet(slot),
+                    Instruction::I32Load(MemArg {
+                        offset: 0,
+                        align: 2,
+                        memory_index: 0,
+                    }),
+                ]);
+                passed.push(slot);
+            } else {
+                for slot in slots.by_ref().take(width) {
+                    self.instructions.push(Instruction::LocalGet(slot));
+                }
+            }
+        }
+        self.instructions.push(Instruction::Call(id));
+        for slot in passed {
+            self.release_local(slot);
+        }
+        Ok(())
+    }
+
+    /// Checks the arguments of `call` against the parameters of the function
+    /// it calls before any of them is walked, so a call the checker let
+    /// through without knowing the types of is reported rather than made
+    /// into an invalid module.
+    fn check_arguments(&self, name: &str, parameters: &[TypeId], call: &Call) -> Result<(), Error> {
+        if parameters.len() != call.arguments.len() {
+            return Err(Error::mismatched_arity(
+                name,
+                parameters.len(),
+                call.arguments.len(),
+                call.span.clone(),
+            ));
+        }
+        for (argument, parameter) in call.arguments.iter().zip(parameters) {
+            if !self.passes_as(argument, *parameter) {
+                return Err(Error::mismatched_types(
+                    self.type_name(*parameter),
+                    self.type_name(argument.ty),
+                    argument.span.clone(),
+                ));
+            }
+        }
         Ok(())
     }
+
+    /// Whether `argument` is passed as the same value types as `parameter`,
+    /// which is all WASM checks. `!` is passed as anything, as it never is.
+    fn passes_as(&self, argument: &Expression, parameter: TypeId) -> bool {
+        argument.ty == TypeId::NEVER
+            || self.convert_type(argument.ty) == self.convert_type(parameter)
+    }
 }
@@ -0,0 +1,209 @@
+use wasm_encoder::{BlockType, Instruction, MemArg, ValType};
+
+use crate::{
+    codegen::*,
+    core::IntrinsicKind,
+    thir::{Expression, Intrinsic},
+};
+
+impl<'a> Context<'a> {
+    /// Lowers the call of an intrinsic into the instructions it stands for,
+    /// the checker having checked its arguments against its signature.
+    pub(super) fn walk_intrinsic(
+        &mut self,
+        intrinsic: &Intrinsic,
+        arguments: &[Expression],
+    ) -> Result<(), Error> {
+        if intrinsic.kind.is_unsafe() && !self.is_unsafe() {
+            return Err(Error::unsafe_required(&intrinsic.name));
+        }
+        if let Some(target) = intrinsic.kind.required_target() {
+            if !self.target().enables(target) {
+                return Err(Error::intrinsic_needs_target(&intrinsic.name, target));
+            }
+        }
+        if intrinsic.kind.is_atomic() && !self.is_thread_safe() {
+            return Err(Error::thread_safe_required(&intrinsic.name));
+        }
+        // the local is given by its place rather than its value.
+        if intrinsic.kind == IntrinsicKind::AddressOf {
+            return self.walk_address_of(arguments);
+        }
+        if intrinsic.kind.takes_vector() {
+            return self.walk_simd(intrinsic, arguments);
+        }
+        for argument in arguments {
+            self.walk(argument)?;
+        }
+        match intrinsic.kind {
+            IntrinsicKind::AddressOf => unreachable!(),
+            IntrinsicKind::SizeOf => {
+                let size = self.size_of(intrinsic.type_arguments[0]);
+                self.instructions.push(Instruction::I32Const(size as i32));
+            }
+            IntrinsicKind::AlignOf => {
+                // every value is as aligned as it is large.
+                let align = self.size_of(intrinsic.type_arguments[0]);
+                self.instructions.push(Instruction::I32Const(align as i32));
+            }
+            IntrinsicKind::Trap => self.instructions.push(Instruction::Unreachable),
+            IntrinsicKind::MemorySize => {
+                self.use_memory();
+                self.instructions.push(Instruction::MemorySize(0));
+            }
+            IntrinsicKind::MemoryGrow => {
+                self.use_memory();
+                self.instructions.push(Instruction::MemoryGrow(0));
+            }
+            IntrinsicKind::MemoryCopy => {
+                self.use_memory();
+                self.instructions
+                    .push(Instruction::MemoryCopy { src: 0, dst: 0 });
+                self.instructions.push(Instruction::I32Const(0));
+            }
+            IntrinsicKind::MemoryFill => {
+                self.use_memory();
+                self.instructions.push(Instruction::MemoryFill(0));
+                self.instructions.push(Instruction::I32Const(0));
+            }
+            IntrinsicKind::Malloc => {
+                let malloc = self.runtime().malloc;
+                self.instructions.push(Instruction::Call(malloc));
+            }
+            IntrinsicKind::Free => {
+                let free = self.runtime().free;
+                self.instructions.push(Instruction::Call(free));
+                self.instructions.push(Instruction::I32Const(0));
+            }
+            IntrinsicKind::Realloc => {
+                let realloc = self.runtime().realloc;
+                self.instructions.push(Instruction::Call(realloc));
+            }
+            IntrinsicKind::StringLen => {
+                self.use_memory();
+                let string = self.scratch_locals(ValType::I32, 1)[0];
+                self.instructions.extend(vec![
+                    Instruction::LocalTee(string),
+                    Instruction::I32Load(MemArg {
+                        offset: 0,
+                        align: 2,
+                        memory_index: 0,
+                    }),
+                ]);
+                self.release_local(string);
+            }
+            IntrinsicKind::StringSlice => {
+                // the offsets are checked here, so a bad one panics with
+                // where the slice is rather than trapping in the runtime.
+                let slice = self.runtime().string_slice;
+                let locals = self.scratch_locals(ValType::I32, 3);
+                let (string, start, end) = (locals[0], locals[1], locals[2]);
+                self.instructions.extend(vec![
+                    Instruction::LocalSet(end),
+                    Instruction::LocalSet(start),
+                    Instruction::LocalSet(string),
+                    Instruction::LocalGet(start),
+                    Instruction::LocalGet(end),
+                    Instruction::I32GtU,
+                    Instruction::LocalGet(end),
+                    Instruction::LocalGet(string),
+                    Instruction::I32Load(MemArg {
+                        offset: 0,
+                        align: 2,
+                        memory_index: 0,
+                    }),
+                    Instruction::I32GtU,
+                    Instruction::I32Or,
+                    Instruction::If(BlockType::Empty),
+                ]);
+                let message = self.string_literal("slice index out of bounds");
+                self.instructions.push(Instruction::I32Const(message));
+                self.panic(&intrinsic.name.span);
+                self.instructions.extend(vec![
+                    Instruction::End,
+                    Instruction::LocalGet(string),
+                    Instruction::LocalGet(start),
+                    Instruction::LocalGet(end),
+                    Instruction::Call(slice),
+                ]);
+            }
+            // the routines take the bits of a float, with the number of
+            // bits of its mantissa and of its exponent.
+            IntrinsicKind::F64ToString => {
+                let to_string = self.floats().to_string;
+                self.instructions.extend(vec![
+                    Instruction::I64ReinterpretF64,
+                    Instruction::I32Const(52),
+                    Instruction::I32Const(11),
+                    Instruction::Call(to_string),
+                ]);
+            }
+            IntrinsicKind::F32ToString => {
+                let to_string = self.floats().to_string;
+                self.instructions.extend(vec![
+                    Instruction::I32ReinterpretF32,
+                    Instruction::I64ExtendI32U,
+                    Instruction::I32Const(23),
+                    Instruction::I32Const(8),
+                    Instruction::Call(to_string),
+                ]);
+            }
+            IntrinsicKind::StringToF64 => {
+                let parse = self.floats().parse;
+                self.instructions.extend(vec![
+                    Instruction::I32Const(52),
+                    Instruction::I32Const(11),
+                    Instruction::Call(parse),
+                    Instruction::F64ReinterpretI64,
+                ]);
+            }
+            IntrinsicKind::StringToF32 => {
+                let parse = self.floats().parse;
+                self.instructions.extend(vec![
+                    Instruction::I32Const(23),
+                    Instruction::I32Const(8),
+                    Instruction::Call(parse),
+                    Instruction::I32WrapI64,
+                    Instruction::F32ReinterpretI32,
+                ]);
+            }
+            IntrinsicKind::Print
+            | IntrinsicKind::Println
+            | IntrinsicKind::Eprint
+            | IntrinsicKind::Eprintln => {
+                let write = self
+                    .runtime()
+                    .write
+                    .ok_or_else(|| Error::intrinsic_needs_target(&intrinsic.name, "wasi"))?;
+                let fd = match intrinsic.kind {
+                    IntrinsicKind::Print | IntrinsicKind::Println => STDOUT,
+                    _ => STDERR,
+                };
+                let newline = matches!(
+                    intrinsic.kind,
+                    IntrinsicKind::Println | IntrinsicKind::Eprintln
+                );
+                self.instructions.extend(vec![
+                    Instruction::I32Const(fd),
+                    Instruction::I32Const(newline as i32),
+                    Instruction::Call(write),
+                    Instruction::I32Const(0),
+                ]);
+            }
+            kind => {
+                // the SIMD intrinsics are lowered above.
+                if let Some(atomic) = Atomic::of(kind) {
+                    self.push_atomic(atomic);
+                }
+                // a store or a fence gives the unit.
+                if matches!(
+                    kind,
+                    IntrinsicKind::AtomicStore | IntrinsicKind::AtomicFence
+                ) {
+                    self.instructions.push(Instruction::I32Const(0));
+                }
+            }
+        }
+        Ok(())
+    }
+}
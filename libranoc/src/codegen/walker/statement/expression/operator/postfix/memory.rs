@@ -0,0 +1,78 @@
+use wasm_encoder::{Instruction, MemArg};
+
+use crate::{codegen::*, syntax::Token, thir::Expression};
+
+/// The raw memory operations with their arity, which may only be called
+/// inside of an `unsafe` block:
+///
+/// - `load8(address)` and `load32(address)` read a byte, zero extended, or
+///   a word.
+/// - `store8(address, value)` and `store32(address, value)` write the low
+///   byte of `value`, or all of it, and give `0`.
+/// - `memory_size()` is the size of the memory in pages of 64KiB.
+/// - `memory_grow(pages)` grows the memory and gives its old size in pages,
+///   or `-1` if it can't grow.
+const MEMORY_BUILTINS: &[(&str, usize)] = &[
+    ("load8", 1),
+    ("load32", 1),
+    ("store8", 2),
+    ("store32", 2),
+    ("memory_size", 0),
+    ("memory_grow", 1),
+];
+
+fn mem_arg(align: u32) -> MemArg {
+    MemArg {
+        offset: 0,
+        align,
+        memory_index: 0,
+    }
+}
+
+impl<'a> Context<'a> {
+    /// Lowers the raw memory operations into the memory instructions of the
+    /// module's own memory. Returns `None` if `name` isn't one of them.
+    pub(super) fn walk_memory_builtin(
+        &mut self,
+        name: &Token,
+        arguments: &[Expression],
+    ) -> Option<Result<(), Error>> {
+        let &(builtin, arity) = MEMORY_BUILTINS
+            .iter()
+            .find(|(builtin, _)| *builtin == name.content)?;
+        if !self.is_unsafe() {
+            return Some(Err(Error::unsafe_required(name)));
+        }
+        if arguments.len() != arity {
+            return Some(Err(Error::mismatched_arity(
+                builtin,
+                arity,
+                arguments.len(),
+                name.span.clone(),
+            )));
+        }
+
+        self.use_memory();
+        for argument in arguments {
+            if let Err(error) = self.walk(argument) {
+                return Some(Err(error));
+            }
+        }
+        match builtin {
+            "load8" => self.instructions.push(Instruction::I32Load8_U(mem_arg(0))),
+            "load32" => self.instructions.push(Instruction::I32Load(mem_arg(2))),
+            "store8" => {
+                self.instructions.push(Instruction::I32Store8(mem_arg(0)));
+                self.instructions.push(Instruction::I32Const(0));
+            }
+            "store32" => {
+                self.instructions.push(Instruction::I32Store(mem_arg(2)));
+                self.instructions.push(Instruction::I32Const(0));
+            }
+            "memory_size" => self.instructions.push(Instruction::MemorySize(0)),
+            "memory_grow" => self.instructions.push(Instruction::MemoryGrow(0)),
+            _ => unreachable!(),
+        }
+        Some(Ok(()))
+    }
+}
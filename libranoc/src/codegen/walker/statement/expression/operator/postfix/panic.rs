@@ -0,0 +1,31 @@
+use crate::{codegen::*, syntax::Token, thir::Expression};
+
+impl<'a> Context<'a> {
+    /// Lowers `panic(message)`, which keeps the message and where it is for
+    /// the host and traps. Returns `None` if `name` isn't `panic`.
+    pub(super) fn walk_panic_builtin(
+        &mut self,
+        name: &Token,
+        arguments: &[Expression],
+    ) -> Option<Result<(), Error>> {
+        if name.content != "panic" {
+            return None;
+        }
+        let message = match arguments {
+            [message] => message,
+            _ => {
+                return Some(Err(Error::mismatched_arity(
+                    "panic",
+                    1,
+                    arguments.len(),
+                    name.span.clone(),
+                )))
+            }
+        };
+        if let Err(error) = self.walk(message) {
+            return Some(Err(error));
+        }
+        self.panic(&name.span);
+        Some(Ok(()))
+    }
+}
@@ -0,0 +1,117 @@
+use wasm_encoder::{Instruction, ValType};
+
+use crate::{
+    codegen::*,
+    core::ast::Name,
+    syntax::{Spanned, Token},
+    thir::{Call, Expression, ExpressionKind},
+};
+
+impl<'a> Context<'a> {
+    /// Lowers `spawn(task, argument)` and `join(handle)` into calls to the
+    /// `thread` host module, which runs `task` on a worker with a fresh
+    /// instance. With the `threads` target, the host gives the instance the
+    /// memory the module imports, so it shares it, and `task` has to be
+    /// `#[thread_safe]`. `scope(task, argument)` calls `task` and joins the
+    /// tasks spawned by this thread until it returns which aren't joined
+    /// yet, so none of them outlives it. Returns `None` if `name` isn't one
+    /// of them.
+    pub(super) fn walk_thread_builtin(
+        &mut self,
+        name: &Token,
+        arguments: &[Expression],
+    ) -> Option<Result<(), Error>> {
+        match name.content.as_str() {
+            "spawn" => Some(self.walk_spawn(name, arguments)),
+            "join" => Some(self.walk_join(name, arguments)),
+            "scope" => Some(self.walk_scope(name, arguments)),
+            _ => None,
+        }
+    }
+
+    fn walk_spawn(&mut self, name: &Token, arguments: &[Expression]) -> Result<(), Error> {
+        let (task_id, task, argument) = match arguments {
+            [Expression {
+                kind: ExpressionKind::Name(Name::Ident(task)),
+                id,
+                ..
+            }, argument] => (*id, task, argument),
+            [task, _] => return Err(Error::unimplemented(task)),
+            _ => {
+                return Err(Error::mismatched_arity(
+                    &name.content,
+                    2,
+                    arguments.len(),
+                    name.span.clone(),
+                ))
+            }
+        };
+        if self.target().threads && !self.is_thread_safe_function(task_id) {
+            return Err(Error::not_thread_safe(&task.content, task.span.clone()));
+        }
+
+        let id = self.resolve(task_id, task)?;
+        let spawn = self.import_builtin(
+            "thread",
+            "spawn",
+            vec![ValType::I32, ValType::I32],
+            vec![ValType::I32],
+        );
+        self.push_table_slot(id);
+        self.walk(argument)?;
+        self.instructions.push(Instruction::Call(spawn));
+        Ok(())
+    }
+
+    fn walk_join(&mut self, name: &Token, arguments: &[Expression]) -> Result<(), Error> {
+        let handle = match arguments {
+            [handle] => handle,
+            _ => {
+                return Err(Error::mismatched_arity(
+                    &name.content,
+                    1,
+                    arguments.len(),
+                    name.span.clone(),
+                ))
+            }
+        };
+        let join = self.import_builtin("thread", "join", vec![ValType::I32], vec![ValType::I32]);
+        self.walk(handle)?;
+        self.instructions.push(Instruction::Call(join));
+        Ok(())
+    }
+
+    /// The host gives the number of tasks spawned so far to `thread.scope`,
+    /// which is kept in a local while `task` runs and given back to
+    /// `thread.join_scope`, joining the ones spawned after it.
+    fn walk_scope(&mut self, name: &Token, arguments: &[Expression]) -> Result<(), Error> {
+        let (task, argument) = match arguments {
+            [task, argument] => (task, argument),
+            _ => {
+                return Err(Error::mismatched_arity(
+                    &name.content,
+                    2,
+                    arguments.len(),
+                    name.span.clone(),
+                ))
+            }
+        };
+        let scope = self.import_builtin("thread", "scope", vec![], vec![ValType::I32]);
+        let join_scope = self.import_builtin("thread", "join_scope", vec![ValType::I32], vec![]);
+        let spawned = self.scratch_locals(ValType::I32, 1)[0];
+        self.instructions.extend(vec![
+            Instruction::Call(scope),
+            Instruction::LocalSet(spawned),
+        ]);
+        self.walk(Call {
+            callee: Box::new(task.clone()),
+            arguments: vec![argument.clone()],
+            span: task.span(),
+        })?;
+        self.instructions.extend(vec![
+            Instruction::LocalGet(spawned),
+            Instruction::Call(join_scope),
+        ]);
+        Ok(())
+    }
+}
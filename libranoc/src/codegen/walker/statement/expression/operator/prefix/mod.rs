@@ -0,0 +1,48 @@
+use wasm_encoder::Instruction;
+
+use crate::{
+    codegen::*,
+    core::TypeId,
+    thir::{Unary, UnaryOperator},
+};
+
+impl<'a> Walker<Unary> for Context<'a> {
+    /// Walks `!x`, `+x` or `-x`, of the types the checker lets them take:
+    /// `!` negates a boolean or the bits of an integer, and `-` a number.
+    fn walk(&mut self, unary: Unary) -> Result<(), Error> {
+        let Unary {
+            operator,
+            operator_span,
+            operand,
+        } = unary;
+        let ty = operand.ty;
+        match operator {
+            UnaryOperator::Plus => self.walk(*operand),
+            UnaryOperator::Minus => {
+                let operand = match self.walk_integer_negation(operator_span, *operand)? {
+                    Some(operand) => operand,
+                    None => return Ok(()),
+                };
+                self.walk(operand)?;
+                match ty {
+                    TypeId::F32 => self.instructions.push(Instruction::F32Neg),
+                    _ => self.instructions.push(Instruction::F64Neg),
+                }
+                Ok(())
+            }
+            UnaryOperator::Not => {
+                self.walk(*operand)?;
+                match ty {
+                    TypeId::BOOL => self.instructions.push(Instruction::I32Eqz),
+                    TypeId::I64 => self
+                        .instructions
+                        .extend(vec![Instruction::I64Const(-1), Instruction::I64Xor]),
+                    _ => self
+                        .instructions
+                        .extend(vec![Instruction::I32Const(-1), Instruction::I32Xor]),
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use wasm_encoder::Instruction;
+
+use crate::{codegen::*, core::ast::Todo, syntax::Spanned};
+
+impl<'a> Walker<Todo> for Context<'a> {
+    fn walk(&mut self, todo: Todo) -> Result<(), Error> {
+        // a `todo` panics with what it describes, which leaves the value it
+        // stands in for unreachable, whatever its type.
+        let message = self.string_literal(&todo.description());
+        self.instructions.push(Instruction::I32Const(message));
+        self.panic(&todo.span());
+
+        Ok(())
+    }
+}
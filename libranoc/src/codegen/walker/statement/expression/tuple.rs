@@ -1,16 +1,11 @@
-use crate::{codegen::*, core::ast::Expression};
+use crate::{codegen::*, thir::Expression};
 
 impl<'a> Walker<Vec<Expression>> for Context<'a> {
-    fn walk(&mut self, mut tuple: Vec<Expression>) -> Result<(), Error> {
-        let len = tuple.len();
-        match len {
-            0 => {}
-            1 => {
-                self.walk(tuple.remove(0))?;
-            }
-            _ => {
-                todo!("tuple is not implemented now");
-            }
+    /// Walks a tuple, which leaves the values of its elements on the stack
+    /// in order. `(a)` is only `a` in parentheses.
+    fn walk(&mut self, tuple: Vec<Expression>) -> Result<(), Error> {
+        for element in tuple {
+            self.walk(element)?;
         }
         Ok(())
     }
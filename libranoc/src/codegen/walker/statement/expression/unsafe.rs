@@ -0,0 +1,10 @@
+use crate::{codegen::*, thir::Unsafe};
+
+impl<'a> Walker<Unsafe> for Context<'a> {
+    fn walk(&mut self, r#unsafe: Unsafe) -> Result<(), Error> {
+        self.enter_unsafe();
+        let result = self.walk(r#unsafe.block);
+        self.leave_unsafe();
+        result
+    }
+}
@@ -0,0 +1,88 @@
+use wasm_encoder::Instruction;
+
+use crate::{
+    codegen::*,
+    core::ast::{Name, Pattern},
+    thir::Let,
+};
+
+impl<'a> Context<'a> {
+    /// Walks a `let` binding a local whose address is taken, which is
+    /// placed at `place` in the frame rather than in a slot.
+    fn walk_framed_let(&mut self, r#let: Let, place: Place) -> Result<(), Error> {
+        let references = self.framed_references(place, r#let.ty);
+        for at in &references {
+            self.own_framed(at.offset);
+        }
+        match r#let.value {
+            Some(value) => {
+                self.walk(value)?;
+                self.store_framed(place, r#let.ty);
+            }
+            // the frame holds whatever the last function placed there left.
+            None => {
+                for at in references {
+                    self.clear_framed(at);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Walker<Let> for Context<'a> {
+    fn walk(&mut self, r#let: Let) -> Result<(), Error> {
+        if let Some(place) = self
+            .let_binding(r#let.id)
+            .and_then(|definition| self.place_local(definition, r#let.ty))
+        {
+            return self.walk_framed_let(r#let, place);
+        }
+        // `let x;` only gets its slot, which is assigned later.
+        let slot = self
+            .let_binding(r#let.id)
+            .map(|definition| self.bind_local(definition, r#let.ty));
+        // the slots of a tuple holding references, which the block owns.
+        let counted: Vec<_> = match slot {
+            Some(slot) => self
+                .leaves(r#let.ty)
+                .into_iter()
+                .enumerate()
+                .filter(|(_, leaf)| self.is_counted(*leaf))
+                .map(|(offset, _)| slot + offset as u32)
+                .collect(),
+            None => Vec::new(),
+        };
+        for slot in &counted {
+            self.own_local(*slot);
+        }
+        let value = match r#let.value {
+            Some(value) => value,
+            None => {
+                // so assigning it later has nothing to release, even if
+                // the block ran before.
+                for slot in counted {
+                    self.instructions.push(Instruction::I32Const(0));
+                    self.instructions.push(Instruction::LocalSet(slot));
+                }
+                return Ok(());
+            }
+        };
+        match (&r#let.pattern, slot) {
+            (Pattern::Slot(Name::Ident(_)), Some(slot)) => {
+                let width = self.leaves(r#let.ty).len() as u32;
+                self.walk(value)?;
+                for slot in (slot..slot + width).rev() {
+                    self.instructions.push(Instruction::LocalSet(slot));
+                }
+            }
+            (Pattern::Slot(Name::Placeholder(_)), _) => {
+                let ty = value.ty;
+                self.walk(value)?;
+                self.release(ty);
+            }
+            (pattern, _) => return Err(Error::unimplemented(pattern)),
+        }
+        Ok(())
+    }
+}
@@ -1,19 +1,21 @@
-use crate::{codegen::*, core::ast::Statement};
+use crate::{codegen::*, thir::Statement};
 
 mod declaration;
 mod expression;
+mod r#let;
 
 pub use declaration::*;
 pub use expression::*;
-use wasm_encoder::Instruction;
 
 impl<'a> Walker<Statement> for Context<'a> {
     fn walk(&mut self, statement: Statement) -> Result<(), Error> {
         match statement {
-            Statement::Declaration(declaration) => self.walk(declaration),
+            Statement::Item(item) => self.walk(item),
+            Statement::Let(r#let) => self.walk(r#let),
             Statement::Expression(expression) => {
+                let ty = expression.ty;
                 self.walk(expression)?;
-                self.instructions.push(Instruction::Drop);
+                self.release(ty);
                 Ok(())
             }
         }
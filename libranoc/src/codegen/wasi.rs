@@ -0,0 +1,65 @@
+use wasm_encoder::{Instruction, ValType};
+
+use crate::core::TypeId;
+
+use super::Context;
+
+/// The module WASI is imported from.
+pub const WASI_MODULE: &str = "wasi_snapshot_preview1";
+
+/// The functions of WASI an `extern fn` of the same name imports with the
+/// `wasi` target, unless it is linked elsewhere: the standard streams, the
+/// clocks and the arguments.
+const WASI_FUNCTIONS: &[&str] = &[
+    "args_get",
+    "args_sizes_get",
+    "clock_res_get",
+    "clock_time_get",
+    "fd_read",
+    "fd_write",
+    "proc_exit",
+];
+
+/// The standard output and error, as WASI numbers them.
+pub const STDOUT: i32 = 1;
+pub const STDERR: i32 = 2;
+
+/// Whether an `extern fn` called `name` is the function of WASI with the
+/// name.
+pub fn is_wasi_function(name: &str) -> bool {
+    WASI_FUNCTIONS.contains(&name)
+}
+
+impl<'a> Context<'a> {
+    /// Imports `fd_write(fd, iovs, iovs_len, nwritten): errno`.
+    pub(super) fn import_fd_write(&mut self) -> u32 {
+        self.import_builtin(
+            WASI_MODULE,
+            "fd_write",
+            vec![ValType::I32; 4],
+            vec![ValType::I32],
+        )
+    }
+
+    /// Defines and exports `_start`, which a WASI command is run by, to call
+    /// `main`. What it gives is the exit code if it is an `i32`, and is
+    /// dropped otherwise.
+    pub(super) fn emit_start(&mut self, main: u32, return_type: TypeId) {
+        let mut body = vec![Instruction::Call(main)];
+        if return_type == TypeId::I32 {
+            let proc_exit =
+                self.import_builtin(WASI_MODULE, "proc_exit", vec![ValType::I32], vec![]);
+            body.push(Instruction::Call(proc_exit));
+        } else {
+            for _ in self.result_types(return_type) {
+                body.push(Instruction::Drop);
+            }
+        }
+        body.push(Instruction::End);
+        let ty = self.declare_function_type(vec![], vec![]);
+        let start = self.declare_function(ty);
+        self.implement_function(start, vec![], body);
+        self.name_function(start, "_start");
+        self.export_function("_start", start);
+    }
+}
@@ -0,0 +1,126 @@
+//! Values of one kind allocated together and referred to by index, which
+//! the tables built from the AST are kept in: the scopes and definitions
+//! of name resolution and the types typeck defines. The AST itself isn't,
+//! as the parser builds it by owning each node through the `Box`es and
+//! `Vec`s of its parent.
+
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ops::{Index, IndexMut},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Where a value lives in an `Arena<T>`. It is only an index, so it is
+/// `Copy` whatever `T` is, and nodes refer to each other through it instead
+/// of owning each other through `Box`es.
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Idx<T> {
+    raw: u32,
+    #[serde(skip)]
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Idx<T> {
+    fn new(raw: u32) -> Self {
+        Idx {
+            raw,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn index(self) -> usize {
+        self.raw as usize
+    }
+}
+
+impl<T> Clone for Idx<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Idx<T> {}
+
+impl<T> PartialEq for Idx<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<T> Eq for Idx<T> {}
+
+impl<T> Hash for Idx<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.raw.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Idx<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Idx({})", self.raw)
+    }
+}
+
+/// The values of one kind, allocated one after another in a single `Vec`.
+/// Values are never removed, so an `Idx` stays valid as long as the arena
+/// lives, and dropping the arena frees every value at once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Arena<T> {
+    values: Vec<T>,
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Arena { values: Vec::new() }
+    }
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Self {
+        Arena::default()
+    }
+
+    pub fn alloc(&mut self, value: T) -> Idx<T> {
+        let idx = Idx::new(self.values.len() as u32);
+        self.values.push(value);
+        idx
+    }
+
+    pub fn get(&self, idx: Idx<T>) -> Option<&T> {
+        self.values.get(idx.index())
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Every value with its index, in the order they were allocated.
+    pub fn iter(&self) -> impl Iterator<Item = (Idx<T>, &T)> {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (Idx::new(index as u32), value))
+    }
+}
+
+impl<T> Index<Idx<T>> for Arena<T> {
+    type Output = T;
+
+    fn index(&self, idx: Idx<T>) -> &T {
+        &self.values[idx.index()]
+    }
+}
+
+impl<T> IndexMut<Idx<T>> for Arena<T> {
+    fn index_mut(&mut self, idx: Idx<T>) -> &mut T {
+        &mut self.values[idx.index()]
+    }
+}
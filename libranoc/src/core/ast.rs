@@ -1,108 +1,812 @@
 use std::fmt;
 
-use crate::syntax::{Span, Spanned, Token};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq)]
+use crate::{
+    core::NodeId,
+    syntax::{Span, Spanned, Token, TokenKind},
+};
+
+pub mod build;
+pub mod cfg;
+pub mod print;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Module {
     pub(crate) nodes: Vec<Node>,
 }
 
-#[derive(Debug, PartialEq)]
+impl Spanned for Module {
+    fn span(&self) -> Span {
+        self.nodes.span()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Node {
-    Directive,
+    Directive(Directive),
     Statement(Statement),
 }
 
-// TODO: directives like #![deny(unused_variable)]
-#[derive(Debug, PartialEq)]
-pub struct Directive {}
+impl Spanned for Node {
+    fn span(&self) -> Span {
+        match self {
+            Node::Directive(directive) => directive.span(),
+            Node::Statement(statement) => statement.span(),
+        }
+    }
+}
+
+/// `#![deny(unused_variable)]`, an attribute of the whole file.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Directive {
+    pub attribute: Attribute,
+}
+
+impl Spanned for Directive {
+    fn span(&self) -> Span {
+        self.attribute.span()
+    }
+}
+
+/// `#[value]` in front of a declaration, or `#![value]` for a directive.
+/// What the value means is up to the consumer of the attribute, which
+/// rejects values it doesn't understand with `Error::invalid_attribute`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Attribute {
+    pub number_sign_token: Token,
+    pub value: AttributeValue,
+    pub right_square_bracket_token: Token,
+}
+
+impl Attribute {
+    pub fn name(&self) -> Option<&str> {
+        self.value.name()
+    }
+}
+
+impl Spanned for Attribute {
+    fn span(&self) -> Span {
+        self.number_sign_token
+            .span
+            .joined(&self.right_square_bracket_token.span)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum AttributeValue {
+    /// `inline`
+    Word(Token),
+    /// `"env"`, `1` or `true`.
+    Literal(Token),
+    /// `module = "env"`
+    KeyValue {
+        key: Token,
+        value: Box<AttributeValue>,
+    },
+    /// `link(module = "env", name = "log")`
+    List {
+        name: Token,
+        items: Vec<AttributeValue>,
+        right_parenthesis_token: Token,
+    },
+}
+
+impl AttributeValue {
+    /// The name of a word, a key or a list.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            AttributeValue::Word(name)
+            | AttributeValue::KeyValue { key: name, .. }
+            | AttributeValue::List { name, .. } => Some(&name.content),
+            AttributeValue::Literal(_) => None,
+        }
+    }
+
+    /// The items of a list, which is nothing for the other values.
+    pub fn items(&self) -> &[AttributeValue] {
+        match self {
+            AttributeValue::List { items, .. } => items,
+            _ => &[],
+        }
+    }
+
+    /// The value given to `key` in a list, like `"env"` for `module` in
+    /// `link(module = "env")`.
+    pub fn value_of(&self, key: &str) -> Option<&AttributeValue> {
+        self.items().iter().find_map(|item| match item {
+            AttributeValue::KeyValue { key: name, value } if name.content == key => Some(&**value),
+            _ => None,
+        })
+    }
+
+    /// The text of a string literal without its quotes.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            AttributeValue::Literal(token) if matches!(token.kind, TokenKind::LiteralString(_)) => {
+                Some(&token.content[1..token.content.len() - 1])
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Spanned for AttributeValue {
+    fn span(&self) -> Span {
+        match self {
+            AttributeValue::Word(token) | AttributeValue::Literal(token) => token.span.clone(),
+            AttributeValue::KeyValue { key, value } => key.span.joined(&value.span()),
+            AttributeValue::List {
+                name,
+                right_parenthesis_token,
+                ..
+            } => name.span.joined(&right_parenthesis_token.span),
+        }
+    }
+}
 
-#[derive(Debug, PartialEq, Clone)]
+/// The `///` lines in front of a declaration, which are markdown.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Documentation {
+    pub lines: Vec<Token>,
+}
+
+impl Documentation {
+    /// Every line without its `///` and the one space behind it, together
+    /// with where that text is, so errors found in the markdown can point
+    /// inside of it.
+    pub fn lines(&self) -> impl Iterator<Item = (&str, Span)> {
+        self.lines.iter().map(|line| {
+            let prefix = if line.content.starts_with("/// ") {
+                4
+            } else {
+                3
+            };
+            let span = Span {
+                range: line.span.range.start + prefix..line.span.range.end,
+                column: line.span.column + prefix,
+                len: line.span.len - prefix,
+                ..line.span.clone()
+            };
+            (&line.content[prefix..], span)
+        })
+    }
+
+    /// The markdown the lines make up.
+    pub fn text(&self) -> String {
+        self.lines()
+            .map(|(line, _)| line)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Spanned for Documentation {
+    fn span(&self) -> Span {
+        self.lines.span()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Statement {
     Declaration(Declaration),
+    Let(Let),
+    /// The span of an expression statement leaves out its `;`.
     Expression(Expression),
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl Spanned for Statement {
+    fn span(&self) -> Span {
+        match self {
+            Statement::Declaration(declaration) => declaration.span(),
+            Statement::Let(r#let) => r#let.span(),
+            Statement::Expression(expression) => expression.span(),
+        }
+    }
+}
+
+/// `let x: i32 = value;`, where the type and the value may be left out. A
+/// local declared without a value has to be assigned before it is read.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Let {
+    pub let_token: Token,
+    pub pattern: Pattern,
+    pub ty: Option<Type>,
+    pub value: Option<Expression>,
+    pub semicolon_token: Token,
+    pub id: NodeId,
+}
+
+impl Spanned for Let {
+    fn span(&self) -> Span {
+        self.let_token.span.joined(&self.semicolon_token.span)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Declaration {
     FunctionDeclaration(FunctionDeclaration),
-    VariableDeclaration,
-    StructDeclaration,
-    UnionDeclaration,
-    TypeDeclaration,
-    TraitDeclaration,
-    ImplDeclaration,
+    UseDeclaration(UseDeclaration),
+    OperatorDeclaration(OperatorDeclaration),
+    ModuleDeclaration(ModuleDeclaration),
+}
+
+impl Spanned for Declaration {
+    fn span(&self) -> Span {
+        match self {
+            Declaration::FunctionDeclaration(declaration) => declaration.span(),
+            Declaration::UseDeclaration(declaration) => declaration.span(),
+            Declaration::OperatorDeclaration(declaration) => declaration.span(),
+            Declaration::ModuleDeclaration(declaration) => declaration.span(),
+        }
+    }
+}
+
+impl Declaration {
+    pub fn id(&self) -> NodeId {
+        match self {
+            Declaration::FunctionDeclaration(declaration) => declaration.id,
+            Declaration::UseDeclaration(declaration) => declaration.id,
+            Declaration::OperatorDeclaration(declaration) => declaration.id,
+            Declaration::ModuleDeclaration(declaration) => declaration.id,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// The declarations keep the span from their documentation up to the end,
+/// as the keywords and the punctuations in between aren't all kept.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct FunctionDeclaration {
+    pub documentation: Option<Documentation>,
+    pub attributes: Vec<Attribute>,
     pub is_pub: bool,
     pub is_extern: bool,
     pub name: Token,
-    // pub type_parameters: Vec<TypeParameter>,
+    pub generic_parameters: Vec<GenericParameter>,
     pub parameters: Vec<(Pattern, Type)>,
     pub return_type: Type,
     // pub where_clauses: Vec<WhereClause>,
     pub body: Option<Block>,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for FunctionDeclaration {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+impl FunctionDeclaration {
+    /// The first attribute called `name`.
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name() == Some(name))
+    }
+}
+
+/// `T` or `T: Add + Display`, declared in the `<...>` behind the name of a
+/// declaration.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct GenericParameter {
+    pub name: Token,
+    pub bounds: Vec<Type>,
+    pub id: NodeId,
+}
+
+impl Spanned for GenericParameter {
+    fn span(&self) -> Span {
+        self.name.span.joined(&self.bounds.span())
+    }
+}
+
+/// `use a::{b, c::d as e, f::*};`, kept as the tree it is written as.
+/// Name resolution expands it into one import for each leaf.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct UseDeclaration {
+    pub documentation: Option<Documentation>,
+    pub is_pub: bool,
+    pub use_token: Token,
+    pub tree: UseTree,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for UseDeclaration {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+/// `operator <+>: Additive, left = concat;`, declaring an infix operator
+/// which calls `function` with both of its operands. Declared operators are
+/// known to the whole file, also in front of their declaration.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct OperatorDeclaration {
+    pub documentation: Option<Documentation>,
+    pub operator_token: Token,
+    /// The punctuations spelling the operator, written without spaces.
+    pub symbol: Vec<Token>,
+    /// The name of one of the precedences of the built in binary operators.
+    pub precedence: Token,
+    /// `left` or `right`.
+    pub associativity: Token,
+    pub function: Token,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for OperatorDeclaration {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+/// `mod name { ... }` declaring a module inline, or `mod name;` declaring
+/// one whose items are in a file of its own, which the module loader finds
+/// from the request `load_requests` makes for it.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ModuleDeclaration {
+    pub documentation: Option<Documentation>,
+    pub is_pub: bool,
+    pub mod_token: Token,
+    pub name: Token,
+    pub body: ModuleBody,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for ModuleDeclaration {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum ModuleBody {
+    Inline {
+        curly_bracket_open_token: Token,
+        module: Module,
+        curly_bracket_close_token: Token,
+    },
+    File(Token),
+}
+
+impl Spanned for ModuleBody {
+    fn span(&self) -> Span {
+        match self {
+            ModuleBody::Inline {
+                curly_bracket_open_token,
+                curly_bracket_close_token,
+                ..
+            } => curly_bracket_open_token
+                .span
+                .joined(&curly_bracket_close_token.span),
+            ModuleBody::File(semicolon_token) => semicolon_token.span.clone(),
+        }
+    }
+}
+
+/// A path followed by what is imported from it. The prefix is empty for
+/// the trees written as `{...}` or `*` without a path in front.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct UseTree {
+    pub prefix: Path,
+    pub kind: UseTreeKind,
+    pub span: Span,
+    pub id: NodeId,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl Spanned for UseTree {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum UseTreeKind {
+    /// `a::b` or `a::b as c`, importing the last segment of the prefix under
+    /// its own name or the one behind `as`.
+    Simple(Option<Token>),
+    /// `a::*`, importing everything public in `a`.
+    Glob(Token),
+    /// `a::{b, c}`, importing each of the inner trees relative to `a`.
+    Nested(Vec<UseTree>),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Expression {
-    Match,
-    Closure,
+    Match(Match),
+    Closure(Closure),
     Literal(Literal),
-    Path,
-    Array,
-    Tuple(Vec<Expression>),
-    Init,
+    Path(Path),
+    /// The elements with the span from `[` to `]`.
+    Array(Vec<Expression>, Span),
+    /// The elements with the span from `(` to `)`, which a parenthesized
+    /// expression doesn't keep as it isn't a tuple.
+    Tuple(Vec<Expression>, Span),
+    Init(Init),
     Operator(Operator),
     Name(Name),
     If(If),
+    Unsafe(Unsafe),
+    Interpolation(Vec<InterpolationPart>),
+    Todo(Todo),
+    Intrinsic(Intrinsic),
 }
 
 impl Spanned for Expression {
-    fn span(&self) -> crate::syntax::Span {
+    fn span(&self) -> Span {
         match self {
-            _ => todo!("SPAN PLEASE"),
+            Expression::Match(r#match) => r#match.span(),
+            Expression::Closure(closure) => closure.span(),
+            Expression::Literal(literal) => literal.span(),
+            Expression::Path(path) => path.span(),
+            Expression::Array(_, span) | Expression::Tuple(_, span) => span.clone(),
+            Expression::Init(init) => init.span(),
+            Expression::Operator(operator) => operator.span(),
+            Expression::Name(name) => name.span(),
+            Expression::If(r#if) => r#if.span(),
+            Expression::Unsafe(r#unsafe) => r#unsafe.span(),
+            Expression::Interpolation(parts) => parts.span(),
+            Expression::Todo(todo) => todo.span(),
+            Expression::Intrinsic(intrinsic) => intrinsic.span(),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl Expression {
+    /// The id of the expression, for the ones with a struct of their own.
+    pub fn id(&self) -> Option<NodeId> {
+        match self {
+            Expression::Match(r#match) => Some(r#match.id),
+            Expression::Closure(closure) => Some(closure.id),
+            Expression::Init(init) => Some(init.id),
+            Expression::If(r#if) => Some(r#if.id),
+            Expression::Unsafe(r#unsafe) => Some(r#unsafe.id),
+            Expression::Todo(todo) => Some(todo.id),
+            Expression::Literal(_)
+            | Expression::Path(_)
+            | Expression::Array(..)
+            | Expression::Tuple(..)
+            | Expression::Operator(_)
+            | Expression::Name(_)
+            | Expression::Interpolation(_)
+            | Expression::Intrinsic(_) => None,
+        }
+    }
+}
+
+/// `match scrutinee { pattern if guard => body, ... }`, whose arms are
+/// tried in order.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Match {
+    pub match_token: Token,
+    pub scrutinee: Box<Expression>,
+    pub arms: Vec<MatchArm>,
+    pub curly_bracket_close_token: Token,
+    pub id: NodeId,
+}
+
+impl Spanned for Match {
+    fn span(&self) -> Span {
+        self.match_token
+            .span
+            .joined(&self.curly_bracket_close_token.span)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    /// The condition behind `if`, which has to hold as well for the arm to
+    /// be taken.
+    pub guard: Option<Expression>,
+    pub body: MatchArmBody,
+    pub id: NodeId,
+}
+
+impl Spanned for MatchArm {
+    fn span(&self) -> Span {
+        self.pattern.span().joined(&self.body.span())
+    }
+}
+
+/// A block ends its arm without a `,`, while an expression needs one in
+/// front of the next arm.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum MatchArmBody {
+    Expression(Box<Expression>),
+    Block(Box<Block>),
+}
+
+impl Spanned for MatchArmBody {
+    fn span(&self) -> Span {
+        match self {
+            MatchArmBody::Expression(expression) => expression.span(),
+            MatchArmBody::Block(block) => block.span(),
+        }
+    }
+}
+
+/// `|x, y: i32| x + y`, or `|x|: i32 { ... }` whose return type makes the
+/// body a block. `||` starts a closure without parameters, and `move` in
+/// front of it makes the closure take what it uses by value.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Closure {
+    pub move_token: Option<Token>,
+    pub vertical_line_token: Token,
+    pub parameters: Vec<(Pattern, Option<Type>)>,
+    pub return_type: Option<Type>,
+    pub body: ClosureBody,
+    pub id: NodeId,
+}
+
+impl Spanned for Closure {
+    fn span(&self) -> Span {
+        self.move_token
+            .as_ref()
+            .unwrap_or(&self.vertical_line_token)
+            .span
+            .joined(&self.body.span())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum ClosureBody {
+    Expression(Box<Expression>),
+    Block(Box<Block>),
+}
+
+impl Spanned for ClosureBody {
+    fn span(&self) -> Span {
+        match self {
+            ClosureBody::Expression(expression) => expression.span(),
+            ClosureBody::Block(block) => block.span(),
+        }
+    }
+}
+
+/// `unsafe { ... }`, the only place the raw memory operations like `load32`
+/// and `store32` may be called.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Unsafe {
+    pub unsafe_token: Token,
+    pub block: Box<Block>,
+    pub id: NodeId,
+}
+
+impl Spanned for Unsafe {
+    fn span(&self) -> Span {
+        self.unsafe_token.span.joined(&self.block.span())
+    }
+}
+
+/// A struct literal like `Point { x: 1, y }`, where a field written without
+/// a value takes the variable of the same name.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Init {
+    pub name: Token,
+    pub fields: Vec<InitField>,
+    pub curly_bracket_close_token: Token,
+    pub id: NodeId,
+}
+
+impl Spanned for Init {
+    fn span(&self) -> Span {
+        self.name.span.joined(&self.curly_bracket_close_token.span)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct InitField {
+    pub name: Token,
+    pub value: Expression,
+    pub id: NodeId,
+}
+
+impl Spanned for InitField {
+    fn span(&self) -> Span {
+        self.name.span.joined(&self.value.span())
+    }
+}
+
+/// A literal as it is written, so `Literal::String` keeps its quotes.
+/// Adjacent string literals are joined into one token spanning all of them.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Literal {
-    String(String),
-    Character(String),
+    String(Token),
+    Character(Token),
     Integer(Integer),
-    Decimal(String),
-    Boolean(String),
+    Decimal(Token),
+    Boolean(Token),
+}
+
+impl Literal {
+    pub fn token(&self) -> &Token {
+        match self {
+            Literal::String(token)
+            | Literal::Character(token)
+            | Literal::Integer(Integer(token))
+            | Literal::Decimal(token)
+            | Literal::Boolean(token) => token,
+        }
+    }
+
+    /// The text of a string literal without its quotes, with `\\`, `\"`,
+    /// `\n`, `\r`, `\t`, `\0`, `\{` and `\}` replaced by what they escape,
+    /// or `None` for the other literals. Any other escape is kept as written.
+    pub fn text(&self) -> Option<String> {
+        let token = match self {
+            Literal::String(token) => token,
+            _ => return None,
+        };
+        let content = &token.content[1..token.content.len() - 1];
+        let mut text = String::with_capacity(content.len());
+        let mut chars = content.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                text.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('r') => text.push('\r'),
+                Some('t') => text.push('\t'),
+                Some('0') => text.push('\0'),
+                Some(c @ ('\\' | '"' | '{' | '}')) => text.push(c),
+                Some(c) => {
+                    text.push('\\');
+                    text.push(c);
+                }
+                None => text.push('\\'),
+            }
+        }
+        Some(text)
+    }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct Integer(pub String);
+impl Spanned for Literal {
+    fn span(&self) -> Span {
+        self.token().span.clone()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Integer(pub Token);
+
+impl Integer {
+    /// The value of the literal as written, in binary after `0b`, octal
+    /// after `0o` and hexadecimal after `0x`, or `None` if it doesn't fit in
+    /// 64 bits.
+    pub fn value(&self) -> Option<u64> {
+        let content = self.0.content.as_str();
+        let (digits, radix) = match content.get(..2) {
+            Some("0b") => (&content[2..], 2),
+            Some("0o") => (&content[2..], 8),
+            Some("0x") => (&content[2..], 16),
+            _ => (content, 10),
+        };
+        u64::from_str_radix(digits, radix).ok()
+    }
+}
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum InterpolationPart {
+    /// One of `"text {`, `} text {` and `} text"`.
+    Fragment(Token),
+    Expression(Expression),
+}
+
+impl Spanned for InterpolationPart {
+    fn span(&self) -> Span {
+        match self {
+            InterpolationPart::Fragment(token) => token.span.clone(),
+            InterpolationPart::Expression(expression) => expression.span(),
+        }
+    }
+}
+
+/// `@name` or `@name<T>`, one of the intrinsics the compiler lowers by
+/// itself, which is called like a function.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Intrinsic {
+    pub at: Token,
+    pub name: Token,
+    pub type_arguments: Vec<TypeParameter>,
+    pub span: Span,
+}
+
+impl Spanned for Intrinsic {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+/// `todo`, `todo("message")` or `unimplemented`, which has the never type
+/// and panics once it is evaluated.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Todo {
+    pub keyword: Token,
+    pub message: Option<Token>,
+    pub id: NodeId,
+}
+
+impl Todo {
+    /// The message without its quotes.
+    pub fn message(&self) -> Option<&str> {
+        self.message
+            .as_ref()
+            .map(|token| &token.content[1..token.content.len() - 1])
+    }
+
+    /// What the panic prints, like `not yet implemented: parse the header`.
+    pub fn description(&self) -> String {
+        let reason = match self.keyword.kind {
+            TokenKind::KeywordUnimplemented => "not implemented",
+            _ => "not yet implemented",
+        };
+        match self.message() {
+            Some(message) => format!("{}: {}", reason, message),
+            None => reason.to_string(),
+        }
+    }
+}
+
+impl Spanned for Todo {
+    fn span(&self) -> Span {
+        match &self.message {
+            Some(message) => self.keyword.span.joined(&message.span),
+            None => self.keyword.span.clone(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Operator {
     Prefix(PrefixOperator),
     Infix(InfixOperator),
     Postfix(PostfixOperator),
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl Spanned for Operator {
+    fn span(&self) -> Span {
+        match self {
+            Operator::Prefix(operator) => operator.span(),
+            Operator::Infix(operator) => operator.span(),
+            Operator::Postfix(operator) => operator.span(),
+        }
+    }
+}
+
+/// The prefix operators keep the span of the operator like the infix ones.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum PrefixOperator {
     Not(Not),
     UnaryPlus(UnaryPlus),
     UnaryMinus(UnaryMinus),
 }
-#[derive(Debug, PartialEq, Clone)]
-pub struct Not(pub Box<Expression>);
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct UnaryPlus(pub Box<Expression>);
+impl Spanned for PrefixOperator {
+    fn span(&self) -> Span {
+        let (PrefixOperator::Not(Not(span, operand))
+        | PrefixOperator::UnaryPlus(UnaryPlus(span, operand))
+        | PrefixOperator::UnaryMinus(UnaryMinus(span, operand))) = self;
+        span.joined(&operand.span())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Not(pub Span, pub Box<Expression>);
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct UnaryMinus(pub Box<Expression>);
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct UnaryPlus(pub Span, pub Box<Expression>);
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct UnaryMinus(pub Span, pub Box<Expression>);
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum InfixOperator {
     LogicalOr(Box<Expression>, Span, Box<Expression>),
     LogicalAnd(Box<Expression>, Span, Box<Expression>),
@@ -121,6 +825,15 @@ pub enum InfixOperator {
     GetFieldNullable(GetFieldNullable),
     RangeRightExclusive(Box<Expression>, Span, Box<Expression>),
     RangeRightInclusive(Box<Expression>, Span, Box<Expression>),
+    BitAnd(Box<Expression>, Span, Box<Expression>),
+    BitOr(Box<Expression>, Span, Box<Expression>),
+    BitXor(Box<Expression>, Span, Box<Expression>),
+    ShiftLeft(Box<Expression>, Span, Box<Expression>),
+    ShiftRight(Box<Expression>, Span, Box<Expression>),
+    Assign(Box<Expression>, Span, Box<Expression>),
+    /// `lhs += rhs` and the like, holding the operation applied before the
+    /// result is assigned back to `lhs`.
+    CompoundAssign(Box<InfixOperator>),
 }
 
 impl InfixOperator {
@@ -143,43 +856,156 @@ impl InfixOperator {
             InfixOperator::GetFieldNullable(..) => "GetField",
             InfixOperator::RangeRightExclusive(..) => "RangeToExlusive",
             InfixOperator::RangeRightInclusive(..) => "RangeToInclusive",
+            InfixOperator::BitAnd(..) => "BitAnd",
+            InfixOperator::BitOr(..) => "BitOr",
+            InfixOperator::BitXor(..) => "BitXor",
+            InfixOperator::ShiftLeft(..) => "ShiftLeft",
+            InfixOperator::ShiftRight(..) => "ShiftRight",
+            InfixOperator::Assign(..) => "Assign",
+            InfixOperator::CompoundAssign(operator) => match operator.as_ref() {
+                InfixOperator::Add(..) => "AddAssign",
+                InfixOperator::Subtract(..) => "SubtractAssign",
+                InfixOperator::Multiply(..) => "MultiplyAssign",
+                InfixOperator::Divide(..) => "DivideAssign",
+                InfixOperator::Remainder(..) => "RemainderAssign",
+                InfixOperator::BitAnd(..) => "BitAndAssign",
+                InfixOperator::BitOr(..) => "BitOrAssign",
+                InfixOperator::BitXor(..) => "BitXorAssign",
+                InfixOperator::ShiftLeft(..) => "ShiftLeftAssign",
+                InfixOperator::ShiftRight(..) => "ShiftRightAssign",
+                _ => "Assign",
+            },
+        }
+    }
+
+    pub fn operands(&self) -> (&Expression, &Expression) {
+        match self {
+            InfixOperator::LogicalOr(lhs, _, rhs)
+            | InfixOperator::LogicalAnd(lhs, _, rhs)
+            | InfixOperator::EqualTo(lhs, _, rhs)
+            | InfixOperator::NotEqualTo(lhs, _, rhs)
+            | InfixOperator::GreaterThan(lhs, _, rhs)
+            | InfixOperator::LessThan(lhs, _, rhs)
+            | InfixOperator::GreaterThanOrEqualTo(lhs, _, rhs)
+            | InfixOperator::LessThanOrEqualTo(lhs, _, rhs)
+            | InfixOperator::Add(lhs, _, rhs)
+            | InfixOperator::Subtract(lhs, _, rhs)
+            | InfixOperator::Multiply(lhs, _, rhs)
+            | InfixOperator::Divide(lhs, _, rhs)
+            | InfixOperator::Remainder(lhs, _, rhs)
+            | InfixOperator::RangeRightExclusive(lhs, _, rhs)
+            | InfixOperator::RangeRightInclusive(lhs, _, rhs)
+            | InfixOperator::BitAnd(lhs, _, rhs)
+            | InfixOperator::BitOr(lhs, _, rhs)
+            | InfixOperator::BitXor(lhs, _, rhs)
+            | InfixOperator::ShiftLeft(lhs, _, rhs)
+            | InfixOperator::ShiftRight(lhs, _, rhs)
+            | InfixOperator::Assign(lhs, _, rhs)
+            | InfixOperator::GetField(GetField(lhs, rhs))
+            | InfixOperator::GetFieldNullable(GetFieldNullable(lhs, rhs)) => (lhs, rhs),
+            InfixOperator::CompoundAssign(operator) => operator.operands(),
         }
     }
+
+    pub fn operands_mut(&mut self) -> (&mut Expression, &mut Expression) {
+        match self {
+            InfixOperator::LogicalOr(lhs, _, rhs)
+            | InfixOperator::LogicalAnd(lhs, _, rhs)
+            | InfixOperator::EqualTo(lhs, _, rhs)
+            | InfixOperator::NotEqualTo(lhs, _, rhs)
+            | InfixOperator::GreaterThan(lhs, _, rhs)
+            | InfixOperator::LessThan(lhs, _, rhs)
+            | InfixOperator::GreaterThanOrEqualTo(lhs, _, rhs)
+            | InfixOperator::LessThanOrEqualTo(lhs, _, rhs)
+            | InfixOperator::Add(lhs, _, rhs)
+            | InfixOperator::Subtract(lhs, _, rhs)
+            | InfixOperator::Multiply(lhs, _, rhs)
+            | InfixOperator::Divide(lhs, _, rhs)
+            | InfixOperator::Remainder(lhs, _, rhs)
+            | InfixOperator::RangeRightExclusive(lhs, _, rhs)
+            | InfixOperator::RangeRightInclusive(lhs, _, rhs)
+            | InfixOperator::BitAnd(lhs, _, rhs)
+            | InfixOperator::BitOr(lhs, _, rhs)
+            | InfixOperator::BitXor(lhs, _, rhs)
+            | InfixOperator::ShiftLeft(lhs, _, rhs)
+            | InfixOperator::ShiftRight(lhs, _, rhs)
+            | InfixOperator::Assign(lhs, _, rhs)
+            | InfixOperator::GetField(GetField(lhs, rhs))
+            | InfixOperator::GetFieldNullable(GetFieldNullable(lhs, rhs)) => (lhs, rhs),
+            InfixOperator::CompoundAssign(operator) => operator.operands_mut(),
+        }
+    }
+}
+
+impl Spanned for InfixOperator {
+    fn span(&self) -> Span {
+        let (lhs, rhs) = self.operands();
+        lhs.span().joined(&rhs.span())
+    }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct GetField(pub Box<Expression>, pub Box<Expression>);
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct GetFieldNullable(pub Box<Expression>, pub Box<Expression>);
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum PostfixOperator {
     Index(Index),
     FunctionCall(FunctionCall),
+    TypeArguments(TypeArguments),
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct Index(pub Box<Expression>, pub Vec<Expression>);
+impl Spanned for PostfixOperator {
+    fn span(&self) -> Span {
+        match self {
+            PostfixOperator::Index(index) => index.span(),
+            PostfixOperator::FunctionCall(function_call) => function_call.span(),
+            PostfixOperator::TypeArguments(type_arguments) => type_arguments.span(),
+        }
+    }
+}
+
+/// The indexed expression, the indices and the span of the `]`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Index(pub Box<Expression>, pub Vec<Expression>, pub Span);
 
 impl Spanned for Index {
-    fn span(&self) -> crate::syntax::Span {
-        self.0.span().joined(&self.1.span())
+    fn span(&self) -> Span {
+        self.0.span().joined(&self.2)
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct FunctionCall(pub Box<Expression>, pub Vec<Expression>);
 
 impl Spanned for FunctionCall {
-    fn span(&self) -> crate::syntax::Span {
+    fn span(&self) -> Span {
         self.0.span().joined(&self.1.span())
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// `g::<i32>`: the expression given type arguments, the arguments and the
+/// span of the `>`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TypeArguments(pub Box<Expression>, pub Vec<TypeParameter>, pub Span);
+
+impl Spanned for TypeArguments {
+    fn span(&self) -> Span {
+        self.0.span().joined(&self.2)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Path(pub Vec<Token>);
 
+impl Spanned for Path {
+    fn span(&self) -> Span {
+        self.0.span()
+    }
+}
+
 impl fmt::Display for Path {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -194,16 +1020,25 @@ impl fmt::Display for Path {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum TypeParameter {
-    Star,
+    Star(Span),
     Specific(Type),
 }
 
+impl Spanned for TypeParameter {
+    fn span(&self) -> Span {
+        match self {
+            TypeParameter::Star(span) => span.clone(),
+            TypeParameter::Specific(ty) => ty.span(),
+        }
+    }
+}
+
 impl fmt::Display for TypeParameter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            TypeParameter::Star => {
+            TypeParameter::Star(_) => {
                 write!(f, "*")
             }
             TypeParameter::Specific(ty) => {
@@ -213,20 +1048,45 @@ impl fmt::Display for TypeParameter {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// A type as it is written. The span of the `()` a function without a
+/// return type returns is empty, right behind its parameters.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Type {
     Basic {
         base: Path,
         type_parameters: Vec<TypeParameter>,
+        span: Span,
     },
-    Tuple(Vec<Type>),
-    Impl(Box<Type>),
-    Nullable(Box<Type>),
+    /// The types with the span from `(` to `)`.
+    Tuple(Vec<Type>, Span),
+    /// The span of `impl` and the type behind it.
+    Impl(Span, Box<Type>),
+    /// The span of `dyn` and the trait behind it.
+    Dyn(Span, Box<Type>),
+    /// The type and the span of the `?` behind it.
+    Nullable(Box<Type>, Span),
     Function {
         // generic and where clause?
         parameters_type: Vec<Type>,
         return_type: Box<Type>,
+        span: Span,
     },
+    /// `!`, the type of expressions which never produce a value, so it fits
+    /// wherever any other type is required.
+    Never(Span),
+}
+
+impl Spanned for Type {
+    fn span(&self) -> Span {
+        match self {
+            Type::Basic { span, .. }
+            | Type::Tuple(_, span)
+            | Type::Function { span, .. }
+            | Type::Never(span) => span.clone(),
+            Type::Impl(span, ty) | Type::Dyn(span, ty) => span.joined(&ty.span()),
+            Type::Nullable(ty, span) => ty.span().joined(span),
+        }
+    }
 }
 
 impl fmt::Display for Type {
@@ -235,6 +1095,12 @@ impl fmt::Display for Type {
             Type::Basic {
                 base,
                 type_parameters,
+                ..
+            } if type_parameters.is_empty() => write!(f, "{}", base),
+            Type::Basic {
+                base,
+                type_parameters,
+                ..
             } => {
                 write!(
                     f,
@@ -247,7 +1113,7 @@ impl fmt::Display for Type {
                         .join(", ")
                 )
             }
-            Type::Tuple(types) => {
+            Type::Tuple(types, _) => {
                 write!(
                     f,
                     "({})",
@@ -258,15 +1124,19 @@ impl fmt::Display for Type {
                         .join("")
                 )
             }
-            Type::Impl(ty) => {
+            Type::Impl(_, ty) => {
                 write!(f, "impl {}", ty)
             }
-            Type::Nullable(ty) => {
+            Type::Dyn(_, ty) => {
+                write!(f, "dyn {}", ty)
+            }
+            Type::Nullable(ty, _) => {
                 write!(f, "{}?", ty)
             }
             Type::Function {
                 parameters_type,
                 return_type,
+                ..
             } => {
                 write!(
                     f,
@@ -279,39 +1149,121 @@ impl fmt::Display for Type {
                     return_type
                 )
             }
+            Type::Never(_) => {
+                write!(f, "!")
+            }
         }
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Pattern {
     Slot(Name),
+    /// Only matching a value equal to the literal, so it can't be used for
+    /// parameters.
+    Literal(Literal),
+}
+
+impl Spanned for Pattern {
+    fn span(&self) -> Span {
+        match self {
+            Pattern::Slot(name) => name.span(),
+            Pattern::Literal(literal) => literal.span(),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Name {
     Ident(Token),
-    Placeholder,
+    /// `_`
+    Placeholder(Token),
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl Spanned for Name {
+    fn span(&self) -> Span {
+        match self {
+            Name::Ident(token) | Name::Placeholder(token) => token.span.clone(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct If {
     pub if_token: Token,
     pub condition: Box<Expression>,
     pub body: Box<Block>,
     pub else_part: Option<Else>,
+    pub id: NodeId,
+}
+
+impl Spanned for If {
+    fn span(&self) -> Span {
+        let end = match &self.else_part {
+            Some(r#else) => r#else.span(),
+            None => self.body.span(),
+        };
+        self.if_token.span.joined(&end)
+    }
+}
+
+impl If {
+    /// The condition and the body of this `if` followed by those of every
+    /// `else if` chained behind it, in the order they are tested.
+    pub fn branches(&self) -> Vec<(&Expression, &Block)> {
+        let mut branches = vec![(self.condition.as_ref(), self.body.as_ref())];
+        let mut else_part = &self.else_part;
+        while let Some(Else::If(_, r#if)) = else_part {
+            branches.push((r#if.condition.as_ref(), r#if.body.as_ref()));
+            else_part = &r#if.else_part;
+        }
+        branches
+    }
+
+    /// The block of the `else` ending the chain, if there is one.
+    pub fn else_block(&self) -> Option<&Block> {
+        let mut else_part = &self.else_part;
+        loop {
+            match else_part {
+                Some(Else::If(_, r#if)) => else_part = &r#if.else_part,
+                Some(Else::Block(_, block)) => return Some(block),
+                None => return None,
+            }
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// What follows the body of an `if`. An `else` always belongs to the
+/// nearest `if` in front of it, so `else if` nests the rest of the chain
+/// inside of the `Else::If`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Else {
     If(Token, Box<If>),
     Block(Token, Box<Block>),
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl Spanned for Else {
+    fn span(&self) -> Span {
+        match self {
+            Else::If(else_token, r#if) => else_token.span.joined(&r#if.span()),
+            Else::Block(else_token, block) => else_token.span.joined(&block.span()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Block {
     pub curly_bracket_open_token: Token,
     pub body: Vec<Statement>,
     pub last_expression: Option<Expression>,
     pub curly_bracket_close_token: Token,
+    pub id: NodeId,
+}
+
+impl Spanned for Block {
+    fn span(&self) -> Span {
+        self.curly_bracket_open_token
+            .span
+            .joined(&self.curly_bracket_close_token.span)
+    }
 }
@@ -0,0 +1,475 @@
+//! Building the AST in code instead of parsing it from source, for the
+//! tools, tests and expansions making up nodes of their own. The nodes have
+//! empty spans and `NodeId::DUMMY` as their ids, like every node a pass
+//! builds itself, and `print` turns them into the source they stand for.
+//!
+//! Functions, blocks, modules, `if`s and `match`es are put together by
+//! builders, like `fn_("main").public().returns(ty("i32")).body(block()
+//! .value(call(name("f"), vec![integer(1)])))`, and the other nodes by plain
+//! functions. The names ending in `_` are the ones which are keywords in
+//! Rust.
+
+use crate::{
+    core::{ast::*, NodeId},
+    syntax::{Span, Token, TokenKind},
+};
+
+fn token(kind: TokenKind, content: impl Into<String>) -> Token {
+    Token {
+        kind,
+        span: Span::EMPTY,
+        content: content.into(),
+    }
+}
+
+fn ident(name: &str) -> Token {
+    token(TokenKind::IdentifierIdentifier(name.to_string()), name)
+}
+
+/// `path` split into its segments at each `separator`, which is `::` in a
+/// `use` and `.` elsewhere.
+fn path_of(path: &str, separator: &str) -> Path {
+    Path(path.split(separator).map(ident).collect())
+}
+
+impl From<FunctionDeclaration> for Statement {
+    fn from(declaration: FunctionDeclaration) -> Self {
+        Statement::Declaration(Declaration::FunctionDeclaration(declaration))
+    }
+}
+
+impl From<UseDeclaration> for Statement {
+    fn from(declaration: UseDeclaration) -> Self {
+        Statement::Declaration(Declaration::UseDeclaration(declaration))
+    }
+}
+
+impl From<ModuleDeclaration> for Statement {
+    fn from(declaration: ModuleDeclaration) -> Self {
+        Statement::Declaration(Declaration::ModuleDeclaration(declaration))
+    }
+}
+
+impl From<Expression> for Statement {
+    fn from(expression: Expression) -> Self {
+        Statement::Expression(expression)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ModuleBuilder {
+    nodes: Vec<Node>,
+}
+
+pub fn module() -> ModuleBuilder {
+    ModuleBuilder::default()
+}
+
+impl ModuleBuilder {
+    pub fn statement(mut self, statement: impl Into<Statement>) -> Self {
+        self.nodes.push(Node::Statement(statement.into()));
+        self
+    }
+
+    pub fn build(self) -> Module {
+        Module { nodes: self.nodes }
+    }
+}
+
+impl From<ModuleBuilder> for Module {
+    fn from(builder: ModuleBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// `mod name { ... }` holding `module`.
+pub fn mod_(name: &str, module: impl Into<Module>) -> ModuleDeclaration {
+    ModuleDeclaration {
+        documentation: None,
+        is_pub: false,
+        mod_token: token(TokenKind::KeywordMod, "mod"),
+        name: ident(name),
+        body: ModuleBody::Inline {
+            curly_bracket_open_token: token(TokenKind::PunctuationLeftCurlyBracket, "{"),
+            module: module.into(),
+            curly_bracket_close_token: token(TokenKind::PunctuationRightCurlyBracket, "}"),
+        },
+        span: Span::EMPTY,
+        id: NodeId::DUMMY,
+    }
+}
+
+/// `use a::b;`, importing the last segment of `path`.
+pub fn use_(path: &str) -> UseDeclaration {
+    UseDeclaration {
+        documentation: None,
+        is_pub: false,
+        use_token: token(TokenKind::KeywordUse, "use"),
+        tree: UseTree {
+            prefix: path_of(path, "::"),
+            kind: UseTreeKind::Simple(None),
+            span: Span::EMPTY,
+            id: NodeId::DUMMY,
+        },
+        span: Span::EMPTY,
+        id: NodeId::DUMMY,
+    }
+}
+
+/// A function returning `()` without parameters nor a body until they are
+/// given.
+#[derive(Debug)]
+pub struct FunctionBuilder {
+    declaration: FunctionDeclaration,
+}
+
+pub fn fn_(name: &str) -> FunctionBuilder {
+    FunctionBuilder {
+        declaration: FunctionDeclaration {
+            documentation: None,
+            attributes: Vec::new(),
+            is_pub: false,
+            is_extern: false,
+            name: ident(name),
+            generic_parameters: Vec::new(),
+            parameters: Vec::new(),
+            return_type: unit(),
+            body: None,
+            span: Span::EMPTY,
+            id: NodeId::DUMMY,
+        },
+    }
+}
+
+impl FunctionBuilder {
+    pub fn public(mut self) -> Self {
+        self.declaration.is_pub = true;
+        self
+    }
+
+    pub fn external(mut self) -> Self {
+        self.declaration.is_extern = true;
+        self
+    }
+
+    pub fn parameter(mut self, name: &str, ty: Type) -> Self {
+        self.declaration
+            .parameters
+            .push((Pattern::Slot(Name::Ident(ident(name))), ty));
+        self
+    }
+
+    pub fn returns(mut self, ty: Type) -> Self {
+        self.declaration.return_type = ty;
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Block>) -> Self {
+        self.declaration.body = Some(body.into());
+        self
+    }
+
+    pub fn build(self) -> FunctionDeclaration {
+        self.declaration
+    }
+}
+
+impl From<FunctionBuilder> for FunctionDeclaration {
+    fn from(builder: FunctionBuilder) -> Self {
+        builder.build()
+    }
+}
+
+impl From<FunctionBuilder> for Statement {
+    fn from(builder: FunctionBuilder) -> Self {
+        builder.build().into()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BlockBuilder {
+    statements: Vec<Statement>,
+    value: Option<Expression>,
+}
+
+pub fn block() -> BlockBuilder {
+    BlockBuilder::default()
+}
+
+impl BlockBuilder {
+    pub fn statement(mut self, statement: impl Into<Statement>) -> Self {
+        self.statements.push(statement.into());
+        self
+    }
+
+    /// The expression ending the block without a `;`, which it evaluates
+    /// to.
+    pub fn value(mut self, value: impl Into<Expression>) -> Self {
+        self.value = Some(value.into());
+        self
+    }
+
+    pub fn build(self) -> Block {
+        Block {
+            curly_bracket_open_token: token(TokenKind::PunctuationLeftCurlyBracket, "{"),
+            body: self.statements,
+            last_expression: self.value,
+            curly_bracket_close_token: token(TokenKind::PunctuationRightCurlyBracket, "}"),
+            id: NodeId::DUMMY,
+        }
+    }
+}
+
+impl From<BlockBuilder> for Block {
+    fn from(builder: BlockBuilder) -> Self {
+        builder.build()
+    }
+}
+
+/// An `if` without an `else` until one is given.
+#[derive(Debug)]
+pub struct IfBuilder {
+    r#if: If,
+}
+
+pub fn if_(condition: impl Into<Expression>, body: impl Into<Block>) -> IfBuilder {
+    IfBuilder {
+        r#if: If {
+            if_token: token(TokenKind::KeywordIf, "if"),
+            condition: Box::new(condition.into()),
+            body: Box::new(body.into()),
+            else_part: None,
+            id: NodeId::DUMMY,
+        },
+    }
+}
+
+impl IfBuilder {
+    /// Puts `else_block` at the end of the `else if` chain.
+    pub fn else_(self, else_block: impl Into<Block>) -> Self {
+        let else_token = token(TokenKind::KeywordElse, "else");
+        self.chain(Else::Block(else_token, Box::new(else_block.into())))
+    }
+
+    /// Puts `r#if` at the end of the `else if` chain.
+    pub fn else_if(self, r#if: IfBuilder) -> Self {
+        let else_token = token(TokenKind::KeywordElse, "else");
+        self.chain(Else::If(else_token, Box::new(r#if.build())))
+    }
+
+    fn chain(mut self, r#else: Else) -> Self {
+        fn last_else(r#if: &mut If) -> &mut Option<Else> {
+            match r#if.else_part {
+                Some(Else::If(_, ref mut r#if)) => last_else(r#if),
+                ref mut else_part => else_part,
+            }
+        }
+        *last_else(&mut self.r#if) = Some(r#else);
+        self
+    }
+
+    pub fn build(self) -> If {
+        self.r#if
+    }
+}
+
+impl From<IfBuilder> for Expression {
+    fn from(builder: IfBuilder) -> Self {
+        Expression::If(builder.build())
+    }
+}
+
+/// A `match` without arms until they are given.
+#[derive(Debug)]
+pub struct MatchBuilder {
+    r#match: Match,
+}
+
+pub fn match_(scrutinee: impl Into<Expression>) -> MatchBuilder {
+    MatchBuilder {
+        r#match: Match {
+            match_token: token(TokenKind::KeywordMatch, "match"),
+            scrutinee: Box::new(scrutinee.into()),
+            arms: Vec::new(),
+            curly_bracket_close_token: token(TokenKind::PunctuationRightCurlyBracket, "}"),
+            id: NodeId::DUMMY,
+        },
+    }
+}
+
+impl MatchBuilder {
+    pub fn arm(self, pattern: Pattern, body: impl Into<Expression>) -> Self {
+        self.arm_with_guard(pattern, None, body)
+    }
+
+    pub fn arm_with_guard(
+        mut self,
+        pattern: Pattern,
+        guard: Option<Expression>,
+        body: impl Into<Expression>,
+    ) -> Self {
+        self.r#match.arms.push(MatchArm {
+            pattern,
+            guard,
+            body: MatchArmBody::Expression(Box::new(body.into())),
+            id: NodeId::DUMMY,
+        });
+        self
+    }
+
+    pub fn build(self) -> Match {
+        self.r#match
+    }
+}
+
+impl From<MatchBuilder> for Expression {
+    fn from(builder: MatchBuilder) -> Self {
+        Expression::Match(builder.build())
+    }
+}
+
+/// `x` binding what it is matched with, or `_` ignoring it.
+pub fn binding(name: &str) -> Pattern {
+    match name {
+        "_" => Pattern::Slot(Name::Placeholder(token(
+            TokenKind::KeywordPlaceholderName,
+            "_",
+        ))),
+        name => Pattern::Slot(Name::Ident(ident(name))),
+    }
+}
+
+pub fn name(name: &str) -> Expression {
+    Expression::Name(Name::Ident(ident(name)))
+}
+
+/// `a.b.c`, split at the `.`s.
+pub fn path(path: &str) -> Expression {
+    Expression::Path(path_of(path, "."))
+}
+
+/// An integer written in decimal, with a `-` in front if it is negative.
+pub fn integer(value: i64) -> Expression {
+    let content = value.unsigned_abs().to_string();
+    let literal = Expression::Literal(Literal::Integer(Integer(token(
+        TokenKind::LiteralNumberIntegral(content.clone()),
+        content,
+    ))));
+    if value < 0 {
+        neg(literal)
+    } else {
+        literal
+    }
+}
+
+pub fn boolean(value: bool) -> Expression {
+    Expression::Literal(Literal::Boolean(token(
+        TokenKind::LiteralBoolean(value.to_string()),
+        value.to_string(),
+    )))
+}
+
+/// A string literal holding `text`, with its `"`s and `\`s escaped.
+pub fn string(text: &str) -> Expression {
+    let content = format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+    Expression::Literal(Literal::String(token(
+        TokenKind::LiteralString(content.clone()),
+        content,
+    )))
+}
+
+pub fn tuple(elements: Vec<Expression>) -> Expression {
+    Expression::Tuple(elements, Span::EMPTY)
+}
+
+pub fn array(elements: Vec<Expression>) -> Expression {
+    Expression::Array(elements, Span::EMPTY)
+}
+
+pub fn call(callee: impl Into<Expression>, arguments: Vec<Expression>) -> Expression {
+    Expression::Operator(Operator::Postfix(PostfixOperator::FunctionCall(
+        FunctionCall(Box::new(callee.into()), arguments),
+    )))
+}
+
+/// `operand.field`.
+pub fn field(operand: impl Into<Expression>, field: &str) -> Expression {
+    Expression::Operator(Operator::Infix(InfixOperator::GetField(GetField(
+        Box::new(operand.into()),
+        Box::new(name(field)),
+    ))))
+}
+
+/// `!operand`
+pub fn not(operand: impl Into<Expression>) -> Expression {
+    Expression::Operator(Operator::Prefix(PrefixOperator::Not(Not(
+        Span::EMPTY,
+        Box::new(operand.into()),
+    ))))
+}
+
+/// `-operand`
+pub fn neg(operand: impl Into<Expression>) -> Expression {
+    Expression::Operator(Operator::Prefix(PrefixOperator::UnaryMinus(UnaryMinus(
+        Span::EMPTY,
+        Box::new(operand.into()),
+    ))))
+}
+
+/// `lhs` and `rhs` around the binary operator `operator`, which is a
+/// variant like `InfixOperator::Add`.
+pub fn infix(
+    lhs: impl Into<Expression>,
+    operator: impl FnOnce(Box<Expression>, Span, Box<Expression>) -> InfixOperator,
+    rhs: impl Into<Expression>,
+) -> Expression {
+    Expression::Operator(Operator::Infix(operator(
+        Box::new(lhs.into()),
+        Span::EMPTY,
+        Box::new(rhs.into()),
+    )))
+}
+
+pub fn unsafe_(block: impl Into<Block>) -> Expression {
+    Expression::Unsafe(Unsafe {
+        unsafe_token: token(TokenKind::KeywordUnsafe, "unsafe"),
+        block: Box::new(block.into()),
+        id: NodeId::DUMMY,
+    })
+}
+
+pub fn todo() -> Expression {
+    Expression::Todo(Todo {
+        keyword: token(TokenKind::KeywordTodo, "todo"),
+        message: None,
+        id: NodeId::DUMMY,
+    })
+}
+
+/// `@name<type_arguments>`, to be called like `call(intrinsic("trap",
+/// vec![]), vec![])`.
+pub fn intrinsic(name: &str, type_arguments: Vec<Type>) -> Expression {
+    Expression::Intrinsic(Intrinsic {
+        at: token(TokenKind::PunctuationCommercialAt, "@"),
+        name: ident(name),
+        type_arguments: type_arguments
+            .into_iter()
+            .map(TypeParameter::Specific)
+            .collect(),
+        span: Span::EMPTY,
+    })
+}
+
+/// A type named by `path` like `i32` or `a.B`, without type parameters.
+pub fn ty(path: &str) -> Type {
+    Type::Basic {
+        base: path_of(path, "."),
+        type_parameters: Vec::new(),
+        span: Span::EMPTY,
+    }
+}
+
+/// `()`
+pub fn unit() -> Type {
+    Type::Tuple(Vec::new(), Span::EMPTY)
+}
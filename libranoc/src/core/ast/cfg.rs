@@ -0,0 +1,141 @@
+//! Conditional compilation: `#[cfg(predicate)]` in front of a declaration
+//! keeps it only when the predicate holds for the configuration compiled
+//! with, and the declarations which don't are dropped from the AST before
+//! anything else sees them.
+//!
+//! A predicate is `name`, which holds when the flag `name` is set,
+//! `key = "value"`, which holds when `key` is set to `value`, or `all(...)`,
+//! `any(...)` and `not(...)` of other predicates.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::core::{
+    ast::*,
+    visit_mut::{self, VisitMut},
+    Error,
+};
+
+const EXPECTED: &str = "a flag, `key = \"value\"`, or `all(...)`, `any(...)` or `not(...)` of them";
+
+/// The flags and the values of the keys `cfg` predicates are evaluated
+/// against, like `target = "wasi"`. A key may be set to more than one value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cfg {
+    flags: HashSet<String>,
+    values: HashMap<String, HashSet<String>>,
+}
+
+impl Cfg {
+    pub fn new() -> Self {
+        Cfg::default()
+    }
+
+    pub fn flag(mut self, name: impl Into<String>) -> Self {
+        self.flags.insert(name.into());
+        self
+    }
+
+    pub fn value(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.values
+            .entry(key.into())
+            .or_default()
+            .insert(value.into());
+        self
+    }
+
+    pub fn is_set(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    pub fn has_value(&self, key: &str, value: &str) -> bool {
+        matches!(self.values.get(key), Some(values) if values.contains(value))
+    }
+
+    /// Whether the predicate `value`, the inside of `cfg(...)`, holds.
+    pub fn evaluate(&self, value: &AttributeValue) -> Result<bool, Error> {
+        match value {
+            AttributeValue::Word(name) => Ok(self.is_set(&name.content)),
+            AttributeValue::KeyValue { key, value } => match value.as_str() {
+                Some(text) => Ok(self.has_value(&key.content, text)),
+                None => Err(Error::invalid_attribute(value, "a string")),
+            },
+            AttributeValue::List { name, items, .. } => match name.content.as_str() {
+                "all" => items
+                    .iter()
+                    .try_fold(true, |holds, item| Ok(self.evaluate(item)? && holds)),
+                "any" => items
+                    .iter()
+                    .try_fold(false, |holds, item| Ok(self.evaluate(item)? || holds)),
+                "not" => match items.as_slice() {
+                    [item] => Ok(!self.evaluate(item)?),
+                    _ => Err(Error::invalid_attribute(value, "a single predicate")),
+                },
+                _ => Err(Error::invalid_attribute(value, EXPECTED)),
+            },
+            AttributeValue::Literal(_) => Err(Error::invalid_attribute(value, EXPECTED)),
+        }
+    }
+
+    /// Whether every `cfg` attribute of `attributes` holds. An attribute
+    /// which isn't a single predicate is reported and keeps the declaration.
+    fn enables(&self, attributes: &[Attribute], errors: &mut Vec<Error>) -> bool {
+        let mut enabled = true;
+        for attribute in attributes {
+            if attribute.name() != Some("cfg") {
+                continue;
+            }
+            let result = match &attribute.value {
+                AttributeValue::List { items, .. } if items.len() == 1 => self.evaluate(&items[0]),
+                value => Err(Error::invalid_attribute(value, "`cfg(predicate)`")),
+            };
+            match result {
+                Ok(holds) => enabled &= holds,
+                Err(error) => errors.push(error),
+            }
+        }
+        enabled
+    }
+}
+
+/// Drops the declarations of `module` the `cfg` attributes of which don't
+/// hold for `cfg`, also inside of modules and blocks, and returns the
+/// attributes which couldn't be evaluated.
+pub fn configure(module: &mut Module, cfg: &Cfg) -> Vec<Error> {
+    let mut configurer = Configurer {
+        cfg,
+        errors: Vec::new(),
+    };
+    configurer.visit_module(module);
+    configurer.errors
+}
+
+struct Configurer<'a> {
+    cfg: &'a Cfg,
+    errors: Vec<Error>,
+}
+
+impl Configurer<'_> {
+    fn keeps(&mut self, statement: &Statement) -> bool {
+        match statement {
+            Statement::Declaration(Declaration::FunctionDeclaration(declaration)) => {
+                self.cfg.enables(&declaration.attributes, &mut self.errors)
+            }
+            _ => true,
+        }
+    }
+}
+
+impl VisitMut for Configurer<'_> {
+    fn visit_module(&mut self, module: &mut Module) {
+        module.nodes.retain(|node| match node {
+            Node::Statement(statement) => self.keeps(statement),
+            Node::Directive(_) => true,
+        });
+        visit_mut::walk_module(self, module)
+    }
+
+    fn visit_block(&mut self, block: &mut Block) {
+        block.body.retain(|statement| self.keeps(statement));
+        visit_mut::walk_block(self, block)
+    }
+}
@@ -0,0 +1,737 @@
+//! Turning the AST back into Rano source. The output is canonical rather
+//! than the original text: it is indented by four spaces, puts every
+//! statement on its own line and only keeps the parentheses the operators
+//! need, so printing a module and parsing it again gives the same AST up to
+//! the spans and the ids. The uses of a declared operator are already calls
+//! of its function in the AST, and are printed as such.
+
+use crate::core::{ast::*, visit::Visit};
+
+pub fn print_module(module: &Module) -> String {
+    let mut printer = Printer::default();
+    printer.module(module);
+    printer.output
+}
+
+pub fn print_statement(statement: &Statement) -> String {
+    let mut printer = Printer::default();
+    printer.statement(statement);
+    printer.output
+}
+
+pub fn print_expression(expression: &Expression) -> String {
+    let mut printer = Printer::default();
+    printer.expression(expression);
+    printer.output
+}
+
+pub fn print_type(ty: &Type) -> String {
+    let mut printer = Printer::default();
+    printer.ty(ty);
+    printer.output
+}
+
+/// How tightly an expression holds together, following the precedences of
+/// the parser. An operand binding looser than its operator is put in
+/// parentheses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    /// The body of a closure takes everything behind it, so a closure is
+    /// always put in parentheses as an operand.
+    Closure,
+    Assignment,
+    Range,
+    LogicalOr,
+    LogicalAnd,
+    Comparison,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
+    Additive,
+    Multiplicative,
+    Prefix,
+    Index,
+    Call,
+    Access,
+    Atom,
+}
+
+fn infix_level(operator: &InfixOperator) -> Level {
+    match operator {
+        InfixOperator::Assign(..) | InfixOperator::CompoundAssign(_) => Level::Assignment,
+        InfixOperator::RangeRightExclusive(..) | InfixOperator::RangeRightInclusive(..) => {
+            Level::Range
+        }
+        InfixOperator::LogicalOr(..) => Level::LogicalOr,
+        InfixOperator::LogicalAnd(..) => Level::LogicalAnd,
+        InfixOperator::EqualTo(..)
+        | InfixOperator::NotEqualTo(..)
+        | InfixOperator::GreaterThan(..)
+        | InfixOperator::LessThan(..)
+        | InfixOperator::GreaterThanOrEqualTo(..)
+        | InfixOperator::LessThanOrEqualTo(..) => Level::Comparison,
+        InfixOperator::BitOr(..) => Level::BitOr,
+        InfixOperator::BitXor(..) => Level::BitXor,
+        InfixOperator::BitAnd(..) => Level::BitAnd,
+        InfixOperator::ShiftLeft(..) | InfixOperator::ShiftRight(..) => Level::Shift,
+        InfixOperator::Add(..) | InfixOperator::Subtract(..) => Level::Additive,
+        InfixOperator::Multiply(..) | InfixOperator::Divide(..) | InfixOperator::Remainder(..) => {
+            Level::Multiplicative
+        }
+        InfixOperator::GetField(_) | InfixOperator::GetFieldNullable(_) => Level::Access,
+    }
+}
+
+/// Whether the operands of `operator` group from the right, like `=` and
+/// `.` do.
+fn is_right_associative(operator: &InfixOperator) -> bool {
+    matches!(infix_level(operator), Level::Assignment | Level::Access)
+}
+
+fn infix_symbol(operator: &InfixOperator) -> &'static str {
+    match operator {
+        InfixOperator::LogicalOr(..) => "||",
+        InfixOperator::LogicalAnd(..) => "&&",
+        InfixOperator::EqualTo(..) => "==",
+        InfixOperator::NotEqualTo(..) => "!=",
+        InfixOperator::GreaterThan(..) => ">",
+        InfixOperator::LessThan(..) => "<",
+        InfixOperator::GreaterThanOrEqualTo(..) => ">=",
+        InfixOperator::LessThanOrEqualTo(..) => "<=",
+        InfixOperator::Add(..) => "+",
+        InfixOperator::Subtract(..) => "-",
+        InfixOperator::Multiply(..) => "*",
+        InfixOperator::Divide(..) => "/",
+        InfixOperator::Remainder(..) => "%",
+        InfixOperator::GetField(_) => ".",
+        InfixOperator::GetFieldNullable(_) => "?.",
+        InfixOperator::RangeRightExclusive(..) => "..",
+        InfixOperator::RangeRightInclusive(..) => "..=",
+        InfixOperator::BitAnd(..) => "&",
+        InfixOperator::BitOr(..) => "|",
+        InfixOperator::BitXor(..) => "^",
+        InfixOperator::ShiftLeft(..) => "<<",
+        InfixOperator::ShiftRight(..) => ">>",
+        InfixOperator::Assign(..) => "=",
+        InfixOperator::CompoundAssign(operator) => match operator.as_ref() {
+            InfixOperator::Add(..) => "+=",
+            InfixOperator::Subtract(..) => "-=",
+            InfixOperator::Multiply(..) => "*=",
+            InfixOperator::Divide(..) => "/=",
+            InfixOperator::Remainder(..) => "%=",
+            InfixOperator::BitAnd(..) => "&=",
+            InfixOperator::BitOr(..) => "|=",
+            InfixOperator::BitXor(..) => "^=",
+            InfixOperator::ShiftLeft(..) => "<<=",
+            InfixOperator::ShiftRight(..) => ">>=",
+            _ => "=",
+        },
+    }
+}
+
+fn level(expression: &Expression) -> Level {
+    match expression {
+        Expression::Closure(_) => Level::Closure,
+        Expression::Operator(Operator::Prefix(_)) => Level::Prefix,
+        Expression::Operator(Operator::Infix(operator)) => infix_level(operator),
+        Expression::Operator(Operator::Postfix(
+            PostfixOperator::Index(_) | PostfixOperator::TypeArguments(_),
+        )) => Level::Index,
+        Expression::Operator(Operator::Postfix(PostfixOperator::FunctionCall(_))) => Level::Call,
+        Expression::Match(_)
+        | Expression::Literal(_)
+        | Expression::Path(_)
+        | Expression::Array(..)
+        | Expression::Tuple(..)
+        | Expression::Init(_)
+        | Expression::Name(_)
+        | Expression::If(_)
+        | Expression::Unsafe(_)
+        | Expression::Interpolation(_)
+        | Expression::Todo(_)
+        | Expression::Intrinsic(_) => Level::Atom,
+    }
+}
+
+/// Whether a struct literal or a closure is somewhere inside of an
+/// expression, which can't be written as it is where a `{` starts a body.
+#[derive(Default)]
+struct BraceFinder {
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for BraceFinder {
+    fn visit_init(&mut self, _init: &'ast Init) {
+        self.found = true;
+    }
+
+    fn visit_closure(&mut self, _closure: &'ast Closure) {
+        self.found = true;
+    }
+}
+
+fn needs_group_before_body(expression: &Expression) -> bool {
+    let mut finder = BraceFinder::default();
+    finder.visit_expression(expression);
+    finder.found
+}
+
+#[derive(Default)]
+struct Printer {
+    output: String,
+    indent: usize,
+}
+
+impl Printer {
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn newline(&mut self) {
+        self.output.push('\n');
+        for _ in 0..self.indent {
+            self.output.push_str("    ");
+        }
+    }
+
+    fn separated<T>(&mut self, items: &[T], mut print: impl FnMut(&mut Self, &T)) {
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 {
+                self.write(", ");
+            }
+            print(self, item);
+        }
+    }
+
+    fn module(&mut self, module: &Module) {
+        self.nodes(&module.nodes);
+        if !module.nodes.is_empty() {
+            self.write("\n");
+        }
+    }
+
+    /// The nodes of a module, with an empty line between two declarations.
+    fn nodes(&mut self, nodes: &[Node]) {
+        for (index, node) in nodes.iter().enumerate() {
+            if index > 0 {
+                let is_declaration =
+                    |node: &Node| matches!(node, Node::Statement(Statement::Declaration(_)));
+                if is_declaration(&nodes[index - 1]) || is_declaration(node) {
+                    self.write("\n");
+                }
+                self.newline();
+            }
+            match node {
+                Node::Directive(directive) => {
+                    self.write("#!");
+                    self.attribute_body(&directive.attribute.value);
+                }
+                Node::Statement(statement) => self.statement(statement),
+            }
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Declaration(declaration) => self.declaration(declaration),
+            Statement::Let(r#let) => {
+                self.write("let ");
+                self.pattern(&r#let.pattern);
+                if let Some(ty) = &r#let.ty {
+                    self.write(": ");
+                    self.ty(ty);
+                }
+                if let Some(value) = &r#let.value {
+                    self.write(" = ");
+                    self.expression(value);
+                }
+                self.write(";");
+            }
+            Statement::Expression(expression) => {
+                self.expression(expression);
+                self.write(";");
+            }
+        }
+    }
+
+    fn documentation(&mut self, documentation: &Option<Documentation>) {
+        if let Some(documentation) = documentation {
+            for line in &documentation.lines {
+                self.write(&line.content);
+                self.newline();
+            }
+        }
+    }
+
+    fn attribute_body(&mut self, value: &AttributeValue) {
+        self.write("[");
+        self.attribute_value(value);
+        self.write("]");
+    }
+
+    fn attribute_value(&mut self, value: &AttributeValue) {
+        match value {
+            AttributeValue::Word(token) | AttributeValue::Literal(token) => {
+                self.write(&token.content)
+            }
+            AttributeValue::KeyValue { key, value } => {
+                self.write(&key.content);
+                self.write(" = ");
+                self.attribute_value(value);
+            }
+            AttributeValue::List { name, items, .. } => {
+                self.write(&name.content);
+                self.write("(");
+                self.separated(items, Printer::attribute_value);
+                self.write(")");
+            }
+        }
+    }
+
+    fn pub_keyword(&mut self, is_pub: bool) {
+        if is_pub {
+            self.write("pub ");
+        }
+    }
+
+    fn declaration(&mut self, declaration: &Declaration) {
+        match declaration {
+            Declaration::FunctionDeclaration(declaration) => self.function_declaration(declaration),
+            Declaration::UseDeclaration(declaration) => {
+                self.documentation(&declaration.documentation);
+                self.pub_keyword(declaration.is_pub);
+                self.write("use ");
+                self.use_tree(&declaration.tree);
+                self.write(";");
+            }
+            Declaration::OperatorDeclaration(declaration) => {
+                self.documentation(&declaration.documentation);
+                self.write("operator ");
+                for token in &declaration.symbol {
+                    self.write(&token.content);
+                }
+                self.write(": ");
+                self.write(&declaration.precedence.content);
+                self.write(", ");
+                self.write(&declaration.associativity.content);
+                self.write(" = ");
+                self.write(&declaration.function.content);
+                self.write(";");
+            }
+            Declaration::ModuleDeclaration(declaration) => {
+                self.documentation(&declaration.documentation);
+                self.pub_keyword(declaration.is_pub);
+                self.write("mod ");
+                self.write(&declaration.name.content);
+                match &declaration.body {
+                    ModuleBody::File(_) => self.write(";"),
+                    ModuleBody::Inline { module, .. } if module.nodes.is_empty() => {
+                        self.write(" {}")
+                    }
+                    ModuleBody::Inline { module, .. } => {
+                        self.write(" {");
+                        self.indent += 1;
+                        self.newline();
+                        self.nodes(&module.nodes);
+                        self.indent -= 1;
+                        self.newline();
+                        self.write("}");
+                    }
+                }
+            }
+        }
+    }
+
+    fn function_declaration(&mut self, declaration: &FunctionDeclaration) {
+        self.documentation(&declaration.documentation);
+        for attribute in &declaration.attributes {
+            self.write("#");
+            self.attribute_body(&attribute.value);
+            self.newline();
+        }
+        self.pub_keyword(declaration.is_pub);
+        if declaration.is_extern {
+            self.write("extern ");
+        }
+        self.write("fn ");
+        self.write(&declaration.name.content);
+        if !declaration.generic_parameters.is_empty() {
+            self.write("<");
+            self.separated(&declaration.generic_parameters, |printer, parameter| {
+                printer.write(&parameter.name.content);
+                for (index, bound) in parameter.bounds.iter().enumerate() {
+                    printer.write(if index == 0 { ": " } else { " + " });
+                    printer.ty(bound);
+                }
+            });
+            self.write(">");
+        }
+        self.write("(");
+        self.separated(&declaration.parameters, |printer, (pattern, ty)| {
+            printer.pattern(pattern);
+            printer.write(": ");
+            printer.ty(ty);
+        });
+        self.write(")");
+        if !matches!(&declaration.return_type, Type::Tuple(types, _) if types.is_empty()) {
+            self.write(": ");
+            self.ty(&declaration.return_type);
+        }
+        match &declaration.body {
+            Some(body) => {
+                self.write(" ");
+                self.block(body);
+            }
+            None => self.write(";"),
+        }
+    }
+
+    fn use_tree(&mut self, tree: &UseTree) {
+        let prefix = tree
+            .prefix
+            .0
+            .iter()
+            .map(|segment| segment.content.as_str())
+            .collect::<Vec<_>>()
+            .join("::");
+        self.write(&prefix);
+        let separator = if prefix.is_empty() { "" } else { "::" };
+        match &tree.kind {
+            UseTreeKind::Simple(Some(alias)) => {
+                self.write(" as ");
+                self.write(&alias.content);
+            }
+            UseTreeKind::Simple(None) => {}
+            UseTreeKind::Glob(_) => {
+                self.write(separator);
+                self.write("*");
+            }
+            UseTreeKind::Nested(trees) => {
+                self.write(separator);
+                self.write("{");
+                self.separated(trees, Printer::use_tree);
+                self.write("}");
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        if block.body.is_empty() && block.last_expression.is_none() {
+            self.write("{}");
+            return;
+        }
+        self.write("{");
+        self.indent += 1;
+        for statement in &block.body {
+            self.newline();
+            self.statement(statement);
+        }
+        if let Some(expression) = &block.last_expression {
+            self.newline();
+            self.expression(expression);
+        }
+        self.indent -= 1;
+        self.newline();
+        self.write("}");
+    }
+
+    /// Writes `expression`, in parentheses unless it binds at least as
+    /// tightly as `minimum`.
+    fn operand(&mut self, expression: &Expression, minimum: Level, strict: bool) {
+        let level = level(expression);
+        if level < minimum || (strict && level == minimum) || level == Level::Closure {
+            self.write("(");
+            self.expression(expression);
+            self.write(")");
+        } else {
+            self.expression(expression);
+        }
+    }
+
+    /// Writes the condition of an `if` or the scrutinee of a `match`, where
+    /// a struct literal has to be in parentheses.
+    fn before_body(&mut self, expression: &Expression) {
+        if needs_group_before_body(expression) {
+            self.write("(");
+            self.expression(expression);
+            self.write(")");
+        } else {
+            self.expression(expression);
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Match(r#match) => {
+                self.write("match ");
+                self.before_body(&r#match.scrutinee);
+                self.write(" {");
+                self.indent += 1;
+                for arm in &r#match.arms {
+                    self.newline();
+                    self.pattern(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        self.write(" if ");
+                        self.expression(guard);
+                    }
+                    self.write(" => ");
+                    match &arm.body {
+                        MatchArmBody::Expression(expression) => {
+                            self.expression(expression);
+                            self.write(",");
+                        }
+                        MatchArmBody::Block(block) => self.block(block),
+                    }
+                }
+                self.indent -= 1;
+                self.newline();
+                self.write("}");
+            }
+            Expression::Closure(closure) => {
+                if closure.move_token.is_some() {
+                    self.write("move ");
+                }
+                self.write("|");
+                self.separated(&closure.parameters, |printer, (pattern, ty)| {
+                    printer.pattern(pattern);
+                    if let Some(ty) = ty {
+                        printer.write(": ");
+                        printer.ty(ty);
+                    }
+                });
+                self.write("|");
+                if let Some(return_type) = &closure.return_type {
+                    self.write(": ");
+                    self.ty(return_type);
+                }
+                self.write(" ");
+                match &closure.body {
+                    ClosureBody::Expression(expression) => self.expression(expression),
+                    ClosureBody::Block(block) => self.block(block),
+                }
+            }
+            Expression::Literal(literal) => self.write(&literal.token().content),
+            Expression::Path(path) => self.path(path),
+            Expression::Array(elements, _) => {
+                self.write("[");
+                self.separated(elements, Printer::expression);
+                self.write("]");
+            }
+            Expression::Tuple(elements, _) => self.tuple(elements),
+            Expression::Init(init) => {
+                self.write(&init.name.content);
+                if init.fields.is_empty() {
+                    self.write(" {}");
+                    return;
+                }
+                self.write(" { ");
+                self.separated(&init.fields, |printer, field| {
+                    printer.write(&field.name.content);
+                    match &field.value {
+                        Expression::Name(Name::Ident(name))
+                            if name.content == field.name.content => {}
+                        value => {
+                            printer.write(": ");
+                            printer.expression(value);
+                        }
+                    }
+                });
+                self.write(" }");
+            }
+            Expression::Operator(operator) => self.operator(operator),
+            Expression::Name(name) => self.name(name),
+            Expression::If(r#if) => self.r#if(r#if),
+            Expression::Unsafe(r#unsafe) => {
+                self.write("unsafe ");
+                self.block(&r#unsafe.block);
+            }
+            Expression::Interpolation(parts) => {
+                for part in parts {
+                    match part {
+                        InterpolationPart::Fragment(token) => self.write(&token.content),
+                        InterpolationPart::Expression(expression) => self.expression(expression),
+                    }
+                }
+            }
+            Expression::Todo(todo) => {
+                self.write(&todo.keyword.content);
+                if let Some(message) = &todo.message {
+                    self.write("(");
+                    self.write(&message.content);
+                    self.write(")");
+                }
+            }
+            Expression::Intrinsic(intrinsic) => {
+                self.write("@");
+                self.write(&intrinsic.name.content);
+                self.type_arguments(&intrinsic.type_arguments);
+            }
+        }
+    }
+
+    /// `()`, `(a,)` or `(a, b)`.
+    fn tuple(&mut self, elements: &[Expression]) {
+        self.write("(");
+        self.separated(elements, Printer::expression);
+        if elements.len() == 1 {
+            self.write(",");
+        }
+        self.write(")");
+    }
+
+    fn r#if(&mut self, r#if: &If) {
+        self.write("if ");
+        self.before_body(&r#if.condition);
+        self.write(" ");
+        self.block(&r#if.body);
+        match &r#if.else_part {
+            Some(Else::If(_, r#if)) => {
+                self.write(" else ");
+                self.r#if(r#if);
+            }
+            Some(Else::Block(_, block)) => {
+                self.write(" else ");
+                self.block(block);
+            }
+            None => {}
+        }
+    }
+
+    fn operator(&mut self, operator: &Operator) {
+        match operator {
+            Operator::Prefix(operator) => {
+                let (symbol, operand) = match operator {
+                    PrefixOperator::Not(Not(_, operand)) => ("!", operand),
+                    PrefixOperator::UnaryPlus(UnaryPlus(_, operand)) => ("+", operand),
+                    PrefixOperator::UnaryMinus(UnaryMinus(_, operand)) => ("-", operand),
+                };
+                self.write(symbol);
+                self.operand(operand, Level::Prefix, false);
+            }
+            Operator::Infix(operator) => {
+                let level = infix_level(operator);
+                let right_associative = is_right_associative(operator);
+                let (lhs, rhs) = operator.operands();
+                self.operand(lhs, level, right_associative);
+                match level {
+                    Level::Access => self.write(infix_symbol(operator)),
+                    _ => {
+                        self.write(" ");
+                        self.write(infix_symbol(operator));
+                        self.write(" ");
+                    }
+                }
+                self.operand(rhs, level, !right_associative);
+            }
+            Operator::Postfix(PostfixOperator::Index(Index(operand, arguments, _))) => {
+                self.operand(operand, Level::Index, false);
+                self.write("[");
+                self.separated(arguments, Printer::expression);
+                self.write("]");
+            }
+            Operator::Postfix(PostfixOperator::TypeArguments(TypeArguments(
+                operand,
+                arguments,
+                _,
+            ))) => {
+                self.operand(operand, Level::Index, false);
+                self.write("::<");
+                self.separated(arguments, |printer, argument| match argument {
+                    TypeParameter::Star(_) => printer.write("*"),
+                    TypeParameter::Specific(ty) => printer.ty(ty),
+                });
+                self.write(">");
+            }
+            Operator::Postfix(PostfixOperator::FunctionCall(FunctionCall(callee, arguments))) => {
+                self.operand(callee, Level::Call, true);
+                match arguments.as_slice() {
+                    [Expression::Tuple(elements, _)] => self.tuple(elements),
+                    arguments => {
+                        self.write("(");
+                        self.separated(arguments, Printer::expression);
+                        self.write(")");
+                    }
+                }
+            }
+        }
+    }
+
+    fn name(&mut self, name: &Name) {
+        match name {
+            Name::Ident(token) | Name::Placeholder(token) => self.write(&token.content),
+        }
+    }
+
+    fn path(&mut self, path: &Path) {
+        let segments = path
+            .0
+            .iter()
+            .map(|segment| segment.content.as_str())
+            .collect::<Vec<_>>();
+        self.write(&segments.join("."));
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Slot(name) => self.name(name),
+            Pattern::Literal(literal) => self.write(&literal.token().content),
+        }
+    }
+
+    /// `<A, B>`, unless there are none.
+    fn type_arguments(&mut self, arguments: &[TypeParameter]) {
+        if !arguments.is_empty() {
+            self.write("<");
+            self.separated(arguments, |printer, argument| match argument {
+                TypeParameter::Star(_) => printer.write("*"),
+                TypeParameter::Specific(ty) => printer.ty(ty),
+            });
+            self.write(">");
+        }
+    }
+
+    fn ty(&mut self, ty: &Type) {
+        match ty {
+            Type::Basic {
+                base,
+                type_parameters,
+                ..
+            } => {
+                self.path(base);
+                self.type_arguments(type_parameters);
+            }
+            Type::Tuple(types, _) => {
+                self.write("(");
+                self.separated(types, Printer::ty);
+                if types.len() == 1 {
+                    self.write(",");
+                }
+                self.write(")");
+            }
+            Type::Impl(_, ty) => {
+                self.write("impl ");
+                self.ty(ty);
+            }
+            Type::Dyn(_, ty) => {
+                self.write("dyn ");
+                self.ty(ty);
+            }
+            Type::Nullable(ty, _) => {
+                self.ty(ty);
+                self.write("?");
+            }
+            Type::Function {
+                parameters_type,
+                return_type,
+                ..
+            } => {
+                self.write("(");
+                self.separated(parameters_type, Printer::ty);
+                self.write(") -> ");
+                self.ty(return_type);
+            }
+            Type::Never(_) => self.write("!"),
+        }
+    }
+}
@@ -2,11 +2,11 @@ use std::fmt;
 
 use thiserror::Error;
 
-use crate::syntax::{Span, Spanned, Token};
+use crate::syntax::{suggest_keyword, Span, Spanned, Token, TokenKind};
 
-use super::ast::Type;
+use super::ast::{AttributeValue, Todo, Type};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 #[repr(u16)]
 pub enum ErrorCode {
     SyntaxError = 0001,
@@ -14,21 +14,47 @@ pub enum ErrorCode {
     UndefinedSymbol = 0003,
     MismatchedType = 0004,
     Unimplemented = 0005,
+    RuntimeError = 0006,
+    MismatchedArity = 0007,
+    NotSendSafe = 0008,
+    InvalidEncoding = 0010,
+    InvalidIr = 0011,
+    RemainingTodo = 0012,
+    NestedTooDeeply = 0013,
+    InvalidAttribute = 0014,
+    UnsafeRequired = 0015,
+    ArithmeticOverflow = 0016,
+    DivisionByZero = 0017,
+    Unused = 0018,
+    Unreachable = 0019,
+    InvalidMacro = 0020,
+    NoMatchingMacroRule = 0021,
+    PossiblyNull = 0025,
+    PossiblyUninitialized = 0026,
+    UseAfterMove = 0027,
+    InvalidIntrinsic = 0029,
+    UnsupportedTarget = 0030,
+    InvalidModule = 0031,
+    ClosureOutlivesCapture = 0032,
+    LinkError = 0033,
+    NativeCodegen = 0034,
+    ComponentInterface = 0035,
+    NotThreadSafe = 0036,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Location {
     Eof,
     Known(Span),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Label {
     pub location: Location,
     pub message: Option<String>,
 }
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, PartialEq)]
 #[error("{message}")]
 pub struct Error {
     pub code: ErrorCode,
@@ -53,6 +79,16 @@ impl Error {
             ],
         }
     }
+    pub fn missing_semicolon(after: &Span) -> Error {
+        Error {
+            code: ErrorCode::SyntaxError,
+            message: "Expected `;` after statement.".to_string(),
+            labels: vec![Label {
+                location: Location::Known(after.end()),
+                message: Some("add `;` here".to_string()),
+            }],
+        }
+    }
     pub fn undefined_symbol(name: impl fmt::Display, span: Span) -> Error {
         Error {
             code: ErrorCode::UndefinedSymbol,
@@ -77,6 +113,46 @@ impl Error {
             }],
         }
     }
+    /// Like `mismatched_type`, for the types the checker infers, which are
+    /// given as they are displayed.
+    pub fn mismatched_types(
+        required: impl fmt::Display,
+        gotten: impl fmt::Display,
+        location: Span,
+    ) -> Error {
+        Error {
+            code: ErrorCode::MismatchedType,
+            message: "Mismatched types.".to_string(),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: Some(format!("Required `{}` but got `{}`", required, gotten)),
+            }],
+        }
+    }
+    pub fn unsupported_operand(
+        operator: impl fmt::Display,
+        ty: impl fmt::Display,
+        location: Span,
+    ) -> Error {
+        Error {
+            code: ErrorCode::MismatchedType,
+            message: format!("`{}` isn't implemented for `{}`.", operator, ty),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: None,
+            }],
+        }
+    }
+    pub fn not_callable(ty: impl fmt::Display, location: Span) -> Error {
+        Error {
+            code: ErrorCode::MismatchedType,
+            message: format!("`{}` isn't a function, so it can't be called.", ty),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: None,
+            }],
+        }
+    }
     pub fn unimplemented(ast: impl Spanned) -> Error {
         Error {
             code: ErrorCode::Unimplemented,
@@ -87,6 +163,557 @@ impl Error {
             }],
         }
     }
+    pub fn mismatched_arity(
+        name: impl fmt::Display,
+        required: usize,
+        gotten: usize,
+        location: Span,
+    ) -> Error {
+        Error {
+            code: ErrorCode::MismatchedArity,
+            message: format!(
+                "`{}` takes {} argument(s) but {} were supplied.",
+                name, required, gotten
+            ),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: None,
+            }],
+        }
+    }
+    pub fn not_send_safe(ty: impl fmt::Display, location: Span) -> Error {
+        Error {
+            code: ErrorCode::NotSendSafe,
+            message: format!("`{}` cannot be sent to another thread.", ty),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: Some("Only `i32`s and booleans may cross threads.".to_owned()),
+            }],
+        }
+    }
+    pub fn not_plain_data(ty: impl fmt::Display, location: Span) -> Error {
+        Error {
+            code: ErrorCode::NotThreadSafe,
+            message: format!(
+                "`{}` points into the heap, which a `#[thread_safe]` function can't use.",
+                ty
+            ),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: Some(
+                    "Only integers, floats, booleans and tuples of them may be used there."
+                        .to_owned(),
+                ),
+            }],
+        }
+    }
+    pub fn invalid_encoding(encoding: &str, byte_offset: usize, location: Span) -> Error {
+        Error {
+            code: ErrorCode::InvalidEncoding,
+            message: format!("The source is not valid {}.", encoding),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: Some(format!("Invalid data at byte {}", byte_offset)),
+            }],
+        }
+    }
+    pub fn invalid_ir(message: impl fmt::Display) -> Error {
+        Error {
+            code: ErrorCode::InvalidIr,
+            message: message.to_string(),
+            labels: Vec::new(),
+        }
+    }
+    pub fn runtime_error(message: impl fmt::Display, location: Location) -> Error {
+        Error {
+            code: ErrorCode::RuntimeError,
+            message: format!("{}", message),
+            labels: vec![Label {
+                location,
+                message: None,
+            }],
+        }
+    }
+    /// `value` of an attribute isn't what its consumer expects, which is
+    /// told by `expected`, like "a string".
+    pub fn invalid_attribute(value: &AttributeValue, expected: impl fmt::Display) -> Error {
+        Error {
+            code: ErrorCode::InvalidAttribute,
+            message: format!("Invalid attribute, expected {}.", expected),
+            labels: vec![Label {
+                location: Location::Known(value.span()),
+                message: None,
+            }],
+        }
+    }
+    pub fn unsafe_required(name: &Token) -> Error {
+        Error {
+            code: ErrorCode::UnsafeRequired,
+            message: format!(
+                "`{}` accesses the memory directly, which is only allowed in an `unsafe` block.",
+                name.content
+            ),
+            labels: vec![Label {
+                location: Location::Known(name.span.clone()),
+                message: None,
+            }],
+        }
+    }
+    pub fn arithmetic_overflow(location: Span) -> Error {
+        Error {
+            code: ErrorCode::ArithmeticOverflow,
+            message: "This constant expression overflows `i32`.".to_string(),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: None,
+            }],
+        }
+    }
+    pub fn literal_out_of_range(location: Span, ty: impl fmt::Display) -> Error {
+        Error {
+            code: ErrorCode::ArithmeticOverflow,
+            message: format!("This literal is out of the range of `{}`.", ty),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: None,
+            }],
+        }
+    }
+    pub fn division_by_zero(divisor: Span) -> Error {
+        Error {
+            code: ErrorCode::DivisionByZero,
+            message: "This constant expression divides by zero.".to_string(),
+            labels: vec![Label {
+                location: Location::Known(divisor),
+                message: Some("this is zero".to_string()),
+            }],
+        }
+    }
+    pub fn unused_binding(name: &Token) -> Error {
+        Error {
+            code: ErrorCode::Unused,
+            message: format!("`{}` is never used.", name.content),
+            labels: vec![Label {
+                location: Location::Known(name.span.clone()),
+                message: Some(format!(
+                    "if this is intentional, name it `_{}` instead",
+                    name.content
+                )),
+            }],
+        }
+    }
+    pub fn uncalled_function(name: &Token) -> Error {
+        Error {
+            code: ErrorCode::Unused,
+            message: format!("The function `{}` is never called.", name.content),
+            labels: vec![Label {
+                location: Location::Known(name.span.clone()),
+                message: None,
+            }],
+        }
+    }
+    pub fn unreachable(location: Span, diverging: Span) -> Error {
+        Error {
+            code: ErrorCode::Unreachable,
+            message: "Unreachable code.".to_string(),
+            labels: vec![
+                Label {
+                    location: Location::Known(location),
+                    message: None,
+                },
+                Label {
+                    location: Location::Known(diverging),
+                    message: Some("any code following this never runs".to_string()),
+                },
+            ],
+        }
+    }
+    pub fn invalid_macro(message: impl Into<String>, token: &Token) -> Error {
+        Error {
+            code: ErrorCode::InvalidMacro,
+            message: message.into(),
+            labels: vec![Label {
+                location: Location::Known(token.span.clone()),
+                message: None,
+            }],
+        }
+    }
+    pub fn no_matching_macro_rule(name: &Token, invocation: Span) -> Error {
+        Error {
+            code: ErrorCode::NoMatchingMacroRule,
+            message: format!(
+                "No rule of macro `{}` matches this invocation.",
+                name.content
+            ),
+            labels: vec![Label {
+                location: Location::Known(invocation),
+                message: None,
+            }],
+        }
+    }
+    pub fn macro_too_deep(name: &Token, max_depth: usize) -> Error {
+        Error {
+            code: ErrorCode::NestedTooDeeply,
+            message: format!(
+                "Macro `{}` expanded too deeply, at most {} levels are allowed.",
+                name.content, max_depth
+            ),
+            labels: vec![Label {
+                location: Location::Known(name.span.clone()),
+                message: Some("the limit is reached here".to_string()),
+            }],
+        }
+    }
+    pub fn remaining_todo(todo: &Todo) -> Error {
+        Error {
+            code: ErrorCode::RemainingTodo,
+            message: format!("Remaining `{}`.", todo.keyword.content),
+            labels: vec![Label {
+                location: Location::Known(todo.span()),
+                message: todo.message().map(|message| message.to_string()),
+            }],
+        }
+    }
+    pub fn possibly_null(ty: impl fmt::Display, location: Span) -> Error {
+        Error {
+            code: ErrorCode::PossiblyNull,
+            message: format!("This value of type `{}` may be `null`.", ty),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: Some("Compare it with `null` first, or use `?.`.".to_owned()),
+            }],
+        }
+    }
+    pub fn possibly_uninitialized(
+        name: impl fmt::Display,
+        location: Span,
+        missing: Span,
+        reason: impl fmt::Display,
+    ) -> Error {
+        Error {
+            code: ErrorCode::PossiblyUninitialized,
+            message: format!("`{}` may be read before it is assigned.", name),
+            labels: vec![
+                Label {
+                    location: Location::Known(location),
+                    message: Some("read here".to_owned()),
+                },
+                Label {
+                    location: Location::Known(missing),
+                    message: Some(reason.to_string()),
+                },
+            ],
+        }
+    }
+    pub fn use_after_move(name: impl fmt::Display, location: Span, moved: Span) -> Error {
+        Error {
+            code: ErrorCode::UseAfterMove,
+            message: format!("`{}` is used after it was moved.", name),
+            labels: vec![
+                Label {
+                    location: Location::Known(location),
+                    message: Some("used here".to_owned()),
+                },
+                Label {
+                    location: Location::Known(moved),
+                    message: Some("moved here".to_owned()),
+                },
+            ],
+        }
+    }
+    pub fn closure_outlives_capture(
+        name: impl fmt::Display,
+        location: Span,
+        captured: Span,
+    ) -> Error {
+        Error {
+            code: ErrorCode::ClosureOutlivesCapture,
+            message: format!(
+                "The closure may outlive `{}`, which it captures by reference.",
+                name
+            ),
+            labels: vec![
+                Label {
+                    location: Location::Known(location),
+                    message: Some("given back here; `move` would capture it by value".to_owned()),
+                },
+                Label {
+                    location: Location::Known(captured),
+                    message: Some("captured here".to_owned()),
+                },
+            ],
+        }
+    }
+    pub fn unknown_intrinsic(name: &Token) -> Error {
+        Error {
+            code: ErrorCode::InvalidIntrinsic,
+            message: format!("There is no intrinsic called `@{}`.", name.content),
+            labels: vec![Label {
+                location: Location::Known(name.span.clone()),
+                message: None,
+            }],
+        }
+    }
+    pub fn uncalled_intrinsic(name: &Token, location: Span) -> Error {
+        Error {
+            code: ErrorCode::InvalidIntrinsic,
+            message: format!(
+                "`@{}` is an intrinsic, which can only be called.",
+                name.content
+            ),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: None,
+            }],
+        }
+    }
+    pub fn mismatched_type_arity(
+        name: impl fmt::Display,
+        required: usize,
+        gotten: usize,
+        location: Span,
+    ) -> Error {
+        Error {
+            code: ErrorCode::MismatchedArity,
+            message: format!(
+                "`{}` takes {} type argument(s) but {} were supplied.",
+                name, required, gotten
+            ),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: None,
+            }],
+        }
+    }
+    /// A target option the codegen can't compile for, which no code in
+    /// particular is to blame for.
+    pub fn unsupported_target(option: &str, reason: &str) -> Error {
+        Error {
+            code: ErrorCode::UnsupportedTarget,
+            message: format!("The `{}` target isn't supported yet: {}.", option, reason),
+            labels: Vec::new(),
+        }
+    }
+    /// An intrinsic which can only be compiled for the target `option`,
+    /// called without it.
+    pub fn intrinsic_needs_target(name: &Token, option: &str) -> Error {
+        Error {
+            code: ErrorCode::UnsupportedTarget,
+            message: format!("`@{}` needs the `{}` target.", name.content, option),
+            labels: vec![Label {
+                location: Location::Known(name.span.clone()),
+                message: None,
+            }],
+        }
+    }
+    /// A builtin which can only be compiled for the target `option`,
+    /// called without it.
+    pub fn builtin_needs_target(name: &Token, option: &str) -> Error {
+        Error {
+            code: ErrorCode::UnsupportedTarget,
+            message: format!("`{}` needs the `{}` target.", name.content, option),
+            labels: vec![Label {
+                location: Location::Known(name.span.clone()),
+                message: None,
+            }],
+        }
+    }
+    /// A field the type of the operand doesn't have, like `t.2` of a pair.
+    pub fn no_field(ty: impl fmt::Display, field: impl fmt::Display, span: Span) -> Error {
+        Error {
+            code: ErrorCode::UndefinedSymbol,
+            message: format!("`{}` has no field `{}`.", ty, field),
+            labels: vec![Label {
+                location: Location::Known(span),
+                message: None,
+            }],
+        }
+    }
+    /// An extern function returning a tuple the target can't give all the
+    /// values of.
+    pub fn extern_results_need_target(name: &Token, option: &str) -> Error {
+        Error {
+            code: ErrorCode::UnsupportedTarget,
+            message: format!(
+                "`{}` returns more values than an extern function can without the `{}` target.",
+                name.content, option
+            ),
+            labels: vec![Label {
+                location: Location::Known(name.span.clone()),
+                message: None,
+            }],
+        }
+    }
+    /// The codegen emitted a module that doesn't validate, which is a bug
+    /// of the codegen rather than of the code compiled.
+    pub fn invalid_module(message: impl fmt::Display) -> Error {
+        Error {
+            code: ErrorCode::InvalidModule,
+            message: format!("The compiled module isn't valid WebAssembly: {}", message),
+            labels: Vec::new(),
+        }
+    }
+    /// A failure of Cranelift, which is a bug of the native backend rather
+    /// than of the code compiled.
+    pub fn native_codegen(message: impl fmt::Display) -> Error {
+        Error {
+            code: ErrorCode::NativeCodegen,
+            message: format!("Failed to compile to native code: {}", message),
+            labels: Vec::new(),
+        }
+    }
+    /// Two of the modules linked together export a function of the same
+    /// name.
+    pub fn exported_twice(name: &str, first: &str, second: &str) -> Error {
+        Error {
+            code: ErrorCode::LinkError,
+            message: format!(
+                "`{}` is exported by both `{}` and `{}`, which are linked together.",
+                name, first, second
+            ),
+            labels: Vec::new(),
+        }
+    }
+    /// A module links to a function of another one with another type than
+    /// the function has.
+    pub fn mismatched_link(importer: &str, module: &str, name: &str) -> Error {
+        Error {
+            code: ErrorCode::LinkError,
+            message: format!(
+                "`{}` links to `{}` of `{}` with another type than it has there.",
+                importer, name, module
+            ),
+            labels: Vec::new(),
+        }
+    }
+    /// A function of the interface of a component, exported or imported,
+    /// takes or gives a value of a type the component can't pass yet.
+    pub fn not_in_interface(name: &Token, ty: &str) -> Error {
+        Error {
+            code: ErrorCode::ComponentInterface,
+            message: format!(
+                "`{}` can't be in the interface of a component, as it takes or gives a `{}`.",
+                name.content, ty
+            ),
+            labels: vec![Label {
+                location: Location::Known(name.span.clone()),
+                message: None,
+            }],
+        }
+    }
+    /// A name of the interface of a component which isn't one in WIT.
+    pub fn invalid_label(name: &Token) -> Error {
+        Error {
+            code: ErrorCode::ComponentInterface,
+            message: format!(
+                "`{}` can't name a function or a parameter of a component, which are words of letters and digits, each starting with a letter, separated by `_`.",
+                name.content
+            ),
+            labels: vec![Label {
+                location: Location::Known(name.span.clone()),
+                message: None,
+            }],
+        }
+    }
+    /// The module imports something a component has nothing to give it
+    /// for, like a builtin of the host.
+    pub fn component_import(module: &str, name: &str) -> Error {
+        Error {
+            code: ErrorCode::ComponentInterface,
+            message: format!(
+                "A component can't import `{}` of `{}`, which the host gives a module.",
+                name, module
+            ),
+            labels: Vec::new(),
+        }
+    }
+    /// An atomic intrinsic is called outside of a `#[thread_safe]`
+    /// function.
+    pub fn thread_safe_required(name: &Token) -> Error {
+        Error {
+            code: ErrorCode::NotThreadSafe,
+            message: format!(
+                "`@{}` is only allowed in a `#[thread_safe]` function.",
+                name.content
+            ),
+            labels: vec![Label {
+                location: Location::Known(name.span.clone()),
+                message: None,
+            }],
+        }
+    }
+    /// A `#[thread_safe]` function uses, or a thread sharing the memory is
+    /// spawned with, something which isn't thread safe, like a function
+    /// without the attribute.
+    pub fn not_thread_safe(name: impl fmt::Display, location: Span) -> Error {
+        Error {
+            code: ErrorCode::NotThreadSafe,
+            message: format!(
+                "`{}` isn't thread safe, so it can't run on a thread sharing the memory.",
+                name
+            ),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: Some(
+                    "Only the `#[thread_safe]` functions and the intrinsics using neither the heap nor the shadow stack are."
+                        .to_owned(),
+                ),
+            }],
+        }
+    }
+    /// A `#[thread_safe]` function has a value passed back through the
+    /// memory, which another thread could overwrite.
+    pub fn spilled_across_threads(ty: impl fmt::Display, location: Span) -> Error {
+        Error {
+            code: ErrorCode::NotThreadSafe,
+            message: format!(
+                "`{}` is passed back through the memory, which a `#[thread_safe]` function shares with other threads.",
+                ty
+            ),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: Some("The `multi-value` target gives small tuples as values.".to_owned()),
+            }],
+        }
+    }
+    /// A SIMD intrinsic given a type argument which isn't a vector.
+    pub fn not_a_vector(name: &Token, ty: impl fmt::Display) -> Error {
+        Error {
+            code: ErrorCode::InvalidIntrinsic,
+            message: format!(
+                "`@{}` takes a vector type like `f32x4`, not `{}`.",
+                name.content, ty
+            ),
+            labels: vec![Label {
+                location: Location::Known(name.span.clone()),
+                message: None,
+            }],
+        }
+    }
+    /// A value of a type which can only be compiled for the target
+    /// `option`, like a vector without `simd`.
+    pub fn type_needs_target(ty: impl fmt::Display, option: &str, location: Span) -> Error {
+        Error {
+            code: ErrorCode::UnsupportedTarget,
+            message: format!("`{}` needs the `{}` target.", ty, option),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: None,
+            }],
+        }
+    }
+    /// A lane of a vector given to a SIMD intrinsic by anything but an
+    /// integer literal below the number of lanes.
+    pub fn invalid_lane(location: Span, lanes: u32) -> Error {
+        Error {
+            code: ErrorCode::InvalidIntrinsic,
+            message: format!("A lane must be an integer literal below {}.", lanes),
+            labels: vec![Label {
+                location: Location::Known(location),
+                message: Some("The lane is encoded in the instruction.".to_owned()),
+            }],
+        }
+    }
 }
 
 impl<'a> From<::nom::Err<crate::syntax::Error>> for Error {
@@ -105,16 +732,76 @@ impl<'a> From<::nom::Err<crate::syntax::Error>> for Error {
                     message: None,
                 }],
             ),
-            ::nom::Err::Error(error) | ::nom::Err::Failure(error) => {
-                let token = &error.input.tokens[0];
+            ::nom::Err::Error(error) | ::nom::Err::Failure(error)
+                if error.is_too_deeply_nested() =>
+            {
+                return Error {
+                    code: ErrorCode::NestedTooDeeply,
+                    message: format!(
+                        "Expression too deeply nested, at most {} levels are allowed.",
+                        error.input.max_depth
+                    ),
+                    labels: vec![Label {
+                        location: match error.input.tokens.first() {
+                            Some(token) => Location::Known(token.span.clone()),
+                            None => Location::Eof,
+                        },
+                        message: Some("the limit is reached here".to_string()),
+                    }],
+                };
+            }
+            ::nom::Err::Error(error) | ::nom::Err::Failure(error)
+                if error.is_struct_literal_not_allowed() =>
+            {
                 (
-                    format!("Unexpected token: {}", token.content),
+                    "Struct literals are not allowed in the condition of an `if` or the scrutinee of a `match`.".to_string(),
                     vec![Label {
-                        location: Location::Known(token.span.clone()),
-                        message: None,
+                        location: match error.input.tokens.first() {
+                            Some(token) => Location::Known(token.span.clone()),
+                            None => Location::Eof,
+                        },
+                        message: Some("wrap the struct literal in parentheses".to_string()),
                     }],
                 )
             }
+            ::nom::Err::Error(error) | ::nom::Err::Failure(error) => {
+                let token = error.input.tokens.first();
+                let found = match token {
+                    Some(token) => format!("`{}`", token.content),
+                    None => "the end of input".to_string(),
+                };
+                let message = match error.expected.split_last() {
+                    Some((last, [])) => format!("Expected {}, found {}.", last.describe(), found),
+                    Some((last, rest)) => format!(
+                        "Expected {} or {}, found {}.",
+                        rest.iter()
+                            .map(|expected| expected.describe())
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        last.describe(),
+                        found
+                    ),
+                    None => match token {
+                        Some(token) => format!("Unexpected token: {}", token.content),
+                        None => "Unexpected end of input.".to_string(),
+                    },
+                };
+                let label = match token {
+                    Some(token) => Label {
+                        location: Location::Known(token.span.clone()),
+                        message: match &token.kind {
+                            TokenKind::IdentifierIdentifier(name) => suggest_keyword(name)
+                                .map(|keyword| format!("Did you mean `{}`?", keyword)),
+                            _ => None,
+                        },
+                    },
+                    None => Label {
+                        location: Location::Eof,
+                        message: None,
+                    },
+                };
+                (message, vec![label])
+            }
         };
         Error {
             code: ErrorCode::SyntaxError,
@@ -0,0 +1,317 @@
+//! The intrinsics, the functions the compiler lowers by itself which are
+//! called as `@name`, so the standard library can be written in Rano
+//! without the compiler growing a builtin for every primitive it needs.
+//! Unlike a builtin, an intrinsic is in no scope and can't be shadowed, and
+//! the checker knows its signature.
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{Type, TypeId, Types};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IntrinsicKind {
+    /// `@size_of<T>()`, the number of bytes a value of `T` takes in memory.
+    SizeOf,
+    /// `@align_of<T>()`, the alignment of a value of `T` in memory.
+    AlignOf,
+    /// `@address_of<T>(local)`, the address of a `let` local of `T`, which
+    /// lives in the frame of its function on the shadow stack rather than
+    /// in a WASM local for its whole life, so writing through the address
+    /// changes it. The address is only valid until the function returns.
+    AddressOf,
+    /// `@trap()`, which stops the program right away.
+    Trap,
+    /// `@memory_size()`, the size of the memory in pages of 64KiB.
+    MemorySize,
+    /// `@memory_grow(pages)`, which grows the memory and gives its old size
+    /// in pages, or `-1` if it can't grow.
+    MemoryGrow,
+    /// `@malloc(size)`, a block of at least `size` bytes from the allocator
+    /// of the runtime, or `0` if the memory can't grow.
+    Malloc,
+    /// `@memory_copy(destination, source, length)`, which copies `length`
+    /// bytes, the two ranges of which may overlap. Like `@memory_fill`, it
+    /// needs the `bulk-memory` proposal.
+    MemoryCopy,
+    /// `@memory_fill(destination, byte, length)`, which sets `length` bytes
+    /// to `byte`.
+    MemoryFill,
+    /// `@free(pointer)`, which gives a block back to the allocator.
+    Free,
+    /// `@realloc(pointer, size)`, the block resized to at least `size`
+    /// bytes, which may have moved.
+    Realloc,
+    /// `@string_len(string)`, the length of a string in bytes.
+    StringLen,
+    /// `@string_slice(string, start, end)`, a copy of the bytes of a string
+    /// from `start` up to `end`, which traps if they aren't in it.
+    StringSlice,
+    /// `@f64_to_string(value)`, the shortest decimal which reads back as
+    /// the float, like `0.1`, `1e16` or `NaN`.
+    F64ToString,
+    /// `@f32_to_string(value)`, the same for an `f32`.
+    F32ToString,
+    /// `@string_to_f64(string)`, the float nearest to the decimal in a
+    /// string, or NaN if it doesn't hold one.
+    StringToF64,
+    /// `@string_to_f32(string)`, the same for an `f32`.
+    StringToF32,
+    /// `@print(string)`, which writes a string to the standard output. Like
+    /// the others writing to the standard streams, it needs the `wasi`
+    /// target.
+    Print,
+    /// `@println(string)`, which writes a string and a newline to the
+    /// standard output.
+    Println,
+    /// `@eprint(string)`, which writes a string to the standard error.
+    Eprint,
+    /// `@eprintln(string)`, which writes a string and a newline to the
+    /// standard error.
+    Eprintln,
+    /// `@atomic_load(address)`, the word at `address` read at once. Like the
+    /// other atomics, it needs the `threads` target and can only be called
+    /// in a `#[thread_safe]` function, and traps if `address` isn't aligned
+    /// to 4 bytes.
+    AtomicLoad,
+    /// `@atomic_store(address, value)`, which writes the word at once.
+    AtomicStore,
+    /// `@atomic_add(address, value)`, which adds to the word at once and
+    /// gives what it was before, like the others changing it do.
+    AtomicAdd,
+    /// `@atomic_sub(address, value)`, which subtracts from the word.
+    AtomicSub,
+    /// `@atomic_and(address, value)`, which ands the bits of the word.
+    AtomicAnd,
+    /// `@atomic_or(address, value)`, which ors the bits of the word.
+    AtomicOr,
+    /// `@atomic_xor(address, value)`, which xors the bits of the word.
+    AtomicXor,
+    /// `@atomic_exchange(address, value)`, which replaces the word.
+    AtomicExchange,
+    /// `@atomic_compare_exchange(address, expected, replacement)`, which
+    /// replaces the word only if it is `expected`.
+    AtomicCompareExchange,
+    /// `@atomic_wait(address, expected, timeout)`, which blocks the thread
+    /// while the word is `expected`, until it is notified or `timeout`
+    /// nanoseconds passed, if it isn't negative. Gives `0` if it was
+    /// notified, `1` if the word wasn't `expected` and `2` on the timeout.
+    AtomicWait,
+    /// `@atomic_notify(address, count)`, which wakes up at most `count` of
+    /// the threads waiting on the word, giving how many it woke up.
+    AtomicNotify,
+    /// `@atomic_fence()`, which orders the accesses to the memory around
+    /// it.
+    AtomicFence,
+    /// `@splat<V>(lane)`, the vector of the type `V` with every lane set to
+    /// `lane`. Like the other SIMD intrinsics, it needs the `simd` target.
+    Splat,
+    /// `@extract_lane<V>(vector, index)`, the lane `index` of a vector,
+    /// which must be an integer literal.
+    ExtractLane,
+    /// `@replace_lane<V>(vector, index, lane)`, the vector with the lane
+    /// `index` replaced by `lane`.
+    ReplaceLane,
+    /// `@shuffle<V>(a, b, (i, j, ..))`, the vector of the lanes of `a`
+    /// followed by those of `b` at the indices in the tuple, one for each
+    /// lane, which must all be integer literals.
+    Shuffle,
+}
+
+impl IntrinsicKind {
+    /// The intrinsic called `@name`.
+    pub fn from_name(name: &str) -> Option<IntrinsicKind> {
+        Some(match name {
+            "size_of" => IntrinsicKind::SizeOf,
+            "align_of" => IntrinsicKind::AlignOf,
+            "address_of" => IntrinsicKind::AddressOf,
+            "trap" => IntrinsicKind::Trap,
+            "memory_size" => IntrinsicKind::MemorySize,
+            "memory_grow" => IntrinsicKind::MemoryGrow,
+            "memory_copy" => IntrinsicKind::MemoryCopy,
+            "memory_fill" => IntrinsicKind::MemoryFill,
+            "malloc" => IntrinsicKind::Malloc,
+            "free" => IntrinsicKind::Free,
+            "realloc" => IntrinsicKind::Realloc,
+            "string_len" => IntrinsicKind::StringLen,
+            "string_slice" => IntrinsicKind::StringSlice,
+            "f64_to_string" => IntrinsicKind::F64ToString,
+            "f32_to_string" => IntrinsicKind::F32ToString,
+            "string_to_f64" => IntrinsicKind::StringToF64,
+            "string_to_f32" => IntrinsicKind::StringToF32,
+            "print" => IntrinsicKind::Print,
+            "println" => IntrinsicKind::Println,
+            "eprint" => IntrinsicKind::Eprint,
+            "eprintln" => IntrinsicKind::Eprintln,
+            "atomic_load" => IntrinsicKind::AtomicLoad,
+            "atomic_store" => IntrinsicKind::AtomicStore,
+            "atomic_add" => IntrinsicKind::AtomicAdd,
+            "atomic_sub" => IntrinsicKind::AtomicSub,
+            "atomic_and" => IntrinsicKind::AtomicAnd,
+            "atomic_or" => IntrinsicKind::AtomicOr,
+            "atomic_xor" => IntrinsicKind::AtomicXor,
+            "atomic_exchange" => IntrinsicKind::AtomicExchange,
+            "atomic_compare_exchange" => IntrinsicKind::AtomicCompareExchange,
+            "atomic_wait" => IntrinsicKind::AtomicWait,
+            "atomic_notify" => IntrinsicKind::AtomicNotify,
+            "atomic_fence" => IntrinsicKind::AtomicFence,
+            "splat" => IntrinsicKind::Splat,
+            "extract_lane" => IntrinsicKind::ExtractLane,
+            "replace_lane" => IntrinsicKind::ReplaceLane,
+            "shuffle" => IntrinsicKind::Shuffle,
+            _ => return None,
+        })
+    }
+
+    /// How many type arguments the intrinsic takes.
+    pub fn type_parameters(self) -> usize {
+        match self {
+            IntrinsicKind::SizeOf | IntrinsicKind::AlignOf | IntrinsicKind::AddressOf => 1,
+            kind if kind.takes_vector() => 1,
+            _ => 0,
+        }
+    }
+
+    /// The type of the intrinsic as a function, given the type arguments
+    /// `type_arguments`, which there are as many of as it takes, and which
+    /// is a vector for a SIMD intrinsic.
+    pub fn signature(self, type_arguments: &[TypeId], types: &mut Types) -> TypeId {
+        let vector = type_arguments.first().copied().unwrap_or(TypeId::NEVER);
+        let (lane, lanes) = vector.lanes().unwrap_or((TypeId::NEVER, 0));
+        let (parameters, return_type) = match self {
+            IntrinsicKind::SizeOf | IntrinsicKind::AlignOf | IntrinsicKind::MemorySize => {
+                (vec![], TypeId::I32)
+            }
+            IntrinsicKind::AddressOf => (vec![type_arguments[0]], TypeId::I32),
+            IntrinsicKind::Trap => (vec![], TypeId::NEVER),
+            IntrinsicKind::MemoryGrow | IntrinsicKind::Malloc => (vec![TypeId::I32], TypeId::I32),
+            IntrinsicKind::MemoryCopy | IntrinsicKind::MemoryFill => {
+                (vec![TypeId::I32; 3], TypeId::UNIT)
+            }
+            IntrinsicKind::Free => (vec![TypeId::I32], TypeId::UNIT),
+            IntrinsicKind::Realloc => (vec![TypeId::I32, TypeId::I32], TypeId::I32),
+            IntrinsicKind::StringLen => (vec![TypeId::STRING], TypeId::I32),
+            IntrinsicKind::StringSlice => (
+                vec![TypeId::STRING, TypeId::I32, TypeId::I32],
+                TypeId::STRING,
+            ),
+            IntrinsicKind::F64ToString => (vec![TypeId::F64], TypeId::STRING),
+            IntrinsicKind::F32ToString => (vec![TypeId::F32], TypeId::STRING),
+            IntrinsicKind::StringToF64 => (vec![TypeId::STRING], TypeId::F64),
+            IntrinsicKind::StringToF32 => (vec![TypeId::STRING], TypeId::F32),
+            IntrinsicKind::Print
+            | IntrinsicKind::Println
+            | IntrinsicKind::Eprint
+            | IntrinsicKind::Eprintln => (vec![TypeId::STRING], TypeId::UNIT),
+            IntrinsicKind::AtomicLoad => (vec![TypeId::I32], TypeId::I32),
+            IntrinsicKind::AtomicStore => (vec![TypeId::I32, TypeId::I32], TypeId::UNIT),
+            IntrinsicKind::AtomicAdd
+            | IntrinsicKind::AtomicSub
+            | IntrinsicKind::AtomicAnd
+            | IntrinsicKind::AtomicOr
+            | IntrinsicKind::AtomicXor
+            | IntrinsicKind::AtomicExchange
+            | IntrinsicKind::AtomicNotify => (vec![TypeId::I32, TypeId::I32], TypeId::I32),
+            IntrinsicKind::AtomicCompareExchange => (vec![TypeId::I32; 3], TypeId::I32),
+            IntrinsicKind::AtomicWait => (vec![TypeId::I32, TypeId::I32, TypeId::I64], TypeId::I32),
+            IntrinsicKind::AtomicFence => (vec![], TypeId::UNIT),
+            IntrinsicKind::Splat => (vec![lane], vector),
+            IntrinsicKind::ExtractLane => (vec![vector, TypeId::I32], lane),
+            IntrinsicKind::ReplaceLane => (vec![vector, TypeId::I32, lane], vector),
+            IntrinsicKind::Shuffle => {
+                let indices = types.intern(Type::Tuple(vec![TypeId::I32; lanes as usize]));
+                (vec![vector, vector, indices], vector)
+            }
+        };
+        types.intern(Type::Function {
+            parameters,
+            return_type,
+        })
+    }
+
+    /// The target or the proposal the intrinsic can only be compiled for,
+    /// as `TargetOptions` names it.
+    pub fn required_target(self) -> Option<&'static str> {
+        match self {
+            IntrinsicKind::MemoryCopy | IntrinsicKind::MemoryFill => Some("bulk-memory"),
+            IntrinsicKind::Print
+            | IntrinsicKind::Println
+            | IntrinsicKind::Eprint
+            | IntrinsicKind::Eprintln => Some("wasi"),
+            kind if kind.is_atomic() => Some("threads"),
+            kind if kind.takes_vector() => Some("simd"),
+            _ => None,
+        }
+    }
+
+    /// Whether the intrinsic accesses the memory directly, which is only
+    /// allowed in an `unsafe` block.
+    pub fn is_unsafe(self) -> bool {
+        !matches!(
+            self,
+            IntrinsicKind::SizeOf
+                | IntrinsicKind::AlignOf
+                | IntrinsicKind::AddressOf
+                | IntrinsicKind::Trap
+                | IntrinsicKind::StringLen
+                | IntrinsicKind::StringSlice
+                | IntrinsicKind::F64ToString
+                | IntrinsicKind::F32ToString
+                | IntrinsicKind::StringToF64
+                | IntrinsicKind::StringToF32
+                | IntrinsicKind::Print
+                | IntrinsicKind::Println
+                | IntrinsicKind::Eprint
+                | IntrinsicKind::Eprintln
+                | IntrinsicKind::AtomicFence
+                | IntrinsicKind::Splat
+                | IntrinsicKind::ExtractLane
+                | IntrinsicKind::ReplaceLane
+                | IntrinsicKind::Shuffle
+        )
+    }
+
+    /// Whether the intrinsic is one of the SIMD ones, the type argument of
+    /// which is the vector they operate on.
+    pub fn takes_vector(self) -> bool {
+        matches!(
+            self,
+            IntrinsicKind::Splat
+                | IntrinsicKind::ExtractLane
+                | IntrinsicKind::ReplaceLane
+                | IntrinsicKind::Shuffle
+        )
+    }
+
+    /// Whether the intrinsic is one of the atomics, which are only allowed
+    /// in a `#[thread_safe]` function.
+    pub fn is_atomic(self) -> bool {
+        matches!(
+            self,
+            IntrinsicKind::AtomicLoad
+                | IntrinsicKind::AtomicStore
+                | IntrinsicKind::AtomicAdd
+                | IntrinsicKind::AtomicSub
+                | IntrinsicKind::AtomicAnd
+                | IntrinsicKind::AtomicOr
+                | IntrinsicKind::AtomicXor
+                | IntrinsicKind::AtomicExchange
+                | IntrinsicKind::AtomicCompareExchange
+                | IntrinsicKind::AtomicWait
+                | IntrinsicKind::AtomicNotify
+                | IntrinsicKind::AtomicFence
+        )
+    }
+
+    /// Whether a `#[thread_safe]` function can call the intrinsic, which it
+    /// can't if it uses the allocator or the shadow stack, neither of which
+    /// can be shared by threads running at once.
+    pub fn is_thread_safe(self) -> bool {
+        !matches!(
+            self,
+            IntrinsicKind::AddressOf
+                | IntrinsicKind::Malloc
+                | IntrinsicKind::Free
+                | IntrinsicKind::Realloc
+        )
+    }
+}
@@ -0,0 +1,74 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::Error;
+
+pub const MAGIC: [u8; 4] = *b"RANO";
+/// Bumped whenever the layout of a serialized intermediate changes, so stale
+/// caches are rejected instead of being misread.
+pub const FORMAT_VERSION: u16 = 3;
+
+const HEADER_LEN: usize = 7;
+
+/// Which intermediate a serialized blob holds.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[repr(u8)]
+pub enum IrKind {
+    Ast = 0,
+    Typed = 2,
+    Meta = 3,
+}
+
+impl IrKind {
+    fn from_u8(kind: u8) -> Option<IrKind> {
+        match kind {
+            0 => Some(IrKind::Ast),
+            2 => Some(IrKind::Typed),
+            3 => Some(IrKind::Meta),
+            _ => None,
+        }
+    }
+}
+
+/// Serializes `value` behind a header of the magic bytes, the format version
+/// and the kind of the intermediate.
+pub fn write_ir<T: Serialize>(kind: IrKind, value: &T) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.push(kind as u8);
+    bincode::serialize_into(&mut bytes, value).map_err(|error| {
+        Error::invalid_ir(format!("Failed to serialize the {:?}: {}", kind, error))
+    })?;
+    Ok(bytes)
+}
+
+pub fn read_ir<T: DeserializeOwned>(kind: IrKind, bytes: &[u8]) -> Result<T, Error> {
+    if bytes.len() < HEADER_LEN || bytes[0..4] != MAGIC {
+        return Err(Error::invalid_ir("Not a serialized Rano intermediate."));
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != FORMAT_VERSION {
+        return Err(Error::invalid_ir(format!(
+            "Serialized with the format version {} but {} is required.",
+            version, FORMAT_VERSION
+        )));
+    }
+    match IrKind::from_u8(bytes[6]) {
+        Some(found) if found == kind => {}
+        Some(found) => {
+            return Err(Error::invalid_ir(format!(
+                "Expected {:?} but found {:?}.",
+                kind, found
+            )))
+        }
+        None => {
+            return Err(Error::invalid_ir(format!(
+                "Unknown intermediate kind {}.",
+                bytes[6]
+            )))
+        }
+    }
+    bincode::deserialize(&bytes[HEADER_LEN..]).map_err(|error| {
+        Error::invalid_ir(format!("Failed to deserialize the {:?}: {}", kind, error))
+    })
+}
@@ -1,4 +1,19 @@
+pub mod arena;
 pub mod ast;
 mod error;
+mod intrinsic;
+pub mod ir;
+mod json;
+mod node_id;
+mod source_map;
+mod ty;
+pub mod visit;
+pub mod visit_mut;
 
-pub use error::{Error, Label, Location, Result};
+pub use error::{Error, ErrorCode, Label, Location, Result};
+pub use intrinsic::IntrinsicKind;
+pub use json::to_json;
+pub(crate) use node_id::NodeIdGenerator;
+pub use node_id::{NodeId, NodeMap};
+pub use source_map::{FileId, SourceFile, SourceMap};
+pub use ty::{Type, TypeId, Types};
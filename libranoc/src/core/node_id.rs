@@ -0,0 +1,85 @@
+use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+/// Identifies a node of the AST, unique among the nodes parsed from the same
+/// source. The declarations, blocks and the expressions with a struct of
+/// their own carry one, and the other nodes are annotated through the
+/// nearest one of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct NodeId(pub u32);
+
+impl NodeId {
+    /// The id of the nodes which weren't parsed, like the ones a pass built
+    /// itself.
+    pub const DUMMY: NodeId = NodeId(u32::MAX);
+}
+
+/// Hands out the `NodeId`s while parsing. It is shared by every copy of the
+/// parser input, so the ids stay unique even though the parser backtracks.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct NodeIdGenerator(Rc<Cell<u32>>);
+
+impl NodeIdGenerator {
+    pub(crate) fn next(&self) -> NodeId {
+        let id = self.0.get();
+        self.0.set(id + 1);
+        NodeId(id)
+    }
+}
+
+/// What a pass found out about the nodes, kept beside the AST instead of in
+/// it, like the type of each expression or what each name refers to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeMap<T> {
+    entries: HashMap<NodeId, T>,
+}
+
+impl<T> Default for NodeMap<T> {
+    fn default() -> Self {
+        NodeMap {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<T> NodeMap<T> {
+    pub fn new() -> Self {
+        NodeMap::default()
+    }
+
+    /// Annotates `id` with `value`, returning what it was annotated with
+    /// before.
+    pub fn insert(&mut self, id: NodeId, value: T) -> Option<T> {
+        self.entries.insert(id, value)
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.entries.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut T> {
+        self.entries.get_mut(&id)
+    }
+
+    pub fn remove(&mut self, id: NodeId) -> Option<T> {
+        self.entries.remove(&id)
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.entries.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The annotations in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &T)> {
+        self.entries.iter().map(|(id, value)| (*id, value))
+    }
+}
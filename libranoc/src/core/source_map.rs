@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use crate::syntax::{tokenize_with, Span, Token, TokenizeOptions};
+
+/// Identifies a file of a `SourceMap`, which each `Span` tells it points
+/// into. Source tokenized without saying which file it is counts as the
+/// first file, so a single file compiles the same with or without a map.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct FileId(pub u32);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    /// What the file is called in diagnostics, usually its path.
+    pub name: String,
+    pub src: String,
+}
+
+/// The files of a compilation, numbered in the order they are added.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, src: impl Into<String>) -> FileId {
+        let file = FileId(self.files.len() as u32);
+        self.files.push(SourceFile {
+            name: name.into(),
+            src: src.into(),
+        });
+        file
+    }
+
+    pub fn get(&self, file: FileId) -> Option<&SourceFile> {
+        self.files.get(file.0 as usize)
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(index, file)| (FileId(index as u32), file))
+    }
+
+    /// The file `span` points into.
+    pub fn file_of(&self, span: &Span) -> Option<&SourceFile> {
+        self.get(span.file)
+    }
+
+    /// The text `span` covers in its file.
+    pub fn text(&self, span: &Span) -> Option<&str> {
+        self.file_of(span)?.src.get(span.range.clone())
+    }
+
+    /// The tokens of `file`, whose spans point into it.
+    pub fn tokenize(&self, file: FileId, options: TokenizeOptions) -> Vec<Token> {
+        match self.get(file) {
+            Some(SourceFile { src, .. }) => tokenize_with(src, TokenizeOptions { file, ..options }),
+            None => Vec::new(),
+        }
+    }
+}
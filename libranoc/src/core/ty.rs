@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    ast::{self, TypeParameter},
+    Error,
+};
+
+/// An interned `Type`, which two types are equal by. The primitives have
+/// the same id in every `Types`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TypeId(pub u32);
+
+impl TypeId {
+    pub const I32: TypeId = TypeId(0);
+    pub const I64: TypeId = TypeId(1);
+    pub const F32: TypeId = TypeId(2);
+    pub const F64: TypeId = TypeId(3);
+    pub const BOOL: TypeId = TypeId(4);
+    pub const STRING: TypeId = TypeId(5);
+    pub const NEVER: TypeId = TypeId(6);
+    /// `()`, the empty tuple.
+    pub const UNIT: TypeId = TypeId(7);
+    pub const I32X4: TypeId = TypeId(8);
+    pub const I64X2: TypeId = TypeId(9);
+    pub const F32X4: TypeId = TypeId(10);
+    pub const F64X2: TypeId = TypeId(11);
+
+    /// The type of the lanes of the vector `self`, with how many it has, or
+    /// `None` if it isn't one.
+    pub fn lanes(self) -> Option<(TypeId, u32)> {
+        match self {
+            TypeId::I32X4 => Some((TypeId::I32, 4)),
+            TypeId::I64X2 => Some((TypeId::I64, 2)),
+            TypeId::F32X4 => Some((TypeId::F32, 4)),
+            TypeId::F64X2 => Some((TypeId::F64, 2)),
+            _ => None,
+        }
+    }
+}
+
+/// A type as the checker and codegen see it, which is the same however it
+/// is written. The types inside of it are interned in the `Types` it is in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Type {
+    I32,
+    I64,
+    F32,
+    F64,
+    Bool,
+    String,
+    /// `!`, the type of expressions which never produce a value.
+    Never,
+    /// `(A, B)`, with `()` for the unit.
+    Tuple(Vec<TypeId>),
+    /// `f32x4` and the like, the 128 bits of the SIMD proposal as lanes of
+    /// the number type in it, as many as fit.
+    Vector(TypeId),
+    /// `Array<T>`
+    Array(TypeId),
+    /// `T?`
+    Optional(TypeId),
+    /// `(A, B) -> R`
+    Function {
+        parameters: Vec<TypeId>,
+        return_type: TypeId,
+    },
+    /// A type declared in the program, by the path to it, with the types
+    /// given to its generic parameters.
+    Adt {
+        name: String,
+        arguments: Vec<TypeId>,
+    },
+    /// `dyn Trait`, a value of any type implementing the trait, by the name
+    /// of the trait.
+    Dynamic(String),
+}
+
+impl Type {
+    /// The primitive called `name`, as it is written in a type.
+    pub fn primitive(name: &str) -> Option<TypeId> {
+        Some(match name {
+            "i32" => TypeId::I32,
+            "i64" => TypeId::I64,
+            "f32" => TypeId::F32,
+            "f64" => TypeId::F64,
+            "bool" => TypeId::BOOL,
+            "string" => TypeId::STRING,
+            "i32x4" => TypeId::I32X4,
+            "i64x2" => TypeId::I64X2,
+            "f32x4" => TypeId::F32X4,
+            "f64x2" => TypeId::F64X2,
+            _ => return None,
+        })
+    }
+}
+
+/// The interner of the types of a compilation, giving every type a single
+/// `TypeId` however often it is interned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Types {
+    types: Vec<Type>,
+    ids: HashMap<Type, TypeId>,
+}
+
+impl Default for Types {
+    fn default() -> Self {
+        let mut types = Types {
+            types: Vec::new(),
+            ids: HashMap::new(),
+        };
+        // in the order of the constants of `TypeId`.
+        for ty in [
+            Type::I32,
+            Type::I64,
+            Type::F32,
+            Type::F64,
+            Type::Bool,
+            Type::String,
+            Type::Never,
+            Type::Tuple(Vec::new()),
+            Type::Vector(TypeId::I32),
+            Type::Vector(TypeId::I64),
+            Type::Vector(TypeId::F32),
+            Type::Vector(TypeId::F64),
+        ]
+        .iter()
+        {
+            types.intern(ty.clone());
+        }
+        types
+    }
+}
+
+impl Types {
+    pub fn new() -> Self {
+        Types::default()
+    }
+
+    pub fn intern(&mut self, ty: Type) -> TypeId {
+        if let Some(id) = self.ids.get(&ty) {
+            return *id;
+        }
+        let id = TypeId(self.types.len() as u32);
+        self.types.push(ty.clone());
+        self.ids.insert(ty, id);
+        id
+    }
+
+    pub fn get(&self, id: TypeId) -> &Type {
+        &self.types[id.0 as usize]
+    }
+
+    /// The type `ty` is written as. `impl Trait` has no representation yet.
+    pub fn lower(&mut self, ty: &ast::Type) -> Result<TypeId, Error> {
+        let lowered = match ty {
+            ast::Type::Basic {
+                base,
+                type_parameters,
+                ..
+            } => {
+                let name = base.to_string();
+                match type_parameters.as_slice() {
+                    [] => match Type::primitive(&name) {
+                        Some(id) => return Ok(id),
+                        None => Type::Adt {
+                            name,
+                            arguments: Vec::new(),
+                        },
+                    },
+                    [TypeParameter::Specific(element)] if name == "Array" => {
+                        Type::Array(self.lower(element)?)
+                    }
+                    parameters => Type::Adt {
+                        name,
+                        arguments: parameters
+                            .iter()
+                            .map(|parameter| match parameter {
+                                TypeParameter::Specific(argument) => self.lower(argument),
+                                _ => Err(Error::unimplemented(ty)),
+                            })
+                            .collect::<Result<_, _>>()?,
+                    },
+                }
+            }
+            ast::Type::Tuple(types, _) => Type::Tuple(self.lower_all(types)?),
+            ast::Type::Impl(..) => return Err(Error::unimplemented(ty)),
+            ast::Type::Dyn(_, inner) => match inner.as_ref() {
+                ast::Type::Basic {
+                    base,
+                    type_parameters,
+                    ..
+                } if type_parameters.is_empty() => Type::Dynamic(base.to_string()),
+                _ => return Err(Error::unimplemented(ty)),
+            },
+            ast::Type::Nullable(ty, _) => Type::Optional(self.lower(ty)?),
+            ast::Type::Function {
+                parameters_type,
+                return_type,
+                ..
+            } => Type::Function {
+                parameters: self.lower_all(parameters_type)?,
+                return_type: self.lower(return_type)?,
+            },
+            ast::Type::Never(_) => return Ok(TypeId::NEVER),
+        };
+        Ok(self.intern(lowered))
+    }
+
+    fn lower_all(&mut self, types: &[ast::Type]) -> Result<Vec<TypeId>, Error> {
+        types.iter().map(|ty| self.lower(ty)).collect()
+    }
+
+    /// Whether a value of `id` is copied where it is used, rather than moved.
+    /// Only the types which own memory on the heap are moved: strings,
+    /// arrays, types declared in the program, trait objects and anything
+    /// holding one. A generic parameter may stand for any of those, so it is
+    /// moved too.
+    pub fn is_copy(&self, id: TypeId) -> bool {
+        match self.get(id) {
+            Type::I32 | Type::I64 | Type::F32 | Type::F64 | Type::Bool | Type::Never => true,
+            Type::Vector(_) | Type::Function { .. } => true,
+            Type::Tuple(types) => types.iter().all(|ty| self.is_copy(*ty)),
+            Type::Optional(ty) => self.is_copy(*ty),
+            Type::String | Type::Array(_) | Type::Adt { .. } | Type::Dynamic(_) => false,
+        }
+    }
+
+    /// `id` written the way it is in the source.
+    pub fn display(&self, id: TypeId) -> String {
+        let list = |types: &[TypeId]| {
+            types
+                .iter()
+                .map(|ty| self.display(*ty))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self.get(id) {
+            Type::I32 => "i32".to_string(),
+            Type::I64 => "i64".to_string(),
+            Type::F32 => "f32".to_string(),
+            Type::F64 => "f64".to_string(),
+            Type::Bool => "bool".to_string(),
+            Type::String => "string".to_string(),
+            Type::Never => "!".to_string(),
+            Type::Tuple(types) if types.len() == 1 => format!("({},)", self.display(types[0])),
+            Type::Tuple(types) => format!("({})", list(types)),
+            Type::Vector(lane) => {
+                let lanes = id.lanes().map_or(0, |(_, lanes)| lanes);
+                format!("{}x{}", self.display(*lane), lanes)
+            }
+            Type::Array(element) => format!("Array<{}>", self.display(*element)),
+            Type::Optional(ty) => format!("{}?", self.display(*ty)),
+            Type::Function {
+                parameters,
+                return_type,
+            } => format!("({}) -> {}", list(parameters), self.display(*return_type)),
+            Type::Adt { name, arguments } if arguments.is_empty() => name.clone(),
+            Type::Adt { name, arguments } => format!("{}<{}>", name, list(arguments)),
+            Type::Dynamic(trait_name) => format!("dyn {}", trait_name),
+        }
+    }
+}
@@ -0,0 +1,428 @@
+//! Walking the AST by reference. Each method of `Visit` visits one kind of
+//! node, and by default goes on with the nodes inside of it through the
+//! `walk_*` function of the same name. A pass overrides the methods for the
+//! nodes it is interested in, and calls the `walk_*` function from there
+//! when it wants to go deeper.
+
+use crate::core::ast::*;
+
+pub trait Visit<'ast> {
+    fn visit_module(&mut self, module: &'ast Module) {
+        walk_module(self, module)
+    }
+
+    fn visit_node(&mut self, node: &'ast Node) {
+        walk_node(self, node)
+    }
+
+    fn visit_directive(&mut self, _directive: &'ast Directive) {}
+
+    fn visit_statement(&mut self, statement: &'ast Statement) {
+        walk_statement(self, statement)
+    }
+
+    fn visit_let(&mut self, r#let: &'ast Let) {
+        walk_let(self, r#let)
+    }
+
+    fn visit_declaration(&mut self, declaration: &'ast Declaration) {
+        walk_declaration(self, declaration)
+    }
+
+    fn visit_function_declaration(&mut self, function_declaration: &'ast FunctionDeclaration) {
+        walk_function_declaration(self, function_declaration)
+    }
+
+    fn visit_generic_parameter(&mut self, generic_parameter: &'ast GenericParameter) {
+        walk_generic_parameter(self, generic_parameter)
+    }
+
+    fn visit_use_declaration(&mut self, use_declaration: &'ast UseDeclaration) {
+        walk_use_declaration(self, use_declaration)
+    }
+
+    fn visit_use_tree(&mut self, use_tree: &'ast UseTree) {
+        walk_use_tree(self, use_tree)
+    }
+
+    fn visit_operator_declaration(&mut self, _operator_declaration: &'ast OperatorDeclaration) {}
+
+    fn visit_module_declaration(&mut self, module_declaration: &'ast ModuleDeclaration) {
+        walk_module_declaration(self, module_declaration)
+    }
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        walk_block(self, block)
+    }
+
+    fn visit_expression(&mut self, expression: &'ast Expression) {
+        walk_expression(self, expression)
+    }
+
+    fn visit_match(&mut self, r#match: &'ast Match) {
+        walk_match(self, r#match)
+    }
+
+    fn visit_match_arm(&mut self, match_arm: &'ast MatchArm) {
+        walk_match_arm(self, match_arm)
+    }
+
+    fn visit_closure(&mut self, closure: &'ast Closure) {
+        walk_closure(self, closure)
+    }
+
+    fn visit_unsafe(&mut self, r#unsafe: &'ast Unsafe) {
+        walk_unsafe(self, r#unsafe)
+    }
+
+    fn visit_init(&mut self, init: &'ast Init) {
+        walk_init(self, init)
+    }
+
+    fn visit_literal(&mut self, _literal: &'ast Literal) {}
+
+    fn visit_path(&mut self, _path: &'ast Path) {}
+
+    fn visit_name(&mut self, _name: &'ast Name) {}
+
+    fn visit_todo(&mut self, _todo: &'ast Todo) {}
+
+    fn visit_interpolation_part(&mut self, interpolation_part: &'ast InterpolationPart) {
+        walk_interpolation_part(self, interpolation_part)
+    }
+
+    fn visit_operator(&mut self, operator: &'ast Operator) {
+        walk_operator(self, operator)
+    }
+
+    fn visit_prefix_operator(&mut self, prefix_operator: &'ast PrefixOperator) {
+        walk_prefix_operator(self, prefix_operator)
+    }
+
+    fn visit_infix_operator(&mut self, infix_operator: &'ast InfixOperator) {
+        walk_infix_operator(self, infix_operator)
+    }
+
+    fn visit_postfix_operator(&mut self, postfix_operator: &'ast PostfixOperator) {
+        walk_postfix_operator(self, postfix_operator)
+    }
+
+    fn visit_if(&mut self, r#if: &'ast If) {
+        walk_if(self, r#if)
+    }
+
+    fn visit_pattern(&mut self, pattern: &'ast Pattern) {
+        walk_pattern(self, pattern)
+    }
+
+    fn visit_type(&mut self, ty: &'ast Type) {
+        walk_type(self, ty)
+    }
+
+    fn visit_type_parameter(&mut self, type_parameter: &'ast TypeParameter) {
+        walk_type_parameter(self, type_parameter)
+    }
+}
+
+pub fn walk_module<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, module: &'ast Module) {
+    for node in &module.nodes {
+        visitor.visit_node(node);
+    }
+}
+
+pub fn walk_node<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, node: &'ast Node) {
+    match node {
+        Node::Directive(directive) => visitor.visit_directive(directive),
+        Node::Statement(statement) => visitor.visit_statement(statement),
+    }
+}
+
+pub fn walk_statement<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, statement: &'ast Statement) {
+    match statement {
+        Statement::Declaration(declaration) => visitor.visit_declaration(declaration),
+        Statement::Let(r#let) => visitor.visit_let(r#let),
+        Statement::Expression(expression) => visitor.visit_expression(expression),
+    }
+}
+
+pub fn walk_let<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, r#let: &'ast Let) {
+    visitor.visit_pattern(&r#let.pattern);
+    if let Some(ty) = &r#let.ty {
+        visitor.visit_type(ty);
+    }
+    if let Some(value) = &r#let.value {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_declaration<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    declaration: &'ast Declaration,
+) {
+    match declaration {
+        Declaration::FunctionDeclaration(function_declaration) => {
+            visitor.visit_function_declaration(function_declaration)
+        }
+        Declaration::UseDeclaration(use_declaration) => {
+            visitor.visit_use_declaration(use_declaration)
+        }
+        Declaration::OperatorDeclaration(operator_declaration) => {
+            visitor.visit_operator_declaration(operator_declaration)
+        }
+        Declaration::ModuleDeclaration(module_declaration) => {
+            visitor.visit_module_declaration(module_declaration)
+        }
+    }
+}
+
+pub fn walk_function_declaration<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    function_declaration: &'ast FunctionDeclaration,
+) {
+    for generic_parameter in &function_declaration.generic_parameters {
+        visitor.visit_generic_parameter(generic_parameter);
+    }
+    for (pattern, ty) in &function_declaration.parameters {
+        visitor.visit_pattern(pattern);
+        visitor.visit_type(ty);
+    }
+    visitor.visit_type(&function_declaration.return_type);
+    if let Some(body) = &function_declaration.body {
+        visitor.visit_block(body);
+    }
+}
+
+pub fn walk_generic_parameter<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    generic_parameter: &'ast GenericParameter,
+) {
+    for bound in &generic_parameter.bounds {
+        visitor.visit_type(bound);
+    }
+}
+
+pub fn walk_use_declaration<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    use_declaration: &'ast UseDeclaration,
+) {
+    visitor.visit_use_tree(&use_declaration.tree);
+}
+
+pub fn walk_use_tree<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, use_tree: &'ast UseTree) {
+    visitor.visit_path(&use_tree.prefix);
+    if let UseTreeKind::Nested(trees) = &use_tree.kind {
+        for tree in trees {
+            visitor.visit_use_tree(tree);
+        }
+    }
+}
+
+pub fn walk_module_declaration<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    module_declaration: &'ast ModuleDeclaration,
+) {
+    if let ModuleBody::Inline { module, .. } = &module_declaration.body {
+        visitor.visit_module(module);
+    }
+}
+
+pub fn walk_block<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, block: &'ast Block) {
+    for statement in &block.body {
+        visitor.visit_statement(statement);
+    }
+    if let Some(expression) = &block.last_expression {
+        visitor.visit_expression(expression);
+    }
+}
+
+pub fn walk_expression<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    expression: &'ast Expression,
+) {
+    match expression {
+        Expression::Match(r#match) => visitor.visit_match(r#match),
+        Expression::Closure(closure) => visitor.visit_closure(closure),
+        Expression::Literal(literal) => visitor.visit_literal(literal),
+        Expression::Path(path) => visitor.visit_path(path),
+        Expression::Array(expressions, _) | Expression::Tuple(expressions, _) => {
+            for expression in expressions {
+                visitor.visit_expression(expression);
+            }
+        }
+        Expression::Init(init) => visitor.visit_init(init),
+        Expression::Operator(operator) => visitor.visit_operator(operator),
+        Expression::Name(name) => visitor.visit_name(name),
+        Expression::If(r#if) => visitor.visit_if(r#if),
+        Expression::Unsafe(r#unsafe) => visitor.visit_unsafe(r#unsafe),
+        Expression::Interpolation(parts) => {
+            for part in parts {
+                visitor.visit_interpolation_part(part);
+            }
+        }
+        Expression::Todo(todo) => visitor.visit_todo(todo),
+        Expression::Intrinsic(intrinsic) => {
+            for type_argument in &intrinsic.type_arguments {
+                visitor.visit_type_parameter(type_argument);
+            }
+        }
+    }
+}
+
+pub fn walk_match<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, r#match: &'ast Match) {
+    visitor.visit_expression(&r#match.scrutinee);
+    for arm in &r#match.arms {
+        visitor.visit_match_arm(arm);
+    }
+}
+
+pub fn walk_match_arm<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, match_arm: &'ast MatchArm) {
+    visitor.visit_pattern(&match_arm.pattern);
+    if let Some(guard) = &match_arm.guard {
+        visitor.visit_expression(guard);
+    }
+    match &match_arm.body {
+        MatchArmBody::Expression(expression) => visitor.visit_expression(expression),
+        MatchArmBody::Block(block) => visitor.visit_block(block),
+    }
+}
+
+pub fn walk_closure<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, closure: &'ast Closure) {
+    for (pattern, ty) in &closure.parameters {
+        visitor.visit_pattern(pattern);
+        if let Some(ty) = ty {
+            visitor.visit_type(ty);
+        }
+    }
+    if let Some(return_type) = &closure.return_type {
+        visitor.visit_type(return_type);
+    }
+    match &closure.body {
+        ClosureBody::Expression(expression) => visitor.visit_expression(expression),
+        ClosureBody::Block(block) => visitor.visit_block(block),
+    }
+}
+
+pub fn walk_unsafe<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, r#unsafe: &'ast Unsafe) {
+    visitor.visit_block(&r#unsafe.block);
+}
+
+pub fn walk_init<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, init: &'ast Init) {
+    for field in &init.fields {
+        visitor.visit_expression(&field.value);
+    }
+}
+
+pub fn walk_interpolation_part<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    interpolation_part: &'ast InterpolationPart,
+) {
+    if let InterpolationPart::Expression(expression) = interpolation_part {
+        visitor.visit_expression(expression);
+    }
+}
+
+pub fn walk_operator<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, operator: &'ast Operator) {
+    match operator {
+        Operator::Prefix(operator) => visitor.visit_prefix_operator(operator),
+        Operator::Infix(operator) => visitor.visit_infix_operator(operator),
+        Operator::Postfix(operator) => visitor.visit_postfix_operator(operator),
+    }
+}
+
+pub fn walk_prefix_operator<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    prefix_operator: &'ast PrefixOperator,
+) {
+    let (PrefixOperator::Not(Not(_, operand))
+    | PrefixOperator::UnaryPlus(UnaryPlus(_, operand))
+    | PrefixOperator::UnaryMinus(UnaryMinus(_, operand))) = prefix_operator;
+    visitor.visit_expression(operand);
+}
+
+pub fn walk_infix_operator<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    infix_operator: &'ast InfixOperator,
+) {
+    let (lhs, rhs) = infix_operator.operands();
+    visitor.visit_expression(lhs);
+    visitor.visit_expression(rhs);
+}
+
+pub fn walk_postfix_operator<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    postfix_operator: &'ast PostfixOperator,
+) {
+    match postfix_operator {
+        PostfixOperator::Index(Index(operand, arguments, _))
+        | PostfixOperator::FunctionCall(FunctionCall(operand, arguments)) => {
+            visitor.visit_expression(operand);
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        }
+        PostfixOperator::TypeArguments(TypeArguments(operand, arguments, _)) => {
+            visitor.visit_expression(operand);
+            for argument in arguments {
+                visitor.visit_type_parameter(argument);
+            }
+        }
+    }
+}
+
+pub fn walk_if<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, r#if: &'ast If) {
+    visitor.visit_expression(&r#if.condition);
+    visitor.visit_block(&r#if.body);
+    match &r#if.else_part {
+        Some(Else::If(_, r#if)) => visitor.visit_if(r#if),
+        Some(Else::Block(_, block)) => visitor.visit_block(block),
+        None => {}
+    }
+}
+
+pub fn walk_pattern<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, pattern: &'ast Pattern) {
+    match pattern {
+        Pattern::Slot(name) => visitor.visit_name(name),
+        Pattern::Literal(literal) => visitor.visit_literal(literal),
+    }
+}
+
+pub fn walk_type<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, ty: &'ast Type) {
+    match ty {
+        Type::Basic {
+            base,
+            type_parameters,
+            ..
+        } => {
+            visitor.visit_path(base);
+            for type_parameter in type_parameters {
+                visitor.visit_type_parameter(type_parameter);
+            }
+        }
+        Type::Tuple(types, _) => {
+            for ty in types {
+                visitor.visit_type(ty);
+            }
+        }
+        Type::Impl(_, ty) | Type::Dyn(_, ty) | Type::Nullable(ty, _) => visitor.visit_type(ty),
+        Type::Function {
+            parameters_type,
+            return_type,
+            ..
+        } => {
+            for ty in parameters_type {
+                visitor.visit_type(ty);
+            }
+            visitor.visit_type(return_type);
+        }
+        Type::Never(_) => {}
+    }
+}
+
+pub fn walk_type_parameter<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    type_parameter: &'ast TypeParameter,
+) {
+    if let TypeParameter::Specific(ty) = type_parameter {
+        visitor.visit_type(ty);
+    }
+}
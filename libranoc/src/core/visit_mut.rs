@@ -0,0 +1,419 @@
+//! Walking the AST by mutable reference, the same way as `Visit` does, for
+//! the passes rewriting the nodes in place.
+
+use crate::core::ast::*;
+
+pub trait VisitMut {
+    fn visit_module(&mut self, module: &mut Module) {
+        walk_module(self, module)
+    }
+
+    fn visit_node(&mut self, node: &mut Node) {
+        walk_node(self, node)
+    }
+
+    fn visit_directive(&mut self, _directive: &mut Directive) {}
+
+    fn visit_statement(&mut self, statement: &mut Statement) {
+        walk_statement(self, statement)
+    }
+
+    fn visit_let(&mut self, r#let: &mut Let) {
+        walk_let(self, r#let)
+    }
+
+    fn visit_declaration(&mut self, declaration: &mut Declaration) {
+        walk_declaration(self, declaration)
+    }
+
+    fn visit_function_declaration(&mut self, function_declaration: &mut FunctionDeclaration) {
+        walk_function_declaration(self, function_declaration)
+    }
+
+    fn visit_generic_parameter(&mut self, generic_parameter: &mut GenericParameter) {
+        walk_generic_parameter(self, generic_parameter)
+    }
+
+    fn visit_use_declaration(&mut self, use_declaration: &mut UseDeclaration) {
+        walk_use_declaration(self, use_declaration)
+    }
+
+    fn visit_use_tree(&mut self, use_tree: &mut UseTree) {
+        walk_use_tree(self, use_tree)
+    }
+
+    fn visit_operator_declaration(&mut self, _operator_declaration: &mut OperatorDeclaration) {}
+
+    fn visit_module_declaration(&mut self, module_declaration: &mut ModuleDeclaration) {
+        walk_module_declaration(self, module_declaration)
+    }
+
+    fn visit_block(&mut self, block: &mut Block) {
+        walk_block(self, block)
+    }
+
+    fn visit_expression(&mut self, expression: &mut Expression) {
+        walk_expression(self, expression)
+    }
+
+    fn visit_match(&mut self, r#match: &mut Match) {
+        walk_match(self, r#match)
+    }
+
+    fn visit_match_arm(&mut self, match_arm: &mut MatchArm) {
+        walk_match_arm(self, match_arm)
+    }
+
+    fn visit_closure(&mut self, closure: &mut Closure) {
+        walk_closure(self, closure)
+    }
+
+    fn visit_unsafe(&mut self, r#unsafe: &mut Unsafe) {
+        walk_unsafe(self, r#unsafe)
+    }
+
+    fn visit_init(&mut self, init: &mut Init) {
+        walk_init(self, init)
+    }
+
+    fn visit_literal(&mut self, _literal: &mut Literal) {}
+
+    fn visit_path(&mut self, _path: &mut Path) {}
+
+    fn visit_name(&mut self, _name: &mut Name) {}
+
+    fn visit_todo(&mut self, _todo: &mut Todo) {}
+
+    fn visit_interpolation_part(&mut self, interpolation_part: &mut InterpolationPart) {
+        walk_interpolation_part(self, interpolation_part)
+    }
+
+    fn visit_operator(&mut self, operator: &mut Operator) {
+        walk_operator(self, operator)
+    }
+
+    fn visit_prefix_operator(&mut self, prefix_operator: &mut PrefixOperator) {
+        walk_prefix_operator(self, prefix_operator)
+    }
+
+    fn visit_infix_operator(&mut self, infix_operator: &mut InfixOperator) {
+        walk_infix_operator(self, infix_operator)
+    }
+
+    fn visit_postfix_operator(&mut self, postfix_operator: &mut PostfixOperator) {
+        walk_postfix_operator(self, postfix_operator)
+    }
+
+    fn visit_if(&mut self, r#if: &mut If) {
+        walk_if(self, r#if)
+    }
+
+    fn visit_pattern(&mut self, pattern: &mut Pattern) {
+        walk_pattern(self, pattern)
+    }
+
+    fn visit_type(&mut self, ty: &mut Type) {
+        walk_type(self, ty)
+    }
+
+    fn visit_type_parameter(&mut self, type_parameter: &mut TypeParameter) {
+        walk_type_parameter(self, type_parameter)
+    }
+}
+
+pub fn walk_module<V: VisitMut + ?Sized>(visitor: &mut V, module: &mut Module) {
+    for node in &mut module.nodes {
+        visitor.visit_node(node);
+    }
+}
+
+pub fn walk_node<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut Node) {
+    match node {
+        Node::Directive(directive) => visitor.visit_directive(directive),
+        Node::Statement(statement) => visitor.visit_statement(statement),
+    }
+}
+
+pub fn walk_statement<V: VisitMut + ?Sized>(visitor: &mut V, statement: &mut Statement) {
+    match statement {
+        Statement::Declaration(declaration) => visitor.visit_declaration(declaration),
+        Statement::Let(r#let) => visitor.visit_let(r#let),
+        Statement::Expression(expression) => visitor.visit_expression(expression),
+    }
+}
+
+pub fn walk_let<V: VisitMut + ?Sized>(visitor: &mut V, r#let: &mut Let) {
+    visitor.visit_pattern(&mut r#let.pattern);
+    if let Some(ty) = &mut r#let.ty {
+        visitor.visit_type(ty);
+    }
+    if let Some(value) = &mut r#let.value {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_declaration<V: VisitMut + ?Sized>(visitor: &mut V, declaration: &mut Declaration) {
+    match declaration {
+        Declaration::FunctionDeclaration(function_declaration) => {
+            visitor.visit_function_declaration(function_declaration)
+        }
+        Declaration::UseDeclaration(use_declaration) => {
+            visitor.visit_use_declaration(use_declaration)
+        }
+        Declaration::OperatorDeclaration(operator_declaration) => {
+            visitor.visit_operator_declaration(operator_declaration)
+        }
+        Declaration::ModuleDeclaration(module_declaration) => {
+            visitor.visit_module_declaration(module_declaration)
+        }
+    }
+}
+
+pub fn walk_function_declaration<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    function_declaration: &mut FunctionDeclaration,
+) {
+    for generic_parameter in &mut function_declaration.generic_parameters {
+        visitor.visit_generic_parameter(generic_parameter);
+    }
+    for (pattern, ty) in &mut function_declaration.parameters {
+        visitor.visit_pattern(pattern);
+        visitor.visit_type(ty);
+    }
+    visitor.visit_type(&mut function_declaration.return_type);
+    if let Some(body) = &mut function_declaration.body {
+        visitor.visit_block(body);
+    }
+}
+
+pub fn walk_generic_parameter<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    generic_parameter: &mut GenericParameter,
+) {
+    for bound in &mut generic_parameter.bounds {
+        visitor.visit_type(bound);
+    }
+}
+
+pub fn walk_use_declaration<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    use_declaration: &mut UseDeclaration,
+) {
+    visitor.visit_use_tree(&mut use_declaration.tree);
+}
+
+pub fn walk_use_tree<V: VisitMut + ?Sized>(visitor: &mut V, use_tree: &mut UseTree) {
+    visitor.visit_path(&mut use_tree.prefix);
+    if let UseTreeKind::Nested(trees) = &mut use_tree.kind {
+        for tree in trees {
+            visitor.visit_use_tree(tree);
+        }
+    }
+}
+
+pub fn walk_module_declaration<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    module_declaration: &mut ModuleDeclaration,
+) {
+    if let ModuleBody::Inline { module, .. } = &mut module_declaration.body {
+        visitor.visit_module(module);
+    }
+}
+
+pub fn walk_block<V: VisitMut + ?Sized>(visitor: &mut V, block: &mut Block) {
+    for statement in &mut block.body {
+        visitor.visit_statement(statement);
+    }
+    if let Some(expression) = &mut block.last_expression {
+        visitor.visit_expression(expression);
+    }
+}
+
+pub fn walk_expression<V: VisitMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    match expression {
+        Expression::Match(r#match) => visitor.visit_match(r#match),
+        Expression::Closure(closure) => visitor.visit_closure(closure),
+        Expression::Literal(literal) => visitor.visit_literal(literal),
+        Expression::Path(path) => visitor.visit_path(path),
+        Expression::Array(expressions, _) | Expression::Tuple(expressions, _) => {
+            for expression in expressions {
+                visitor.visit_expression(expression);
+            }
+        }
+        Expression::Init(init) => visitor.visit_init(init),
+        Expression::Operator(operator) => visitor.visit_operator(operator),
+        Expression::Name(name) => visitor.visit_name(name),
+        Expression::If(r#if) => visitor.visit_if(r#if),
+        Expression::Unsafe(r#unsafe) => visitor.visit_unsafe(r#unsafe),
+        Expression::Interpolation(parts) => {
+            for part in parts {
+                visitor.visit_interpolation_part(part);
+            }
+        }
+        Expression::Todo(todo) => visitor.visit_todo(todo),
+        Expression::Intrinsic(intrinsic) => {
+            for type_argument in &mut intrinsic.type_arguments {
+                visitor.visit_type_parameter(type_argument);
+            }
+        }
+    }
+}
+
+pub fn walk_match<V: VisitMut + ?Sized>(visitor: &mut V, r#match: &mut Match) {
+    visitor.visit_expression(&mut r#match.scrutinee);
+    for arm in &mut r#match.arms {
+        visitor.visit_match_arm(arm);
+    }
+}
+
+pub fn walk_match_arm<V: VisitMut + ?Sized>(visitor: &mut V, match_arm: &mut MatchArm) {
+    visitor.visit_pattern(&mut match_arm.pattern);
+    if let Some(guard) = &mut match_arm.guard {
+        visitor.visit_expression(guard);
+    }
+    match &mut match_arm.body {
+        MatchArmBody::Expression(expression) => visitor.visit_expression(expression),
+        MatchArmBody::Block(block) => visitor.visit_block(block),
+    }
+}
+
+pub fn walk_closure<V: VisitMut + ?Sized>(visitor: &mut V, closure: &mut Closure) {
+    for (pattern, ty) in &mut closure.parameters {
+        visitor.visit_pattern(pattern);
+        if let Some(ty) = ty {
+            visitor.visit_type(ty);
+        }
+    }
+    if let Some(return_type) = &mut closure.return_type {
+        visitor.visit_type(return_type);
+    }
+    match &mut closure.body {
+        ClosureBody::Expression(expression) => visitor.visit_expression(expression),
+        ClosureBody::Block(block) => visitor.visit_block(block),
+    }
+}
+
+pub fn walk_unsafe<V: VisitMut + ?Sized>(visitor: &mut V, r#unsafe: &mut Unsafe) {
+    visitor.visit_block(&mut r#unsafe.block);
+}
+
+pub fn walk_init<V: VisitMut + ?Sized>(visitor: &mut V, init: &mut Init) {
+    for field in &mut init.fields {
+        visitor.visit_expression(&mut field.value);
+    }
+}
+
+pub fn walk_interpolation_part<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    interpolation_part: &mut InterpolationPart,
+) {
+    if let InterpolationPart::Expression(expression) = interpolation_part {
+        visitor.visit_expression(expression);
+    }
+}
+
+pub fn walk_operator<V: VisitMut + ?Sized>(visitor: &mut V, operator: &mut Operator) {
+    match operator {
+        Operator::Prefix(operator) => visitor.visit_prefix_operator(operator),
+        Operator::Infix(operator) => visitor.visit_infix_operator(operator),
+        Operator::Postfix(operator) => visitor.visit_postfix_operator(operator),
+    }
+}
+
+pub fn walk_prefix_operator<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    prefix_operator: &mut PrefixOperator,
+) {
+    let (PrefixOperator::Not(Not(_, operand))
+    | PrefixOperator::UnaryPlus(UnaryPlus(_, operand))
+    | PrefixOperator::UnaryMinus(UnaryMinus(_, operand))) = prefix_operator;
+    visitor.visit_expression(operand);
+}
+
+pub fn walk_infix_operator<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    infix_operator: &mut InfixOperator,
+) {
+    let (lhs, rhs) = infix_operator.operands_mut();
+    visitor.visit_expression(lhs);
+    visitor.visit_expression(rhs);
+}
+
+pub fn walk_postfix_operator<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    postfix_operator: &mut PostfixOperator,
+) {
+    match postfix_operator {
+        PostfixOperator::Index(Index(operand, arguments, _))
+        | PostfixOperator::FunctionCall(FunctionCall(operand, arguments)) => {
+            visitor.visit_expression(operand);
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        }
+        PostfixOperator::TypeArguments(TypeArguments(operand, arguments, _)) => {
+            visitor.visit_expression(operand);
+            for argument in arguments {
+                visitor.visit_type_parameter(argument);
+            }
+        }
+    }
+}
+
+pub fn walk_if<V: VisitMut + ?Sized>(visitor: &mut V, r#if: &mut If) {
+    visitor.visit_expression(&mut r#if.condition);
+    visitor.visit_block(&mut r#if.body);
+    match &mut r#if.else_part {
+        Some(Else::If(_, r#if)) => visitor.visit_if(r#if),
+        Some(Else::Block(_, block)) => visitor.visit_block(block),
+        None => {}
+    }
+}
+
+pub fn walk_pattern<V: VisitMut + ?Sized>(visitor: &mut V, pattern: &mut Pattern) {
+    match pattern {
+        Pattern::Slot(name) => visitor.visit_name(name),
+        Pattern::Literal(literal) => visitor.visit_literal(literal),
+    }
+}
+
+pub fn walk_type<V: VisitMut + ?Sized>(visitor: &mut V, ty: &mut Type) {
+    match ty {
+        Type::Basic {
+            base,
+            type_parameters,
+            ..
+        } => {
+            visitor.visit_path(base);
+            for type_parameter in type_parameters {
+                visitor.visit_type_parameter(type_parameter);
+            }
+        }
+        Type::Tuple(types, _) => {
+            for ty in types {
+                visitor.visit_type(ty);
+            }
+        }
+        Type::Impl(_, ty) | Type::Dyn(_, ty) | Type::Nullable(ty, _) => visitor.visit_type(ty),
+        Type::Function {
+            parameters_type,
+            return_type,
+            ..
+        } => {
+            for ty in parameters_type {
+                visitor.visit_type(ty);
+            }
+            visitor.visit_type(return_type);
+        }
+        Type::Never(_) => {}
+    }
+}
+
+pub fn walk_type_parameter<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    type_parameter: &mut TypeParameter,
+) {
+    if let TypeParameter::Specific(ty) = type_parameter {
+        visitor.visit_type(ty);
+    }
+}
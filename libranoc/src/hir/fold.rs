@@ -0,0 +1,395 @@
+use std::convert::TryFrom;
+
+use crate::{
+    codegen::Overflow,
+    core::{
+        ast::{Integer, Literal},
+        Error, TypeId,
+    },
+    hir::*,
+    interpret::{self, Fault, Value},
+    syntax::{Span, Spanned, Token, TokenKind},
+    typeck::Typeck,
+};
+
+/// The value of an expression known without running it. Only `i32`
+/// integers are known this way for now.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Constant {
+    Integer(i32),
+    Boolean(bool),
+}
+
+impl Constant {
+    /// `value` of the type `ty` given by the interpreter, which is a
+    /// boolean or an `i32`.
+    fn of(value: Value, ty: TypeId) -> Constant {
+        match (value, ty) {
+            (Value::Integer(value), TypeId::BOOL) => Constant::Boolean(value != 0),
+            (Value::Integer(value), _) => Constant::Integer(value),
+            (value, _) => unreachable!("`{}` isn't a constant", value),
+        }
+    }
+
+    /// The literal written as if `self` was in the source at `span`, with
+    /// integers in decimal.
+    pub fn to_literal(self, span: Span) -> Literal {
+        match self {
+            Constant::Integer(value) => Literal::Integer(Integer(Token {
+                kind: TokenKind::LiteralNumberIntegral(value.to_string()),
+                span,
+                content: value.to_string(),
+            })),
+            Constant::Boolean(value) => Literal::Boolean(Token {
+                kind: TokenKind::LiteralBoolean(value.to_string()),
+                span,
+                content: value.to_string(),
+            }),
+        }
+    }
+}
+
+impl From<Constant> for Value {
+    fn from(constant: Constant) -> Value {
+        match constant {
+            Constant::Integer(value) => Value::Integer(value),
+            Constant::Boolean(value) => Value::Integer(value as i32),
+        }
+    }
+}
+
+/// The value of `expression` if it is made of literals and operators on
+/// them only, which is what array lengths and the initializers of constants
+/// have to be. An operation overflowing or dividing by zero is an error
+/// rather than wrapping around at run time.
+pub fn evaluate_constant(expression: &Expression) -> Result<Option<Constant>, Error> {
+    match &expression.kind {
+        ExpressionKind::Literal(literal) => literal_constant(literal),
+        ExpressionKind::Unary(Unary {
+            operator: UnaryOperator::Minus,
+            operator_span,
+            operand,
+        }) if is_integer_literal(operand) => {
+            negated_integer_literal(operator_span, operand).map(Some)
+        }
+        ExpressionKind::Unary(unary) => match evaluate_constant(&unary.operand)? {
+            Some(operand) => unary_operation(unary, operand, &expression.span),
+            None => Ok(None),
+        },
+        ExpressionKind::Binary(binary) => {
+            let lhs = match evaluate_constant(&binary.lhs)? {
+                Some(lhs) => lhs,
+                None => return Ok(None),
+            };
+            if let Some(value) = short_circuit(binary.operator, lhs) {
+                return Ok(Some(value));
+            }
+            match evaluate_constant(&binary.rhs)? {
+                Some(rhs) => binary_operation(binary, lhs, rhs, &expression.span),
+                None => Ok(None),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Replaces the constant expressions in `module` by their values, so codegen
+/// neither emits the operations nor calls the operators on them. Integer
+/// literals are rewritten in decimal along the way. Only `i32` and `bool`
+/// expressions are folded, the ones of other types left to run. Without
+/// `operations`, only the literals are rewritten, and the operations are
+/// left to run too, but still reported if they overflow.
+pub fn fold_constants(module: &mut Module, typeck: &Typeck, operations: bool) -> Vec<Error> {
+    let mut folding = Folding {
+        typeck,
+        operations,
+        errors: Vec::new(),
+    };
+    fold_module(module, &mut folding);
+    folding.errors
+}
+
+struct Folding<'a> {
+    typeck: &'a Typeck,
+    /// Whether the operations on constants are replaced by their values.
+    operations: bool,
+    errors: Vec<Error>,
+}
+
+fn fold_module(module: &mut Module, folding: &mut Folding) {
+    for statement in &mut module.statements {
+        fold_statement(statement, folding);
+    }
+}
+
+fn fold_statement(statement: &mut Statement, folding: &mut Folding) {
+    match statement {
+        Statement::Item(Item::Function(function)) => {
+            if let Some(body) = &mut function.body {
+                fold_block(body, folding);
+            }
+        }
+        Statement::Item(Item::Module(declaration)) => {
+            if let Some(body) = &mut declaration.body {
+                fold_module(body, folding);
+            }
+        }
+        Statement::Item(Item::Use(_)) => {}
+        Statement::Let(r#let) => {
+            if let Some(value) = &mut r#let.value {
+                fold_expression(value, folding);
+            }
+        }
+        Statement::Expression(expression) => {
+            fold_expression(expression, folding);
+        }
+    }
+}
+
+fn fold_block(block: &mut Block, folding: &mut Folding) {
+    for statement in &mut block.statements {
+        fold_statement(statement, folding);
+    }
+    if let Some(value) = &mut block.value {
+        fold_expression(value, folding);
+    }
+}
+
+/// Folds the operands before the operation, so each operation only looks at
+/// the values they have and an error is reported once, by the innermost
+/// operation causing it. Gives the value of `expression`, if it is constant.
+fn fold_expression(expression: &mut Expression, folding: &mut Folding) -> Option<Constant> {
+    // an expression the checker couldn't type is folded as it was before.
+    let foldable = matches!(
+        folding.typeck.type_of(expression.id),
+        None | Some(TypeId::I32) | Some(TypeId::BOOL)
+    );
+    let literal = match &expression.kind {
+        ExpressionKind::Literal(_) => true,
+        ExpressionKind::Unary(Unary {
+            operator: UnaryOperator::Minus,
+            operand,
+            ..
+        }) => is_integer_literal(operand),
+        _ => false,
+    };
+    let folded = match &mut expression.kind {
+        ExpressionKind::Literal(literal) if foldable => literal_constant(literal),
+        // the operand of an operation of another type, which is only read.
+        ExpressionKind::Literal(literal) => return literal_constant(literal).ok().flatten(),
+        ExpressionKind::Unary(Unary {
+            operator: UnaryOperator::Minus,
+            operator_span,
+            operand,
+        }) if foldable && is_integer_literal(operand) => {
+            negated_integer_literal(operator_span, operand).map(Some)
+        }
+        ExpressionKind::Unary(unary) => match fold_expression(&mut unary.operand, folding) {
+            Some(operand) if foldable => unary_operation(unary, operand, &expression.span),
+            _ => Ok(None),
+        },
+        ExpressionKind::Binary(binary) => {
+            let lhs = fold_expression(&mut binary.lhs, folding);
+            let rhs = fold_expression(&mut binary.rhs, folding);
+            if !foldable {
+                return None;
+            }
+            match (lhs, rhs) {
+                (Some(lhs), _) if short_circuit(binary.operator, lhs).is_some() => {
+                    Ok(short_circuit(binary.operator, lhs))
+                }
+                (Some(lhs), Some(rhs)) => binary_operation(binary, lhs, rhs, &expression.span),
+                _ => Ok(None),
+            }
+        }
+        kind => {
+            fold_children(kind, folding);
+            Ok(None)
+        }
+    };
+    match folded {
+        Ok(Some(constant)) => {
+            if literal || folding.operations {
+                expression.kind =
+                    ExpressionKind::Literal(constant.to_literal(expression.span.clone()));
+            }
+            Some(constant)
+        }
+        Ok(None) => None,
+        Err(error) => {
+            folding.errors.push(error);
+            None
+        }
+    }
+}
+
+fn fold_children(kind: &mut ExpressionKind, folding: &mut Folding) {
+    match kind {
+        ExpressionKind::Array(elements) | ExpressionKind::Tuple(elements) => {
+            for element in elements {
+                fold_expression(element, folding);
+            }
+        }
+        ExpressionKind::Init(init) => {
+            for (_, value) in &mut init.fields {
+                fold_expression(value, folding);
+            }
+        }
+        ExpressionKind::Assign(assign) => {
+            fold_expression(&mut assign.target, folding);
+            fold_expression(&mut assign.value, folding);
+        }
+        ExpressionKind::Field(field) => {
+            fold_expression(&mut field.operand, folding);
+        }
+        ExpressionKind::Instantiate(instantiate) => {
+            fold_expression(&mut instantiate.operand, folding);
+        }
+        ExpressionKind::Call(call) => {
+            fold_expression(&mut call.callee, folding);
+            for argument in &mut call.arguments {
+                fold_expression(argument, folding);
+            }
+        }
+        ExpressionKind::Index(index) => {
+            fold_expression(&mut index.operand, folding);
+            for index in &mut index.indices {
+                fold_expression(index, folding);
+            }
+        }
+        ExpressionKind::If(r#if) => {
+            fold_expression(&mut r#if.condition, folding);
+            fold_block(&mut r#if.body, folding);
+            if let Some(else_block) = &mut r#if.else_block {
+                fold_block(else_block, folding);
+            }
+        }
+        ExpressionKind::Match(r#match) => {
+            fold_expression(&mut r#match.scrutinee, folding);
+            for arm in &mut r#match.arms {
+                if let Some(guard) = &mut arm.guard {
+                    fold_expression(guard, folding);
+                }
+                fold_expression(&mut arm.body, folding);
+            }
+        }
+        ExpressionKind::Closure(closure) => {
+            fold_expression(&mut closure.body, folding);
+        }
+        ExpressionKind::Block(block) => fold_block(block, folding),
+        ExpressionKind::Unsafe(r#unsafe) => fold_block(&mut r#unsafe.block, folding),
+        ExpressionKind::Literal(_)
+        | ExpressionKind::Name(_)
+        | ExpressionKind::Path(_)
+        | ExpressionKind::Unary(_)
+        | ExpressionKind::Binary(_)
+        | ExpressionKind::Null
+        | ExpressionKind::Todo(_)
+        | ExpressionKind::Intrinsic(_) => {}
+    }
+}
+
+fn is_integer_literal(expression: &Expression) -> bool {
+    matches!(
+        expression.kind,
+        ExpressionKind::Literal(Literal::Integer(_))
+    )
+}
+
+/// The value of an integer literal as written, which may be one more than
+/// `i32::MAX` if it is negated.
+fn integer_literal(integer: &Integer) -> Result<i64, Error> {
+    integer
+        .value()
+        .and_then(|value| i64::try_from(value).ok())
+        .ok_or_else(|| Error::arithmetic_overflow(integer.0.span.clone()))
+}
+
+fn to_i32(value: i64, span: &Span) -> Result<i32, Error> {
+    i32::try_from(value).map_err(|_| Error::arithmetic_overflow(span.clone()))
+}
+
+fn literal_constant(literal: &Literal) -> Result<Option<Constant>, Error> {
+    match literal {
+        Literal::Integer(integer) => {
+            let value = integer_literal(integer)?;
+            to_i32(value, &integer.0.span).map(|value| Some(Constant::Integer(value)))
+        }
+        Literal::Boolean(token) => Ok(Some(Constant::Boolean(token.content == "true"))),
+        Literal::String(_) | Literal::Character(_) | Literal::Decimal(_) => Ok(None),
+    }
+}
+
+/// `-2147483648` is a negated literal out of the range of `i32` on its own,
+/// so it is negated before checking the range.
+fn negated_integer_literal(operator_span: &Span, operand: &Expression) -> Result<Constant, Error> {
+    let integer = match &operand.kind {
+        ExpressionKind::Literal(Literal::Integer(integer)) => integer,
+        _ => unreachable!(),
+    };
+    let value = integer_literal(integer)?;
+    to_i32(-value, &operator_span.joined(&operand.span)).map(Constant::Integer)
+}
+
+/// The operations on the constants are the ones the interpreter runs, with
+/// the overflows trapping, and what would panic there is reported instead.
+fn unary_operation(
+    unary: &Unary,
+    operand: Constant,
+    span: &Span,
+) -> Result<Option<Constant>, Error> {
+    let ty = match operand {
+        Constant::Integer(_) => TypeId::I32,
+        // left to codegen, which reports the mismatched types.
+        Constant::Boolean(_) if unary.operator != UnaryOperator::Not => return Ok(None),
+        Constant::Boolean(_) => TypeId::BOOL,
+    };
+    interpret::unary_operation(&unary.operator, ty, operand.into(), Overflow::Trap)
+        .map(|value| value.map(|value| Constant::of(value, ty)))
+        .map_err(|_| Error::arithmetic_overflow(span.clone()))
+}
+
+/// The value of `lhs && rhs` or `lhs || rhs` decided by `lhs` alone.
+fn short_circuit(operator: BinaryOperator, lhs: Constant) -> Option<Constant> {
+    match (operator, lhs) {
+        (BinaryOperator::LogicalAnd, Constant::Boolean(false)) => Some(Constant::Boolean(false)),
+        (BinaryOperator::LogicalOr, Constant::Boolean(true)) => Some(Constant::Boolean(true)),
+        _ => None,
+    }
+}
+
+fn binary_operation(
+    binary: &Binary,
+    lhs: Constant,
+    rhs: Constant,
+    span: &Span,
+) -> Result<Option<Constant>, Error> {
+    use BinaryOperator::*;
+    let ty = match (binary.operator, lhs, rhs) {
+        (EqualTo, ..)
+        | (NotEqualTo, ..)
+        | (GreaterThan, ..)
+        | (LessThan, ..)
+        | (GreaterThanOrEqualTo, ..)
+        | (LessThanOrEqualTo, ..) => TypeId::BOOL,
+        (LogicalAnd, Constant::Boolean(_), Constant::Boolean(_))
+        | (LogicalOr, Constant::Boolean(_), Constant::Boolean(_)) => TypeId::BOOL,
+        (_, Constant::Integer(_), Constant::Integer(_)) => TypeId::I32,
+        _ => return Ok(None),
+    };
+    // unlike at run time, where only the low bits of `rhs` count.
+    if let (ShiftLeft, Constant::Integer(rhs)) | (ShiftRight, Constant::Integer(rhs)) =
+        (binary.operator, rhs)
+    {
+        if !(0..32).contains(&rhs) {
+            return Err(Error::arithmetic_overflow(span.clone()));
+        }
+    }
+    match interpret::binary_operation(binary.operator, lhs.into(), rhs.into(), Overflow::Trap) {
+        Ok(value) => Ok(value.map(|value| Constant::of(value, ty))),
+        Err(Fault::DivisionByZero) | Err(Fault::RemainderByZero) => {
+            Err(Error::division_by_zero(binary.rhs.span()))
+        }
+        Err(Fault::Overflow(_)) => Err(Error::arithmetic_overflow(span.clone())),
+    }
+}
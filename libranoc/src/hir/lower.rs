@@ -0,0 +1,463 @@
+use crate::{
+    core::{
+        ast::{
+            self, ClosureBody, Declaration, Else, InfixOperator, InterpolationPart, MatchArmBody,
+            ModuleBody, Name, Node, Operator, Pattern, PostfixOperator, PrefixOperator,
+        },
+        NodeIdGenerator,
+    },
+    hir::*,
+    syntax::{Span, Spanned, Token, TokenKind},
+};
+
+/// The name `?.` binds its receiver to, which can't clash with a variable
+/// as it isn't an identifier.
+pub(crate) const NULLABLE_RECEIVER: &str = "?.";
+
+/// Lowers `module` into the HIR. It can't fail, as everything the parser
+/// accepts has a meaning in the HIR, even if codegen doesn't support it.
+pub fn lower(module: ast::Module) -> Module {
+    LoweringContext::default().lower_module(module)
+}
+
+#[derive(Default)]
+struct LoweringContext {
+    ids: NodeIdGenerator,
+}
+
+impl LoweringContext {
+    fn expression(&self, kind: ExpressionKind, span: Span) -> Expression {
+        Expression {
+            kind,
+            span,
+            id: self.ids.next(),
+        }
+    }
+
+    fn lower_module(&mut self, module: ast::Module) -> Module {
+        let mut directives = Vec::new();
+        let mut statements = Vec::new();
+        for node in module.nodes {
+            match node {
+                Node::Directive(directive) => directives.push(directive),
+                Node::Statement(statement) => statements.extend(self.lower_statement(statement)),
+            }
+        }
+        Module {
+            directives,
+            statements,
+        }
+    }
+
+    fn lower_statement(&mut self, statement: ast::Statement) -> Option<Statement> {
+        match statement {
+            ast::Statement::Declaration(declaration) => {
+                self.lower_declaration(declaration).map(Statement::Item)
+            }
+            ast::Statement::Let(r#let) => Some(Statement::Let(Let {
+                span: r#let.span(),
+                pattern: r#let.pattern,
+                ty: r#let.ty,
+                value: r#let.value.map(|value| self.lower_expression(value)),
+                id: self.ids.next(),
+            })),
+            ast::Statement::Expression(expression) => {
+                Some(Statement::Expression(self.lower_expression(expression)))
+            }
+        }
+    }
+
+    fn lower_declaration(&mut self, declaration: Declaration) -> Option<Item> {
+        match declaration {
+            Declaration::FunctionDeclaration(declaration) => Some(Item::Function(Function {
+                attributes: declaration.attributes,
+                is_pub: declaration.is_pub,
+                is_extern: declaration.is_extern,
+                name: declaration.name,
+                generic_parameters: declaration.generic_parameters,
+                parameters: declaration.parameters,
+                return_type: declaration.return_type,
+                body: declaration.body.map(|body| self.lower_block(body)),
+                span: declaration.span,
+                id: self.ids.next(),
+            })),
+            Declaration::UseDeclaration(declaration) => Some(Item::Use(declaration)),
+            Declaration::OperatorDeclaration(_) => None,
+            Declaration::ModuleDeclaration(declaration) => Some(Item::Module(ModuleDeclaration {
+                is_pub: declaration.is_pub,
+                name: declaration.name,
+                body: match declaration.body {
+                    ModuleBody::Inline { module, .. } => Some(self.lower_module(module)),
+                    ModuleBody::File(_) => None,
+                },
+                span: declaration.span,
+                id: self.ids.next(),
+            })),
+        }
+    }
+
+    fn lower_block(&mut self, block: ast::Block) -> Block {
+        let span = block.span();
+        Block {
+            statements: block
+                .body
+                .into_iter()
+                .filter_map(|statement| self.lower_statement(statement))
+                .collect(),
+            value: block
+                .last_expression
+                .map(|expression| Box::new(self.lower_expression(expression))),
+            span,
+            id: self.ids.next(),
+        }
+    }
+
+    /// `block` as an expression, like the body of a match arm.
+    fn lower_block_expression(&mut self, block: ast::Block) -> Expression {
+        let block = self.lower_block(block);
+        let span = block.span.clone();
+        self.expression(ExpressionKind::Block(block), span)
+    }
+
+    fn lower_expressions(&mut self, expressions: Vec<ast::Expression>) -> Vec<Expression> {
+        expressions
+            .into_iter()
+            .map(|expression| self.lower_expression(expression))
+            .collect()
+    }
+
+    fn lower_boxed(&mut self, expression: ast::Expression) -> Box<Expression> {
+        Box::new(self.lower_expression(expression))
+    }
+
+    fn lower_expression(&mut self, expression: ast::Expression) -> Expression {
+        let span = expression.span();
+        let kind = match expression {
+            ast::Expression::Match(r#match) => ExpressionKind::Match(Match {
+                scrutinee: self.lower_boxed(*r#match.scrutinee),
+                arms: r#match
+                    .arms
+                    .into_iter()
+                    .map(|arm| MatchArm {
+                        pattern: arm.pattern,
+                        guard: arm.guard.map(|guard| self.lower_expression(guard)),
+                        body: match arm.body {
+                            MatchArmBody::Expression(body) => self.lower_expression(*body),
+                            MatchArmBody::Block(body) => self.lower_block_expression(*body),
+                        },
+                    })
+                    .collect(),
+            }),
+            ast::Expression::Closure(closure) => ExpressionKind::Closure(Closure {
+                is_move: closure.move_token.is_some(),
+                parameters: closure.parameters,
+                return_type: closure.return_type,
+                body: Box::new(match closure.body {
+                    ClosureBody::Expression(body) => self.lower_expression(*body),
+                    ClosureBody::Block(body) => self.lower_block_expression(*body),
+                }),
+            }),
+            ast::Expression::Literal(literal) => ExpressionKind::Literal(literal),
+            ast::Expression::Path(path) => ExpressionKind::Path(path),
+            ast::Expression::Array(elements, _) => {
+                ExpressionKind::Array(self.lower_expressions(elements))
+            }
+            ast::Expression::Tuple(elements, _) => {
+                ExpressionKind::Tuple(self.lower_expressions(elements))
+            }
+            ast::Expression::Init(init) => ExpressionKind::Init(Init {
+                name: init.name,
+                fields: init
+                    .fields
+                    .into_iter()
+                    .map(|field| (field.name, self.lower_expression(field.value)))
+                    .collect(),
+            }),
+            ast::Expression::Operator(operator) => return self.lower_operator(operator),
+            ast::Expression::Name(name) => ExpressionKind::Name(name),
+            ast::Expression::If(r#if) => ExpressionKind::If(self.lower_if(r#if)),
+            ast::Expression::Unsafe(r#unsafe) => ExpressionKind::Unsafe(Unsafe {
+                block: self.lower_block(*r#unsafe.block),
+            }),
+            ast::Expression::Interpolation(parts) => return self.lower_interpolation(parts),
+            ast::Expression::Todo(todo) => ExpressionKind::Todo(todo),
+            ast::Expression::Intrinsic(intrinsic) => ExpressionKind::Intrinsic(intrinsic),
+        };
+        self.expression(kind, span)
+    }
+
+    /// Nests the rest of an `else if` chain into the `else` block.
+    fn lower_if(&mut self, r#if: ast::If) -> If {
+        let condition = self.lower_boxed(*r#if.condition);
+        let body = self.lower_block(*r#if.body);
+        let else_block = r#if.else_part.map(|else_part| match else_part {
+            Else::If(_, r#if) => {
+                let span = r#if.span();
+                let r#if = self.lower_if(*r#if);
+                Block {
+                    statements: Vec::new(),
+                    value: Some(Box::new(
+                        self.expression(ExpressionKind::If(r#if), span.clone()),
+                    )),
+                    span,
+                    id: self.ids.next(),
+                }
+            }
+            Else::Block(_, block) => self.lower_block(*block),
+        });
+        If {
+            condition,
+            body,
+            else_block,
+        }
+    }
+
+    fn lower_operator(&mut self, operator: Operator) -> Expression {
+        let span = operator.span();
+        let kind = match operator {
+            Operator::Prefix(operator) => {
+                let (operator, operator_span, operand) = match operator {
+                    PrefixOperator::Not(ast::Not(span, operand)) => {
+                        (UnaryOperator::Not, span, operand)
+                    }
+                    PrefixOperator::UnaryPlus(ast::UnaryPlus(span, operand)) => {
+                        (UnaryOperator::Plus, span, operand)
+                    }
+                    PrefixOperator::UnaryMinus(ast::UnaryMinus(span, operand)) => {
+                        (UnaryOperator::Minus, span, operand)
+                    }
+                };
+                ExpressionKind::Unary(Unary {
+                    operator,
+                    operator_span,
+                    operand: self.lower_boxed(*operand),
+                })
+            }
+            Operator::Infix(operator) => return self.lower_infix(operator),
+            Operator::Postfix(PostfixOperator::Index(ast::Index(operand, indices, _))) => {
+                ExpressionKind::Index(Index {
+                    operand: self.lower_boxed(*operand),
+                    indices: self.lower_expressions(indices),
+                })
+            }
+            Operator::Postfix(PostfixOperator::TypeArguments(ast::TypeArguments(
+                operand,
+                type_arguments,
+                _,
+            ))) => ExpressionKind::Instantiate(Instantiate {
+                operand: self.lower_boxed(*operand),
+                type_arguments,
+            }),
+            Operator::Postfix(PostfixOperator::FunctionCall(ast::FunctionCall(
+                callee,
+                mut arguments,
+            ))) => {
+                // `f(a, b)` is `f` applied to the tuple `(a, b)` in the AST.
+                if let [ast::Expression::Tuple(..)] = arguments.as_slice() {
+                    if let Some(ast::Expression::Tuple(elements, _)) = arguments.pop() {
+                        arguments = elements;
+                    }
+                }
+                ExpressionKind::Call(Call {
+                    callee: self.lower_boxed(*callee),
+                    arguments: self.lower_expressions(arguments),
+                    span: span.clone(),
+                })
+            }
+        };
+        self.expression(kind, span)
+    }
+
+    fn lower_infix(&mut self, operator: InfixOperator) -> Expression {
+        let span = operator.span();
+        let kind = match operator {
+            InfixOperator::GetField(ast::GetField(operand, field)) => {
+                ExpressionKind::Field(Field {
+                    operand: self.lower_boxed(*operand),
+                    field: self.lower_boxed(*field),
+                })
+            }
+            InfixOperator::GetFieldNullable(ast::GetFieldNullable(operand, field)) => {
+                return self.lower_get_field_nullable(*operand, *field)
+            }
+            InfixOperator::Assign(target, _, value) => ExpressionKind::Assign(Assign {
+                target: self.lower_boxed(*target),
+                value: self.lower_boxed(*value),
+            }),
+            // `lhs += rhs` is `lhs = lhs + rhs`, evaluating `lhs` twice.
+            InfixOperator::CompoundAssign(operation) => {
+                let target = operation.operands().0.clone();
+                ExpressionKind::Assign(Assign {
+                    target: self.lower_boxed(target),
+                    value: Box::new(self.lower_infix(*operation)),
+                })
+            }
+            operator => {
+                let (operator, operator_span, lhs, rhs) = match operator {
+                    InfixOperator::LogicalOr(lhs, span, rhs) => {
+                        (BinaryOperator::LogicalOr, span, lhs, rhs)
+                    }
+                    InfixOperator::LogicalAnd(lhs, span, rhs) => {
+                        (BinaryOperator::LogicalAnd, span, lhs, rhs)
+                    }
+                    InfixOperator::EqualTo(lhs, span, rhs) => {
+                        (BinaryOperator::EqualTo, span, lhs, rhs)
+                    }
+                    InfixOperator::NotEqualTo(lhs, span, rhs) => {
+                        (BinaryOperator::NotEqualTo, span, lhs, rhs)
+                    }
+                    InfixOperator::GreaterThan(lhs, span, rhs) => {
+                        (BinaryOperator::GreaterThan, span, lhs, rhs)
+                    }
+                    InfixOperator::LessThan(lhs, span, rhs) => {
+                        (BinaryOperator::LessThan, span, lhs, rhs)
+                    }
+                    InfixOperator::GreaterThanOrEqualTo(lhs, span, rhs) => {
+                        (BinaryOperator::GreaterThanOrEqualTo, span, lhs, rhs)
+                    }
+                    InfixOperator::LessThanOrEqualTo(lhs, span, rhs) => {
+                        (BinaryOperator::LessThanOrEqualTo, span, lhs, rhs)
+                    }
+                    InfixOperator::Add(lhs, span, rhs) => (BinaryOperator::Add, span, lhs, rhs),
+                    InfixOperator::Subtract(lhs, span, rhs) => {
+                        (BinaryOperator::Subtract, span, lhs, rhs)
+                    }
+                    InfixOperator::Multiply(lhs, span, rhs) => {
+                        (BinaryOperator::Multiply, span, lhs, rhs)
+                    }
+                    InfixOperator::Divide(lhs, span, rhs) => {
+                        (BinaryOperator::Divide, span, lhs, rhs)
+                    }
+                    InfixOperator::Remainder(lhs, span, rhs) => {
+                        (BinaryOperator::Remainder, span, lhs, rhs)
+                    }
+                    InfixOperator::RangeRightExclusive(lhs, span, rhs) => {
+                        (BinaryOperator::RangeRightExclusive, span, lhs, rhs)
+                    }
+                    InfixOperator::RangeRightInclusive(lhs, span, rhs) => {
+                        (BinaryOperator::RangeRightInclusive, span, lhs, rhs)
+                    }
+                    InfixOperator::BitAnd(lhs, span, rhs) => {
+                        (BinaryOperator::BitAnd, span, lhs, rhs)
+                    }
+                    InfixOperator::BitOr(lhs, span, rhs) => (BinaryOperator::BitOr, span, lhs, rhs),
+                    InfixOperator::BitXor(lhs, span, rhs) => {
+                        (BinaryOperator::BitXor, span, lhs, rhs)
+                    }
+                    InfixOperator::ShiftLeft(lhs, span, rhs) => {
+                        (BinaryOperator::ShiftLeft, span, lhs, rhs)
+                    }
+                    InfixOperator::ShiftRight(lhs, span, rhs) => {
+                        (BinaryOperator::ShiftRight, span, lhs, rhs)
+                    }
+                    InfixOperator::GetField(_)
+                    | InfixOperator::GetFieldNullable(_)
+                    | InfixOperator::Assign(..)
+                    | InfixOperator::CompoundAssign(_) => unreachable!(),
+                };
+                ExpressionKind::Binary(Binary {
+                    operator,
+                    operator_span,
+                    lhs: self.lower_boxed(*lhs),
+                    rhs: self.lower_boxed(*rhs),
+                })
+            }
+        };
+        self.expression(kind, span)
+    }
+
+    /// `operand?.field` is
+    ///
+    /// ```text
+    /// match operand {
+    ///     receiver if receiver == null => null,
+    ///     receiver => receiver.field,
+    /// }
+    /// ```
+    ///
+    /// so `operand` is only evaluated once, where `receiver` is named `?.`.
+    fn lower_get_field_nullable(
+        &mut self,
+        operand: ast::Expression,
+        field: ast::Expression,
+    ) -> Expression {
+        let span = operand.span().joined(&field.span());
+        let operand_span = operand.span();
+        let receiver = Name::Ident(Token {
+            kind: TokenKind::IdentifierIdentifier(NULLABLE_RECEIVER.to_owned()),
+            span: operand_span.clone(),
+            content: NULLABLE_RECEIVER.to_owned(),
+        });
+        let receiver_expression = |this: &Self| {
+            this.expression(ExpressionKind::Name(receiver.clone()), operand_span.clone())
+        };
+
+        let is_null = Binary {
+            operator: BinaryOperator::EqualTo,
+            operator_span: operand_span.clone(),
+            lhs: Box::new(receiver_expression(self)),
+            rhs: Box::new(self.expression(ExpressionKind::Null, operand_span.clone())),
+        };
+        let null_arm = MatchArm {
+            pattern: Pattern::Slot(receiver.clone()),
+            guard: Some(self.expression(ExpressionKind::Binary(is_null), operand_span.clone())),
+            body: self.expression(ExpressionKind::Null, span.clone()),
+        };
+        let get_field = Field {
+            operand: Box::new(receiver_expression(self)),
+            field: self.lower_boxed(field),
+        };
+        let value_arm = MatchArm {
+            pattern: Pattern::Slot(receiver.clone()),
+            guard: None,
+            body: self.expression(ExpressionKind::Field(get_field), span.clone()),
+        };
+        let r#match = Match {
+            scrutinee: self.lower_boxed(operand),
+            arms: vec![null_arm, value_arm],
+        };
+        self.expression(ExpressionKind::Match(r#match), span)
+    }
+
+    /// `"a {b} c"` is `"a " + b + " c"`. The first fragment is kept even if
+    /// it is empty, so the sum starts with a string.
+    fn lower_interpolation(&mut self, parts: Vec<InterpolationPart>) -> Expression {
+        let mut operands = Vec::new();
+        for part in parts {
+            match part {
+                InterpolationPart::Fragment(fragment) => {
+                    // `"text {`, `} text {` and `} text"` all have one
+                    // character around the text.
+                    let text = &fragment.content[1..fragment.content.len() - 1];
+                    if !text.is_empty() || operands.is_empty() {
+                        let content = format!("\"{}\"", text);
+                        let literal = ast::Literal::String(Token {
+                            kind: TokenKind::LiteralString(content.clone()),
+                            span: fragment.span.clone(),
+                            content,
+                        });
+                        operands
+                            .push(self.expression(ExpressionKind::Literal(literal), fragment.span));
+                    }
+                }
+                InterpolationPart::Expression(expression) => {
+                    operands.push(self.lower_expression(expression))
+                }
+            }
+        }
+        let mut operands = operands.into_iter();
+        let first = operands
+            .next()
+            .expect("an interpolation starts with a fragment");
+        operands.fold(first, |lhs, rhs| {
+            let span = lhs.span.joined(&rhs.span);
+            let binary = Binary {
+                operator: BinaryOperator::Add,
+                operator_span: rhs.span.clone(),
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+            self.expression(ExpressionKind::Binary(binary), span)
+        })
+    }
+}
@@ -0,0 +1,338 @@
+//! The high-level IR codegen works on, which is the AST with its sugar
+//! lowered into a small core by `lower`. Compound assignments become plain
+//! assignments, `?.` a `match` on `null`, string interpolation a chain of
+//! `+`, `else if` a nested `if` and calls take their arguments one by one,
+//! so codegen only has to know about the constructs left here.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        ast::{
+            Attribute, Directive, GenericParameter, Intrinsic, Literal, Name, Path, Pattern, Todo,
+            Type, TypeParameter, UseDeclaration,
+        },
+        NodeId,
+    },
+    syntax::{Span, Spanned, Token},
+};
+
+mod fold;
+mod lower;
+
+pub use fold::*;
+pub use lower::*;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Module {
+    pub directives: Vec<Directive>,
+    pub statements: Vec<Statement>,
+}
+
+/// The statements of the AST without the operator declarations, which only
+/// the parser needs.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Statement {
+    Item(Item),
+    Let(Let),
+    Expression(Expression),
+}
+
+impl Spanned for Statement {
+    fn span(&self) -> Span {
+        match self {
+            Statement::Item(item) => item.span(),
+            Statement::Let(r#let) => r#let.span(),
+            Statement::Expression(expression) => expression.span(),
+        }
+    }
+}
+
+/// `let pattern: Type = value;`, whose type and value may be left out.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Let {
+    pub pattern: Pattern,
+    pub ty: Option<Type>,
+    pub value: Option<Expression>,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for Let {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Item {
+    Function(Function),
+    Use(UseDeclaration),
+    Module(ModuleDeclaration),
+}
+
+impl Spanned for Item {
+    fn span(&self) -> Span {
+        match self {
+            Item::Function(function) => function.span(),
+            Item::Use(declaration) => declaration.span(),
+            Item::Module(declaration) => declaration.span(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Function {
+    pub attributes: Vec<Attribute>,
+    pub is_pub: bool,
+    pub is_extern: bool,
+    pub name: Token,
+    pub generic_parameters: Vec<GenericParameter>,
+    pub parameters: Vec<(Pattern, Type)>,
+    pub return_type: Type,
+    pub body: Option<Block>,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for Function {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+impl Function {
+    /// The first attribute called `name`.
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name() == Some(name))
+    }
+}
+
+/// `mod name { ... }` with its lowered items, or `mod name;` without them,
+/// as they are in a file of their own.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ModuleDeclaration {
+    pub is_pub: bool,
+    pub name: Token,
+    pub body: Option<Module>,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for ModuleDeclaration {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Block {
+    pub statements: Vec<Statement>,
+    pub value: Option<Box<Expression>>,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for Block {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+/// The ids of the HIR are handed out anew while lowering, to every block
+/// and expression including the ones a desugaring made up, so they don't
+/// match the ids of the AST.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for Expression {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum ExpressionKind {
+    Literal(Literal),
+    Name(Name),
+    Path(Path),
+    Array(Vec<Expression>),
+    Tuple(Vec<Expression>),
+    Init(Init),
+    Unary(Unary),
+    Binary(Binary),
+    Assign(Assign),
+    Field(Field),
+    Call(Call),
+    Index(Index),
+    Instantiate(Instantiate),
+    If(If),
+    Match(Match),
+    Closure(Closure),
+    Block(Block),
+    Unsafe(Unsafe),
+    /// Only made up by lowering `?.` for now, as there is no literal for it.
+    Null,
+    Todo(Todo),
+    Intrinsic(Intrinsic),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Init {
+    pub name: Token,
+    pub fields: Vec<(Token, Expression)>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum UnaryOperator {
+    Not,
+    Plus,
+    Minus,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Unary {
+    pub operator: UnaryOperator,
+    pub operator_span: Span,
+    pub operand: Box<Expression>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum BinaryOperator {
+    LogicalOr,
+    LogicalAnd,
+    EqualTo,
+    NotEqualTo,
+    GreaterThan,
+    LessThan,
+    GreaterThanOrEqualTo,
+    LessThanOrEqualTo,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    RangeRightExclusive,
+    RangeRightInclusive,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+}
+
+impl BinaryOperator {
+    pub fn trait_name(&self) -> &'static str {
+        match self {
+            BinaryOperator::LogicalOr => "LogicalOr",
+            BinaryOperator::LogicalAnd => "LogicalAnd",
+            BinaryOperator::EqualTo => "PartialEq",
+            BinaryOperator::NotEqualTo => "PartialEq",
+            BinaryOperator::GreaterThan => "PartialOrd",
+            BinaryOperator::LessThan => "PartialOrd",
+            BinaryOperator::GreaterThanOrEqualTo => "PartialOrd",
+            BinaryOperator::LessThanOrEqualTo => "PartialOrd",
+            BinaryOperator::Add => "Add",
+            BinaryOperator::Subtract => "Subtract",
+            BinaryOperator::Multiply => "Multiply",
+            BinaryOperator::Divide => "Divide",
+            BinaryOperator::Remainder => "Remainder",
+            BinaryOperator::RangeRightExclusive => "RangeToExlusive",
+            BinaryOperator::RangeRightInclusive => "RangeToInclusive",
+            BinaryOperator::BitAnd => "BitAnd",
+            BinaryOperator::BitOr => "BitOr",
+            BinaryOperator::BitXor => "BitXor",
+            BinaryOperator::ShiftLeft => "ShiftLeft",
+            BinaryOperator::ShiftRight => "ShiftRight",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Binary {
+    pub operator: BinaryOperator,
+    pub operator_span: Span,
+    pub lhs: Box<Expression>,
+    pub rhs: Box<Expression>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Assign {
+    pub target: Box<Expression>,
+    pub value: Box<Expression>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Field {
+    pub operand: Box<Expression>,
+    pub field: Box<Expression>,
+}
+
+/// A call with its arguments, which are never a single tuple holding all of
+/// them like in the AST.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Call {
+    pub callee: Box<Expression>,
+    pub arguments: Vec<Expression>,
+    pub span: Span,
+}
+
+impl Spanned for Call {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Index {
+    pub operand: Box<Expression>,
+    pub indices: Vec<Expression>,
+}
+
+/// `g::<i32>`, the generic `operand` given its type arguments.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Instantiate {
+    pub operand: Box<Expression>,
+    pub type_arguments: Vec<TypeParameter>,
+}
+
+/// An `if` with at most an `else` block, in which the rest of an `else if`
+/// chain is nested.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct If {
+    pub condition: Box<Expression>,
+    pub body: Block,
+    pub else_block: Option<Block>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Match {
+    pub scrutinee: Box<Expression>,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: Expression,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Closure {
+    pub is_move: bool,
+    pub parameters: Vec<(Pattern, Option<Type>)>,
+    pub return_type: Option<Type>,
+    pub body: Box<Expression>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Unsafe {
+    pub block: Block,
+}
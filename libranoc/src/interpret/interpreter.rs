@@ -0,0 +1,599 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryFrom,
+    rc::Rc,
+};
+
+use crate::{
+    codegen::{link_of, typed_module, CompileOptions, Overflow},
+    core::{
+        arena::Idx,
+        ast::{self, Literal, Name, Node, Pattern},
+        Error, Location, NodeId, TypeId,
+    },
+    semantic::{Definition, DefinitionKind, Resolution},
+    syntax::{snippet::Snippet, Span, Spanned, Token, TokenKind},
+    thir::{
+        Binary, Block, Call, Expression, ExpressionKind, Function, If, Item, Let, Statement, Unary,
+        UnaryOperator,
+    },
+};
+
+use super::{binary_operation, unary_operation, Trace, TraceEvent, Value};
+
+pub type ExternFunction = Box<dyn FnMut(&[Value]) -> Value>;
+
+/// The name of the function the statements of the snippets are run in,
+/// which can't clash with a function of theirs as it isn't an identifier.
+const SESSION: &str = "<session>";
+
+/// Runs the typed IR of checked Rano without compiling it, so a function
+/// can be called where no WASM runtime is, tried out in a test, or typed
+/// into the REPL. It gives the results the WASM backend does: the integer
+/// arithmetic does what the `Overflow` of each function says, and what
+/// panics there, like a `todo`, is an error here. The `extern fn`s are
+/// given by the host, by the name they are imported by.
+///
+/// Every transition is recorded into a bounded trace, which can be stepped
+/// through backwards, and a run can be replayed from the trace without
+/// calling the host again.
+pub struct Interpreter {
+    options: CompileOptions,
+    /// The declarations loaded so far, which every module checked next
+    /// starts with.
+    declarations: Vec<ast::Statement>,
+    functions: HashMap<NodeId, Rc<Function>>,
+    /// The functions at the top of the module by their names, which the
+    /// host calls them by.
+    names: HashMap<String, NodeId>,
+    resolution: Resolution,
+    overflow: Overflow,
+    externs: HashMap<String, ExternFunction>,
+    /// The values of the parameters and the locals of each function being
+    /// run, the innermost last.
+    frames: Vec<HashMap<Binding, Value>>,
+    max_depth: usize,
+    replay: Option<VecDeque<Value>>,
+    trace: Trace,
+}
+
+/// What a variable of a function holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Binding {
+    Parameter(u32),
+    Local(Idx<Definition>),
+}
+
+impl Interpreter {
+    /// Every call nests a few evaluations of the interpreter in each other,
+    /// which take kilobytes of the native stack in a debug build, so the
+    /// calls are bounded well before a thread of 2 MiB runs out of it.
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    pub fn new() -> Self {
+        Interpreter::with_trace(Trace::default())
+    }
+
+    pub fn with_trace(trace: Trace) -> Self {
+        Interpreter {
+            options: CompileOptions::default(),
+            declarations: Vec::new(),
+            functions: HashMap::new(),
+            names: HashMap::new(),
+            resolution: Resolution::default(),
+            overflow: Overflow::default(),
+            externs: HashMap::new(),
+            frames: vec![HashMap::new()],
+            max_depth: Interpreter::DEFAULT_MAX_DEPTH,
+            replay: None,
+            trace,
+        }
+    }
+
+    /// Creates an interpreter which answers every host call with the result
+    /// recorded in `trace` instead of calling the host, so a recorded run
+    /// can be reproduced step by step without repeating its side effects.
+    pub fn replaying(trace: &Trace) -> Self {
+        let mut interpreter = Interpreter::with_trace(Trace::new(trace.capacity()));
+        interpreter.replay = Some(trace.extern_results().cloned().collect());
+        interpreter
+    }
+
+    /// Checks what is loaded from now on like it is compiled with `options`,
+    /// and runs it with their `overflow`.
+    pub fn set_options(&mut self, options: CompileOptions) {
+        self.overflow = options.overflow;
+        self.options = options;
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Gives the `extern fn` imported by `name`, which is the `name` of its
+    /// `#[link(...)]` if it has one.
+    pub fn define_extern<F>(&mut self, name: impl Into<String>, function: F)
+    where
+        F: FnMut(&[Value]) -> Value + 'static,
+    {
+        self.externs.insert(name.into(), Box::new(function));
+    }
+
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    pub fn trace_mut(&mut self) -> &mut Trace {
+        &mut self.trace
+    }
+
+    /// Checks the declarations of `module` along with the ones loaded
+    /// before, to call them.
+    pub fn load(&mut self, module: ast::Module) -> Result<(), Vec<Error>> {
+        let declarations = module
+            .nodes
+            .into_iter()
+            .filter_map(|node| match node {
+                Node::Statement(statement @ ast::Statement::Declaration(_)) => Some(statement),
+                _ => None,
+            })
+            .collect();
+        self.run_session(declarations, Vec::new()).map(|_| ())
+    }
+
+    /// Checks and runs a snippet typed into the REPL, in which the functions
+    /// declared before are seen. Gives the values of
+    /// its expression statements, with the value of its trailing expression
+    /// last if it has one.
+    pub fn load_snippet(&mut self, snippet: Snippet) -> Result<Vec<Value>, Vec<Error>> {
+        let (declarations, mut statements): (Vec<_>, Vec<_>) = snippet
+            .statements
+            .into_iter()
+            .partition(|statement| matches!(statement, ast::Statement::Declaration(_)));
+        statements.extend(snippet.last_expression.map(ast::Statement::Expression));
+        self.run_session(declarations, statements)
+    }
+
+    /// Calls the function called `name` at the top of the module.
+    pub fn call(&mut self, name: &str, arguments: Vec<Value>) -> Result<Value, Error> {
+        let id = *self.names.get(name).ok_or_else(|| {
+            Error::runtime_error(format!("Undefined symbol `{}`.", name), Location::Eof)
+        })?;
+        self.call_function(id, arguments, None)
+    }
+
+    /// The value of `expression`, in the function being run.
+    pub fn evaluate(&mut self, expression: &Expression) -> Result<Value, Error> {
+        let Expression { kind, ty, span, id } = expression;
+        match kind {
+            ExpressionKind::Literal(literal) => literal_value(*ty, literal, span),
+            ExpressionKind::Name(Name::Ident(_)) => self
+                .binding(*id)
+                .and_then(|binding| self.frame().get(&binding).cloned())
+                .ok_or_else(|| Error::unimplemented(span)),
+            ExpressionKind::Tuple(elements) => {
+                let mut values = Vec::new();
+                for element in elements {
+                    values.push(self.evaluate(element)?);
+                }
+                Ok(Value::Tuple(values))
+            }
+            ExpressionKind::Field(field) => {
+                let index = match &field.field.kind {
+                    ExpressionKind::Literal(Literal::Integer(index)) => index.value(),
+                    _ => None,
+                };
+                match (self.evaluate(&field.operand)?, index) {
+                    (Value::Tuple(mut values), Some(index)) if (index as usize) < values.len() => {
+                        Ok(values.swap_remove(index as usize))
+                    }
+                    _ => Err(Error::unimplemented(&*field.field)),
+                }
+            }
+            ExpressionKind::Unary(unary) => self.unary(unary),
+            ExpressionKind::Binary(binary) => self.binary(binary),
+            ExpressionKind::Assign(assign) => {
+                let (binding, token) = match &assign.target.kind {
+                    ExpressionKind::Name(Name::Ident(token)) => {
+                        (self.binding(assign.target.id), token)
+                    }
+                    _ => return Err(Error::unimplemented(&*assign.target)),
+                };
+                let binding = binding.ok_or_else(|| Error::unimplemented(&*assign.target))?;
+                let value = self.evaluate(&assign.value)?;
+                self.bind(binding, token, value);
+                Ok(Value::UNIT)
+            }
+            ExpressionKind::Call(call) => self.call_expression(call),
+            ExpressionKind::If(r#if) => self.r#if(r#if),
+            ExpressionKind::Block(block) => self.block(block),
+            ExpressionKind::Unsafe(r#unsafe) => self.block(&r#unsafe.block),
+            ExpressionKind::Todo(todo) => Err(Error::runtime_error(
+                todo.description(),
+                Location::Known(span.clone()),
+            )),
+            _ => Err(Error::unimplemented(span)),
+        }
+    }
+
+    /// Checks the declarations loaded before with `declarations`, and runs
+    /// `statements`, giving the values of the expressions among them. The
+    /// declarations are kept once they check.
+    fn run_session(
+        &mut self,
+        declarations: Vec<ast::Statement>,
+        statements: Vec<ast::Statement>,
+    ) -> Result<Vec<Value>, Vec<Error>> {
+        let mut nodes: Vec<_> = self
+            .declarations
+            .iter()
+            .chain(&declarations)
+            .cloned()
+            .map(Node::Statement)
+            .collect();
+        if !statements.is_empty() {
+            nodes.push(Node::Statement(ast::Statement::Declaration(
+                ast::Declaration::FunctionDeclaration(session_function(statements)),
+            )));
+        }
+        let typed = typed_module(ast::Module { nodes }, &self.options)?;
+        self.resolution = typed.resolution;
+        self.functions.clear();
+        self.names.clear();
+        let mut errors = Vec::new();
+        self.declare(typed.module.statements, true, &mut errors);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        self.declarations.extend(declarations);
+        let session = match self.names.remove(SESSION) {
+            Some(id) => self.functions.remove(&id).expect("the session is declared"),
+            None => return Ok(Vec::new()),
+        };
+        let body = session.body.as_ref().expect("the session has a body");
+
+        self.frames.push(HashMap::new());
+        let mut values = Vec::new();
+        let mut result = Ok(());
+        for statement in &body.statements {
+            result = match statement {
+                Statement::Expression(expression) => {
+                    self.evaluate(expression).map(|value| values.push(value))
+                }
+                statement => self.statement(statement),
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        self.frames.pop();
+        result.map_err(|error| vec![error])?;
+        Ok(values)
+    }
+
+    /// Takes the functions of `statements`, and of the modules among them,
+    /// reporting the other items. The functions of a block are taken when
+    /// it runs.
+    fn declare(&mut self, statements: Vec<Statement>, top: bool, errors: &mut Vec<Error>) {
+        for statement in statements {
+            match statement {
+                Statement::Item(Item::Function(function)) => {
+                    if top {
+                        self.names
+                            .insert(function.name.content.clone(), function.id);
+                    }
+                    self.functions.insert(function.id, Rc::new(function));
+                }
+                Statement::Item(Item::Module(declaration)) => {
+                    if let Some(body) = declaration.body {
+                        self.declare(body.statements, false, errors);
+                    }
+                }
+                Statement::Item(Item::Use(_)) => {}
+                statement => errors.push(Error::unimplemented(statement)),
+            }
+        }
+    }
+
+    fn record(&mut self, event: TraceEvent) {
+        let depth = self.frames.len() - 1;
+        self.trace.record(depth, event);
+    }
+
+    fn frame(&self) -> &HashMap<Binding, Value> {
+        self.frames.last().expect("there is always a frame")
+    }
+
+    fn frame_mut(&mut self) -> &mut HashMap<Binding, Value> {
+        self.frames.last_mut().expect("there is always a frame")
+    }
+
+    /// What the name expression `id` refers to, if it is a parameter or a
+    /// local.
+    fn binding(&self, id: NodeId) -> Option<Binding> {
+        let definition = self.resolution.target(*self.resolution.references.get(id)?);
+        match self.resolution.definitions[definition].kind {
+            DefinitionKind::Parameter { index, .. } => Some(Binding::Parameter(index)),
+            DefinitionKind::Local => Some(Binding::Local(definition)),
+            _ => None,
+        }
+    }
+
+    /// Sets the variable `binding`, written `token`, to `value`.
+    fn bind(&mut self, binding: Binding, token: &Token, value: Value) {
+        self.record(TraceEvent::Bind {
+            name: token.content.clone(),
+            value: value.clone(),
+        });
+        self.frame_mut().insert(binding, value);
+    }
+
+    fn call_function(
+        &mut self,
+        id: NodeId,
+        arguments: Vec<Value>,
+        span: Option<Span>,
+    ) -> Result<Value, Error> {
+        let location = || span.clone().map_or(Location::Eof, Location::Known);
+        let function = self.functions[&id].clone();
+        let name = &function.name.content;
+        if function.is_extern {
+            let name = match function.attribute("link") {
+                Some(link) => link_of(&link.value, &function.name)?.1,
+                None => name.clone(),
+            };
+            return self.call_extern(name, arguments, location());
+        }
+        if self.frames.len() > self.max_depth {
+            return Err(Error::runtime_error(
+                format!("Stack overflow while calling `{}`.", name),
+                location(),
+            ));
+        }
+        let overflow = match function.attribute("overflow") {
+            Some(attribute) => Overflow::from_attribute(&attribute.value)?,
+            None => self.overflow,
+        };
+        let body = function
+            .body
+            .as_ref()
+            .ok_or_else(|| Error::unimplemented(&function.name))?;
+
+        self.record(TraceEvent::Call {
+            function: name.clone(),
+            arguments: arguments.clone(),
+        });
+        let overflow = std::mem::replace(&mut self.overflow, overflow);
+        self.frames
+            .push((0..).map(Binding::Parameter).zip(arguments).collect());
+        let result = self.block(body);
+        self.frames.pop();
+        self.overflow = overflow;
+        let value = result?;
+        self.record(TraceEvent::Return {
+            function: name.clone(),
+            value: value.clone(),
+        });
+
+        Ok(value)
+    }
+
+    fn call_extern(
+        &mut self,
+        name: String,
+        arguments: Vec<Value>,
+        location: Location,
+    ) -> Result<Value, Error> {
+        let value = if let Some(replay) = &mut self.replay {
+            replay.pop_front().ok_or_else(|| {
+                Error::runtime_error(
+                    format!("The replayed trace has no result for `{}`.", name),
+                    location,
+                )
+            })?
+        } else {
+            let function = self.externs.get_mut(&name).ok_or_else(|| {
+                Error::runtime_error(format!("Undefined symbol `{}`.", name), location)
+            })?;
+            function(&arguments)
+        };
+        self.record(TraceEvent::Extern {
+            function: name,
+            arguments,
+            value: value.clone(),
+        });
+
+        Ok(value)
+    }
+
+    fn call_expression(&mut self, call: &Call) -> Result<Value, Error> {
+        let function = match &call.callee.kind {
+            ExpressionKind::Name(Name::Ident(_)) => self
+                .resolution
+                .references
+                .get(call.callee.id)
+                .map(|definition| {
+                    &self.resolution.definitions[self.resolution.target(*definition)].kind
+                }),
+            _ => None,
+        };
+        let id = match function {
+            Some(DefinitionKind::Function { id, .. }) if self.functions.contains_key(id) => *id,
+            _ => return Err(Error::unimplemented(call)),
+        };
+        let mut arguments = Vec::new();
+        for argument in &call.arguments {
+            arguments.push(self.evaluate(argument)?);
+        }
+        self.call_function(id, arguments, Some(call.span.clone()))
+    }
+
+    /// Runs `block` with the functions it declares, which are taken the
+    /// first time it runs, so they can be called before their declaration.
+    fn block(&mut self, block: &Block) -> Result<Value, Error> {
+        for statement in &block.statements {
+            if let Statement::Item(Item::Function(function)) = statement {
+                self.functions
+                    .entry(function.id)
+                    .or_insert_with(|| Rc::new(function.clone()));
+            }
+        }
+        for statement in &block.statements {
+            self.statement(statement)?;
+        }
+        match &block.value {
+            Some(value) => self.evaluate(value),
+            None => Ok(Value::UNIT),
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), Error> {
+        match statement {
+            Statement::Let(r#let) => self.r#let(r#let),
+            Statement::Expression(expression) => self.evaluate(expression).map(|_| ()),
+            Statement::Item(Item::Function(_)) | Statement::Item(Item::Use(_)) => Ok(()),
+            Statement::Item(item) => Err(Error::unimplemented(item)),
+        }
+    }
+
+    /// Binds the local `let` declares to its value. One declared without a
+    /// value stays unbound until it is assigned.
+    fn r#let(&mut self, r#let: &Let) -> Result<(), Error> {
+        let value = match &r#let.value {
+            Some(value) => self.evaluate(value)?,
+            None => return Ok(()),
+        };
+        match (&r#let.pattern, self.resolution.lets.get(r#let.id)) {
+            (Pattern::Slot(Name::Placeholder(_)), _) => Ok(()),
+            (Pattern::Slot(Name::Ident(token)), Some(definition)) => {
+                self.bind(Binding::Local(*definition), token, value);
+                Ok(())
+            }
+            _ => Err(Error::unimplemented(r#let)),
+        }
+    }
+
+    fn r#if(&mut self, r#if: &If) -> Result<Value, Error> {
+        let taken = self.evaluate(&r#if.condition)? != Value::Integer(0);
+        self.record(TraceEvent::Branch { taken });
+        if taken {
+            self.block(&r#if.body)
+        } else {
+            match &r#if.else_block {
+                Some(block) => self.block(block),
+                None => Ok(Value::UNIT),
+            }
+        }
+    }
+
+    fn unary(&mut self, unary: &Unary) -> Result<Value, Error> {
+        let span = &unary.operator_span;
+        let operand = self.evaluate(&unary.operand)?;
+        let value = unary_operation(
+            &unary.operator,
+            unary.operand.ty,
+            operand.clone(),
+            self.overflow,
+        )
+        .map_err(|fault| Error::runtime_error(fault.message(), Location::Known(span.clone())))?
+        .ok_or_else(|| Error::unimplemented(span))?;
+        self.record(TraceEvent::Operator {
+            operator: match unary.operator {
+                UnaryOperator::Not => "Not",
+                UnaryOperator::Plus => "UnaryPlus",
+                UnaryOperator::Minus => "UnaryMinus",
+            },
+            operands: vec![operand],
+            value: value.clone(),
+        });
+        Ok(value)
+    }
+
+    fn binary(&mut self, binary: &Binary) -> Result<Value, Error> {
+        let span = &binary.operator_span;
+        // both operands are evaluated, as the WASM backend does.
+        let lhs = self.evaluate(&binary.lhs)?;
+        let rhs = self.evaluate(&binary.rhs)?;
+        let operands = vec![lhs.clone(), rhs.clone()];
+        let value = binary_operation(binary.operator, lhs, rhs, self.overflow)
+            .map_err(|fault| Error::runtime_error(fault.message(), Location::Known(span.clone())))?
+            .ok_or_else(|| Error::unimplemented(span))?;
+        self.record(TraceEvent::Operator {
+            operator: binary.operator.trait_name(),
+            operands,
+            value: value.clone(),
+        });
+        Ok(value)
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+/// The function the statements of the snippets are checked and run in,
+/// which gives nothing back, so they are all expression statements.
+fn session_function(body: Vec<ast::Statement>) -> ast::FunctionDeclaration {
+    let span = body.span();
+    let token = |kind: TokenKind, content: &str| Token {
+        kind,
+        span: span.clone(),
+        content: content.to_owned(),
+    };
+    ast::FunctionDeclaration {
+        documentation: None,
+        attributes: Vec::new(),
+        is_pub: false,
+        is_extern: false,
+        name: token(TokenKind::IdentifierIdentifier(SESSION.to_owned()), SESSION),
+        generic_parameters: Vec::new(),
+        parameters: Vec::new(),
+        return_type: ast::Type::Tuple(Vec::new(), span.clone()),
+        body: Some(ast::Block {
+            curly_bracket_open_token: token(TokenKind::PunctuationLeftCurlyBracket, "{"),
+            body,
+            last_expression: None,
+            curly_bracket_close_token: token(TokenKind::PunctuationRightCurlyBracket, "}"),
+            id: NodeId::DUMMY,
+        }),
+        span: span.clone(),
+        id: NodeId::DUMMY,
+    }
+}
+
+/// The value of a literal of the type `ty`, which the literal alone doesn't
+/// tell for an integer or a decimal.
+fn literal_value(ty: TypeId, literal: &Literal, span: &Span) -> Result<Value, Error> {
+    Ok(match literal {
+        Literal::Integer(integer) => {
+            let value = integer.value();
+            let span = integer.0.span.clone();
+            if ty == TypeId::I64 {
+                value
+                    .and_then(|value| i64::try_from(value).ok())
+                    .map(Value::Long)
+                    .ok_or_else(|| Error::literal_out_of_range(span, "i64"))?
+            } else {
+                value
+                    .and_then(|value| i32::try_from(value).ok())
+                    .map(Value::Integer)
+                    .ok_or_else(|| Error::literal_out_of_range(span, "i32"))?
+            }
+        }
+        // the token is digits around a point, which both parse.
+        Literal::Decimal(token) => {
+            if ty == TypeId::F32 {
+                Value::Float(token.content.parse().expect("a decimal parses"))
+            } else {
+                Value::Double(token.content.parse().expect("a decimal parses"))
+            }
+        }
+        Literal::Boolean(token) => Value::Integer((token.content == "true") as i32),
+        Literal::String(_) => {
+            Value::String(literal.text().expect("a string literal has a text").into())
+        }
+        Literal::Character(_) => return Err(Error::unimplemented(span)),
+    })
+}
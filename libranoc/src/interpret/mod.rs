@@ -0,0 +1,9 @@
+mod interpreter;
+mod operation;
+mod trace;
+mod value;
+
+pub use interpreter::*;
+pub use operation::*;
+pub use trace::*;
+pub use value::*;
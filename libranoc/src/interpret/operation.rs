@@ -0,0 +1,200 @@
+use std::{
+    convert::TryFrom,
+    ops::{Add, Div, Mul, Sub},
+};
+
+use crate::{
+    codegen::Overflow,
+    core::TypeId,
+    hir::{BinaryOperator, UnaryOperator},
+};
+
+use super::Value;
+
+/// Why an operation on integers panics rather than giving a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The operation called by its verb overflows, which traps.
+    Overflow(&'static str),
+    DivisionByZero,
+    RemainderByZero,
+}
+
+impl Fault {
+    /// The message of the panic, the one the WASM backend gives.
+    pub fn message(self) -> String {
+        match self {
+            Fault::Overflow(verb) => format!("attempt to {} with overflow", verb),
+            Fault::DivisionByZero => "attempt to divide by zero".to_owned(),
+            Fault::RemainderByZero => {
+                "attempt to calculate the remainder with a divisor of zero".to_owned()
+            }
+        }
+    }
+}
+
+/// `operator` on `operand` of the type `ty`, or `None` if it has no meaning
+/// there.
+pub fn unary_operation(
+    operator: &UnaryOperator,
+    ty: TypeId,
+    operand: Value,
+    overflow: Overflow,
+) -> Result<Option<Value>, Fault> {
+    Ok(Some(match (operator, operand) {
+        (UnaryOperator::Plus, operand) => operand,
+        (UnaryOperator::Minus, Value::Integer(operand)) => {
+            Value::Integer(arithmetic(BinaryOperator::Subtract, 0, operand, overflow)?)
+        }
+        (UnaryOperator::Minus, Value::Long(operand)) => {
+            Value::Long(arithmetic(BinaryOperator::Subtract, 0, operand, overflow)?)
+        }
+        (UnaryOperator::Minus, Value::Float(operand)) => Value::Float(-operand),
+        (UnaryOperator::Minus, Value::Double(operand)) => Value::Double(-operand),
+        (UnaryOperator::Not, Value::Integer(operand)) if ty == TypeId::BOOL => {
+            Value::Integer((operand == 0) as i32)
+        }
+        (UnaryOperator::Not, Value::Integer(operand)) => Value::Integer(!operand),
+        (UnaryOperator::Not, Value::Long(operand)) => Value::Long(!operand),
+        _ => return Ok(None),
+    }))
+}
+
+/// `operator` on `lhs` and `rhs`, which are both evaluated, or `None` if it
+/// has no meaning on them. Booleans are integers here, so `&&` and `||` are
+/// `&` and `|` on them.
+pub fn binary_operation(
+    operator: BinaryOperator,
+    lhs: Value,
+    rhs: Value,
+    overflow: Overflow,
+) -> Result<Option<Value>, Fault> {
+    use BinaryOperator::*;
+    let boolean = |value: bool| Value::Integer(value as i32);
+    Ok(Some(match (lhs, rhs) {
+        (Value::Integer(lhs), Value::Integer(rhs)) => match operator {
+            EqualTo => boolean(lhs == rhs),
+            NotEqualTo => boolean(lhs != rhs),
+            GreaterThan => boolean(lhs > rhs),
+            LessThan => boolean(lhs < rhs),
+            GreaterThanOrEqualTo => boolean(lhs >= rhs),
+            LessThanOrEqualTo => boolean(lhs <= rhs),
+            BitAnd | LogicalAnd => Value::Integer(lhs & rhs),
+            BitOr | LogicalOr => Value::Integer(lhs | rhs),
+            BitXor => Value::Integer(lhs ^ rhs),
+            ShiftLeft => Value::Integer(lhs.wrapping_shl(rhs as u32)),
+            ShiftRight => Value::Integer(lhs.wrapping_shr(rhs as u32)),
+            RangeRightExclusive | RangeRightInclusive => return Ok(None),
+            operator => Value::Integer(arithmetic(operator, lhs, rhs, overflow)?),
+        },
+        (Value::Long(lhs), Value::Long(rhs)) => match operator {
+            EqualTo => boolean(lhs == rhs),
+            NotEqualTo => boolean(lhs != rhs),
+            GreaterThan => boolean(lhs > rhs),
+            LessThan => boolean(lhs < rhs),
+            GreaterThanOrEqualTo => boolean(lhs >= rhs),
+            LessThanOrEqualTo => boolean(lhs <= rhs),
+            BitAnd => Value::Long(lhs & rhs),
+            BitOr => Value::Long(lhs | rhs),
+            BitXor => Value::Long(lhs ^ rhs),
+            ShiftLeft => Value::Long(lhs.wrapping_shl(rhs as u32)),
+            ShiftRight => Value::Long(lhs.wrapping_shr(rhs as u32)),
+            LogicalAnd | LogicalOr | RangeRightExclusive | RangeRightInclusive => return Ok(None),
+            operator => Value::Long(arithmetic(operator, lhs, rhs, overflow)?),
+        },
+        (Value::Float(lhs), Value::Float(rhs)) => {
+            return Ok(decimal(operator, lhs, rhs, Value::Float))
+        }
+        (Value::Double(lhs), Value::Double(rhs)) => {
+            return Ok(decimal(operator, lhs, rhs, Value::Double))
+        }
+        (Value::String(lhs), Value::String(rhs)) => match operator {
+            Add => Value::String(format!("{}{}", lhs, rhs).into()),
+            EqualTo => boolean(lhs == rhs),
+            NotEqualTo => boolean(lhs != rhs),
+            _ => return Ok(None),
+        },
+        _ => return Ok(None),
+    }))
+}
+
+/// `+`, `-`, `*`, `/` or `%` of two integers of the type `T`, which does
+/// what `overflow` says on overflow. Dividing by zero panics whatever it
+/// says, and so does the remainder of the minimum by `-1` when it traps.
+fn arithmetic<T>(operator: BinaryOperator, lhs: T, rhs: T, overflow: Overflow) -> Result<T, Fault>
+where
+    T: Into<i128> + TryFrom<i128> + Bounded,
+{
+    let (lhs, rhs) = (lhs.into(), rhs.into());
+    let (exact, verb) = match operator {
+        BinaryOperator::Add => (lhs + rhs, "add"),
+        BinaryOperator::Subtract => (lhs - rhs, "subtract"),
+        BinaryOperator::Multiply => (lhs * rhs, "multiply"),
+        BinaryOperator::Divide if rhs == 0 => return Err(Fault::DivisionByZero),
+        BinaryOperator::Divide => (lhs / rhs, "divide"),
+        BinaryOperator::Remainder if rhs == 0 => return Err(Fault::RemainderByZero),
+        BinaryOperator::Remainder
+            if lhs == T::MIN.into() && rhs == -1 && overflow == Overflow::Trap =>
+        {
+            return Err(Fault::Overflow("calculate the remainder"))
+        }
+        BinaryOperator::Remainder => (lhs % rhs, "calculate the remainder"),
+        _ => unreachable!("not an arithmetic operator"),
+    };
+    if let Ok(value) = T::try_from(exact) {
+        return Ok(value);
+    }
+    match overflow {
+        Overflow::Trap => Err(Fault::Overflow(verb)),
+        Overflow::Wrap => Ok(T::wrap(exact)),
+        Overflow::Saturate if exact < 0 => Ok(T::MIN),
+        Overflow::Saturate => Ok(T::MAX),
+    }
+}
+
+/// The integer types the values are of.
+trait Bounded: Sized {
+    const MIN: Self;
+    const MAX: Self;
+    /// The low bits of `value`.
+    fn wrap(value: i128) -> Self;
+}
+
+impl Bounded for i32 {
+    const MIN: Self = i32::MIN;
+    const MAX: Self = i32::MAX;
+    fn wrap(value: i128) -> Self {
+        value as i32
+    }
+}
+
+impl Bounded for i64 {
+    const MIN: Self = i64::MIN;
+    const MAX: Self = i64::MAX;
+    fn wrap(value: i128) -> Self {
+        value as i64
+    }
+}
+
+/// The value of `lhs` and `rhs`, two decimals made values by `value`, by
+/// the arithmetic or the comparison `operator`.
+fn decimal<T>(operator: BinaryOperator, lhs: T, rhs: T, value: fn(T) -> Value) -> Option<Value>
+where
+    T: PartialOrd + Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T>,
+{
+    use BinaryOperator::*;
+    let boolean = |value: bool| Value::Integer(value as i32);
+    Some(match operator {
+        Add => value(lhs + rhs),
+        Subtract => value(lhs - rhs),
+        Multiply => value(lhs * rhs),
+        Divide => value(lhs / rhs),
+        EqualTo => boolean(lhs == rhs),
+        NotEqualTo => boolean(lhs != rhs),
+        GreaterThan => boolean(lhs > rhs),
+        LessThan => boolean(lhs < rhs),
+        GreaterThanOrEqualTo => boolean(lhs >= rhs),
+        LessThanOrEqualTo => boolean(lhs <= rhs),
+        _ => return None,
+    })
+}
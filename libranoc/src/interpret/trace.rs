@@ -0,0 +1,200 @@
+use std::{collections::VecDeque, fmt};
+
+use super::Value;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TraceEvent {
+    Call {
+        function: String,
+        arguments: Vec<Value>,
+    },
+    Return {
+        function: String,
+        value: Value,
+    },
+    Extern {
+        function: String,
+        arguments: Vec<Value>,
+        value: Value,
+    },
+    Bind {
+        name: String,
+        value: Value,
+    },
+    Branch {
+        taken: bool,
+    },
+    Operator {
+        operator: &'static str,
+        operands: Vec<Value>,
+        value: Value,
+    },
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |values: &Vec<Value>| {
+            values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            TraceEvent::Call {
+                function,
+                arguments,
+            } => write!(f, "call {}({})", function, join(arguments)),
+            TraceEvent::Return { function, value } => write!(f, "return {} = {}", function, value),
+            TraceEvent::Extern {
+                function,
+                arguments,
+                value,
+            } => write!(f, "extern {}({}) = {}", function, join(arguments), value),
+            TraceEvent::Bind { name, value } => write!(f, "bind {} = {}", name, value),
+            TraceEvent::Branch { taken } => {
+                write!(f, "branch {}", if *taken { "then" } else { "else" })
+            }
+            TraceEvent::Operator {
+                operator,
+                operands,
+                value,
+            } => write!(f, "{}({}) = {}", operator, join(operands), value),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TraceEntry {
+    pub step: u64,
+    pub depth: usize,
+    pub event: TraceEvent,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{:<6}{}{}",
+            self.step,
+            "  ".repeat(self.depth),
+            self.event
+        )
+    }
+}
+
+/// A bounded buffer of every state transition the interpreter made.
+///
+/// Once the buffer is full the oldest entries are evicted, so `step` numbers
+/// keep counting from the start of the run even if the beginning is gone.
+/// The cursor lets a debugger walk the recorded history back and forth.
+#[derive(Debug, Clone)]
+pub struct Trace {
+    capacity: usize,
+    entries: VecDeque<TraceEntry>,
+    next_step: u64,
+    cursor: Option<usize>,
+}
+
+impl Trace {
+    pub const DEFAULT_CAPACITY: usize = 4096;
+
+    pub fn new(capacity: usize) -> Self {
+        Trace {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+            next_step: 0,
+            cursor: None,
+        }
+    }
+
+    pub(crate) fn record(&mut self, depth: usize, event: TraceEvent) {
+        if self.capacity == 0 {
+            self.next_step += 1;
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+            self.cursor = self.cursor.map(|cursor| cursor.saturating_sub(1));
+        }
+        self.entries.push_back(TraceEntry {
+            step: self.next_step,
+            depth,
+            event,
+        });
+        self.next_step += 1;
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The number of transitions which no longer fit into the buffer.
+    pub fn evicted(&self) -> u64 {
+        self.next_step - self.entries.len() as u64
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &TraceEntry> {
+        self.entries.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.next_step = 0;
+        self.cursor = None;
+    }
+
+    pub fn current(&self) -> Option<&TraceEntry> {
+        self.cursor.and_then(|cursor| self.entries.get(cursor))
+    }
+
+    /// Moves the cursor one transition into the past, starting from the
+    /// latest one if the cursor has not been placed yet.
+    pub fn step_back(&mut self) -> Option<&TraceEntry> {
+        let cursor = match self.cursor {
+            Some(0) => return None,
+            Some(cursor) => cursor - 1,
+            None => self.entries.len().checked_sub(1)?,
+        };
+        self.cursor = Some(cursor);
+        self.entries.get(cursor)
+    }
+
+    pub fn step_forward(&mut self) -> Option<&TraceEntry> {
+        let cursor = self.cursor? + 1;
+        if cursor >= self.entries.len() {
+            return None;
+        }
+        self.cursor = Some(cursor);
+        self.entries.get(cursor)
+    }
+
+    pub fn seek(&mut self, step: u64) -> Option<&TraceEntry> {
+        let cursor = self.entries.iter().position(|entry| entry.step == step)?;
+        self.cursor = Some(cursor);
+        self.entries.get(cursor)
+    }
+
+    /// The results of the host calls in recording order, which is everything
+    /// needed to replay the run without touching the host again.
+    pub fn extern_results(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().filter_map(|entry| match &entry.event {
+            TraceEvent::Extern { value, .. } => Some(value),
+            _ => None,
+        })
+    }
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Trace::new(Trace::DEFAULT_CAPACITY)
+    }
+}
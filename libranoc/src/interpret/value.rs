@@ -0,0 +1,56 @@
+use std::{fmt, rc::Rc};
+
+/// Values the interpreter works with, one for each type of the typed IR it
+/// runs: `i32`, `i64`, `f32`, `f64`, `string` and the tuples.
+///
+/// Booleans are represented as `Integer(0)` and `Integer(1)` the same way the
+/// wasm backend lowers them to `i32`, so the interpreter and the
+/// compiled module agree.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Integer(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(Rc<str>),
+    Tuple(Vec<Value>),
+}
+
+impl Value {
+    pub const UNIT: Value = Value::Tuple(Vec::new());
+
+    pub fn as_integer(&self) -> Option<i32> {
+        match self {
+            Value::Integer(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Integer(0))
+            && !matches!(self, Value::Tuple(values) if values.is_empty())
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Integer(v) => write!(f, "{}", v),
+            Value::Long(v) => write!(f, "{}", v),
+            Value::Float(v) => write!(f, "{:?}", v),
+            Value::Double(v) => write!(f, "{:?}", v),
+            Value::String(text) => write!(f, "{:?}", text),
+            Value::Tuple(values) => {
+                write!(
+                    f,
+                    "({})",
+                    values
+                        .iter()
+                        .map(|value| format!("{},", value))
+                        .collect::<Vec<_>>()
+                        .join("")
+                )
+            }
+        }
+    }
+}
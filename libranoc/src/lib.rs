@@ -1,3 +1,8 @@
 pub mod codegen;
 pub mod core;
+pub mod hir;
+pub mod interpret;
+pub mod semantic;
 pub mod syntax;
+pub mod thir;
+pub mod typeck;
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use crate::{
+    core::{ast::*, Error},
+    syntax::Token,
+};
+
+/// A single name a `use` declaration brings into scope.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Import {
+    /// The item at `path`, visible as `name` which is either the last
+    /// segment of the path or the name given with `as`.
+    Single { path: Path, name: Token },
+    /// Every public item inside of `path`.
+    Glob { path: Path, star: Token },
+}
+
+fn expand_into(tree: &UseTree, parent: &[Token], imports: &mut Vec<Import>) {
+    let mut path = parent.to_vec();
+    path.extend(tree.prefix.0.iter().cloned());
+    match &tree.kind {
+        UseTreeKind::Simple(alias) => {
+            let name = alias
+                .clone()
+                .or_else(|| path.last().cloned())
+                .expect("a simple use tree always ends with a name");
+            imports.push(Import::Single {
+                path: Path(path),
+                name,
+            });
+        }
+        UseTreeKind::Glob(star) => imports.push(Import::Glob {
+            path: Path(path),
+            star: star.clone(),
+        }),
+        UseTreeKind::Nested(trees) => {
+            for tree in trees {
+                expand_into(tree, &path, imports);
+            }
+        }
+    }
+}
+
+/// Flattens `tree` into its leaves, so `a::{b, c::d as e, f::*}` becomes
+/// `a::b`, `a::c::d as e` and `a::f::*`.
+pub fn expand_use_tree(tree: &UseTree) -> Vec<Import> {
+    let mut imports = Vec::new();
+    expand_into(tree, &[], &mut imports);
+    imports
+}
+
+/// Every import of the `use` declarations at the top of `module`, in source
+/// order. Two imports binding the same name are rejected, no matter whether
+/// they come from the same declaration.
+pub fn resolve_imports(module: &Module) -> Result<Vec<Import>, Error> {
+    let mut imports = Vec::new();
+    for node in &module.nodes {
+        if let Node::Statement(Statement::Declaration(Declaration::UseDeclaration(declaration))) =
+            node
+        {
+            imports.extend(expand_use_tree(&declaration.tree));
+        }
+    }
+
+    let mut names: HashMap<&str, &Token> = HashMap::new();
+    for import in &imports {
+        if let Import::Single { name, .. } = import {
+            if let Some(before) = names.insert(&name.content, name) {
+                return Err(Error::redefined(
+                    name.content.clone(),
+                    before.span.clone(),
+                    name.span.clone(),
+                ));
+            }
+        }
+    }
+    Ok(imports)
+}
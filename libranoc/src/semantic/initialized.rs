@@ -0,0 +1,304 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    core::{arena::Idx, Error},
+    hir::{BinaryOperator, Block, Expression, ExpressionKind, Item, Let, Module, Statement},
+    semantic::{Definition, Resolution},
+    syntax::{Span, Spanned},
+};
+
+/// Why a local may still be unassigned where it is read.
+#[derive(Debug, Clone, PartialEq)]
+enum Missing {
+    /// Declared by the `let` with this span, without a value.
+    Declared(Span),
+    /// Not assigned in the branch with this span.
+    Branch(Span),
+    /// Not assigned when the condition with this span evaluates to `value`.
+    Condition { span: Span, value: bool },
+}
+
+impl Missing {
+    fn span(&self) -> Span {
+        match self {
+            Missing::Declared(span) | Missing::Branch(span) | Missing::Condition { span, .. } => {
+                span.clone()
+            }
+        }
+    }
+}
+
+impl fmt::Display for Missing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Missing::Declared(_) => write!(f, "declared here without a value"),
+            Missing::Branch(_) => write!(f, "not assigned in this branch"),
+            Missing::Condition { value, .. } => write!(f, "not assigned when this is {}", value),
+        }
+    }
+}
+
+/// What is known at a point of a function: whether it is reached at all, and
+/// the locals which may not be assigned yet.
+#[derive(Debug, Clone)]
+struct State {
+    reachable: bool,
+    uninitialized: HashMap<Idx<Definition>, Missing>,
+}
+
+impl State {
+    fn new() -> Self {
+        State {
+            reachable: true,
+            uninitialized: HashMap::new(),
+        }
+    }
+}
+
+/// Reports every read of a local declared by a `let` without a value which
+/// some path to it doesn't assign, naming the branch leaving it unassigned.
+/// Each local is reported once, at the first of such reads.
+pub fn check_initialized(module: &Module, resolution: &Resolution) -> Vec<Error> {
+    let mut checker = InitializationChecker {
+        resolution,
+        state: State::new(),
+        errors: Vec::new(),
+    };
+    checker.check_statements(&module.statements);
+    checker.errors
+}
+
+struct InitializationChecker<'a> {
+    resolution: &'a Resolution,
+    state: State,
+    errors: Vec<Error>,
+}
+
+impl InitializationChecker<'_> {
+    fn check_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::Item(item) => self.check_item(item),
+                Statement::Let(r#let) => self.check_let(r#let),
+                Statement::Expression(expression) => self.check_expression(expression),
+            }
+        }
+    }
+
+    /// Checks `item` on its own, as a function never sees the locals of the
+    /// one it is written in.
+    fn check_item(&mut self, item: &Item) {
+        let state = std::mem::replace(&mut self.state, State::new());
+        match item {
+            Item::Function(function) => {
+                if let Some(body) = &function.body {
+                    self.check_block(body);
+                }
+            }
+            Item::Module(declaration) => {
+                if let Some(module) = &declaration.body {
+                    self.check_statements(&module.statements);
+                }
+            }
+            Item::Use(_) => {}
+        }
+        self.state = state;
+    }
+
+    fn check_let(&mut self, r#let: &Let) {
+        match &r#let.value {
+            Some(value) => self.check_expression(value),
+            None => {
+                if let Some(local) = self.resolution.lets.get(r#let.id) {
+                    self.state
+                        .uninitialized
+                        .insert(*local, Missing::Declared(r#let.span()));
+                }
+            }
+        }
+    }
+
+    fn check_block(&mut self, block: &Block) {
+        self.check_statements(&block.statements);
+        if let Some(value) = &block.value {
+            self.check_expression(value);
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Expression) {
+        match &expression.kind {
+            ExpressionKind::Name(_) => {
+                let local = match self.resolution.references.get(expression.id) {
+                    Some(local) => *local,
+                    None => return,
+                };
+                if !self.state.reachable {
+                    return;
+                }
+                if let Some(missing) = self.state.uninitialized.remove(&local) {
+                    self.errors.push(Error::possibly_uninitialized(
+                        &self.resolution.definitions[local].name.content,
+                        expression.span(),
+                        missing.span(),
+                        missing,
+                    ));
+                }
+            }
+            ExpressionKind::Assign(assign) => match &assign.target.kind {
+                ExpressionKind::Name(_) => {
+                    self.check_expression(&assign.value);
+                    if let Some(local) = self.resolution.references.get(assign.target.id) {
+                        self.state.uninitialized.remove(local);
+                    }
+                }
+                _ => {
+                    self.check_expression(&assign.target);
+                    self.check_expression(&assign.value);
+                }
+            },
+            ExpressionKind::If(r#if) => {
+                self.check_expression(&r#if.condition);
+                let before = self.state.clone();
+                self.check_block(&r#if.body);
+                let body = std::mem::replace(&mut self.state, before.clone());
+                let else_missing = match &r#if.else_block {
+                    Some(else_block) => {
+                        self.check_block(else_block);
+                        Missing::Branch(else_block.span())
+                    }
+                    None => Missing::Condition {
+                        span: r#if.condition.span(),
+                        value: false,
+                    },
+                };
+                let else_state = std::mem::replace(&mut self.state, before.clone());
+                self.state = join(
+                    &before,
+                    vec![
+                        (body, Missing::Branch(r#if.body.span())),
+                        (else_state, else_missing),
+                    ],
+                );
+            }
+            ExpressionKind::Match(r#match) => {
+                self.check_expression(&r#match.scrutinee);
+                let before = self.state.clone();
+                let mut arms = Vec::new();
+                for arm in &r#match.arms {
+                    self.state = before.clone();
+                    if let Some(guard) = &arm.guard {
+                        self.check_expression(guard);
+                    }
+                    self.check_expression(&arm.body);
+                    arms.push((self.state.clone(), Missing::Branch(arm.body.span())));
+                }
+                self.state = join(&before, arms);
+            }
+            ExpressionKind::Binary(binary)
+                if matches!(
+                    binary.operator,
+                    BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr
+                ) =>
+            {
+                self.check_expression(&binary.lhs);
+                let before = self.state.clone();
+                self.check_expression(&binary.rhs);
+                let rhs = std::mem::replace(&mut self.state, before.clone());
+                // the rhs is skipped when `||` finds the lhs true, and `&&`
+                // false.
+                let skipped = Missing::Condition {
+                    span: binary.lhs.span(),
+                    value: binary.operator == BinaryOperator::LogicalOr,
+                };
+                self.state = join(
+                    &before,
+                    vec![
+                        (rhs, Missing::Branch(binary.rhs.span())),
+                        (before.clone(), skipped),
+                    ],
+                );
+            }
+            ExpressionKind::Binary(binary) => {
+                self.check_expression(&binary.lhs);
+                self.check_expression(&binary.rhs);
+            }
+            ExpressionKind::Closure(closure) => {
+                // the body runs whenever the closure is called, which leaves
+                // the locals around it as they are.
+                let state = self.state.clone();
+                self.check_expression(&closure.body);
+                self.state = state;
+            }
+            ExpressionKind::Todo(_) => self.state.reachable = false,
+            ExpressionKind::Intrinsic(_) => {}
+            ExpressionKind::Block(block) => self.check_block(block),
+            ExpressionKind::Unsafe(r#unsafe) => self.check_block(&r#unsafe.block),
+            ExpressionKind::Array(elements) | ExpressionKind::Tuple(elements) => {
+                elements
+                    .iter()
+                    .for_each(|element| self.check_expression(element));
+            }
+            ExpressionKind::Init(init) => {
+                init.fields
+                    .iter()
+                    .for_each(|(_, value)| self.check_expression(value));
+            }
+            ExpressionKind::Unary(unary) => self.check_expression(&unary.operand),
+            ExpressionKind::Field(field) => self.check_expression(&field.operand),
+            ExpressionKind::Instantiate(instantiate) => self.check_expression(&instantiate.operand),
+            ExpressionKind::Call(call) => {
+                self.check_expression(&call.callee);
+                call.arguments
+                    .iter()
+                    .for_each(|argument| self.check_expression(argument));
+            }
+            ExpressionKind::Index(index) => {
+                self.check_expression(&index.operand);
+                index
+                    .indices
+                    .iter()
+                    .for_each(|index| self.check_expression(index));
+            }
+            ExpressionKind::Literal(_) | ExpressionKind::Path(_) | ExpressionKind::Null => {}
+        }
+    }
+}
+
+/// The state after the `branches` starting from `before`, each with why a
+/// local it leaves unassigned is. The ones which aren't reached are left
+/// out, and a local unassigned in only some of the others is reported for
+/// the first of those, unless a branch has a reason of its own for it, like
+/// an inner branch.
+fn join(before: &State, branches: Vec<(State, Missing)>) -> State {
+    let reached: Vec<_> = branches
+        .into_iter()
+        .filter(|(state, _)| state.reachable)
+        .collect();
+    if reached.is_empty() {
+        return State {
+            reachable: false,
+            uninitialized: before.uninitialized.clone(),
+        };
+    }
+    let mut uninitialized = HashMap::new();
+    for (state, missing) in &reached {
+        for (local, reason) in &state.uninitialized {
+            if uninitialized.contains_key(local) {
+                continue;
+            }
+            let everywhere = reached
+                .iter()
+                .all(|(state, _)| state.uninitialized.contains_key(local));
+            let joined = if before.uninitialized.get(local) != Some(reason) || everywhere {
+                reason.clone()
+            } else {
+                missing.clone()
+            };
+            uninitialized.insert(*local, joined);
+        }
+    }
+    State {
+        reachable: true,
+        uninitialized,
+    }
+}
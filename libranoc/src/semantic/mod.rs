@@ -0,0 +1,15 @@
+mod import;
+mod initialized;
+mod module;
+mod resolve;
+mod symbol;
+mod todo;
+mod unused;
+
+pub use import::*;
+pub use initialized::*;
+pub use module::*;
+pub use resolve::*;
+pub use symbol::*;
+pub use todo::*;
+pub use unused::*;
@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use crate::{core::ast::*, syntax::Token};
+
+/// A `mod name;` whose items the module loader has to read from a file.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LoadRequest {
+    /// The names of the inline modules the declaration is in, outermost
+    /// first.
+    pub parents: Vec<String>,
+    pub name: Token,
+}
+
+impl LoadRequest {
+    /// Where the file is relative to the directory of the file making the
+    /// request, `a/b/name.rano` for a declaration inside `mod a { mod b {`.
+    pub fn relative_path(&self) -> PathBuf {
+        let mut path: PathBuf = self.parents.iter().collect();
+        path.push(format!("{}.rano", self.name.content));
+        path
+    }
+}
+
+fn collect_requests(module: &Module, parents: &mut Vec<String>, requests: &mut Vec<LoadRequest>) {
+    for node in &module.nodes {
+        if let Node::Statement(Statement::Declaration(Declaration::ModuleDeclaration(
+            declaration,
+        ))) = node
+        {
+            match &declaration.body {
+                ModuleBody::Inline { module, .. } => {
+                    parents.push(declaration.name.content.clone());
+                    collect_requests(module, parents, requests);
+                    parents.pop();
+                }
+                ModuleBody::File(_) => requests.push(LoadRequest {
+                    parents: parents.clone(),
+                    name: declaration.name.clone(),
+                }),
+            }
+        }
+    }
+}
+
+/// Every `mod name;` in `module` and in the modules declared inline in it,
+/// in source order.
+pub fn load_requests(module: &Module) -> Vec<LoadRequest> {
+    let mut requests = Vec::new();
+    collect_requests(module, &mut Vec::new(), &mut requests);
+    requests
+}
@@ -0,0 +1,626 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        arena::{Arena, Idx},
+        ast::{Name, Path, Pattern},
+        Error, NodeId, NodeMap,
+    },
+    hir::*,
+    semantic::{expand_use_tree, Import},
+    syntax::{ExpansionId, Span, Spanned, Token, TokenKind},
+};
+
+/// The functions codegen lowers by itself, which are in scope everywhere
+/// unless something of the same name shadows them.
+pub const BUILTINS: &[&str] = &[
+    "spawn",
+    "join",
+    "scope",
+    "channel",
+    "send",
+    "receive",
+    "select",
+    "share",
+    "close",
+    "load8",
+    "load32",
+    "store8",
+    "store32",
+    "memory_size",
+    "memory_grow",
+    "panic",
+];
+
+/// A region of the source where names may be defined: the builtins at the
+/// root, then the modules, functions, blocks, match arms and closures.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Scope {
+    pub parent: Option<Idx<Scope>>,
+    pub span: Span,
+    names: HashMap<String, Idx<Definition>>,
+    /// The paths of the glob imports, whose names are only known once the
+    /// modules they import from are loaded.
+    globs: Vec<Path>,
+}
+
+impl Scope {
+    fn new(parent: Option<Idx<Scope>>, span: Span) -> Self {
+        Scope {
+            parent,
+            span,
+            names: HashMap::new(),
+            globs: Vec::new(),
+        }
+    }
+
+    /// What `name` refers to in this scope itself, without its parents.
+    pub fn get(&self, name: &str) -> Option<Idx<Definition>> {
+        self.names.get(name).copied()
+    }
+
+    /// The names defined in this scope in no particular order, each with the
+    /// definition last given to it. A local or parameter a macro expansion
+    /// made is named `name#expansion`, which nothing written can refer to.
+    pub fn names(&self) -> impl Iterator<Item = (&str, Idx<Definition>)> {
+        self.names
+            .iter()
+            .map(|(name, definition)| (name.as_str(), *definition))
+    }
+
+    pub fn globs(&self) -> &[Path] {
+        &self.globs
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Definition {
+    pub name: Token,
+    pub kind: DefinitionKind,
+    /// The scope the definition is in.
+    pub scope: Idx<Scope>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum DefinitionKind {
+    Builtin,
+    Function {
+        id: NodeId,
+        is_extern: bool,
+    },
+    /// A module with the scope of its items, unless they are in a file of
+    /// their own.
+    Module {
+        id: NodeId,
+        scope: Option<Idx<Scope>>,
+    },
+    /// A name brought in by `use`, with the definition it stands for if the
+    /// path leads to one in this file.
+    Import {
+        path: Path,
+        target: Option<Idx<Definition>>,
+    },
+    /// The `index`th parameter of the function with the id `function`.
+    Parameter {
+        function: NodeId,
+        index: u32,
+    },
+    /// A variable bound by a pattern, like the one of a match arm or a
+    /// closure parameter.
+    Local,
+}
+
+impl DefinitionKind {
+    /// Whether the definition is an item, which two of in the same scope
+    /// can't have the same name, while a local shadows whatever came before.
+    pub fn is_item(&self) -> bool {
+        matches!(
+            self,
+            DefinitionKind::Function { .. }
+                | DefinitionKind::Module { .. }
+                | DefinitionKind::Import { .. }
+        )
+    }
+}
+
+/// The scope tree of a module and what each of its names refers to.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Resolution {
+    pub scopes: Arena<Scope>,
+    pub definitions: Arena<Definition>,
+    /// The definition each name and path expression refers to, by the id of
+    /// the expression. A field of a module like `m.f` refers to the item as
+    /// well as its name does.
+    pub references: NodeMap<Idx<Definition>>,
+    /// Where each of the `references` is written, which is only the name of
+    /// the item for `m.f` and the last segment for a path.
+    pub reference_spans: NodeMap<Span>,
+    /// The definition of each function and module, by the id of the item.
+    pub items: NodeMap<Idx<Definition>>,
+    /// The local each `let` binds, by the id of the `let`.
+    pub lets: NodeMap<Idx<Definition>>,
+    /// The locals the parameters of each closure bind, by the id of the
+    /// closure, with `None` for one whose pattern binds none.
+    pub closure_parameters: NodeMap<Vec<Option<Idx<Definition>>>>,
+    /// Left out of the cached typed IR, which is only kept without any.
+    #[serde(skip)]
+    pub errors: Vec<Error>,
+}
+
+impl Resolution {
+    /// What the name or path expression `id` refers to.
+    pub fn reference(&self, id: NodeId) -> Option<&Definition> {
+        self.references
+            .get(id)
+            .map(|definition| &self.definitions[*definition])
+    }
+
+    /// `definition`, or what it imports if it is an import leading to a
+    /// definition in this file.
+    pub fn target(&self, definition: Idx<Definition>) -> Idx<Definition> {
+        let mut definition = definition;
+        // the imports never form a cycle, as one leading back to itself is
+        // left unresolved.
+        while let DefinitionKind::Import {
+            target: Some(target),
+            ..
+        } = &self.definitions[definition].kind
+        {
+            definition = *target;
+        }
+        definition
+    }
+}
+
+/// Builds the scope tree of `module` and resolves every name and path in it.
+/// A name which isn't defined is an error unless a glob import could bring
+/// it in, as is defining an item twice in the same scope.
+pub fn resolve(module: &Module) -> Resolution {
+    let mut scopes = Arena::new();
+    let root = scopes.alloc(Scope::new(None, Span::EMPTY));
+    let mut resolver = Resolver {
+        resolution: Resolution {
+            scopes,
+            definitions: Arena::new(),
+            references: NodeMap::new(),
+            reference_spans: NodeMap::new(),
+            items: NodeMap::new(),
+            lets: NodeMap::new(),
+            closure_parameters: NodeMap::new(),
+            errors: Vec::new(),
+        },
+        scope: root,
+    };
+    for builtin in BUILTINS {
+        let name = Token {
+            kind: TokenKind::IdentifierIdentifier(builtin.to_string()),
+            span: Span::EMPTY,
+            content: builtin.to_string(),
+        };
+        resolver.define(name, DefinitionKind::Builtin);
+    }
+
+    resolver.enter_scope(Span::EMPTY);
+    resolver.define_items(&module.statements);
+    resolver.resolve_imports();
+    resolver.resolve_statements(&module.statements);
+    resolver.resolution
+}
+
+/// What a local or parameter `name` is called in its scope: the name made
+/// by a macro expansion is told apart from the same name written elsewhere by
+/// the expansion, as `#` is never part of a name.
+fn hygienic_name(name: &Token) -> String {
+    if name.span.expansion == ExpansionId::ROOT {
+        name.content.clone()
+    } else {
+        format!("{}#{}", name.content, name.span.expansion.0)
+    }
+}
+
+struct Resolver {
+    resolution: Resolution,
+    scope: Idx<Scope>,
+}
+
+impl Resolver {
+    /// Enters a new scope inside of the current one, returning the current
+    /// one to leave back to.
+    fn enter_scope(&mut self, span: Span) -> Idx<Scope> {
+        let parent = self.scope;
+        self.scope = self.resolution.scopes.alloc(Scope::new(Some(parent), span));
+        parent
+    }
+
+    fn leave_scope(&mut self, parent: Idx<Scope>) {
+        self.scope = parent;
+    }
+
+    fn define(&mut self, name: Token, kind: DefinitionKind) -> Idx<Definition> {
+        let content = match kind {
+            DefinitionKind::Local | DefinitionKind::Parameter { .. } => hygienic_name(&name),
+            _ => name.content.clone(),
+        };
+        let scope = &self.resolution.scopes[self.scope];
+        if let Some(before) = scope.get(&content) {
+            let before = &self.resolution.definitions[before];
+            if (kind.is_item() && before.kind.is_item())
+                || matches!(kind, DefinitionKind::Parameter { .. })
+            {
+                self.resolution.errors.push(Error::redefined(
+                    name.content.clone(),
+                    before.name.span.clone(),
+                    name.span.clone(),
+                ));
+            }
+        }
+        let definition = self.resolution.definitions.alloc(Definition {
+            name,
+            kind,
+            scope: self.scope,
+        });
+        self.resolution.scopes[self.scope]
+            .names
+            .insert(content, definition);
+        definition
+    }
+
+    /// What `name` refers to in `scope` or its parents, leaving out `skip`.
+    fn lookup(
+        &self,
+        scope: Idx<Scope>,
+        name: &str,
+        skip: Option<Idx<Definition>>,
+    ) -> Option<Idx<Definition>> {
+        let mut scope = Some(scope);
+        while let Some(current) = scope {
+            let current = &self.resolution.scopes[current];
+            match current.get(name) {
+                Some(definition) if Some(definition) != skip => return Some(definition),
+                _ => scope = current.parent,
+            }
+        }
+        None
+    }
+
+    /// What the name expression `name` refers to. A name a macro expansion
+    /// made sees the locals and parameters of the same expansion and the
+    /// items, but not the locals around the invocation, which keeps them
+    /// apart when they are called the same.
+    fn lookup_name(&self, name: &Token) -> Option<Idx<Definition>> {
+        if name.span.expansion == ExpansionId::ROOT {
+            return self.lookup(self.scope, &name.content, None);
+        }
+        self.lookup(self.scope, &hygienic_name(name), None)
+            .or_else(|| {
+                self.lookup(self.scope, &name.content, None)
+                    .filter(|definition| {
+                        !matches!(
+                            self.resolution.definitions[*definition].kind,
+                            DefinitionKind::Local | DefinitionKind::Parameter { .. }
+                        )
+                    })
+            })
+    }
+
+    /// Follows `path` from `scope` through the modules its segments name.
+    fn lookup_path(
+        &self,
+        scope: Idx<Scope>,
+        path: &Path,
+        skip: Option<Idx<Definition>>,
+    ) -> Option<Idx<Definition>> {
+        let (first, rest) = path.0.split_first()?;
+        let mut definition = self.lookup(scope, &first.content, skip)?;
+        for segment in rest {
+            definition = self.member(definition, &segment.content)?;
+        }
+        Some(definition)
+    }
+
+    /// The item called `name` inside of `module`, if it is a module.
+    fn member(&self, module: Idx<Definition>, name: &str) -> Option<Idx<Definition>> {
+        match &self.resolution.definitions[self.resolution.target(module)].kind {
+            DefinitionKind::Module {
+                scope: Some(scope), ..
+            } => self.resolution.scopes[*scope].get(name),
+            _ => None,
+        }
+    }
+
+    fn refer(&mut self, id: NodeId, span: Span, definition: Idx<Definition>) {
+        self.resolution.references.insert(id, definition);
+        self.resolution.reference_spans.insert(id, span);
+    }
+
+    /// Whether a glob import is visible from the current scope.
+    fn has_glob(&self) -> bool {
+        let mut scope = Some(self.scope);
+        while let Some(current) = scope {
+            let current = &self.resolution.scopes[current];
+            if !current.globs.is_empty() {
+                return true;
+            }
+            scope = current.parent;
+        }
+        false
+    }
+
+    fn undefined(&mut self, name: impl std::fmt::Display, span: Span) {
+        if !self.has_glob() {
+            self.resolution
+                .errors
+                .push(Error::undefined_symbol(name, span));
+        }
+    }
+
+    /// Defines the items of `statements` before any of them is resolved, so
+    /// an item can be used above where it is declared.
+    fn define_items(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            let item = match statement {
+                Statement::Item(item) => item,
+                Statement::Let(_) | Statement::Expression(_) => continue,
+            };
+            match item {
+                Item::Function(function) => {
+                    let definition = self.define(
+                        function.name.clone(),
+                        DefinitionKind::Function {
+                            id: function.id,
+                            is_extern: function.is_extern,
+                        },
+                    );
+                    self.resolution.items.insert(function.id, definition);
+                }
+                Item::Module(module) => {
+                    let scope = module.body.as_ref().map(|body| {
+                        let parent = self.enter_scope(module.span.clone());
+                        self.define_items(&body.statements);
+                        let scope = self.scope;
+                        self.leave_scope(parent);
+                        scope
+                    });
+                    let definition = self.define(
+                        module.name.clone(),
+                        DefinitionKind::Module {
+                            id: module.id,
+                            scope,
+                        },
+                    );
+                    self.resolution.items.insert(module.id, definition);
+                }
+                Item::Use(declaration) => {
+                    for import in expand_use_tree(&declaration.tree) {
+                        match import {
+                            Import::Single { path, name } => {
+                                self.define(name, DefinitionKind::Import { path, target: None });
+                            }
+                            Import::Glob { path, .. } => {
+                                self.resolution.scopes[self.scope].globs.push(path)
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds the targets of the imports not resolved yet. An import can go
+    /// through another one, so this repeats until no more are found.
+    fn resolve_imports(&mut self) {
+        loop {
+            let unresolved: Vec<_> = self
+                .resolution
+                .definitions
+                .iter()
+                .filter_map(|(definition, Definition { kind, scope, .. })| match kind {
+                    DefinitionKind::Import { path, target: None } => {
+                        Some((definition, *scope, path.clone()))
+                    }
+                    _ => None,
+                })
+                .collect();
+            let mut found_any = false;
+            for (definition, scope, path) in unresolved {
+                let found = match self.lookup_path(scope, &path, Some(definition)) {
+                    // an import leading back to itself is left unresolved.
+                    Some(found) if self.resolution.target(found) != definition => found,
+                    _ => continue,
+                };
+                if let DefinitionKind::Import { target, .. } =
+                    &mut self.resolution.definitions[definition].kind
+                {
+                    *target = Some(found);
+                    found_any = true;
+                }
+            }
+            if !found_any {
+                break;
+            }
+        }
+    }
+
+    fn resolve_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::Item(item) => self.resolve_item(item),
+                Statement::Let(r#let) => {
+                    // the value can't see the local it is given to.
+                    if let Some(value) = &r#let.value {
+                        self.resolve_expression(value);
+                    }
+                    if let Some(definition) = self.bind(&r#let.pattern) {
+                        self.resolution.lets.insert(r#let.id, definition);
+                    }
+                }
+                Statement::Expression(expression) => self.resolve_expression(expression),
+            }
+        }
+    }
+
+    fn resolve_item(&mut self, item: &Item) {
+        match item {
+            Item::Function(function) => {
+                let parent = self.enter_scope(function.span.clone());
+                for (index, (pattern, _)) in function.parameters.iter().enumerate() {
+                    if let Pattern::Slot(Name::Ident(name)) = pattern {
+                        self.define(
+                            name.clone(),
+                            DefinitionKind::Parameter {
+                                function: function.id,
+                                index: index as u32,
+                            },
+                        );
+                    }
+                }
+                if let Some(body) = &function.body {
+                    self.resolve_block(body);
+                }
+                self.leave_scope(parent);
+            }
+            Item::Module(module) => {
+                let scope =
+                    self.resolution.items.get(module.id).and_then(|definition| {
+                        match &self.resolution.definitions[*definition].kind {
+                            DefinitionKind::Module { scope, .. } => *scope,
+                            _ => None,
+                        }
+                    });
+                if let (Some(scope), Some(body)) = (scope, &module.body) {
+                    let parent = self.scope;
+                    self.scope = scope;
+                    self.resolve_statements(&body.statements);
+                    self.leave_scope(parent);
+                }
+            }
+            Item::Use(_) => {}
+        }
+    }
+
+    fn resolve_block(&mut self, block: &Block) {
+        let parent = self.enter_scope(block.span.clone());
+        self.define_items(&block.statements);
+        self.resolve_imports();
+        self.resolve_statements(&block.statements);
+        if let Some(value) = &block.value {
+            self.resolve_expression(value);
+        }
+        self.leave_scope(parent);
+    }
+
+    /// Binds the name of `pattern` in the current scope.
+    fn bind(&mut self, pattern: &Pattern) -> Option<Idx<Definition>> {
+        match pattern {
+            Pattern::Slot(Name::Ident(name)) => {
+                Some(self.define(name.clone(), DefinitionKind::Local))
+            }
+            _ => None,
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: &Expression) {
+        match &expression.kind {
+            ExpressionKind::Literal(_)
+            | ExpressionKind::Null
+            | ExpressionKind::Todo(_)
+            | ExpressionKind::Intrinsic(_) => {}
+            ExpressionKind::Name(Name::Ident(name)) => match self.lookup_name(name) {
+                Some(definition) => self.refer(expression.id, name.span.clone(), definition),
+                None => self.undefined(&name.content, name.span.clone()),
+            },
+            ExpressionKind::Name(Name::Placeholder(_)) => {}
+            ExpressionKind::Path(path) => match self.lookup_path(self.scope, path, None) {
+                Some(definition) => {
+                    let last = path.0.last().expect("a resolved path isn't empty");
+                    self.refer(expression.id, last.span.clone(), definition)
+                }
+                None => self.undefined(path, path.span()),
+            },
+            ExpressionKind::Array(elements) | ExpressionKind::Tuple(elements) => {
+                for element in elements {
+                    self.resolve_expression(element);
+                }
+            }
+            ExpressionKind::Init(init) => {
+                for (_, value) in &init.fields {
+                    self.resolve_expression(value);
+                }
+            }
+            ExpressionKind::Unary(unary) => self.resolve_expression(&unary.operand),
+            ExpressionKind::Binary(binary) => {
+                self.resolve_expression(&binary.lhs);
+                self.resolve_expression(&binary.rhs);
+            }
+            ExpressionKind::Assign(assign) => {
+                self.resolve_expression(&assign.target);
+                self.resolve_expression(&assign.value);
+            }
+            ExpressionKind::Field(field) => {
+                self.resolve_expression(&field.operand);
+                // `m.f` is the item `f` inside of the module `m`, while any
+                // other field is looked up in the type of the operand.
+                let module = self.resolution.references.get(field.operand.id).copied();
+                if let (Some(module), ExpressionKind::Name(Name::Ident(name))) =
+                    (module, &field.field.kind)
+                {
+                    if let Some(member) = self.member(module, &name.content) {
+                        self.refer(field.field.id, name.span.clone(), member);
+                        self.resolution.references.insert(expression.id, member);
+                    }
+                }
+            }
+            ExpressionKind::Call(call) => {
+                self.resolve_expression(&call.callee);
+                for argument in &call.arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+            ExpressionKind::Instantiate(instantiate) => {
+                self.resolve_expression(&instantiate.operand)
+            }
+            ExpressionKind::Index(index) => {
+                self.resolve_expression(&index.operand);
+                for index in &index.indices {
+                    self.resolve_expression(index);
+                }
+            }
+            ExpressionKind::If(r#if) => {
+                self.resolve_expression(&r#if.condition);
+                self.resolve_block(&r#if.body);
+                if let Some(else_block) = &r#if.else_block {
+                    self.resolve_block(else_block);
+                }
+            }
+            ExpressionKind::Match(r#match) => {
+                self.resolve_expression(&r#match.scrutinee);
+                for arm in &r#match.arms {
+                    let parent = self.enter_scope(arm.pattern.span().joined(&arm.body.span));
+                    self.bind(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        self.resolve_expression(guard);
+                    }
+                    self.resolve_expression(&arm.body);
+                    self.leave_scope(parent);
+                }
+            }
+            ExpressionKind::Closure(closure) => {
+                let parent = self.enter_scope(expression.span.clone());
+                let parameters = closure
+                    .parameters
+                    .iter()
+                    .map(|(pattern, _)| self.bind(pattern))
+                    .collect();
+                self.resolution
+                    .closure_parameters
+                    .insert(expression.id, parameters);
+                self.resolve_expression(&closure.body);
+                self.leave_scope(parent);
+            }
+            ExpressionKind::Block(block) => self.resolve_block(block),
+            ExpressionKind::Unsafe(r#unsafe) => self.resolve_block(&r#unsafe.block),
+        }
+    }
+}
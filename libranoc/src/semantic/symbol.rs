@@ -0,0 +1,112 @@
+use crate::{
+    core::{arena::Idx, ast::Module},
+    hir::{self, NULLABLE_RECEIVER},
+    semantic::{resolve, Definition, DefinitionKind, Resolution},
+    syntax::Span,
+};
+
+/// Where a symbol is written in the source, either as the name of its
+/// definition or as a reference to it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Occurrence {
+    pub span: Span,
+    pub definition: Idx<Definition>,
+    pub is_definition: bool,
+}
+
+/// The definitions of a module and where each of them is used, looked up by
+/// where they are written, for the tools working on the source like an
+/// editor going to a definition or renaming a symbol.
+#[derive(Debug)]
+pub struct SymbolTable {
+    resolution: Resolution,
+    /// In source order.
+    occurrences: Vec<Occurrence>,
+}
+
+impl SymbolTable {
+    /// Resolves `module` to build its symbol table.
+    pub fn build(module: Module) -> SymbolTable {
+        SymbolTable::new(resolve(&hir::lower(module)))
+    }
+
+    pub fn new(resolution: Resolution) -> SymbolTable {
+        // the builtins aren't written anywhere, and the names a desugaring
+        // made up aren't written where they seem to be.
+        let is_written = |definition: &Definition| {
+            !matches!(definition.kind, DefinitionKind::Builtin)
+                && definition.name.content != NULLABLE_RECEIVER
+        };
+        let mut occurrences: Vec<_> = resolution
+            .definitions
+            .iter()
+            .filter(|(_, definition)| is_written(definition))
+            .map(|(definition, Definition { name, .. })| Occurrence {
+                span: name.span.clone(),
+                definition,
+                is_definition: true,
+            })
+            .chain(
+                resolution
+                    .reference_spans
+                    .iter()
+                    .filter_map(|(id, span)| Some((span, *resolution.references.get(id)?)))
+                    .filter(|(_, definition)| is_written(&resolution.definitions[*definition]))
+                    .map(|(span, definition)| Occurrence {
+                        span: span.clone(),
+                        definition,
+                        is_definition: false,
+                    }),
+            )
+            .collect();
+        occurrences
+            .sort_by_key(|occurrence| (occurrence.span.range.start, occurrence.span.range.end));
+        SymbolTable {
+            resolution,
+            occurrences,
+        }
+    }
+
+    pub fn resolution(&self) -> &Resolution {
+        &self.resolution
+    }
+
+    pub fn definition(&self, definition: Idx<Definition>) -> &Definition {
+        &self.resolution.definitions[definition]
+    }
+
+    /// Every symbol written in the source, in source order.
+    pub fn occurrences(&self) -> &[Occurrence] {
+        &self.occurrences
+    }
+
+    /// The innermost occurrence covering the whole of `span`.
+    pub fn occurrence_at(&self, span: &Span) -> Option<&Occurrence> {
+        self.occurrences
+            .iter()
+            .filter(|occurrence| {
+                occurrence.span.range.start <= span.range.start
+                    && span.range.end <= occurrence.span.range.end
+            })
+            .min_by_key(|occurrence| occurrence.span.range.len())
+    }
+
+    /// The definition of the symbol written at `span`, going through the
+    /// imports to what they import when it is in this file.
+    pub fn find_definition(&self, span: &Span) -> Option<&Definition> {
+        let occurrence = self.occurrence_at(span)?;
+        Some(self.definition(self.resolution.target(occurrence.definition)))
+    }
+
+    /// Where `definition` is referred to, in source order. A name used
+    /// through an import refers to the import rather than to what it
+    /// imports, so these are only the ones to rename along with it.
+    pub fn find_references(&self, definition: Idx<Definition>) -> impl Iterator<Item = &Span> {
+        self.occurrences
+            .iter()
+            .filter(move |occurrence| {
+                !occurrence.is_definition && occurrence.definition == definition
+            })
+            .map(|occurrence| &occurrence.span)
+    }
+}
@@ -0,0 +1,28 @@
+use crate::core::{ast::*, visit::Visit, Error};
+
+#[derive(Default)]
+struct TodoCollector<'a> {
+    todos: Vec<&'a Todo>,
+}
+
+impl<'a> Visit<'a> for TodoCollector<'a> {
+    fn visit_todo(&mut self, todo: &'a Todo) {
+        self.todos.push(todo);
+    }
+}
+
+/// Every `todo` and `unimplemented` left in `module`, in source order.
+pub fn collect_todos(module: &Module) -> Vec<&Todo> {
+    let mut collector = TodoCollector::default();
+    collector.visit_module(module);
+    collector.todos
+}
+
+/// The opt-in lint listing the remaining `todo`s of a build, one diagnostic
+/// for each.
+pub fn lint_todos(module: &Module) -> Vec<Error> {
+    collect_todos(module)
+        .into_iter()
+        .map(Error::remaining_todo)
+        .collect()
+}
@@ -0,0 +1,227 @@
+use std::collections::HashSet;
+
+use crate::{
+    core::{arena::Idx, ast, Error, NodeId},
+    hir::{self, Block, Expression, ExpressionKind, Item, Module, Statement, NULLABLE_RECEIVER},
+    semantic::{resolve, Definition, DefinitionKind, Resolution},
+    syntax::{Span, Spanned},
+};
+
+/// The lint for what is written but never used: the parameters and locals
+/// never referred to, the private functions never called and the statements
+/// following a `todo`, which never run. Names starting with `_` are meant to
+/// be unused and left out.
+///
+/// Nothing is reported for a module the resolver has errors for, as compiling
+/// it reports those instead.
+pub fn lint_unused(module: &ast::Module) -> Vec<Error> {
+    let module = hir::lower(module.clone());
+    let resolution = resolve(&module);
+    if !resolution.errors.is_empty() {
+        return Vec::new();
+    }
+    let mut linter = UnusedLinter {
+        resolution: &resolution,
+        private_functions: HashSet::new(),
+        implemented_functions: HashSet::new(),
+        warnings: Vec::new(),
+    };
+    linter.lint_statements(&module.statements, None);
+    linter.lint_definitions();
+    linter
+        .warnings
+        .sort_by_key(|(span, _)| (span.range.start, span.range.end));
+    linter
+        .warnings
+        .into_iter()
+        .map(|(_, warning)| warning)
+        .collect()
+}
+
+struct UnusedLinter<'a> {
+    resolution: &'a Resolution,
+    /// The functions neither `pub` nor `extern`, which nothing outside of
+    /// the module can call.
+    private_functions: HashSet<NodeId>,
+    /// The functions with a body, the parameters of which are meant to be
+    /// used.
+    implemented_functions: HashSet<NodeId>,
+    /// With where they are reported, to sort them by.
+    warnings: Vec<(Span, Error)>,
+}
+
+impl<'a> UnusedLinter<'a> {
+    fn lint_definitions(&mut self) {
+        let resolution = self.resolution;
+        let mut used: HashSet<Idx<Definition>> = resolution
+            .references
+            .iter()
+            .map(|(_, definition)| *definition)
+            .collect();
+        // what an import stands for is used through it.
+        used.extend(resolution.definitions.iter().filter_map(|(_, definition)| {
+            match &definition.kind {
+                DefinitionKind::Import { target, .. } => *target,
+                _ => None,
+            }
+        }));
+        for (idx, definition) in resolution.definitions.iter() {
+            let name = &definition.name;
+            if used.contains(&idx)
+                || name.content.starts_with('_')
+                || name.content == NULLABLE_RECEIVER
+            {
+                continue;
+            }
+            let warning = match &definition.kind {
+                DefinitionKind::Parameter { function, .. }
+                    if self.implemented_functions.contains(function) =>
+                {
+                    Error::unused_binding(name)
+                }
+                DefinitionKind::Local => Error::unused_binding(name),
+                DefinitionKind::Function { id, .. } if self.private_functions.contains(id) => {
+                    Error::uncalled_function(name)
+                }
+                _ => continue,
+            };
+            self.warnings.push((name.span.clone(), warning));
+        }
+    }
+
+    /// Lints `statements` and `value`, reporting the first expression
+    /// following one which never finishes, and returns whether any of them
+    /// doesn't. Items are defined wherever they are written in a block, so
+    /// they are never unreachable.
+    fn lint_statements(&mut self, statements: &[Statement], value: Option<&Expression>) -> bool {
+        // the first expression never finishing, and whether the one
+        // following it has been reported.
+        let mut diverging: Option<(Span, bool)> = None;
+        let expressions = statements.iter().filter_map(|statement| match statement {
+            Statement::Item(_) => None,
+            Statement::Let(r#let) => r#let.value.as_ref(),
+            Statement::Expression(expression) => Some(expression),
+        });
+        for statement in statements {
+            if let Statement::Item(item) = statement {
+                self.lint_item(item);
+            }
+        }
+        for expression in expressions.chain(value) {
+            match &mut diverging {
+                Some((_, true)) => {}
+                Some((span, reported)) => {
+                    self.warnings.push((
+                        expression.span(),
+                        Error::unreachable(expression.span(), span.clone()),
+                    ));
+                    *reported = true;
+                }
+                None => {
+                    if self.lint_expression(expression) {
+                        diverging = Some((expression.span(), false));
+                    }
+                }
+            }
+        }
+        diverging.is_some()
+    }
+
+    fn lint_item(&mut self, item: &Item) {
+        match item {
+            Item::Function(function) => {
+                if !function.is_pub && !function.is_extern {
+                    self.private_functions.insert(function.id);
+                }
+                if let Some(body) = &function.body {
+                    self.implemented_functions.insert(function.id);
+                    self.lint_block(body);
+                }
+            }
+            Item::Module(declaration) => {
+                if let Some(Module { statements, .. }) = &declaration.body {
+                    self.lint_statements(statements, None);
+                }
+            }
+            Item::Use(_) => {}
+        }
+    }
+
+    fn lint_block(&mut self, block: &Block) -> bool {
+        self.lint_statements(&block.statements, block.value.as_deref())
+    }
+
+    /// Lints the blocks inside of `expression`, returning whether it never
+    /// finishes, which is so of `todo` and a block holding one.
+    fn lint_expression(&mut self, expression: &Expression) -> bool {
+        match &expression.kind {
+            ExpressionKind::Todo(_) => true,
+            ExpressionKind::Block(block) => self.lint_block(block),
+            ExpressionKind::Unsafe(r#unsafe) => self.lint_block(&r#unsafe.block),
+            ExpressionKind::If(r#if) => {
+                let condition = self.lint_expression(&r#if.condition);
+                let body = self.lint_block(&r#if.body);
+                let else_block = r#if
+                    .else_block
+                    .as_ref()
+                    .map(|else_block| self.lint_block(else_block));
+                condition || (body && else_block == Some(true))
+            }
+            ExpressionKind::Array(elements) | ExpressionKind::Tuple(elements) => {
+                self.lint_expressions(elements)
+            }
+            ExpressionKind::Init(init) => {
+                self.lint_expressions(init.fields.iter().map(|(_, value)| value))
+            }
+            ExpressionKind::Unary(unary) => self.lint_expression(&unary.operand),
+            ExpressionKind::Binary(binary) => {
+                self.lint_expressions([&*binary.lhs, &*binary.rhs].iter().copied())
+            }
+            ExpressionKind::Assign(assign) => {
+                self.lint_expressions([&*assign.target, &*assign.value].iter().copied())
+            }
+            ExpressionKind::Field(field) => self.lint_expression(&field.operand),
+            ExpressionKind::Instantiate(instantiate) => self.lint_expression(&instantiate.operand),
+            ExpressionKind::Call(call) => {
+                let callee = self.lint_expression(&call.callee);
+                self.lint_expressions(&call.arguments) || callee
+            }
+            ExpressionKind::Index(index) => {
+                let operand = self.lint_expression(&index.operand);
+                self.lint_expressions(&index.indices) || operand
+            }
+            ExpressionKind::Match(r#match) => {
+                let scrutinee = self.lint_expression(&r#match.scrutinee);
+                for arm in &r#match.arms {
+                    if let Some(guard) = &arm.guard {
+                        self.lint_expression(guard);
+                    }
+                    self.lint_expression(&arm.body);
+                }
+                scrutinee
+            }
+            ExpressionKind::Closure(closure) => {
+                self.lint_expression(&closure.body);
+                false
+            }
+            ExpressionKind::Literal(_)
+            | ExpressionKind::Name(_)
+            | ExpressionKind::Path(_)
+            | ExpressionKind::Null
+            | ExpressionKind::Intrinsic(_) => false,
+        }
+    }
+
+    /// Lints every one of `expressions`, returning whether any of them never
+    /// finishes.
+    fn lint_expressions<'e>(
+        &mut self,
+        expressions: impl IntoIterator<Item = &'e Expression>,
+    ) -> bool {
+        let mut diverges = false;
+        for expression in expressions {
+            diverges |= self.lint_expression(expression);
+        }
+        diverges
+    }
+}
@@ -0,0 +1,858 @@
+use crate::syntax::{
+    parse::{
+        declared_operators, find_infix_operator, Associativity, DeclaredOperator, ParseOptions,
+        Precedence, PREFIX_OPERATORS,
+    },
+    Token, TokenKind,
+};
+
+use super::{SyntaxElement, SyntaxKind, SyntaxNode, Trivia};
+
+/// Builds the concrete syntax tree with the same grammar and the same
+/// binding powers as the parser, but without ever failing: whatever doesn't
+/// fit is kept in an `Error` node, so the tree covers all of the source.
+pub(super) struct Builder<'a> {
+    src: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+    /// Where the trivia in front of the next token starts.
+    offset: usize,
+    /// The children of every unfinished node, one after another. Finishing a
+    /// node takes everything since its checkpoint.
+    children: Vec<SyntaxElement>,
+    depth: usize,
+    max_depth: usize,
+    /// Whether `Name {` starts a struct literal, see `ParseInput`.
+    struct_literals: bool,
+    operators: Vec<DeclaredOperator>,
+}
+
+impl<'a> Builder<'a> {
+    pub(super) fn new(src: &'a str, mut tokens: Vec<Token>, options: &ParseOptions) -> Self {
+        // doc comments are kept as trivia, like the other comments.
+        tokens.retain(|token| !matches!(token.kind, TokenKind::DocComment(_)));
+        Builder {
+            src,
+            operators: declared_operators(&tokens, options),
+            tokens,
+            position: 0,
+            offset: 0,
+            children: Vec::new(),
+            depth: 0,
+            max_depth: options.max_depth,
+            struct_literals: true,
+        }
+    }
+
+    pub(super) fn module(mut self) -> SyntaxNode {
+        while self.peek().is_some() {
+            self.statement();
+        }
+        self.trivia();
+        SyntaxNode {
+            kind: SyntaxKind::Module,
+            children: self.children,
+        }
+    }
+
+    fn nth(&self, n: usize) -> Option<&TokenKind> {
+        self.tokens.get(self.position + n).map(|token| &token.kind)
+    }
+
+    fn peek(&self) -> Option<&TokenKind> {
+        self.nth(0)
+    }
+
+    fn at(&self, kind: TokenKind) -> bool {
+        self.peek() == Some(&kind)
+    }
+
+    fn trivia(&mut self) {
+        let end = match self.tokens.get(self.position) {
+            Some(token) => token.span.range.start,
+            None => self.src.len(),
+        };
+        if end > self.offset {
+            self.children.push(SyntaxElement::Trivia(Trivia {
+                text: self.src[self.offset..end].to_string(),
+                range: self.offset..end,
+            }));
+            self.offset = end;
+        }
+    }
+
+    fn bump(&mut self) {
+        self.trivia();
+        let token = self.tokens[self.position].clone();
+        self.offset = token.span.range.end;
+        self.position += 1;
+        self.children.push(SyntaxElement::Token(token));
+    }
+
+    fn eat(&mut self, kind: TokenKind) -> bool {
+        let found = self.at(kind);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    /// Marks where a node starts, leaving the trivia in front of it outside.
+    fn checkpoint(&mut self) -> usize {
+        self.trivia();
+        self.children.len()
+    }
+
+    fn finish(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        let children = self.children.split_off(checkpoint);
+        self.children
+            .push(SyntaxElement::Node(SyntaxNode { kind, children }));
+    }
+
+    /// Runs `f` between delimiters, where struct literals are allowed again.
+    fn enclosed(&mut self, f: impl FnOnce(&mut Self)) {
+        let struct_literals = self.struct_literals;
+        self.struct_literals = true;
+        f(self);
+        self.struct_literals = struct_literals;
+    }
+
+    fn error_token(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.bump();
+        self.finish(checkpoint, SyntaxKind::Error);
+    }
+
+    fn statement(&mut self) {
+        if self.at(TokenKind::PunctuationNumberSign)
+            && self.nth(1) == Some(&TokenKind::PunctuationExclamationMark)
+        {
+            self.attribute();
+        } else if self.at(TokenKind::KeywordUse)
+            || self.at(TokenKind::KeywordPub) && self.nth(1) == Some(&TokenKind::KeywordUse)
+        {
+            self.use_declaration();
+        } else if self.at(TokenKind::KeywordMod)
+            || self.at(TokenKind::KeywordPub) && self.nth(1) == Some(&TokenKind::KeywordMod)
+        {
+            self.module_declaration();
+        } else if self.at(TokenKind::KeywordOperator) {
+            self.operator_declaration();
+        } else if self.at(TokenKind::KeywordLet) {
+            self.let_statement();
+        } else if matches!(
+            self.peek(),
+            Some(TokenKind::PunctuationNumberSign)
+                | Some(TokenKind::KeywordPub)
+                | Some(TokenKind::KeywordExtern)
+                | Some(TokenKind::KeywordFn)
+        ) {
+            self.function_declaration();
+        } else {
+            let checkpoint = self.checkpoint();
+            if !self.expression(0) {
+                self.error_token();
+            } else if self.eat(TokenKind::PunctuationSemicolon) {
+                self.finish(checkpoint, SyntaxKind::ExpressionStatement);
+            }
+        }
+    }
+
+    /// `let pattern: Type = value;`, with the type and the value optional.
+    fn let_statement(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.bump();
+        if matches!(
+            self.peek(),
+            Some(TokenKind::IdentifierIdentifier(_)) | Some(TokenKind::KeywordPlaceholderName)
+        ) {
+            self.bump();
+        }
+        if self.eat(TokenKind::PunctuationColon) {
+            self.ty();
+        }
+        if self.eat(TokenKind::PunctuationEqualsSign) {
+            self.expression(0);
+        }
+        self.eat(TokenKind::PunctuationSemicolon);
+        self.finish(checkpoint, SyntaxKind::LetStatement);
+    }
+
+    /// `#[...]` or `#![...]`, keeping everything up to the matching `]`.
+    fn attribute(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.bump();
+        self.eat(TokenKind::PunctuationExclamationMark);
+        let mut depth = 0;
+        while let Some(kind) = self.peek() {
+            match kind {
+                TokenKind::PunctuationLeftSquareBracket => depth += 1,
+                TokenKind::PunctuationRightSquareBracket => depth -= 1,
+                _ if depth == 0 => break,
+                _ => {}
+            }
+            self.bump();
+            if depth == 0 {
+                break;
+            }
+        }
+        self.finish(checkpoint, SyntaxKind::Attribute);
+    }
+
+    fn function_declaration(&mut self) {
+        let checkpoint = self.checkpoint();
+        while self.at(TokenKind::PunctuationNumberSign) {
+            self.attribute();
+        }
+        self.eat(TokenKind::KeywordPub);
+        self.eat(TokenKind::KeywordExtern);
+        self.eat(TokenKind::KeywordFn);
+        if matches!(self.peek(), Some(TokenKind::IdentifierIdentifier(_))) {
+            self.bump();
+        }
+        if self.at(TokenKind::PunctuationLessThanSign) {
+            self.generic_parameter_list();
+        }
+        if self.at(TokenKind::PunctuationLeftParenthesis) {
+            self.parameter_list(TokenKind::PunctuationRightParenthesis);
+        }
+        if self.at(TokenKind::PunctuationColon) {
+            let return_type = self.checkpoint();
+            self.bump();
+            self.ty();
+            self.finish(return_type, SyntaxKind::ReturnType);
+        }
+        if !self.eat(TokenKind::PunctuationSemicolon)
+            && self.at(TokenKind::PunctuationLeftCurlyBracket)
+        {
+            self.block();
+        }
+        self.finish(checkpoint, SyntaxKind::FunctionDeclaration);
+    }
+
+    fn operator_declaration(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.bump();
+        while let Some(kind) = self.peek() {
+            match kind {
+                TokenKind::PunctuationLeftCurlyBracket
+                | TokenKind::PunctuationRightCurlyBracket
+                | TokenKind::KeywordFn => break,
+                TokenKind::PunctuationSemicolon => {
+                    self.bump();
+                    break;
+                }
+                _ => self.bump(),
+            }
+        }
+        self.finish(checkpoint, SyntaxKind::OperatorDeclaration);
+    }
+
+    fn module_declaration(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.eat(TokenKind::KeywordPub);
+        self.bump();
+        if matches!(self.peek(), Some(TokenKind::IdentifierIdentifier(_))) {
+            self.bump();
+        }
+        if self.eat(TokenKind::PunctuationLeftCurlyBracket) {
+            while self.peek().is_some() && !self.at(TokenKind::PunctuationRightCurlyBracket) {
+                self.statement();
+            }
+            self.eat(TokenKind::PunctuationRightCurlyBracket);
+        } else {
+            self.eat(TokenKind::PunctuationSemicolon);
+        }
+        self.finish(checkpoint, SyntaxKind::ModuleDeclaration);
+    }
+
+    fn use_declaration(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.eat(TokenKind::KeywordPub);
+        self.bump();
+        self.use_tree();
+        self.eat(TokenKind::PunctuationSemicolon);
+        self.finish(checkpoint, SyntaxKind::UseDeclaration);
+    }
+
+    /// Whether the next two tokens are the colons of a `::` with nothing
+    /// between them.
+    fn at_path_separator(&self) -> bool {
+        self.at(TokenKind::PunctuationColon)
+            && self.nth(1) == Some(&TokenKind::PunctuationColon)
+            && self.tokens[self.position].span.range.end
+                == self.tokens[self.position + 1].span.range.start
+    }
+
+    fn use_tree(&mut self) {
+        let checkpoint = self.checkpoint();
+        let position = self.position;
+        let mut after_name = false;
+        while matches!(self.peek(), Some(TokenKind::IdentifierIdentifier(_))) {
+            self.bump();
+            after_name = !self.at_path_separator();
+            if after_name {
+                break;
+            }
+            self.bump();
+            self.bump();
+        }
+        match self.peek() {
+            Some(TokenKind::KeywordAs) if after_name => {
+                self.bump();
+                if matches!(self.peek(), Some(TokenKind::IdentifierIdentifier(_))) {
+                    self.bump();
+                }
+            }
+            Some(TokenKind::PunctuationAsterisk) if !after_name => self.bump(),
+            Some(TokenKind::PunctuationLeftCurlyBracket)
+                if !after_name && self.depth < self.max_depth =>
+            {
+                self.depth += 1;
+                self.use_tree_list();
+                self.depth -= 1;
+            }
+            _ => {}
+        }
+        if self.position != position {
+            self.finish(checkpoint, SyntaxKind::UseTree);
+        }
+    }
+
+    fn use_tree_list(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.bump();
+        while let Some(kind) = self.peek() {
+            match kind {
+                TokenKind::PunctuationRightCurlyBracket | TokenKind::PunctuationSemicolon => break,
+                TokenKind::PunctuationComma => self.bump(),
+                TokenKind::IdentifierIdentifier(_)
+                | TokenKind::PunctuationAsterisk
+                | TokenKind::PunctuationLeftCurlyBracket => {
+                    let position = self.position;
+                    self.use_tree();
+                    if self.position == position {
+                        self.error_token();
+                    }
+                }
+                _ => self.error_token(),
+            }
+        }
+        self.eat(TokenKind::PunctuationRightCurlyBracket);
+        self.finish(checkpoint, SyntaxKind::UseTreeList);
+    }
+
+    fn generic_parameter_list(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.bump();
+        while let Some(kind) = self.peek() {
+            match kind {
+                TokenKind::PunctuationGreaterThanSign
+                | TokenKind::PunctuationLeftParenthesis
+                | TokenKind::PunctuationLeftCurlyBracket
+                | TokenKind::PunctuationSemicolon => break,
+                TokenKind::PunctuationComma => self.bump(),
+                TokenKind::IdentifierIdentifier(_) => {
+                    let parameter = self.checkpoint();
+                    self.bump();
+                    if self.eat(TokenKind::PunctuationColon) {
+                        self.ty();
+                        while self.eat(TokenKind::PunctuationPlusSign) {
+                            self.ty();
+                        }
+                    }
+                    self.finish(parameter, SyntaxKind::GenericParameter);
+                }
+                _ => self.error_token(),
+            }
+        }
+        self.eat(TokenKind::PunctuationGreaterThanSign);
+        self.finish(checkpoint, SyntaxKind::GenericParameterList);
+    }
+
+    fn type_argument_list(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.bump();
+        while let Some(kind) = self.peek() {
+            match kind {
+                TokenKind::PunctuationGreaterThanSign
+                | TokenKind::PunctuationRightParenthesis
+                | TokenKind::PunctuationLeftCurlyBracket
+                | TokenKind::PunctuationSemicolon => break,
+                TokenKind::PunctuationComma | TokenKind::PunctuationAsterisk => self.bump(),
+                _ if self.starts_type() => {
+                    let position = self.position;
+                    self.ty();
+                    if self.position == position {
+                        self.error_token();
+                    }
+                }
+                _ => self.error_token(),
+            }
+        }
+        self.eat(TokenKind::PunctuationGreaterThanSign);
+        self.finish(checkpoint, SyntaxKind::TypeArgumentList);
+    }
+
+    /// The parameters up to `close`, which is `)` for a function and `|` for
+    /// a closure.
+    fn parameter_list(&mut self, close: TokenKind) {
+        let checkpoint = self.checkpoint();
+        self.bump();
+        while let Some(kind) = self.peek() {
+            match kind {
+                kind if *kind == close => break,
+                TokenKind::PunctuationLeftCurlyBracket | TokenKind::PunctuationSemicolon => break,
+                TokenKind::PunctuationComma => self.bump(),
+                TokenKind::IdentifierIdentifier(_) | TokenKind::KeywordPlaceholderName => {
+                    let parameter = self.checkpoint();
+                    self.bump();
+                    if self.eat(TokenKind::PunctuationColon) {
+                        self.ty();
+                    }
+                    self.finish(parameter, SyntaxKind::Parameter);
+                }
+                _ => self.error_token(),
+            }
+        }
+        self.eat(close);
+        self.finish(checkpoint, SyntaxKind::ParameterList);
+    }
+
+    fn ty(&mut self) {
+        let checkpoint = self.checkpoint();
+        let kind = match self.peek() {
+            Some(TokenKind::PunctuationExclamationMark) => {
+                self.bump();
+                SyntaxKind::Type
+            }
+            Some(TokenKind::KeywordImpl) | Some(TokenKind::KeywordDyn) => {
+                self.bump();
+                self.basic_type();
+                SyntaxKind::Type
+            }
+            Some(TokenKind::IdentifierIdentifier(_)) => {
+                self.basic_type();
+                SyntaxKind::Type
+            }
+            Some(TokenKind::PunctuationLeftParenthesis) if self.depth < self.max_depth => {
+                self.depth += 1;
+                let is_tuple = self.parenthesized(Self::ty, Self::starts_type);
+                let kind = if self.eat(TokenKind::PunctuationsSingleRightArrow) {
+                    self.ty();
+                    SyntaxKind::FunctionType
+                } else if is_tuple {
+                    SyntaxKind::TupleType
+                } else {
+                    SyntaxKind::ParenthesizedType
+                };
+                self.depth -= 1;
+                kind
+            }
+            _ => return,
+        };
+        self.finish(checkpoint, kind);
+        while self.eat(TokenKind::PunctuationQuestionMark) {
+            self.finish(checkpoint, SyntaxKind::NullableType);
+        }
+    }
+
+    fn starts_type(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(TokenKind::PunctuationExclamationMark)
+                | Some(TokenKind::KeywordImpl)
+                | Some(TokenKind::KeywordDyn)
+                | Some(TokenKind::IdentifierIdentifier(_))
+                | Some(TokenKind::PunctuationLeftParenthesis)
+        )
+    }
+
+    fn basic_type(&mut self) {
+        self.path();
+        if self.at(TokenKind::PunctuationLessThanSign) {
+            self.type_argument_list();
+        }
+    }
+
+    fn path(&mut self) {
+        self.bump();
+        while self.at(TokenKind::PunctuationFullStop)
+            && matches!(self.nth(1), Some(TokenKind::IdentifierIdentifier(_)))
+        {
+            self.bump();
+            self.bump();
+        }
+    }
+
+    /// Takes `(`, the comma separated elements and `)`, telling whether it
+    /// was a tuple rather than a single element in parentheses.
+    fn parenthesized(&mut self, element: fn(&mut Self), starts: fn(&Self) -> bool) -> bool {
+        self.bump();
+        let mut elements = 0;
+        let mut last_comma = false;
+        while let Some(kind) = self.peek() {
+            match kind {
+                TokenKind::PunctuationRightParenthesis
+                | TokenKind::PunctuationRightCurlyBracket
+                | TokenKind::PunctuationSemicolon => break,
+                TokenKind::PunctuationComma => {
+                    self.bump();
+                    last_comma = true;
+                }
+                _ if starts(self) => {
+                    let position = self.position;
+                    element(self);
+                    if self.position == position {
+                        // nested too deeply to take the element.
+                        self.error_token();
+                    }
+                    elements += 1;
+                    last_comma = false;
+                }
+                _ => self.error_token(),
+            }
+        }
+        self.eat(TokenKind::PunctuationRightParenthesis);
+        elements != 1 || last_comma
+    }
+
+    fn block(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.bump();
+        self.enclosed(|builder| {
+            while builder.peek().is_some() && !builder.at(TokenKind::PunctuationRightCurlyBracket) {
+                builder.statement();
+            }
+        });
+        self.eat(TokenKind::PunctuationRightCurlyBracket);
+        self.finish(checkpoint, SyntaxKind::Block);
+    }
+
+    fn starts_expression(&self) -> bool {
+        match self.peek() {
+            Some(kind) => {
+                matches!(
+                    kind,
+                    TokenKind::LiteralString(_)
+                        | TokenKind::LiteralCharacter(_)
+                        | TokenKind::LiteralBoolean(_)
+                        | TokenKind::LiteralNumberIntegral(_)
+                        | TokenKind::LiteralNumberDecimal(_)
+                        | TokenKind::LiteralNumberExponent(_)
+                        | TokenKind::LiteralStringHead(_)
+                        | TokenKind::IdentifierIdentifier(_)
+                        | TokenKind::KeywordPlaceholderName
+                        | TokenKind::KeywordIf
+                        | TokenKind::KeywordMatch
+                        | TokenKind::KeywordUnsafe
+                        | TokenKind::KeywordTodo
+                        | TokenKind::KeywordMove
+                        | TokenKind::PunctuationVerticalLine
+                        | TokenKind::PunctuationsLogicalOr
+                        | TokenKind::KeywordUnimplemented
+                        | TokenKind::PunctuationCommercialAt
+                        | TokenKind::PunctuationLeftParenthesis
+                ) || self.at_prefix_operator()
+            }
+            None => false,
+        }
+    }
+
+    fn at_prefix_operator(&self) -> bool {
+        PREFIX_OPERATORS
+            .iter()
+            .any(|(prefix, _)| self.peek() == Some(prefix))
+    }
+
+    /// Takes an expression, unless nothing can start one here.
+    fn expression(&mut self, binding_power: u8) -> bool {
+        if self.depth >= self.max_depth || !self.starts_expression() {
+            return false;
+        }
+        self.depth += 1;
+        self.expression_inner(binding_power);
+        self.depth -= 1;
+        true
+    }
+
+    fn expression_inner(&mut self, binding_power: u8) {
+        let checkpoint = self.checkpoint();
+        if self.at_prefix_operator() {
+            self.bump();
+            self.expression(Precedence::Prefix.left_binding_power());
+            self.finish(checkpoint, SyntaxKind::PrefixExpression);
+        } else {
+            self.primary();
+        }
+
+        loop {
+            if self.at(TokenKind::PunctuationLeftSquareBracket)
+                && Precedence::Index.left_binding_power() >= binding_power
+            {
+                self.bump();
+                self.enclosed(|builder| {
+                    builder.expression(0);
+                });
+                self.eat(TokenKind::PunctuationRightSquareBracket);
+                self.finish(checkpoint, SyntaxKind::IndexExpression);
+                continue;
+            }
+            if self.at_path_separator()
+                && self.nth(2) == Some(&TokenKind::PunctuationLessThanSign)
+                && Precedence::Index.left_binding_power() >= binding_power
+            {
+                self.bump();
+                self.bump();
+                self.type_argument_list();
+                self.finish(checkpoint, SyntaxKind::TypeArgumentsExpression);
+                continue;
+            }
+            if let Some(found) = find_infix_operator(&self.tokens[self.position..], &self.operators)
+            {
+                let (left_binding_power, right_binding_power) = found.binding_powers();
+                if left_binding_power >= binding_power {
+                    for _ in 0..found.len() {
+                        self.bump();
+                    }
+                    self.expression(right_binding_power);
+                    self.finish(checkpoint, SyntaxKind::InfixExpression);
+                    continue;
+                }
+                // binding less tightly, it ends the expression: `f(a) + b`
+                // doesn't call `f(a)` with `+b`.
+                break;
+            }
+            // `show fib 15` calls `show` with `fib 15`.
+            let (left_binding_power, right_binding_power) =
+                Precedence::Call.binding_powers(Associativity::Right);
+            if left_binding_power >= binding_power && self.expression(right_binding_power) {
+                self.finish(checkpoint, SyntaxKind::CallExpression);
+                continue;
+            }
+            break;
+        }
+    }
+
+    fn primary(&mut self) {
+        let checkpoint = self.checkpoint();
+        let kind = match self.peek() {
+            Some(TokenKind::LiteralString(_)) => {
+                while matches!(self.peek(), Some(TokenKind::LiteralString(_))) {
+                    self.bump();
+                }
+                SyntaxKind::LiteralExpression
+            }
+            Some(TokenKind::LiteralStringHead(_)) => {
+                self.bump();
+                self.enclosed(|builder| {
+                    builder.expression(0);
+                    while matches!(builder.peek(), Some(TokenKind::LiteralStringMiddle(_))) {
+                        builder.bump();
+                        builder.expression(0);
+                    }
+                });
+                if matches!(self.peek(), Some(TokenKind::LiteralStringTail(_))) {
+                    self.bump();
+                }
+                SyntaxKind::InterpolationExpression
+            }
+            Some(TokenKind::IdentifierIdentifier(_))
+                if self.struct_literals
+                    && self.nth(1) == Some(&TokenKind::PunctuationLeftCurlyBracket) =>
+            {
+                self.init_expression();
+                SyntaxKind::InitExpression
+            }
+            Some(TokenKind::IdentifierIdentifier(_)) | Some(TokenKind::KeywordPlaceholderName) => {
+                self.bump();
+                SyntaxKind::NameExpression
+            }
+            Some(TokenKind::KeywordIf) => {
+                self.if_expression();
+                return;
+            }
+            Some(TokenKind::KeywordMatch) => {
+                self.match_expression();
+                return;
+            }
+            Some(TokenKind::KeywordMove)
+            | Some(TokenKind::PunctuationVerticalLine)
+            | Some(TokenKind::PunctuationsLogicalOr) => {
+                self.closure_expression();
+                return;
+            }
+            Some(TokenKind::KeywordUnsafe) => {
+                self.bump();
+                if self.at(TokenKind::PunctuationLeftCurlyBracket) {
+                    self.block();
+                }
+                SyntaxKind::UnsafeExpression
+            }
+            Some(TokenKind::KeywordTodo) | Some(TokenKind::KeywordUnimplemented) => {
+                self.bump();
+                if self.at(TokenKind::PunctuationLeftParenthesis)
+                    && matches!(self.nth(1), Some(TokenKind::LiteralString(_)))
+                    && self.nth(2) == Some(&TokenKind::PunctuationRightParenthesis)
+                {
+                    self.bump();
+                    self.bump();
+                    self.bump();
+                } else if matches!(self.peek(), Some(TokenKind::LiteralString(_))) {
+                    self.bump();
+                }
+                SyntaxKind::TodoExpression
+            }
+            Some(TokenKind::PunctuationCommercialAt) => {
+                self.bump();
+                if matches!(self.peek(), Some(TokenKind::IdentifierIdentifier(_))) {
+                    self.bump();
+                }
+                if self.at(TokenKind::PunctuationLessThanSign) {
+                    self.type_argument_list();
+                }
+                SyntaxKind::IntrinsicExpression
+            }
+            Some(TokenKind::PunctuationLeftParenthesis) => {
+                let element = |builder: &mut Self| {
+                    builder.enclosed(|builder| {
+                        builder.expression(0);
+                    });
+                };
+                if self.parenthesized(element, Self::starts_expression) {
+                    SyntaxKind::TupleExpression
+                } else {
+                    SyntaxKind::ParenthesizedExpression
+                }
+            }
+            _ => {
+                self.bump();
+                SyntaxKind::LiteralExpression
+            }
+        };
+        self.finish(checkpoint, kind);
+    }
+
+    fn init_expression(&mut self) {
+        self.bump();
+        self.bump();
+        self.enclosed(|builder| {
+            while let Some(kind) = builder.peek() {
+                match kind {
+                    TokenKind::PunctuationRightCurlyBracket | TokenKind::PunctuationSemicolon => {
+                        break
+                    }
+                    TokenKind::PunctuationComma => builder.bump(),
+                    TokenKind::IdentifierIdentifier(_) => {
+                        let field = builder.checkpoint();
+                        builder.bump();
+                        if builder.eat(TokenKind::PunctuationColon) {
+                            builder.expression(0);
+                        }
+                        builder.finish(field, SyntaxKind::InitField);
+                    }
+                    _ => builder.error_token(),
+                }
+            }
+        });
+        self.eat(TokenKind::PunctuationRightCurlyBracket);
+    }
+
+    fn if_expression(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.bump();
+        let struct_literals = self.struct_literals;
+        self.struct_literals = false;
+        self.expression(0);
+        self.struct_literals = struct_literals;
+        if self.at(TokenKind::PunctuationLeftCurlyBracket) {
+            self.block();
+        }
+        if self.at(TokenKind::KeywordElse) {
+            let else_clause = self.checkpoint();
+            self.bump();
+            if self.at(TokenKind::KeywordIf) {
+                self.if_expression();
+            } else if self.at(TokenKind::PunctuationLeftCurlyBracket) {
+                self.block();
+            }
+            self.finish(else_clause, SyntaxKind::ElseClause);
+        }
+        self.finish(checkpoint, SyntaxKind::IfExpression);
+    }
+
+    fn match_expression(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.bump();
+        let struct_literals = self.struct_literals;
+        self.struct_literals = false;
+        self.expression(0);
+        self.struct_literals = struct_literals;
+        if self.eat(TokenKind::PunctuationLeftCurlyBracket) {
+            self.enclosed(|builder| {
+                while let Some(kind) = builder.peek() {
+                    match kind {
+                        TokenKind::PunctuationRightCurlyBracket => break,
+                        TokenKind::PunctuationComma => builder.bump(),
+                        TokenKind::LiteralString(_)
+                        | TokenKind::LiteralCharacter(_)
+                        | TokenKind::LiteralBoolean(_)
+                        | TokenKind::LiteralNumberIntegral(_)
+                        | TokenKind::LiteralNumberDecimal(_)
+                        | TokenKind::LiteralNumberExponent(_)
+                        | TokenKind::IdentifierIdentifier(_)
+                        | TokenKind::KeywordPlaceholderName => builder.match_arm(),
+                        _ => builder.error_token(),
+                    }
+                }
+            });
+            self.eat(TokenKind::PunctuationRightCurlyBracket);
+        }
+        self.finish(checkpoint, SyntaxKind::MatchExpression);
+    }
+
+    fn match_arm(&mut self) {
+        let checkpoint = self.checkpoint();
+        self.bump();
+        if self.at(TokenKind::KeywordIf) {
+            let guard = self.checkpoint();
+            self.bump();
+            self.expression(0);
+            self.finish(guard, SyntaxKind::MatchGuard);
+        }
+        if self.eat(TokenKind::PunctuationsDoubleRightArrow) {
+            if self.at(TokenKind::PunctuationLeftCurlyBracket) {
+                self.block();
+            } else {
+                self.expression(0);
+            }
+        }
+        self.finish(checkpoint, SyntaxKind::MatchArm);
+    }
+
+    fn closure_expression(&mut self) {
+        let checkpoint = self.checkpoint();
+        if self.at(TokenKind::KeywordMove) {
+            self.bump();
+        }
+        if self.at(TokenKind::PunctuationsLogicalOr) {
+            let parameter_list = self.checkpoint();
+            self.bump();
+            self.finish(parameter_list, SyntaxKind::ParameterList);
+        } else {
+            self.parameter_list(TokenKind::PunctuationVerticalLine);
+        }
+        if self.at(TokenKind::PunctuationColon) {
+            let return_type = self.checkpoint();
+            self.bump();
+            self.ty();
+            self.finish(return_type, SyntaxKind::ReturnType);
+        }
+        if self.at(TokenKind::PunctuationLeftCurlyBracket) {
+            self.block();
+        } else {
+            self.expression(0);
+        }
+        self.finish(checkpoint, SyntaxKind::ClosureExpression);
+    }
+}
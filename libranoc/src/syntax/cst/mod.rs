@@ -0,0 +1,25 @@
+//! A lossless syntax tree keeping every token and the whitespace between
+//! them, for tools which work on the source as it is written, like
+//! formatters and editors. The compiler itself only needs the AST.
+
+mod build;
+mod nodes;
+mod tree;
+
+pub use nodes::*;
+pub use tree::*;
+
+use crate::syntax::{tokenize_with, ParseOptions, TokenizeOptions};
+
+use build::Builder;
+
+/// Parses `src` into a tree whose root is a `SyntaxKind::Module`. This never
+/// fails; broken source ends up in `SyntaxKind::Error` nodes, and the
+/// errors themselves are still reported by `parse`.
+pub fn parse_cst(src: &str) -> SyntaxNode {
+    parse_cst_with(src, TokenizeOptions::default(), &ParseOptions::default())
+}
+
+pub fn parse_cst_with(src: &str, tokenize: TokenizeOptions, options: &ParseOptions) -> SyntaxNode {
+    Builder::new(src, tokenize_with(src, tokenize), options).module()
+}
@@ -0,0 +1,564 @@
+use crate::syntax::{Token, TokenKind};
+
+use super::{SyntaxKind, SyntaxNode};
+
+macro_rules! cst_node {
+    ($name:ident) => {
+        #[derive(Debug, Clone, Copy)]
+        pub struct $name<'a>(&'a SyntaxNode);
+
+        impl<'a> $name<'a> {
+            pub fn cast(node: &'a SyntaxNode) -> Option<Self> {
+                if node.kind == SyntaxKind::$name {
+                    Some($name(node))
+                } else {
+                    None
+                }
+            }
+
+            pub fn syntax(&self) -> &'a SyntaxNode {
+                self.0
+            }
+        }
+    };
+}
+
+impl SyntaxKind {
+    pub fn is_expression(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::LiteralExpression
+                | SyntaxKind::InterpolationExpression
+                | SyntaxKind::NameExpression
+                | SyntaxKind::TodoExpression
+                | SyntaxKind::IntrinsicExpression
+                | SyntaxKind::IfExpression
+                | SyntaxKind::MatchExpression
+                | SyntaxKind::ClosureExpression
+                | SyntaxKind::UnsafeExpression
+                | SyntaxKind::InitExpression
+                | SyntaxKind::ParenthesizedExpression
+                | SyntaxKind::TupleExpression
+                | SyntaxKind::PrefixExpression
+                | SyntaxKind::InfixExpression
+                | SyntaxKind::CallExpression
+                | SyntaxKind::IndexExpression
+                | SyntaxKind::TypeArgumentsExpression
+        )
+    }
+
+    pub fn is_type(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Type
+                | SyntaxKind::TupleType
+                | SyntaxKind::ParenthesizedType
+                | SyntaxKind::FunctionType
+                | SyntaxKind::NullableType
+        )
+    }
+}
+
+impl SyntaxNode {
+    fn child_token(&self, kind: TokenKind) -> Option<&Token> {
+        self.child_tokens().find(|token| token.kind == kind)
+    }
+
+    fn child<'a, T>(&'a self, cast: fn(&'a SyntaxNode) -> Option<T>) -> Option<T> {
+        self.child_nodes().find_map(cast)
+    }
+
+    fn identifier(&self) -> Option<&Token> {
+        self.child_tokens()
+            .find(|token| matches!(token.kind, TokenKind::IdentifierIdentifier(_)))
+    }
+
+    fn expressions(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.child_nodes().filter(|node| node.kind.is_expression())
+    }
+
+    fn nth_expression(&self, n: usize) -> Option<&SyntaxNode> {
+        self.expressions().nth(n)
+    }
+}
+
+cst_node!(Module);
+
+impl<'a> Module<'a> {
+    pub fn items(&self) -> impl Iterator<Item = &'a SyntaxNode> {
+        self.0.child_nodes()
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = FunctionDeclaration<'a>> {
+        self.0.child_nodes().filter_map(FunctionDeclaration::cast)
+    }
+
+    pub fn use_declarations(&self) -> impl Iterator<Item = UseDeclaration<'a>> {
+        self.0.child_nodes().filter_map(UseDeclaration::cast)
+    }
+
+    pub fn module_declarations(&self) -> impl Iterator<Item = ModuleDeclaration<'a>> {
+        self.0.child_nodes().filter_map(ModuleDeclaration::cast)
+    }
+}
+
+cst_node!(Attribute);
+
+impl<'a> Attribute<'a> {
+    /// Whether this is `#![...]`, applying to the file.
+    pub fn is_inner(&self) -> bool {
+        self.0
+            .child_token(TokenKind::PunctuationExclamationMark)
+            .is_some()
+    }
+
+    pub fn name(&self) -> Option<&'a Token> {
+        self.0.identifier()
+    }
+}
+
+cst_node!(ModuleDeclaration);
+
+impl<'a> ModuleDeclaration<'a> {
+    pub fn is_pub(&self) -> bool {
+        self.0.child_token(TokenKind::KeywordPub).is_some()
+    }
+
+    pub fn name(&self) -> Option<&'a Token> {
+        self.0.identifier()
+    }
+
+    /// Whether the items are written inside of `{...}` rather than in a
+    /// file of their own.
+    pub fn is_inline(&self) -> bool {
+        self.0
+            .child_token(TokenKind::PunctuationLeftCurlyBracket)
+            .is_some()
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &'a SyntaxNode> {
+        self.0.child_nodes()
+    }
+}
+
+cst_node!(OperatorDeclaration);
+
+impl<'a> OperatorDeclaration<'a> {
+    /// The punctuations spelling the operator.
+    pub fn symbol(&self) -> impl Iterator<Item = &'a Token> {
+        self.0
+            .child_tokens()
+            .skip(1)
+            .take_while(|token| token.kind != TokenKind::PunctuationColon)
+    }
+
+    fn names(&self) -> impl Iterator<Item = &'a Token> {
+        self.0
+            .child_tokens()
+            .filter(|token| matches!(token.kind, TokenKind::IdentifierIdentifier(_)))
+    }
+
+    pub fn precedence(&self) -> Option<&'a Token> {
+        self.names().next()
+    }
+
+    pub fn associativity(&self) -> Option<&'a Token> {
+        self.names().nth(1)
+    }
+
+    pub fn function(&self) -> Option<&'a Token> {
+        self.names().nth(2)
+    }
+}
+
+cst_node!(UseDeclaration);
+
+impl<'a> UseDeclaration<'a> {
+    pub fn is_pub(&self) -> bool {
+        self.0.child_token(TokenKind::KeywordPub).is_some()
+    }
+
+    pub fn tree(&self) -> Option<UseTree<'a>> {
+        self.0.child(UseTree::cast)
+    }
+}
+
+cst_node!(UseTree);
+
+impl<'a> UseTree<'a> {
+    /// The names of the path, without the `::` between them and without the
+    /// name given with `as`.
+    pub fn segments(&self) -> impl Iterator<Item = &'a Token> {
+        self.0
+            .child_tokens()
+            .take_while(|token| token.kind != TokenKind::KeywordAs)
+            .filter(|token| matches!(token.kind, TokenKind::IdentifierIdentifier(_)))
+    }
+
+    pub fn alias(&self) -> Option<&'a Token> {
+        self.0
+            .child_tokens()
+            .skip_while(|token| token.kind != TokenKind::KeywordAs)
+            .nth(1)
+    }
+
+    pub fn is_glob(&self) -> bool {
+        self.0.child_token(TokenKind::PunctuationAsterisk).is_some()
+    }
+
+    pub fn tree_list(&self) -> Option<UseTreeList<'a>> {
+        self.0.child(UseTreeList::cast)
+    }
+}
+
+cst_node!(UseTreeList);
+
+impl<'a> UseTreeList<'a> {
+    pub fn trees(&self) -> impl Iterator<Item = UseTree<'a>> {
+        self.0.child_nodes().filter_map(UseTree::cast)
+    }
+}
+
+cst_node!(FunctionDeclaration);
+
+impl<'a> FunctionDeclaration<'a> {
+    pub fn attributes(&self) -> impl Iterator<Item = Attribute<'a>> {
+        self.0.child_nodes().filter_map(Attribute::cast)
+    }
+
+    pub fn is_pub(&self) -> bool {
+        self.0.child_token(TokenKind::KeywordPub).is_some()
+    }
+
+    pub fn is_extern(&self) -> bool {
+        self.0.child_token(TokenKind::KeywordExtern).is_some()
+    }
+
+    pub fn name(&self) -> Option<&'a Token> {
+        self.0.identifier()
+    }
+
+    pub fn generic_parameter_list(&self) -> Option<GenericParameterList<'a>> {
+        self.0.child(GenericParameterList::cast)
+    }
+
+    pub fn parameter_list(&self) -> Option<ParameterList<'a>> {
+        self.0.child(ParameterList::cast)
+    }
+
+    pub fn return_type(&self) -> Option<&'a SyntaxNode> {
+        self.0
+            .child_nodes()
+            .find(|node| node.kind == SyntaxKind::ReturnType)?
+            .child_nodes()
+            .find(|node| node.kind.is_type())
+    }
+
+    pub fn body(&self) -> Option<Block<'a>> {
+        self.0.child(Block::cast)
+    }
+}
+
+cst_node!(GenericParameterList);
+
+impl<'a> GenericParameterList<'a> {
+    pub fn parameters(&self) -> impl Iterator<Item = GenericParameter<'a>> {
+        self.0.child_nodes().filter_map(GenericParameter::cast)
+    }
+}
+
+cst_node!(GenericParameter);
+
+impl<'a> GenericParameter<'a> {
+    pub fn name(&self) -> Option<&'a Token> {
+        self.0.identifier()
+    }
+
+    pub fn bounds(&self) -> impl Iterator<Item = &'a SyntaxNode> {
+        self.0.child_nodes().filter(|node| node.kind.is_type())
+    }
+}
+
+cst_node!(ParameterList);
+
+impl<'a> ParameterList<'a> {
+    pub fn parameters(&self) -> impl Iterator<Item = Parameter<'a>> {
+        self.0.child_nodes().filter_map(Parameter::cast)
+    }
+}
+
+cst_node!(Parameter);
+
+impl<'a> Parameter<'a> {
+    pub fn name(&self) -> Option<&'a Token> {
+        self.0.child_tokens().next()
+    }
+
+    pub fn ty(&self) -> Option<&'a SyntaxNode> {
+        self.0.child_nodes().find(|node| node.kind.is_type())
+    }
+}
+
+cst_node!(Block);
+
+impl<'a> Block<'a> {
+    pub fn statements(&self) -> impl Iterator<Item = &'a SyntaxNode> {
+        self.0
+            .child_nodes()
+            .filter(|node| !node.kind.is_expression())
+    }
+
+    /// The expression the block evaluates to, written without a semicolon.
+    pub fn last_expression(&self) -> Option<&'a SyntaxNode> {
+        self.0
+            .child_nodes()
+            .last()
+            .filter(|node| node.kind.is_expression())
+    }
+}
+
+cst_node!(ExpressionStatement);
+
+impl<'a> ExpressionStatement<'a> {
+    pub fn expression(&self) -> Option<&'a SyntaxNode> {
+        self.0.nth_expression(0)
+    }
+}
+
+cst_node!(LetStatement);
+
+impl<'a> LetStatement<'a> {
+    /// The value after `=`, if the local is given one.
+    pub fn value(&self) -> Option<&'a SyntaxNode> {
+        self.0.nth_expression(0)
+    }
+}
+
+cst_node!(IfExpression);
+
+impl<'a> IfExpression<'a> {
+    pub fn condition(&self) -> Option<&'a SyntaxNode> {
+        self.0.nth_expression(0)
+    }
+
+    pub fn body(&self) -> Option<Block<'a>> {
+        self.0.child(Block::cast)
+    }
+
+    pub fn else_clause(&self) -> Option<ElseClause<'a>> {
+        self.0.child(ElseClause::cast)
+    }
+}
+
+cst_node!(ElseClause);
+
+impl<'a> ElseClause<'a> {
+    pub fn if_expression(&self) -> Option<IfExpression<'a>> {
+        self.0.child(IfExpression::cast)
+    }
+
+    pub fn block(&self) -> Option<Block<'a>> {
+        self.0.child(Block::cast)
+    }
+}
+
+cst_node!(ClosureExpression);
+
+impl<'a> ClosureExpression<'a> {
+    pub fn is_move(&self) -> bool {
+        self.0.child_token(TokenKind::KeywordMove).is_some()
+    }
+
+    pub fn parameter_list(&self) -> Option<ParameterList<'a>> {
+        self.0.child(ParameterList::cast)
+    }
+
+    pub fn return_type(&self) -> Option<&'a SyntaxNode> {
+        self.0
+            .child_nodes()
+            .find(|node| node.kind == SyntaxKind::ReturnType)?
+            .child_nodes()
+            .find(|node| node.kind.is_type())
+    }
+
+    /// The expression or the block the closure evaluates.
+    pub fn body(&self) -> Option<&'a SyntaxNode> {
+        self.0
+            .child_nodes()
+            .find(|node| node.kind.is_expression() || node.kind == SyntaxKind::Block)
+    }
+}
+
+cst_node!(UnsafeExpression);
+
+impl<'a> UnsafeExpression<'a> {
+    pub fn body(&self) -> Option<Block<'a>> {
+        self.0.child(Block::cast)
+    }
+}
+
+cst_node!(MatchExpression);
+
+impl<'a> MatchExpression<'a> {
+    pub fn scrutinee(&self) -> Option<&'a SyntaxNode> {
+        self.0.nth_expression(0)
+    }
+
+    pub fn arms(&self) -> impl Iterator<Item = MatchArm<'a>> {
+        self.0.child_nodes().filter_map(MatchArm::cast)
+    }
+}
+
+cst_node!(MatchArm);
+
+impl<'a> MatchArm<'a> {
+    /// The literal or the name the arm matches.
+    pub fn pattern(&self) -> Option<&'a Token> {
+        self.0.child_tokens().next()
+    }
+
+    /// The condition behind `if`.
+    pub fn guard(&self) -> Option<&'a SyntaxNode> {
+        self.0
+            .child_nodes()
+            .find(|node| node.kind == SyntaxKind::MatchGuard)?
+            .nth_expression(0)
+    }
+
+    /// The expression or the block behind `=>`.
+    pub fn body(&self) -> Option<&'a SyntaxNode> {
+        self.0
+            .child_nodes()
+            .find(|node| node.kind.is_expression() || node.kind == SyntaxKind::Block)
+    }
+}
+
+cst_node!(InfixExpression);
+
+impl<'a> InfixExpression<'a> {
+    pub fn lhs(&self) -> Option<&'a SyntaxNode> {
+        self.0.nth_expression(0)
+    }
+
+    /// The tokens spelling the operator, like `<` and `<=` for `<<=`.
+    pub fn operator(&self) -> Vec<&'a Token> {
+        self.0.child_tokens().collect()
+    }
+
+    pub fn rhs(&self) -> Option<&'a SyntaxNode> {
+        self.0.nth_expression(1)
+    }
+}
+
+cst_node!(PrefixExpression);
+
+impl<'a> PrefixExpression<'a> {
+    pub fn operator(&self) -> Option<&'a Token> {
+        self.0.child_tokens().next()
+    }
+
+    pub fn operand(&self) -> Option<&'a SyntaxNode> {
+        self.0.nth_expression(0)
+    }
+}
+
+cst_node!(CallExpression);
+
+impl<'a> CallExpression<'a> {
+    pub fn callee(&self) -> Option<&'a SyntaxNode> {
+        self.0.nth_expression(0)
+    }
+
+    pub fn argument(&self) -> Option<&'a SyntaxNode> {
+        self.0.nth_expression(1)
+    }
+}
+
+cst_node!(IndexExpression);
+
+impl<'a> IndexExpression<'a> {
+    pub fn target(&self) -> Option<&'a SyntaxNode> {
+        self.0.nth_expression(0)
+    }
+
+    pub fn index(&self) -> Option<&'a SyntaxNode> {
+        self.0.nth_expression(1)
+    }
+}
+
+cst_node!(TypeArgumentsExpression);
+
+impl<'a> TypeArgumentsExpression<'a> {
+    pub fn target(&self) -> Option<&'a SyntaxNode> {
+        self.0.nth_expression(0)
+    }
+
+    pub fn type_arguments(&self) -> Option<&'a SyntaxNode> {
+        self.0
+            .child_nodes()
+            .find(|node| node.kind == SyntaxKind::TypeArgumentList)
+    }
+}
+
+cst_node!(TupleExpression);
+
+impl<'a> TupleExpression<'a> {
+    pub fn elements(&self) -> impl Iterator<Item = &'a SyntaxNode> {
+        self.0.expressions()
+    }
+}
+
+cst_node!(NameExpression);
+
+impl<'a> NameExpression<'a> {
+    pub fn token(&self) -> Option<&'a Token> {
+        self.0.child_tokens().next()
+    }
+}
+
+cst_node!(TodoExpression);
+
+impl<'a> TodoExpression<'a> {
+    pub fn keyword(&self) -> Option<&'a Token> {
+        self.0.child_tokens().next()
+    }
+
+    pub fn message(&self) -> Option<&'a Token> {
+        self.0
+            .child_tokens()
+            .find(|token| matches!(token.kind, TokenKind::LiteralString(_)))
+    }
+}
+
+cst_node!(IntrinsicExpression);
+
+impl<'a> IntrinsicExpression<'a> {
+    pub fn name(&self) -> Option<&'a Token> {
+        self.0.identifier()
+    }
+}
+
+cst_node!(InitExpression);
+
+impl<'a> InitExpression<'a> {
+    pub fn name(&self) -> Option<&'a Token> {
+        self.0.identifier()
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = InitField<'a>> {
+        self.0.child_nodes().filter_map(InitField::cast)
+    }
+}
+
+cst_node!(InitField);
+
+impl<'a> InitField<'a> {
+    pub fn name(&self) -> Option<&'a Token> {
+        self.0.identifier()
+    }
+
+    /// The value behind the `:`, which is `None` for a shorthand field.
+    pub fn value(&self) -> Option<&'a SyntaxNode> {
+        self.0.nth_expression(0)
+    }
+}
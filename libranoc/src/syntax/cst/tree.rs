@@ -0,0 +1,163 @@
+use std::{fmt, ops::Range};
+
+use crate::syntax::Token;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    Module,
+    Attribute,
+    FunctionDeclaration,
+    OperatorDeclaration,
+    UseDeclaration,
+    ModuleDeclaration,
+    UseTree,
+    UseTreeList,
+    GenericParameterList,
+    GenericParameter,
+    ParameterList,
+    Parameter,
+    ReturnType,
+    Type,
+    TupleType,
+    ParenthesizedType,
+    FunctionType,
+    NullableType,
+    TypeArgumentList,
+    Block,
+    ExpressionStatement,
+    LetStatement,
+    LiteralExpression,
+    InterpolationExpression,
+    NameExpression,
+    TodoExpression,
+    IntrinsicExpression,
+    IfExpression,
+    ElseClause,
+    MatchExpression,
+    ClosureExpression,
+    UnsafeExpression,
+    MatchArm,
+    MatchGuard,
+    InitExpression,
+    InitField,
+    ParenthesizedExpression,
+    TupleExpression,
+    PrefixExpression,
+    InfixExpression,
+    CallExpression,
+    IndexExpression,
+    /// `g::<i32>`.
+    TypeArgumentsExpression,
+    /// Tokens which don't fit where they are written.
+    Error,
+}
+
+/// The source text between two tokens, which the tokenizer skips, along
+/// with doc comments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub text: String,
+    pub range: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(Token),
+    Trivia(Trivia),
+}
+
+impl SyntaxElement {
+    pub fn range(&self) -> Option<Range<usize>> {
+        match self {
+            SyntaxElement::Node(node) => node.range(),
+            SyntaxElement::Token(token) => Some(token.span.range.clone()),
+            SyntaxElement::Trivia(trivia) => Some(trivia.range.clone()),
+        }
+    }
+}
+
+impl fmt::Display for SyntaxElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntaxElement::Node(node) => write!(f, "{}", node),
+            SyntaxElement::Token(token) => write!(f, "{}", token.content),
+            SyntaxElement::Trivia(trivia) => write!(f, "{}", trivia.text),
+        }
+    }
+}
+
+/// A node of the concrete syntax tree. Unlike the AST it keeps every token
+/// and the trivia around them, so printing the root gives back the source
+/// byte for byte.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    pub(super) kind: SyntaxKind,
+    pub(super) children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn children(&self) -> &[SyntaxElement] {
+        &self.children
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            _ => None,
+        })
+    }
+
+    /// The tokens directly inside of this node, like the keywords and the
+    /// punctuations of a construct.
+    pub fn child_tokens(&self) -> impl Iterator<Item = &Token> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            _ => None,
+        })
+    }
+
+    /// Every token inside of this node, in source order.
+    pub fn descendant_tokens(&self) -> Vec<&Token> {
+        let mut tokens = Vec::new();
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.descendant_tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+                SyntaxElement::Trivia(_) => {}
+            }
+        }
+        tokens
+    }
+
+    /// The byte range the node covers, which is `None` only for an empty
+    /// module.
+    pub fn range(&self) -> Option<Range<usize>> {
+        let start = self.children.iter().find_map(SyntaxElement::range)?;
+        let end = self.children.iter().rev().find_map(SyntaxElement::range)?;
+        Some(start.start..end.end)
+    }
+
+    /// The innermost node covering `offset`.
+    pub fn node_at(&self, offset: usize) -> Option<&SyntaxNode> {
+        if !self.range()?.contains(&offset) {
+            return None;
+        }
+        self.child_nodes()
+            .find_map(|node| node.node_at(offset))
+            .or(Some(self))
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            write!(f, "{}", child)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,629 @@
+use std::fmt;
+
+use crate::{
+    core::ast::*,
+    syntax::{parse, tokenize, Span, Spanned, Token},
+};
+
+/// One node of the AST as `parse_debug` shows it: what kind of node it is,
+/// where it is and the nodes inside of it. The span of a node is the one
+/// the AST has for it, and is `None` only for an empty module.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DebugNode {
+    pub kind: &'static str,
+    pub span: Option<Span>,
+    /// The source of a token.
+    pub text: Option<String>,
+    pub children: Vec<DebugNode>,
+}
+
+/// Parses `src` and returns its AST as a tree of `DebugNode`s. Printing the
+/// tree gives one line for every node, indented by its depth, with the
+/// byte range and the line and column it starts at. `fn f() {}` gives
+///
+/// ```text
+/// Module 0..9 1:1
+///   FunctionDeclaration 0..9 1:1
+///     Name 3..4 1:4 "f"
+///     ReturnType 6..6 1:7
+///       TupleType 6..6 1:7
+///     Block 7..9 1:8
+///       LeftCurlyBracket 7..8 1:8 "{"
+///       RightCurlyBracket 8..9 1:9 "}"
+/// ```
+pub fn parse_debug(src: &str) -> crate::core::Result<DebugNode> {
+    Ok(DebugNode::from(&parse(tokenize(src))?))
+}
+
+/// Parses `src` and returns its AST as JSON, for the tools consuming Rano
+/// ASTs without linking libranoc. Every enum is written as an object with
+/// the variant as its only key, and every token and node keeps its span.
+pub fn parse_to_json(src: &str) -> crate::core::Result<String> {
+    crate::core::to_json(&parse(tokenize(src))?)
+}
+
+impl DebugNode {
+    fn new(kind: &'static str, children: Vec<DebugNode>) -> Self {
+        let span = children
+            .iter()
+            .filter_map(|child| child.span.as_ref())
+            .fold(None, |span: Option<Span>, child| match span {
+                Some(span) => Some(span.joined(child)),
+                None => Some(child.clone()),
+            });
+        DebugNode {
+            kind,
+            span,
+            text: None,
+            children,
+        }
+    }
+
+    fn token(kind: &'static str, token: &Token) -> Self {
+        DebugNode {
+            kind,
+            span: Some(token.span.clone()),
+            text: Some(token.content.clone()),
+            children: Vec::new(),
+        }
+    }
+
+    fn leaf(kind: &'static str, span: Span, text: impl Into<String>) -> Self {
+        DebugNode {
+            kind,
+            span: Some(span),
+            text: Some(text.into()),
+            children: Vec::new(),
+        }
+    }
+
+    /// Gives the node the span of `node`, for the nodes whose span reaches
+    /// past their children, like a tuple over its parentheses.
+    fn spanning(mut self, node: &impl Spanned) -> Self {
+        self.span = Some(node.span());
+        self
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}{}", "", self.kind, indent = depth * 2)?;
+        if let Some(span) = &self.span {
+            write!(
+                f,
+                " {}..{} {}:{}",
+                span.range.start,
+                span.range.end,
+                span.line + 1,
+                span.column + 1
+            )?;
+        }
+        if let Some(text) = &self.text {
+            write!(f, " {:?}", text)?;
+        }
+        writeln!(f)?;
+        for child in &self.children {
+            child.write(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DebugNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+impl From<&Module> for DebugNode {
+    fn from(module: &Module) -> Self {
+        DebugNode::new("Module", module.nodes.iter().map(node).collect())
+    }
+}
+
+fn node(node: &Node) -> DebugNode {
+    match node {
+        Node::Directive(directive) => {
+            DebugNode::new("Directive", vec![attribute(&directive.attribute)])
+        }
+        Node::Statement(statement) => self::statement(statement),
+    }
+}
+
+fn attribute(attribute: &Attribute) -> DebugNode {
+    DebugNode::new(
+        "Attribute",
+        vec![
+            DebugNode::token("NumberSign", &attribute.number_sign_token),
+            attribute_value(&attribute.value),
+            DebugNode::token("RightSquareBracket", &attribute.right_square_bracket_token),
+        ],
+    )
+}
+
+fn attribute_value(value: &AttributeValue) -> DebugNode {
+    match value {
+        AttributeValue::Word(word) => DebugNode::token("Word", word),
+        AttributeValue::Literal(literal) => DebugNode::token("Literal", literal),
+        AttributeValue::KeyValue { key, value } => DebugNode::new(
+            "KeyValue",
+            vec![DebugNode::token("Key", key), attribute_value(value)],
+        ),
+        AttributeValue::List {
+            name,
+            items,
+            right_parenthesis_token,
+        } => {
+            let mut children = vec![DebugNode::token("Name", name)];
+            children.extend(items.iter().map(attribute_value));
+            children.push(DebugNode::token(
+                "RightParenthesis",
+                right_parenthesis_token,
+            ));
+            DebugNode::new("List", children)
+        }
+    }
+}
+
+fn documentation(documentation: &Option<Documentation>) -> Vec<DebugNode> {
+    documentation
+        .iter()
+        .map(|documentation| {
+            DebugNode::new(
+                "Documentation",
+                documentation
+                    .lines
+                    .iter()
+                    .map(|line| DebugNode::token("DocComment", line))
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+fn statement(statement: &Statement) -> DebugNode {
+    match statement {
+        Statement::Declaration(declaration) => self::declaration(declaration),
+        Statement::Let(r#let) => {
+            let mut children = vec![
+                DebugNode::token("Let", &r#let.let_token),
+                pattern(&r#let.pattern),
+            ];
+            children.extend(r#let.ty.iter().map(ty));
+            children.extend(r#let.value.iter().map(expression));
+            DebugNode::new("LetStatement", children).spanning(r#let)
+        }
+        Statement::Expression(expression) => {
+            DebugNode::new("ExpressionStatement", vec![self::expression(expression)])
+        }
+    }
+}
+
+fn declaration(declaration: &Declaration) -> DebugNode {
+    match declaration {
+        Declaration::FunctionDeclaration(function) => function_declaration(function),
+        Declaration::UseDeclaration(declaration) => {
+            let mut children = documentation(&declaration.documentation);
+            children.push(DebugNode::token("Use", &declaration.use_token));
+            children.push(use_tree(&declaration.tree));
+            let mut node = DebugNode::new("UseDeclaration", children).spanning(declaration);
+            if declaration.is_pub {
+                node.text = Some("pub".to_string());
+            }
+            node
+        }
+        Declaration::OperatorDeclaration(declaration) => {
+            let mut children = documentation(&declaration.documentation);
+            children.push(DebugNode::token("Operator", &declaration.operator_token));
+            children.extend(
+                declaration
+                    .symbol
+                    .iter()
+                    .map(|token| DebugNode::token("Symbol", token)),
+            );
+            children.push(DebugNode::token("Precedence", &declaration.precedence));
+            children.push(DebugNode::token(
+                "Associativity",
+                &declaration.associativity,
+            ));
+            children.push(DebugNode::token("Function", &declaration.function));
+            DebugNode::new("OperatorDeclaration", children).spanning(declaration)
+        }
+        Declaration::ModuleDeclaration(declaration) => {
+            let mut children = documentation(&declaration.documentation);
+            children.push(DebugNode::token("Mod", &declaration.mod_token));
+            children.push(DebugNode::token("Name", &declaration.name));
+            match &declaration.body {
+                ModuleBody::Inline {
+                    curly_bracket_open_token,
+                    module,
+                    curly_bracket_close_token,
+                } => {
+                    children.push(DebugNode::token(
+                        "LeftCurlyBracket",
+                        curly_bracket_open_token,
+                    ));
+                    children.push(DebugNode::from(module));
+                    children.push(DebugNode::token(
+                        "RightCurlyBracket",
+                        curly_bracket_close_token,
+                    ));
+                }
+                ModuleBody::File(semicolon_token) => {
+                    children.push(DebugNode::token("Semicolon", semicolon_token))
+                }
+            }
+            let mut node = DebugNode::new("ModuleDeclaration", children).spanning(declaration);
+            if declaration.is_pub {
+                node.text = Some("pub".to_string());
+            }
+            node
+        }
+    }
+}
+
+fn function_declaration(function: &FunctionDeclaration) -> DebugNode {
+    let mut children = documentation(&function.documentation);
+    children.extend(function.attributes.iter().map(attribute));
+    children.push(DebugNode::token("Name", &function.name));
+    children.extend(function.generic_parameters.iter().map(|parameter| {
+        let mut children = vec![DebugNode::token("Name", &parameter.name)];
+        children.extend(parameter.bounds.iter().map(ty));
+        DebugNode::new("GenericParameter", children)
+    }));
+    children.extend(function.parameters.iter().map(|(pattern, parameter_type)| {
+        DebugNode::new(
+            "Parameter",
+            vec![self::pattern(pattern), ty(parameter_type)],
+        )
+    }));
+    children.push(DebugNode::new(
+        "ReturnType",
+        vec![ty(&function.return_type)],
+    ));
+    children.extend(function.body.iter().map(block));
+    let mut node = DebugNode::new("FunctionDeclaration", children).spanning(function);
+    node.text = match (function.is_pub, function.is_extern) {
+        (true, true) => Some("pub extern".to_string()),
+        (true, false) => Some("pub".to_string()),
+        (false, true) => Some("extern".to_string()),
+        (false, false) => None,
+    };
+    node
+}
+
+fn use_tree(tree: &UseTree) -> DebugNode {
+    let mut children = vec![path(&tree.prefix)];
+    match &tree.kind {
+        UseTreeKind::Simple(alias) => {
+            children.extend(alias.iter().map(|alias| DebugNode::token("Alias", alias)))
+        }
+        UseTreeKind::Glob(star) => children.push(DebugNode::token("Glob", star)),
+        UseTreeKind::Nested(trees) => children.extend(trees.iter().map(use_tree)),
+    }
+    DebugNode::new("UseTree", children).spanning(tree)
+}
+
+fn path(path: &Path) -> DebugNode {
+    DebugNode::new(
+        "Path",
+        path.0
+            .iter()
+            .map(|segment| DebugNode::token("Segment", segment))
+            .collect(),
+    )
+}
+
+fn pattern(pattern: &Pattern) -> DebugNode {
+    match pattern {
+        Pattern::Slot(slot) => DebugNode::new("SlotPattern", vec![name(slot)]),
+        Pattern::Literal(literal) => DebugNode::new("LiteralPattern", vec![self::literal(literal)]),
+    }
+}
+
+fn name(name: &Name) -> DebugNode {
+    match name {
+        Name::Ident(token) => DebugNode::token("Name", token),
+        Name::Placeholder(token) => DebugNode::token("Placeholder", token),
+    }
+}
+
+fn ty(ty: &Type) -> DebugNode {
+    match ty {
+        Type::Basic {
+            base,
+            type_parameters,
+            ..
+        } => {
+            let mut children = vec![path(base)];
+            children.extend(type_parameters.iter().map(|parameter| match parameter {
+                TypeParameter::Star(span) => {
+                    DebugNode::leaf("StarTypeParameter", span.clone(), "*")
+                }
+                TypeParameter::Specific(parameter) => self::ty(parameter),
+            }));
+            DebugNode::new("BasicType", children).spanning(ty)
+        }
+        Type::Tuple(types, _) => {
+            DebugNode::new("TupleType", types.iter().map(self::ty).collect()).spanning(ty)
+        }
+        Type::Impl(_, inner) => DebugNode::new("ImplType", vec![self::ty(inner)]).spanning(ty),
+        Type::Dyn(_, inner) => DebugNode::new("DynType", vec![self::ty(inner)]).spanning(ty),
+        Type::Nullable(inner, _) => {
+            DebugNode::new("NullableType", vec![self::ty(inner)]).spanning(ty)
+        }
+        Type::Function {
+            parameters_type,
+            return_type,
+            ..
+        } => {
+            let mut children: Vec<_> = parameters_type.iter().map(self::ty).collect();
+            children.push(self::ty(return_type));
+            DebugNode::new("FunctionType", children).spanning(ty)
+        }
+        Type::Never(span) => DebugNode::leaf("NeverType", span.clone(), "!"),
+    }
+}
+
+fn block(block: &Block) -> DebugNode {
+    let mut children = vec![DebugNode::token(
+        "LeftCurlyBracket",
+        &block.curly_bracket_open_token,
+    )];
+    children.extend(block.body.iter().map(statement));
+    children.extend(block.last_expression.iter().map(expression));
+    children.push(DebugNode::token(
+        "RightCurlyBracket",
+        &block.curly_bracket_close_token,
+    ));
+    DebugNode::new("Block", children)
+}
+
+fn r#if(r#if: &If) -> DebugNode {
+    let mut children = vec![
+        DebugNode::token("If", &r#if.if_token),
+        expression(&r#if.condition),
+        block(&r#if.body),
+    ];
+    match &r#if.else_part {
+        Some(Else::If(else_token, inner)) => children.push(DebugNode::new(
+            "Else",
+            vec![DebugNode::token("Else", else_token), self::r#if(inner)],
+        )),
+        Some(Else::Block(else_token, inner)) => children.push(DebugNode::new(
+            "Else",
+            vec![DebugNode::token("Else", else_token), block(inner)],
+        )),
+        None => {}
+    }
+    DebugNode::new("IfExpression", children)
+}
+
+fn literal(literal: &Literal) -> DebugNode {
+    match literal {
+        Literal::String(token) => DebugNode::token("StringLiteral", token),
+        Literal::Character(token) => DebugNode::token("CharacterLiteral", token),
+        Literal::Integer(Integer(token)) => DebugNode::token("IntegerLiteral", token),
+        Literal::Decimal(token) => DebugNode::token("DecimalLiteral", token),
+        Literal::Boolean(token) => DebugNode::token("BooleanLiteral", token),
+    }
+}
+
+fn expression(expression: &Expression) -> DebugNode {
+    match expression {
+        Expression::Match(r#match) => {
+            let mut children = vec![
+                DebugNode::token("Match", &r#match.match_token),
+                self::expression(&r#match.scrutinee),
+            ];
+            children.extend(r#match.arms.iter().map(|arm| {
+                let mut children = vec![pattern(&arm.pattern)];
+                children.extend(
+                    arm.guard
+                        .iter()
+                        .map(|guard| DebugNode::new("Guard", vec![self::expression(guard)])),
+                );
+                children.push(match &arm.body {
+                    MatchArmBody::Expression(expression) => self::expression(expression),
+                    MatchArmBody::Block(body) => block(body),
+                });
+                DebugNode::new("MatchArm", children)
+            }));
+            children.push(DebugNode::token(
+                "RightCurlyBracket",
+                &r#match.curly_bracket_close_token,
+            ));
+            DebugNode::new("MatchExpression", children)
+        }
+        Expression::Closure(closure) => {
+            let mut children: Vec<_> = closure
+                .move_token
+                .iter()
+                .map(|token| DebugNode::token("Move", token))
+                .collect();
+            children.push(DebugNode::token(
+                "VerticalLine",
+                &closure.vertical_line_token,
+            ));
+            children.extend(closure.parameters.iter().map(|(pattern, parameter_type)| {
+                let mut children = vec![self::pattern(pattern)];
+                children.extend(parameter_type.iter().map(ty));
+                DebugNode::new("Parameter", children)
+            }));
+            children.extend(
+                closure
+                    .return_type
+                    .iter()
+                    .map(|return_type| DebugNode::new("ReturnType", vec![ty(return_type)])),
+            );
+            children.push(match &closure.body {
+                ClosureBody::Expression(expression) => self::expression(expression),
+                ClosureBody::Block(body) => block(body),
+            });
+            DebugNode::new("ClosureExpression", children)
+        }
+        Expression::Path(path) => DebugNode::new("PathExpression", vec![self::path(path)]),
+        Expression::Array(elements, _) => DebugNode::new(
+            "ArrayExpression",
+            elements.iter().map(self::expression).collect(),
+        )
+        .spanning(expression),
+        Expression::Literal(literal) => self::literal(literal),
+        Expression::Tuple(elements, _) => DebugNode::new(
+            "TupleExpression",
+            elements.iter().map(self::expression).collect(),
+        )
+        .spanning(expression),
+        Expression::Init(init) => {
+            let mut children = vec![DebugNode::token("Name", &init.name)];
+            children.extend(init.fields.iter().map(|field| {
+                DebugNode::new(
+                    "InitField",
+                    vec![
+                        DebugNode::token("Name", &field.name),
+                        self::expression(&field.value),
+                    ],
+                )
+            }));
+            children.push(DebugNode::token(
+                "RightCurlyBracket",
+                &init.curly_bracket_close_token,
+            ));
+            DebugNode::new("InitExpression", children)
+        }
+        Expression::Operator(operator) => self::operator(operator),
+        Expression::Name(name) => DebugNode::new("NameExpression", vec![self::name(name)]),
+        Expression::If(r#if) => self::r#if(r#if),
+        Expression::Unsafe(r#unsafe) => DebugNode::new(
+            "UnsafeExpression",
+            vec![
+                DebugNode::token("Unsafe", &r#unsafe.unsafe_token),
+                block(&r#unsafe.block),
+            ],
+        ),
+        Expression::Interpolation(parts) => DebugNode::new(
+            "InterpolationExpression",
+            parts
+                .iter()
+                .map(|part| match part {
+                    InterpolationPart::Fragment(fragment) => DebugNode::token("Fragment", fragment),
+                    InterpolationPart::Expression(expression) => self::expression(expression),
+                })
+                .collect(),
+        ),
+        Expression::Todo(todo) => {
+            let mut children = vec![DebugNode::token("Keyword", &todo.keyword)];
+            children.extend(
+                todo.message
+                    .iter()
+                    .map(|message| DebugNode::token("Message", message)),
+            );
+            DebugNode::new("TodoExpression", children)
+        }
+        Expression::Intrinsic(intrinsic) => {
+            let mut children = vec![DebugNode::token("Name", &intrinsic.name)];
+            children.extend(
+                intrinsic
+                    .type_arguments
+                    .iter()
+                    .map(|argument| match argument {
+                        TypeParameter::Star(span) => {
+                            DebugNode::leaf("StarTypeParameter", span.clone(), "*")
+                        }
+                        TypeParameter::Specific(argument) => self::ty(argument),
+                    }),
+            );
+            DebugNode::new("IntrinsicExpression", children).spanning(&intrinsic.span)
+        }
+    }
+}
+
+fn operator(operator: &Operator) -> DebugNode {
+    match operator {
+        Operator::Prefix(prefix) => {
+            let (kind, span, operand) = match prefix {
+                PrefixOperator::Not(Not(span, operand)) => ("Not", span, operand),
+                PrefixOperator::UnaryPlus(UnaryPlus(span, operand)) => ("UnaryPlus", span, operand),
+                PrefixOperator::UnaryMinus(UnaryMinus(span, operand)) => {
+                    ("UnaryMinus", span, operand)
+                }
+            };
+            let operator = DebugNode {
+                kind: "Operator",
+                span: Some(span.clone()),
+                text: None,
+                children: Vec::new(),
+            };
+            let mut node = DebugNode::new("PrefixExpression", vec![operator, expression(operand)]);
+            node.text = Some(kind.to_string());
+            node
+        }
+        Operator::Infix(infix) => infix_operator(infix),
+        Operator::Postfix(PostfixOperator::Index(index)) => {
+            let Index(target, indexes, _) = index;
+            let mut children = vec![expression(target)];
+            children.extend(indexes.iter().map(expression));
+            DebugNode::new("IndexExpression", children).spanning(index)
+        }
+        Operator::Postfix(PostfixOperator::TypeArguments(type_arguments)) => {
+            let TypeArguments(target, arguments, _) = type_arguments;
+            let mut children = vec![expression(target)];
+            children.extend(arguments.iter().map(|argument| match argument {
+                TypeParameter::Star(span) => {
+                    DebugNode::leaf("StarTypeParameter", span.clone(), "*")
+                }
+                TypeParameter::Specific(argument) => self::ty(argument),
+            }));
+            DebugNode::new("TypeArgumentsExpression", children).spanning(type_arguments)
+        }
+        Operator::Postfix(PostfixOperator::FunctionCall(FunctionCall(callee, arguments))) => {
+            let mut children = vec![expression(callee)];
+            children.extend(arguments.iter().map(expression));
+            DebugNode::new("CallExpression", children)
+        }
+    }
+}
+
+/// The span of the operator itself, which isn't kept for `.` and `?.`.
+fn operator_span(infix: &InfixOperator) -> Option<Span> {
+    match infix {
+        InfixOperator::LogicalOr(_, span, _)
+        | InfixOperator::LogicalAnd(_, span, _)
+        | InfixOperator::EqualTo(_, span, _)
+        | InfixOperator::NotEqualTo(_, span, _)
+        | InfixOperator::GreaterThan(_, span, _)
+        | InfixOperator::LessThan(_, span, _)
+        | InfixOperator::GreaterThanOrEqualTo(_, span, _)
+        | InfixOperator::LessThanOrEqualTo(_, span, _)
+        | InfixOperator::Add(_, span, _)
+        | InfixOperator::Subtract(_, span, _)
+        | InfixOperator::Multiply(_, span, _)
+        | InfixOperator::Divide(_, span, _)
+        | InfixOperator::Remainder(_, span, _)
+        | InfixOperator::RangeRightExclusive(_, span, _)
+        | InfixOperator::RangeRightInclusive(_, span, _)
+        | InfixOperator::BitAnd(_, span, _)
+        | InfixOperator::BitOr(_, span, _)
+        | InfixOperator::BitXor(_, span, _)
+        | InfixOperator::ShiftLeft(_, span, _)
+        | InfixOperator::ShiftRight(_, span, _)
+        | InfixOperator::Assign(_, span, _) => Some(span.clone()),
+        InfixOperator::GetField(_) | InfixOperator::GetFieldNullable(_) => None,
+        InfixOperator::CompoundAssign(operation) => operator_span(operation),
+    }
+}
+
+fn infix_operator(infix: &InfixOperator) -> DebugNode {
+    let (lhs, rhs) = infix.operands();
+    let mut children = vec![expression(lhs)];
+    if let Some(span) = operator_span(infix) {
+        children.push(DebugNode {
+            kind: "Operator",
+            span: Some(span),
+            text: None,
+            children: Vec::new(),
+        });
+    }
+    children.push(expression(rhs));
+    let mut node = DebugNode::new("InfixExpression", children);
+    node.text = Some(infix.trait_name().to_string());
+    node
+}
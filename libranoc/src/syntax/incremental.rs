@@ -0,0 +1,165 @@
+use std::ops::Range;
+
+use super::{
+    tokenize::{create_tokenizer_with, resume_tokenizer_with},
+    Token, TokenKind, TokenizeOptions,
+};
+
+/// Which tokens an edit replaced: `removed` indexes the token list before the
+/// edit and `inserted` the one after it. Every other token is unchanged apart
+/// from being moved.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TokenDelta {
+    pub removed: Range<usize>,
+    pub inserted: Range<usize>,
+}
+
+/// A source text together with its tokens, kept up to date by re-lexing only
+/// the region around each edit.
+#[derive(Debug, Clone)]
+pub struct IncrementalTokenizer {
+    options: TokenizeOptions,
+    source: String,
+    tokens: Vec<Token>,
+}
+
+/// The change of the interpolation nesting caused by `kind`.
+fn nesting(kind: &TokenKind) -> isize {
+    match kind {
+        TokenKind::LiteralStringHead(_) => 1,
+        TokenKind::LiteralStringTail(_) => -1,
+        _ => 0,
+    }
+}
+
+impl IncrementalTokenizer {
+    pub fn new(source: impl Into<String>, options: TokenizeOptions) -> Self {
+        let source = source.into();
+        let tokens = create_tokenizer_with(&source, options.clone()).collect();
+        IncrementalTokenizer {
+            options,
+            source,
+            tokens,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn into_tokens(self) -> Vec<Token> {
+        self.tokens
+    }
+
+    /// Replaces the bytes in `range` with `text` and re-lexes as few tokens
+    /// as possible.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds or not on `char` boundaries.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) -> TokenDelta {
+        self.source.replace_range(range.clone(), text);
+        let shift = text.len() as isize - range.len() as isize;
+
+        // `top_level[i]` tells whether tokens[i] starts outside of every
+        // interpolated string, so lexing can be resumed there.
+        let mut depth = 0;
+        let top_level = self
+            .tokens
+            .iter()
+            .map(|token| {
+                let resumable = depth == 0;
+                depth += nesting(&token.kind);
+                resumable
+            })
+            .collect::<Vec<_>>();
+
+        let mut start = self
+            .tokens
+            .iter()
+            .position(|token| token.span.range.end >= range.start)
+            .unwrap_or(self.tokens.len())
+            .min(self.tokens.len().saturating_sub(1));
+        while start > 0 && self.tokens[start].span.range.start > range.start {
+            start -= 1;
+        }
+        // an edit can merge a token with the ones glued to its left, like
+        // `1.5e+` followed by `3`, and complete a literal which failed to
+        // lex earlier, like an unclosed `"`.
+        while start > 0
+            && self.tokens[start - 1].span.range.end == self.tokens[start].span.range.start
+        {
+            start -= 1;
+        }
+        if let Some(error) = self.tokens[..start]
+            .iter()
+            .position(|token| token.kind == TokenKind::Error)
+        {
+            start = error;
+        }
+        while start > 0 && !top_level[start] {
+            start -= 1;
+        }
+
+        let lexer: Box<dyn Iterator<Item = Token> + '_> = if start == 0 {
+            Box::new(create_tokenizer_with(&self.source, self.options.clone()))
+        } else {
+            Box::new(resume_tokenizer_with(
+                &self.source,
+                self.options.clone(),
+                &self.tokens[start],
+            ))
+        };
+
+        // the old tokens behind the edit are candidates to synchronize with:
+        // once a fresh token equals one of them moved by `shift`, at the same
+        // column and outside of any interpolation, the rest of the old
+        // tokens are still valid.
+        let mut old = start;
+        let mut depth = 0;
+        let mut inserted = Vec::new();
+        let mut end = self.tokens.len();
+        let mut line_shift = 0;
+        for token in lexer {
+            while old < self.tokens.len()
+                && (self.tokens[old].span.range.start as isize + shift)
+                    < token.span.range.start as isize
+            {
+                old += 1;
+            }
+            if depth == 0 {
+                if let Some(candidate) = self.tokens.get(old) {
+                    if candidate.span.range.start >= range.end
+                        && top_level[old]
+                        && candidate.kind == token.kind
+                        && candidate.span.column == token.span.column
+                        && candidate.span.len == token.span.len
+                        && (candidate.span.range.start as isize + shift)
+                            == token.span.range.start as isize
+                    {
+                        end = old;
+                        line_shift = token.span.line as isize - candidate.span.line as isize;
+                        break;
+                    }
+                }
+            }
+            depth += nesting(&token.kind);
+            inserted.push(token);
+        }
+
+        for token in &mut self.tokens[end..] {
+            token.span = token.span.shifted(shift, line_shift);
+        }
+        let inserted_len = inserted.len();
+        self.tokens.splice(start..end, inserted);
+
+        TokenDelta {
+            removed: start..end,
+            inserted: start..(start + inserted_len),
+        }
+    }
+}
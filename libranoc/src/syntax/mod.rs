@@ -1,6 +1,21 @@
+pub mod cst;
+mod debug;
+mod incremental;
 mod parse;
+mod source;
+mod suggest;
 mod tokenize;
 
-pub use parse::parse;
+pub use debug::{parse_debug, parse_to_json, DebugNode};
+pub use incremental::{IncrementalTokenizer, TokenDelta};
 pub(crate) use parse::Error;
-pub use tokenize::{create_tokenizer, tokenize, Span, Spanned, Token, TokenKind};
+pub use parse::{
+    parse, parse_recovering, parse_recovering_with, parse_with, snippet, IncrementalParser,
+    NodeDelta, ParseOptions,
+};
+pub use source::{decode_source, DecodeError};
+pub use suggest::{edit_distance, suggest_keyword};
+pub use tokenize::{
+    create_tokenizer, create_tokenizer_with, tokenize, tokenize_with, ColumnMode, ExpansionId,
+    Span, Spanned, Token, TokenKind, TokenizeOptions, KEYWORDS, PUNCTUATIONS,
+};
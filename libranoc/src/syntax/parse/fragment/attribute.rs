@@ -0,0 +1,89 @@
+use crate::{
+    core::ast::{Attribute, AttributeValue},
+    syntax::parse::*,
+};
+
+fn parse_attribute_literal(i: ParseInput) -> ParseResult<Token> {
+    satisfy(|token| {
+        matches!(
+            token.kind,
+            TokenKind::LiteralString(_)
+                | TokenKind::LiteralCharacter(_)
+                | TokenKind::LiteralBoolean(_)
+                | TokenKind::LiteralNumberIntegral(_)
+                | TokenKind::LiteralNumberDecimal(_)
+                | TokenKind::LiteralNumberExponent(_)
+        )
+    })(i)
+}
+
+/// `word`, a literal, `key = value` or `name(value, ...)`, where lists may
+/// nest.
+pub fn parse_attribute_value(i: ParseInput) -> ParseResult<AttributeValue> {
+    if let Ok((i, literal)) = parse_attribute_literal(i.clone()) {
+        return Ok((i, AttributeValue::Literal(literal)));
+    }
+    let (i, name) = context("an attribute", parse_identifier)(i)?;
+    if let Ok((i, _)) = tag(TokenKind::PunctuationEqualsSign)(i.clone()) {
+        let (i, value) = cut(nested(parse_attribute_value))(i)?;
+        return Ok((
+            i,
+            AttributeValue::KeyValue {
+                key: name,
+                value: Box::new(value),
+            },
+        ));
+    }
+    if let Ok((i, _)) = tag(TokenKind::PunctuationLeftParenthesis)(i.clone()) {
+        let (i, (items, _)) = separated_trailing0(
+            tag(TokenKind::PunctuationComma),
+            nested(parse_attribute_value),
+        )(i)?;
+        let (i, right_parenthesis_token) = cut(tag(TokenKind::PunctuationRightParenthesis))(i)?;
+        return Ok((
+            i,
+            AttributeValue::List {
+                name,
+                items,
+                right_parenthesis_token,
+            },
+        ));
+    }
+    Ok((i, AttributeValue::Word(name)))
+}
+
+fn parse_attribute_body(i: ParseInput) -> ParseResult<(AttributeValue, Token)> {
+    let (i, _) = cut(tag(TokenKind::PunctuationLeftSquareBracket))(i)?;
+    let (i, value) = cut(parse_attribute_value)(i)?;
+    let (i, right_square_bracket_token) = cut(tag(TokenKind::PunctuationRightSquareBracket))(i)?;
+    Ok((i, (value, right_square_bracket_token)))
+}
+
+/// `#[value]` in front of a declaration.
+pub fn parse_attribute(i: ParseInput) -> ParseResult<Attribute> {
+    let (i, number_sign_token) = tag(TokenKind::PunctuationNumberSign)(i)?;
+    let (i, (value, right_square_bracket_token)) = parse_attribute_body(i)?;
+    Ok((
+        i,
+        Attribute {
+            number_sign_token,
+            value,
+            right_square_bracket_token,
+        },
+    ))
+}
+
+/// `#![value]`, applying to the file it is written in.
+pub fn parse_inner_attribute(i: ParseInput) -> ParseResult<Attribute> {
+    let (i, number_sign_token) = tag(TokenKind::PunctuationNumberSign)(i)?;
+    let (i, _) = tag(TokenKind::PunctuationExclamationMark)(i)?;
+    let (i, (value, right_square_bracket_token)) = parse_attribute_body(i)?;
+    Ok((
+        i,
+        Attribute {
+            number_sign_token,
+            value,
+            right_square_bracket_token,
+        },
+    ))
+}
@@ -1,13 +1,117 @@
-use crate::{core::ast::Block, syntax::parse::*};
+use ::nom::{error::ParseError, Err};
+
+use crate::{
+    core::ast::{Block, Expression, Statement},
+    syntax::parse::{
+        recovery::{broken_block_statement_len, recover},
+        *,
+    },
+};
+
+/// A statement of a block, or the end of the block with the expression it
+/// ends with.
+enum Part {
+    Statement(Box<Statement>),
+    End(Option<Box<Expression>>),
+}
+
+/// The next statement of a block. An expression without a `;` behind it
+/// ends the block, which is found in one pass instead of parsing the
+/// expression again after it failed as a statement, so nested blocks don't
+/// take exponential time.
+fn parse_block_part(i: ParseInput) -> ParseResult<Part> {
+    match alt((parse_let_statement, parse_declaration_statement))(i.clone()) {
+        Ok((rest, statement)) => return Ok((rest, Part::Statement(Box::new(statement)))),
+        Err(Err::Error(_)) => {}
+        Err(e) => return Err(e),
+    }
+    match parse_expression(i.clone()) {
+        Ok((rest, expression)) => match tag(TokenKind::PunctuationSemicolon)(rest.clone()) {
+            Ok((rest, _)) => {
+                let statement = Statement::Expression(expression);
+                Ok((rest, Part::Statement(Box::new(statement))))
+            }
+            Err(_) => match tag(TokenKind::PunctuationRightCurlyBracket)(rest.clone()) {
+                Ok(_) => Ok((rest, Part::End(Some(Box::new(expression))))),
+                Err(error) => Err(error.map(|error| {
+                    Error::expected(rest, Expected::Token(TokenKind::PunctuationSemicolon))
+                        .or(error)
+                })),
+            },
+        },
+        Err(Err::Error(_)) => Ok((i, Part::End(None))),
+        Err(e) => Err(e),
+    }
+}
+
+/// The next statement of a block which recovers from the broken ones,
+/// where something which is neither a statement nor the end of the block
+/// is broken too.
+fn parse_recovering_block_part(i: ParseInput) -> ParseResult<Part> {
+    let (rest, part) = parse_block_part(i)?;
+    match (&part, rest.tokens.first()) {
+        (Part::End(None), Some(token)) if token.kind != TokenKind::PunctuationRightCurlyBracket => {
+            Err(Err::Failure(Error::expected(
+                rest,
+                Expected::Token(TokenKind::PunctuationRightCurlyBracket),
+            )))
+        }
+        _ => Ok((rest, part)),
+    }
+}
+
+/// The statements of a block and the expression it ends with. While parsing
+/// recovers from errors, a broken statement is skipped up to its `;` or the
+/// end of the block, and its error kept aside.
+fn parse_block_body(i: ParseInput) -> ParseResult<(Vec<Statement>, Option<Expression>)> {
+    let mut body = Vec::new();
+    let mut i = i;
+    loop {
+        let recovered = match &i.recovered {
+            Some(recovered) => recovered.clone(),
+            None => match parse_block_part(i)? {
+                (rest, Part::Statement(statement)) => {
+                    body.push(*statement);
+                    i = rest;
+                    continue;
+                }
+                (rest, Part::End(last_expression)) => {
+                    return Ok((rest, (body, last_expression.map(|expression| *expression))))
+                }
+            },
+        };
+        if matches!(
+            i.tokens.first(),
+            None | Some(Token {
+                kind: TokenKind::PunctuationRightCurlyBracket,
+                ..
+            })
+        ) {
+            return Ok((i, (body, None)));
+        }
+        let item = recover(
+            i.clone(),
+            parse_recovering_block_part,
+            broken_block_statement_len,
+        );
+        recovered.borrow_mut().extend(item.errors);
+        i.tokens.drain(..item.taken);
+        match item.node {
+            Some(Part::Statement(statement)) => body.push(*statement),
+            Some(Part::End(last_expression)) => {
+                return Ok((i, (body, last_expression.map(|expression| *expression))))
+            }
+            None => {}
+        }
+    }
+}
 
 pub fn parse_block(i: ParseInput) -> ParseResult<Block> {
     let (i, curly_bracket_open_token) = tag(TokenKind::PunctuationLeftCurlyBracket)(i)?;
-    let original_binding_power = i.binding_power;
-    let (i, (body, last_expression)) =
-        cut(tuple((many0(parse_statement), opt(parse_expression))))(i.with_binding_power(0))?;
-    let (i, curly_bracket_close_token) =
-        tag(TokenKind::PunctuationRightCurlyBracket)(i.with_binding_power(original_binding_power))?;
+    let (i, (body, last_expression)) = cut(enclosed(parse_block_body))(i)?;
+    let (i, curly_bracket_close_token) = tag(TokenKind::PunctuationRightCurlyBracket)(i)?;
 
+    let id = i.next_id();
     Ok((
         i,
         Block {
@@ -15,6 +119,7 @@ pub fn parse_block(i: ParseInput) -> ParseResult<Block> {
             body,
             last_expression,
             curly_bracket_close_token,
+            id,
         },
     ))
 }
@@ -0,0 +1,17 @@
+use crate::{core::ast::Documentation, syntax::parse::*};
+
+fn parse_doc_comment(i: ParseInput) -> ParseResult<Token> {
+    satisfy(|token| matches!(token.kind, TokenKind::DocComment(_)))(i)
+}
+
+/// The `///` lines in front of a declaration, folded into one
+/// `Documentation`.
+pub fn parse_documentation(i: ParseInput) -> ParseResult<Option<Documentation>> {
+    map(many0(parse_doc_comment), |lines| {
+        if lines.is_empty() {
+            None
+        } else {
+            Some(Documentation { lines })
+        }
+    })(i)
+}
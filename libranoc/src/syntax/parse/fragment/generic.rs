@@ -0,0 +1,77 @@
+use crate::{
+    core::ast::{GenericParameter, TypeParameter},
+    syntax::{parse::*, Span},
+};
+
+pub fn parse_generic_parameter(i: ParseInput) -> ParseResult<GenericParameter> {
+    let (i, name) = parse_identifier(i)?;
+    let (i, bounds) = opt(preceded(
+        tag(TokenKind::PunctuationColon),
+        cut(separated_list1(
+            tag(TokenKind::PunctuationPlusSign),
+            parse_type,
+        )),
+    ))(i)?;
+    let id = i.next_id();
+    Ok((
+        i,
+        GenericParameter {
+            name,
+            bounds: bounds.unwrap_or_default(),
+            id,
+        },
+    ))
+}
+
+/// `<T, U: Bound>` behind the name of a declaration. Declarations never
+/// stand where an expression could continue, so unlike in an expression the
+/// `<` can't be a comparison here.
+pub fn parse_generic_parameters(i: ParseInput) -> ParseResult<Vec<GenericParameter>> {
+    map(
+        delimited(
+            tag(TokenKind::PunctuationLessThanSign),
+            separated_trailing0(tag(TokenKind::PunctuationComma), parse_generic_parameter),
+            cut(tag(TokenKind::PunctuationGreaterThanSign)),
+        ),
+        |(parameters, _)| parameters,
+    )(i)
+}
+
+fn parse_type_argument(i: ParseInput) -> ParseResult<TypeParameter> {
+    alt((
+        map(tag(TokenKind::PunctuationAsterisk), |star| {
+            TypeParameter::Star(star.span)
+        }),
+        map(parse_type, TypeParameter::Specific),
+    ))(i)
+}
+
+/// `<i32, *>` behind the path of a type. `>>` is lexed as two tokens, so
+/// nested arguments like `Vec<Vec<i32>>` close without splitting anything.
+pub fn parse_type_arguments(i: ParseInput) -> ParseResult<Vec<TypeParameter>> {
+    map(
+        delimited(
+            tag(TokenKind::PunctuationLessThanSign),
+            separated_trailing0(tag(TokenKind::PunctuationComma), parse_type_argument),
+            cut(tag(TokenKind::PunctuationGreaterThanSign)),
+        ),
+        |(arguments, _)| arguments,
+    )(i)
+}
+
+/// `::<i32>` behind an expression, with the span of the `>`. The `::` tells
+/// the `<` apart from a comparison, which it would be right behind the
+/// expression: `g<i32>(1)` compares `g` with `i32` and `(1)`.
+pub fn parse_turbofish(i: ParseInput) -> ParseResult<(Vec<TypeParameter>, Span)> {
+    map(
+        preceded(
+            parse_path_separator,
+            cut(tuple((
+                tag(TokenKind::PunctuationLessThanSign),
+                separated_trailing0(tag(TokenKind::PunctuationComma), parse_type_argument),
+                tag(TokenKind::PunctuationGreaterThanSign),
+            ))),
+        ),
+        |(_, (arguments, _), close)| (arguments, close.span),
+    )(i)
+}
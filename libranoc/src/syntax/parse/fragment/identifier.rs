@@ -1,9 +1,9 @@
 use crate::syntax::{parse::*, TokenKind};
 
 pub fn parse_identifier(i: ParseInput) -> ParseResult<Token> {
-    let (i, token) = any(i)?;
+    let (rest, token) = any(i.clone())?;
     if let TokenKind::IdentifierIdentifier(_) = &token.kind {
-        Ok((i, token))
+        Ok((rest, token))
     } else {
         err_tag(i)
     }
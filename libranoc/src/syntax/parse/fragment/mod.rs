@@ -1,13 +1,19 @@
+mod attribute;
+mod block;
+mod documentation;
+mod generic;
 mod identifier;
 mod name;
 mod path;
 mod pattern;
-mod block;
 mod r#type;
 
+pub use attribute::*;
+pub use block::*;
+pub use documentation::*;
+pub use generic::*;
 pub use identifier::*;
 pub use name::*;
 pub use path::*;
 pub use pattern::*;
 pub use r#type::*;
-pub use block::*;
@@ -6,3 +6,15 @@ pub fn parse_path(i: ParseInput) -> ParseResult<Path> {
         Path,
     )(i)
 }
+
+/// `::`, which is lexed as two colons and only counts as a separator when
+/// nothing is written between them.
+pub fn parse_path_separator(i: ParseInput) -> ParseResult<()> {
+    let (rest, first) = tag(TokenKind::PunctuationColon)(i.clone())?;
+    let (rest, second) = tag(TokenKind::PunctuationColon)(rest)?;
+    if first.span.range.end == second.span.range.start {
+        Ok((rest, ()))
+    } else {
+        err_tag(i)
+    }
+}
@@ -1,9 +1,22 @@
 use crate::{core::ast::Pattern, syntax::parse::*};
 
+pub fn parse_pattern_literal(i: ParseInput) -> ParseResult<Pattern> {
+    map(parse_literal, Pattern::Literal)(i)
+}
+
 pub fn parse_pattern_slot(i: ParseInput) -> ParseResult<Pattern> {
     map(parse_name, Pattern::Slot)(i)
 }
 
+/// A pattern which matches every value, like the ones of parameters.
 pub fn parse_pattern(i: ParseInput) -> ParseResult<Pattern> {
     parse_pattern_slot(i)
 }
+
+/// A pattern which may fail to match, like the ones of `match` arms.
+pub fn parse_refutable_pattern(i: ParseInput) -> ParseResult<Pattern> {
+    context(
+        "a pattern",
+        alt((parse_pattern_literal, parse_pattern_slot)),
+    )(i)
+}
@@ -1,29 +1,103 @@
 use crate::{core::ast::Type, syntax::parse::*};
 
 pub fn parse_type_basic(i: ParseInput) -> ParseResult<Type> {
-    let (i, base) = parse_path(i)?;
-
-    // TODO: TypeParameters
-    let type_parameters = Vec::new();
+    let (i, ((base, type_parameters), span)) =
+        spanned(tuple((parse_path, opt(parse_type_arguments))))(i)?;
+    let type_parameters = type_parameters.unwrap_or_default();
 
     Ok((
         i,
         Type::Basic {
             base,
             type_parameters,
+            span,
         },
     ))
 }
 
 pub fn parse_type_impl(i: ParseInput) -> ParseResult<Type> {
     map(
-        preceded(tag(TokenKind::KeywordImpl), cut(parse_type_basic)),
-        |ty| Type::Impl(Box::new(ty)),
+        tuple((tag(TokenKind::KeywordImpl), cut(parse_type_basic))),
+        |(impl_token, ty)| Type::Impl(impl_token.span, Box::new(ty)),
+    )(i)
+}
+
+pub fn parse_type_dyn(i: ParseInput) -> ParseResult<Type> {
+    map(
+        tuple((tag(TokenKind::KeywordDyn), cut(parse_type_basic))),
+        |(dyn_token, ty)| Type::Dyn(dyn_token.span, Box::new(ty)),
     )(i)
 }
 
+pub fn parse_type_never(i: ParseInput) -> ParseResult<Type> {
+    map(tag(TokenKind::PunctuationExclamationMark), |token| {
+        Type::Never(token.span)
+    })(i)
+}
+
+/// Parses a tuple type, or a single type in parentheses, following the same
+/// trailing comma rule as tuple expressions.
+pub fn parse_type_tuple(i: ParseInput) -> ParseResult<Type> {
+    let (i, ((mut types, last_comma), span)) = spanned(delimited(
+        tag(TokenKind::PunctuationLeftParenthesis),
+        separated_trailing0(tag(TokenKind::PunctuationComma), parse_type),
+        tag(TokenKind::PunctuationRightParenthesis),
+    ))(i)?;
+
+    let ty = if types.len() == 1 && !last_comma {
+        types.swap_remove(0)
+    } else {
+        Type::Tuple(types, span)
+    };
+
+    Ok((i, ty))
+}
+
+/// Parses a function type, `(A, B) -> R`.
+pub fn parse_type_function(i: ParseInput) -> ParseResult<Type> {
+    let (i, (((parameters_type, _), _, return_type), span)) = spanned(tuple((
+        delimited(
+            tag(TokenKind::PunctuationLeftParenthesis),
+            separated_trailing0(tag(TokenKind::PunctuationComma), parse_type),
+            tag(TokenKind::PunctuationRightParenthesis),
+        ),
+        tag(TokenKind::PunctuationsSingleRightArrow),
+        cut(parse_type),
+    )))(i)?;
+
+    Ok((
+        i,
+        Type::Function {
+            parameters_type,
+            return_type: Box::new(return_type),
+            span,
+        },
+    ))
+}
+
+/// Parses a type, which is optional for each `?` behind it.
 pub fn parse_type(i: ParseInput) -> ParseResult<Type> {
-    alt((parse_type_basic, parse_type_impl))(i)
+    nested(context(
+        "a type",
+        map(
+            tuple((
+                alt((
+                    parse_type_basic,
+                    parse_type_impl,
+                    parse_type_dyn,
+                    parse_type_never,
+                    parse_type_function,
+                    parse_type_tuple,
+                )),
+                many0(tag(TokenKind::PunctuationQuestionMark)),
+            )),
+            |(ty, question_marks)| {
+                question_marks.into_iter().fold(ty, |ty, question_mark| {
+                    Type::Nullable(Box::new(ty), question_mark.span)
+                })
+            },
+        ),
+    ))(i)
 }
 
 pub fn parse_type_annotation(i: ParseInput) -> ParseResult<Type> {
@@ -0,0 +1,599 @@
+use std::{ops::Range, rc::Rc};
+
+use crate::{
+    core::{ast::*, Error, Location, NodeIdGenerator},
+    syntax::{IncrementalTokenizer, Span, Token, TokenizeOptions},
+};
+
+use super::{declared_operators, recovery::parse_item, DeclaredOperator, ParseInput, ParseOptions};
+
+/// Moves the spans of a node reused behind an edit. Reused nodes start at
+/// the same column as before, so only their bytes and lines move.
+trait Shift {
+    fn shift(&mut self, bytes: isize, lines: isize);
+}
+
+impl Shift for Span {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        *self = self.shifted(bytes, lines);
+    }
+}
+
+impl Shift for Token {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        self.span.shift(bytes, lines);
+    }
+}
+
+impl<T: Shift> Shift for Box<T> {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        self.as_mut().shift(bytes, lines);
+    }
+}
+
+impl<T: Shift> Shift for Option<T> {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        if let Some(inner) = self {
+            inner.shift(bytes, lines);
+        }
+    }
+}
+
+impl<T: Shift> Shift for Vec<T> {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        for inner in self {
+            inner.shift(bytes, lines);
+        }
+    }
+}
+
+impl<A: Shift, B: Shift> Shift for (A, B) {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        self.0.shift(bytes, lines);
+        self.1.shift(bytes, lines);
+    }
+}
+
+impl Shift for Error {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        for label in &mut self.labels {
+            if let Location::Known(span) = &mut label.location {
+                span.shift(bytes, lines);
+            }
+        }
+    }
+}
+
+impl Shift for Node {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        match self {
+            Node::Directive(directive) => directive.attribute.shift(bytes, lines),
+            Node::Statement(statement) => statement.shift(bytes, lines),
+        }
+    }
+}
+
+impl Shift for Statement {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        match self {
+            Statement::Declaration(Declaration::FunctionDeclaration(function)) => {
+                function.documentation.shift(bytes, lines);
+                function.attributes.shift(bytes, lines);
+                function.name.shift(bytes, lines);
+                function.generic_parameters.shift(bytes, lines);
+                function.parameters.shift(bytes, lines);
+                function.return_type.shift(bytes, lines);
+                function.body.shift(bytes, lines);
+                function.span.shift(bytes, lines);
+            }
+            Statement::Declaration(Declaration::OperatorDeclaration(declaration)) => {
+                declaration.documentation.shift(bytes, lines);
+                declaration.operator_token.shift(bytes, lines);
+                declaration.symbol.shift(bytes, lines);
+                declaration.precedence.shift(bytes, lines);
+                declaration.associativity.shift(bytes, lines);
+                declaration.function.shift(bytes, lines);
+                declaration.span.shift(bytes, lines);
+            }
+            Statement::Declaration(Declaration::UseDeclaration(declaration)) => {
+                declaration.documentation.shift(bytes, lines);
+                declaration.use_token.shift(bytes, lines);
+                declaration.tree.shift(bytes, lines);
+                declaration.span.shift(bytes, lines);
+            }
+            Statement::Declaration(Declaration::ModuleDeclaration(declaration)) => {
+                declaration.documentation.shift(bytes, lines);
+                declaration.mod_token.shift(bytes, lines);
+                declaration.name.shift(bytes, lines);
+                match &mut declaration.body {
+                    ModuleBody::Inline {
+                        curly_bracket_open_token,
+                        module,
+                        curly_bracket_close_token,
+                    } => {
+                        curly_bracket_open_token.shift(bytes, lines);
+                        module.nodes.shift(bytes, lines);
+                        curly_bracket_close_token.shift(bytes, lines);
+                    }
+                    ModuleBody::File(semicolon_token) => semicolon_token.shift(bytes, lines),
+                }
+                declaration.span.shift(bytes, lines);
+            }
+            Statement::Let(r#let) => {
+                r#let.let_token.shift(bytes, lines);
+                r#let.pattern.shift(bytes, lines);
+                r#let.ty.shift(bytes, lines);
+                r#let.value.shift(bytes, lines);
+                r#let.semicolon_token.shift(bytes, lines);
+            }
+            Statement::Expression(expression) => expression.shift(bytes, lines),
+        }
+    }
+}
+
+impl Shift for Documentation {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        self.lines.shift(bytes, lines);
+    }
+}
+
+impl Shift for Attribute {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        self.number_sign_token.shift(bytes, lines);
+        self.value.shift(bytes, lines);
+        self.right_square_bracket_token.shift(bytes, lines);
+    }
+}
+
+impl Shift for AttributeValue {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        match self {
+            AttributeValue::Word(token) | AttributeValue::Literal(token) => {
+                token.shift(bytes, lines)
+            }
+            AttributeValue::KeyValue { key, value } => {
+                key.shift(bytes, lines);
+                value.shift(bytes, lines);
+            }
+            AttributeValue::List {
+                name,
+                items,
+                right_parenthesis_token,
+            } => {
+                name.shift(bytes, lines);
+                items.shift(bytes, lines);
+                right_parenthesis_token.shift(bytes, lines);
+            }
+        }
+    }
+}
+
+impl Shift for UseTree {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        self.prefix.0.shift(bytes, lines);
+        match &mut self.kind {
+            UseTreeKind::Simple(alias) => alias.shift(bytes, lines),
+            UseTreeKind::Glob(star) => star.shift(bytes, lines),
+            UseTreeKind::Nested(trees) => trees.shift(bytes, lines),
+        }
+        self.span.shift(bytes, lines);
+    }
+}
+
+impl Shift for GenericParameter {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        self.name.shift(bytes, lines);
+        self.bounds.shift(bytes, lines);
+    }
+}
+
+impl Shift for Pattern {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        match self {
+            Pattern::Slot(name) => name.shift(bytes, lines),
+            Pattern::Literal(literal) => literal.shift(bytes, lines),
+        }
+    }
+}
+
+impl Shift for Literal {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        match self {
+            Literal::String(token)
+            | Literal::Character(token)
+            | Literal::Integer(Integer(token))
+            | Literal::Decimal(token)
+            | Literal::Boolean(token) => token.shift(bytes, lines),
+        }
+    }
+}
+
+impl Shift for Name {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        match self {
+            Name::Ident(token) | Name::Placeholder(token) => token.shift(bytes, lines),
+        }
+    }
+}
+
+impl Shift for Type {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        match self {
+            Type::Basic {
+                base,
+                type_parameters,
+                span,
+            } => {
+                base.0.shift(bytes, lines);
+                type_parameters.shift(bytes, lines);
+                span.shift(bytes, lines);
+            }
+            Type::Tuple(types, span) => {
+                types.shift(bytes, lines);
+                span.shift(bytes, lines);
+            }
+            Type::Impl(span, ty) | Type::Dyn(span, ty) | Type::Nullable(ty, span) => {
+                span.shift(bytes, lines);
+                ty.shift(bytes, lines);
+            }
+            Type::Function {
+                parameters_type,
+                return_type,
+                span,
+            } => {
+                parameters_type.shift(bytes, lines);
+                return_type.shift(bytes, lines);
+                span.shift(bytes, lines);
+            }
+            Type::Never(span) => span.shift(bytes, lines),
+        }
+    }
+}
+
+impl Shift for TypeParameter {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        match self {
+            TypeParameter::Star(span) => span.shift(bytes, lines),
+            TypeParameter::Specific(ty) => ty.shift(bytes, lines),
+        }
+    }
+}
+
+impl Shift for Block {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        self.curly_bracket_open_token.shift(bytes, lines);
+        self.body.shift(bytes, lines);
+        self.last_expression.shift(bytes, lines);
+        self.curly_bracket_close_token.shift(bytes, lines);
+    }
+}
+
+impl Shift for If {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        self.if_token.shift(bytes, lines);
+        self.condition.shift(bytes, lines);
+        self.body.shift(bytes, lines);
+        match &mut self.else_part {
+            Some(Else::If(token, r#if)) => {
+                token.shift(bytes, lines);
+                r#if.shift(bytes, lines);
+            }
+            Some(Else::Block(token, block)) => {
+                token.shift(bytes, lines);
+                block.shift(bytes, lines);
+            }
+            None => {}
+        }
+    }
+}
+
+impl Shift for Expression {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        match self {
+            Expression::Tuple(expressions, span) | Expression::Array(expressions, span) => {
+                expressions.shift(bytes, lines);
+                span.shift(bytes, lines);
+            }
+            Expression::Operator(Operator::Prefix(operator)) => match operator {
+                PrefixOperator::Not(Not(span, operand))
+                | PrefixOperator::UnaryPlus(UnaryPlus(span, operand))
+                | PrefixOperator::UnaryMinus(UnaryMinus(span, operand)) => {
+                    span.shift(bytes, lines);
+                    operand.shift(bytes, lines);
+                }
+            },
+            Expression::Operator(Operator::Infix(operator)) => operator.shift(bytes, lines),
+            Expression::Operator(Operator::Postfix(operator)) => match operator {
+                PostfixOperator::Index(Index(target, arguments, span)) => {
+                    target.shift(bytes, lines);
+                    arguments.shift(bytes, lines);
+                    span.shift(bytes, lines);
+                }
+                PostfixOperator::FunctionCall(FunctionCall(target, arguments)) => {
+                    target.shift(bytes, lines);
+                    arguments.shift(bytes, lines);
+                }
+                PostfixOperator::TypeArguments(TypeArguments(target, arguments, span)) => {
+                    target.shift(bytes, lines);
+                    arguments.shift(bytes, lines);
+                    span.shift(bytes, lines);
+                }
+            },
+            Expression::Name(name) => name.shift(bytes, lines),
+            Expression::If(r#if) => r#if.shift(bytes, lines),
+            Expression::Unsafe(r#unsafe) => {
+                r#unsafe.unsafe_token.shift(bytes, lines);
+                r#unsafe.block.shift(bytes, lines);
+            }
+            Expression::Interpolation(parts) => {
+                for part in parts {
+                    match part {
+                        InterpolationPart::Fragment(token) => token.shift(bytes, lines),
+                        InterpolationPart::Expression(expression) => expression.shift(bytes, lines),
+                    }
+                }
+            }
+            Expression::Todo(todo) => {
+                todo.keyword.shift(bytes, lines);
+                todo.message.shift(bytes, lines);
+            }
+            Expression::Intrinsic(intrinsic) => {
+                intrinsic.at.shift(bytes, lines);
+                intrinsic.name.shift(bytes, lines);
+                intrinsic.type_arguments.shift(bytes, lines);
+                intrinsic.span.shift(bytes, lines);
+            }
+            Expression::Init(init) => {
+                init.name.shift(bytes, lines);
+                for field in &mut init.fields {
+                    field.name.shift(bytes, lines);
+                    field.value.shift(bytes, lines);
+                }
+                init.curly_bracket_close_token.shift(bytes, lines);
+            }
+            Expression::Match(r#match) => {
+                r#match.match_token.shift(bytes, lines);
+                r#match.scrutinee.shift(bytes, lines);
+                for arm in &mut r#match.arms {
+                    arm.pattern.shift(bytes, lines);
+                    arm.guard.shift(bytes, lines);
+                    match &mut arm.body {
+                        MatchArmBody::Expression(expression) => expression.shift(bytes, lines),
+                        MatchArmBody::Block(block) => block.shift(bytes, lines),
+                    }
+                }
+                r#match.curly_bracket_close_token.shift(bytes, lines);
+            }
+            Expression::Closure(closure) => {
+                closure.move_token.shift(bytes, lines);
+                closure.vertical_line_token.shift(bytes, lines);
+                closure.parameters.shift(bytes, lines);
+                closure.return_type.shift(bytes, lines);
+                match &mut closure.body {
+                    ClosureBody::Expression(expression) => expression.shift(bytes, lines),
+                    ClosureBody::Block(block) => block.shift(bytes, lines),
+                }
+            }
+            Expression::Literal(literal) => literal.shift(bytes, lines),
+            Expression::Path(path) => path.0.shift(bytes, lines),
+        }
+    }
+}
+
+impl Shift for InfixOperator {
+    fn shift(&mut self, bytes: isize, lines: isize) {
+        match self {
+            InfixOperator::LogicalOr(lhs, span, rhs)
+            | InfixOperator::LogicalAnd(lhs, span, rhs)
+            | InfixOperator::EqualTo(lhs, span, rhs)
+            | InfixOperator::NotEqualTo(lhs, span, rhs)
+            | InfixOperator::GreaterThan(lhs, span, rhs)
+            | InfixOperator::LessThan(lhs, span, rhs)
+            | InfixOperator::GreaterThanOrEqualTo(lhs, span, rhs)
+            | InfixOperator::LessThanOrEqualTo(lhs, span, rhs)
+            | InfixOperator::Add(lhs, span, rhs)
+            | InfixOperator::Subtract(lhs, span, rhs)
+            | InfixOperator::Multiply(lhs, span, rhs)
+            | InfixOperator::Divide(lhs, span, rhs)
+            | InfixOperator::Remainder(lhs, span, rhs)
+            | InfixOperator::RangeRightExclusive(lhs, span, rhs)
+            | InfixOperator::RangeRightInclusive(lhs, span, rhs)
+            | InfixOperator::BitAnd(lhs, span, rhs)
+            | InfixOperator::BitOr(lhs, span, rhs)
+            | InfixOperator::BitXor(lhs, span, rhs)
+            | InfixOperator::ShiftLeft(lhs, span, rhs)
+            | InfixOperator::ShiftRight(lhs, span, rhs)
+            | InfixOperator::Assign(lhs, span, rhs) => {
+                lhs.shift(bytes, lines);
+                span.shift(bytes, lines);
+                rhs.shift(bytes, lines);
+            }
+            InfixOperator::GetField(GetField(lhs, rhs))
+            | InfixOperator::GetFieldNullable(GetFieldNullable(lhs, rhs)) => {
+                lhs.shift(bytes, lines);
+                rhs.shift(bytes, lines);
+            }
+            InfixOperator::CompoundAssign(operator) => operator.shift(bytes, lines),
+        }
+    }
+}
+
+/// A top level statement together with the tokens it was parsed from.
+#[derive(Debug)]
+struct Item {
+    tokens: Range<usize>,
+    /// The end of the tokens the parser looked at, so an edit there may
+    /// change the statement.
+    examined: usize,
+    /// The span of the first token, to tell how far the item moved.
+    start: Span,
+    node: Option<Node>,
+    errors: Vec<Error>,
+}
+
+/// Which top level statements an edit replaced: `removed` indexes the
+/// statements before the edit and `inserted` the ones after it. Every other
+/// statement was kept without parsing it again.
+#[derive(Debug, PartialEq, Clone)]
+pub struct NodeDelta {
+    pub removed: Range<usize>,
+    pub inserted: Range<usize>,
+}
+
+/// A source text together with its top level statements, kept up to date by
+/// re-lexing and reparsing only the statements touched by each edit. Broken
+/// statements are skipped the same way `parse_recovering` does.
+#[derive(Debug)]
+pub struct IncrementalParser {
+    tokenizer: IncrementalTokenizer,
+    options: ParseOptions,
+    /// The operators declared in the source, see `ParseInput`.
+    operators: Rc<Vec<DeclaredOperator>>,
+    /// Shared by every reparse, so the fresh statements never reuse the ids
+    /// of the kept ones.
+    ids: NodeIdGenerator,
+    items: Vec<Item>,
+}
+
+impl IncrementalParser {
+    pub fn new(
+        source: impl Into<String>,
+        tokenize: TokenizeOptions,
+        options: ParseOptions,
+    ) -> Self {
+        let tokenizer = IncrementalTokenizer::new(source, tokenize);
+        let operators = Rc::new(declared_operators(tokenizer.tokens(), &options));
+        let mut parser = IncrementalParser {
+            tokenizer,
+            options,
+            operators,
+            ids: NodeIdGenerator::default(),
+            items: Vec::new(),
+        };
+        parser.items = parser.parse_from(0, |_| false);
+        parser
+    }
+
+    pub fn source(&self) -> &str {
+        self.tokenizer.source()
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        self.tokenizer.tokens()
+    }
+
+    /// The statements which parsed, in source order.
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.items.iter().filter_map(|item| item.node.as_ref())
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Error> {
+        self.items.iter().flat_map(|item| &item.errors)
+    }
+
+    pub fn module(&self) -> Module {
+        Module {
+            nodes: self.nodes().cloned().collect(),
+        }
+    }
+
+    /// Parses items starting at the token `position` until the tokens run
+    /// out or `stop` tells that the rest is known already.
+    fn parse_from(&self, mut position: usize, stop: impl Fn(usize) -> bool) -> Vec<Item> {
+        let tokens = self.tokenizer.tokens();
+        let mut items = Vec::new();
+        while position < tokens.len() && !stop(position) {
+            let i = ParseInput::with_operators(
+                tokens[position..].to_vec(),
+                &self.options,
+                self.operators.clone(),
+            )
+            .with_ids(self.ids.clone());
+            let item = parse_item(i);
+            items.push(Item {
+                tokens: position..position + item.taken,
+                examined: position + item.examined,
+                start: tokens[position].span.clone(),
+                node: item.node,
+                errors: item.errors,
+            });
+            position += item.taken;
+        }
+        items
+    }
+
+    /// Replaces the bytes in `range` with `text` and reparses the statements
+    /// from the first one which looked at a changed token, until the old
+    /// statements behind the edit line up again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds or not on `char` boundaries.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) -> NodeDelta {
+        let delta = self.tokenizer.edit(range, text);
+        let operators = declared_operators(self.tokenizer.tokens(), &self.options);
+        if operators != *self.operators {
+            // the operators change how every statement parses.
+            self.operators = Rc::new(operators);
+            let removed = self.items.len();
+            self.items = self.parse_from(0, |_| false);
+            return NodeDelta {
+                removed: 0..removed,
+                inserted: 0..self.items.len(),
+            };
+        }
+        let token_shift = delta.inserted.len() as isize - delta.removed.len() as isize;
+
+        let first = self
+            .items
+            .iter()
+            .position(|item| item.examined > delta.removed.start)
+            .unwrap_or(self.items.len());
+        let start = match self.items.get(first) {
+            Some(item) => item.tokens.start,
+            None => self.items.last().map_or(0, |item| item.tokens.end),
+        };
+
+        // the old items behind the removed tokens can be kept once reparsing
+        // reaches the place they moved to.
+        let moved = |item: &Item| (item.tokens.start as isize + token_shift) as usize;
+        let reusable = &self.items[first..];
+        let reusable_from = reusable
+            .iter()
+            .position(|item| item.tokens.start >= delta.removed.end)
+            .unwrap_or(reusable.len());
+        let inserted_end = delta.inserted.end;
+        let fresh = self.parse_from(start, |position| {
+            position >= inserted_end
+                && reusable[reusable_from..]
+                    .iter()
+                    .any(|item| moved(item) == position)
+        });
+
+        let end = fresh.last().map_or(start, |item| item.tokens.end);
+        let last = first
+            + reusable
+                .iter()
+                .position(|item| item.tokens.start >= delta.removed.end && moved(item) >= end)
+                .unwrap_or(reusable.len());
+        let tokens = self.tokenizer.tokens();
+        for item in &mut self.items[last..] {
+            let position = moved(item);
+            let span = &tokens[position].span;
+            let bytes = span.range.start as isize - item.start.range.start as isize;
+            let lines = span.line as isize - item.start.line as isize;
+            item.tokens = position..position + item.tokens.len();
+            item.examined = (item.examined as isize + token_shift) as usize;
+            item.start = span.clone();
+            item.node.shift(bytes, lines);
+            item.errors.shift(bytes, lines);
+        }
+
+        let inserted = fresh.len();
+        self.items.splice(first..last, fresh);
+        NodeDelta {
+            removed: first..last,
+            inserted: first..first + inserted,
+        }
+    }
+}
@@ -0,0 +1,836 @@
+//! Declarative macros, which are expanded over the tokens before parsing.
+//!
+//! ```text
+//! macro max {
+//!     ($a:expr) => { $a };
+//!     ($a:expr, $($rest:expr),+) => { match $a { a if a > max!($($rest),+) => a, _ => max!($($rest),+) } };
+//! }
+//! ```
+//!
+//! A macro is a list of rules, each with a matcher and a transcriber. An
+//! invocation like `max!(x, 1)` is replaced by the transcriber of the first
+//! rule whose matcher matches the tokens between its delimiters, and what it
+//! is replaced by is expanded again. Macros are known to the whole file,
+//! also in front of their declaration, like the declared operators.
+//!
+//! `$name:kind` in a matcher binds a fragment: an `expr`, a `ty`, an
+//! `ident`, a `literal`, a `block` or a single token tree `tt`. `$( ... )`
+//! followed by an optional separator and `*`, `+` or `?` repeats what is
+//! inside, and the transcriber repeats the `$( ... )` using its fragments
+//! the same number of times. An expression fragment and an expansion which
+//! is an expression are put in parentheses, so the operators around them
+//! don't change how they group.
+//!
+//! The tokens made by the transcriber have the span of the invocation with
+//! an `ExpansionId` of their own, while the tokens of the fragments keep
+//! theirs. The resolver keeps the variables of different expansions apart
+//! with it, which makes the macros hygienic.
+
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    core::Error,
+    syntax::{
+        parse::{
+            declared_operators, parse_expression, parse_type, DeclaredOperator, ParseInput,
+            ParseOptions,
+        },
+        ExpansionId, Span, Token, TokenKind,
+    },
+};
+
+/// How many times an expansion may expand another macro in turn.
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenTree {
+    Token(Token),
+    /// The tokens between a pair of delimiters, with the delimiters.
+    Group(Token, Vec<TokenTree>, Token),
+}
+
+impl TokenTree {
+    fn first(&self) -> &Token {
+        match self {
+            TokenTree::Token(token) | TokenTree::Group(token, ..) => token,
+        }
+    }
+
+    fn flatten_into(&self, tokens: &mut Vec<Token>) {
+        match self {
+            TokenTree::Token(token) => tokens.push(token.clone()),
+            TokenTree::Group(open, trees, close) => {
+                tokens.push(open.clone());
+                for tree in trees {
+                    tree.flatten_into(tokens);
+                }
+                tokens.push(close.clone());
+            }
+        }
+    }
+}
+
+fn flatten(trees: &[TokenTree]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for tree in trees {
+        tree.flatten_into(&mut tokens);
+    }
+    tokens
+}
+
+fn closing(kind: &TokenKind) -> Option<TokenKind> {
+    match kind {
+        TokenKind::PunctuationLeftParenthesis => Some(TokenKind::PunctuationRightParenthesis),
+        TokenKind::PunctuationLeftSquareBracket => Some(TokenKind::PunctuationRightSquareBracket),
+        TokenKind::PunctuationLeftCurlyBracket => Some(TokenKind::PunctuationRightCurlyBracket),
+        _ => None,
+    }
+}
+
+fn is_closing(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::PunctuationRightParenthesis
+            | TokenKind::PunctuationRightSquareBracket
+            | TokenKind::PunctuationRightCurlyBracket
+    )
+}
+
+/// The index of the token closing the delimiter `tokens` starts with, if
+/// every delimiter in between is closed by its pair.
+fn matching_close(tokens: &[Token]) -> Option<usize> {
+    let mut expected = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if let Some(close) = closing(&token.kind) {
+            expected.push(close);
+        } else if is_closing(&token.kind) {
+            if expected.pop() != Some(token.kind.clone()) {
+                return None;
+            }
+            if expected.is_empty() {
+                return Some(index);
+            }
+        }
+        if index == 0 && expected.is_empty() {
+            return None;
+        }
+    }
+    None
+}
+
+/// Groups `tokens` into token trees, failing on a delimiter without its
+/// pair.
+fn token_trees(tokens: &[Token]) -> Result<Vec<TokenTree>, Error> {
+    let mut trees = Vec::new();
+    let mut index = 0;
+    while index < tokens.len() {
+        let token = &tokens[index];
+        if closing(&token.kind).is_some() {
+            let close = matching_close(&tokens[index..])
+                .ok_or_else(|| Error::invalid_macro("This delimiter is never closed.", token))?
+                + index;
+            trees.push(TokenTree::Group(
+                token.clone(),
+                token_trees(&tokens[(index + 1)..close])?,
+                tokens[close].clone(),
+            ));
+            index = close + 1;
+        } else if is_closing(&token.kind) {
+            return Err(Error::invalid_macro(
+                "This delimiter is never opened.",
+                token,
+            ));
+        } else {
+            trees.push(TokenTree::Token(token.clone()));
+            index += 1;
+        }
+    }
+    Ok(trees)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FragmentKind {
+    Expression,
+    Type,
+    Identifier,
+    Literal,
+    Block,
+    TokenTree,
+}
+
+impl FragmentKind {
+    fn from_name(name: &Token) -> Result<FragmentKind, Error> {
+        Ok(match name.content.as_str() {
+            "expr" => FragmentKind::Expression,
+            "ty" => FragmentKind::Type,
+            "ident" => FragmentKind::Identifier,
+            "literal" => FragmentKind::Literal,
+            "block" => FragmentKind::Block,
+            "tt" => FragmentKind::TokenTree,
+            _ => {
+                return Err(Error::invalid_macro(
+                    "Unknown fragment kind, expected `expr`, `ty`, `ident`, `literal`, `block` or `tt`.",
+                    name,
+                ))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Repetition {
+    /// `*`
+    ZeroOrMore,
+    /// `+`
+    OneOrMore,
+    /// `?`
+    ZeroOrOne,
+}
+
+impl Repetition {
+    fn from_token(token: &Token) -> Option<Repetition> {
+        match token.kind {
+            TokenKind::PunctuationAsterisk => Some(Repetition::ZeroOrMore),
+            TokenKind::PunctuationPlusSign => Some(Repetition::OneOrMore),
+            TokenKind::PunctuationQuestionMark => Some(Repetition::ZeroOrOne),
+            _ => None,
+        }
+    }
+}
+
+/// What a matcher and a transcriber are made of.
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Token(Token),
+    Group(Token, Vec<Part>, Token),
+    /// `$name:kind` in a matcher, or `$name` in a transcriber.
+    Fragment(Token, Option<FragmentKind>),
+    /// `$( ... ) separator? op` with its separator.
+    Repeated(Vec<Part>, Option<Token>, Repetition, Token),
+}
+
+/// The parts of the matcher or the transcriber `trees`, which are the same
+/// except for the kinds of the fragments.
+fn parts(trees: &[TokenTree], is_matcher: bool) -> Result<Vec<Part>, Error> {
+    let mut parts = Vec::new();
+    let mut trees = trees.iter().peekable();
+    while let Some(tree) = trees.next() {
+        let dollar = match tree {
+            TokenTree::Token(token) if token.kind == TokenKind::PunctuationDollarSign => token,
+            TokenTree::Token(token) => {
+                parts.push(Part::Token(token.clone()));
+                continue;
+            }
+            TokenTree::Group(open, inner, close) => {
+                parts.push(Part::Group(
+                    open.clone(),
+                    self::parts(inner, is_matcher)?,
+                    close.clone(),
+                ));
+                continue;
+            }
+        };
+        match trees.next() {
+            Some(TokenTree::Token(
+                name @ Token {
+                    kind: TokenKind::IdentifierIdentifier(_),
+                    ..
+                },
+            )) => {
+                let kind = if is_matcher {
+                    match (trees.next(), trees.next()) {
+                        (Some(TokenTree::Token(colon)), Some(TokenTree::Token(kind)))
+                            if colon.kind == TokenKind::PunctuationColon =>
+                        {
+                            Some(FragmentKind::from_name(kind)?)
+                        }
+                        _ => {
+                            return Err(Error::invalid_macro(
+                                "Expected `:` and the kind of the fragment.",
+                                name,
+                            ))
+                        }
+                    }
+                } else {
+                    None
+                };
+                parts.push(Part::Fragment(name.clone(), kind));
+            }
+            Some(TokenTree::Group(open, inner, _))
+                if open.kind == TokenKind::PunctuationLeftParenthesis =>
+            {
+                let inner = self::parts(inner, is_matcher)?;
+                let (separator, op) = match trees.next() {
+                    Some(TokenTree::Token(token)) => match Repetition::from_token(token) {
+                        Some(repetition) => (None, (repetition, token)),
+                        None => match trees.next() {
+                            Some(TokenTree::Token(op)) if Repetition::from_token(op).is_some() => (
+                                Some(token.clone()),
+                                (Repetition::from_token(op).unwrap(), op),
+                            ),
+                            _ => {
+                                return Err(Error::invalid_macro(
+                                    "Expected `*`, `+` or `?` after the separator.",
+                                    token,
+                                ))
+                            }
+                        },
+                    },
+                    _ => {
+                        return Err(Error::invalid_macro(
+                            "Expected `*`, `+` or `?` after the repetition.",
+                            open,
+                        ))
+                    }
+                };
+                parts.push(Part::Repeated(inner, separator, op.0, op.1.clone()));
+            }
+            _ => {
+                return Err(Error::invalid_macro(
+                    "Expected the name of a fragment or `(` after `$`.",
+                    dollar,
+                ))
+            }
+        }
+    }
+    Ok(parts)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    matcher: Vec<Part>,
+    transcriber: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Macro {
+    name: Token,
+    rules: Vec<Rule>,
+}
+
+/// The rules of a macro from the tokens between the `{` and the `}` of its
+/// declaration, `(matcher) => { transcriber };` each.
+fn rules(trees: &[TokenTree]) -> Result<Vec<Rule>, Error> {
+    let mut rules = Vec::new();
+    let mut trees = trees.iter();
+    while let Some(tree) = trees.next() {
+        let matcher = match tree {
+            TokenTree::Group(_, matcher, _) => matcher,
+            TokenTree::Token(token) => {
+                return Err(Error::invalid_macro(
+                    "Expected the matcher of a rule in delimiters.",
+                    token,
+                ))
+            }
+        };
+        match trees.next() {
+            Some(TokenTree::Token(arrow))
+                if arrow.kind == TokenKind::PunctuationsDoubleRightArrow => {}
+            _ => {
+                return Err(Error::invalid_macro(
+                    "Expected `=>` after the matcher.",
+                    tree.first(),
+                ))
+            }
+        }
+        let transcriber = match trees.next() {
+            Some(TokenTree::Group(_, transcriber, _)) => transcriber,
+            _ => {
+                return Err(Error::invalid_macro(
+                    "Expected the transcriber of a rule in delimiters.",
+                    tree.first(),
+                ))
+            }
+        };
+        rules.push(Rule {
+            matcher: parts(matcher, true)?,
+            transcriber: parts(transcriber, false)?,
+        });
+        let mut rest = trees.clone();
+        if let Some(TokenTree::Token(semicolon)) = rest.next() {
+            if semicolon.kind == TokenKind::PunctuationSemicolon {
+                trees = rest;
+            }
+        }
+    }
+    Ok(rules)
+}
+
+/// Takes every `macro name { ... }` out of `tokens`, leaving the ones which
+/// aren't closed to the parser to report.
+fn take_macros(tokens: &mut Vec<Token>) -> Result<HashMap<String, Macro>, Error> {
+    let mut macros = HashMap::new();
+    let mut index = 0;
+    while index < tokens.len() {
+        let declaration = match &tokens[index..] {
+            [keyword, name, open, ..]
+                if keyword.kind == TokenKind::KeywordMacro
+                    && matches!(name.kind, TokenKind::IdentifierIdentifier(_))
+                    && open.kind == TokenKind::PunctuationLeftCurlyBracket =>
+            {
+                matching_close(&tokens[(index + 2)..])
+                    .map(|close| (name.clone(), close + index + 2))
+            }
+            _ => None,
+        };
+        match declaration {
+            Some((name, close)) => {
+                let body = token_trees(&tokens[(index + 3)..close])?;
+                let declared = Macro {
+                    rules: rules(&body)?,
+                    name: name.clone(),
+                };
+                if let Some(before) = macros.insert(name.content.clone(), declared) {
+                    return Err(Error::redefined(name.content, before.name.span, name.span));
+                }
+                tokens.drain(index..=close);
+            }
+            None => index += 1,
+        }
+    }
+    Ok(macros)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Binding {
+    Fragment(Vec<Token>, FragmentKind),
+    /// What each repetition bound.
+    Repeated(Vec<Binding>),
+}
+
+type Bindings = HashMap<String, Binding>;
+
+/// The names of the fragments in `parts`, also inside of repetitions.
+fn fragment_names(parts: &[Part], names: &mut Vec<String>) {
+    for part in parts {
+        match part {
+            Part::Fragment(name, _) => names.push(name.content.clone()),
+            Part::Group(_, inner, _) | Part::Repeated(inner, ..) => fragment_names(inner, names),
+            Part::Token(_) => {}
+        }
+    }
+}
+
+struct Matcher<'a> {
+    options: &'a ParseOptions,
+    /// The operators declared in the file, which expression fragments are
+    /// parsed with.
+    operators: Rc<Vec<DeclaredOperator>>,
+}
+
+impl Matcher<'_> {
+    /// How many of the trees of `input` `parts` match, binding the fragments
+    /// into `bindings`.
+    fn match_parts(
+        &self,
+        parts: &[Part],
+        input: &[TokenTree],
+        bindings: &mut Bindings,
+    ) -> Option<usize> {
+        let mut taken = 0;
+        for part in parts {
+            let rest = &input[taken..];
+            taken += match part {
+                Part::Token(token) => match rest.first() {
+                    Some(TokenTree::Token(actual)) if actual.kind == token.kind => 1,
+                    _ => return None,
+                },
+                Part::Group(open, inner, _) => match rest.first() {
+                    Some(TokenTree::Group(actual, trees, _)) if actual.kind == open.kind => {
+                        if self.match_parts(inner, trees, bindings)? != trees.len() {
+                            return None;
+                        }
+                        1
+                    }
+                    _ => return None,
+                },
+                Part::Fragment(name, kind) => {
+                    let kind = kind.unwrap_or(FragmentKind::TokenTree);
+                    let len = self.match_fragment(kind, rest)?;
+                    bindings.insert(
+                        name.content.clone(),
+                        Binding::Fragment(flatten(&rest[..len]), kind),
+                    );
+                    len
+                }
+                Part::Repeated(inner, separator, repetition, _) => {
+                    self.match_repeated(inner, separator.as_ref(), *repetition, rest, bindings)?
+                }
+            };
+        }
+        Some(taken)
+    }
+
+    fn match_repeated(
+        &self,
+        inner: &[Part],
+        separator: Option<&Token>,
+        repetition: Repetition,
+        input: &[TokenTree],
+        bindings: &mut Bindings,
+    ) -> Option<usize> {
+        let mut names = Vec::new();
+        fragment_names(inner, &mut names);
+        let mut repeated: Vec<Bindings> = Vec::new();
+        let mut taken = 0;
+        loop {
+            if repetition == Repetition::ZeroOrOne && repeated.len() == 1 {
+                break;
+            }
+            let mut start = taken;
+            if let (Some(separator), false) = (separator, repeated.is_empty()) {
+                match input.get(start) {
+                    Some(TokenTree::Token(token)) if token.kind == separator.kind => start += 1,
+                    _ => break,
+                }
+            }
+            let mut iteration = Bindings::new();
+            match self.match_parts(inner, &input[start..], &mut iteration) {
+                Some(len) if len > 0 || start > taken => {
+                    taken = start + len;
+                    repeated.push(iteration);
+                }
+                _ => break,
+            }
+        }
+        if repetition == Repetition::OneOrMore && repeated.is_empty() {
+            return None;
+        }
+        for name in names {
+            let iterations = repeated
+                .iter_mut()
+                .filter_map(|iteration| iteration.remove(&name))
+                .collect();
+            bindings.insert(name, Binding::Repeated(iterations));
+        }
+        Some(taken)
+    }
+
+    /// How many of the trees of `input` the fragment takes.
+    fn match_fragment(&self, kind: FragmentKind, input: &[TokenTree]) -> Option<usize> {
+        let first = input.first()?;
+        match kind {
+            FragmentKind::TokenTree => Some(1),
+            FragmentKind::Identifier => match first {
+                TokenTree::Token(Token {
+                    kind: TokenKind::IdentifierIdentifier(_),
+                    ..
+                }) => Some(1),
+                _ => None,
+            },
+            FragmentKind::Literal => match first {
+                TokenTree::Token(Token {
+                    kind:
+                        TokenKind::LiteralCharacter(_)
+                        | TokenKind::LiteralString(_)
+                        | TokenKind::LiteralNumberIntegral(_)
+                        | TokenKind::LiteralNumberDecimal(_)
+                        | TokenKind::LiteralNumberExponent(_)
+                        | TokenKind::LiteralBoolean(_),
+                    ..
+                }) => Some(1),
+                _ => None,
+            },
+            FragmentKind::Block => match first {
+                TokenTree::Group(open, ..)
+                    if open.kind == TokenKind::PunctuationLeftCurlyBracket =>
+                {
+                    Some(1)
+                }
+                _ => None,
+            },
+            FragmentKind::Expression => self.match_parsed(input, |i| {
+                parse_expression(i).ok().map(|(rest, _)| rest.tokens.len())
+            }),
+            FragmentKind::Type => self.match_parsed(input, |i| {
+                parse_type(i).ok().map(|(rest, _)| rest.tokens.len())
+            }),
+        }
+    }
+
+    /// How many of the trees of `input` `parse` takes, which returns the
+    /// number of tokens it leaves. It has to stop in between two trees.
+    fn match_parsed<P>(&self, input: &[TokenTree], parse: P) -> Option<usize>
+    where
+        P: FnOnce(ParseInput) -> Option<usize>,
+    {
+        let tokens = flatten(input);
+        let left = parse(ParseInput::with_operators(
+            tokens.clone(),
+            self.options,
+            self.operators.clone(),
+        ))?;
+        let mut taken_tokens = tokens.len() - left;
+        let mut taken = 0;
+        while taken_tokens > 0 {
+            let mut tree = Vec::new();
+            input[taken].flatten_into(&mut tree);
+            taken_tokens = taken_tokens.checked_sub(tree.len())?;
+            taken += 1;
+        }
+        Some(taken).filter(|taken| *taken > 0)
+    }
+}
+
+struct Transcriber<'a> {
+    /// The span of the invocation, which the tokens of the transcriber get.
+    span: Span,
+    expansion: ExpansionId,
+    bindings: &'a Bindings,
+}
+
+impl Transcriber<'_> {
+    fn made(&self, token: &Token) -> Token {
+        Token {
+            span: Span {
+                expansion: self.expansion,
+                ..self.span.clone()
+            },
+            ..token.clone()
+        }
+    }
+
+    /// The binding of `name` in the repetitions `indices`.
+    fn binding(&self, name: &Token, indices: &[usize]) -> Result<&Binding, Error> {
+        let mut binding = self.bindings.get(&name.content).ok_or_else(|| {
+            Error::invalid_macro(
+                format!("`${}` isn't bound by the matcher.", name.content),
+                name,
+            )
+        })?;
+        for index in indices {
+            match binding {
+                Binding::Repeated(iterations) => match iterations.get(*index) {
+                    Some(iteration) => binding = iteration,
+                    None => break,
+                },
+                Binding::Fragment(..) => break,
+            }
+        }
+        Ok(binding)
+    }
+
+    /// How many times the repeated `parts` repeat, which is how many times
+    /// the fragments repeated in the matcher at this depth did.
+    fn repetitions(&self, parts: &[Part], indices: &[usize]) -> Result<Option<usize>, Error> {
+        let mut count = None;
+        for part in parts {
+            let found = match part {
+                Part::Fragment(name, _) => match self.binding(name, indices)? {
+                    Binding::Repeated(iterations) => Some((iterations.len(), name)),
+                    Binding::Fragment(..) => None,
+                },
+                Part::Group(open, inner, _) => {
+                    self.repetitions(inner, indices)?.map(|len| (len, open))
+                }
+                Part::Repeated(inner, _, _, op) => {
+                    self.repetitions(inner, indices)?.map(|len| (len, op))
+                }
+                Part::Token(_) => None,
+            };
+            match (count, found) {
+                (Some(count), Some((len, token))) if count != len => {
+                    return Err(Error::invalid_macro(
+                        "The fragments of this repetition repeat a different number of times.",
+                        token,
+                    ))
+                }
+                (None, Some((len, _))) => count = Some(len),
+                _ => {}
+            }
+        }
+        Ok(count)
+    }
+
+    fn transcribe(
+        &self,
+        parts: &[Part],
+        indices: &mut Vec<usize>,
+        output: &mut Vec<Token>,
+    ) -> Result<(), Error> {
+        for part in parts {
+            match part {
+                Part::Token(token) => output.push(self.made(token)),
+                Part::Group(open, inner, close) => {
+                    output.push(self.made(open));
+                    self.transcribe(inner, indices, output)?;
+                    output.push(self.made(close));
+                }
+                Part::Fragment(name, _) => match self.binding(name, indices)? {
+                    Binding::Fragment(tokens, FragmentKind::Expression) if tokens.len() > 1 => {
+                        output.extend(parenthesized(tokens.clone()))
+                    }
+                    Binding::Fragment(tokens, _) => output.extend(tokens.iter().cloned()),
+                    Binding::Repeated(_) => {
+                        return Err(Error::invalid_macro(
+                            format!(
+                                "`${}` repeats in the matcher, so it has to be in a repetition.",
+                                name.content
+                            ),
+                            name,
+                        ))
+                    }
+                },
+                Part::Repeated(inner, separator, _, op) => {
+                    let count = self.repetitions(inner, indices)?.ok_or_else(|| {
+                        Error::invalid_macro(
+                            "This repetition doesn't use a fragment repeating in the matcher.",
+                            op,
+                        )
+                    })?;
+                    for index in 0..count {
+                        if let (Some(separator), true) = (separator, index > 0) {
+                            output.push(self.made(separator));
+                        }
+                        indices.push(index);
+                        self.transcribe(inner, indices, output)?;
+                        indices.pop();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `tokens` in parentheses with the spans of their ends.
+fn parenthesized(tokens: Vec<Token>) -> Vec<Token> {
+    let (first, last) = match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => (first.span.start(), last.span.end()),
+        _ => return tokens,
+    };
+    let mut output = Vec::with_capacity(tokens.len() + 2);
+    output.push(Token {
+        kind: TokenKind::PunctuationLeftParenthesis,
+        span: first,
+        content: "(".to_string(),
+    });
+    output.extend(tokens);
+    output.push(Token {
+        kind: TokenKind::PunctuationRightParenthesis,
+        span: last,
+        content: ")".to_string(),
+    });
+    output
+}
+
+/// Whether an expansion is items or statements rather than an expression,
+/// which it is put in parentheses as.
+fn is_statements(tokens: &[Token]) -> bool {
+    let starts_item = matches!(
+        tokens.first().map(|token| &token.kind),
+        Some(
+            TokenKind::KeywordFn
+                | TokenKind::KeywordPub
+                | TokenKind::KeywordExtern
+                | TokenKind::KeywordMod
+                | TokenKind::KeywordUse
+                | TokenKind::KeywordOperator
+                | TokenKind::PunctuationNumberSign
+        )
+    );
+    let mut depth = 0usize;
+    let has_semicolon = tokens.iter().any(|token| {
+        if closing(&token.kind).is_some() {
+            depth += 1;
+        } else if is_closing(&token.kind) {
+            depth = depth.saturating_sub(1);
+        }
+        depth == 0 && token.kind == TokenKind::PunctuationSemicolon
+    });
+    starts_item || has_semicolon
+}
+
+struct Expander<'a> {
+    macros: HashMap<String, Macro>,
+    options: &'a ParseOptions,
+    operators: Rc<Vec<DeclaredOperator>>,
+    last_expansion: u32,
+}
+
+impl Expander<'_> {
+    fn expand(&mut self, tokens: Vec<Token>, depth: usize) -> Result<Vec<Token>, Error> {
+        let mut output = Vec::with_capacity(tokens.len());
+        let mut index = 0;
+        while index < tokens.len() {
+            let invocation = match &tokens[index..] {
+                [name, bang, open, ..]
+                    if bang.kind == TokenKind::PunctuationExclamationMark
+                        && closing(&open.kind).is_some()
+                        && matches!(name.kind, TokenKind::IdentifierIdentifier(_))
+                        && self.macros.contains_key(&name.content) =>
+                {
+                    matching_close(&tokens[(index + 2)..]).map(|close| (name, close + index + 2))
+                }
+                _ => None,
+            };
+            let (name, close) = match invocation {
+                Some(invocation) => invocation,
+                None => {
+                    output.push(tokens[index].clone());
+                    index += 1;
+                    continue;
+                }
+            };
+            if depth == MAX_EXPANSION_DEPTH {
+                return Err(Error::macro_too_deep(name, MAX_EXPANSION_DEPTH));
+            }
+            let span = name.span.joined(&tokens[close].span);
+            let input = token_trees(&tokens[(index + 3)..close])?;
+            let expansion = self.invoke(name, span, &input)?;
+            let expansion = self.expand(expansion, depth + 1)?;
+            if expansion.len() > 1 && !is_statements(&expansion) {
+                output.extend(parenthesized(expansion));
+            } else {
+                output.extend(expansion);
+            }
+            index = close + 1;
+        }
+        Ok(output)
+    }
+
+    /// The tokens the first rule of the macro `name` matching `input` makes.
+    fn invoke(
+        &mut self,
+        name: &Token,
+        span: Span,
+        input: &[TokenTree],
+    ) -> Result<Vec<Token>, Error> {
+        let matcher = Matcher {
+            options: self.options,
+            operators: self.operators.clone(),
+        };
+        for rule in &self.macros[&name.content].rules {
+            let mut bindings = Bindings::new();
+            if matcher.match_parts(&rule.matcher, input, &mut bindings) != Some(input.len()) {
+                continue;
+            }
+            self.last_expansion += 1;
+            let transcriber = Transcriber {
+                span,
+                expansion: ExpansionId(self.last_expansion),
+                bindings: &bindings,
+            };
+            let mut output = Vec::new();
+            transcriber.transcribe(&rule.transcriber, &mut Vec::new(), &mut output)?;
+            return Ok(output);
+        }
+        Err(Error::no_matching_macro_rule(name, span))
+    }
+}
+
+/// Takes the macros declared in `tokens` out of them and expands their
+/// invocations.
+pub fn expand_macros(mut tokens: Vec<Token>, options: &ParseOptions) -> Result<Vec<Token>, Error> {
+    let macros = take_macros(&mut tokens)?;
+    if macros.is_empty() {
+        return Ok(tokens);
+    }
+    Expander {
+        operators: Rc::new(declared_operators(&tokens, options)),
+        macros,
+        options,
+        last_expansion: ExpansionId::ROOT.0,
+    }
+    .expand(tokens, 0)
+}
@@ -1,20 +1,46 @@
 use crate::core::ast::Module;
 
 mod fragment;
+mod incremental;
+mod macros;
 mod nom;
+mod recovery;
+pub mod snippet;
 mod statement;
 
 pub(super) use self::nom::*;
 pub(super) use fragment::*;
 pub(super) use statement::*;
 
+pub use incremental::{IncrementalParser, NodeDelta};
+pub use macros::expand_macros;
+pub use recovery::{parse_recovering, parse_recovering_with};
+
 pub use crate::syntax::{
     parse::nom::{Error, ParseResult},
     Token, TokenKind,
 };
 
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// How deeply expressions and types may nest before parsing gives up,
+    /// which keeps the recursive parsers from overflowing the stack.
+    pub max_depth: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions { max_depth: 128 }
+    }
+}
+
 pub fn parse(tokens: Vec<Token>) -> crate::core::Result<Module> {
-    let i = ParseInput::new(tokens);
+    parse_with(tokens, &ParseOptions::default())
+}
+
+pub fn parse_with(tokens: Vec<Token>, options: &ParseOptions) -> crate::core::Result<Module> {
+    let tokens = expand_macros(tokens, options)?;
+    let i = ParseInput::new(tokens, options);
     let (_, nodes) = all_consuming(many0(parse_statement_node))(i)?;
     Ok(Module { nodes })
 }
@@ -0,0 +1,106 @@
+use nom::error::{ContextError, ErrorKind, ParseError};
+
+use crate::syntax::{parse::nom::ParseInput, TokenKind};
+
+/// Something the parser would have accepted where it failed.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expected {
+    Token(TokenKind),
+    /// A whole construct, like "an expression", named by `context`.
+    Description(&'static str),
+}
+
+impl Expected {
+    pub fn describe(&self) -> String {
+        match self {
+            Expected::Token(kind) => kind.describe(),
+            Expected::Description(description) => description.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    pub input: ParseInput,
+    pub kind: ErrorKind,
+    pub expected: Vec<Expected>,
+}
+
+impl Error {
+    pub fn expected(input: ParseInput, expected: Expected) -> Self {
+        Error {
+            input,
+            kind: ErrorKind::Tag,
+            expected: vec![expected],
+        }
+    }
+
+    /// The input nests deeper than `ParseOptions::max_depth` allows.
+    pub fn too_deeply_nested(input: ParseInput) -> Self {
+        Error {
+            input,
+            kind: ErrorKind::TooLarge,
+            expected: Vec::new(),
+        }
+    }
+
+    pub fn is_too_deeply_nested(&self) -> bool {
+        self.kind == ErrorKind::TooLarge
+    }
+
+    /// A struct literal is written where a `{` starts something else, like
+    /// in the condition of an `if`.
+    pub fn struct_literal_not_allowed(input: ParseInput) -> Self {
+        Error {
+            input,
+            kind: ErrorKind::Verify,
+            expected: Vec::new(),
+        }
+    }
+
+    pub fn is_struct_literal_not_allowed(&self) -> bool {
+        self.kind == ErrorKind::Verify
+    }
+}
+
+impl ParseError<ParseInput> for Error {
+    fn from_error_kind(input: ParseInput, kind: ErrorKind) -> Self {
+        Error {
+            input,
+            kind,
+            expected: Vec::new(),
+        }
+    }
+
+    fn append(_input: ParseInput, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    /// Keeps the alternative which got the furthest, or everything both
+    /// expected if they failed at the same token.
+    fn or(mut self, other: Self) -> Self {
+        match self.input.tokens.len().cmp(&other.input.tokens.len()) {
+            std::cmp::Ordering::Less => self,
+            std::cmp::Ordering::Greater => other,
+            std::cmp::Ordering::Equal => {
+                for expected in other.expected {
+                    if !self.expected.contains(&expected) {
+                        self.expected.push(expected);
+                    }
+                }
+                self
+            }
+        }
+    }
+}
+
+impl ContextError<ParseInput> for Error {
+    /// Names what was expected after a parser which failed without consuming
+    /// anything; a deeper failure already tells more than the name does.
+    fn add_context(input: ParseInput, context: &'static str, mut other: Self) -> Self {
+        if other.input.tokens.len() == input.tokens.len() {
+            other.expected = vec![Expected::Description(context)];
+        }
+        other
+    }
+}
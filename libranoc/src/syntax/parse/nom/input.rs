@@ -1,27 +1,93 @@
-use std::{iter::Enumerate, ops::RangeFrom, vec::IntoIter};
+use std::{cell::RefCell, iter::Enumerate, ops::RangeFrom, rc::Rc, vec::IntoIter};
 
 use nom::{InputIter, InputLength, InputTake, Needed, Slice};
 
-use crate::syntax::Token;
+use crate::{
+    core::{Error, NodeId, NodeIdGenerator},
+    syntax::{
+        parse::{declared_operators, DeclaredOperator, ParseOptions},
+        Token,
+    },
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ParseInput {
     pub(crate) tokens: Vec<Token>,
     pub(crate) binding_power: u8,
+    /// How many expressions and types the parser is currently inside of.
+    pub(crate) depth: usize,
+    pub(crate) max_depth: usize,
+    /// Whether `Name { ... }` may be a struct literal here. It may not in the
+    /// condition of an `if` or the scrutinee of a `match`, where the `{`
+    /// starts the body instead.
+    pub(crate) struct_literals: bool,
+    /// The operators declared anywhere in the file, which the precedence
+    /// table is extended with.
+    pub(crate) operators: Rc<Vec<DeclaredOperator>>,
+    pub(crate) ids: NodeIdGenerator,
+    /// Where the errors of the broken statements skipped inside of blocks
+    /// are kept while parsing recovers from them, or `None` while it stops
+    /// at the first one.
+    pub(crate) recovered: Option<Rc<RefCell<Vec<Error>>>>,
 }
 
 impl ParseInput {
-    pub(crate) fn new(tokens: Vec<Token>) -> Self {
+    pub(crate) fn new(tokens: Vec<Token>, options: &ParseOptions) -> Self {
+        let operators = Rc::new(declared_operators(&tokens, options));
+        ParseInput::with_operators(tokens, options, operators)
+    }
+
+    /// Starts parsing `tokens` with the `operators` declared in the whole
+    /// file, for when `tokens` are only a part of it.
+    pub(crate) fn with_operators(
+        tokens: Vec<Token>,
+        options: &ParseOptions,
+        operators: Rc<Vec<DeclaredOperator>>,
+    ) -> Self {
         ParseInput {
             tokens,
             binding_power: 0,
+            depth: 0,
+            max_depth: options.max_depth,
+            struct_literals: true,
+            operators,
+            ids: NodeIdGenerator::default(),
+            recovered: None,
         }
     }
 
+    /// Continues with the ids of `ids`, for when the nodes parsed here join
+    /// the ones parsed before.
+    pub(crate) fn with_ids(self, ids: NodeIdGenerator) -> Self {
+        ParseInput { ids, ..self }
+    }
+
+    /// Recovers from the broken statements inside of blocks from here on,
+    /// keeping their errors with the ones recovered from already, if any.
+    pub(crate) fn recovering(self) -> Self {
+        let recovered = Some(self.recovered.clone().unwrap_or_default());
+        ParseInput { recovered, ..self }
+    }
+
+    pub(crate) fn next_id(&self) -> NodeId {
+        self.ids.next()
+    }
+
     pub(crate) fn with_binding_power(self, binding_power: u8) -> Self {
         ParseInput {
-            tokens: self.tokens,
             binding_power,
+            ..self
+        }
+    }
+
+    pub(crate) fn with_depth(self, depth: usize) -> Self {
+        ParseInput { depth, ..self }
+    }
+
+    pub(crate) fn with_struct_literals(self, struct_literals: bool) -> Self {
+        ParseInput {
+            struct_literals,
+            ..self
         }
     }
 }
@@ -68,7 +134,10 @@ impl InputTake for ParseInput {
     fn take(&self, count: usize) -> Self {
         ParseInput {
             tokens: self.tokens[0..count].to_vec(),
-            binding_power: self.binding_power,
+            operators: self.operators.clone(),
+            ids: self.ids.clone(),
+            recovered: self.recovered.clone(),
+            ..*self
         }
     }
     #[inline]
@@ -77,11 +146,17 @@ impl InputTake for ParseInput {
         (
             ParseInput {
                 tokens: suffix.to_vec(),
-                binding_power: self.binding_power,
+                operators: self.operators.clone(),
+                ids: self.ids.clone(),
+                recovered: self.recovered.clone(),
+                ..*self
             },
             ParseInput {
                 tokens: prefix.to_vec(),
-                binding_power: self.binding_power,
+                operators: self.operators.clone(),
+                ids: self.ids.clone(),
+                recovered: self.recovered.clone(),
+                ..*self
             },
         )
     }
@@ -91,7 +166,10 @@ impl Slice<RangeFrom<usize>> for ParseInput {
     fn slice(&self, range: RangeFrom<usize>) -> Self {
         ParseInput {
             tokens: self.tokens[range].to_vec(),
-            binding_power: self.binding_power,
+            operators: self.operators.clone(),
+            ids: self.ids.clone(),
+            recovered: self.recovered.clone(),
+            ..*self
         }
     }
 }
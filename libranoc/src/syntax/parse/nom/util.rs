@@ -1,33 +1,32 @@
 use nom::{
     error::{ErrorKind, ParseError},
-    Err, IResult, InputIter, InputTake, Slice,
+    Err, InputIter, InputTake, Slice,
 };
 
-use crate::syntax::{parse::nom::ParseInput, Token, TokenKind};
+use crate::syntax::{
+    parse::nom::{Error, Expected, ParseInput, ParseResult},
+    Span, Token, TokenKind,
+};
 
 pub use ::nom::{
     branch::alt,
     combinator::{all_consuming, cut, map, opt},
-    multi::{fold_many0, many0, separated_list0, separated_list1},
+    error::context,
+    multi::{fold_many0, many0, separated_list1},
     sequence::{delimited, preceded, terminated, tuple},
 };
 
 #[inline(always)]
-pub fn err_kind<T, Error: ParseError<ParseInput>>(
-    i: ParseInput,
-    kind: ErrorKind,
-) -> IResult<ParseInput, T, Error> {
+pub fn err_kind<T>(i: ParseInput, kind: ErrorKind) -> ParseResult<T> {
     Err(Err::Error(Error::from_error_kind(i, kind)))
 }
 
 #[inline(always)]
-pub fn err_tag<T, Error: ParseError<ParseInput>>(i: ParseInput) -> IResult<ParseInput, T, Error> {
+pub fn err_tag<T>(i: ParseInput) -> ParseResult<T> {
     err_kind(i, ErrorKind::Tag)
 }
 
-pub fn satisfy<F, Error: ParseError<ParseInput>>(
-    cond: F,
-) -> impl Fn(ParseInput) -> IResult<ParseInput, Token, Error>
+pub fn satisfy<F>(cond: F) -> impl Fn(ParseInput) -> ParseResult<Token>
 where
     F: Fn(&Token) -> bool,
 {
@@ -40,7 +39,7 @@ where
     }
 }
 
-pub fn any<Error: ParseError<ParseInput>>(i: ParseInput) -> IResult<ParseInput, Token, Error> {
+pub fn any(i: ParseInput) -> ParseResult<Token> {
     match i.slice_index(1) {
         Ok(index) => {
             let (i, part) = i.take_split(index);
@@ -50,14 +49,113 @@ pub fn any<Error: ParseError<ParseInput>>(i: ParseInput) -> IResult<ParseInput,
     }
 }
 
-pub fn tag<Error: ParseError<ParseInput>>(
-    tag: TokenKind,
-) -> impl Fn(ParseInput) -> IResult<ParseInput, Token, Error> {
+pub fn tag(tag: TokenKind) -> impl Fn(ParseInput) -> ParseResult<Token> {
     move |i| match i.iter_elements().next().map(|t| {
         let b = t.kind == tag;
         (t, b)
     }) {
         Some((t, true)) => Ok((i.slice(1..), t)),
-        _ => err_kind(i, ErrorKind::Tag),
+        _ => Err(Err::Error(Error::expected(i, Expected::Token(tag.clone())))),
+    }
+}
+
+/// Parses `element`s separated by `separator`, where a trailing separator is
+/// allowed after at least one element. Returns the elements together with
+/// whether the trailing separator was present.
+pub fn separated_trailing0<S, E, T, U>(
+    separator: S,
+    element: E,
+) -> impl FnMut(ParseInput) -> ParseResult<(Vec<T>, bool)>
+where
+    S: FnMut(ParseInput) -> ParseResult<U>,
+    E: FnMut(ParseInput) -> ParseResult<T>,
+{
+    let mut separator = separator;
+    let mut element = element;
+    move |i| {
+        let mut elements = Vec::new();
+        let mut i = match element(i.clone()) {
+            Ok((i, first)) => {
+                elements.push(first);
+                i
+            }
+            Err(Err::Error(_)) => return Ok((i, (elements, false))),
+            Err(e) => return Err(e),
+        };
+        loop {
+            i = match separator(i.clone()) {
+                Ok((i, _)) => i,
+                Err(Err::Error(_)) => return Ok((i, (elements, false))),
+                Err(e) => return Err(e),
+            };
+            i = match element(i.clone()) {
+                Ok((i, next)) => {
+                    elements.push(next);
+                    i
+                }
+                Err(Err::Error(_)) => return Ok((i, (elements, true))),
+                Err(e) => return Err(e),
+            };
+        }
+    }
+}
+
+/// Runs `parser` one level deeper, failing for good once the input nests
+/// deeper than its limit instead of recursing any further.
+pub fn nested<T, P>(mut parser: P) -> impl FnMut(ParseInput) -> ParseResult<T>
+where
+    P: FnMut(ParseInput) -> ParseResult<T>,
+{
+    move |i| {
+        let depth = i.depth;
+        if depth >= i.max_depth {
+            return Err(Err::Failure(Error::too_deeply_nested(i)));
+        }
+        let (i, output) = parser(i.with_depth(depth + 1))?;
+        Ok((i.with_depth(depth), output))
+    }
+}
+
+/// Runs `parser` on what is written between delimiters like `(` and `)`,
+/// where neither the binding power nor the restrictions of the surrounding
+/// expression apply, and restores them afterwards.
+pub fn enclosed<T, P>(mut parser: P) -> impl FnMut(ParseInput) -> ParseResult<T>
+where
+    P: FnMut(ParseInput) -> ParseResult<T>,
+{
+    move |i| {
+        let (binding_power, struct_literals) = (i.binding_power, i.struct_literals);
+        let (i, output) = parser(i.with_binding_power(0).with_struct_literals(true))?;
+        Ok((
+            i.with_binding_power(binding_power)
+                .with_struct_literals(struct_literals),
+            output,
+        ))
+    }
+}
+
+/// The span of the tokens taken between `start` and `rest`, for the nodes
+/// which don't keep all of their tokens. It is empty, right in front of
+/// `rest`, if nothing was taken.
+pub fn taken_span(start: &ParseInput, rest: &ParseInput) -> Span {
+    let taken = start.tokens.len() - rest.tokens.len();
+    match (start.tokens.first(), taken) {
+        (Some(first), 0) => first.span.start(),
+        (Some(first), taken) => first.span.joined(&start.tokens[taken - 1].span),
+        (None, _) => Span::EMPTY,
+    }
+}
+
+/// Runs `parser` and gives the span of the tokens it took along with its
+/// output.
+pub fn spanned<T, P>(mut parser: P) -> impl FnMut(ParseInput) -> ParseResult<(T, Span)>
+where
+    P: FnMut(ParseInput) -> ParseResult<T>,
+{
+    move |i| {
+        let start = i.clone();
+        let (i, output) = parser(i)?;
+        let span = taken_span(&start, &i);
+        Ok((i, (output, span)))
     }
 }
@@ -0,0 +1,218 @@
+use crate::core::{
+    ast::{Module, Node},
+    Error,
+};
+
+use super::{
+    expand_macros, parse_statement_node, Expected, ParseInput, ParseOptions, ParseResult, Token,
+    TokenKind,
+};
+
+/// The number of tokens making up the broken statement `tokens` starts with:
+/// everything up to a `;` outside of any delimiters, or up to the `}`
+/// closing the first top level `{`, like the end of a function body.
+pub(super) fn broken_statement_len(tokens: &[Token]) -> usize {
+    let mut depth = 0usize;
+    for (index, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::PunctuationLeftParenthesis
+            | TokenKind::PunctuationLeftSquareBracket
+            | TokenKind::PunctuationLeftCurlyBracket => depth += 1,
+            TokenKind::PunctuationRightParenthesis | TokenKind::PunctuationRightSquareBracket => {
+                depth = depth.saturating_sub(1)
+            }
+            TokenKind::PunctuationRightCurlyBracket => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return index + 1;
+                }
+            }
+            TokenKind::PunctuationSemicolon if depth == 0 => return index + 1,
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+/// The number of tokens making up the broken statement `tokens` starts with
+/// inside of a block: everything up to a `;` outside of any delimiters, or
+/// up to the `}` closing the block, which is left to close it. It is never
+/// zero, so parsing always goes on.
+pub(super) fn broken_block_statement_len(tokens: &[Token]) -> usize {
+    let mut depth = 0usize;
+    for (index, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::PunctuationLeftParenthesis
+            | TokenKind::PunctuationLeftSquareBracket
+            | TokenKind::PunctuationLeftCurlyBracket => depth += 1,
+            TokenKind::PunctuationRightParenthesis
+            | TokenKind::PunctuationRightSquareBracket
+            | TokenKind::PunctuationRightCurlyBracket
+                if depth > 0 =>
+            {
+                depth -= 1
+            }
+            TokenKind::PunctuationRightCurlyBracket => return index.max(1),
+            TokenKind::PunctuationSemicolon if depth == 0 => return index + 1,
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+/// Whether a `;` left out in front of the token at `position` is what broke
+/// the statement: the token is on a new line, can't go on an expression but
+/// only start a statement, or the tokens ran out.
+fn missing_semicolon_at(tokens: &[Token], position: usize) -> bool {
+    match tokens.get(position) {
+        Some(token) => {
+            token.span.line > tokens[position - 1].span.line
+                || matches!(
+                    token.kind,
+                    TokenKind::KeywordFn
+                        | TokenKind::KeywordPub
+                        | TokenKind::KeywordExtern
+                        | TokenKind::KeywordUse
+                        | TokenKind::KeywordMod
+                        | TokenKind::KeywordMacro
+                        | TokenKind::KeywordOperator
+                        | TokenKind::KeywordLet
+                        | TokenKind::PunctuationNumberSign
+                        | TokenKind::DocComment(_)
+                )
+        }
+        None => true,
+    }
+}
+
+pub(super) struct Item<T = Node> {
+    /// How many tokens the statement is made of, which is never zero.
+    pub taken: usize,
+    /// How many tokens the parser looked at, which goes beyond `taken` when
+    /// it peeked at the next token or failed behind the skipped tokens.
+    pub examined: usize,
+    /// The statement, unless it was broken and skipped.
+    pub node: Option<T>,
+    /// The errors of the statement, and of the broken statements skipped
+    /// inside of its blocks.
+    pub errors: Vec<Error>,
+}
+
+/// Parses the statement `i` starts with, or skips the broken statement
+/// there.
+pub(super) fn parse_item(i: ParseInput) -> Item {
+    recover(i, parse_statement_node, broken_statement_len)
+}
+
+/// Parses what `i` starts with by `parser`, or skips the `skipped` tokens
+/// of it if it is broken. Every `;` missing where one was expected is
+/// reported and parsing goes on as if it was there, as long as that gets
+/// the parser further. The blocks inside of it recover from their broken
+/// statements in turn, so one of them only skips itself.
+pub(super) fn recover<T>(
+    i: ParseInput,
+    parser: impl Fn(ParseInput) -> ParseResult<T>,
+    skipped: impl Fn(&[Token]) -> usize,
+) -> Item<T> {
+    let i = i.recovering();
+    let recovered = i.recovered.clone().unwrap_or_default();
+    // the errors of the blocks of an attempt which failed are dropped with it.
+    let mark = recovered.borrow().len();
+    let mut input = i.clone();
+    let mut inserted: Vec<usize> = Vec::new();
+    let mut errors = Vec::new();
+    // the tokens of `input` up to `position` which are really in `i`.
+    let original = |inserted: &[usize], position: usize| {
+        position - inserted.iter().filter(|&&at| at < position).count()
+    };
+    loop {
+        recovered.borrow_mut().truncate(mark);
+        let len = input.tokens.len();
+        let error = match parser(input.clone()) {
+            Ok((rest, node)) => {
+                let taken = original(&inserted, len - rest.tokens.len());
+                errors.extend(recovered.borrow_mut().drain(mark..));
+                return Item {
+                    taken,
+                    examined: taken + 1,
+                    node: Some(node),
+                    errors,
+                };
+            }
+            Err(error) => error,
+        };
+        let (failed_at, expects_semicolon) = match &error {
+            ::nom::Err::Error(error) | ::nom::Err::Failure(error) => (
+                len - error.input.tokens.len(),
+                error
+                    .expected
+                    .contains(&Expected::Token(TokenKind::PunctuationSemicolon)),
+            ),
+            ::nom::Err::Incomplete(_) => (len, false),
+        };
+        let progressed = match inserted.last() {
+            Some(&last) => failed_at > last + 1,
+            None => true,
+        };
+        if !progressed {
+            // the last `;` didn't help, so it wasn't what was missing.
+            errors.pop();
+        } else if expects_semicolon
+            && failed_at > 0
+            && missing_semicolon_at(&input.tokens, failed_at)
+        {
+            let span = input.tokens[failed_at - 1].span.clone();
+            errors.push(Error::missing_semicolon(&span));
+            input.tokens.insert(
+                failed_at,
+                Token {
+                    kind: TokenKind::PunctuationSemicolon,
+                    span: span.end(),
+                    content: ";".to_string(),
+                },
+            );
+            inserted.push(failed_at);
+            continue;
+        }
+        recovered.borrow_mut().truncate(mark);
+        let taken = skipped(&i.tokens);
+        errors.push(error.into());
+        return Item {
+            taken,
+            examined: taken.max(original(&inserted, failed_at) + 1),
+            node: None,
+            errors,
+        };
+    }
+}
+
+/// Parses as many statements as possible instead of stopping at the first
+/// syntax error. A broken statement is skipped up to the next statement
+/// boundary, and every error is returned next to the statements which did
+/// parse. Inside of a block, that is the next `;` or the end of the block,
+/// so the rest of the block and what it is in are kept. A statement only
+/// missing its `;` is kept, with an error pointing where the `;` belongs.
+pub fn parse_recovering(tokens: Vec<Token>) -> (Module, Vec<Error>) {
+    parse_recovering_with(tokens, &ParseOptions::default())
+}
+
+pub fn parse_recovering_with(tokens: Vec<Token>, options: &ParseOptions) -> (Module, Vec<Error>) {
+    let mut errors = Vec::new();
+    // what can't be expanded is parsed as it is, reporting the rest of it.
+    let tokens = match expand_macros(tokens.clone(), options) {
+        Ok(expanded) => expanded,
+        Err(error) => {
+            errors.push(error);
+            tokens
+        }
+    };
+    let mut i = ParseInput::new(tokens, options);
+    let mut nodes = Vec::new();
+    while !i.tokens.is_empty() {
+        let item = parse_item(i.clone());
+        nodes.extend(item.node);
+        errors.extend(item.errors);
+        i.tokens.drain(..item.taken);
+    }
+    (Module { nodes }, errors)
+}
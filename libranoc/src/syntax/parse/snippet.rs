@@ -0,0 +1,178 @@
+//! Entry points for parsing pieces of source on their own rather than whole
+//! modules, e.g. a line typed into the REPL or a formula given by a tool.
+
+use crate::{
+    core::{
+        ast::{Expression, Statement, Type},
+        Error, Location, Result,
+    },
+    syntax::{tokenize_with, Token, TokenizeOptions},
+};
+
+use super::{
+    all_consuming, many0, opt, tag, terminated, tuple, ParseInput, ParseOptions, TokenKind,
+};
+
+#[derive(Debug, Clone)]
+pub struct SnippetOptions {
+    pub tokenize: TokenizeOptions,
+    pub parse: ParseOptions,
+    /// Whether `1 + 2;` is accepted where an expression is expected.
+    pub allow_trailing_semicolon: bool,
+}
+
+impl Default for SnippetOptions {
+    fn default() -> Self {
+        SnippetOptions {
+            tokenize: TokenizeOptions::default(),
+            parse: ParseOptions::default(),
+            allow_trailing_semicolon: true,
+        }
+    }
+}
+
+/// Statements followed by at most one expression without a semicolon, just
+/// like the inside of a block.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Snippet {
+    pub statements: Vec<Statement>,
+    pub last_expression: Option<Expression>,
+}
+
+/// The outcome of parsing a piece of source which may continue on the next
+/// lines, as it does while someone types a function into the REPL.
+#[derive(Debug)]
+pub enum ParseStatus<T> {
+    Complete(T),
+    /// The source stops in the middle of something, like inside unbalanced
+    /// delimiters or right after an operator, so more input may fix it.
+    Incomplete,
+    Invalid(Error),
+}
+
+impl<T> ParseStatus<T> {
+    pub fn is_incomplete(&self) -> bool {
+        matches!(self, ParseStatus::Incomplete)
+    }
+}
+
+/// Whether `tokens` end while something is still open. A closing delimiter
+/// without its opening one can never be completed, so it is not counted as
+/// incomplete.
+fn is_unfinished(tokens: &[Token]) -> bool {
+    let mut depth = 0usize;
+    for token in tokens {
+        match token.kind {
+            TokenKind::PunctuationLeftParenthesis
+            | TokenKind::PunctuationLeftSquareBracket
+            | TokenKind::PunctuationLeftCurlyBracket => depth += 1,
+            TokenKind::PunctuationRightParenthesis
+            | TokenKind::PunctuationRightSquareBracket
+            | TokenKind::PunctuationRightCurlyBracket => match depth.checked_sub(1) {
+                Some(rest) => depth = rest,
+                None => return false,
+            },
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return true;
+    }
+    let last = match tokens.last() {
+        Some(last) => last,
+        None => return false,
+    };
+    match &last.kind {
+        // the lexer has no rule for a string missing its closing quote, so
+        // it ends up in an identifier.
+        TokenKind::IdentifierIdentifier(name) => name.starts_with('"'),
+        TokenKind::LiteralCharacter(_)
+        | TokenKind::LiteralString(_)
+        | TokenKind::LiteralStringTail(_)
+        | TokenKind::LiteralNumberIntegral(_)
+        | TokenKind::LiteralNumberDecimal(_)
+        | TokenKind::LiteralNumberExponent(_)
+        | TokenKind::LiteralBoolean(_)
+        | TokenKind::PunctuationRightParenthesis
+        | TokenKind::PunctuationRightSquareBracket
+        | TokenKind::PunctuationRightCurlyBracket
+        | TokenKind::PunctuationSemicolon
+        | TokenKind::PunctuationQuestionMark
+        | TokenKind::KeywordPlaceholderName
+        | TokenKind::KeywordBreak
+        | TokenKind::KeywordContinue
+        | TokenKind::KeywordReturn
+        | TokenKind::KeywordSelf
+        | TokenKind::KeywordSelfType
+        | TokenKind::Error => false,
+        // operators, `,`, `->`, `fn` and the like need something after them.
+        _ => true,
+    }
+}
+
+fn input(src: &str, options: &SnippetOptions) -> ParseInput {
+    ParseInput::new(tokenize_with(src, options.tokenize.clone()), &options.parse)
+}
+
+pub fn parse_expression(src: &str) -> Result<Expression> {
+    parse_expression_with(src, &SnippetOptions::default())
+}
+
+pub fn parse_expression_with(src: &str, options: &SnippetOptions) -> Result<Expression> {
+    let i = input(src, options);
+    let (_, expression) = if options.allow_trailing_semicolon {
+        all_consuming(terminated(
+            super::parse_expression,
+            opt(tag(TokenKind::PunctuationSemicolon)),
+        ))(i)?
+    } else {
+        all_consuming(super::parse_expression)(i)?
+    };
+    Ok(expression)
+}
+
+pub fn parse_type(src: &str) -> Result<Type> {
+    parse_type_with(src, &SnippetOptions::default())
+}
+
+pub fn parse_type_with(src: &str, options: &SnippetOptions) -> Result<Type> {
+    let (_, ty) = all_consuming(super::parse_type)(input(src, options))?;
+    Ok(ty)
+}
+
+pub fn parse_snippet(src: &str) -> Result<Snippet> {
+    parse_snippet_with(src, &SnippetOptions::default())
+}
+
+pub fn parse_snippet_with(src: &str, options: &SnippetOptions) -> Result<Snippet> {
+    let (_, (statements, last_expression)) = all_consuming(tuple((
+        many0(super::parse_statement),
+        opt(super::parse_expression),
+    )))(input(src, options))?;
+    Ok(Snippet {
+        statements,
+        last_expression,
+    })
+}
+
+pub fn parse_snippet_status(src: &str) -> ParseStatus<Snippet> {
+    parse_snippet_status_with(src, &SnippetOptions::default())
+}
+
+pub fn parse_snippet_status_with(src: &str, options: &SnippetOptions) -> ParseStatus<Snippet> {
+    if is_unfinished(&tokenize_with(src, options.tokenize.clone())) {
+        return ParseStatus::Incomplete;
+    }
+    match parse_snippet_with(src, options) {
+        Ok(snippet) => ParseStatus::Complete(snippet),
+        Err(error)
+            if error
+                .labels
+                .iter()
+                .any(|label| matches!(label.location, Location::Eof)) =>
+        {
+            ParseStatus::Incomplete
+        }
+        Err(error) => ParseStatus::Invalid(error),
+    }
+}
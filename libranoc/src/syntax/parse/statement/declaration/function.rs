@@ -4,46 +4,53 @@ use crate::{
 };
 
 pub fn parse_function_declaration(i: ParseInput) -> ParseResult<FunctionDeclaration> {
+    let start = i.clone();
+    let (i, documentation) = parse_documentation(i)?;
+    let (i, attributes) = many0(parse_attribute)(i)?;
     let (i, pub_token) = opt(tag(TokenKind::KeywordPub))(i)?;
     let (i, extern_token) = opt(tag(TokenKind::KeywordExtern))(i)?;
     let (i, _) = tag(TokenKind::KeywordFn)(i)?;
     let (i, name) = cut(parse_identifier)(i)?;
+    let (i, generic_parameters) = opt(parse_generic_parameters)(i)?;
 
     let (i, parameters) = opt(delimited(
         tag(TokenKind::PunctuationLeftParenthesis),
-        |i| {
-            let (i, res) = separated_list0(tag(TokenKind::PunctuationComma), |i| {
+        map(
+            separated_trailing0(tag(TokenKind::PunctuationComma), |i| {
                 let (i, pattern) = parse_pattern(i)?;
                 let (i, ty) = parse_type_annotation(i)?;
                 Ok((i, (pattern, ty)))
-            })(i)?;
-            if res.len() > 0 {
-                let (i, _) = opt(tag(TokenKind::PunctuationComma))(i)?;
-                Ok((i, res))
-            } else {
-                Ok((i, res))
-            }
-        },
+            }),
+            |(parameters, _)| parameters,
+        ),
         tag(TokenKind::PunctuationRightParenthesis),
     ))(i)?;
 
+    let parameters_end = taken_span(&start, &i).end();
     let (i, return_type) = opt(preceded(tag(TokenKind::PunctuationColon), parse_type))(i)?;
-    let return_type = return_type.unwrap_or_else(|| Type::Tuple(Vec::new()));
+    let return_type = return_type.unwrap_or_else(|| Type::Tuple(Vec::new(), parameters_end));
 
     let (i, body) = cut(alt((
         map(tag(TokenKind::PunctuationSemicolon), |_| None),
         map(parse_block, Some),
     )))(i)?;
 
+    let span = taken_span(&start, &i);
+    let id = i.next_id();
     Ok((
         i,
         FunctionDeclaration {
+            documentation,
+            attributes,
             is_pub: pub_token.is_some(),
             is_extern: extern_token.is_some(),
             name,
+            generic_parameters: generic_parameters.unwrap_or_default(),
             parameters: parameters.unwrap_or_else(|| Vec::new()),
             return_type,
             body,
+            span,
+            id,
         },
     ))
 }
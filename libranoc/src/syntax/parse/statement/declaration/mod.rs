@@ -4,11 +4,22 @@ use crate::{
 };
 
 mod function;
+mod module;
+mod operator;
+mod r#use;
 
 pub use function::*;
+pub use module::*;
+pub use operator::*;
+pub use r#use::*;
 
 pub fn parse_declaration(i: ParseInput) -> ParseResult<Declaration> {
-    parse_function_declaration_declaration(i)
+    alt((
+        parse_function_declaration_declaration,
+        parse_use_declaration_declaration,
+        parse_module_declaration_declaration,
+        parse_operator_declaration_declaration,
+    ))(i)
 }
 
 pub fn parse_declaration_statement(i: ParseInput) -> ParseResult<Statement> {
@@ -0,0 +1,49 @@
+use crate::{
+    core::ast::{Declaration, Module, ModuleBody, ModuleDeclaration},
+    syntax::parse::*,
+};
+
+fn parse_inline_module_body(i: ParseInput) -> ParseResult<ModuleBody> {
+    let (i, curly_bracket_open_token) = tag(TokenKind::PunctuationLeftCurlyBracket)(i)?;
+    let (i, nodes) = many0(parse_statement_node)(i)?;
+    let (i, curly_bracket_close_token) = cut(tag(TokenKind::PunctuationRightCurlyBracket))(i)?;
+    Ok((
+        i,
+        ModuleBody::Inline {
+            curly_bracket_open_token,
+            module: Module { nodes },
+            curly_bracket_close_token,
+        },
+    ))
+}
+
+pub fn parse_module_declaration(i: ParseInput) -> ParseResult<ModuleDeclaration> {
+    let start = i.clone();
+    let (i, documentation) = parse_documentation(i)?;
+    let (i, pub_token) = opt(tag(TokenKind::KeywordPub))(i)?;
+    let (i, mod_token) = tag(TokenKind::KeywordMod)(i)?;
+    let (i, name) = cut(parse_identifier)(i)?;
+    let (i, body) = cut(alt((
+        map(tag(TokenKind::PunctuationSemicolon), ModuleBody::File),
+        nested(parse_inline_module_body),
+    )))(i)?;
+    let span = taken_span(&start, &i);
+    let id = i.next_id();
+    Ok((
+        i,
+        ModuleDeclaration {
+            documentation,
+            is_pub: pub_token.is_some(),
+            mod_token,
+            name,
+            body,
+            span,
+            id,
+        },
+    ))
+}
+
+pub fn parse_module_declaration_declaration(i: ParseInput) -> ParseResult<Declaration> {
+    let (s, declaration) = parse_module_declaration(i)?;
+    Ok((s, Declaration::ModuleDeclaration(declaration)))
+}
@@ -0,0 +1,107 @@
+use ::nom::Err;
+
+use crate::{
+    core::ast::{Declaration, OperatorDeclaration},
+    syntax::{parse::*, PUNCTUATIONS},
+};
+
+/// The punctuations an operator may be spelled with. Delimiters and
+/// separators are left out, and so is `:` which ends the symbol.
+fn is_operator_punctuation(token: &Token) -> bool {
+    !matches!(
+        token.kind,
+        TokenKind::PunctuationColon
+            | TokenKind::PunctuationSemicolon
+            | TokenKind::PunctuationComma
+            | TokenKind::PunctuationLeftParenthesis
+            | TokenKind::PunctuationRightParenthesis
+            | TokenKind::PunctuationLeftSquareBracket
+            | TokenKind::PunctuationRightSquareBracket
+            | TokenKind::PunctuationLeftCurlyBracket
+            | TokenKind::PunctuationRightCurlyBracket
+    ) && PUNCTUATIONS.iter().any(|(_, kind)| *kind == token.kind)
+}
+
+fn parse_operator_symbol(i: ParseInput) -> ParseResult<Vec<Token>> {
+    let (mut rest, first) = satisfy(is_operator_punctuation)(i.clone())?;
+    let mut symbol = vec![first];
+    while let Ok((next_rest, next)) = satisfy(is_operator_punctuation)(rest.clone()) {
+        if symbol[symbol.len() - 1].span.range.end != next.span.range.start {
+            break;
+        }
+        symbol.push(next);
+        rest = next_rest;
+    }
+    Ok((rest, symbol))
+}
+
+/// An identifier which `valid` accepts, failing with `description` as what
+/// was expected otherwise.
+fn parse_name_of(
+    valid: fn(&str) -> bool,
+    description: &'static str,
+) -> impl Fn(ParseInput) -> ParseResult<Token> {
+    move |i| match parse_identifier(i.clone()) {
+        Ok((rest, token)) if valid(&token.content) => Ok((rest, token)),
+        _ => Err(Err::Failure(Error::expected(
+            i,
+            Expected::Description(description),
+        ))),
+    }
+}
+
+pub fn parse_operator_declaration(i: ParseInput) -> ParseResult<OperatorDeclaration> {
+    let start = i.clone();
+    let (i, documentation) = parse_documentation(i)?;
+    let (i, operator_token) = tag(TokenKind::KeywordOperator)(i)?;
+    let (i, symbol) = cut(context("an operator", parse_operator_symbol))(i)?;
+    let (i, _) = cut(tag(TokenKind::PunctuationColon))(i)?;
+    let (i, precedence) = parse_name_of(
+        |name| Precedence::from_name(name).is_some(),
+        "a precedence like `Additive`",
+    )(i)?;
+    let (i, _) = cut(tag(TokenKind::PunctuationComma))(i)?;
+    let (i, associativity) = parse_name_of(
+        |name| Associativity::from_name(name).is_some(),
+        "`left` or `right`",
+    )(i)?;
+    let (i, _) = cut(tag(TokenKind::PunctuationEqualsSign))(i)?;
+    let (i, function) = cut(parse_identifier)(i)?;
+    let (i, _) = cut(tag(TokenKind::PunctuationSemicolon))(i)?;
+    let span = taken_span(&start, &i);
+    let id = i.next_id();
+    Ok((
+        i,
+        OperatorDeclaration {
+            documentation,
+            operator_token,
+            symbol,
+            precedence,
+            associativity,
+            function,
+            span,
+            id,
+        },
+    ))
+}
+
+pub fn parse_operator_declaration_declaration(i: ParseInput) -> ParseResult<Declaration> {
+    let (s, declaration) = parse_operator_declaration(i)?;
+    Ok((s, Declaration::OperatorDeclaration(declaration)))
+}
+
+/// Every operator declared in `tokens`. They are collected before parsing,
+/// so an operator can be used anywhere in the file it is declared in.
+pub fn declared_operators(tokens: &[Token], options: &ParseOptions) -> Vec<DeclaredOperator> {
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| token.kind == TokenKind::KeywordOperator)
+        .filter_map(|(index, _)| {
+            let i =
+                ParseInput::with_operators(tokens[index..].to_vec(), options, Default::default());
+            let (_, declaration) = parse_operator_declaration(i).ok()?;
+            DeclaredOperator::new(&declaration)
+        })
+        .collect()
+}
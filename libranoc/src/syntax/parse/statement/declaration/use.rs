@@ -0,0 +1,76 @@
+use crate::{
+    core::ast::{Declaration, Path, UseDeclaration, UseTree, UseTreeKind},
+    syntax::parse::*,
+};
+
+fn parse_use_tree_group(i: ParseInput) -> ParseResult<Vec<UseTree>> {
+    map(
+        delimited(
+            tag(TokenKind::PunctuationLeftCurlyBracket),
+            separated_trailing0(tag(TokenKind::PunctuationComma), nested(parse_use_tree)),
+            cut(tag(TokenKind::PunctuationRightCurlyBracket)),
+        ),
+        |(trees, _)| trees,
+    )(i)
+}
+
+/// `a::b as c`, `a::*` or `a::{...}`, where the path in front may be empty
+/// for the last two.
+pub fn parse_use_tree(i: ParseInput) -> ParseResult<UseTree> {
+    let start = i.clone();
+    let (i, mut segments) = many0(terminated(parse_identifier, parse_path_separator))(i)?;
+    let (i, (last, kind)) = alt((
+        map(tag(TokenKind::PunctuationAsterisk), |star| {
+            (None, UseTreeKind::Glob(star))
+        }),
+        map(parse_use_tree_group, |trees| {
+            (None, UseTreeKind::Nested(trees))
+        }),
+        map(
+            tuple((
+                parse_identifier,
+                opt(preceded(tag(TokenKind::KeywordAs), cut(parse_identifier))),
+            )),
+            |(last, alias)| (Some(last), UseTreeKind::Simple(alias)),
+        ),
+    ))(i)?;
+    segments.extend(last);
+    let span = taken_span(&start, &i);
+    let id = i.next_id();
+    Ok((
+        i,
+        UseTree {
+            prefix: Path(segments),
+            kind,
+            span,
+            id,
+        },
+    ))
+}
+
+pub fn parse_use_declaration(i: ParseInput) -> ParseResult<UseDeclaration> {
+    let start = i.clone();
+    let (i, documentation) = parse_documentation(i)?;
+    let (i, pub_token) = opt(tag(TokenKind::KeywordPub))(i)?;
+    let (i, use_token) = tag(TokenKind::KeywordUse)(i)?;
+    let (i, tree) = cut(parse_use_tree)(i)?;
+    let (i, _) = cut(tag(TokenKind::PunctuationSemicolon))(i)?;
+    let span = taken_span(&start, &i);
+    let id = i.next_id();
+    Ok((
+        i,
+        UseDeclaration {
+            documentation,
+            is_pub: pub_token.is_some(),
+            use_token,
+            tree,
+            span,
+            id,
+        },
+    ))
+}
+
+pub fn parse_use_declaration_declaration(i: ParseInput) -> ParseResult<Declaration> {
+    let (s, declaration) = parse_use_declaration(i)?;
+    Ok((s, Declaration::UseDeclaration(declaration)))
+}
@@ -0,0 +1,15 @@
+use crate::{core::ast::Expression, syntax::parse::*};
+
+/// Parses an array literal, like `[1, 2, 3]`, which may end with a comma.
+pub fn parse_array_expression(i: ParseInput) -> ParseResult<Expression> {
+    let (i, ((elements, _), span)) = spanned(delimited(
+        tag(TokenKind::PunctuationLeftSquareBracket),
+        enclosed(separated_trailing0(
+            tag(TokenKind::PunctuationComma),
+            parse_expression,
+        )),
+        tag(TokenKind::PunctuationRightSquareBracket),
+    ))(i)?;
+
+    Ok((i, Expression::Array(elements, span)))
+}
@@ -0,0 +1,62 @@
+use crate::{core::ast::*, syntax::parse::*};
+
+type ClosureParameter = (Pattern, Option<Type>);
+
+fn parse_closure_parameter(i: ParseInput) -> ParseResult<ClosureParameter> {
+    let (i, pattern) = parse_pattern(i)?;
+    let (i, ty) = opt(parse_type_annotation)(i)?;
+    Ok((i, (pattern, ty)))
+}
+
+/// `|x, y: i32|`, or `||` which is a single token.
+fn parse_closure_parameters(i: ParseInput) -> ParseResult<(Token, Vec<ClosureParameter>)> {
+    if let Ok((i, token)) = tag(TokenKind::PunctuationsLogicalOr)(i.clone()) {
+        return Ok((i, (token, Vec::new())));
+    }
+    let (i, vertical_line_token) = tag(TokenKind::PunctuationVerticalLine)(i)?;
+    let (i, (parameters, _)) =
+        separated_trailing0(tag(TokenKind::PunctuationComma), parse_closure_parameter)(i)?;
+    let (i, _) = cut(tag(TokenKind::PunctuationVerticalLine))(i)?;
+    Ok((i, (vertical_line_token, parameters)))
+}
+
+/// The body takes everything up to where the surrounding expression can't
+/// go on, so `|x| x + 1` adds inside of the closure.
+pub fn parse_closure(i: ParseInput) -> ParseResult<Closure> {
+    let (i, move_token) = opt(tag(TokenKind::KeywordMove))(i)?;
+    let (i, (vertical_line_token, parameters)) = if move_token.is_some() {
+        cut(parse_closure_parameters)(i)?
+    } else {
+        parse_closure_parameters(i)?
+    };
+    let (i, return_type) = opt(parse_type_annotation)(i)?;
+    let original_binding_power = i.binding_power;
+    let (i, body) = match return_type {
+        Some(_) => cut(map(parse_block, |block| {
+            ClosureBody::Block(Box::new(block))
+        }))(i)?,
+        None => cut(alt((
+            map(parse_block, |block| ClosureBody::Block(Box::new(block))),
+            map(parse_expression, |expression| {
+                ClosureBody::Expression(Box::new(expression))
+            }),
+        )))(i.with_binding_power(0))?,
+    };
+
+    let id = i.next_id();
+    Ok((
+        i.with_binding_power(original_binding_power),
+        Closure {
+            move_token,
+            vertical_line_token,
+            parameters,
+            return_type,
+            body,
+            id,
+        },
+    ))
+}
+
+pub fn parse_closure_expression(i: ParseInput) -> ParseResult<Expression> {
+    map(parse_closure, Expression::Closure)(i)
+}
@@ -1,25 +1,24 @@
-use crate::{
-    core::ast::Expression,
-    syntax::{parse::*},
-};
+use crate::{core::ast::Expression, syntax::parse::*};
 
+/// Parses either a parenthesized expression or a tuple.
+///
+/// A single element in parentheses is only a grouping, so `(a + b) * c`
+/// multiplies the sum. A tuple with one element needs a trailing comma, like
+/// `(a,)`, while `()` is the empty tuple.
 pub fn parse_group_tuple_expression(i: ParseInput) -> ParseResult<Expression> {
-    let i = i.with_binding_power(0);
-    let (i, (mut elements, last_comma)) = delimited(
+    let (i, ((mut elements, last_comma), span)) = spanned(delimited(
         tag(TokenKind::PunctuationLeftParenthesis),
-        |i| {
-            let (i, elements) =
-                separated_list0(tag(TokenKind::PunctuationComma), parse_expression)(i)?;
-            let (i, last_comma) = opt(tag(TokenKind::PunctuationComma))(i)?;
-            Ok((i, (elements, last_comma.is_some())))
-        },
+        enclosed(separated_trailing0(
+            tag(TokenKind::PunctuationComma),
+            parse_expression,
+        )),
         tag(TokenKind::PunctuationRightParenthesis),
-    )(i)?;
+    ))(i)?;
 
-    let expr = if elements.len() > 1 || last_comma {
+    let expr = if elements.len() == 1 && !last_comma {
         elements.swap_remove(0)
     } else {
-        Expression::Tuple(elements)
+        Expression::Tuple(elements, span)
     };
 
     Ok((i, expr))
@@ -1,5 +1,8 @@
 use crate::{core::ast::*, syntax::parse::*};
 
+/// `else if ...` or `else { ... }`. The `else` is taken by the innermost
+/// `if` still open, as every body is a block there is no other `if` it could
+/// belong to.
 fn parse_else(i: ParseInput) -> ParseResult<Else> {
     let (i, else_token) = tag(TokenKind::KeywordElse)(i)?;
 
@@ -15,13 +18,16 @@ fn parse_else(i: ParseInput) -> ParseResult<Else> {
     Ok((i, r#else))
 }
 
+/// An `if` with its `else if`s and `else`, where each `else if` is an `If`
+/// nested in the `else` of the one before; `If::branches` flattens them.
 pub fn parse_if(i: ParseInput) -> ParseResult<If> {
     let (i, if_token) = tag(TokenKind::KeywordIf)(i)?;
-    let original_binding_power = i.binding_power;
-    let (i, condition) = parse_expression(i.with_binding_power(0))?;
-    let (i, body) = parse_block(i)?;
+    let (original_binding_power, struct_literals) = (i.binding_power, i.struct_literals);
+    let (i, condition) = parse_expression(i.with_binding_power(0).with_struct_literals(false))?;
+    let (i, body) = parse_block(i.with_struct_literals(struct_literals))?;
     let (i, else_part) = opt(parse_else)(i)?;
 
+    let id = i.next_id();
     Ok((
         i.with_binding_power(original_binding_power),
         If {
@@ -29,6 +35,7 @@ pub fn parse_if(i: ParseInput) -> ParseResult<If> {
             condition: Box::new(condition),
             body: Box::new(body),
             else_part,
+            id,
         },
     ))
 }
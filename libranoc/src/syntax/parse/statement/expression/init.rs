@@ -0,0 +1,59 @@
+use ::nom::Err;
+
+use crate::{core::ast::*, syntax::parse::*};
+
+fn parse_init_field(i: ParseInput) -> ParseResult<InitField> {
+    let (i, name) = parse_identifier(i)?;
+    let (i, value) = opt(preceded(
+        tag(TokenKind::PunctuationColon),
+        cut(parse_expression),
+    ))(i)?;
+    let value = value.unwrap_or_else(|| Expression::Name(Name::Ident(name.clone())));
+    let id = i.next_id();
+    Ok((i, InitField { name, value, id }))
+}
+
+/// Whether the tokens after the name look like `{ field: `, which only a
+/// struct literal can start with.
+fn looks_like_init(i: &ParseInput) -> bool {
+    matches!(
+        i.tokens
+            .get(1..4)
+            .map(|tokens| [&tokens[0].kind, &tokens[1].kind, &tokens[2].kind]),
+        Some([
+            TokenKind::PunctuationLeftCurlyBracket,
+            TokenKind::IdentifierIdentifier(_),
+            TokenKind::PunctuationColon,
+        ])
+    )
+}
+
+/// `Name { field: value, ... }`. Where struct literals aren't allowed the
+/// `{` belongs to something else, like the body of an `if`, unless it can
+/// only be a struct literal, which is then reported as such.
+pub fn parse_init_expression(i: ParseInput) -> ParseResult<Expression> {
+    if !i.struct_literals {
+        return if looks_like_init(&i) {
+            Err(Err::Failure(Error::struct_literal_not_allowed(i)))
+        } else {
+            err_tag(i)
+        };
+    }
+    let (i, name) = parse_identifier(i)?;
+    let (i, _) = tag(TokenKind::PunctuationLeftCurlyBracket)(i)?;
+    let (i, (fields, _)) = enclosed(separated_trailing0(
+        tag(TokenKind::PunctuationComma),
+        parse_init_field,
+    ))(i)?;
+    let (i, curly_bracket_close_token) = cut(tag(TokenKind::PunctuationRightCurlyBracket))(i)?;
+    let id = i.next_id();
+    Ok((
+        i,
+        Expression::Init(Init {
+            name,
+            fields,
+            curly_bracket_close_token,
+            id,
+        }),
+    ))
+}
@@ -0,0 +1,20 @@
+use crate::{core::ast::*, syntax::parse::*};
+
+/// `@name<T>`. An intrinsic is only ever called, so unlike behind a name
+/// the `<` can't start a comparison here.
+pub fn parse_intrinsic_expression(i: ParseInput) -> ParseResult<Expression> {
+    let (i, ((at, name, type_arguments), span)) = spanned(tuple((
+        tag(TokenKind::PunctuationCommercialAt),
+        cut(parse_identifier),
+        opt(parse_type_arguments),
+    )))(i)?;
+    Ok((
+        i,
+        Expression::Intrinsic(Intrinsic {
+            at,
+            name,
+            type_arguments: type_arguments.unwrap_or_default(),
+            span,
+        }),
+    ))
+}
@@ -1,47 +1,62 @@
-use crate::{
-    core::ast::*,
-    syntax::parse::*,
-};
+use crate::{core::ast::*, syntax::parse::*};
+
+fn parse_literal_string_token(i: ParseInput) -> ParseResult<Token> {
+    satisfy(|token| matches!(token.kind, TokenKind::LiteralString(_)))(i)
+}
+
+/// Joins string literals which only have whitespace between them, like
+/// `"abc" "def"`, into a single `"abcdef"` spanning all of them.
+fn concat_string_literals(first: Token, rest: Vec<Token>) -> Token {
+    rest.into_iter().fold(first, |joined, token| {
+        let content = format!(
+            "{}{}",
+            &joined.content[..joined.content.len() - 1],
+            &token.content[1..]
+        );
+        Token {
+            kind: TokenKind::LiteralString(content.clone()),
+            span: joined.span.joined(&token.span),
+            content,
+        }
+    })
+}
 
 pub fn parse_literal_string(i: ParseInput) -> ParseResult<Literal> {
-    let (i, token) = any(i)?;
-    if let TokenKind::LiteralString(v) = &token.kind {
-        Ok((i, Literal::String(v.clone())))
-    } else {
-        err_tag(i)
-    }
+    let (i, first) = parse_literal_string_token(i)?;
+    let (i, rest) = many0(parse_literal_string_token)(i)?;
+    Ok((i, Literal::String(concat_string_literals(first, rest))))
 }
 
 pub fn parse_literal_character(i: ParseInput) -> ParseResult<Literal> {
-    let (i, token) = any(i)?;
-    if let TokenKind::LiteralCharacter(v) = &token.kind {
-        Ok((i, Literal::Character(v.clone())))
+    let (rest, token) = any(i.clone())?;
+    if let TokenKind::LiteralCharacter(_) = &token.kind {
+        Ok((rest, Literal::Character(token)))
     } else {
         err_tag(i)
     }
 }
 
 pub fn parse_literal_boolean(i: ParseInput) -> ParseResult<Literal> {
-    let (i, token) = any(i)?;
-    if let TokenKind::LiteralBoolean(v) = &token.kind {
-        Ok((i, Literal::Boolean(v.clone())))
+    let (rest, token) = any(i.clone())?;
+    if let TokenKind::LiteralBoolean(_) = &token.kind {
+        Ok((rest, Literal::Boolean(token)))
     } else {
         err_tag(i)
     }
 }
 
 pub fn parse_literal_integer(i: ParseInput) -> ParseResult<Literal> {
-    let (i, token) = any(i)?;
-    if let TokenKind::LiteralNumberIntegral(v) = &token.kind {
-        Ok((i, Literal::Integer(Integer(v.clone()))))
+    let (rest, token) = any(i.clone())?;
+    if let TokenKind::LiteralNumberIntegral(_) = &token.kind {
+        Ok((rest, Literal::Integer(Integer(token))))
     } else {
         err_tag(i)
     }
 }
 pub fn parse_literal_decimal(i: ParseInput) -> ParseResult<Literal> {
-    let (i, token) = any(i)?;
-    if let TokenKind::LiteralNumberDecimal(v) = &token.kind {
-        Ok((i, Literal::Decimal(v.clone())))
+    let (rest, token) = any(i.clone())?;
+    if let TokenKind::LiteralNumberDecimal(_) = &token.kind {
+        Ok((rest, Literal::Decimal(token)))
     } else {
         err_tag(i)
     }
@@ -60,3 +75,28 @@ pub fn parse_literal(i: ParseInput) -> ParseResult<Literal> {
 pub fn parse_literal_expression(i: ParseInput) -> ParseResult<Expression> {
     map(parse_literal, Expression::Literal)(i)
 }
+
+fn parse_interpolated_expression(i: ParseInput) -> ParseResult<InterpolationPart> {
+    map(enclosed(parse_expression), InterpolationPart::Expression)(i)
+}
+
+pub fn parse_interpolation_expression(i: ParseInput) -> ParseResult<Expression> {
+    let (i, head) = satisfy(|token| matches!(token.kind, TokenKind::LiteralStringHead(_)))(i)?;
+    let (i, expression) = cut(parse_interpolated_expression)(i)?;
+    let (i, middles) = many0(tuple((
+        satisfy(|token| matches!(token.kind, TokenKind::LiteralStringMiddle(_))),
+        cut(parse_interpolated_expression),
+    )))(i)?;
+    let (i, tail) = cut(satisfy(|token| {
+        matches!(token.kind, TokenKind::LiteralStringTail(_))
+    }))(i)?;
+
+    let mut parts = vec![InterpolationPart::Fragment(head), expression];
+    for (middle, expression) in middles {
+        parts.push(InterpolationPart::Fragment(middle));
+        parts.push(expression);
+    }
+    parts.push(InterpolationPart::Fragment(tail));
+
+    Ok((i, Expression::Interpolation(parts)))
+}
@@ -0,0 +1,87 @@
+use ::nom::{error::ParseError, Err};
+
+use crate::{core::ast::*, syntax::parse::*};
+
+fn parse_match_arm_body(i: ParseInput) -> ParseResult<MatchArmBody> {
+    alt((
+        map(parse_block, |block| MatchArmBody::Block(Box::new(block))),
+        map(parse_expression, |expression| {
+            MatchArmBody::Expression(Box::new(expression))
+        }),
+    ))(i)
+}
+
+fn parse_match_arm(i: ParseInput) -> ParseResult<MatchArm> {
+    let (i, pattern) = parse_refutable_pattern(i)?;
+    let (i, guard) = opt(preceded(tag(TokenKind::KeywordIf), cut(parse_expression)))(i)?;
+    let (i, _) = cut(tag(TokenKind::PunctuationsDoubleRightArrow))(i)?;
+    let (i, body) = cut(parse_match_arm_body)(i)?;
+    let id = i.next_id();
+    Ok((
+        i,
+        MatchArm {
+            pattern,
+            guard,
+            body,
+            id,
+        },
+    ))
+}
+
+/// The arms up to the closing `}`. A `,` is optional behind the last arm and
+/// behind an arm whose body is a block.
+fn parse_match_arms(i: ParseInput) -> ParseResult<Vec<MatchArm>> {
+    let mut arms = Vec::new();
+    let mut i = i;
+    while tag(TokenKind::PunctuationRightCurlyBracket)(i.clone()).is_err() {
+        let (rest, arm) = parse_match_arm(i)?;
+        let needs_comma = matches!(arm.body, MatchArmBody::Expression(_));
+        arms.push(arm);
+        i = match tag(TokenKind::PunctuationComma)(rest.clone()) {
+            Ok((rest, _)) => rest,
+            Err(_) if !needs_comma => rest,
+            Err(_) => match tag(TokenKind::PunctuationRightCurlyBracket)(rest.clone()) {
+                Ok(_) => rest,
+                Err(_) => {
+                    return Err(Err::Failure(
+                        Error::expected(rest.clone(), Expected::Token(TokenKind::PunctuationComma))
+                            .or(Error::expected(
+                                rest,
+                                Expected::Token(TokenKind::PunctuationRightCurlyBracket),
+                            )),
+                    ))
+                }
+            },
+        };
+    }
+    Ok((i, arms))
+}
+
+/// `match` with the scrutinee, which can't be a struct literal for the same
+/// reason as the condition of an `if`, and the arms inside of `{...}`.
+pub fn parse_match(i: ParseInput) -> ParseResult<Match> {
+    let (i, match_token) = tag(TokenKind::KeywordMatch)(i)?;
+    let (original_binding_power, struct_literals) = (i.binding_power, i.struct_literals);
+    let (i, scrutinee) =
+        cut(parse_expression)(i.with_binding_power(0).with_struct_literals(false))?;
+    let i = i.with_struct_literals(struct_literals);
+    let (i, _) = cut(tag(TokenKind::PunctuationLeftCurlyBracket))(i)?;
+    let (i, arms) = cut(enclosed(parse_match_arms))(i)?;
+    let (i, curly_bracket_close_token) = cut(tag(TokenKind::PunctuationRightCurlyBracket))(i)?;
+
+    let id = i.next_id();
+    Ok((
+        i.with_binding_power(original_binding_power),
+        Match {
+            match_token,
+            scrutinee: Box::new(scrutinee),
+            arms,
+            curly_bracket_close_token,
+            id,
+        },
+    ))
+}
+
+pub fn parse_match_expression(i: ParseInput) -> ParseResult<Expression> {
+    map(parse_match, Expression::Match)(i)
+}
@@ -1,84 +1,134 @@
 use crate::{core::ast::*, syntax::parse::*};
 
+mod array;
+mod closure;
 mod group_tuple;
 mod r#if;
+mod init;
+mod intrinsic;
 mod literal;
+mod r#match;
 mod name;
 mod operator;
+mod precedence;
+mod todo;
+mod r#unsafe;
 
+pub use array::*;
+pub use closure::*;
 pub use group_tuple::*;
+pub use init::*;
+pub use intrinsic::*;
 pub use literal::*;
 pub use name::*;
 pub use operator::*;
+pub use precedence::*;
 pub use r#if::*;
+pub use r#match::*;
+pub use r#unsafe::*;
+pub use todo::*;
 
 pub fn parse_simple_expression(i: ParseInput) -> ParseResult<Expression> {
     alt((
         parse_literal_expression,
+        parse_interpolation_expression,
+        parse_init_expression,
         parse_name_expression,
         parse_if_expression,
+        parse_match_expression,
+        parse_closure_expression,
+        parse_unsafe_expression,
+        parse_todo_expression,
+        parse_intrinsic_expression,
     ))(i)
 }
 pub fn parse_expression(i: ParseInput) -> ParseResult<Expression> {
-    let (i, lhs) = alt((
-        |i| {
-            let (i, operator) = parse_prefix_operator(i)?;
-            let original_binding_power = i.binding_power;
-            let (i, rhs) = parse_expression(i.with_binding_power(operator.right_binding_power))?;
-            Ok((
-                i.with_binding_power(original_binding_power),
-                Expression::Operator(Operator::Prefix((operator.constructor)(Box::new(rhs)))),
-            ))
-        },
-        parse_group_tuple_expression,
-        parse_simple_expression,
-    ))(i)?;
+    nested(parse_expression_inner)(i)
+}
+
+fn parse_expression_inner(i: ParseInput) -> ParseResult<Expression> {
+    let (i, lhs) = context(
+        "an expression",
+        alt((
+            |i| {
+                let (i, operator) = parse_prefix_operator(i)?;
+                let original_binding_power = i.binding_power;
+                let (i, rhs) =
+                    cut(parse_expression)(i.with_binding_power(operator.right_binding_power))?;
+                Ok((
+                    i.with_binding_power(original_binding_power),
+                    Expression::Operator(Operator::Prefix((operator.constructor)(Box::new(rhs)))),
+                ))
+            },
+            parse_group_tuple_expression,
+            parse_array_expression,
+            parse_simple_expression,
+        )),
+    )(i)?;
 
     type Transformer = Box<dyn FnOnce(Box<Expression>) -> Expression>;
     let (i, lhs) = fold_many0(
         alt((
-            |i| {
+            |i: ParseInput| {
+                let start = i.clone();
                 let (i, operator) = parse_postfix_operator(i)?;
                 if operator.left_binding_power < i.binding_power {
-                    return err_tag(i);
+                    return err_tag(start);
                 }
 
-                let original_binding_power = i.binding_power;
-                let (i, tails) = (operator.tails)(i.with_binding_power(0))?;
-                let (i, _) = (operator.close)(i.with_binding_power(original_binding_power))?;
+                let (i, tails) = (operator.tails)(i)?;
+                let (i, close) = (operator.close)(i)?;
                 let constructor = operator.constructor;
 
                 let transformer: Transformer = Box::new(move |lhs| {
-                    Expression::Operator(Operator::Postfix(constructor(lhs, tails)))
+                    Expression::Operator(Operator::Postfix(constructor(lhs, tails, close)))
                 });
                 Ok((i, transformer))
             },
-            |i| {
+            |i: ParseInput| {
+                if Precedence::Index.left_binding_power() < i.binding_power {
+                    return err_tag(i);
+                }
+                let (i, (arguments, close)) = parse_turbofish(i)?;
+
+                let transformer: Transformer = Box::new(move |lhs| {
+                    Expression::Operator(Operator::Postfix(PostfixOperator::TypeArguments(
+                        TypeArguments(lhs, arguments, close),
+                    )))
+                });
+                Ok((i, transformer))
+            },
+            |i: ParseInput| {
+                let start = i.clone();
                 let (i, operator) = parse_infix_operator(i)?;
                 if operator.left_binding_power < i.binding_power {
-                    return err_tag(i);
+                    return err_tag(start);
                 }
 
                 let original_binding_power = i.binding_power;
+                // an infix operator can't end an expression.
                 let (i, rhs) =
-                    parse_expression(i.with_binding_power(operator.right_binding_power))?;
+                    cut(parse_expression)(i.with_binding_power(operator.right_binding_power))?;
 
                 let transformer: Transformer = Box::new(move |lhs| {
-                    Expression::Operator(Operator::Infix((operator.constructor)(
-                        lhs,
-                        operator.operator.span,
-                        Box::new(rhs),
-                    )))
+                    (operator.constructor)(lhs, operator.operator.span, Box::new(rhs))
                 });
                 Ok((i.with_binding_power(original_binding_power), transformer))
             },
             |i: ParseInput| {
-                if 15 < i.binding_power {
+                // `show fib 15` calls `show` with `fib 15`, but an infix
+                // operator binding less tightly ends the expression: `f(a) +
+                // b` doesn't call `f(a)` with `+b`.
+                let (left_binding_power, right_binding_power) =
+                    Precedence::Call.binding_powers(Associativity::Right);
+                if left_binding_power < i.binding_power
+                    || find_infix_operator(&i.tokens, &i.operators).is_some()
+                {
                     return err_tag(i);
                 }
 
                 let original_binding_power = i.binding_power;
-                let (i, rhs) = parse_expression(i.with_binding_power(15))?;
+                let (i, rhs) = parse_expression(i.with_binding_power(right_binding_power))?;
 
                 let transformer: Transformer = Box::new(move |lhs| {
                     Expression::Operator(Operator::Postfix(PostfixOperator::FunctionCall(
@@ -8,206 +8,101 @@ pub struct OperatorBindingPowerPrefix {
     pub right_binding_power: u8,
 }
 
+pub type InfixExpressionConstructor =
+    Box<dyn FnOnce(Box<Expression>, Span, Box<Expression>) -> Expression>;
+
 pub struct OperatorBindingPowerInfix {
     pub operator: Token,
-    pub constructor: Box<dyn FnOnce(Box<Expression>, Span, Box<Expression>) -> InfixOperator>,
+    pub constructor: InfixExpressionConstructor,
     pub left_binding_power: u8,
     pub right_binding_power: u8,
 }
 
 pub struct OperatorBindingPowerPostfix {
-    pub constructor: Box<dyn FnOnce(Box<Expression>, Vec<Expression>) -> PostfixOperator>,
+    pub constructor: Box<dyn FnOnce(Box<Expression>, Vec<Expression>, Span) -> PostfixOperator>,
     pub left_binding_power: u8,
     pub tails: Box<dyn FnOnce(ParseInput) -> ParseResult<Vec<Expression>>>,
-    pub close: Box<dyn FnOnce(ParseInput) -> ParseResult<()>>,
+    /// Takes the closing punctuation and gives its span.
+    pub close: Box<dyn FnOnce(ParseInput) -> ParseResult<Span>>,
 }
 
 pub fn parse_prefix_operator(i: ParseInput) -> ParseResult<OperatorBindingPowerPrefix> {
-    alt((
-        map(tag(TokenKind::PunctuationExclamationMark), |_| {
-            OperatorBindingPowerPrefix {
-                constructor: Box::new(|expr| PrefixOperator::Not(Not(expr))),
-                right_binding_power: 13,
-            }
-        }),
-        map(tag(TokenKind::PunctuationPlusSign), |_| {
+    let (rest, token) = any(i.clone())?;
+    match PREFIX_OPERATORS
+        .iter()
+        .find(|(kind, _)| *kind == token.kind)
+    {
+        Some((_, constructor)) => Ok((
+            rest,
             OperatorBindingPowerPrefix {
-                constructor: Box::new(|expr| PrefixOperator::UnaryPlus(UnaryPlus(expr))),
-                right_binding_power: 13,
-            }
-        }),
-        map(tag(TokenKind::PunctuationHyphenMinus), |_| {
-            OperatorBindingPowerPrefix {
-                constructor: Box::new(|expr| PrefixOperator::UnaryMinus(UnaryMinus(expr))),
-                right_binding_power: 13,
-            }
-        }),
-    ))(i)
+                constructor: Box::new(move |operand| constructor(token.span, operand)),
+                right_binding_power: Precedence::Prefix.left_binding_power(),
+            },
+        )),
+        None => err_tag(i),
+    }
 }
 
 pub fn parse_infix_operator(i: ParseInput) -> ParseResult<OperatorBindingPowerInfix> {
-    alt((
-        map(tag(TokenKind::PunctuationsLogicalOr), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| InfixOperator::LogicalOr(lhs, span, rhs)),
-                left_binding_power: 1,
-                right_binding_power: 2,
-            }
-        }),
-        map(tag(TokenKind::PunctuationsLogicalAnd), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| InfixOperator::LogicalAnd(lhs, span, rhs)),
-                left_binding_power: 3,
-                right_binding_power: 4,
-            }
-        }),
-        map(tag(TokenKind::PunctuationsEqualTo), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| InfixOperator::EqualTo(lhs, span, rhs)),
-                left_binding_power: 5,
-                right_binding_power: 6,
-            }
-        }),
-        map(tag(TokenKind::PunctuationsNotEqualTo), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| InfixOperator::NotEqualTo(lhs, span, rhs)),
-                left_binding_power: 5,
-                right_binding_power: 6,
-            }
-        }),
-        map(tag(TokenKind::PunctuationGreaterThanSign), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| InfixOperator::GreaterThan(lhs, span, rhs)),
-                left_binding_power: 7,
-                right_binding_power: 8,
-            }
-        }),
-        map(tag(TokenKind::PunctuationLessThanSign), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| InfixOperator::LessThan(lhs, span, rhs)),
-                left_binding_power: 7,
-                right_binding_power: 8,
-            }
-        }),
-        map(
-            tag(TokenKind::PunctuationsGreaterThanOrEqualTo),
-            |operator| OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| {
-                    InfixOperator::GreaterThanOrEqualTo(lhs, span, rhs)
-                }),
-                left_binding_power: 7,
-                right_binding_power: 8,
-            },
-        ),
-        map(tag(TokenKind::PunctuationsLessThanOrEqualTo), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| {
-                    InfixOperator::LessThanOrEqualTo(lhs, span, rhs)
-                }),
-                left_binding_power: 7,
-                right_binding_power: 8,
-            }
-        }),
-        map(tag(TokenKind::PunctuationPlusSign), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| InfixOperator::Add(lhs, span, rhs)),
-                left_binding_power: 9,
-                right_binding_power: 10,
-            }
-        }),
-        map(tag(TokenKind::PunctuationHyphenMinus), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| InfixOperator::Subtract(lhs, span, rhs)),
-                left_binding_power: 9,
-                right_binding_power: 10,
-            }
-        }),
-        map(tag(TokenKind::PunctuationAsterisk), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| InfixOperator::Multiply(lhs, span, rhs)),
-                left_binding_power: 11,
-                right_binding_power: 12,
-            }
-        }),
-        map(tag(TokenKind::PunctuationSolidus), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| InfixOperator::Divide(lhs, span, rhs)),
-                left_binding_power: 11,
-                right_binding_power: 12,
-            }
-        }),
-        map(tag(TokenKind::PunctuationPercentSign), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, operator, rhs| {
-                    InfixOperator::Remainder(lhs, operator, rhs)
-                }),
-                left_binding_power: 11,
-                right_binding_power: 12,
-            }
-        }),
-        map(tag(TokenKind::PunctuationFullStop), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| InfixOperator::GetField(GetField(lhs, rhs))),
-                left_binding_power: 17,
-                right_binding_power: 16,
-            }
-        }),
-        map(tag(TokenKind::PunctuationsGetFieldNullable), |operator| {
-            OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| {
-                    InfixOperator::GetFieldNullable(GetFieldNullable(lhs, rhs))
-                }),
-                left_binding_power: 17,
-                right_binding_power: 16,
-            }
-        }),
-        map(
-            tag(TokenKind::PunctuationsRangeRightExclusive),
-            |operator| OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| {
-                    InfixOperator::RangeRightExclusive(lhs, span, rhs)
-                }),
-                left_binding_power: 19,
-                right_binding_power: 18,
-            },
-        ),
-        map(
-            tag(TokenKind::PunctuationsRangeRightInclusive),
-            |operator| OperatorBindingPowerInfix {
-                operator,
-                constructor: Box::new(|lhs, span, rhs| {
-                    InfixOperator::RangeRightInclusive(lhs, span, rhs)
-                }),
-                left_binding_power: 19,
-                right_binding_power: 18,
-            },
-        ),
-    ))(i)
+    let found = match find_infix_operator(&i.tokens, &i.operators) {
+        Some(found) => found,
+        None => return err_tag(i),
+    };
+    let tokens = &i.tokens[..found.len()];
+    let operator = Token {
+        kind: tokens[0].kind.clone(),
+        span: tokens[0].span.joined(&tokens[tokens.len() - 1].span),
+        content: tokens.iter().map(|token| token.content.as_str()).collect(),
+    };
+    let (left_binding_power, right_binding_power) = found.binding_powers();
+    let constructor: InfixExpressionConstructor = match found {
+        InfixOperatorMatch::BuiltIn(entry) => Box::new(move |lhs, span, rhs| {
+            Expression::Operator(Operator::Infix((entry.constructor)(lhs, span, rhs)))
+        }),
+        InfixOperatorMatch::Declared(declared) => {
+            // `a <+> b` calls the function of the operator with `a` and `b`,
+            // which is named where the operator is written.
+            let function = Token {
+                kind: TokenKind::IdentifierIdentifier(declared.function.clone()),
+                span: operator.span.clone(),
+                content: declared.function.clone(),
+            };
+            Box::new(move |lhs, _, rhs| {
+                Expression::Operator(Operator::Postfix(PostfixOperator::FunctionCall(
+                    FunctionCall(
+                        Box::new(Expression::Name(Name::Ident(function))),
+                        vec![*lhs, *rhs],
+                    ),
+                )))
+            })
+        }
+    };
+    Ok((
+        ParseInput {
+            tokens: i.tokens[tokens.len()..].to_vec(),
+            ..i
+        },
+        OperatorBindingPowerInfix {
+            operator,
+            constructor,
+            left_binding_power,
+            right_binding_power,
+        },
+    ))
 }
 
 pub fn parse_postfix_operator(i: ParseInput) -> ParseResult<OperatorBindingPowerPostfix> {
     map(tag(TokenKind::PunctuationLeftSquareBracket), |_| {
         OperatorBindingPowerPostfix {
-            constructor: Box::new(|expr, tails| PostfixOperator::Index(Index(expr, tails))),
-            left_binding_power: 14,
-            tails: Box::new(map(parse_expression, |expr| vec![expr])),
-            close: Box::new(map(tag(TokenKind::PunctuationRightSquareBracket), |_| ())),
+            constructor: Box::new(|expr, tails, close| {
+                PostfixOperator::Index(Index(expr, tails, close))
+            }),
+            left_binding_power: Precedence::Index.left_binding_power(),
+            tails: Box::new(enclosed(map(parse_expression, |expr| vec![expr]))),
+            close: Box::new(map(
+                tag(TokenKind::PunctuationRightSquareBracket),
+                |token| token.span,
+            )),
         }
     })(i)
 }
@@ -0,0 +1,422 @@
+use crate::{
+    core::ast::*,
+    syntax::{Span, Token, TokenKind},
+};
+
+/// How tightly operators bind, from the loosest to the tightest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    Assignment = 1,
+    Range,
+    LogicalOr,
+    LogicalAnd,
+    Comparison,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
+    Additive,
+    Multiplicative,
+    Prefix,
+    Index,
+    /// Calling a function by juxtaposition, like `show fib 15`.
+    Call,
+    Access,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+impl Precedence {
+    /// The binding powers of the left and the right side of an operator of
+    /// this precedence. An operator only takes a left operand parsed with a
+    /// binding power not greater than its left one, so the right operand of a
+    /// left associative operator stops before the next operator of the same
+    /// precedence, and the one of a right associative operator doesn't.
+    pub fn binding_powers(self, associativity: Associativity) -> (u8, u8) {
+        let base = self as u8 * 2;
+        match associativity {
+            Associativity::Left => (base, base + 1),
+            Associativity::Right => (base + 1, base),
+        }
+    }
+
+    pub fn left_binding_power(self) -> u8 {
+        self.binding_powers(Associativity::Left).0
+    }
+
+    /// The precedence of the binary operators called `name`, which is what a
+    /// declared operator refers to.
+    pub fn from_name(name: &str) -> Option<Precedence> {
+        Some(match name {
+            "Assignment" => Precedence::Assignment,
+            "Range" => Precedence::Range,
+            "LogicalOr" => Precedence::LogicalOr,
+            "LogicalAnd" => Precedence::LogicalAnd,
+            "Comparison" => Precedence::Comparison,
+            "BitOr" => Precedence::BitOr,
+            "BitXor" => Precedence::BitXor,
+            "BitAnd" => Precedence::BitAnd,
+            "Shift" => Precedence::Shift,
+            "Additive" => Precedence::Additive,
+            "Multiplicative" => Precedence::Multiplicative,
+            _ => return None,
+        })
+    }
+}
+
+impl Associativity {
+    pub fn from_name(name: &str) -> Option<Associativity> {
+        match name {
+            "left" => Some(Associativity::Left),
+            "right" => Some(Associativity::Right),
+            _ => None,
+        }
+    }
+}
+
+pub type InfixConstructor = fn(Box<Expression>, Span, Box<Expression>) -> InfixOperator;
+
+pub struct InfixOperatorEntry {
+    /// The tokens spelling the operator, which have to be written without
+    /// spaces between them, so `<` `<` is a shift but `< <` is not.
+    pub tokens: &'static [TokenKind],
+    pub precedence: Precedence,
+    pub associativity: Associativity,
+    pub constructor: InfixConstructor,
+}
+
+macro_rules! infix {
+    ([$($token:ident),+], $precedence:ident, $associativity:ident, $constructor:expr) => {
+        InfixOperatorEntry {
+            tokens: &[$(TokenKind::$token),+],
+            precedence: Precedence::$precedence,
+            associativity: Associativity::$associativity,
+            constructor: $constructor,
+        }
+    };
+}
+
+fn get_field(lhs: Box<Expression>, _: Span, rhs: Box<Expression>) -> InfixOperator {
+    InfixOperator::GetField(GetField(lhs, rhs))
+}
+
+fn get_field_nullable(lhs: Box<Expression>, _: Span, rhs: Box<Expression>) -> InfixOperator {
+    InfixOperator::GetFieldNullable(GetFieldNullable(lhs, rhs))
+}
+
+macro_rules! compound_assign {
+    ($name:ident, $operator:ident) => {
+        fn $name(lhs: Box<Expression>, span: Span, rhs: Box<Expression>) -> InfixOperator {
+            InfixOperator::CompoundAssign(Box::new(InfixOperator::$operator(lhs, span, rhs)))
+        }
+    };
+}
+
+compound_assign!(add_assign, Add);
+compound_assign!(subtract_assign, Subtract);
+compound_assign!(multiply_assign, Multiply);
+compound_assign!(divide_assign, Divide);
+compound_assign!(remainder_assign, Remainder);
+compound_assign!(bit_and_assign, BitAnd);
+compound_assign!(bit_or_assign, BitOr);
+compound_assign!(bit_xor_assign, BitXor);
+compound_assign!(shift_left_assign, ShiftLeft);
+compound_assign!(shift_right_assign, ShiftRight);
+
+/// Every infix operator. Adding one is adding an entry here; the parser
+/// tries the longest spellings first, so their order doesn't matter.
+pub const INFIX_OPERATORS: &[InfixOperatorEntry] = &[
+    infix!(
+        [PunctuationEqualsSign],
+        Assignment,
+        Right,
+        InfixOperator::Assign
+    ),
+    infix!(
+        [PunctuationPlusSign, PunctuationEqualsSign],
+        Assignment,
+        Right,
+        add_assign
+    ),
+    infix!(
+        [PunctuationHyphenMinus, PunctuationEqualsSign],
+        Assignment,
+        Right,
+        subtract_assign
+    ),
+    infix!(
+        [PunctuationAsterisk, PunctuationEqualsSign],
+        Assignment,
+        Right,
+        multiply_assign
+    ),
+    infix!(
+        [PunctuationSolidus, PunctuationEqualsSign],
+        Assignment,
+        Right,
+        divide_assign
+    ),
+    infix!(
+        [PunctuationPercentSign, PunctuationEqualsSign],
+        Assignment,
+        Right,
+        remainder_assign
+    ),
+    infix!(
+        [PunctuationAmpersand, PunctuationEqualsSign],
+        Assignment,
+        Right,
+        bit_and_assign
+    ),
+    infix!(
+        [PunctuationVerticalLine, PunctuationEqualsSign],
+        Assignment,
+        Right,
+        bit_or_assign
+    ),
+    infix!(
+        [PunctuationCircumflexAccent, PunctuationEqualsSign],
+        Assignment,
+        Right,
+        bit_xor_assign
+    ),
+    infix!(
+        [PunctuationLessThanSign, PunctuationsLessThanOrEqualTo],
+        Assignment,
+        Right,
+        shift_left_assign
+    ),
+    infix!(
+        [PunctuationGreaterThanSign, PunctuationsGreaterThanOrEqualTo],
+        Assignment,
+        Right,
+        shift_right_assign
+    ),
+    infix!(
+        [PunctuationsRangeRightExclusive],
+        Range,
+        Left,
+        InfixOperator::RangeRightExclusive
+    ),
+    infix!(
+        [PunctuationsRangeRightInclusive],
+        Range,
+        Left,
+        InfixOperator::RangeRightInclusive
+    ),
+    infix!(
+        [PunctuationsLogicalOr],
+        LogicalOr,
+        Left,
+        InfixOperator::LogicalOr
+    ),
+    infix!(
+        [PunctuationsLogicalAnd],
+        LogicalAnd,
+        Left,
+        InfixOperator::LogicalAnd
+    ),
+    infix!(
+        [PunctuationsEqualTo],
+        Comparison,
+        Left,
+        InfixOperator::EqualTo
+    ),
+    infix!(
+        [PunctuationsNotEqualTo],
+        Comparison,
+        Left,
+        InfixOperator::NotEqualTo
+    ),
+    infix!(
+        [PunctuationGreaterThanSign],
+        Comparison,
+        Left,
+        InfixOperator::GreaterThan
+    ),
+    infix!(
+        [PunctuationLessThanSign],
+        Comparison,
+        Left,
+        InfixOperator::LessThan
+    ),
+    infix!(
+        [PunctuationsGreaterThanOrEqualTo],
+        Comparison,
+        Left,
+        InfixOperator::GreaterThanOrEqualTo
+    ),
+    infix!(
+        [PunctuationsLessThanOrEqualTo],
+        Comparison,
+        Left,
+        InfixOperator::LessThanOrEqualTo
+    ),
+    infix!([PunctuationVerticalLine], BitOr, Left, InfixOperator::BitOr),
+    infix!(
+        [PunctuationCircumflexAccent],
+        BitXor,
+        Left,
+        InfixOperator::BitXor
+    ),
+    infix!([PunctuationAmpersand], BitAnd, Left, InfixOperator::BitAnd),
+    infix!(
+        [PunctuationLessThanSign, PunctuationLessThanSign],
+        Shift,
+        Left,
+        InfixOperator::ShiftLeft
+    ),
+    infix!(
+        [PunctuationGreaterThanSign, PunctuationGreaterThanSign],
+        Shift,
+        Left,
+        InfixOperator::ShiftRight
+    ),
+    infix!([PunctuationPlusSign], Additive, Left, InfixOperator::Add),
+    infix!(
+        [PunctuationHyphenMinus],
+        Additive,
+        Left,
+        InfixOperator::Subtract
+    ),
+    infix!(
+        [PunctuationAsterisk],
+        Multiplicative,
+        Left,
+        InfixOperator::Multiply
+    ),
+    infix!(
+        [PunctuationSolidus],
+        Multiplicative,
+        Left,
+        InfixOperator::Divide
+    ),
+    infix!(
+        [PunctuationPercentSign],
+        Multiplicative,
+        Left,
+        InfixOperator::Remainder
+    ),
+    infix!([PunctuationFullStop], Access, Right, get_field),
+    infix!(
+        [PunctuationsGetFieldNullable],
+        Access,
+        Right,
+        get_field_nullable
+    ),
+];
+
+/// An operator declared with `operator`, calling the function named
+/// `function` with both of its operands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeclaredOperator {
+    pub tokens: Vec<TokenKind>,
+    pub precedence: Precedence,
+    pub associativity: Associativity,
+    pub function: String,
+}
+
+impl DeclaredOperator {
+    /// The operator `declaration` declares, unless it names an unknown
+    /// precedence or associativity.
+    pub fn new(declaration: &OperatorDeclaration) -> Option<DeclaredOperator> {
+        Some(DeclaredOperator {
+            tokens: declaration
+                .symbol
+                .iter()
+                .map(|token| token.kind.clone())
+                .collect(),
+            precedence: Precedence::from_name(&declaration.precedence.content)?,
+            associativity: Associativity::from_name(&declaration.associativity.content)?,
+            function: declaration.function.content.clone(),
+        })
+    }
+}
+
+/// Whether `tokens` starts with the `spelling` of an operator, without any
+/// space between its tokens.
+pub fn spells(tokens: &[Token], spelling: &[TokenKind]) -> bool {
+    tokens.len() >= spelling.len()
+        && tokens
+            .iter()
+            .zip(spelling)
+            .all(|(token, kind)| token.kind == *kind)
+        && tokens[..spelling.len()]
+            .windows(2)
+            .all(|pair| pair[0].span.range.end == pair[1].span.range.start)
+}
+
+pub enum InfixOperatorMatch<'a> {
+    BuiltIn(&'static InfixOperatorEntry),
+    Declared(&'a DeclaredOperator),
+}
+
+impl InfixOperatorMatch<'_> {
+    /// The number of tokens spelling the operator.
+    pub fn len(&self) -> usize {
+        match self {
+            InfixOperatorMatch::BuiltIn(entry) => entry.tokens.len(),
+            InfixOperatorMatch::Declared(operator) => operator.tokens.len(),
+        }
+    }
+
+    pub fn binding_powers(&self) -> (u8, u8) {
+        match self {
+            InfixOperatorMatch::BuiltIn(entry) => {
+                entry.precedence.binding_powers(entry.associativity)
+            }
+            InfixOperatorMatch::Declared(operator) => {
+                operator.precedence.binding_powers(operator.associativity)
+            }
+        }
+    }
+}
+
+/// The infix operator `tokens` starts with, built in or `declared`,
+/// preferring the longest spelling. A declared operator spelled like a built
+/// in one never shadows it.
+pub fn find_infix_operator<'a>(
+    tokens: &[Token],
+    declared: &'a [DeclaredOperator],
+) -> Option<InfixOperatorMatch<'a>> {
+    let built_in = INFIX_OPERATORS
+        .iter()
+        .filter(|entry| spells(tokens, entry.tokens))
+        .max_by_key(|entry| entry.tokens.len())
+        .map(InfixOperatorMatch::BuiltIn);
+    let declared = declared
+        .iter()
+        .filter(|operator| spells(tokens, &operator.tokens))
+        .max_by_key(|operator| operator.tokens.len())
+        .map(InfixOperatorMatch::Declared);
+    match (built_in, declared) {
+        (Some(built_in), Some(declared)) if declared.len() > built_in.len() => Some(declared),
+        (Some(built_in), _) => Some(built_in),
+        (None, declared) => declared,
+    }
+}
+
+pub type PrefixConstructor = fn(Span, Box<Expression>) -> PrefixOperator;
+
+fn not(span: Span, operand: Box<Expression>) -> PrefixOperator {
+    PrefixOperator::Not(Not(span, operand))
+}
+
+fn unary_plus(span: Span, operand: Box<Expression>) -> PrefixOperator {
+    PrefixOperator::UnaryPlus(UnaryPlus(span, operand))
+}
+
+fn unary_minus(span: Span, operand: Box<Expression>) -> PrefixOperator {
+    PrefixOperator::UnaryMinus(UnaryMinus(span, operand))
+}
+
+/// Every prefix operator, all of them binding as `Precedence::Prefix`.
+pub const PREFIX_OPERATORS: &[(TokenKind, PrefixConstructor)] = &[
+    (TokenKind::PunctuationExclamationMark, not),
+    (TokenKind::PunctuationPlusSign, unary_plus),
+    (TokenKind::PunctuationHyphenMinus, unary_minus),
+];
@@ -0,0 +1,29 @@
+use crate::{core::ast::*, syntax::parse::*};
+
+fn parse_todo_message(i: ParseInput) -> ParseResult<Token> {
+    satisfy(|token| matches!(token.kind, TokenKind::LiteralString(_)))(i)
+}
+
+pub fn parse_todo_expression(i: ParseInput) -> ParseResult<Expression> {
+    let (i, keyword) = alt((
+        tag(TokenKind::KeywordTodo),
+        tag(TokenKind::KeywordUnimplemented),
+    ))(i)?;
+    let (i, message) = opt(alt((
+        delimited(
+            tag(TokenKind::PunctuationLeftParenthesis),
+            parse_todo_message,
+            tag(TokenKind::PunctuationRightParenthesis),
+        ),
+        parse_todo_message,
+    )))(i)?;
+    let id = i.next_id();
+    Ok((
+        i,
+        Expression::Todo(Todo {
+            keyword,
+            message,
+            id,
+        }),
+    ))
+}
@@ -0,0 +1,20 @@
+use crate::{core::ast::*, syntax::parse::*};
+
+pub fn parse_unsafe(i: ParseInput) -> ParseResult<Unsafe> {
+    let (i, unsafe_token) = tag(TokenKind::KeywordUnsafe)(i)?;
+    let (i, block) = cut(parse_block)(i)?;
+
+    let id = i.next_id();
+    Ok((
+        i,
+        Unsafe {
+            unsafe_token,
+            block: Box::new(block),
+            id,
+        },
+    ))
+}
+
+pub fn parse_unsafe_expression(i: ParseInput) -> ParseResult<Expression> {
+    map(parse_unsafe, Expression::Unsafe)(i)
+}
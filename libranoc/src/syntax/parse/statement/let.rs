@@ -0,0 +1,29 @@
+use crate::{core::ast::*, syntax::parse::*};
+
+pub fn parse_let(i: ParseInput) -> ParseResult<Let> {
+    let (i, let_token) = tag(TokenKind::KeywordLet)(i)?;
+    let (i, pattern) = cut(parse_pattern)(i)?;
+    let (i, ty) = opt(parse_type_annotation)(i)?;
+    let (i, value) = opt(preceded(
+        tag(TokenKind::PunctuationEqualsSign),
+        cut(parse_expression),
+    ))(i)?;
+    let (i, semicolon_token) = cut(tag(TokenKind::PunctuationSemicolon))(i)?;
+
+    let id = i.next_id();
+    Ok((
+        i,
+        Let {
+            let_token,
+            pattern,
+            ty,
+            value,
+            semicolon_token,
+            id,
+        },
+    ))
+}
+
+pub fn parse_let_statement(i: ParseInput) -> ParseResult<Statement> {
+    map(parse_let, Statement::Let)(i)
+}
@@ -1,18 +1,30 @@
 use crate::{
-    core::ast::{Node, Statement},
+    core::ast::{Directive, Node, Statement},
     syntax::parse::*,
 };
 
 mod declaration;
 mod expression;
+mod r#let;
 
 pub use declaration::*;
 pub use expression::*;
+pub use r#let::*;
 
 pub fn parse_statement(i: ParseInput) -> ParseResult<Statement> {
-    alt((parse_declaration_statement, parse_expression_statement))(i)
+    context(
+        "a statement",
+        alt((parse_declaration_statement, parse_expression_statement)),
+    )(i)
+}
+
+pub fn parse_directive(i: ParseInput) -> ParseResult<Directive> {
+    map(parse_inner_attribute, |attribute| Directive { attribute })(i)
 }
 
 pub fn parse_statement_node(i: ParseInput) -> ParseResult<Node> {
-    map(parse_statement, Node::Statement)(i)
+    alt((
+        map(parse_directive, Node::Directive),
+        map(parse_statement, Node::Statement),
+    ))(i)
 }
@@ -0,0 +1,100 @@
+use crate::{
+    core::{Error, FileId},
+    syntax::{ExpansionId, Span, TokenizeOptions},
+};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Locates `range` of `src` the same way the tokenizer does, for errors
+/// found before there are tokens to point at.
+fn span_at(src: &str, range: std::ops::Range<usize>) -> Span {
+    let before = &src[..range.start];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    Span {
+        line: before.matches('\n').count(),
+        column: TokenizeOptions::default().advance_column(0, &before[line_start..]),
+        len: range.len(),
+        range,
+        file: FileId::default(),
+        expansion: ExpansionId::ROOT,
+    }
+}
+
+/// A source file which isn't valid in its encoding.
+#[derive(Debug)]
+pub struct DecodeError {
+    pub error: Error,
+    /// The source with the invalid data replaced by `U+FFFD`, which the
+    /// span of the error covers, so it can be shown with the source.
+    pub lossy: String,
+}
+
+impl DecodeError {
+    /// The error for the invalid data at `byte_offset` of the file, which
+    /// `lossy` has the first `U+FFFD` of at `offset`.
+    fn new(encoding: &str, byte_offset: usize, lossy: String, offset: usize) -> Self {
+        let range = offset..(offset + char::REPLACEMENT_CHARACTER.len_utf8());
+        DecodeError {
+            error: Error::invalid_encoding(encoding, byte_offset, span_at(&lossy, range)),
+            lossy,
+        }
+    }
+}
+
+fn decode_utf16(bytes: &[u8], to_unit: fn([u8; 2]) -> u16) -> Result<String, DecodeError> {
+    let pairs = bytes.chunks_exact(2);
+    let leftover = !pairs.remainder().is_empty();
+    let units: Vec<u16> = pairs.map(|pair| to_unit([pair[0], pair[1]])).collect();
+    let mut src = String::with_capacity(units.len());
+    // where the first invalid unit is, in the bytes and in `src`.
+    let mut invalid = None;
+    let mut unit = 0;
+    for ch in std::char::decode_utf16(units.iter().copied()) {
+        let ch = ch.unwrap_or_else(|_| {
+            // counting the byte order mark in.
+            invalid.get_or_insert((2 + unit * 2, src.len()));
+            char::REPLACEMENT_CHARACTER
+        });
+        src.push(ch);
+        unit += ch.len_utf16();
+    }
+    if leftover {
+        invalid.get_or_insert((2 + bytes.len() - 1, src.len()));
+        src.push(char::REPLACEMENT_CHARACTER);
+    }
+    match invalid {
+        Some((byte_offset, offset)) => Err(DecodeError::new("UTF-16", byte_offset, src, offset)),
+        None => Ok(src),
+    }
+}
+
+/// Turns the raw bytes of a source file into the text the tokenizer reads.
+///
+/// A UTF-8 byte order mark is stripped, UTF-16 input with a byte order
+/// mark is transcoded, and anything else must be valid UTF-8; the error
+/// points at the first offending byte instead of leaving it to become an
+/// `Error` token.
+pub fn decode_source(bytes: &[u8]) -> Result<String, DecodeError> {
+    if let Some(bytes) = bytes.strip_prefix(UTF16LE_BOM) {
+        return decode_utf16(bytes, u16::from_le_bytes);
+    }
+    if let Some(bytes) = bytes.strip_prefix(UTF16BE_BOM) {
+        return decode_utf16(bytes, u16::from_be_bytes);
+    }
+    let (bom, bytes) = match bytes.strip_prefix(UTF8_BOM) {
+        Some(bytes) => (UTF8_BOM.len(), bytes),
+        None => (0, bytes),
+    };
+    match std::str::from_utf8(bytes) {
+        Ok(src) => Ok(src.to_string()),
+        Err(error) => {
+            // the valid prefix is kept as it is, so the first `U+FFFD` is
+            // right behind it.
+            let valid = error.valid_up_to();
+            let lossy = String::from_utf8_lossy(bytes).into_owned();
+            Err(DecodeError::new("UTF-8", bom + valid, lossy, valid))
+        }
+    }
+}
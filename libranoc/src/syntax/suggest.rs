@@ -0,0 +1,42 @@
+use super::tokenize::KEYWORDS;
+
+/// The number of single character insertions, deletions, substitutions and
+/// swaps of neighbours needed to turn `a` into `b`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    // three rows of the table, since a swap looks two characters back.
+    let mut before_previous = vec![0; b.len() + 1];
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before_previous[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before_previous, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// The keyword `name` was most likely meant to be, if it is a near miss.
+pub fn suggest_keyword(name: &str) -> Option<&'static str> {
+    // one typo in every three characters, and none in names too short to
+    // tell a typo from a different word.
+    let threshold = name.chars().count() / 3;
+    KEYWORDS
+        .iter()
+        .map(|(keyword, _)| *keyword)
+        .filter(|keyword| keyword.chars().all(char::is_alphabetic))
+        .map(|keyword| (edit_distance(name, keyword), keyword))
+        .filter(|(distance, _)| *distance > 0 && *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, keyword)| keyword)
+}
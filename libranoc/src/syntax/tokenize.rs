@@ -1,10 +1,64 @@
 use std::{
-    cmp::{max, min},
+    cmp::max,
+    collections::VecDeque,
     ops::{Range, RangeBounds},
 };
 
 use logos::Lexer;
 pub use logos::Logos;
+use serde::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthChar;
+
+use crate::core::FileId;
+
+/// How `Span::column` counts the characters in front of a token.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnMode {
+    /// Raw UTF-8 bytes, tabs included.
+    Bytes,
+    /// Unicode scalar values.
+    Chars,
+    /// Terminal cells, so wide characters count twice and combining marks
+    /// don't count at all.
+    Width,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenizeOptions {
+    pub column_mode: ColumnMode,
+    /// The distance between tab stops, ignored for `ColumnMode::Bytes`.
+    pub tab_width: usize,
+    /// Whether string literals embedding `{expression}`s are split into
+    /// fragments and the tokens of the expressions.
+    pub interpolation: bool,
+    /// The file of the source, which the spans of the tokens point into.
+    pub file: FileId,
+}
+
+impl TokenizeOptions {
+    /// Advances `column` over `text`, which must not contain line breaks.
+    pub fn advance_column(&self, column: usize, text: &str) -> usize {
+        if self.column_mode == ColumnMode::Bytes {
+            return column + text.len();
+        }
+        text.chars().fold(column, |column, ch| match ch {
+            '\t' if self.tab_width > 0 => column + self.tab_width - column % self.tab_width,
+            _ if self.column_mode == ColumnMode::Chars => column + 1,
+            _ => column + ch.width().unwrap_or(0),
+        })
+    }
+}
+
+impl Default for TokenizeOptions {
+    fn default() -> Self {
+        TokenizeOptions {
+            column_mode: ColumnMode::Width,
+            tab_width: 4,
+            interpolation: true,
+            file: FileId::default(),
+        }
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct TokenExtras {
@@ -12,12 +66,26 @@ pub struct TokenExtras {
     line: usize,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// Identifies the macro expansion which made a token, so the variables a
+/// macro introduces don't clash with the ones around where it is invoked.
+/// `ExpansionId::ROOT` is the source as it is written.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct ExpansionId(pub u32);
+
+impl ExpansionId {
+    pub const ROOT: ExpansionId = ExpansionId(0);
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Span {
     pub range: Range<usize>,
     pub line: usize,
     pub column: usize,
     pub len: usize,
+    pub file: FileId,
+    pub expansion: ExpansionId,
 }
 
 impl Span {
@@ -26,16 +94,66 @@ impl Span {
         line: 0,
         column: 0,
         len: 0,
+        file: FileId(0),
+        expansion: ExpansionId::ROOT,
     };
 
+    /// The span moved by `bytes` and `lines`, as text inserted or removed
+    /// in front of it on an earlier line does.
+    pub fn shifted(&self, bytes: isize, lines: isize) -> Span {
+        Span {
+            range: (self.range.start as isize + bytes) as usize
+                ..(self.range.end as isize + bytes) as usize,
+            line: (self.line as isize + lines) as usize,
+            ..self.clone()
+        }
+    }
+
+    /// The empty span right behind this one, where something missing after
+    /// it belongs.
+    pub fn end(&self) -> Span {
+        Span {
+            range: self.range.end..self.range.end,
+            column: self.column + self.len,
+            len: 0,
+            ..self.clone()
+        }
+    }
+
+    /// The empty span right in front of this one.
+    pub fn start(&self) -> Span {
+        Span {
+            range: self.range.start..self.range.start,
+            len: 0,
+            ..self.clone()
+        }
+    }
+
+    /// The span from the start of the earlier span to the end of the later
+    /// one, which starts on the line and the column of the earlier one.
+    /// `Span::EMPTY` joins into nothing. Both are expected to be in the same
+    /// file.
     pub fn joined(&self, other: &Span) -> Span {
-        let range = min(self.range.start, other.range.start)..max(self.range.end, other.range.end);
+        if *self == Span::EMPTY {
+            return other.clone();
+        }
+        if *other == Span::EMPTY {
+            return self.clone();
+        }
+        let first = if self.range.start <= other.range.start {
+            self
+        } else {
+            other
+        };
+        let range = first.range.start..max(self.range.end, other.range.end);
         let len = range.end - range.start;
         Span {
             range,
-            line: min(self.line, other.line),
-            column: min(self.column, other.column),
+            line: first.line,
+            column: first.column,
             len,
+            file: first.file,
+            expansion: first.expansion,
         }
     }
 }
@@ -44,7 +162,16 @@ pub trait Spanned {
     fn span(&self) -> Span;
 }
 
-impl<T> Spanned for &'_ T where T: Spanned {
+impl Spanned for Span {
+    fn span(&self) -> Span {
+        self.clone()
+    }
+}
+
+impl<T> Spanned for &'_ T
+where
+    T: Spanned,
+{
     fn span(&self) -> Span {
         <T as Spanned>::span(self)
     }
@@ -60,14 +187,20 @@ where
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
     pub content: String,
 }
 
-#[derive(Logos, Debug, PartialEq, Clone)]
+impl Spanned for Token {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+#[derive(Logos, Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[logos(extras = TokenExtras)]
 pub enum TokenKind {
     // #========== Punctuation ==========#
@@ -142,6 +275,8 @@ pub enum TokenKind {
     PunctuationsGreaterThanOrEqualTo,
     #[token("->")]
     PunctuationsSingleRightArrow,
+    #[token("=>")]
+    PunctuationsDoubleRightArrow,
     #[token("..")]
     PunctuationsRangeRightExclusive,
     #[token("..=")]
@@ -155,6 +290,8 @@ pub enum TokenKind {
     KeywordBreak,
     #[token("continue")]
     KeywordContinue,
+    #[token("dyn")]
+    KeywordDyn,
     #[token("else")]
     KeywordElse,
     #[token("extern")]
@@ -171,8 +308,16 @@ pub enum TokenKind {
     KeywordIn,
     #[token("let")]
     KeywordLet,
+    #[token("macro")]
+    KeywordMacro,
     #[token("match")]
     KeywordMatch,
+    #[token("mod")]
+    KeywordMod,
+    #[token("move")]
+    KeywordMove,
+    #[token("operator")]
+    KeywordOperator,
     #[token("pub")]
     KeywordPub,
     #[token("return")]
@@ -183,12 +328,18 @@ pub enum TokenKind {
     KeywordSelfType,
     #[token("struct")]
     KeywordStruct,
+    #[token("todo")]
+    KeywordTodo,
     #[token("trait")]
     KeywordTrait,
     #[token("type")]
     KeywordType,
+    #[token("unimplemented")]
+    KeywordUnimplemented,
     #[token("union")]
     KeywordUnion,
+    #[token("unsafe")]
+    KeywordUnsafe,
     #[token("use")]
     KeywordUse,
     #[token("where")]
@@ -218,6 +369,22 @@ pub enum TokenKind {
     LiteralNumberExponent(String),
     #[regex("(true|false)", |lex| lex.slice().to_owned())]
     LiteralBoolean(String),
+    // `"text {` , `} text {` and `} text"` around the embedded expressions of
+    // an interpolated string, split from a `LiteralString` after lexing.
+    LiteralStringHead(String),
+    LiteralStringMiddle(String),
+    LiteralStringTail(String),
+
+    // #========== Comment ==========#
+    /// `/// text`, documenting the item behind it. Every line is a token of
+    /// its own.
+    #[regex(
+        "///[^\n\u{000B}\u{000C}\r\u{0085}\u{2028}\u{2029}]*",
+        |lex| lex.slice().to_owned()
+    )]
+    DocComment(String),
+    #[regex("//[^\n\u{000B}\u{000C}\r\u{0085}\u{2028}\u{2029}]*", logos::skip)]
+    Comment,
 
     /*
      * '\n'       : LINE FEED
@@ -273,33 +440,319 @@ pub enum TokenKind {
     Error,
 }
 
+/// The textual form of every keyword, in the order of `TokenKind`.
+pub const KEYWORDS: &[(&str, TokenKind)] = &[
+    ("as", TokenKind::KeywordAs),
+    ("break", TokenKind::KeywordBreak),
+    ("continue", TokenKind::KeywordContinue),
+    ("dyn", TokenKind::KeywordDyn),
+    ("else", TokenKind::KeywordElse),
+    ("extern", TokenKind::KeywordExtern),
+    ("fn", TokenKind::KeywordFn),
+    ("for", TokenKind::KeywordFor),
+    ("if", TokenKind::KeywordIf),
+    ("impl", TokenKind::KeywordImpl),
+    ("in", TokenKind::KeywordIn),
+    ("let", TokenKind::KeywordLet),
+    ("macro", TokenKind::KeywordMacro),
+    ("match", TokenKind::KeywordMatch),
+    ("mod", TokenKind::KeywordMod),
+    ("move", TokenKind::KeywordMove),
+    ("operator", TokenKind::KeywordOperator),
+    ("pub", TokenKind::KeywordPub),
+    ("return", TokenKind::KeywordReturn),
+    ("self", TokenKind::KeywordSelf),
+    ("Self", TokenKind::KeywordSelfType),
+    ("struct", TokenKind::KeywordStruct),
+    ("todo", TokenKind::KeywordTodo),
+    ("trait", TokenKind::KeywordTrait),
+    ("type", TokenKind::KeywordType),
+    ("unimplemented", TokenKind::KeywordUnimplemented),
+    ("union", TokenKind::KeywordUnion),
+    ("unsafe", TokenKind::KeywordUnsafe),
+    ("use", TokenKind::KeywordUse),
+    ("where", TokenKind::KeywordWhere),
+    ("while", TokenKind::KeywordWhile),
+    ("_", TokenKind::KeywordPlaceholderName),
+];
+
+/// The textual form of every punctuation, in the order of `TokenKind`.
+pub const PUNCTUATIONS: &[(&str, TokenKind)] = &[
+    ("!", TokenKind::PunctuationExclamationMark),
+    ("#", TokenKind::PunctuationNumberSign),
+    ("$", TokenKind::PunctuationDollarSign),
+    ("%", TokenKind::PunctuationPercentSign),
+    ("&", TokenKind::PunctuationAmpersand),
+    ("*", TokenKind::PunctuationAsterisk),
+    ("+", TokenKind::PunctuationPlusSign),
+    (",", TokenKind::PunctuationComma),
+    ("-", TokenKind::PunctuationHyphenMinus),
+    (".", TokenKind::PunctuationFullStop),
+    ("/", TokenKind::PunctuationSolidus),
+    (":", TokenKind::PunctuationColon),
+    (";", TokenKind::PunctuationSemicolon),
+    ("<", TokenKind::PunctuationLessThanSign),
+    ("=", TokenKind::PunctuationEqualsSign),
+    (">", TokenKind::PunctuationGreaterThanSign),
+    ("?", TokenKind::PunctuationQuestionMark),
+    ("@", TokenKind::PunctuationCommercialAt),
+    ("\\", TokenKind::PunctuationReverseSolidus),
+    ("^", TokenKind::PunctuationCircumflexAccent),
+    ("|", TokenKind::PunctuationVerticalLine),
+    ("~", TokenKind::PunctuationTilde),
+    ("(", TokenKind::PunctuationLeftParenthesis),
+    ("[", TokenKind::PunctuationLeftSquareBracket),
+    ("{", TokenKind::PunctuationLeftCurlyBracket),
+    (")", TokenKind::PunctuationRightParenthesis),
+    ("]", TokenKind::PunctuationRightSquareBracket),
+    ("}", TokenKind::PunctuationRightCurlyBracket),
+    ("&&", TokenKind::PunctuationsLogicalAnd),
+    ("||", TokenKind::PunctuationsLogicalOr),
+    ("==", TokenKind::PunctuationsEqualTo),
+    ("!=", TokenKind::PunctuationsNotEqualTo),
+    ("<=", TokenKind::PunctuationsLessThanOrEqualTo),
+    (">=", TokenKind::PunctuationsGreaterThanOrEqualTo),
+    ("->", TokenKind::PunctuationsSingleRightArrow),
+    ("=>", TokenKind::PunctuationsDoubleRightArrow),
+    ("..", TokenKind::PunctuationsRangeRightExclusive),
+    ("..=", TokenKind::PunctuationsRangeRightInclusive),
+    ("?.", TokenKind::PunctuationsGetFieldNullable),
+];
+
+impl TokenKind {
+    /// How diagnostics refer to the token, like `` `;` `` or `an identifier`.
+    pub fn describe(&self) -> String {
+        if let Some((text, _)) = KEYWORDS
+            .iter()
+            .chain(PUNCTUATIONS)
+            .find(|(_, kind)| kind == self)
+        {
+            return format!("`{}`", text);
+        }
+        match self {
+            TokenKind::IdentifierIdentifier(_) => "an identifier",
+            TokenKind::LiteralCharacter(_) => "a character literal",
+            TokenKind::LiteralString(_)
+            | TokenKind::LiteralStringHead(_)
+            | TokenKind::LiteralStringMiddle(_)
+            | TokenKind::LiteralStringTail(_) => "a string literal",
+            TokenKind::LiteralNumberIntegral(_)
+            | TokenKind::LiteralNumberDecimal(_)
+            | TokenKind::LiteralNumberExponent(_) => "a number",
+            TokenKind::LiteralBoolean(_) => "a boolean",
+            TokenKind::DocComment(_) => "a doc comment",
+            _ => "an unknown token",
+        }
+        .to_string()
+    }
+}
+
 struct RanoLexer<'a> {
     logos_lexer: Lexer<'a, TokenKind>,
+    options: TokenizeOptions,
+    // the byte offset and the column measured the last time, so a line is
+    // only scanned once no matter how many tokens it has.
+    column_cursor: (usize, usize),
+    pending: VecDeque<Token>,
+}
+
+impl RanoLexer<'_> {
+    fn column(&mut self, start: usize) -> usize {
+        let line_start = self.logos_lexer.extras.last_linefeed;
+        let (mut offset, mut column) = self.column_cursor;
+        if offset < line_start || offset > start {
+            offset = line_start;
+            column = 0;
+        }
+        let column = self
+            .options
+            .advance_column(column, &self.logos_lexer.source()[offset..start]);
+        self.column_cursor = (start, column);
+        column
+    }
+
+    /// Splits an interpolated string literal into its fragments and the
+    /// tokens of its embedded expressions, or returns `None` if it embeds
+    /// nothing. `\{` escapes a brace, and so does leaving the braces empty,
+    /// so a literal like `"{}"` stays one.
+    fn split_interpolation(&self, literal: &Token) -> Option<Vec<Token>> {
+        let text = &literal.content;
+        let base = literal.span.range.start;
+        // the line and column of `offset` inside the literal.
+        let position = |offset: usize| {
+            let before = &text[..offset];
+            match before.rfind(['\n', '\r']) {
+                Some(line_start) => (
+                    literal.span.line + before.matches('\n').count(),
+                    self.options.advance_column(0, &before[line_start + 1..]),
+                ),
+                None => (
+                    literal.span.line,
+                    self.options.advance_column(literal.span.column, before),
+                ),
+            }
+        };
+        let token = |kind: TokenKind, range: Range<usize>| {
+            let (line, column) = position(range.start);
+            Token {
+                kind,
+                span: Span {
+                    range: (base + range.start)..(base + range.end),
+                    line,
+                    column,
+                    len: range.len(),
+                    file: literal.span.file,
+                    expansion: literal.span.expansion,
+                },
+                content: text[range].to_string(),
+            }
+        };
+
+        let mut tokens = Vec::new();
+        let mut fragment_start = 0;
+        let mut chars = text.char_indices().skip(1);
+        while let Some((index, ch)) = chars.next() {
+            match ch {
+                '\\' => {
+                    chars.next();
+                }
+                '{' => {
+                    let mut depth = 0;
+                    let close = text[index..].char_indices().find_map(|(offset, ch)| {
+                        match ch {
+                            '{' => depth += 1,
+                            '}' if depth == 1 => return Some(index + offset),
+                            '}' => depth -= 1,
+                            _ => {}
+                        }
+                        None
+                    })?;
+                    // `{}` embeds nothing, so it is kept as it is written.
+                    if text[(index + 1)..close].trim().is_empty() {
+                        continue;
+                    }
+                    let fragment = text[fragment_start..=index].to_string();
+                    tokens.push(token(
+                        if fragment_start == 0 {
+                            TokenKind::LiteralStringHead(fragment)
+                        } else {
+                            TokenKind::LiteralStringMiddle(fragment)
+                        },
+                        fragment_start..(index + 1),
+                    ));
+                    let expression = &text[(index + 1)..close];
+                    tokens.extend(create_tokenizer_with(expression, self.options.clone()).map(
+                        |inner| {
+                            let range = (index + 1 + inner.span.range.start)
+                                ..(index + 1 + inner.span.range.end);
+                            token(inner.kind, range)
+                        },
+                    ));
+                    fragment_start = close;
+                    for (index, _) in chars.by_ref() {
+                        if index >= close {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if tokens.is_empty() {
+            return None;
+        }
+        let fragment = text[fragment_start..].to_string();
+        tokens.push(token(
+            TokenKind::LiteralStringTail(fragment),
+            fragment_start..text.len(),
+        ));
+
+        Some(tokens)
+    }
 }
 
 impl<'a> Iterator for RanoLexer<'a> {
     type Item = Token;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.logos_lexer.next().map(|kind| Token {
+        if let Some(token) = self.pending.pop_front() {
+            return Some(token);
+        }
+        let kind = self.logos_lexer.next()?;
+        let range = self.logos_lexer.span();
+        let token = Token {
             kind,
             span: Span {
-                range: self.logos_lexer.span(),
                 line: self.logos_lexer.extras.line,
-                column: self.logos_lexer.span().end - self.logos_lexer.extras.last_linefeed,
-                len: self.logos_lexer.span().len(),
+                column: self.column(range.start),
+                len: range.len(),
+                range,
+                file: self.options.file,
+                expansion: ExpansionId::ROOT,
             },
             content: self.logos_lexer.slice().to_string(),
-        })
+        };
+        if self.options.interpolation && matches!(token.kind, TokenKind::LiteralString(_)) {
+            if let Some(tokens) = self.split_interpolation(&token) {
+                self.pending.extend(tokens);
+                return self.pending.pop_front();
+            }
+        }
+        Some(token)
     }
 }
 
+/// The length of the `#!/usr/bin/env rano` line the source starts with, if
+/// any. `#![...]` is an inner attribute rather than a shebang.
+fn shebang_len(src: &str) -> usize {
+    if !src.starts_with("#!") || src[2..].trim_start().starts_with('[') {
+        return 0;
+    }
+    src.find(['\n', '\r']).unwrap_or(src.len())
+}
+
 pub fn create_tokenizer<'a>(src: &'a str) -> impl Iterator<Item = Token> + 'a {
+    create_tokenizer_with(src, TokenizeOptions::default())
+}
+
+pub fn create_tokenizer_with<'a>(
+    src: &'a str,
+    options: TokenizeOptions,
+) -> impl Iterator<Item = Token> + 'a {
+    let mut logos_lexer = TokenKind::lexer(src);
+    logos_lexer.bump(shebang_len(src));
     RanoLexer {
-        logos_lexer: TokenKind::lexer(src),
+        logos_lexer,
+        options,
+        column_cursor: (0, 0),
+        pending: VecDeque::new(),
+    }
+}
+
+/// Continues tokenizing `src` from the start of `token`, which must have come
+/// from tokenizing the same text in front of it with the same options.
+pub(super) fn resume_tokenizer_with<'a>(
+    src: &'a str,
+    options: TokenizeOptions,
+    token: &Token,
+) -> impl Iterator<Item = Token> + 'a {
+    let start = token.span.range.start;
+    let mut logos_lexer = TokenKind::lexer(src);
+    logos_lexer.bump(start);
+    logos_lexer.extras.line = token.span.line;
+    logos_lexer.extras.last_linefeed = start;
+    RanoLexer {
+        logos_lexer,
+        options,
+        column_cursor: (start, token.span.column),
+        pending: VecDeque::new(),
     }
 }
 
 pub fn tokenize(src: &str) -> Vec<Token> {
     create_tokenizer(src).collect()
 }
+
+pub fn tokenize_with(src: &str, options: TokenizeOptions) -> Vec<Token> {
+    create_tokenizer_with(src, options).collect()
+}
@@ -0,0 +1,236 @@
+use crate::{
+    core::{NodeId, TypeId},
+    hir,
+    semantic::Resolution,
+    thir::*,
+    typeck::Typeck,
+};
+
+/// Builds the THIR of `module`, which `resolution` resolved and `typeck`
+/// typed without errors.
+pub fn build(module: hir::Module, resolution: &Resolution, typeck: &Typeck) -> Module {
+    Builder { resolution, typeck }.build_module(module)
+}
+
+struct Builder<'a> {
+    resolution: &'a Resolution,
+    typeck: &'a Typeck,
+}
+
+impl Builder<'_> {
+    fn type_of(&self, id: NodeId) -> TypeId {
+        self.typeck.type_of(id).unwrap_or(TypeId::I32)
+    }
+
+    fn build_module(&self, module: hir::Module) -> Module {
+        Module {
+            directives: module.directives,
+            statements: self.build_statements(module.statements),
+        }
+    }
+
+    fn build_statements(&self, statements: Vec<hir::Statement>) -> Vec<Statement> {
+        statements
+            .into_iter()
+            .map(|statement| self.build_statement(statement))
+            .collect()
+    }
+
+    fn build_statement(&self, statement: hir::Statement) -> Statement {
+        match statement {
+            hir::Statement::Item(item) => Statement::Item(self.build_item(item)),
+            hir::Statement::Let(r#let) => Statement::Let(Let {
+                pattern: r#let.pattern,
+                ty: self
+                    .resolution
+                    .lets
+                    .get(r#let.id)
+                    .and_then(|local| self.typeck.definitions.get(local))
+                    .copied()
+                    .unwrap_or(TypeId::I32),
+                value: r#let.value.map(|value| self.build_expression(value)),
+                span: r#let.span,
+                id: r#let.id,
+            }),
+            hir::Statement::Expression(expression) => {
+                Statement::Expression(self.build_expression(expression))
+            }
+        }
+    }
+
+    fn build_item(&self, item: hir::Item) -> Item {
+        match item {
+            hir::Item::Function(function) => {
+                let (parameters, return_type) = match self.typeck.signature_of(function.id) {
+                    Some(signature) => signature,
+                    None => (vec![TypeId::I32; function.parameters.len()], TypeId::UNIT),
+                };
+                Item::Function(Function {
+                    attributes: function.attributes,
+                    is_pub: function.is_pub,
+                    is_extern: function.is_extern,
+                    name: function.name,
+                    generic_parameters: function.generic_parameters,
+                    parameters: function
+                        .parameters
+                        .into_iter()
+                        .map(|(pattern, _)| pattern)
+                        .zip(parameters)
+                        .collect(),
+                    return_type,
+                    body: function.body.map(|body| self.build_block(body)),
+                    span: function.span,
+                    id: function.id,
+                })
+            }
+            hir::Item::Use(declaration) => Item::Use(declaration),
+            hir::Item::Module(declaration) => Item::Module(ModuleDeclaration {
+                is_pub: declaration.is_pub,
+                name: declaration.name,
+                body: declaration.body.map(|body| self.build_module(body)),
+                span: declaration.span,
+                id: declaration.id,
+            }),
+        }
+    }
+
+    fn build_block(&self, block: hir::Block) -> Block {
+        Block {
+            ty: self.type_of(block.id),
+            statements: self.build_statements(block.statements),
+            value: block.value.map(|value| self.build_boxed(*value)),
+            span: block.span,
+            id: block.id,
+        }
+    }
+
+    fn build_boxed(&self, expression: hir::Expression) -> Box<Expression> {
+        Box::new(self.build_expression(expression))
+    }
+
+    fn build_expressions(&self, expressions: Vec<hir::Expression>) -> Vec<Expression> {
+        expressions
+            .into_iter()
+            .map(|expression| self.build_expression(expression))
+            .collect()
+    }
+
+    fn build_expression(&self, expression: hir::Expression) -> Expression {
+        use hir::ExpressionKind as Hir;
+        let kind = match expression.kind {
+            // nothing is instantiated yet, so the operand is what codegen
+            // is left with: the generic function it names is reported there.
+            Hir::Instantiate(instantiate) => return self.build_expression(*instantiate.operand),
+            Hir::Literal(literal) => ExpressionKind::Literal(literal),
+            Hir::Name(name) => ExpressionKind::Name(name),
+            Hir::Path(path) => ExpressionKind::Path(path),
+            Hir::Array(elements) => ExpressionKind::Array(self.build_expressions(elements)),
+            Hir::Tuple(elements) => ExpressionKind::Tuple(self.build_expressions(elements)),
+            Hir::Init(init) => ExpressionKind::Init(Init {
+                name: init.name,
+                fields: init
+                    .fields
+                    .into_iter()
+                    .map(|(name, value)| (name, self.build_expression(value)))
+                    .collect(),
+            }),
+            Hir::Unary(unary) => ExpressionKind::Unary(Unary {
+                operator: unary.operator,
+                operator_span: unary.operator_span,
+                operand: self.build_boxed(*unary.operand),
+            }),
+            Hir::Binary(binary) => ExpressionKind::Binary(Binary {
+                operator: binary.operator,
+                operator_span: binary.operator_span,
+                lhs: self.build_boxed(*binary.lhs),
+                rhs: self.build_boxed(*binary.rhs),
+            }),
+            Hir::Assign(assign) => ExpressionKind::Assign(Assign {
+                target: self.build_boxed(*assign.target),
+                value: self.build_boxed(*assign.value),
+            }),
+            Hir::Field(field) => ExpressionKind::Field(Field {
+                operand: self.build_boxed(*field.operand),
+                field: self.build_boxed(*field.field),
+            }),
+            Hir::Call(call) => ExpressionKind::Call(Call {
+                callee: self.build_boxed(*call.callee),
+                arguments: self.build_expressions(call.arguments),
+                span: call.span,
+            }),
+            Hir::Index(index) => ExpressionKind::Index(Index {
+                operand: self.build_boxed(*index.operand),
+                indices: self.build_expressions(index.indices),
+            }),
+            Hir::If(r#if) => ExpressionKind::If(If {
+                condition: self.build_boxed(*r#if.condition),
+                body: self.build_block(r#if.body),
+                else_block: r#if.else_block.map(|block| self.build_block(block)),
+            }),
+            Hir::Match(r#match) => ExpressionKind::Match(Match {
+                scrutinee: self.build_boxed(*r#match.scrutinee),
+                arms: r#match
+                    .arms
+                    .into_iter()
+                    .map(|arm| MatchArm {
+                        pattern: arm.pattern,
+                        guard: arm.guard.map(|guard| self.build_expression(guard)),
+                        body: self.build_expression(arm.body),
+                    })
+                    .collect(),
+            }),
+            Hir::Closure(closure) => ExpressionKind::Closure(Closure {
+                captures: self
+                    .typeck
+                    .captures
+                    .get(expression.id)
+                    .cloned()
+                    .unwrap_or_default(),
+                parameters: closure
+                    .parameters
+                    .into_iter()
+                    .map(|(pattern, _)| pattern)
+                    .collect(),
+                body: self.build_boxed(*closure.body),
+            }),
+            Hir::Block(block) => ExpressionKind::Block(self.build_block(block)),
+            Hir::Unsafe(r#unsafe) => ExpressionKind::Unsafe(Unsafe {
+                block: self.build_block(r#unsafe.block),
+            }),
+            Hir::Null => ExpressionKind::Null,
+            Hir::Todo(todo) => ExpressionKind::Todo(todo),
+            Hir::Intrinsic(intrinsic) => {
+                let (kind, type_arguments) = self
+                    .typeck
+                    .intrinsics
+                    .get(expression.id)
+                    .cloned()
+                    .expect("the checker reports every intrinsic it can't tell");
+                ExpressionKind::Intrinsic(Intrinsic {
+                    kind,
+                    name: intrinsic.name,
+                    type_arguments,
+                })
+            }
+        };
+        let ty = self.type_of(expression.id);
+        let kind = match self.typeck.coercions.get(expression.id) {
+            Some(coercion) => ExpressionKind::Coerce(Coerce {
+                coercion: coercion.clone(),
+                operand: Box::new(Expression {
+                    kind,
+                    ty: coercion.source(&self.typeck.types, ty),
+                    span: expression.span.clone(),
+                    id: expression.id,
+                }),
+            }),
+            None => kind,
+        };
+        Expression {
+            kind,
+            ty,
+            span: expression.span,
+            id: expression.id,
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! The typed IR codegen works on, which is the HIR once the checker typed
+//! it. Every expression and block has the `TypeId` of the value it
+//! produces, and every function and local the types the checker gave them,
+//! so codegen never has to guess which WASM value type something is.
+//!
+//! A value the checker coerced is wrapped in a `Coerce` with the type it is
+//! converted to, around the expression with the type it had.
+//!
+//! The checker gives no type to the calls of the builtins, which codegen
+//! lowers by itself, and all of which produce an `i32`, so that is their
+//! type here.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{
+        ast::{
+            Attribute, Directive, GenericParameter, Literal, Name, Path, Pattern, Todo,
+            UseDeclaration,
+        },
+        IntrinsicKind, NodeId, TypeId,
+    },
+    syntax::{Span, Spanned, Token},
+    typeck::{captures::Capture, coercion::Coercion},
+};
+
+mod build;
+
+pub use crate::hir::{BinaryOperator, UnaryOperator};
+pub use build::*;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Module {
+    pub directives: Vec<Directive>,
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Statement {
+    Item(Item),
+    Let(Let),
+    Expression(Expression),
+}
+
+impl Spanned for Statement {
+    fn span(&self) -> Span {
+        match self {
+            Statement::Item(item) => item.span(),
+            Statement::Let(r#let) => r#let.span(),
+            Statement::Expression(expression) => expression.span(),
+        }
+    }
+}
+
+/// `let pattern = value;`, with the type of the local it binds.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Let {
+    pub pattern: Pattern,
+    pub ty: TypeId,
+    pub value: Option<Expression>,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for Let {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Item {
+    Function(Function),
+    Use(UseDeclaration),
+    Module(ModuleDeclaration),
+}
+
+impl Spanned for Item {
+    fn span(&self) -> Span {
+        match self {
+            Item::Function(function) => function.span(),
+            Item::Use(declaration) => declaration.span(),
+            Item::Module(declaration) => declaration.span(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Function {
+    pub attributes: Vec<Attribute>,
+    pub is_pub: bool,
+    pub is_extern: bool,
+    pub name: Token,
+    pub generic_parameters: Vec<GenericParameter>,
+    pub parameters: Vec<(Pattern, TypeId)>,
+    pub return_type: TypeId,
+    pub body: Option<Block>,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for Function {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+impl Function {
+    /// The first attribute called `name`.
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name() == Some(name))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ModuleDeclaration {
+    pub is_pub: bool,
+    pub name: Token,
+    pub body: Option<Module>,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for ModuleDeclaration {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Block {
+    pub statements: Vec<Statement>,
+    pub value: Option<Box<Expression>>,
+    /// `!` if the block never finishes.
+    pub ty: TypeId,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for Block {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+/// An expression with the ids of the HIR, which the resolution of its names
+/// is kept by.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub ty: TypeId,
+    pub span: Span,
+    pub id: NodeId,
+}
+
+impl Spanned for Expression {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+impl Expression {
+    /// The blocks the expression is made of directly.
+    pub fn blocks(&self) -> Vec<&Block> {
+        match &self.kind {
+            ExpressionKind::If(r#if) => std::iter::once(&r#if.body)
+                .chain(&r#if.else_block)
+                .collect(),
+            ExpressionKind::Block(block) => vec![block],
+            ExpressionKind::Unsafe(r#unsafe) => vec![&r#unsafe.block],
+            _ => Vec::new(),
+        }
+    }
+
+    /// The expressions the expression is made of directly, besides the
+    /// ones in its blocks.
+    pub fn operands(&self) -> Vec<&Expression> {
+        match &self.kind {
+            ExpressionKind::Array(elements) | ExpressionKind::Tuple(elements) => {
+                elements.iter().collect()
+            }
+            ExpressionKind::Init(init) => init.fields.iter().map(|(_, value)| value).collect(),
+            ExpressionKind::Unary(unary) => vec![&unary.operand],
+            ExpressionKind::Binary(binary) => vec![&binary.lhs, &binary.rhs],
+            ExpressionKind::Assign(assign) => vec![&assign.target, &assign.value],
+            ExpressionKind::Field(field) => vec![&field.operand, &field.field],
+            ExpressionKind::Call(call) => std::iter::once(&*call.callee)
+                .chain(&call.arguments)
+                .collect(),
+            ExpressionKind::Index(index) => std::iter::once(&*index.operand)
+                .chain(&index.indices)
+                .collect(),
+            ExpressionKind::If(r#if) => vec![&r#if.condition],
+            ExpressionKind::Match(r#match) => std::iter::once(&*r#match.scrutinee)
+                .chain(
+                    r#match
+                        .arms
+                        .iter()
+                        .flat_map(|arm| arm.guard.iter().chain(std::iter::once(&arm.body))),
+                )
+                .collect(),
+            ExpressionKind::Closure(closure) => vec![&closure.body],
+            ExpressionKind::Coerce(coerce) => vec![&coerce.operand],
+            ExpressionKind::Literal(_)
+            | ExpressionKind::Name(_)
+            | ExpressionKind::Path(_)
+            | ExpressionKind::Block(_)
+            | ExpressionKind::Unsafe(_)
+            | ExpressionKind::Null
+            | ExpressionKind::Todo(_)
+            | ExpressionKind::Intrinsic(_) => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum ExpressionKind {
+    Literal(Literal),
+    Name(Name),
+    Path(Path),
+    Array(Vec<Expression>),
+    Tuple(Vec<Expression>),
+    Init(Init),
+    Unary(Unary),
+    Binary(Binary),
+    Assign(Assign),
+    Field(Field),
+    Call(Call),
+    Index(Index),
+    If(If),
+    Match(Match),
+    Closure(Closure),
+    Block(Block),
+    Unsafe(Unsafe),
+    Null,
+    Todo(Todo),
+    Intrinsic(Intrinsic),
+    Coerce(Coerce),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Init {
+    pub name: Token,
+    pub fields: Vec<(Token, Expression)>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Unary {
+    pub operator: UnaryOperator,
+    pub operator_span: Span,
+    pub operand: Box<Expression>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Binary {
+    pub operator: BinaryOperator,
+    pub operator_span: Span,
+    pub lhs: Box<Expression>,
+    pub rhs: Box<Expression>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Assign {
+    pub target: Box<Expression>,
+    pub value: Box<Expression>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Field {
+    pub operand: Box<Expression>,
+    pub field: Box<Expression>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Call {
+    pub callee: Box<Expression>,
+    pub arguments: Vec<Expression>,
+    pub span: Span,
+}
+
+impl Spanned for Call {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Index {
+    pub operand: Box<Expression>,
+    pub indices: Vec<Expression>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct If {
+    pub condition: Box<Expression>,
+    pub body: Block,
+    pub else_block: Option<Block>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Match {
+    pub scrutinee: Box<Expression>,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: Expression,
+}
+
+/// A closure, the parameter and return types of which are in the type of
+/// the expression, with the locals it captures.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Closure {
+    pub captures: Vec<Capture>,
+    pub parameters: Vec<Pattern>,
+    pub body: Box<Expression>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Unsafe {
+    pub block: Block,
+}
+
+/// A called `@name`, with the types it is given.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Intrinsic {
+    pub kind: IntrinsicKind,
+    pub name: Token,
+    pub type_arguments: Vec<TypeId>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Coerce {
+    pub coercion: Coercion,
+    pub operand: Box<Expression>,
+}
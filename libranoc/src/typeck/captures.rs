@@ -0,0 +1,435 @@
+//! The locals each closure captures from the function or the closure it is
+//! made in, and how it holds them.
+//!
+//! A closure takes a local by value when it is `move`, when its body moves
+//! the value out of the local, or when the value is copied anyway, and by
+//! reference otherwise: to see what is assigned to the local after the
+//! closure is made, and to assign to it itself. A closure captures what
+//! the closures in its body capture from outside of it, by reference if
+//! they do.
+//!
+//! A local captured by reference stays where it is, so the closure can't
+//! be used once the function or the closure it is in returns. Giving such
+//! a closure back from there, or a tuple or an array holding it, is
+//! reported, pointing to `move`.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{arena::Idx, ast::Name, Error, NodeMap, Type, TypeId},
+    hir::{Block, Expression, ExpressionKind, Item, Module, Statement},
+    semantic::{Definition, DefinitionKind, Resolution},
+    syntax::{Span, Spanned},
+    typeck::Typeck,
+};
+
+/// How a closure holds a local it captures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaptureMode {
+    /// A copy of the value, or the value moved into the closure.
+    Value,
+    /// The local itself, which the closure reads and assigns where it is.
+    Reference,
+}
+
+/// A local of the function or closure a closure is made in, which the
+/// closure uses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Capture {
+    pub local: Idx<Definition>,
+    pub mode: CaptureMode,
+    /// The type of the local, which codegen lays the closure out by.
+    pub ty: TypeId,
+    /// The first use of the local in the closure.
+    pub span: Span,
+}
+
+/// How the body of a closure uses a local.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Use {
+    Read,
+    Move,
+    /// Assigns to it, or captures it by reference, which both need the
+    /// local itself.
+    Borrow,
+}
+
+/// The captures of every closure by the id of its expression, and the
+/// closures given back while capturing a local by reference which doesn't
+/// live as long.
+pub(super) fn find_captures(
+    module: &Module,
+    resolution: &Resolution,
+    typeck: &Typeck,
+) -> (NodeMap<Vec<Capture>>, Vec<Error>) {
+    let mut finder = CaptureFinder {
+        resolution,
+        typeck,
+        captures: NodeMap::new(),
+        errors: Vec::new(),
+    };
+    finder.find_in_statements(&module.statements, &mut Vec::new());
+    (finder.captures, finder.errors)
+}
+
+struct CaptureFinder<'a> {
+    resolution: &'a Resolution,
+    typeck: &'a Typeck,
+    captures: NodeMap<Vec<Capture>>,
+    errors: Vec<Error>,
+}
+
+impl CaptureFinder<'_> {
+    fn find_in_statements(
+        &mut self,
+        statements: &[Statement],
+        uses: &mut Vec<(Idx<Definition>, Use, Span)>,
+    ) {
+        for statement in statements {
+            match statement {
+                Statement::Item(item) => self.find_in_item(item),
+                Statement::Let(r#let) => {
+                    if let Some(value) = &r#let.value {
+                        self.find(value, true, uses);
+                    }
+                }
+                Statement::Expression(expression) => self.find(expression, true, uses),
+            }
+        }
+    }
+
+    /// An item captures nothing, but the closures in it do.
+    fn find_in_item(&mut self, item: &Item) {
+        match item {
+            Item::Function(function) => {
+                if let Some(body) = &function.body {
+                    self.find_in_block(body, &mut Vec::new());
+                    self.check_escapes(body, None);
+                }
+            }
+            Item::Module(declaration) => {
+                if let Some(module) = &declaration.body {
+                    self.find_in_statements(&module.statements, &mut Vec::new());
+                }
+            }
+            Item::Use(_) => {}
+        }
+    }
+
+    fn find_in_block(&mut self, block: &Block, uses: &mut Vec<(Idx<Definition>, Use, Span)>) {
+        self.find_in_statements(&block.statements, uses);
+        if let Some(value) = &block.value {
+            self.find(value, true, uses);
+        }
+    }
+
+    /// The parameter or local `expression` names.
+    fn local(&self, expression: &Expression) -> Option<Idx<Definition>> {
+        if !matches!(expression.kind, ExpressionKind::Name(Name::Ident(_))) {
+            return None;
+        }
+        let definition = *self.resolution.references.get(expression.id)?;
+        match self.resolution.definitions[definition].kind {
+            DefinitionKind::Local | DefinitionKind::Parameter { .. } => Some(definition),
+            _ => None,
+        }
+    }
+
+    fn is_copy(&self, local: Idx<Definition>) -> bool {
+        self.typeck
+            .definitions
+            .get(&local)
+            .is_none_or(|ty| self.typeck.types.is_copy(*ty))
+    }
+
+    /// The type of `local`, which the checker gave it, or its function for a
+    /// parameter.
+    fn type_of(&self, local: Idx<Definition>) -> TypeId {
+        let ty = match self.resolution.definitions[local].kind {
+            DefinitionKind::Parameter { function, index } => self
+                .typeck
+                .items
+                .get(function)
+                .and_then(|ty| match self.typeck.types.get(*ty) {
+                    Type::Function { parameters, .. } => parameters.get(index as usize).copied(),
+                    _ => None,
+                }),
+            _ => self.typeck.definitions.get(&local).copied(),
+        };
+        ty.unwrap_or(TypeId::I32)
+    }
+
+    /// Whether `local` is defined in the closure spanning `closure`, which
+    /// is the span of its scope.
+    fn is_inside(&self, local: Idx<Definition>, closure: &Span) -> bool {
+        let mut scope = Some(self.resolution.definitions[local].scope);
+        while let Some(id) = scope {
+            if self.resolution.scopes[id].span == *closure {
+                return true;
+            }
+            scope = self.resolution.scopes[id].parent;
+        }
+        false
+    }
+
+    /// Records the uses of the locals in `expression` in `uses`, with the
+    /// value moved out of the local if `moves`, like the moves are checked.
+    fn find(
+        &mut self,
+        expression: &Expression,
+        moves: bool,
+        uses: &mut Vec<(Idx<Definition>, Use, Span)>,
+    ) {
+        match &expression.kind {
+            ExpressionKind::Name(_) => {
+                if let Some(local) = self.local(expression) {
+                    let r#use = if moves && !self.is_copy(local) {
+                        Use::Move
+                    } else {
+                        Use::Read
+                    };
+                    uses.push((local, r#use, expression.span()));
+                }
+            }
+            ExpressionKind::Assign(assign) => {
+                self.find(&assign.value, true, uses);
+                match self.local(&assign.target) {
+                    Some(local) => uses.push((local, Use::Borrow, assign.target.span())),
+                    None => self.find(&assign.target, false, uses),
+                }
+            }
+            ExpressionKind::If(r#if) => {
+                self.find(&r#if.condition, false, uses);
+                self.find_in_block(&r#if.body, uses);
+                if let Some(else_block) = &r#if.else_block {
+                    self.find_in_block(else_block, uses);
+                }
+            }
+            ExpressionKind::Match(r#match) => {
+                self.find(&r#match.scrutinee, false, uses);
+                for arm in &r#match.arms {
+                    if let Some(guard) = &arm.guard {
+                        self.find(guard, false, uses);
+                    }
+                    self.find(&arm.body, moves, uses);
+                }
+            }
+            ExpressionKind::Binary(binary) => {
+                self.find(&binary.lhs, false, uses);
+                self.find(&binary.rhs, false, uses);
+            }
+            ExpressionKind::Closure(closure) => {
+                let mut inner = Vec::new();
+                self.find(&closure.body, true, &mut inner);
+                let captures = self.decide(&expression.span, closure.is_move, inner);
+                for capture in &captures {
+                    let r#use = match capture.mode {
+                        CaptureMode::Reference => Use::Borrow,
+                        CaptureMode::Value if self.is_copy(capture.local) => Use::Read,
+                        CaptureMode::Value => Use::Move,
+                    };
+                    uses.push((capture.local, r#use, capture.span.clone()));
+                }
+                self.check_escapes_of(closure.body.as_ref(), &expression.span);
+                self.captures.insert(expression.id, captures);
+            }
+            ExpressionKind::Block(block) => self.find_in_block(block, uses),
+            ExpressionKind::Unsafe(r#unsafe) => self.find_in_block(&r#unsafe.block, uses),
+            ExpressionKind::Array(elements) | ExpressionKind::Tuple(elements) => {
+                for element in elements {
+                    self.find(element, true, uses);
+                }
+            }
+            ExpressionKind::Init(init) => {
+                for (_, value) in &init.fields {
+                    self.find(value, true, uses);
+                }
+            }
+            ExpressionKind::Unary(unary) => self.find(&unary.operand, false, uses),
+            ExpressionKind::Field(field) => self.find(&field.operand, false, uses),
+            ExpressionKind::Instantiate(instantiate) => {
+                self.find(&instantiate.operand, false, uses)
+            }
+            ExpressionKind::Call(call) => {
+                self.find(&call.callee, false, uses);
+                let moves = !matches!(call.callee.kind, ExpressionKind::Intrinsic(_));
+                for argument in &call.arguments {
+                    self.find(argument, moves, uses);
+                }
+            }
+            ExpressionKind::Index(index) => {
+                self.find(&index.operand, false, uses);
+                for index in &index.indices {
+                    self.find(index, false, uses);
+                }
+            }
+            ExpressionKind::Literal(_)
+            | ExpressionKind::Path(_)
+            | ExpressionKind::Null
+            | ExpressionKind::Todo(_)
+            | ExpressionKind::Intrinsic(_) => {}
+        }
+    }
+
+    /// The captures of the closure spanning `closure` out of the `uses` of
+    /// its body, in the order they are first used.
+    fn decide(
+        &self,
+        closure: &Span,
+        is_move: bool,
+        uses: Vec<(Idx<Definition>, Use, Span)>,
+    ) -> Vec<Capture> {
+        let mut captured: Vec<(Idx<Definition>, Use, Span)> = Vec::new();
+        for (local, r#use, span) in uses {
+            if self.is_inside(local, closure) {
+                continue;
+            }
+            match captured.iter_mut().find(|(other, _, _)| *other == local) {
+                Some((_, strongest, _)) => *strongest = (*strongest).max(r#use),
+                None => captured.push((local, r#use, span)),
+            }
+        }
+        captured
+            .into_iter()
+            .map(|(local, r#use, span)| {
+                let by_value =
+                    is_move || r#use == Use::Move || r#use == Use::Read && self.is_copy(local);
+                Capture {
+                    local,
+                    mode: if by_value {
+                        CaptureMode::Value
+                    } else {
+                        CaptureMode::Reference
+                    },
+                    ty: self.type_of(local),
+                    span,
+                }
+            })
+            .collect()
+    }
+
+    fn check_escapes_of(&mut self, body: &Expression, closure: &Span) {
+        if let ExpressionKind::Block(block) = &body.kind {
+            self.check_escapes(block, Some(closure));
+        } else {
+            let lets = HashMap::new();
+            self.report_escapes(body, &lets, Some(closure));
+        }
+    }
+
+    /// Reports the closures `block` gives back which capture one of its
+    /// locals by reference: any of a function, or the ones defined in the
+    /// closure spanning `closure`.
+    fn check_escapes(&mut self, block: &Block, closure: Option<&Span>) {
+        let mut lets = HashMap::new();
+        collect_lets(block, self.resolution, &mut lets);
+        if let Some(value) = &block.value {
+            self.report_escapes(value, &lets, closure);
+        }
+    }
+
+    fn report_escapes(
+        &mut self,
+        value: &Expression,
+        lets: &HashMap<Idx<Definition>, &Expression>,
+        closure: Option<&Span>,
+    ) {
+        let mut given = Vec::new();
+        self.given_closures(value, lets, &mut given);
+        for escaping in given {
+            let captures = match self.captures.get(escaping.id) {
+                Some(captures) => captures.clone(),
+                None => continue,
+            };
+            for capture in captures {
+                let local_to_frame =
+                    closure.is_none_or(|closure| self.is_inside(capture.local, closure));
+                if capture.mode == CaptureMode::Reference && local_to_frame {
+                    self.errors.push(Error::closure_outlives_capture(
+                        &self.resolution.definitions[capture.local].name.content,
+                        escaping.span(),
+                        capture.span,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// The closures `value` gives back, directly or through the `let`s of
+    /// the block it is the value of.
+    fn given_closures<'e>(
+        &self,
+        value: &'e Expression,
+        lets: &HashMap<Idx<Definition>, &'e Expression>,
+        given: &mut Vec<&'e Expression>,
+    ) {
+        match &value.kind {
+            ExpressionKind::Closure(_) => given.push(value),
+            ExpressionKind::Name(_) => {
+                if let Some(value) = self.local(value).and_then(|local| lets.get(&local)) {
+                    self.given_closures(value, lets, given);
+                }
+            }
+            ExpressionKind::Block(block) => {
+                if let Some(value) = &block.value {
+                    self.given_closures(value, lets, given);
+                }
+            }
+            ExpressionKind::Unsafe(r#unsafe) => {
+                if let Some(value) = &r#unsafe.block.value {
+                    self.given_closures(value, lets, given);
+                }
+            }
+            ExpressionKind::If(r#if) => {
+                for block in std::iter::once(&r#if.body).chain(&r#if.else_block) {
+                    if let Some(value) = &block.value {
+                        self.given_closures(value, lets, given);
+                    }
+                }
+            }
+            ExpressionKind::Match(r#match) => {
+                for arm in &r#match.arms {
+                    self.given_closures(&arm.body, lets, given);
+                }
+            }
+            ExpressionKind::Array(elements) | ExpressionKind::Tuple(elements) => {
+                for element in elements {
+                    self.given_closures(element, lets, given);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The value of each `let` of a single local in `block` and the blocks its
+/// value comes from, which are the ones a closure can be given back from.
+fn collect_lets<'e>(
+    block: &'e Block,
+    resolution: &Resolution,
+    lets: &mut HashMap<Idx<Definition>, &'e Expression>,
+) {
+    for statement in &block.statements {
+        if let Statement::Let(r#let) = statement {
+            if let (Some(local), Some(value)) = (resolution.lets.get(r#let.id), &r#let.value) {
+                lets.insert(*local, value);
+            }
+        }
+    }
+    let mut inner = |expression: &'e Expression| match &expression.kind {
+        ExpressionKind::Block(block) => collect_lets(block, resolution, lets),
+        ExpressionKind::Unsafe(r#unsafe) => collect_lets(&r#unsafe.block, resolution, lets),
+        ExpressionKind::If(r#if) => {
+            collect_lets(&r#if.body, resolution, lets);
+            if let Some(else_block) = &r#if.else_block {
+                collect_lets(else_block, resolution, lets);
+            }
+        }
+        _ => {}
+    };
+    if let Some(value) = &block.value {
+        inner(value);
+    }
+}
@@ -0,0 +1,62 @@
+//! Coercions, the conversions of a value to the type required where it is
+//! used which happen without being written.
+//!
+//! A value is only coerced at a coercion site: where the type it has to be
+//! is known before it is checked, like an argument, the value of a `let`
+//! with a type, an assigned value, or the value of a block or a function.
+//! The checker records the coercion of each site in `Typeck::coercions`,
+//! and codegen emits the conversion it tells from there, so both agree on
+//! which values are converted.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{Error, Type, TypeId, Types},
+    syntax::Span,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Coercion {
+    /// `T` to `T?`, as a value which isn't `null`.
+    Optional,
+    /// A function declared in the program to a pointer to it, of the same
+    /// function type, which can be kept and called later.
+    FunctionPointer,
+}
+
+impl Coercion {
+    /// The type of the value before it is coerced to `target`.
+    pub fn source(&self, types: &Types, target: TypeId) -> TypeId {
+        match (self, types.get(target)) {
+            (Coercion::Optional, Type::Optional(inner)) => *inner,
+            _ => target,
+        }
+    }
+}
+
+/// The coercion of a value of `gotten` to `required` at a coercion site, if
+/// it needs one, where `function_item` tells whether the value is a function
+/// declared in the program. `!` needs none, as it never has a value.
+pub fn coerce(
+    types: &Types,
+    required: TypeId,
+    gotten: TypeId,
+    function_item: bool,
+    location: Span,
+) -> Result<Option<Coercion>, Error> {
+    if gotten == TypeId::NEVER {
+        return Ok(None);
+    }
+    if required == gotten {
+        let is_function = matches!(types.get(gotten), Type::Function { .. });
+        return Ok(Some(Coercion::FunctionPointer).filter(|_| function_item && is_function));
+    }
+    match types.get(required) {
+        Type::Optional(inner) if *inner == gotten => Ok(Some(Coercion::Optional)),
+        _ => Err(Error::mismatched_types(
+            types.display(required),
+            types.display(gotten),
+            location,
+        )),
+    }
+}
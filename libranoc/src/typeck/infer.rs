@@ -0,0 +1,1002 @@
+use std::collections::HashMap;
+
+use crate::{
+    core::{
+        arena::Idx,
+        ast::{Intrinsic, Literal, Name, Pattern, Type as AstType, TypeParameter},
+        Error, FileId, IntrinsicKind, NodeId, Type, TypeId, Types,
+    },
+    hir::*,
+    semantic::{Definition, DefinitionKind, Resolution},
+    syntax::{ExpansionId, Span, Spanned, Token},
+    typeck::{coercion, Typeck},
+};
+
+/// Where the name of a local is written, which tells the definition a
+/// pattern binds.
+type BindingKey = (FileId, usize, ExpansionId);
+
+fn binding_key(name: &Token) -> BindingKey {
+    (name.span.file, name.span.range.start, name.span.expansion)
+}
+
+pub(super) struct Checker<'a> {
+    resolution: &'a Resolution,
+    typeck: Typeck,
+    /// The locals by where their names are written. The arms `?.` is
+    /// lowered to bind the same name, so it may be more than one.
+    locals: HashMap<BindingKey, Vec<Idx<Definition>>>,
+    /// The types of the optional locals known not to be `null` where the
+    /// checker is, by a comparison with `null` around it.
+    narrowed: HashMap<Idx<Definition>, TypeId>,
+    /// How many type parameters the generic functions have, as they have
+    /// no type to tell.
+    generics: HashMap<NodeId, usize>,
+}
+
+/// A local which isn't `null`, with the type of its value.
+type Narrowing = (Idx<Definition>, TypeId);
+
+impl<'a> Checker<'a> {
+    pub(super) fn new(resolution: &'a Resolution) -> Self {
+        let mut locals = HashMap::<_, Vec<_>>::new();
+        for (idx, definition) in resolution.definitions.iter() {
+            if let DefinitionKind::Local = definition.kind {
+                locals
+                    .entry(binding_key(&definition.name))
+                    .or_default()
+                    .push(idx);
+            }
+        }
+        Checker {
+            resolution,
+            typeck: Typeck::default(),
+            locals,
+            narrowed: HashMap::new(),
+            generics: HashMap::new(),
+        }
+    }
+
+    pub(super) fn finish(mut self) -> Typeck {
+        for (idx, definition) in self.resolution.definitions.iter() {
+            if matches!(
+                definition.kind,
+                DefinitionKind::Function { .. } | DefinitionKind::Parameter { .. }
+            ) {
+                if let Some(ty) = self.definition_type(idx) {
+                    self.typeck.definitions.insert(idx, ty);
+                }
+            }
+        }
+        self.typeck
+    }
+
+    pub(super) fn check_module(&mut self, module: &Module) {
+        // the functions of every module can be called from any other one.
+        self.declare_module(&module.statements);
+        self.check_statements(&module.statements);
+    }
+
+    fn declare_module(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::Item(Item::Function(function)) => self.declare_function(function),
+                Statement::Item(Item::Module(ModuleDeclaration {
+                    body: Some(module), ..
+                })) => self.declare_module(&module.statements),
+                _ => {}
+            }
+        }
+    }
+
+    /// Gives `function` the type its signature is written as. A generic
+    /// function has none, as there is no type for its parameters yet.
+    fn declare_function(&mut self, function: &Function) {
+        if !function.generic_parameters.is_empty() {
+            self.generics
+                .insert(function.id, function.generic_parameters.len());
+            return;
+        }
+        let parameters: Option<Vec<_>> = function
+            .parameters
+            .iter()
+            .map(|(_, ty)| self.lower(ty))
+            .collect();
+        let return_type = self.lower(&function.return_type);
+        if let (Some(parameters), Some(return_type)) = (parameters, return_type) {
+            let ty = self.typeck.types.intern(Type::Function {
+                parameters,
+                return_type,
+            });
+            self.typeck.items.insert(function.id, ty);
+        }
+    }
+
+    /// The type `ty` is written as. One without a representation yet, like
+    /// `impl Trait`, is left unchecked rather than reported.
+    fn lower(&mut self, ty: &AstType) -> Option<TypeId> {
+        self.typeck.types.lower(ty).ok()
+    }
+
+    fn display(&self, ty: TypeId) -> String {
+        self.typeck.types.display(ty)
+    }
+
+    /// The type of what `definition` defines, if it is a value.
+    fn definition_type(&self, definition: Idx<Definition>) -> Option<TypeId> {
+        let definition = self.resolution.target(definition);
+        match &self.resolution.definitions[definition].kind {
+            DefinitionKind::Function { id, .. } => self.typeck.items.get(*id).copied(),
+            DefinitionKind::Parameter { function, index } => {
+                match self.typeck.types.get(*self.typeck.items.get(*function)?) {
+                    Type::Function { parameters, .. } => parameters.get(*index as usize).copied(),
+                    _ => None,
+                }
+            }
+            DefinitionKind::Local => self.typeck.definitions.get(&definition).copied(),
+            DefinitionKind::Builtin
+            | DefinitionKind::Module { .. }
+            | DefinitionKind::Import { .. } => None,
+        }
+    }
+
+    /// The type of the builtin `definition` defines, for the ones codegen
+    /// doesn't check the arguments of itself.
+    fn builtin_type(&mut self, definition: Idx<Definition>) -> Option<TypeId> {
+        let definition = &self.resolution.definitions[self.resolution.target(definition)];
+        match (&definition.kind, definition.name.content.as_str()) {
+            (DefinitionKind::Builtin, "panic") => Some(self.typeck.types.intern(Type::Function {
+                parameters: vec![TypeId::STRING],
+                return_type: TypeId::NEVER,
+            })),
+            _ => None,
+        }
+    }
+
+    /// Gives the local `pattern` binds the type `ty`, or checks the literal
+    /// it matches against it, or against the value of it if it is optional.
+    fn bind(&mut self, pattern: &Pattern, ty: Option<TypeId>) {
+        match pattern {
+            Pattern::Slot(Name::Ident(name)) => {
+                if let (Some(definitions), Some(ty)) = (self.locals.get(&binding_key(name)), ty) {
+                    for definition in definitions {
+                        self.typeck.definitions.insert(*definition, ty);
+                    }
+                }
+            }
+            Pattern::Slot(Name::Placeholder(_)) => {}
+            Pattern::Literal(literal) => {
+                let ty = ty.map(|ty| match self.typeck.types.get(ty) {
+                    Type::Optional(inner) => *inner,
+                    _ => ty,
+                });
+                if let (Some(literal_type), Some(ty)) = (self.literal_type(literal, ty), ty) {
+                    self.expect(ty, literal_type, literal.span());
+                }
+            }
+        }
+    }
+
+    /// Whether `gotten` fits where `required` is without being converted,
+    /// reporting it if it doesn't. `!` fits everywhere.
+    fn expect(&mut self, required: TypeId, gotten: TypeId, location: Span) -> Option<TypeId> {
+        if required == gotten || gotten == TypeId::NEVER {
+            Some(gotten)
+        } else {
+            self.typeck.errors.push(Error::mismatched_types(
+                self.display(required),
+                self.display(gotten),
+                location,
+            ));
+            None
+        }
+    }
+
+    /// The type of branches of the types `first` and `second`, which is the
+    /// one which isn't `!` if only one of them is.
+    fn join(
+        &mut self,
+        first: Option<TypeId>,
+        second: Option<TypeId>,
+        location: Span,
+    ) -> Option<TypeId> {
+        match (first?, second?) {
+            (TypeId::NEVER, second) => Some(second),
+            (first, second) => self.expect(first, second, location).map(|_| first),
+        }
+    }
+
+    fn check_function(&mut self, function: &Function) {
+        let body = match &function.body {
+            Some(body) => body,
+            None => return,
+        };
+        let return_type =
+            self.typeck
+                .items
+                .get(function.id)
+                .map(|ty| match self.typeck.types.get(*ty) {
+                    Type::Function { return_type, .. } => *return_type,
+                    _ => unreachable!("a function has a function type"),
+                });
+        self.check_block(body, return_type);
+    }
+
+    /// Checks `statements`, returning whether any of them never finishes.
+    fn check_statements(&mut self, statements: &[Statement]) -> bool {
+        let mut diverges = false;
+        for statement in statements {
+            match statement {
+                Statement::Item(Item::Function(function)) => self.check_function(function),
+                Statement::Item(Item::Module(ModuleDeclaration {
+                    body: Some(module), ..
+                })) => {
+                    self.check_statements(&module.statements);
+                }
+                Statement::Item(_) => {}
+                Statement::Let(r#let) => {
+                    let ty = r#let.ty.as_ref().and_then(|ty| self.lower(ty));
+                    let value = r#let.value.as_ref().and_then(|value| self.check(value, ty));
+                    diverges |= value == Some(TypeId::NEVER);
+                    self.bind(&r#let.pattern, ty.or(value));
+                }
+                Statement::Expression(expression) => {
+                    diverges |= self.infer(expression) == Some(TypeId::NEVER);
+                    // `if x == null { return }` leaves `x` not `null` after it.
+                    if let ExpressionKind::If(If {
+                        condition,
+                        body,
+                        else_block: None,
+                    }) = &expression.kind
+                    {
+                        if self.typeck.type_of(body.id) == Some(TypeId::NEVER) {
+                            let (_, when_false) = self.condition_narrowings(condition);
+                            self.narrowed.extend(when_false);
+                        }
+                    }
+                }
+            }
+        }
+        diverges
+    }
+
+    fn check_block(&mut self, block: &Block, expected: Option<TypeId>) -> Option<TypeId> {
+        self.narrowing(Vec::new(), |this| {
+            this.check_block_narrowed(block, expected)
+        })
+    }
+
+    fn check_block_narrowed(&mut self, block: &Block, expected: Option<TypeId>) -> Option<TypeId> {
+        for statement in &block.statements {
+            if let Statement::Item(Item::Function(function)) = statement {
+                self.declare_function(function);
+            }
+        }
+        let diverges = self.check_statements(&block.statements);
+        let ty = match &block.value {
+            Some(value) => self.check(value, expected),
+            None if diverges => Some(TypeId::NEVER),
+            None => match expected {
+                Some(expected) => self.expect(expected, TypeId::UNIT, block.span.end()),
+                None => Some(TypeId::UNIT),
+            },
+        };
+        if let Some(ty) = ty {
+            self.typeck.expressions.insert(block.id, ty);
+        }
+        ty
+    }
+
+    /// Runs `check` with the locals of `narrowings` narrowed, and drops the
+    /// narrowings made inside of it afterwards. A local assigned inside of
+    /// it stays not narrowed, as it may be `null` again.
+    fn narrowing<T>(
+        &mut self,
+        narrowings: Vec<Narrowing>,
+        check: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let outside = self.narrowed.clone();
+        self.narrowed.extend(narrowings);
+        let result = check(self);
+        let inside = std::mem::take(&mut self.narrowed);
+        self.narrowed = outside
+            .into_iter()
+            .filter(|(definition, _)| inside.contains_key(definition))
+            .collect();
+        result
+    }
+
+    /// The locals which aren't `null` when `condition`, already checked, is
+    /// true, and the ones when it is false.
+    fn condition_narrowings(&self, condition: &Expression) -> (Vec<Narrowing>, Vec<Narrowing>) {
+        match &condition.kind {
+            ExpressionKind::Tuple(elements) if elements.len() == 1 => {
+                self.condition_narrowings(&elements[0])
+            }
+            ExpressionKind::Unary(Unary {
+                operator: UnaryOperator::Not,
+                operand,
+                ..
+            }) => {
+                let (when_true, when_false) = self.condition_narrowings(operand);
+                (when_false, when_true)
+            }
+            ExpressionKind::Binary(Binary {
+                operator, lhs, rhs, ..
+            }) => match operator {
+                BinaryOperator::LogicalAnd => {
+                    let (mut when_true, _) = self.condition_narrowings(lhs);
+                    when_true.extend(self.condition_narrowings(rhs).0);
+                    (when_true, Vec::new())
+                }
+                BinaryOperator::LogicalOr => {
+                    let (_, mut when_false) = self.condition_narrowings(lhs);
+                    when_false.extend(self.condition_narrowings(rhs).1);
+                    (Vec::new(), when_false)
+                }
+                BinaryOperator::EqualTo | BinaryOperator::NotEqualTo => {
+                    let narrowing = match (&lhs.kind, &rhs.kind) {
+                        (_, ExpressionKind::Null) => self.non_null(lhs),
+                        (ExpressionKind::Null, _) => self.non_null(rhs),
+                        _ => None,
+                    };
+                    let narrowing = narrowing.into_iter().collect();
+                    if *operator == BinaryOperator::EqualTo {
+                        (Vec::new(), narrowing)
+                    } else {
+                        (narrowing, Vec::new())
+                    }
+                }
+                _ => (Vec::new(), Vec::new()),
+            },
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
+    /// The optional local `expression` names, with the type of its value.
+    fn non_null(&self, expression: &Expression) -> Option<Narrowing> {
+        let local = self.local(expression)?;
+        match self.typeck.types.get(self.typeck.type_of(expression.id)?) {
+            Type::Optional(ty) => Some((local, *ty)),
+            _ => None,
+        }
+    }
+
+    /// Whether `expression` names a function declared in the program.
+    fn is_function_item(&self, expression: &Expression) -> bool {
+        if !matches!(
+            expression.kind,
+            ExpressionKind::Name(Name::Ident(_)) | ExpressionKind::Path(_)
+        ) {
+            return false;
+        }
+        match self.resolution.references.get(expression.id) {
+            Some(definition) => matches!(
+                self.resolution.definitions[self.resolution.target(*definition)].kind,
+                DefinitionKind::Function { .. }
+            ),
+            None => false,
+        }
+    }
+
+    /// How many type arguments `expression` takes, which only the generic
+    /// functions it names do.
+    fn type_parameters(&self, expression: &Expression) -> usize {
+        let definition = match self.resolution.references.get(expression.id) {
+            Some(definition) => self.resolution.target(*definition),
+            None => return 0,
+        };
+        match self.resolution.definitions[definition].kind {
+            DefinitionKind::Function { id, .. } => self.generics.get(&id).copied().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// The local or parameter `expression` names.
+    fn local(&self, expression: &Expression) -> Option<Idx<Definition>> {
+        if !matches!(expression.kind, ExpressionKind::Name(Name::Ident(_))) {
+            return None;
+        }
+        let definition = self
+            .resolution
+            .target(*self.resolution.references.get(expression.id)?);
+        match self.resolution.definitions[definition].kind {
+            DefinitionKind::Local | DefinitionKind::Parameter { .. } => Some(definition),
+            _ => None,
+        }
+    }
+
+    fn infer(&mut self, expression: &Expression) -> Option<TypeId> {
+        self.check(expression, None)
+    }
+
+    /// Infers the type of `expression` and checks it against `expected`,
+    /// which also guides the inference of the expressions inside of it.
+    /// Where a type is expected is a coercion site.
+    fn check(&mut self, expression: &Expression, expected: Option<TypeId>) -> Option<TypeId> {
+        let ty = self.synthesize(expression, expected);
+        let ty = match (ty, expected) {
+            (Some(ty), Some(expected)) => self.coerce(expression, expected, ty),
+            (ty, _) => ty,
+        };
+        if let Some(ty) = ty {
+            self.typeck.expressions.insert(expression.id, ty);
+        }
+        ty
+    }
+
+    /// Converts the value of `expression`, of the type `gotten`, to the type
+    /// `required` where it is, recording how it is converted, or reports
+    /// that it can't be.
+    fn coerce(
+        &mut self,
+        expression: &Expression,
+        required: TypeId,
+        gotten: TypeId,
+    ) -> Option<TypeId> {
+        let function_item = self.is_function_item(expression);
+        match coercion::coerce(
+            &self.typeck.types,
+            required,
+            gotten,
+            function_item,
+            expression.span(),
+        ) {
+            Ok(Some(coercion)) => {
+                self.typeck.coercions.insert(expression.id, coercion);
+                Some(required)
+            }
+            Ok(None) => Some(gotten),
+            Err(error) => {
+                self.typeck.errors.push(error);
+                None
+            }
+        }
+    }
+
+    /// An integer literal takes the integer type `expected` is, or the one
+    /// of its value if it is an optional, and is an `i32` when it fits in
+    /// one or an `i64` otherwise. A decimal one is an `f64` unless an `f32`
+    /// is expected.
+    fn literal_type(&mut self, literal: &Literal, expected: Option<TypeId>) -> Option<TypeId> {
+        let expected = expected.map(|ty| match self.typeck.types.get(ty) {
+            Type::Optional(inner) => *inner,
+            _ => ty,
+        });
+        match literal {
+            Literal::Integer(_) if matches!(expected, Some(ty) if is_integer(ty)) => expected,
+            // one more than `i32::MAX`, which is `i32::MIN` negated.
+            Literal::Integer(integer) => match integer.value() {
+                Some(value) if value > 1 << 31 => Some(TypeId::I64),
+                _ => Some(TypeId::I32),
+            },
+            Literal::Decimal(_) if expected == Some(TypeId::F32) => expected,
+            Literal::Decimal(_) => Some(TypeId::F64),
+            Literal::Boolean(_) => Some(TypeId::BOOL),
+            Literal::String(_) => Some(TypeId::STRING),
+            Literal::Character(_) => None,
+        }
+    }
+
+    fn synthesize(&mut self, expression: &Expression, expected: Option<TypeId>) -> Option<TypeId> {
+        match &expression.kind {
+            ExpressionKind::Literal(literal) => self.literal_type(literal, expected),
+            ExpressionKind::Name(Name::Ident(_)) | ExpressionKind::Path(_) => {
+                let definition = *self.resolution.references.get(expression.id)?;
+                match self.narrowed.get(&self.resolution.target(definition)) {
+                    Some(ty) => Some(*ty),
+                    None => self
+                        .definition_type(definition)
+                        .or_else(|| self.builtin_type(definition)),
+                }
+            }
+            ExpressionKind::Name(Name::Placeholder(_)) => None,
+            ExpressionKind::Instantiate(instantiate) => {
+                let ty = self.infer(&instantiate.operand);
+                let required = self.type_parameters(&instantiate.operand);
+                if instantiate.type_arguments.len() != required {
+                    self.typeck.errors.push(Error::mismatched_type_arity(
+                        callee_name(&instantiate.operand),
+                        required,
+                        instantiate.type_arguments.len(),
+                        expression.span.clone(),
+                    ));
+                    return None;
+                }
+                ty
+            }
+            ExpressionKind::Array(elements) => {
+                let hint = expected.and_then(|ty| match self.typeck.types.get(ty) {
+                    Type::Array(element) => Some(*element),
+                    _ => None,
+                });
+                let mut element = hint;
+                for expression in elements {
+                    let ty = self.check(expression, element);
+                    element = element.or(ty);
+                }
+                let element = element?;
+                Some(self.typeck.types.intern(Type::Array(element)))
+            }
+            // `(a)` is `a` in parentheses.
+            ExpressionKind::Tuple(elements) if elements.len() == 1 => {
+                self.check(&elements[0], expected)
+            }
+            ExpressionKind::Tuple(elements) => {
+                let hints = expected.and_then(|ty| match self.typeck.types.get(ty) {
+                    Type::Tuple(types) if types.len() == elements.len() => Some(types.clone()),
+                    _ => None,
+                });
+                let types: Vec<_> = elements
+                    .iter()
+                    .enumerate()
+                    .map(|(index, element)| {
+                        let hint = hints.as_ref().map(|hints| hints[index]);
+                        self.check(element, hint)
+                    })
+                    .collect();
+                let types = types.into_iter().collect::<Option<_>>()?;
+                Some(self.typeck.types.intern(Type::Tuple(types)))
+            }
+            ExpressionKind::Init(init) => {
+                for (_, value) in &init.fields {
+                    self.infer(value);
+                }
+                Some(self.typeck.types.intern(Type::Adt {
+                    name: init.name.content.clone(),
+                    arguments: Vec::new(),
+                }))
+            }
+            ExpressionKind::Unary(unary) => self.synthesize_unary(unary, expected),
+            ExpressionKind::Binary(binary) => self.synthesize_binary(binary, expected),
+            ExpressionKind::Assign(assign) => {
+                if let Some(local) = self.local(&assign.target) {
+                    self.narrowed.remove(&local);
+                }
+                let target = self.infer(&assign.target);
+                let value = self.check(&assign.value, target);
+                // `let x;` takes the type of the value first assigned to it.
+                if let (None, Some(local), Some(value)) =
+                    (target, self.local(&assign.target), value)
+                {
+                    self.typeck.definitions.entry(local).or_insert(value);
+                }
+                Some(TypeId::UNIT)
+            }
+            ExpressionKind::Field(field) => {
+                let operand = self.infer(&field.operand);
+                if let Some(operand) = operand {
+                    if let Type::Optional(_) = self.typeck.types.get(operand) {
+                        self.typeck.errors.push(Error::possibly_null(
+                            self.display(operand),
+                            field.operand.span(),
+                        ));
+                        return None;
+                    }
+                }
+                // `t.0` is the first element of a tuple.
+                if let (Some(operand), ExpressionKind::Literal(Literal::Integer(index))) =
+                    (operand, &field.field.kind)
+                {
+                    if let Type::Tuple(elements) = self.typeck.types.get(operand) {
+                        let element = index
+                            .value()
+                            .and_then(|index| elements.get(index as usize).copied());
+                        if element.is_none() {
+                            self.typeck.errors.push(Error::no_field(
+                                self.display(operand),
+                                &index.0.content,
+                                field.field.span(),
+                            ));
+                        }
+                        return element;
+                    }
+                }
+                // only the items of a module and the elements of a tuple have
+                // a known field for now.
+                let definition = *self.resolution.references.get(expression.id)?;
+                self.definition_type(definition)
+            }
+            ExpressionKind::Call(call) => self.synthesize_call(call),
+            ExpressionKind::Index(index) => {
+                let operand = self.infer(&index.operand);
+                for index in &index.indices {
+                    self.infer(index);
+                }
+                match self.typeck.types.get(operand?) {
+                    Type::Array(element) => Some(*element),
+                    _ => None,
+                }
+            }
+            ExpressionKind::If(r#if) => {
+                self.check(&r#if.condition, Some(TypeId::BOOL));
+                let (when_true, when_false) = self.condition_narrowings(&r#if.condition);
+                match &r#if.else_block {
+                    Some(else_block) => {
+                        let body = self
+                            .narrowing(when_true, |this| this.check_block(&r#if.body, expected));
+                        let hint = expected.or(body.filter(|ty| *ty != TypeId::NEVER));
+                        let else_type =
+                            self.narrowing(when_false, |this| this.check_block(else_block, hint));
+                        self.join(body, else_type, else_block.span.clone())
+                    }
+                    None => {
+                        self.narrowing(when_true, |this| this.check_block(&r#if.body, None));
+                        Some(TypeId::UNIT)
+                    }
+                }
+            }
+            ExpressionKind::Match(r#match) => {
+                let mut scrutinee = self.infer(&r#match.scrutinee);
+                let mut ty = Some(TypeId::NEVER);
+                for arm in &r#match.arms {
+                    self.bind(&arm.pattern, scrutinee);
+                    let (when_true, when_false) = match &arm.guard {
+                        Some(guard) => {
+                            self.check(guard, Some(TypeId::BOOL));
+                            self.condition_narrowings(guard)
+                        }
+                        None => (Vec::new(), Vec::new()),
+                    };
+                    let hint = expected.or(ty.filter(|ty| *ty != TypeId::NEVER));
+                    let body = self.narrowing(when_true, |this| this.check(&arm.body, hint));
+                    ty = self.join(ty, body, arm.body.span());
+                    // an arm taking every `null`, like the first one `?.` is
+                    // lowered to, leaves the value to the arms after it.
+                    if let Pattern::Slot(Name::Ident(name)) = &arm.pattern {
+                        let bindings = &self.locals[&binding_key(name)];
+                        if let Some((_, value)) = when_false
+                            .iter()
+                            .find(|(local, _)| bindings.contains(local))
+                        {
+                            scrutinee = Some(*value);
+                        }
+                    }
+                }
+                ty
+            }
+            ExpressionKind::Closure(closure) => {
+                let parameters: Vec<_> = closure
+                    .parameters
+                    .iter()
+                    .map(|(pattern, ty)| {
+                        let ty = ty.as_ref().and_then(|ty| self.lower(ty));
+                        self.bind(pattern, ty);
+                        ty
+                    })
+                    .collect();
+                let return_type = closure.return_type.as_ref().and_then(|ty| self.lower(ty));
+                let body = self.check(&closure.body, return_type);
+                let parameters = parameters.into_iter().collect::<Option<_>>()?;
+                Some(self.typeck.types.intern(Type::Function {
+                    parameters,
+                    return_type: return_type.or(body)?,
+                }))
+            }
+            ExpressionKind::Block(block) => self.check_block(block, expected),
+            ExpressionKind::Unsafe(r#unsafe) => self.check_block(&r#unsafe.block, expected),
+            ExpressionKind::Null => {
+                expected.filter(|ty| matches!(self.typeck.types.get(*ty), Type::Optional(_)))
+            }
+            ExpressionKind::Todo(_) => Some(TypeId::NEVER),
+            ExpressionKind::Intrinsic(intrinsic) => {
+                self.typeck.errors.push(Error::uncalled_intrinsic(
+                    &intrinsic.name,
+                    expression.span(),
+                ));
+                None
+            }
+        }
+    }
+
+    fn synthesize_unary(&mut self, unary: &Unary, expected: Option<TypeId>) -> Option<TypeId> {
+        let operand = self.check(&unary.operand, expected)?;
+        let (name, supported) = match unary.operator {
+            UnaryOperator::Not => ("Not", operand == TypeId::BOOL || is_integer(operand)),
+            UnaryOperator::Plus => ("Plus", is_number(operand)),
+            UnaryOperator::Minus => ("Negate", is_number(operand)),
+        };
+        if supported {
+            Some(operand)
+        } else {
+            self.typeck.errors.push(Error::unsupported_operand(
+                name,
+                self.display(operand),
+                unary.operator_span.clone(),
+            ));
+            None
+        }
+    }
+
+    fn synthesize_binary(&mut self, binary: &Binary, expected: Option<TypeId>) -> Option<TypeId> {
+        use BinaryOperator::*;
+        let Binary {
+            operator,
+            operator_span,
+            lhs,
+            rhs,
+        } = binary;
+        match operator {
+            LogicalOr | LogicalAnd => {
+                self.check(lhs, Some(TypeId::BOOL));
+                // `rhs` is only run when `lhs` is true for `&&`, and false for
+                // `||`.
+                let (when_true, when_false) = self.condition_narrowings(lhs);
+                let narrowings = if *operator == LogicalAnd {
+                    when_true
+                } else {
+                    when_false
+                };
+                self.narrowing(narrowings, |this| this.check(rhs, Some(TypeId::BOOL)));
+                Some(TypeId::BOOL)
+            }
+            RangeRightExclusive | RangeRightInclusive => {
+                let lhs = self.infer(lhs);
+                self.check(rhs, lhs);
+                None
+            }
+            _ => {
+                let hint = match operator {
+                    EqualTo | NotEqualTo | GreaterThan | LessThan | GreaterThanOrEqualTo
+                    | LessThanOrEqualTo => None,
+                    _ => expected,
+                };
+                // an integer literal takes the type of the other operand,
+                // whichever side it is on.
+                if hint.is_none() && is_integer_literal(lhs) && !is_integer_literal(rhs) {
+                    let rhs_type = self.infer(rhs);
+                    let lhs_type = self.check(lhs, rhs_type);
+                    return self.binary_type(*operator, operator_span, lhs_type.or(rhs_type)?);
+                }
+                let lhs_type = self.check(lhs, hint);
+                // strings are concatenated, which is also how an
+                // interpolation is lowered. Nothing is turned into a string
+                // for it, so only a string can be interpolated.
+                if *operator == Add && lhs_type == Some(TypeId::STRING) {
+                    self.check(rhs, Some(TypeId::STRING));
+                    return Some(TypeId::STRING);
+                }
+                let rhs_type = self.check(rhs, lhs_type);
+                self.binary_type(*operator, operator_span, lhs_type.or(rhs_type)?)
+            }
+        }
+    }
+
+    /// The type of an operation on operands of the type `operand`, or `None`
+    /// with the error reported if they don't support it.
+    fn binary_type(
+        &mut self,
+        operator: BinaryOperator,
+        operator_span: &Span,
+        operand: TypeId,
+    ) -> Option<TypeId> {
+        use BinaryOperator::*;
+        // a vector is added, subtracted and multiplied lane by lane, but
+        // only divided with lanes of floats, and only the bits of one of
+        // integers are operated on.
+        let supported = match operator {
+            EqualTo | NotEqualTo => true,
+            BitAnd | BitOr | BitXor => {
+                operand == TypeId::BOOL || is_integer(operand) || is_integer_vector(operand)
+            }
+            ShiftLeft | ShiftRight => is_integer(operand),
+            Add | Subtract | Multiply => is_number(operand) || operand.lanes().is_some(),
+            Divide => is_number(operand) || is_float_vector(operand),
+            _ => is_number(operand),
+        };
+        if !supported {
+            self.typeck.errors.push(Error::unsupported_operand(
+                operator.trait_name(),
+                self.display(operand),
+                operator_span.clone(),
+            ));
+            return None;
+        }
+        match operator {
+            EqualTo | NotEqualTo | GreaterThan | LessThan | GreaterThanOrEqualTo
+            | LessThanOrEqualTo => Some(TypeId::BOOL),
+            _ => Some(operand),
+        }
+    }
+
+    /// The signature of the intrinsic `callee` calls, recording which one
+    /// it is and the types it is given.
+    fn intrinsic(&mut self, callee: &Expression, intrinsic: &Intrinsic) -> Option<TypeId> {
+        let kind = match IntrinsicKind::from_name(&intrinsic.name.content) {
+            Some(kind) => kind,
+            None => {
+                self.typeck
+                    .errors
+                    .push(Error::unknown_intrinsic(&intrinsic.name));
+                return None;
+            }
+        };
+        if intrinsic.type_arguments.len() != kind.type_parameters() {
+            self.typeck.errors.push(Error::mismatched_type_arity(
+                callee_name(callee),
+                kind.type_parameters(),
+                intrinsic.type_arguments.len(),
+                intrinsic.span.clone(),
+            ));
+            return None;
+        }
+        let mut type_arguments = Vec::new();
+        for argument in &intrinsic.type_arguments {
+            // codegen needs to know every type an intrinsic is given, so
+            // one without a representation can't be left unchecked here.
+            match argument {
+                TypeParameter::Specific(ty) => match self.lower(ty) {
+                    Some(ty) => type_arguments.push(ty),
+                    None => {
+                        self.typeck.errors.push(Error::unimplemented(ty));
+                        return None;
+                    }
+                },
+                TypeParameter::Star(span) => {
+                    self.typeck.errors.push(Error::unimplemented(span));
+                    return None;
+                }
+            }
+        }
+        if kind.takes_vector() && type_arguments[0].lanes().is_none() {
+            self.typeck.errors.push(Error::not_a_vector(
+                &intrinsic.name,
+                self.display(type_arguments[0]),
+            ));
+            return None;
+        }
+        let ty = kind.signature(&type_arguments, &mut self.typeck.types);
+        self.typeck
+            .intrinsics
+            .insert(callee.id, (kind, type_arguments));
+        self.typeck.expressions.insert(callee.id, ty);
+        Some(ty)
+    }
+
+    fn synthesize_call(&mut self, call: &Call) -> Option<TypeId> {
+        if let (Some(builtin @ ("spawn" | "scope")), [task, argument]) =
+            (self.builtin_name(&call.callee), &call.arguments[..])
+        {
+            let spawned = builtin == "spawn";
+            return self.synthesize_task(spawned, task, argument);
+        }
+        let callee = match &call.callee.kind {
+            ExpressionKind::Intrinsic(intrinsic) => self.intrinsic(&call.callee, intrinsic),
+            _ => self.infer(&call.callee),
+        };
+        let signature = callee.map(|ty| self.typeck.types.get(ty).clone());
+        match signature {
+            Some(Type::Function {
+                parameters,
+                return_type,
+            }) => {
+                if parameters.len() != call.arguments.len() {
+                    self.typeck.errors.push(Error::mismatched_arity(
+                        callee_name(&call.callee),
+                        parameters.len(),
+                        call.arguments.len(),
+                        call.span.clone(),
+                    ));
+                    for argument in &call.arguments {
+                        self.infer(argument);
+                    }
+                } else {
+                    for (argument, parameter) in call.arguments.iter().zip(parameters) {
+                        self.check(argument, Some(parameter));
+                    }
+                }
+                Some(return_type)
+            }
+            Some(Type::Never) | None => {
+                for argument in &call.arguments {
+                    self.infer(argument);
+                }
+                None
+            }
+            Some(_) => {
+                self.typeck.errors.push(Error::not_callable(
+                    self.display(callee?),
+                    call.callee.span(),
+                ));
+                None
+            }
+        }
+    }
+
+    /// The name of the builtin `callee` is, if it is one.
+    fn builtin_name(&self, callee: &Expression) -> Option<&str> {
+        let definition = *self.resolution.references.get(callee.id)?;
+        let definition = &self.resolution.definitions[self.resolution.target(definition)];
+        match definition.kind {
+            DefinitionKind::Builtin => Some(definition.name.content.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Checks `spawn(task, argument)` and `scope(task, argument)`, which
+    /// call `task` with `argument`. `scope` does so on this thread, giving
+    /// its result, and `spawn` on another thread, which gives the result to
+    /// the one joining it, so both have to be sent between threads.
+    fn synthesize_task(
+        &mut self,
+        spawned: bool,
+        task: &Expression,
+        argument: &Expression,
+    ) -> Option<TypeId> {
+        let signature = self.infer(task).map(|ty| self.typeck.types.get(ty).clone());
+        let (parameters, return_type) = match signature {
+            Some(Type::Function {
+                parameters,
+                return_type,
+            }) => (parameters, return_type),
+            _ => {
+                self.infer(argument);
+                return None;
+            }
+        };
+        if parameters.len() != 1 {
+            self.typeck.errors.push(Error::mismatched_arity(
+                callee_name(task),
+                parameters.len(),
+                1,
+                task.span(),
+            ));
+            self.infer(argument);
+            return None;
+        }
+        self.check(argument, Some(parameters[0]));
+        if !spawned {
+            return Some(return_type);
+        }
+        let unsafe_type = [(parameters[0], argument), (return_type, task)]
+            .iter()
+            .copied()
+            .find(|(ty, _)| !is_send_safe(&self.typeck.types, *ty));
+        if let Some((ty, expression)) = unsafe_type {
+            self.typeck
+                .errors
+                .push(Error::not_send_safe(self.display(ty), expression.span()));
+        }
+        None
+    }
+}
+
+/// Whether a value of `ty` can be given to a task on a worker thread, or
+/// given back by it, which the host passes between the instances as one
+/// `i32`.
+fn is_send_safe(types: &Types, ty: TypeId) -> bool {
+    matches!(types.get(ty), Type::I32 | Type::Bool)
+}
+
+fn is_integer_literal(expression: &Expression) -> bool {
+    matches!(
+        expression.kind,
+        ExpressionKind::Literal(Literal::Integer(_))
+    )
+}
+
+fn is_integer(ty: TypeId) -> bool {
+    ty == TypeId::I32 || ty == TypeId::I64
+}
+
+fn is_number(ty: TypeId) -> bool {
+    is_integer(ty) || ty == TypeId::F32 || ty == TypeId::F64
+}
+
+fn is_integer_vector(ty: TypeId) -> bool {
+    matches!(ty.lanes(), Some((lane, _)) if is_integer(lane))
+}
+
+fn is_float_vector(ty: TypeId) -> bool {
+    matches!(ty.lanes(), Some((lane, _)) if !is_integer(lane))
+}
+
+/// How the callee of a call is named in an error.
+fn callee_name(callee: &Expression) -> String {
+    match &callee.kind {
+        ExpressionKind::Name(Name::Ident(name)) => name.content.clone(),
+        ExpressionKind::Path(path) => path.to_string(),
+        ExpressionKind::Intrinsic(intrinsic) => format!("@{}", intrinsic.name.content),
+        _ => "the function".to_string(),
+    }
+}
@@ -0,0 +1,110 @@
+//! Type checking of the HIR. The types of the expressions are inferred
+//! bidirectionally: an expression whose type is required by its context,
+//! like an argument or the body of a function, is checked against it, and
+//! any other one is inferred from its operands and the definitions its
+//! names refer to. What it finds is kept in a `Typeck` beside the HIR, which
+//! codegen reads the WASM value types from.
+//!
+//! Numbers are never converted implicitly, so an `i32` doesn't fit where an
+//! `i64` is required, nor an integer where a float is. Only an integer
+//! literal has no type of its own: it takes the integer type its context
+//! requires, the type of the other operand of an operator, or else `i32`, or
+//! `i64` when it doesn't fit in an `i32`.
+//!
+//! An intrinsic is checked against the signature the compiler gives it,
+//! and can only be called, as it has no function codegen could point to.
+//!
+//! A value is converted implicitly only at the coercion sites, where the
+//! type it has to be is known: to an optional of its type, and from a
+//! function to a pointer to it.
+//!
+//! An optional is narrowed to the type of its value where comparing it with
+//! `null` tells it isn't, in the branch of an `if` or a guarded `match` arm,
+//! behind `&&` and `||`, and after an `if` returning when it is, until it is
+//! assigned to again.
+//!
+//! A string, an array or a value of a type declared in the program owns
+//! memory on the heap, and is moved rather than copied where it is passed
+//! on: as an argument, into a local, an array, a tuple or a field, or out of
+//! a block. What it is moved out of can't be used until it is assigned
+//! again, so every value has a single owner codegen can free it behind.
+//! Operators, field accesses, indexing and intrinsics only read their
+//! operands. A closure moves what it captures by value when it is made.
+
+use std::collections::HashMap;
+
+use crate::{
+    core::{arena::Idx, Error, IntrinsicKind, NodeId, NodeMap, Type, TypeId, Types},
+    hir::Module,
+    semantic::{Definition, Resolution},
+};
+
+pub mod captures;
+pub mod coercion;
+mod infer;
+mod moves;
+
+use captures::Capture;
+use coercion::Coercion;
+use infer::Checker;
+
+/// The types the checker gave a module.
+#[derive(Debug, Default)]
+pub struct Typeck {
+    pub types: Types,
+    /// The type of each expression and block, by its id. An expression the
+    /// type of which couldn't be inferred, like the call of a builtin, has
+    /// none.
+    pub expressions: NodeMap<TypeId>,
+    /// The type of each function, parameter and local.
+    pub definitions: HashMap<Idx<Definition>, TypeId>,
+    /// The type of each function, by the id of the item.
+    pub items: NodeMap<TypeId>,
+    /// How the value of each expression at a coercion site is converted to
+    /// the type required there, if it is. The type of the expression is the
+    /// one it is converted to.
+    pub coercions: NodeMap<Coercion>,
+    /// The intrinsic each called `@name` is, with the types it is given.
+    pub intrinsics: NodeMap<(IntrinsicKind, Vec<TypeId>)>,
+    /// The locals each closure captures, by the id of its expression.
+    pub captures: NodeMap<Vec<Capture>>,
+    pub errors: Vec<Error>,
+}
+
+impl Typeck {
+    /// The type of the expression or block `id`.
+    pub fn type_of(&self, id: NodeId) -> Option<TypeId> {
+        self.expressions.get(id).copied()
+    }
+
+    /// The parameter and return types of the function item `id`.
+    pub fn signature_of(&self, id: NodeId) -> Option<(Vec<TypeId>, TypeId)> {
+        match self.types.get(*self.items.get(id)?) {
+            Type::Function {
+                parameters,
+                return_type,
+            } => Some((parameters.clone(), *return_type)),
+            _ => None,
+        }
+    }
+}
+
+/// Infers and checks the types of `module`, whose names `resolution`
+/// resolved. Every type error is reported, with the expressions depending on
+/// a broken one left without a type instead of reporting it again, and the
+/// closures outliving what they capture and the uses of moved values once
+/// the types are known.
+pub fn check(module: &Module, resolution: &Resolution) -> Typeck {
+    let mut checker = Checker::new(resolution);
+    checker.check_module(module);
+    let mut typeck = checker.finish();
+    if typeck.errors.is_empty() {
+        let (captures, errors) = captures::find_captures(module, resolution, &typeck);
+        typeck.captures = captures;
+        typeck.errors = errors;
+    }
+    if typeck.errors.is_empty() {
+        typeck.errors = moves::check_moves(module, resolution, &typeck);
+    }
+    typeck
+}
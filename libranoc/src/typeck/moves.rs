@@ -0,0 +1,267 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    core::{arena::Idx, ast::Name, Error, TypeId},
+    hir::{BinaryOperator, Block, Expression, ExpressionKind, Item, Module, Statement},
+    semantic::{Definition, DefinitionKind, Resolution},
+    syntax::{Span, Spanned},
+    typeck::Typeck,
+};
+
+/// What is known at a point of a function: whether it is reached at all, and
+/// where each local which may have been moved out of was, by the first move.
+#[derive(Debug, Clone)]
+struct State {
+    reachable: bool,
+    moved: HashMap<Idx<Definition>, Span>,
+}
+
+impl State {
+    fn new() -> Self {
+        State {
+            reachable: true,
+            moved: HashMap::new(),
+        }
+    }
+}
+
+/// Reports every use of a parameter or local after its value was moved out
+/// of it on some path leading there. Each local is reported once, at the
+/// first of such uses.
+pub(super) fn check_moves(module: &Module, resolution: &Resolution, typeck: &Typeck) -> Vec<Error> {
+    let mut checker = MoveChecker {
+        resolution,
+        typeck,
+        state: State::new(),
+        reported: HashSet::new(),
+        errors: Vec::new(),
+    };
+    checker.check_statements(&module.statements);
+    checker.errors
+}
+
+struct MoveChecker<'a> {
+    resolution: &'a Resolution,
+    typeck: &'a Typeck,
+    state: State,
+    /// The locals a use after a move was reported for.
+    reported: HashSet<Idx<Definition>>,
+    errors: Vec<Error>,
+}
+
+impl MoveChecker<'_> {
+    fn check_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::Item(item) => self.check_item(item),
+                Statement::Let(r#let) => {
+                    if let Some(value) = &r#let.value {
+                        self.check_expression(value, true);
+                    }
+                }
+                Statement::Expression(expression) => self.check_expression(expression, true),
+            }
+        }
+    }
+
+    fn check_item(&mut self, item: &Item) {
+        let state = std::mem::replace(&mut self.state, State::new());
+        match item {
+            Item::Function(function) => {
+                if let Some(body) = &function.body {
+                    self.check_block(body);
+                }
+            }
+            Item::Module(declaration) => {
+                if let Some(module) = &declaration.body {
+                    self.check_statements(&module.statements);
+                }
+            }
+            Item::Use(_) => {}
+        }
+        self.state = state;
+    }
+
+    fn check_block(&mut self, block: &Block) {
+        self.check_statements(&block.statements);
+        if let Some(value) = &block.value {
+            self.check_expression(value, true);
+        }
+    }
+
+    /// The parameter or local `expression` names, if the value it holds is
+    /// moved.
+    fn moved_local(&self, expression: &Expression) -> Option<Idx<Definition>> {
+        if !matches!(expression.kind, ExpressionKind::Name(Name::Ident(_))) {
+            return None;
+        }
+        let definition = *self.resolution.references.get(expression.id)?;
+        match self.resolution.definitions[definition].kind {
+            DefinitionKind::Local | DefinitionKind::Parameter { .. } => {}
+            _ => return None,
+        }
+        self.typeck.definitions.get(&definition)?;
+        if self.is_copy(definition) {
+            None
+        } else {
+            Some(definition)
+        }
+    }
+
+    fn is_copy(&self, local: Idx<Definition>) -> bool {
+        self.typeck
+            .definitions
+            .get(&local)
+            .is_none_or(|ty| self.typeck.types.is_copy(*ty))
+    }
+
+    /// Checks `expression`, the value of which is moved where it is used if
+    /// `moves`, like an argument, or only read, like the operand of an
+    /// operator or a field access.
+    fn check_expression(&mut self, expression: &Expression, moves: bool) {
+        match &expression.kind {
+            ExpressionKind::Name(_) => {
+                let local = match self.moved_local(expression) {
+                    Some(local) => local,
+                    None => return,
+                };
+                if !self.state.reachable {
+                    return;
+                }
+                if let Some(moved) = self.state.moved.get(&local) {
+                    if self.reported.insert(local) {
+                        self.errors.push(Error::use_after_move(
+                            &self.resolution.definitions[local].name.content,
+                            expression.span(),
+                            moved.clone(),
+                        ));
+                    }
+                } else if moves {
+                    self.state.moved.insert(local, expression.span());
+                }
+            }
+            ExpressionKind::Assign(assign) => {
+                self.check_expression(&assign.value, true);
+                match self.moved_local(&assign.target) {
+                    // a new value is moved in.
+                    Some(local) => {
+                        self.state.moved.remove(&local);
+                    }
+                    None => self.check_expression(&assign.target, false),
+                }
+            }
+            ExpressionKind::If(r#if) => {
+                self.check_expression(&r#if.condition, false);
+                let before = self.state.clone();
+                self.check_block(&r#if.body);
+                let body = std::mem::replace(&mut self.state, before);
+                if let Some(else_block) = &r#if.else_block {
+                    self.check_block(else_block);
+                }
+                let else_state = std::mem::replace(&mut self.state, State::new());
+                self.state = join(vec![body, else_state]);
+            }
+            ExpressionKind::Match(r#match) => {
+                self.check_expression(&r#match.scrutinee, false);
+                let before = self.state.clone();
+                let mut arms = Vec::new();
+                for arm in &r#match.arms {
+                    self.state = before.clone();
+                    if let Some(guard) = &arm.guard {
+                        self.check_expression(guard, false);
+                    }
+                    self.check_expression(&arm.body, moves);
+                    arms.push(self.state.clone());
+                }
+                self.state = join(arms);
+            }
+            ExpressionKind::Binary(binary)
+                if matches!(
+                    binary.operator,
+                    BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr
+                ) =>
+            {
+                self.check_expression(&binary.lhs, false);
+                let before = self.state.clone();
+                self.check_expression(&binary.rhs, false);
+                // the rhs may be skipped.
+                self.state = join(vec![before, self.state.clone()]);
+            }
+            ExpressionKind::Binary(binary) => {
+                self.check_expression(&binary.lhs, false);
+                self.check_expression(&binary.rhs, false);
+            }
+            ExpressionKind::Closure(closure) => {
+                // what the body moves is captured when the closure is made,
+                // and all of what a `move` closure uses.
+                self.check_expression(&closure.body, true);
+                if closure.is_move {
+                    let captures = self.typeck.captures.get(expression.id);
+                    for capture in captures.into_iter().flatten() {
+                        if !self.is_copy(capture.local) && self.state.reachable {
+                            self.state
+                                .moved
+                                .entry(capture.local)
+                                .or_insert_with(|| capture.span.clone());
+                        }
+                    }
+                }
+            }
+            ExpressionKind::Block(block) => self.check_block(block),
+            ExpressionKind::Unsafe(r#unsafe) => self.check_block(&r#unsafe.block),
+            ExpressionKind::Array(elements) | ExpressionKind::Tuple(elements) => {
+                for element in elements {
+                    self.check_expression(element, true);
+                }
+            }
+            ExpressionKind::Init(init) => {
+                for (_, value) in &init.fields {
+                    self.check_expression(value, true);
+                }
+            }
+            ExpressionKind::Unary(unary) => self.check_expression(&unary.operand, false),
+            ExpressionKind::Field(field) => self.check_expression(&field.operand, false),
+            ExpressionKind::Instantiate(instantiate) => {
+                self.check_expression(&instantiate.operand, false)
+            }
+            ExpressionKind::Call(call) => {
+                self.check_expression(&call.callee, false);
+                // an intrinsic only reads its arguments.
+                let moves = !matches!(call.callee.kind, ExpressionKind::Intrinsic(_));
+                for argument in &call.arguments {
+                    self.check_expression(argument, moves);
+                }
+            }
+            ExpressionKind::Index(index) => {
+                self.check_expression(&index.operand, false);
+                for index in &index.indices {
+                    self.check_expression(index, false);
+                }
+            }
+            ExpressionKind::Literal(_)
+            | ExpressionKind::Path(_)
+            | ExpressionKind::Null
+            | ExpressionKind::Todo(_)
+            | ExpressionKind::Intrinsic(_) => {}
+        }
+        if self.typeck.type_of(expression.id) == Some(TypeId::NEVER) {
+            self.state.reachable = false;
+        }
+    }
+}
+
+/// The state after any of `branches`, leaving out the ones which aren't
+/// reached. A local moved in any of the others may have been moved.
+fn join(branches: Vec<State>) -> State {
+    let mut joined = State {
+        reachable: false,
+        moved: HashMap::new(),
+    };
+    for state in branches.into_iter().filter(|state| state.reachable) {
+        joined.reachable = true;
+        for (local, span) in state.moved {
+            joined.moved.entry(local).or_insert(span);
+        }
+    }
+    joined
+}
@@ -0,0 +1,130 @@
+use std::{fs, process::Command};
+
+use libranoc::{
+    codegen::{compile_artifacts, CompileOptions},
+    syntax::{parse, tokenize},
+};
+
+const SOURCE: &str = "pub fn trapping(a: i32): i32 { a + 1 } \
+                      #[overflow(wrap)] pub fn wrapping(a: i32): i32 { a + 1 } \
+                      #[overflow(saturate)] pub fn saturating(a: i32): i32 { a * 2 } \
+                      pub fn fail(): i32 { panic(\"no\") } \
+                      pub fn divide(a: i32, b: i32): i32 { a / b } \
+                      pub fn remainder(a: i32, b: i32): i32 { a % b } \
+                      #[overflow(wrap)] pub fn wrapping_divide(a: i32, b: i32): i32 { a / b }";
+
+/// Whether Node.js runs here, to run the module with. The tests are skipped
+/// where it doesn't.
+fn available() -> bool {
+    Command::new("node").arg("--version").output().is_ok()
+}
+
+/// What the `calls` of the test `name` give, evaluated one after the other
+/// by Node.js with the exports of `SOURCE` as `e`, where `panicked(call)`
+/// gives the message of the panic `call` ends in.
+fn run(name: &str, calls: &[&str]) -> Vec<String> {
+    let module = parse(tokenize(SOURCE)).expect("the source parses");
+    let (artifacts, errors) = compile_artifacts(module, &CompileOptions::default());
+    assert!(errors.is_empty(), "{:?}", errors);
+    let wasm = std::env::temp_dir().join(format!("rano-{}-{}.wasm", name, std::process::id()));
+    fs::write(&wasm, artifacts.wasm).unwrap();
+    let script = format!(
+        "const fs = require('fs');\n\
+         const module = new WebAssembly.Module(fs.readFileSync(process.argv[1]));\n\
+         const e = new WebAssembly.Instance(module, {{}}).exports;\n\
+         const words = new Int32Array(e.memory.buffer);\n\
+         const text = (string) => {{\n\
+             const length = new DataView(e.memory.buffer).getInt32(string, true);\n\
+             return new TextDecoder().decode(new Uint8Array(e.memory.buffer, string + 4, length));\n\
+         }};\n\
+         const panicked = (call) => {{\n\
+             try {{ return call(); }} catch (error) {{ return text(words[3]); }}\n\
+         }};\n\
+         for (const call of [{}]) {{ console.log(String(call())); }}\n",
+        calls
+            .iter()
+            .map(|call| format!("() => {}", call))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let output = Command::new("node")
+        .args(["-e", &script])
+        .arg(&wasm)
+        .output()
+        .unwrap();
+    fs::remove_file(wasm).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn arithmetic_does_what_the_overflow_says() {
+    if !available() {
+        return;
+    }
+    let results = run(
+        "overflow",
+        &[
+            "panicked(() => e.trapping(2147483647))",
+            "e.wrapping(2147483647)",
+            "e.saturating(2000000000)",
+            "e.saturating(-2000000000)",
+        ],
+    );
+    assert_eq!(
+        results,
+        [
+            "attempt to add with overflow",
+            "-2147483648",
+            "2147483647",
+            "-2147483648"
+        ]
+    );
+}
+
+#[test]
+fn panics_keep_their_message_and_location_for_the_host() {
+    if !available() {
+        return;
+    }
+    let results = run("panic", &["panicked(() => e.fail())", "text(words[4])"]);
+    // the file isn't named, and the source is a single line.
+    let column = SOURCE.find("panic(").unwrap() + 1;
+    assert_eq!(
+        results,
+        ["no".to_owned(), format!("<unknown>:1:{}", column)]
+    );
+}
+
+#[test]
+fn division_truncates_and_checks_its_divisor() {
+    if !available() {
+        return;
+    }
+    let results = run(
+        "division",
+        &[
+            "e.divide(-7, 2)",
+            "e.remainder(-7, 2)",
+            "panicked(() => e.divide(1, 0))",
+            "panicked(() => e.remainder(1, 0))",
+            "panicked(() => e.divide(-2147483648, -1))",
+            "e.wrapping_divide(-2147483648, -1)",
+        ],
+    );
+    assert_eq!(
+        results,
+        [
+            "-3",
+            "-1",
+            "attempt to divide by zero",
+            "attempt to calculate the remainder with a divisor of zero",
+            "attempt to divide with overflow",
+            "-2147483648",
+        ]
+    );
+}
@@ -0,0 +1,170 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use libranoc::{
+    codegen::{compile_artifacts, compile_with, CompileOptions, Overflow, C},
+    syntax::{parse, tokenize},
+};
+
+/// Named like functions of `<stdlib.h>`, whatever they do, to check they
+/// don't clash with them.
+const SOURCE: &str = "pub fn div(a: i32, b: i32): i32 { a / b } \
+                      pub fn ldiv(a: i64, b: i64): i64 { a / b } \
+                      pub fn abs(a: i32, b: i32): i32 { a % b }";
+
+/// The calls made to the functions of `SOURCE`, by the bits of their
+/// integers, or `0` for the remainder, and their arguments.
+const CASES: &[(u32, i64, i64)] = &[
+    (32, 7, 2),
+    (32, -7, 2),
+    (32, i32::MIN as i64, -1),
+    (32, i32::MAX as i64, -1),
+    (32, i32::MIN as i64, 1),
+    (32, 5, 0),
+    (64, -9, 4),
+    (64, i64::MIN, -1),
+    (64, i64::MAX, -1),
+    (64, 5, 0),
+    (0, -7, 2),
+    (0, i32::MIN as i64, -1),
+    (0, 5, 0),
+];
+
+/// Whether `program` runs here. The tests comparing the backends are
+/// skipped where a C compiler or Node.js to run the WASM module with isn't.
+fn available(program: &str) -> bool {
+    Command::new(program).arg("--version").output().is_ok()
+}
+
+/// A directory of its own for the files of `name`.
+fn directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("rano-c-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// What each of the `CASES` gives compiled to C with `overflow`, or `trap`.
+fn run_c(overflow: Overflow, directory: &Path) -> Vec<String> {
+    let options = CompileOptions {
+        overflow,
+        ..CompileOptions::default()
+    };
+    let module = parse(tokenize(SOURCE)).expect("the source parses");
+    let (artifacts, errors) = compile_with(module, &options, &C);
+    assert!(errors.is_empty(), "{:?}", errors);
+    fs::write(directory.join("main.c"), artifacts.c.unwrap()).unwrap();
+    fs::write(directory.join("main.h"), artifacts.c_header.unwrap()).unwrap();
+    // the header is all a program calling the functions needs.
+    fs::write(
+        directory.join("run.c"),
+        "#include <stdio.h>\n#include <stdlib.h>\n#include \"main.h\"\n\
+         int main(int argc, char **argv) {\n\
+             int64_t a = strtoll(argv[2], 0, 10), b = strtoll(argv[3], 0, 10);\n\
+             if (argv[1][0] == '3') printf(\"%d\\n\", rano_fn_div((int32_t)a, (int32_t)b));\n\
+             else if (argv[1][0] == '0') printf(\"%d\\n\", rano_fn_abs((int32_t)a, (int32_t)b));\n\
+             else printf(\"%lld\\n\", (long long)rano_fn_ldiv(a, b));\n\
+             return 0;\n\
+         }\n",
+    )
+    .unwrap();
+    let status = Command::new("cc")
+        .current_dir(directory)
+        .args([
+            "-std=c99", "-Wall", "-Werror", "main.c", "run.c", "-o", "run",
+        ])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    CASES
+        .iter()
+        .map(|(bits, a, b)| {
+            let output = Command::new(directory.join("run"))
+                .args([bits.to_string(), a.to_string(), b.to_string()])
+                .output()
+                .unwrap();
+            if output.status.success() {
+                String::from_utf8(output.stdout).unwrap().trim().to_owned()
+            } else {
+                "trap".to_owned()
+            }
+        })
+        .collect()
+}
+
+/// What each of the `CASES` gives compiled to WASM with `overflow` and run
+/// by Node.js, or `trap`.
+fn run_wasm(overflow: Overflow, directory: &Path) -> Vec<String> {
+    let options = CompileOptions {
+        overflow,
+        ..CompileOptions::default()
+    };
+    let module = parse(tokenize(SOURCE)).expect("the source parses");
+    let (artifacts, errors) = compile_artifacts(module, &options);
+    assert!(errors.is_empty(), "{:?}", errors);
+    let wasm = directory.join("main.wasm");
+    fs::write(&wasm, artifacts.wasm).unwrap();
+    let calls = CASES
+        .iter()
+        .map(|(bits, a, b)| match bits {
+            32 => format!("() => e.div({}, {})", a, b),
+            0 => format!("() => e.abs({}, {})", a, b),
+            _ => format!("() => e.ldiv({}n, {}n)", a, b),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let script = format!(
+        "const fs = require('fs');\n\
+         WebAssembly.instantiate(fs.readFileSync(process.argv[1])).then(({{ instance }}) => {{\n\
+             const e = instance.exports;\n\
+             for (const call of [{}]) {{\n\
+                 try {{ console.log(String(call())); }} catch (error) {{ console.log('trap'); }}\n\
+             }}\n\
+         }});\n",
+        calls
+    );
+    let output = Command::new("node")
+        .args(["-e", &script])
+        .arg(&wasm)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn division_does_what_the_wasm_backend_does() {
+    if !available("cc") || !available("node") {
+        return;
+    }
+    for (overflow, name) in [
+        (Overflow::Trap, "trap"),
+        (Overflow::Wrap, "wrap"),
+        (Overflow::Saturate, "saturate"),
+    ] {
+        let directory = directory(name);
+        let c = run_c(overflow, &directory);
+        assert_eq!(c, run_wasm(overflow, &directory), "{}", name);
+        // the minimum divided by `-1`.
+        let expected = match overflow {
+            Overflow::Trap => ("trap".to_owned(), "trap".to_owned()),
+            Overflow::Wrap => (i32::MIN.to_string(), i64::MIN.to_string()),
+            Overflow::Saturate => (i32::MAX.to_string(), i64::MAX.to_string()),
+        };
+        assert_eq!((c[2].clone(), c[7].clone()), expected, "{}", name);
+        let remainder = if overflow == Overflow::Trap {
+            "trap"
+        } else {
+            "0"
+        };
+        assert_eq!(c[11], remainder, "{}", name);
+        assert_eq!((&c[5][..], &c[9][..], &c[12][..]), ("trap", "trap", "trap"));
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+use std::{fs, process::Command};
+
+use libranoc::{
+    codegen::{compile_artifacts, CompileOptions},
+    syntax::{parse, tokenize},
+};
+
+const SOURCE: &str = "fn next(c: i32, s: i32): i32 { s * 10 + receive(c) } \
+                      pub fn unbounded(): i32 { \
+                          let c = channel(0); \
+                          send(c, 1); send(c, 2); send(c, 3); \
+                          let s = receive(c); \
+                          send(c, 4); send(c, 5); send(c, 6); send(c, 7); send(c, 8); \
+                          let s = next(c, next(c, next(c, next(c, next(c, next(c, next(c, s))))))); \
+                          close(c); \
+                          s \
+                      } \
+                      pub fn shared(): i32 { let c = channel(2); let d = share(c); close(c); d } \
+                      pub fn echo(c: i32, value: i32): i32 { send(c, value); receive(c) } \
+                      pub fn closed(c: i32): i32 { close(c) }";
+
+/// Whether Node.js runs here, to run the module with. The tests are skipped
+/// where it doesn't.
+fn available() -> bool {
+    Command::new("node").arg("--version").output().is_ok()
+}
+
+/// What the `calls` of the test `name` give, evaluated one after the other
+/// by Node.js with the exports of `SOURCE` as `e` and its memory as the
+/// words `words`.
+fn run(name: &str, calls: &[&str]) -> Vec<String> {
+    let mut options = CompileOptions::default();
+    options.target.enable("threads");
+    let module = parse(tokenize(SOURCE)).expect("the source parses");
+    let (artifacts, errors) = compile_artifacts(module, &options);
+    assert!(errors.is_empty(), "{:?}", errors);
+    let wasm = std::env::temp_dir().join(format!("rano-{}-{}.wasm", name, std::process::id()));
+    fs::write(&wasm, artifacts.wasm).unwrap();
+    let script = format!(
+        "const fs = require('fs');\n\
+         const memory = new WebAssembly.Memory({{ initial: 17, maximum: 16384, shared: true }});\n\
+         const module = new WebAssembly.Module(fs.readFileSync(process.argv[1]));\n\
+         const e = new WebAssembly.Instance(module, {{ env: {{ memory }} }}).exports;\n\
+         const words = new Int32Array(memory.buffer);\n\
+         let c;\n\
+         for (const call of [{}]) {{ console.log(String(call())); }}\n",
+        calls
+            .iter()
+            .map(|call| format!("() => {}", call))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let output = Command::new("node")
+        .args(["-e", &script])
+        .arg(&wasm)
+        .output()
+        .unwrap();
+    fs::remove_file(wasm).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn unbounded_channels_grow_keeping_their_order() {
+    if !available() {
+        return;
+    }
+    // the ring of 4 slots is full with its oldest value in the second.
+    assert_eq!(run("unbounded", &["e.unbounded()"]), vec!["12345678"]);
+}
+
+#[test]
+fn channels_are_freed_with_their_last_reference() {
+    if !available() {
+        return;
+    }
+    // the second word of the memory is the first freed block.
+    let results = run(
+        "freed",
+        &[
+            "c = e.shared()",
+            "e.echo(c, 7)",
+            "words[1]",
+            "e.closed(c)",
+            "words[1] != 0",
+        ],
+    );
+    assert_eq!(&results[1..], ["7", "0", "0", "true"]);
+}
@@ -0,0 +1,96 @@
+use std::{fs, process::Command};
+
+use libranoc::{
+    codegen::{compile_artifacts, CompileOptions},
+    syntax::{parse, tokenize},
+};
+
+const SOURCE: &str = "fn apply(f: (i32) -> i32, x: i32): i32 { f(x) } \
+                      fn double(x: i32): i32 { x * 2 } \
+                      fn adder(n: i32): (i32) -> i32 { move |x: i32| x + n } \
+                      pub fn copied(): i32 { let a = 40; let g = |x: i32| x + a; a = 0; apply(g, 2) } \
+                      pub fn moved(): i32 { let add = adder(5); add(10) } \
+                      pub fn item(): i32 { apply(double, 21) } \
+                      pub fn assigned(): i32 { \
+                          let a = 1; \
+                          let set = |x: i32| { a = x; }; \
+                          set(5); set(7); \
+                          a \
+                      } \
+                      pub fn nested(): i32 { \
+                          let a = 1; \
+                          let outer = |x: i32| { let inner = |y: i32| { a = a + x + y; }; inner(10); }; \
+                          outer(100); \
+                          a \
+                      }";
+
+/// Whether Node.js runs here, to run the module with. The tests are skipped
+/// where it doesn't.
+fn available() -> bool {
+    Command::new("node").arg("--version").output().is_ok()
+}
+
+/// What the `calls` of the test `name` give, evaluated one after the other
+/// by Node.js with the exports of `SOURCE` as `e` and its memory as the
+/// words `words`.
+fn run(name: &str, calls: &[&str]) -> Vec<String> {
+    let module = parse(tokenize(SOURCE)).expect("the source parses");
+    let (artifacts, errors) = compile_artifacts(module, &CompileOptions::default());
+    assert!(errors.is_empty(), "{:?}", errors);
+    let wasm = std::env::temp_dir().join(format!("rano-{}-{}.wasm", name, std::process::id()));
+    fs::write(&wasm, artifacts.wasm).unwrap();
+    let script = format!(
+        "const fs = require('fs');\n\
+         const module = new WebAssembly.Module(fs.readFileSync(process.argv[1]));\n\
+         const e = new WebAssembly.Instance(module, {{}}).exports;\n\
+         const words = new Int32Array(e.memory.buffer);\n\
+         for (const call of [{}]) {{ console.log(String(call())); }}\n",
+        calls
+            .iter()
+            .map(|call| format!("() => {}", call))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let output = Command::new("node")
+        .args(["-e", &script])
+        .arg(&wasm)
+        .output()
+        .unwrap();
+    fs::remove_file(wasm).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn closures_capture_copies_by_value() {
+    if !available() {
+        return;
+    }
+    // what is assigned to `a` after `g` is made isn't seen by it.
+    let results = run("value", &["e.copied()", "e.moved()", "e.item()"]);
+    assert_eq!(results, ["42", "15", "42"]);
+}
+
+#[test]
+fn closures_capture_locals_by_reference() {
+    if !available() {
+        return;
+    }
+    // `inner` assigns to `a` through the environment of `outer`.
+    let results = run("reference", &["e.assigned()", "e.nested()"]);
+    assert_eq!(results, ["7", "111"]);
+}
+
+#[test]
+fn environments_are_freed_with_their_last_reference() {
+    if !available() {
+        return;
+    }
+    // the second word of the memory is the first freed block.
+    let results = run("freed", &["words[1]", "e.copied()", "words[1] != 0"]);
+    assert_eq!(results, ["0", "42", "true"]);
+}
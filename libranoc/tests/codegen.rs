@@ -0,0 +1,718 @@
+use libranoc::{
+    codegen::{
+        compile_artifacts, read_meta, Cache, CompileOptions, Linker, OptLevel, TargetOptions,
+        FEATURES, META_SECTION,
+    },
+    core::Location,
+    syntax::{parse, tokenize},
+};
+
+/// The text of `source` compiled, which printing it validates, failing on
+/// any error.
+fn compile(source: &str) -> String {
+    compile_with(source, CompileOptions::default())
+}
+
+fn compile_with(source: &str, options: CompileOptions) -> String {
+    let options = CompileOptions {
+        text: true,
+        ..options
+    };
+    let module = parse(tokenize(source)).expect("the source parses");
+    let (artifacts, errors) = compile_artifacts(module, &options);
+    let messages: Vec<_> = errors.iter().map(|error| &error.message).collect();
+    assert!(messages.is_empty(), "{:?}", messages);
+    artifacts.text.expect("the text is printed")
+}
+
+/// The text of `source` compiled for a target with the proposals
+/// `features`.
+fn compile_for(source: &str, features: &[&str]) -> String {
+    let mut options = CompileOptions::default();
+    for feature in features {
+        assert!(options.target.enable(feature), "{}", feature);
+    }
+    compile_with(source, options)
+}
+
+/// The messages of the errors compiling `source` fails with.
+fn compile_errors(source: &str) -> Vec<String> {
+    let module = parse(tokenize(source)).expect("the source parses");
+    let (_, errors) = compile_artifacts(module, &CompileOptions::default());
+    errors.into_iter().map(|error| error.message).collect()
+}
+
+#[test]
+fn use_declarations_compile_to_nothing() {
+    let text = compile("use a::{b, c as d}; pub fn f(): i32 { 1 }");
+    assert!(text.contains("(export \"f\""));
+}
+
+#[test]
+fn module_items_are_named_by_their_path() {
+    let text = compile(
+        "pub mod m { pub fn f(a: i32): i32 { a + g() } fn g(): i32 { 1 } \
+         pub mod n { pub fn h(): i32 { 5 } } }
+         mod private { pub fn hidden(): i32 { 0 } }
+         use m::f;
+         use m::n::h;
+         pub fn main(): i32 { f(1) + h() }",
+    );
+    assert!(text.contains("(export \"m::f\""));
+    assert!(text.contains("(export \"m::n::h\""));
+    assert!(!text.contains("(export \"private::hidden\""));
+    assert!(text.contains("(func $m::f"));
+}
+
+#[test]
+fn generic_functions_are_reported() {
+    let errors = compile_errors("fn id<T>(x: T): T { x } pub fn f(): i32 { id(1) }");
+    assert_eq!(errors, vec!["Unimplemented.", "Unimplemented."]);
+}
+
+#[test]
+fn prefix_operators_compile() {
+    let text = compile(
+        "pub fn f(a: i32, b: bool, c: f64, d: i64): i32 { \
+         let x = -a; let y = !b; let z = -c; let w = !a; let v = -d; +x }",
+    );
+    assert!(text.contains("f64.neg"));
+    assert!(text.contains("i32.eqz"));
+}
+
+#[test]
+fn constructs_codegen_lacks_are_reported() {
+    for source in &[
+        "pub fn f(): i32 { match 1 { _ => 2 } }",
+        "pub fn f(): i32 { let a = [1, 2]; a[0] }",
+        "pub fn f(): i32 { let c = 'a'; 1 }",
+        "#![x] pub fn f(): i32 { 1 }",
+    ] {
+        assert_eq!(compile_errors(source), vec!["Unimplemented."], "{}", source);
+    }
+}
+
+#[test]
+fn parameters_captured_by_reference_are_reported() {
+    // only a `let` local can be placed in the frame.
+    assert_eq!(
+        compile_errors("pub fn f(a: i32): i32 { let g = || { a = 2; }; g(); a }"),
+        vec!["Unimplemented."]
+    );
+}
+
+#[test]
+fn constant_operations_are_folded() {
+    let folded = |source| {
+        let options = CompileOptions {
+            opt_level: OptLevel::O1,
+            ..CompileOptions::default()
+        };
+        compile_with(source, options)
+    };
+    assert!(folded("pub fn f(): i32 { 6 * 7 }").contains("i32.const 42"));
+    assert!(folded("pub fn f(): bool { !(1 < 2) || 3 == 3 }").contains("i32.const 1"));
+    assert_eq!(
+        compile_errors("pub fn f(): i32 { 2147483647 + 1 }"),
+        vec!["This constant expression overflows `i32`."]
+    );
+    assert_eq!(
+        compile_errors("pub fn f(): i32 { 1 % 0 }"),
+        vec!["This constant expression divides by zero."]
+    );
+    assert_eq!(
+        compile_errors("pub fn f(): i32 { 1 << 32 }"),
+        vec!["This constant expression overflows `i32`."]
+    );
+}
+
+#[test]
+fn only_what_fits_an_i32_is_sent_to_tasks() {
+    let text = compile("fn task(n: i32): i32 { n * 2 } pub fn f(): i32 { join(spawn(task, 21)) }");
+    assert!(text.contains("(import \"thread\" \"spawn\""));
+    assert_eq!(
+        compile_errors("fn wide(n: i64): i64 { n } pub fn f(): i32 { join(spawn(wide, 1)) }"),
+        vec!["`i64` cannot be sent to another thread."]
+    );
+    assert_eq!(
+        compile_errors(
+            "fn pair(n: i32): (i32, i32) { (n, n) } pub fn f(): i32 { join(spawn(pair, 1)) }"
+        ),
+        vec!["`(i32, i32)` cannot be sent to another thread."]
+    );
+}
+
+#[test]
+fn what_cannot_be_sent_is_reported_where_it_is_given() {
+    let ranges = |source: &str| -> Vec<_> {
+        let module = parse(tokenize(source)).expect("the source parses");
+        let (_, errors) = compile_artifacts(module, &CompileOptions::default());
+        errors
+            .iter()
+            .flat_map(|error| &error.labels)
+            .map(|label| match &label.location {
+                Location::Known(span) => span.range.clone(),
+                _ => panic!("the label has no location"),
+            })
+            .collect()
+    };
+    // the argument is checked first, then the result.
+    assert_eq!(
+        ranges("fn wide(n: i64): i64 { n } pub fn f(): i32 { join(spawn(wide, 1)) }"),
+        vec![62..63]
+    );
+    assert_eq!(
+        ranges("fn half(n: i32): f32 { 0.5 } pub fn f(): i32 { join(spawn(half, 1)) }"),
+        vec![58..62]
+    );
+    assert_eq!(
+        compile_errors("fn two(a: i32, b: i32): i32 { a } pub fn f(): i32 { join(spawn(two, 1)) }"),
+        vec!["`two` takes 2 argument(s) but 1 were supplied."]
+    );
+}
+
+#[test]
+fn scopes_join_the_tasks_spawned_in_them() {
+    let text = compile(
+        "fn task(n: i32): i32 { n * 2 } \
+         fn both(n: i32): i32 { spawn(task, n); spawn(task, n + 1); n } \
+         pub fn f(): i32 { scope(both, 1) + 1 }",
+    );
+    assert!(text.contains("(import \"thread\" \"scope\""));
+    assert!(text.contains("(import \"thread\" \"join_scope\""));
+    assert!(text.contains("call $both"));
+    // a task may hold anything, as it runs on the thread of the scope.
+    compile("fn task(s: string): string { s } pub fn f(): string { scope(task, \"a\") }");
+    assert_eq!(
+        compile_errors("fn task(n: i32): i32 { n } pub fn f(): bool { scope(task, 1) }"),
+        vec!["Mismatched types."]
+    );
+}
+
+#[test]
+fn channels_wait_on_the_shared_memory() {
+    let mut options = CompileOptions::default();
+    options.target.enable("threads");
+    let text = compile_with(
+        "pub fn f(n: i32): i32 { let c = channel(2); send(c, n); receive(select(c, c)) }",
+        options,
+    );
+    assert!(text.contains("memory.atomic.wait32"));
+    assert!(!text.contains("(import \"channel\""));
+    assert_eq!(
+        compile_errors("pub fn f(): i32 { channel(1) }"),
+        vec!["`channel` needs the `threads` target."]
+    );
+}
+
+#[test]
+fn todos_and_overflows_panic_through_the_runtime() {
+    let text = compile(r#"pub fn f(n: i32): i32 { n + 1 } pub fn g(): i32 { todo "later" }"#);
+    assert!(!text.contains("(import \"rano\""));
+    assert!(text.contains("not yet implemented: later"));
+    assert!(text.contains("attempt to add with overflow"));
+}
+
+#[test]
+fn tuples_and_arrays_are_compared_by_their_elements() {
+    let text = compile(r#"pub fn f(): bool { [("a", 1)] == [("a", 1)] }"#);
+    assert!(text.contains("call $rano_string_eq"));
+    let text = compile("pub fn f(): bool { ([1, 2], 3) != ([1, 2], 4) }");
+    assert!(text.contains("(func $rano_eq__Array<i32>"));
+    assert!(text.contains("call $rano_eq__Array<i32>"));
+}
+
+#[test]
+fn type_arguments_are_counted() {
+    assert_eq!(
+        compile_errors(
+            "fn g<T>(a: T): T { a } fn h(a: i32): i32 { a } \
+             pub fn f(): i32 { g::<i32, i64>(1) + h::<i32>(2) }"
+        ),
+        vec![
+            "`g` takes 1 type argument(s) but 2 were supplied.",
+            "`h` takes 0 type argument(s) but 1 were supplied."
+        ]
+    );
+}
+
+#[test]
+fn only_strings_are_interpolated() {
+    let text = compile(r#"pub fn f(a: string): string { "a {a} b" }"#);
+    assert!(text.contains("call $rano_string_concat"));
+
+    let source = r#"pub fn f(a: i32): string { "a {a} b" }"#;
+    let module = parse(tokenize(source)).expect("the source parses");
+    let (_, errors) = compile_artifacts(module, &CompileOptions::default());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "Mismatched types.");
+    let label = &errors[0].labels[0];
+    assert_eq!(
+        label.message.as_deref(),
+        Some("Required `string` but got `i32`")
+    );
+    assert!(matches!(&label.location, Location::Known(span) if span.range == (31..32)));
+}
+
+#[test]
+fn names_are_resolved_in_their_scope() {
+    assert_eq!(
+        compile_errors("pub fn f(): i32 { x }"),
+        vec!["Undefined symbol `x`."]
+    );
+    // a shadowed local is read in the initializer of the one shadowing it.
+    compile("pub fn f(): i32 { let a = 1; let a = a + 1; a }");
+}
+
+#[test]
+fn mismatched_types_are_reported() {
+    assert_eq!(
+        compile_errors("pub fn f(): string { 1 }"),
+        vec!["Mismatched types."]
+    );
+}
+
+#[test]
+fn integer_literals_are_typed_by_what_is_expected() {
+    assert!(compile("pub fn f(): i64 { 1 }").contains("i64.const 1"));
+}
+
+#[test]
+fn optionals_are_not_used_as_their_values() {
+    assert_eq!(
+        compile_errors("pub fn f(a: i32?): i32 { a + 1 }"),
+        vec!["Mismatched types."]
+    );
+}
+
+#[test]
+fn reads_of_unassigned_locals_are_reported() {
+    assert_eq!(
+        compile_errors("pub fn f(): i32 { let a: i32; a }"),
+        vec!["`a` may be read before it is assigned."]
+    );
+}
+
+#[test]
+fn uses_after_a_move_are_reported() {
+    assert_eq!(
+        compile_errors("pub fn f(a: string): string { let b = a; a }"),
+        vec!["`a` is used after it was moved."]
+    );
+}
+
+#[test]
+fn intrinsics_compile_to_instructions() {
+    assert!(compile("pub fn f(): i32 { @size_of<i64>() }").contains("i32.const 8"));
+}
+
+#[test]
+fn calls_are_checked_against_the_parameters() {
+    assert_eq!(
+        compile_errors("fn g(a: i32): i32 { a } pub fn f(): i32 { g(1, 2) }").len(),
+        1
+    );
+    assert_eq!(
+        compile_errors("fn g(a: i32): i32 { a } pub fn f(): i32 { g(true) }").len(),
+        1
+    );
+}
+
+#[test]
+fn strings_are_concatenated_by_the_runtime() {
+    let text = compile(r#"pub fn f(a: string): string { a + "b" }"#);
+    assert!(text.contains("call $rano_string_concat"));
+    assert!(text.contains("(export \"rano_malloc\""));
+}
+
+#[test]
+fn the_wasi_target_writes_through_fd_write() {
+    let mut options = CompileOptions::default();
+    options.target.wasi = true;
+    let source = r#"pub fn f() { @println("hi") }"#;
+    let text = compile_with(source, options);
+    assert!(text.contains("(import \"wasi_snapshot_preview1\" \"fd_write\""));
+    assert_eq!(compile_errors(source).len(), 1);
+}
+
+#[test]
+fn tuples_are_returned_as_multiple_values() {
+    let source = "pub fn f(): (i32, i64) { (1, 2) }";
+    assert!(compile_for(source, &["multi-value"]).contains("(result i32 i64)"));
+    assert!(!compile_for(source, &[]).contains("(result i32 i64)"));
+}
+
+#[test]
+fn self_calls_in_tail_position_become_tail_calls() {
+    let source = "pub fn f(n: i32): i32 { if n == 0 { 0 } else { f(n - 1) } } \
+                  extern fn PartialEq__i32_i32(a: i32, b: i32): bool;";
+    assert!(compile_for(source, &["tail-call"]).contains("return_call $f"));
+    assert!(!compile(source).contains("return_call"));
+}
+
+#[test]
+fn division_checks_for_zero_and_overflow() {
+    let text = compile("pub fn f(a: i32, b: i32): i32 { a / b }");
+    assert!(text.contains("attempt to divide by zero"));
+    assert!(text.contains("attempt to divide with overflow"));
+}
+
+#[test]
+fn constant_array_literals_are_placed_once_in_the_data() {
+    let text = compile("pub fn f(): bool { [7, 8] == [7, 8] }");
+    assert_eq!(
+        text.matches(r#""\02\00\00\00\07\00\00\00\08\00\00\00""#)
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn bindings_are_made_when_asked() {
+    let module = parse(tokenize("pub fn f(a: string): i32 { 1 }")).unwrap();
+    let options = CompileOptions {
+        bindings: true,
+        ..CompileOptions::default()
+    };
+    let (artifacts, errors) = compile_artifacts(module, &options);
+    assert!(errors.is_empty());
+    assert!(artifacts
+        .bindings
+        .unwrap()
+        .dts
+        .contains("f(a: string): number"));
+}
+
+#[test]
+fn components_are_made_when_asked() {
+    let module = parse(tokenize("pub fn f(a: string): i32 { 1 }")).unwrap();
+    let options = CompileOptions {
+        component: Some("w".to_owned()),
+        ..CompileOptions::default()
+    };
+    let (artifacts, errors) = compile_artifacts(module, &options);
+    assert!(errors.is_empty());
+    assert!(artifacts.component.unwrap().wit.contains("world w"));
+}
+
+#[test]
+fn extern_functions_are_imported_where_they_are_linked() {
+    let text = compile(
+        r#"#[link(module = "env", name = "twice")] extern fn double(a: i32): i32;
+           pub fn f(): i32 { double(1) }"#,
+    );
+    assert!(text.contains("(import \"env\" \"twice\""));
+}
+
+#[test]
+fn recursion_is_guarded_when_asked() {
+    let source = "pub fn f(n: i32): i32 { f(n) }";
+    let options = CompileOptions {
+        max_call_depth: Some(100),
+        ..CompileOptions::default()
+    };
+    assert!(compile_with(source, options).contains("stack overflow"));
+    assert!(!compile(source).contains("stack overflow"));
+}
+
+#[test]
+fn vectors_compile_to_simd() {
+    let text = compile_for("pub fn f(a: i32x4, b: i32x4): i32x4 { a + b }", &["simd"]);
+    assert!(text.contains("i32x4.add"));
+}
+
+#[test]
+fn panics_are_thrown_with_exception_handling() {
+    // the text format can't be printed with tags.
+    let module = parse(tokenize(r#"pub fn f(): i32 { todo "later" }"#)).unwrap();
+    let mut options = CompileOptions::default();
+    options.target.enable("exception-handling");
+    let (artifacts, errors) = compile_artifacts(module, &options);
+    assert!(errors.is_empty());
+    let tag = b"rano_panic_tag";
+    assert!(artifacts.wasm.windows(tag.len()).any(|bytes| bytes == tag));
+}
+
+#[test]
+fn locals_are_declared_and_function_types_shared() {
+    let text =
+        compile("pub fn f(a: i32): i32 { let b = a; b = b * 3; b } pub fn g(a: i32): i32 { a }");
+    assert!(text.contains("(param $a i32) (result i32)\n    (local $b i32)"));
+    assert_eq!(text.matches("(func (param i32) (result i32))").count(), 1);
+}
+
+#[test]
+fn names_and_line_info_are_emitted() {
+    let wasm = |dwarf| {
+        let module = parse(tokenize(
+            "pub fn f(a: i32): i32 { let counter = a; counter }",
+        ))
+        .unwrap();
+        let options = CompileOptions {
+            dwarf,
+            file_names: vec!["main.rano".to_owned()],
+            ..CompileOptions::default()
+        };
+        let (artifacts, errors) = compile_artifacts(module, &options);
+        assert!(errors.is_empty());
+        artifacts.wasm
+    };
+    let has = |wasm: &[u8], bytes: &[u8]| wasm.windows(bytes.len()).any(|window| window == bytes);
+    let names = wasm(false);
+    assert!(has(&names, b"\x04name"));
+    assert!(has(&names, b"counter"));
+    assert!(!has(&names, b".debug_line"));
+    let lines = wasm(true);
+    assert!(has(&lines, b".debug_line"));
+    assert!(has(&lines, b"main.rano"));
+}
+
+#[test]
+fn source_maps_are_referred_to_by_the_module() {
+    let module = parse(tokenize("pub fn f(a: i32): i32 {\n    a * 2\n}")).unwrap();
+    let options = CompileOptions {
+        file_names: vec!["main.rano".to_owned()],
+        source_map: Some("main.wasm.map".to_owned()),
+        ..CompileOptions::default()
+    };
+    let (artifacts, errors) = compile_artifacts(module, &options);
+    assert!(errors.is_empty());
+    let map = artifacts.source_map.expect("the source map is made");
+    assert!(map.contains(r#""version":3"#));
+    assert!(map.contains(r#""sources":["main.rano"]"#));
+    let url = b"\x10sourceMappingURL\x0dmain.wasm.map";
+    assert!(artifacts.wasm.windows(url.len()).any(|bytes| bytes == url));
+}
+
+#[test]
+fn the_text_format_is_printed_when_asked() {
+    let source = "pub fn f(): i32 { 1 }";
+    assert!(compile(source).starts_with("(module\n"));
+    let (artifacts, _) =
+        compile_artifacts(parse(tokenize(source)).unwrap(), &CompileOptions::default());
+    assert_eq!(artifacts.text, None);
+}
+
+#[test]
+fn proposals_are_enabled_by_their_names() {
+    let mut target = TargetOptions::default();
+    for feature in FEATURES {
+        assert!(!target.enables(feature));
+    }
+    assert!(target.enable("threads"));
+    assert!(target.enables("threads") && target.enables("bulk-memory"));
+    assert!(!target.enable("gc"));
+    assert_eq!(
+        compile_errors("pub fn f(a: i32x4): i32x4 { a }"),
+        vec!["`i32x4` needs the `simd` target."]
+    );
+    let source = "pub fn f(a: string): string { @string_slice(a, 0, 1) }";
+    assert!(compile_for(source, &["bulk-memory"]).contains("memory.copy"));
+    assert!(!compile(source).contains("memory.copy"));
+}
+
+#[test]
+fn small_functions_are_inlined_at_o2() {
+    let source = "fn small(a: i32): i32 { a } #[inline(never)] fn kept(a: i32): i32 { a } \
+                  pub fn f(a: i32): i32 { small(a) + kept(a) }";
+    let at = |opt_level| {
+        let options = CompileOptions {
+            opt_level,
+            ..CompileOptions::default()
+        };
+        compile_with(source, options)
+    };
+    let inlined = at(OptLevel::O2);
+    assert!(!inlined.contains("call $small"));
+    assert!(inlined.contains("call $kept"));
+    assert!(at(OptLevel::O1).contains("call $small"));
+}
+
+#[test]
+fn what_nothing_exported_uses_is_left_out_at_o1() {
+    let source = "fn unused(): string { \"never printed\" } fn used(): i32 { 1 } \
+                  pub fn f(): i32 { used() }";
+    let at = |opt_level| {
+        let options = CompileOptions {
+            opt_level,
+            ..CompileOptions::default()
+        };
+        compile_with(source, options)
+    };
+    let eliminated = at(OptLevel::O1);
+    assert!(!eliminated.contains("$unused"));
+    assert!(!eliminated.contains("never printed"));
+    assert!(eliminated.contains("$used"));
+    let kept = at(OptLevel::O0);
+    assert!(kept.contains("$unused"));
+    assert!(kept.contains("never printed"));
+}
+
+#[test]
+fn obvious_sequences_are_simplified_at_o1() {
+    let source = "#[overflow(wrap)] pub fn f(a: i32): i32 { let b = a * 1; b }";
+    let at = |opt_level| {
+        let options = CompileOptions {
+            opt_level,
+            ..CompileOptions::default()
+        };
+        let text = compile_with(source, options);
+        let start = text.find("(func $f (").expect("`f` is compiled");
+        let end = text[start + 1..]
+            .find("(func")
+            .map_or(text.len(), |end| start + 1 + end);
+        text[start..end].to_string()
+    };
+    let simplified = at(OptLevel::O1);
+    assert!(simplified.contains("local.tee"));
+    assert!(!simplified.contains("i32.mul"));
+    let written = at(OptLevel::O0);
+    assert!(!written.contains("local.tee"));
+    assert!(written.contains("i32.mul"));
+}
+
+#[test]
+fn opt_levels_are_named_by_their_digit() {
+    assert_eq!(OptLevel::from_name("0"), Some(OptLevel::O0));
+    assert_eq!(OptLevel::from_name("2"), Some(OptLevel::O2));
+    assert_eq!(OptLevel::from_name("3"), None);
+    assert_eq!(OptLevel::default(), OptLevel::O0);
+    assert!(!OptLevel::O0.fold_constants() && OptLevel::O0.name_every_local());
+    assert!(OptLevel::O1.eliminate_dead_code() && !OptLevel::O1.inline());
+    assert!(OptLevel::O2.inline() && !OptLevel::O2.name_every_local());
+}
+
+#[test]
+fn modules_compiled_on_their_own_are_linked_into_one() {
+    let options = CompileOptions {
+        text: true,
+        ..CompileOptions::default()
+    };
+    let mut linker = Linker::new(&options);
+    let math = parse(tokenize("pub fn double(a: i32): i32 { a + a }")).unwrap();
+    assert!(linker.compile("math", math).is_empty());
+    let main = parse(tokenize(
+        r#"#[link(module = "math", name = "double")] extern fn double(a: i32): i32;
+           pub fn f(a: i32): i32 { double(a) }"#,
+    ))
+    .unwrap();
+    assert!(linker.compile("main", main).is_empty());
+    let (artifacts, errors) = linker.link();
+    assert!(errors.is_empty());
+    let text = artifacts.text.expect("the text is printed");
+    assert!(!text.contains("(import \"math\""));
+    assert!(text.contains("(export \"f\""));
+    assert!(text.contains("call $double"));
+    assert_eq!(text.matches("(func $rano_malloc ").count(), 1);
+}
+
+#[test]
+fn checked_modules_are_cached_by_their_contents() {
+    let directory = std::env::temp_dir().join(format!("rano-cache-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    let source = "pub fn f(a: i32): i32 { a + 1 }";
+    let at = |opt_level| {
+        let options = CompileOptions {
+            cache: Some(Cache::new(&directory)),
+            opt_level,
+            ..CompileOptions::default()
+        };
+        compile_with(source, options)
+    };
+    let entries = || std::fs::read_dir(&directory).map_or(0, |entries| entries.count());
+    let checked = at(OptLevel::O0);
+    assert_eq!(entries(), 1);
+    assert_eq!(at(OptLevel::O0), checked);
+    assert_eq!(entries(), 1);
+    // the levels which fold the constants keep their own.
+    at(OptLevel::O1);
+    assert_eq!(entries(), 2);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn address_taken_locals_live_in_frames_on_the_shadow_stack() {
+    let text = compile(
+        "pub fn f(): i32 { let a = 7; @address_of<i32>(a) } \
+         pub fn g(): i32 { let a = 7; a }",
+    );
+    let f = &text[text.find("(func $f (").unwrap()..text.find("(func $g (").unwrap()];
+    let g = &text[text.find("(func $g (").unwrap()..];
+    assert!(f.contains("(local $frame i32)"));
+    assert!(f.contains("i32.store"));
+    assert_eq!(f.matches("global.set 0").count(), 2);
+    assert!(!g.contains("$frame") && !g.contains("global.get 0"));
+    assert!(text.contains("stack overflow"));
+}
+
+#[test]
+fn the_exported_signatures_are_described_in_rano_meta() {
+    let module = parse(tokenize("pub fn f(a: i32, b: string): i64 { 1 } fn g() {}")).unwrap();
+    let (artifacts, errors) = compile_artifacts(module, &CompileOptions::default());
+    assert!(errors.is_empty());
+    let section = wasmparser::Parser::new(0)
+        .parse_all(&artifacts.wasm)
+        .find_map(|payload| match payload {
+            Ok(wasmparser::Payload::CustomSection { name, data, .. }) if name == META_SECTION => {
+                Some(data)
+            }
+            _ => None,
+        })
+        .expect("the section is emitted");
+    let meta = read_meta(section).unwrap();
+    assert_eq!(meta.compiler, env!("CARGO_PKG_VERSION"));
+    assert_eq!(meta.exports.len(), 1);
+    let f = &meta.exports[0];
+    assert_eq!(f.name, "f");
+    assert_eq!(
+        f.parameters,
+        [
+            ("a".to_owned(), "i32".to_owned()),
+            ("b".to_owned(), "string".to_owned())
+        ]
+    );
+    assert_eq!(f.return_type, "i64");
+}
+
+#[test]
+fn atomics_are_only_called_in_thread_safe_functions() {
+    let text = compile_for(
+        "#[thread_safe] pub fn f(a: i32): i32 { unsafe { @atomic_add(a, 1) + @atomic_load(a) } }",
+        &["threads"],
+    );
+    assert!(text.contains("i32.atomic.rmw.add"));
+    assert!(text.contains("i32.atomic.load"));
+    assert!(text.contains("shared"));
+    let errors = |source| {
+        let mut options = CompileOptions::default();
+        options.target.enable("threads");
+        let module = parse(tokenize(source)).expect("the source parses");
+        let (_, errors) = compile_artifacts(module, &options);
+        errors
+            .into_iter()
+            .map(|error| error.message)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        errors("pub fn f(a: i32): i32 { unsafe { @atomic_load(a) } }"),
+        vec!["`@atomic_load` is only allowed in a `#[thread_safe]` function."]
+    );
+    assert_eq!(
+        errors("#[thread_safe] pub fn f(a: string): i32 { 1 }"),
+        vec!["`string` points into the heap, which a `#[thread_safe]` function can't use."]
+    );
+    assert_eq!(
+        compile_errors("#[thread_safe] pub fn f(a: i32): i32 { unsafe { @atomic_load(a) } }"),
+        vec!["`@atomic_load` needs the `threads` target."]
+    );
+}
+
+#[test]
+fn unknown_targets_are_left_alone() {
+    let mut target = TargetOptions::default();
+    assert!(!target.wasi && !target.simd && !target.threads);
+    assert!(!target.enable("gc"));
+    assert_eq!(target, TargetOptions::default());
+    assert!(target.enable("simd"));
+    assert!(target.simd && !target.bulk_memory);
+}
@@ -0,0 +1,83 @@
+use std::{fs, process::Command};
+
+use libranoc::{
+    codegen::{compile_artifacts, CompileOptions},
+    syntax::{parse, tokenize},
+};
+
+const SOURCE: &str = "fn some(a: i32?): i32? { a } \
+                      pub fn argument(): i32? { some(1) } \
+                      pub fn wide(a: i64?): i64? { a } \
+                      pub fn long(): i64? { wide(5) } \
+                      pub fn pair(): (i32, f64)? { (2, 1.5) } \
+                      pub fn text(): string? { \"a\" }";
+
+/// Whether Node.js runs here, to run the module with. The tests are skipped
+/// where it doesn't.
+fn available() -> bool {
+    Command::new("node").arg("--version").output().is_ok()
+}
+
+/// What the `calls` of the exports of `SOURCE` as `e` give, evaluated by
+/// Node.js, with the values of a result of more than one joined by commas.
+fn run(calls: &[&str]) -> Vec<String> {
+    // the values of an optional are all given back with `multi-value`.
+    let mut options = CompileOptions::default();
+    options.target.enable("multi-value");
+    let module = parse(tokenize(SOURCE)).expect("the source parses");
+    let (artifacts, errors) = compile_artifacts(module, &options);
+    assert!(errors.is_empty(), "{:?}", errors);
+    let wasm = std::env::temp_dir().join(format!("rano-coercion-{}.wasm", std::process::id()));
+    fs::write(&wasm, artifacts.wasm).unwrap();
+    let script = format!(
+        "const fs = require('fs');\n\
+         WebAssembly.instantiate(fs.readFileSync(process.argv[1])).then(({{ instance }}) => {{\n\
+             const e = instance.exports;\n\
+             for (const call of [{}]) {{ console.log(String(call())); }}\n\
+         }});\n",
+        calls
+            .iter()
+            .map(|call| format!("() => {}", call))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let output = Command::new("node")
+        .args(["-e", &script])
+        .arg(&wasm)
+        .output()
+        .unwrap();
+    fs::remove_file(wasm).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn values_are_coerced_to_optionals() {
+    if !available() {
+        return;
+    }
+    // an optional of a value which isn't a pointer is whether it isn't
+    // `null`, then the value.
+    let results = run(&[
+        "e.argument()",
+        "e.long()",
+        "e.wide(0, 0n)",
+        "e.pair()",
+        "e.text() != 0",
+    ]);
+    assert_eq!(results, ["1,1", "1,5", "0,0", "1,2,1.5", "true"]);
+}
+
+#[test]
+fn values_are_not_coerced_to_trait_objects() {
+    // no impl tells which traits a type implements.
+    let source = "fn show(a: dyn Show): i32 { 1 } pub fn f(): i32 { show(1) }";
+    let module = parse(tokenize(source)).expect("the source parses");
+    let (_, errors) = compile_artifacts(module, &CompileOptions::default());
+    let messages: Vec<_> = errors.into_iter().map(|error| error.message).collect();
+    assert_eq!(messages, ["Mismatched types."]);
+}
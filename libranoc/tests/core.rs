@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+
+use libranoc::{
+    core::{
+        arena::Arena,
+        ast::{
+            build::{self, block, call, fn_, integer, name, ty},
+            print::print_module,
+            Block, FunctionDeclaration, Literal, Module,
+        },
+        to_json,
+        visit::{walk_block, walk_function_declaration, Visit},
+        visit_mut::VisitMut,
+        NodeId, NodeMap, SourceMap, Type, TypeId, Types,
+    },
+    syntax::{parse, parse_to_json, tokenize, TokenizeOptions},
+};
+
+/// The module `source` parses to.
+fn module(source: &str) -> Module {
+    parse(tokenize(source)).expect("the source parses")
+}
+
+#[test]
+fn visitors_reach_every_literal() {
+    #[derive(Default)]
+    struct Integers(Vec<u64>);
+    impl<'ast> Visit<'ast> for Integers {
+        fn visit_literal(&mut self, literal: &'ast Literal) {
+            if let Literal::Integer(integer) = literal {
+                self.0.extend(integer.value());
+            }
+        }
+    }
+    struct Double;
+    impl VisitMut for Double {
+        fn visit_literal(&mut self, literal: &mut Literal) {
+            if let Literal::Integer(integer) = literal {
+                integer.0.content = (integer.value().unwrap() * 2).to_string();
+            }
+        }
+    }
+
+    let mut module = module("fn f(a: i32): i32 { if a { 1 } else { g(2, (3, 4)) } }");
+    let mut integers = Integers::default();
+    integers.visit_module(&module);
+    assert_eq!(integers.0, vec![1, 2, 3, 4]);
+    Double.visit_module(&mut module);
+    assert!(print_module(&module).contains("g(4, (6, 8))"));
+}
+
+#[test]
+fn the_ast_is_serialized_into_json() {
+    let json = to_json(&module("fn f() {}")).unwrap();
+    assert!(json.starts_with(r#"{"nodes":[{"Statement":{"Declaration":{"FunctionDeclaration":{"#));
+    assert!(json.contains(
+        r#""name":{"kind":{"IdentifierIdentifier":"f"},"span":{"range":{"start":3,"end":4},"#
+    ));
+    assert_eq!(parse_to_json("fn f() {}").unwrap(), json);
+    // strings are escaped.
+    assert!(to_json(&module(r#"fn f() { "a\"b" }"#))
+        .unwrap()
+        .contains(r#""content":"\"a\\\"b\"""#));
+}
+
+#[test]
+fn nodes_are_given_ids_of_their_own() {
+    #[derive(Default)]
+    struct Ids(Vec<NodeId>);
+    impl<'ast> Visit<'ast> for Ids {
+        fn visit_function_declaration(&mut self, function: &'ast FunctionDeclaration) {
+            self.0.push(function.id);
+            walk_function_declaration(self, function);
+        }
+
+        fn visit_block(&mut self, block: &'ast Block) {
+            self.0.push(block.id);
+            walk_block(self, block);
+        }
+    }
+
+    let mut ids = Ids::default();
+    ids.visit_module(&module("fn f() { if a { 1 } else { 2 }; } fn g() { f() }"));
+    assert_eq!(ids.0.len(), 6);
+    assert!(!ids.0.contains(&NodeId::DUMMY));
+    let unique: HashSet<_> = ids.0.iter().collect();
+    assert_eq!(unique.len(), ids.0.len());
+
+    let mut map = NodeMap::new();
+    assert_eq!(map.insert(ids.0[0], "f"), None);
+    assert_eq!(map.insert(ids.0[0], "g"), Some("f"));
+    assert_eq!(map.get(ids.0[0]), Some(&"g"));
+    assert_eq!(map.get(ids.0[1]), None);
+}
+
+#[test]
+fn arenas_give_back_what_was_allocated() {
+    let mut arena = Arena::new();
+    let a = arena.alloc("a");
+    let b = arena.alloc("b");
+    assert_ne!(a, b);
+    assert_eq!((arena[a], arena.get(b)), ("a", Some(&"b")));
+    assert_eq!(
+        arena
+            .iter()
+            .map(|(idx, value)| (idx.index(), *value))
+            .collect::<Vec<_>>(),
+        vec![(0, "a"), (1, "b")]
+    );
+}
+
+#[test]
+fn built_modules_print_as_the_source_they_stand_for() {
+    let main = fn_("main")
+        .public()
+        .returns(ty("i32"))
+        .body(block().value(call(name("f"), vec![integer(1)])));
+    let printed = print_module(&build::module().statement(main).build());
+    assert_eq!(printed, "pub fn main(): i32 {\n    f(1)\n}\n");
+    assert!(parse(tokenize(&printed)).is_ok());
+}
+
+#[test]
+fn spans_know_the_file_they_point_into() {
+    let mut files = SourceMap::new();
+    files.add("a.rano", "fn a() {}");
+    let b = files.add("b.rano", "fn bb() {}");
+    let tokens = files.tokenize(b, TokenizeOptions::default());
+    let name = &tokens[1].span;
+    assert_eq!(name.file, b);
+    assert_eq!(files.file_of(name).unwrap().name, "b.rano");
+    assert_eq!(files.text(name), Some("bb"));
+}
+
+#[test]
+fn types_are_interned_once() {
+    let mut types = Types::new();
+    let pair = types.intern(Type::Tuple(vec![TypeId::I32, TypeId::STRING]));
+    let optional = types.intern(Type::Optional(pair));
+    assert_eq!(types.intern(Type::Optional(pair)), optional);
+    assert_eq!(types.display(optional), "(i32, string)?");
+    assert_eq!(types.intern(Type::I32), TypeId::I32);
+    assert!(types.is_copy(TypeId::I32));
+    assert!(!types.is_copy(optional));
+}
@@ -0,0 +1,108 @@
+use std::{fs, process::Command};
+
+use libranoc::{
+    codegen::{compile_artifacts, CompileOptions},
+    syntax::{parse, tokenize},
+};
+
+const SOURCE: &str = "pub fn show(x: f64): string { @f64_to_string(x) } \
+                      pub fn show32(x: f32): string { @f32_to_string(x) } \
+                      pub fn read(s: string): f64 { @string_to_f64(s) } \
+                      pub fn third(): f64 { @string_to_f64(\"0.3333333333333333\") } \
+                      pub fn tenth32(): f32 { @string_to_f32(\"1e-1\") } \
+                      pub fn invalid(): f64 { @string_to_f64(\"1.5x\") }";
+
+/// Whether Node.js runs here, to run the module with. The tests are skipped
+/// where it doesn't.
+fn available() -> bool {
+    Command::new("node").arg("--version").output().is_ok()
+}
+
+/// What the `calls` of the test `name` give, evaluated one after the other
+/// by Node.js with the exports of `SOURCE` as `e`, where `text(string)`
+/// reads a string out of its memory.
+fn run(name: &str, calls: &[&str]) -> Vec<String> {
+    let module = parse(tokenize(SOURCE)).expect("the source parses");
+    let (artifacts, errors) = compile_artifacts(module, &CompileOptions::default());
+    assert!(errors.is_empty(), "{:?}", errors);
+    let wasm = std::env::temp_dir().join(format!("rano-{}-{}.wasm", name, std::process::id()));
+    fs::write(&wasm, artifacts.wasm).unwrap();
+    let script = format!(
+        "const fs = require('fs');\n\
+         const module = new WebAssembly.Module(fs.readFileSync(process.argv[1]));\n\
+         const e = new WebAssembly.Instance(module, {{}}).exports;\n\
+         const text = (string) => {{\n\
+             const length = new DataView(e.memory.buffer).getInt32(string, true);\n\
+             return new TextDecoder().decode(new Uint8Array(e.memory.buffer, string + 4, length));\n\
+         }};\n\
+         for (const call of [{}]) {{ console.log(String(call())); }}\n",
+        calls
+            .iter()
+            .map(|call| format!("() => {}", call))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let output = Command::new("node")
+        .args(["-e", &script])
+        .arg(&wasm)
+        .output()
+        .unwrap();
+    fs::remove_file(wasm).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn floats_are_formatted_as_their_shortest_decimal() {
+    if !available() {
+        return;
+    }
+    let results = run(
+        "format",
+        &[
+            "text(e.show(0.1))",
+            "text(e.show(1 / 3))",
+            "text(e.show(100))",
+            "text(e.show(-0))",
+            "text(e.show(1e16))",
+            "text(e.show(5e-324))",
+            "text(e.show(NaN))",
+            "text(e.show(-Infinity))",
+            "text(e.show32(0.1))",
+        ],
+    );
+    assert_eq!(
+        results,
+        [
+            "0.1",
+            "0.3333333333333333",
+            "100.0",
+            "-0.0",
+            "1e16",
+            "5e-324",
+            "NaN",
+            "-inf",
+            "0.1"
+        ]
+    );
+}
+
+#[test]
+fn decimals_are_parsed_into_the_nearest_float() {
+    if !available() {
+        return;
+    }
+    let results = run(
+        "parse",
+        &[
+            "e.third() === 1 / 3",
+            "e.tenth32() === Math.fround(0.1)",
+            "e.invalid()",
+        ],
+    );
+    assert_eq!(results, ["true", "true", "NaN"]);
+}
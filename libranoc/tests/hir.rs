@@ -0,0 +1,68 @@
+use libranoc::{
+    hir::{
+        evaluate_constant, lower, Binary, BinaryOperator, Constant, Expression, ExpressionKind,
+        Item, Statement,
+    },
+    syntax::{parse, tokenize},
+};
+
+/// The statements of the body of the first function of `source`, lowered.
+fn body(source: &str) -> Vec<Statement> {
+    let module = lower(parse(tokenize(source)).expect("the source parses"));
+    match module.statements.into_iter().next() {
+        Some(Statement::Item(Item::Function(function))) => {
+            function.body.expect("the function has a body").statements
+        }
+        _ => panic!("the source starts with a function"),
+    }
+}
+
+/// The operator of `expression`, which is a binary one.
+fn operator(expression: &Expression) -> BinaryOperator {
+    match &expression.kind {
+        ExpressionKind::Binary(Binary { operator, .. }) => *operator,
+        kind => panic!("{:?} isn't a binary operation", kind),
+    }
+}
+
+#[test]
+fn compound_assignments_are_lowered_to_plain_ones() {
+    match &body("fn f(a: i32) { a *= 2; }")[0] {
+        Statement::Expression(Expression {
+            kind: ExpressionKind::Assign(assign),
+            ..
+        }) => assert_eq!(operator(&assign.value), BinaryOperator::Multiply),
+        statement => panic!("{:?} isn't an assignment", statement),
+    }
+}
+
+#[test]
+fn interpolations_are_lowered_to_concatenations() {
+    match &body(r#"fn f(a: string) { "a {a} b"; }"#)[0] {
+        Statement::Expression(expression) => {
+            assert_eq!(operator(expression), BinaryOperator::Add);
+        }
+        statement => panic!("{:?} isn't an expression", statement),
+    }
+}
+
+#[test]
+fn expressions_of_literals_are_evaluated() {
+    let evaluate = |expression: &str| match &body(&format!("fn f(a: i32) {{ {}; }}", expression))[0]
+    {
+        Statement::Expression(expression) => evaluate_constant(expression),
+        statement => panic!("{:?} isn't an expression", statement),
+    };
+    assert_eq!(evaluate("6 * 7").unwrap(), Some(Constant::Integer(42)));
+    assert_eq!(
+        evaluate("1 < 2 && !false").unwrap(),
+        Some(Constant::Boolean(true))
+    );
+    assert_eq!(
+        evaluate("-2147483648").unwrap(),
+        Some(Constant::Integer(i32::MIN))
+    );
+    assert_eq!(evaluate("a + 1").unwrap(), None);
+    assert!(evaluate("2147483647 + 1").is_err());
+    assert!(evaluate("1 / 0").is_err());
+}
@@ -0,0 +1,194 @@
+use libranoc::{
+    codegen::{CompileOptions, Overflow},
+    interpret::{Interpreter, TraceEvent, Value},
+    syntax::{parse, snippet::parse_snippet, tokenize},
+};
+
+/// An interpreter with the functions of `source` loaded.
+fn load(source: &str) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    let module = parse(tokenize(source)).expect("the source parses");
+    interpreter.load(module).expect("the source checks");
+    interpreter
+}
+
+/// The value `source` gives back from its `main`, or the message of what
+/// panics.
+fn run(source: &str) -> Result<Value, String> {
+    load(source)
+        .call("main", Vec::new())
+        .map_err(|error| error.message)
+}
+
+#[test]
+fn arithmetic_does_what_the_overflow_says() {
+    let source = "fn main(): i32 { let a = 2147483647; a + 1 }";
+    assert_eq!(run(source).unwrap_err(), "attempt to add with overflow");
+    let wrapping = format!("#[overflow(wrap)] {}", source);
+    assert_eq!(run(&wrapping).unwrap(), Value::Integer(i32::MIN));
+    let saturating = format!("#[overflow(saturate)] {}", source);
+    assert_eq!(run(&saturating).unwrap(), Value::Integer(i32::MAX));
+}
+
+#[test]
+fn overflow_of_the_options_applies_to_every_function() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_options(CompileOptions {
+        overflow: Overflow::Wrap,
+        ..CompileOptions::default()
+    });
+    let module = parse(tokenize(
+        "fn main(): i32 { let a = 0 - 2147483647 - 1; -a }",
+    ))
+    .unwrap();
+    interpreter.load(module).unwrap();
+    assert_eq!(
+        interpreter.call("main", Vec::new()).unwrap(),
+        Value::Integer(i32::MIN)
+    );
+}
+
+#[test]
+fn values_of_every_type_are_computed() {
+    assert_eq!(
+        run("fn main(): i64 { let a: i64 = 4000000000; a * 2 }").unwrap(),
+        Value::Long(8_000_000_000)
+    );
+    assert_eq!(
+        run("fn main(): f64 { 1.5 * 2.0 }").unwrap(),
+        Value::Double(3.0)
+    );
+    assert_eq!(
+        run(r#"fn main(): string { "a" + "b" }"#).unwrap(),
+        Value::String("ab".into())
+    );
+    assert_eq!(
+        run("fn main(): i32 { let t = (1, 2); t.1 }").unwrap(),
+        Value::Integer(2)
+    );
+    assert_eq!(
+        run("fn main(): bool { !(1 < 2) || true }").unwrap(),
+        Value::Integer(1)
+    );
+}
+
+#[test]
+fn division_by_zero_panics() {
+    let source = "fn main(): i32 { let a = 0; 1 / a }";
+    assert_eq!(run(source).unwrap_err(), "attempt to divide by zero");
+}
+
+#[test]
+fn todo_panics() {
+    let source = "fn main(): i32 { todo(\"later\") }";
+    assert_eq!(run(source).unwrap_err(), "not yet implemented: later");
+}
+
+#[test]
+fn recursion_is_bounded() {
+    let mut interpreter = load("fn f(n: i32): i32 { f(n + 1) }");
+    interpreter.set_max_depth(16);
+    let error = interpreter.call("f", vec![Value::Integer(0)]).unwrap_err();
+    assert_eq!(error.message, "Stack overflow while calling `f`.");
+}
+
+#[test]
+fn recursion_past_the_default_depth_is_reported() {
+    let source = "fn f(n: i32): i32 { if n == 0 { 0 } else { let a = f(n - 1); a + 1 } }";
+    let mut interpreter = load(source);
+    let depth = Interpreter::DEFAULT_MAX_DEPTH as i32;
+    assert_eq!(
+        interpreter
+            .call("f", vec![Value::Integer(depth - 1)])
+            .unwrap(),
+        Value::Integer(depth - 1)
+    );
+    let error = interpreter
+        .call("f", vec![Value::Integer(depth)])
+        .unwrap_err();
+    assert_eq!(error.message, "Stack overflow while calling `f`.");
+    let error = load("fn f(n: i32): i32 { f(n + 1) }")
+        .call("f", vec![Value::Integer(0)])
+        .unwrap_err();
+    assert_eq!(error.message, "Stack overflow while calling `f`.");
+}
+
+#[test]
+fn nested_functions_are_declared_once() {
+    let mut interpreter = load("fn f(a: i32): i32 { let b = g(a); fn g(b: i32): i32 { b + 1 } b }");
+    assert_eq!(
+        interpreter.call("f", vec![Value::Integer(1)]).unwrap(),
+        Value::Integer(2)
+    );
+    assert_eq!(
+        interpreter.call("f", vec![Value::Integer(2)]).unwrap(),
+        Value::Integer(3)
+    );
+    assert!(interpreter.call("g", vec![Value::Integer(1)]).is_err());
+}
+
+#[test]
+fn extern_functions_are_given_by_the_host() {
+    let mut interpreter = load(
+        "#[link(module = \"host\", name = \"twice\")] extern fn double(a: i32): i32; \
+         fn main(): i32 { double(4) + 1 }",
+    );
+    interpreter.define_extern("twice", |arguments| match arguments {
+        [Value::Integer(value)] => Value::Integer(value * 2),
+        _ => Value::UNIT,
+    });
+    assert_eq!(
+        interpreter.call("main", Vec::new()).unwrap(),
+        Value::Integer(9)
+    );
+}
+
+#[test]
+fn snippets_see_the_functions_before() {
+    let mut interpreter = Interpreter::new();
+    let mut snippet = |source: &str| {
+        interpreter
+            .load_snippet(parse_snippet(source).expect("the snippet parses"))
+            .map_err(|errors| errors[0].message.clone())
+    };
+    assert_eq!(snippet("fn f(a: i32): i32 { a * 2 }"), Ok(Vec::new()));
+    assert_eq!(
+        snippet("f(1); f(2) + 1"),
+        Ok(vec![Value::Integer(2), Value::Integer(5)])
+    );
+    assert!(snippet("fn f(): i32 { 1 }").is_err());
+    assert!(snippet("g(1)").is_err());
+    assert_eq!(snippet("f(3)"), Ok(vec![Value::Integer(6)]));
+}
+
+#[test]
+fn runs_are_traced_and_replayed() {
+    let source = "extern fn show(value: i32): i32; \
+                  fn main(): i32 { let a = show(1); if a > 0 { a } else { 0 } }";
+    let mut interpreter = load(source);
+    interpreter.define_extern("show", |_| Value::Integer(5));
+    assert_eq!(
+        interpreter.call("main", Vec::new()).unwrap(),
+        Value::Integer(5)
+    );
+    let events: Vec<_> = interpreter
+        .trace()
+        .entries()
+        .map(|entry| entry.event.clone())
+        .collect();
+    assert_eq!(
+        events.first(),
+        Some(&TraceEvent::Call {
+            function: "main".to_owned(),
+            arguments: Vec::new(),
+        })
+    );
+    assert!(events.contains(&TraceEvent::Branch { taken: true }));
+
+    let mut replay = Interpreter::replaying(interpreter.trace());
+    replay
+        .load(parse(tokenize(source)).unwrap())
+        .expect("the source checks");
+    assert_eq!(replay.call("main", Vec::new()).unwrap(), Value::Integer(5));
+    assert_eq!(replay.trace().len(), interpreter.trace().len());
+}
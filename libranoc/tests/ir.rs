@@ -0,0 +1,24 @@
+use libranoc::{
+    core::{
+        ast::Module,
+        ir::{read_ir, write_ir, IrKind, FORMAT_VERSION},
+    },
+    syntax::{parse, tokenize},
+};
+
+#[test]
+fn intermediates_of_another_format_are_rejected() {
+    let module = parse(tokenize("fn f(): i32 { 1 }")).unwrap();
+    let mut bytes = write_ir(IrKind::Ast, &module).unwrap();
+    assert_eq!(read_ir::<Module>(IrKind::Ast, &bytes).unwrap(), module);
+
+    bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+    let error = read_ir::<Module>(IrKind::Ast, &bytes).unwrap_err();
+    assert_eq!(
+        error.message,
+        format!(
+            "Serialized with the format version 1 but {} is required.",
+            FORMAT_VERSION
+        )
+    );
+}
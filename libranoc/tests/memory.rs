@@ -0,0 +1,118 @@
+use std::{fs, process::Command};
+
+use libranoc::{
+    codegen::{compile_artifacts, CompileOptions},
+    syntax::{parse, tokenize},
+};
+
+const SOURCE: &str = "pub fn greeting(): string { \"hi\" + \"!\" } \
+                      pub fn same(a: string, b: string): bool { a == b } \
+                      pub fn doubled(a: string): i32 { let b = a + a; @string_len(b) }";
+
+/// Whether Node.js runs here, to run the module with. The tests are skipped
+/// where it doesn't.
+fn available() -> bool {
+    Command::new("node").arg("--version").output().is_ok()
+}
+
+/// What the `calls` of the test `name` give, evaluated one after the other
+/// by Node.js with the exports of `SOURCE` as `e` and its memory as the
+/// words `words`, where `text(string)` reads a string.
+fn run(name: &str, calls: &[&str]) -> Vec<String> {
+    let module = parse(tokenize(SOURCE)).expect("the source parses");
+    let (artifacts, errors) = compile_artifacts(module, &CompileOptions::default());
+    assert!(errors.is_empty(), "{:?}", errors);
+    let wasm = std::env::temp_dir().join(format!("rano-{}-{}.wasm", name, std::process::id()));
+    fs::write(&wasm, artifacts.wasm).unwrap();
+    let script = format!(
+        "const fs = require('fs');\n\
+         const module = new WebAssembly.Module(fs.readFileSync(process.argv[1]));\n\
+         const e = new WebAssembly.Instance(module, {{}}).exports;\n\
+         const words = new Int32Array(e.memory.buffer);\n\
+         const text = (string) => {{\n\
+             const length = new DataView(e.memory.buffer).getInt32(string, true);\n\
+             return new TextDecoder().decode(new Uint8Array(e.memory.buffer, string + 4, length));\n\
+         }};\n\
+         let a;\n\
+         for (const call of [{}]) {{ console.log(String(call())); }}\n",
+        calls
+            .iter()
+            .map(|call| format!("() => {}", call))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let output = Command::new("node")
+        .args(["-e", &script])
+        .arg(&wasm)
+        .output()
+        .unwrap();
+    fs::remove_file(wasm).unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn blocks_are_aligned_and_reused_once_freed() {
+    if !available() {
+        return;
+    }
+    // the second word of the memory is the first freed block, by its header.
+    let results = run(
+        "allocator",
+        &[
+            "a = e.rano_malloc(10)",
+            "a % 8",
+            "e.rano_free(a)",
+            "words[1] === a - 8",
+            "e.rano_malloc(3) === a",
+            "e.rano_malloc(-1)",
+        ],
+    );
+    assert_eq!(&results[1..], ["0", "undefined", "true", "true", "0"]);
+}
+
+#[test]
+fn strings_are_concatenated_and_compared_by_their_bytes() {
+    if !available() {
+        return;
+    }
+    let results = run(
+        "strings",
+        &[
+            "a = e.greeting()",
+            "text(a)",
+            "e.same(a, e.greeting())",
+            "e.doubled(a)",
+        ],
+    );
+    assert_eq!(&results[1..], ["hi!", "1", "6"]);
+}
+
+#[test]
+fn values_are_freed_with_their_last_reference() {
+    if !available() {
+        return;
+    }
+    // `doubled` releases the reference it is given and the concatenation it
+    // makes, and the one kept here frees the greeting.
+    let results = run(
+        "references",
+        &[
+            "a = e.greeting()",
+            "words[1]",
+            "e.rano_retain(a) === a",
+            "e.doubled(a)",
+            "words[1] !== 0 && words[1] !== a - 8",
+            "e.rano_release(a)",
+            "words[1] === a - 8",
+        ],
+    );
+    assert_eq!(
+        &results[1..],
+        ["0", "true", "6", "true", "undefined", "true"]
+    );
+}
@@ -0,0 +1,103 @@
+#![cfg(all(target_arch = "x86_64", target_os = "linux"))]
+
+use std::{fs, process::Command};
+
+use libranoc::{
+    codegen::{compile_with, CompileOptions, Cranelift, Overflow},
+    syntax::{parse, tokenize},
+};
+
+const SOURCE: &str = "pub fn quotient(a: i32, b: i32): i32 { a / b } \
+                      pub fn modulo(a: i32, b: i32): i32 { a % b } \
+                      pub fn long_quotient(a: i64, b: i64): i64 { a / b }";
+
+/// The function of `SOURCE` called, its arguments, and what it gives with
+/// each of the `Overflow` policies, or `trap`, which are what the WASM
+/// backend gives.
+const CASES: &[(&str, i64, i64, [&str; 3])] = &[
+    ("quotient", 7, -2, ["-3", "-3", "-3"]),
+    ("quotient", 7, -1, ["-7", "-7", "-7"]),
+    (
+        "quotient",
+        i32::MIN as i64,
+        -1,
+        ["trap", "-2147483648", "2147483647"],
+    ),
+    ("quotient", 7, 0, ["trap", "trap", "trap"]),
+    ("modulo", -7, 2, ["-1", "-1", "-1"]),
+    ("modulo", i32::MIN as i64, -1, ["trap", "0", "0"]),
+    ("modulo", 7, 0, ["trap", "trap", "trap"]),
+    (
+        "long_quotient",
+        i64::MIN,
+        -1,
+        ["trap", "-9223372036854775808", "9223372036854775807"],
+    ),
+];
+
+/// Whether a C compiler runs here to link the object with. The tests are
+/// skipped where it doesn't.
+fn available() -> bool {
+    Command::new("cc").arg("--version").output().is_ok()
+}
+
+#[test]
+fn division_does_what_the_overflow_says() {
+    if !available() {
+        return;
+    }
+    for (index, overflow) in [Overflow::Trap, Overflow::Wrap, Overflow::Saturate]
+        .iter()
+        .enumerate()
+    {
+        let directory =
+            std::env::temp_dir().join(format!("rano-native-{}-{}", index, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let options = CompileOptions {
+            overflow: *overflow,
+            ..CompileOptions::default()
+        };
+        let module = parse(tokenize(SOURCE)).expect("the source parses");
+        let (artifacts, errors) = compile_with(module, &options, &Cranelift::host());
+        assert!(errors.is_empty(), "{:?}", errors);
+        fs::write(directory.join("main.o"), artifacts.object.unwrap()).unwrap();
+        fs::write(
+            directory.join("run.c"),
+            "#include <stdint.h>\n#include <stdio.h>\n#include <stdlib.h>\n#include <string.h>\n\
+             int32_t quotient(int32_t, int32_t);\n\
+             int32_t modulo(int32_t, int32_t);\n\
+             int64_t long_quotient(int64_t, int64_t);\n\
+             int main(int argc, char **argv) {\n\
+                 int64_t a = strtoll(argv[2], 0, 10), b = strtoll(argv[3], 0, 10);\n\
+                 if (!strcmp(argv[1], \"quotient\")) printf(\"%d\\n\", quotient(a, b));\n\
+                 else if (!strcmp(argv[1], \"modulo\")) printf(\"%d\\n\", modulo(a, b));\n\
+                 else printf(\"%lld\\n\", (long long)long_quotient(a, b));\n\
+                 return 0;\n\
+             }\n",
+        )
+        .unwrap();
+        let status = Command::new("cc")
+            .current_dir(&directory)
+            .args(["run.c", "main.o", "-o", "run"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        for (function, a, b, results) in CASES {
+            let output = Command::new(directory.join("run"))
+                .args([function.to_string(), a.to_string(), b.to_string()])
+                .output()
+                .unwrap();
+            let result = if output.status.success() {
+                String::from_utf8(output.stdout).unwrap().trim().to_owned()
+            } else {
+                "trap".to_owned()
+            };
+            assert_eq!(
+                result, results[index],
+                "{}({}, {}) with {:?}",
+                function, a, b, overflow
+            );
+        }
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,333 @@
+use libranoc::{
+    core::ast::{
+        cfg::{configure, Cfg},
+        print::print_module,
+    },
+    syntax::{
+        cst::parse_cst,
+        parse, parse_debug, parse_recovering, parse_with,
+        snippet::{self, parse_snippet_status, ParseStatus, SnippetOptions},
+        suggest_keyword, tokenize, DebugNode, IncrementalParser, IncrementalTokenizer,
+        ParseOptions, Spanned, TokenizeOptions,
+    },
+};
+
+/// The kinds of the nodes `parse_debug` gives for `source`, depth first.
+fn kinds(source: &str) -> Vec<&'static str> {
+    fn visit(node: &DebugNode, kinds: &mut Vec<&'static str>) {
+        kinds.push(node.kind);
+        for child in &node.children {
+            visit(child, kinds);
+        }
+    }
+    let mut kinds = Vec::new();
+    visit(&parse_debug(source).expect("the source parses"), &mut kinds);
+    kinds
+}
+
+/// The number of nodes of the kind `kind` in `source`.
+fn count(source: &str, kind: &str) -> usize {
+    kinds(source).into_iter().filter(|k| *k == kind).count()
+}
+
+/// The message of the error parsing `source` fails with.
+fn parse_error(source: &str) -> String {
+    parse(tokenize(source))
+        .expect_err("the source is invalid")
+        .message
+}
+
+/// The messages of the errors parsing `source` recovers from, and the
+/// number of statements it keeps.
+fn recover(source: &str) -> (Vec<String>, usize) {
+    let (module, errors) = parse_recovering(tokenize(source));
+    (
+        errors.into_iter().map(|error| error.message).collect(),
+        DebugNode::from(&module).children.len(),
+    )
+}
+
+#[test]
+fn a_missing_semicolon_is_reported_where_it_belongs() {
+    let missing = || vec!["Expected `;` after statement.".to_owned()];
+    assert_eq!(recover("fn f() { let a = 1 let b = 2; }"), (missing(), 1));
+    assert_eq!(
+        recover("fn f() { let a = 1 fn g() {} let b = 2; }"),
+        (missing(), 1)
+    );
+}
+
+#[test]
+fn broken_statements_only_skip_themselves() {
+    let (errors, kept) =
+        recover("fn f() { let = 1; let a = if b { let = 3; 2 } else { 4 }; a + } fn g() { 1 }");
+    assert_eq!(
+        errors,
+        vec![
+            "Expected `_`, found `=`.",
+            "Expected `_`, found `=`.",
+            "Expected an expression, found `}`."
+        ]
+    );
+    assert_eq!(kept, 2);
+}
+
+#[test]
+fn generic_parameters_are_parsed() {
+    assert_eq!(
+        count("fn f<T, U: Show>(a: T, b: U): T { a }", "GenericParameter"),
+        2
+    );
+}
+
+#[test]
+fn type_arguments_follow_a_turbofish() {
+    let source = "fn f() { g::<i32, *>(1) + m.h::<Vec<i32>>(a); }";
+    assert_eq!(count(source, "TypeArgumentsExpression"), 2);
+    assert_eq!(count(source, "CallExpression"), 2);
+    assert_eq!(count(source, "StarTypeParameter"), 1);
+    assert_eq!(parse_cst(source).to_string(), source);
+    let printed = print_module(&parse(tokenize(source)).unwrap());
+    assert_eq!(kinds(&printed), kinds(source));
+
+    // without the `::`, the `<` still compares.
+    assert_eq!(count("fn f() { a < b; }", "TypeArgumentsExpression"), 0);
+    assert_eq!(
+        parse_error("fn f() { g::<i32; }"),
+        "Expected `>`, found `;`."
+    );
+    assert_eq!(
+        parse_error("fn f() { g::i32; }"),
+        "Expected `<`, found `i32`."
+    );
+}
+
+#[test]
+fn a_shebang_is_skipped() {
+    assert_eq!(kinds("#!/usr/bin/env rano\nfn f() {}"), kinds("fn f() {}"));
+    assert_eq!(count("#![x]\nfn f() {}", "Directive"), 1);
+}
+
+#[test]
+fn edits_retokenize_like_the_whole_source() {
+    let mut tokenizer = IncrementalTokenizer::new("fn f() { 1 }", TokenizeOptions::default());
+    tokenizer.edit(9..10, "a + 2");
+    assert_eq!(tokenizer.tokens(), &tokenize("fn f() { a + 2 }")[..]);
+}
+
+#[test]
+fn unfinished_input_is_told_apart_from_invalid_input() {
+    assert!(parse_snippet_status("fn f() {").is_incomplete());
+    assert!(parse_snippet_status("1 +").is_incomplete());
+    assert!(matches!(parse_snippet_status(")"), ParseStatus::Invalid(_)));
+    assert!(matches!(
+        parse_snippet_status("fn f() { 1 }"),
+        ParseStatus::Complete(_)
+    ));
+}
+
+#[test]
+fn misspelled_keywords_are_suggested() {
+    assert_eq!(suggest_keyword("mathc"), Some("match"));
+    assert_eq!(suggest_keyword("xyzzy"), None);
+}
+
+#[test]
+fn adjacent_string_literals_are_one_literal() {
+    assert_eq!(count(r#"fn f() { "a" "b" }"#, "StringLiteral"), 1);
+}
+
+#[test]
+fn expected_tokens_are_named() {
+    assert_eq!(
+        parse_error("fn f() { let a = 1 let b = 2; }"),
+        "Expected `;`, found `let`."
+    );
+    assert_eq!(
+        parse_error("fn f() { 1 } fn g() {"),
+        "Expected `}`, found the end of input."
+    );
+}
+
+#[test]
+fn operators_group_by_their_precedence() {
+    let tree = |source| format!("{:?}", parse_debug(source).unwrap().children[0]);
+    assert_ne!(tree("fn f() { 1 + 2 * 3 }"), tree("fn f() { (1 + 2) * 3 }"));
+    assert_eq!(count("fn f() { 1 + 2 * 3 }", "InfixExpression"), 2);
+}
+
+#[test]
+fn one_parenthesized_expression_is_a_grouping() {
+    assert_eq!(count("fn f() { (1) }", "TupleExpression"), 0);
+    assert_eq!(count("fn f() { (1,) }", "TupleExpression"), 1);
+}
+
+#[test]
+fn trailing_commas_are_allowed() {
+    assert!(parse_debug(
+        "fn f(a: i32, b: i32,): (i32, i32,) { g(a, b,); match a { 1 => 2, _ => 3, } }"
+    )
+    .is_ok());
+}
+
+#[test]
+fn nesting_is_bounded() {
+    let source = format!("fn f() {{ {}1{} }}", "(".repeat(20), ")".repeat(20));
+    assert!(parse(tokenize(&source)).is_ok());
+    let options = ParseOptions { max_depth: 10 };
+    assert!(parse_with(tokenize(&source), &options).is_err());
+}
+
+#[test]
+fn the_syntax_tree_keeps_every_token() {
+    let source = "/// Doc.\nfn f(a: i32): i32 { // comment\n    if a > 1 { a * 2 } else { -a }\n}";
+    assert_eq!(parse_cst(source).to_string(), source);
+}
+
+#[test]
+fn edits_reparse_like_the_whole_source() {
+    let source = "fn f() { 1 } fn g() { 2 } fn h() { 3 }";
+    let mut parser = IncrementalParser::new(source, Default::default(), Default::default());
+    let delta = parser.edit(22..23, "b");
+    assert_eq!(delta.removed, 1..2);
+    assert_eq!(delta.inserted, 1..2);
+    assert_eq!(
+        DebugNode::from(&parser.module()),
+        parse_debug("fn f() { 1 } fn g() { b } fn h() { 3 }").unwrap()
+    );
+}
+
+#[test]
+fn else_if_chains_nest_in_the_else() {
+    let source = "fn f(a: i32) { if a { 1 } else if b { 2 } else { 3 } }";
+    assert_eq!(count(source, "IfExpression"), 2);
+    assert_eq!(count(source, "Block"), 4);
+}
+
+#[test]
+fn struct_literals_are_not_taken_for_the_body_of_an_if() {
+    assert_eq!(count("fn f() { if a { b } }", "InitExpression"), 0);
+    assert_eq!(
+        count("fn f() { if (A { b: 1 }) { c } }", "InitExpression"),
+        1
+    );
+}
+
+#[test]
+fn declared_operators_are_parsed_with_their_precedence() {
+    let source = "fn f() { 1 <+> 2 * 3 } operator <+>: Additive, left = plus;";
+    assert_eq!(count(source, "OperatorDeclaration"), 1);
+    // `1 <+> (2 * 3)`, calling `plus`.
+    assert_eq!(count(source, "CallExpression"), 1);
+    assert_eq!(count(source, "InfixExpression"), 1);
+}
+
+#[test]
+fn attributes_take_structured_values() {
+    let source = r#"#[link(module = "env", name = "f")] #[x(a(b, 1), c)] extern fn f();"#;
+    assert_eq!(count(source, "KeyValue"), 2);
+}
+
+#[test]
+fn doc_comments_are_attached_to_the_declaration() {
+    assert_eq!(
+        count("/// Does\n/// nothing.\nfn f() {}", "Documentation"),
+        1
+    );
+}
+
+#[test]
+fn match_arms_are_parsed() {
+    let source = "fn f(a: i32) { match a { 1 => 2, n if n > 1 => { n } _ => 0 } }";
+    assert_eq!(count(source, "MatchArm"), 3);
+    assert_eq!(count(source, "Guard"), 1);
+}
+
+#[test]
+fn closures_take_the_rest_of_the_expression() {
+    let source = "fn f() { let g = |x, y: i32| x + y; let h = move || 1; }";
+    assert_eq!(count(source, "ClosureExpression"), 2);
+    assert_eq!(count(source, "Move"), 1);
+}
+
+#[test]
+fn unsafe_blocks_are_parsed() {
+    assert_eq!(count("fn f() { unsafe { 1 } }", "UnsafeExpression"), 1);
+}
+
+#[test]
+fn printed_source_parses_to_the_same_tree() {
+    let source = "/// Doc.\nfn f(a: i32): i32 { // comment\n    if a > 1 { a * 2 } else { -a }\n}";
+    let printed = print_module(&parse(tokenize(source)).unwrap());
+    assert_eq!(kinds(&printed), kinds(source));
+}
+
+#[test]
+fn macros_are_expanded_before_parsing() {
+    let source = "macro twice { ($a:expr) => { $a + $a }; } fn f() { twice!(1) * 2 }";
+    assert_eq!(count(source, "InfixExpression"), 2);
+    assert_eq!(
+        parse_error("macro m { () => {}; } fn f() { m!(1) }"),
+        "No rule of macro `m` matches this invocation."
+    );
+}
+
+#[test]
+fn declarations_are_kept_by_their_cfg() {
+    let source = r#"#[cfg(target = "wasi")] fn f() {} #[cfg(not(debug))] fn g() {} fn h() {}"#;
+    let mut module = parse(tokenize(source)).unwrap();
+    let errors = configure(&mut module, &Cfg::new().flag("debug"));
+    assert!(errors.is_empty());
+    assert_eq!(DebugNode::from(&module).children.len(), 1);
+}
+
+#[test]
+fn intrinsics_are_parsed() {
+    let source = "fn f() { unsafe { @store<i32>(0, @size_of<i64>()) } }";
+    assert_eq!(count(source, "IntrinsicExpression"), 2);
+}
+
+#[test]
+fn snippets_are_parsed_on_their_own() {
+    assert!(snippet::parse_expression("1 + 2;").is_ok());
+    let options = SnippetOptions {
+        allow_trailing_semicolon: false,
+        ..SnippetOptions::default()
+    };
+    assert!(snippet::parse_expression_with("1 + 2;", &options).is_err());
+    assert!(snippet::parse_type("(i32, string)?").is_ok());
+    let snippet = snippet::parse_snippet("fn g() {} g(); 1").unwrap();
+    assert_eq!(snippet.statements.len(), 2);
+    assert!(snippet.last_expression.is_some());
+}
+
+#[test]
+fn use_trees_take_groups_globs_and_renames() {
+    let source = "use a::{b, c as d, e::*};";
+    assert_eq!(count(source, "UseTree"), 4);
+    assert_eq!(count(source, "Alias"), 1);
+    assert_eq!(count(source, "Glob"), 1);
+}
+
+#[test]
+fn the_dump_gives_the_span_of_every_node() {
+    let dump = parse_debug("fn f() {}").unwrap().to_string();
+    assert!(dump.starts_with("Module 0..9 1:1\n  FunctionDeclaration 0..9 1:1\n"));
+    assert!(dump.contains("    Name 3..4 1:4 \"f\"\n"));
+}
+
+#[test]
+fn modules_are_declared_inline_or_in_their_file() {
+    let source = "mod m { fn f() {} } mod n;";
+    assert_eq!(count(source, "ModuleDeclaration"), 2);
+    // the module of the source, and the one inline.
+    assert_eq!(count(source, "Module"), 2);
+}
+
+#[test]
+fn every_node_spans_its_tokens() {
+    let expression = snippet::parse_expression("f(1) + -a.b").unwrap();
+    assert_eq!(expression.span().range, 0..11);
+    let source = "fn f() { 1 }";
+    assert_eq!(parse(tokenize(source)).unwrap().span().range, 0..12);
+}
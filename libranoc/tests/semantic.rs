@@ -0,0 +1,67 @@
+use libranoc::{
+    core::ast::Module,
+    semantic::{lint_todos, lint_unused, DefinitionKind, SymbolTable},
+    syntax::{parse, tokenize},
+};
+
+/// The module `source` parses to.
+fn module(source: &str) -> Module {
+    parse(tokenize(source)).expect("the source parses")
+}
+
+#[test]
+fn remaining_todos_are_listed() {
+    let warnings = lint_todos(&module(
+        r#"fn f(): i32 { todo "later" } fn g(): i32 { unimplemented }"#,
+    ));
+    let messages: Vec<_> = warnings.iter().map(|warning| &warning.message).collect();
+    assert_eq!(
+        messages,
+        vec!["Remaining `todo`.", "Remaining `unimplemented`."]
+    );
+    assert_eq!(warnings[0].labels[0].message.as_deref(), Some("later"));
+}
+
+#[test]
+fn symbols_are_looked_up_by_where_they_are_written() {
+    let source = "fn f(a: i32): i32 { a + a } fn g(): i32 { f(1) }";
+    let table = SymbolTable::build(module(source));
+    let at = |offset: usize| {
+        table
+            .occurrences()
+            .iter()
+            .find(|occurrence| occurrence.span.range.start == offset)
+            .expect("a symbol is written there")
+    };
+    let call = at(source.find("f(1)").unwrap());
+    assert!(!call.is_definition);
+    let f = table.find_definition(&call.span).unwrap();
+    assert_eq!(f.name.span.range, 3..4);
+    assert!(matches!(f.kind, DefinitionKind::Function { .. }));
+
+    let a = at(5);
+    assert!(a.is_definition);
+    let references: Vec<_> = table
+        .find_references(a.definition)
+        .map(|span| span.range.clone())
+        .collect();
+    assert_eq!(references, vec![20..21, 24..25]);
+}
+
+#[test]
+fn what_is_never_used_is_reported() {
+    let warnings = lint_unused(&module(
+        "fn f(a: i32, _b: i32): i32 { let c = 1; todo; 2 } \
+         pub fn g(): i32 { h() } fn h(): i32 { 1 }",
+    ));
+    let messages: Vec<_> = warnings.iter().map(|warning| &warning.message).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "The function `f` is never called.",
+            "`a` is never used.",
+            "`c` is never used.",
+            "Unreachable code.",
+        ]
+    );
+}
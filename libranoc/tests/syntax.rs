@@ -0,0 +1,112 @@
+use libranoc::{
+    core::Location,
+    syntax::{decode_source, tokenize, tokenize_with, ColumnMode, TokenKind, TokenizeOptions},
+};
+
+/// The kinds of the tokens of `source`.
+fn kinds(source: &str) -> Vec<TokenKind> {
+    tokenize(source)
+        .into_iter()
+        .map(|token| token.kind)
+        .collect()
+}
+
+/// The text the error decoding `bytes` points at, in the source it is
+/// shown with, and the message of its label.
+fn decode_error(bytes: &[u8]) -> (String, String) {
+    let error = decode_source(bytes).expect_err("the source is invalid");
+    let label = &error.error.labels[0];
+    let span = match &label.location {
+        Location::Known(span) => span,
+        Location::Eof => panic!("the error points at the end"),
+    };
+    (
+        error.lossy[span.range.clone()].to_owned(),
+        label.message.clone().unwrap_or_default(),
+    )
+}
+
+#[test]
+fn decode_errors_point_into_the_source_they_are_shown_with() {
+    let replaced = || char::REPLACEMENT_CHARACTER.to_string();
+    assert_eq!(
+        decode_error(b"fn \xff() {}"),
+        (replaced(), "Invalid data at byte 3".to_owned())
+    );
+    assert_eq!(
+        decode_error(b"\xef\xbb\xbfa\n\xe2\x82"),
+        (replaced(), "Invalid data at byte 5".to_owned())
+    );
+    let utf16 = |text: &[u16]| -> Vec<u8> {
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend(text.iter().flat_map(|unit| unit.to_le_bytes().to_vec()));
+        bytes
+    };
+    // an unpaired surrogate.
+    assert_eq!(
+        decode_error(&utf16(&[0x61, 0x0a, 0xd800, 0x62])),
+        (replaced(), "Invalid data at byte 6".to_owned())
+    );
+    let mut odd = utf16(&[0x61]);
+    odd.push(0x62);
+    assert_eq!(
+        decode_error(&odd),
+        (replaced(), "Invalid data at byte 4".to_owned())
+    );
+}
+
+#[test]
+fn literals_embedding_nothing_tokenize_as_before_interpolation() {
+    let without_interpolation = TokenizeOptions {
+        interpolation: false,
+        ..TokenizeOptions::default()
+    };
+    for source in &[
+        r#""""#,
+        r#""text""#,
+        r#""{}""#,
+        r#""{ }""#,
+        r#""a {} b {}""#,
+        r#""\{x}""#,
+        r#""{""#,
+        r#""}""#,
+    ] {
+        assert_eq!(
+            tokenize(source),
+            tokenize_with(source, without_interpolation.clone()),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn embedded_expressions_are_split_from_the_fragments() {
+    assert_eq!(
+        kinds(r#""a {x + 1} b {}""#),
+        vec![
+            TokenKind::LiteralStringHead(r#""a {"#.to_owned()),
+            TokenKind::IdentifierIdentifier("x".to_owned()),
+            TokenKind::PunctuationPlusSign,
+            TokenKind::LiteralNumberIntegral("1".to_owned()),
+            TokenKind::LiteralStringTail(r#"} b {}""#.to_owned()),
+        ]
+    );
+    // the spans point into the literal.
+    assert_eq!(tokenize(r#""a {x + 1}""#)[1].span.range, 4..5);
+}
+
+#[test]
+fn columns_count_what_the_mode_says() {
+    let column = |column_mode| {
+        let options = TokenizeOptions {
+            column_mode,
+            ..TokenizeOptions::default()
+        };
+        tokenize_with("\t\"漢\" b", options)[1].span.column
+    };
+    // the tab stops at 4, and the wide character takes two cells.
+    assert_eq!(column(ColumnMode::Width), 9);
+    assert_eq!(column(ColumnMode::Chars), 8);
+    assert_eq!(column(ColumnMode::Bytes), 7);
+}
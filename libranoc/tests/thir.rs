@@ -0,0 +1,47 @@
+use libranoc::{
+    core::TypeId,
+    hir::lower,
+    semantic::resolve,
+    syntax::{parse, tokenize},
+    thir::{build, ExpressionKind, Function, Item, Statement},
+    typeck,
+};
+
+/// The first function of `source`, lowered, checked and typed.
+fn function(source: &str) -> Function {
+    let module = lower(parse(tokenize(source)).expect("the source parses"));
+    let resolution = resolve(&module);
+    assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+    let typeck = typeck::check(&module, &resolution);
+    assert!(typeck.errors.is_empty(), "{:?}", typeck.errors);
+    match build(module, &resolution, &typeck)
+        .statements
+        .into_iter()
+        .next()
+    {
+        Some(Statement::Item(Item::Function(function))) => function,
+        _ => panic!("the source starts with a function"),
+    }
+}
+
+#[test]
+fn every_local_and_expression_has_its_type() {
+    let function = function("fn f(a: i32): i64 { let b: i64 = 1; b }");
+    assert_eq!(function.parameters[0].1, TypeId::I32);
+    assert_eq!(function.return_type, TypeId::I64);
+    let body = function.body.expect("the function has a body");
+    assert_eq!(body.ty, TypeId::I64);
+    match &body.statements[0] {
+        Statement::Let(r#let) => {
+            assert_eq!(r#let.ty, TypeId::I64);
+            assert_eq!(
+                r#let.value.as_ref().map(|value| value.ty),
+                Some(TypeId::I64)
+            );
+        }
+        statement => panic!("{:?} isn't a `let`", statement),
+    }
+    let value = body.value.expect("the block has a value");
+    assert_eq!(value.ty, TypeId::I64);
+    assert!(matches!(value.kind, ExpressionKind::Name(_)));
+}
@@ -1,8 +1,25 @@
 use std::{fs, path::PathBuf};
 
 use anyhow::{anyhow, bail};
-use libranoc::{codegen, core::Error, syntax};
-use wasmer::{imports, Function, Instance, Module, Store};
+use clap::{App, Arg, SubCommand};
+use codespan_reporting::diagnostic::Severity;
+use libranoc::{
+    codegen,
+    core::{
+        ast::cfg::{configure, Cfg},
+        Error, FileId, SourceMap,
+    },
+    interpret::{Interpreter, Value},
+    semantic,
+    syntax::{self, TokenizeOptions},
+};
+use wasmer::{
+    imports, Cranelift, Exports, Features, Function, ImportObject, Instance, Memory, Module, Store,
+    JIT,
+};
+
+mod repl;
+mod thread;
 
 mod external {
     pub fn show(i: i32) -> i32 {
@@ -23,7 +40,44 @@ mod ops {
     }
 }
 
-fn report_error(src: &String, error: Error) -> anyhow::Result<()> {
+/// The imports of an instance of `module`, sharing `memory` with the
+/// others if it is compiled for the `threads` target.
+pub fn create_imports(module: &Module, memory: Option<Memory>) -> ImportObject {
+    let store = module.store();
+    let mut imports = imports! {
+        "extern" => {
+            "show" => Function::new_native(store, external::show),
+            "Add__i32_i32" => Function::new_native(store, ops::add_i32_i32),
+            "Subtract__i32_i32" => Function::new_native(store, ops::subtract_i32_i32),
+            "PartialOrd__i32_i32" => Function::new_native(store, ops::cmp_i32_i32),
+        },
+        "thread" => {
+            "spawn" => Function::new_native_with_env(
+                store,
+                thread::ThreadEnv {
+                    module: module.clone(),
+                    memory: memory.clone(),
+                },
+                thread::spawn,
+            ),
+            "join" => Function::new_native(store, thread::join),
+            "scope" => Function::new_native(store, thread::scope),
+            "join_scope" => Function::new_native(store, thread::join_scope),
+        },
+    };
+    if let Some(memory) = memory {
+        let mut env = Exports::new();
+        env.insert("memory", memory);
+        imports.register("env", env);
+    }
+    imports
+}
+
+fn report_error(sources: &SourceMap, error: Error) -> anyhow::Result<()> {
+    report(sources, error, Severity::Error)
+}
+
+fn report(sources: &SourceMap, error: Error, severity: Severity) -> anyhow::Result<()> {
     use codespan_reporting::{
         diagnostic::{Diagnostic, Label},
         files::SimpleFiles,
@@ -32,9 +86,12 @@ fn report_error(src: &String, error: Error) -> anyhow::Result<()> {
             termcolor::{ColorChoice, StandardStream},
         },
     };
+    // added in the same order, so the ids of both are the same.
     let mut files = SimpleFiles::new();
-    let file_id = files.add("main.rano", src);
-    let diagnostic = Diagnostic::error()
+    for (_, file) in sources.files() {
+        files.add(file.name.as_str(), file.src.as_str());
+    }
+    let diagnostic = Diagnostic::new(severity)
         .with_message(error.message)
         .with_code(format!("E{:04}", error.code as u16))
         .with_labels(
@@ -42,13 +99,18 @@ fn report_error(src: &String, error: Error) -> anyhow::Result<()> {
                 .labels
                 .iter()
                 .map(|label| {
-                    let mut diagnostic_label = Label::primary(
-                        file_id,
-                        match &label.location {
-                            libranoc::core::Location::Eof => src.len()..(src.len() + 1),
-                            libranoc::core::Location::Known(span) => span.range.clone(),
-                        },
-                    );
+                    let mut diagnostic_label = match &label.location {
+                        // the end of the input is the end of the first file.
+                        libranoc::core::Location::Eof => {
+                            let len = sources
+                                .get(FileId::default())
+                                .map_or(0, |file| file.src.len());
+                            Label::primary(0, len..(len + 1))
+                        }
+                        libranoc::core::Location::Known(span) => {
+                            Label::primary(span.file.0 as usize, span.range.clone())
+                        }
+                    };
                     if let Some(message) = &label.message {
                         diagnostic_label = diagnostic_label.with_message(message.clone());
                     }
@@ -57,55 +119,341 @@ fn report_error(src: &String, error: Error) -> anyhow::Result<()> {
                 .collect(),
         );
     let mut writer = StandardStream::stderr(ColorChoice::Always);
-    let config = term::Config::default();
+    // keep the columns codespan prints in line with the ones the tokenizer computes.
+    let config = term::Config {
+        tab_width: TokenizeOptions::default().tab_width,
+        ..term::Config::default()
+    };
     term::emit(&mut writer, &config, &files, &diagnostic)?;
 
     Ok(())
 }
 
 fn main() -> anyhow::Result<()> {
+    let matches = App::new("ranoc")
+        .arg(
+            Arg::with_name("todos")
+                .long("todos")
+                .help("Lists every `todo` and `unimplemented` left in the sources"),
+        )
+        .arg(
+            Arg::with_name("cfg")
+                .long("cfg")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("FLAG|KEY=VALUE")
+                .help("Sets a flag or a value for the `#[cfg(...)]` attributes"),
+        )
+        .arg(Arg::with_name("release").long("release").help(
+            "Builds without the checks of debug builds, wrapping on overflow and optimizing at -O2",
+        ))
+        .arg(
+            Arg::with_name("opt-level")
+                .short("O")
+                .takes_value(true)
+                .possible_values(&["0", "1", "2"])
+                .help("Sets how much to optimize, from 0 for debug builds to 2 for release ones"),
+        )
+        .arg(
+            Arg::with_name("overflow")
+                .long("overflow")
+                .takes_value(true)
+                .possible_values(&["trap", "wrap", "saturate"])
+                .help("Sets what the integer arithmetic does on overflow"),
+        )
+        .arg(
+            Arg::with_name("feature")
+                .long("feature")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .possible_values(codegen::FEATURES)
+                .help("Enables a WASM proposal the engine running the module supports"),
+        )
+        .arg(
+            Arg::with_name("debug")
+                .short("g")
+                .long("debug")
+                .help("Emits DWARF mapping the compiled code back to the sources"),
+        )
+        .arg(
+            Arg::with_name("max-call-depth")
+                .long("max-call-depth")
+                .takes_value(true)
+                .value_name("CALLS")
+                .help("Panics with a stack overflow once CALLS calls of the recursive functions are running"),
+        )
+        .arg(
+            Arg::with_name("cache")
+                .long("cache")
+                .takes_value(true)
+                .value_name("DIR")
+                .help("Keeps the checked modules in DIR, so the ones which didn't change aren't checked again"),
+        )
+        .arg(
+            Arg::with_name("c")
+                .long("c")
+                .conflicts_with("native")
+                .help("Compiles to portable C source, main.c and main.h, instead of running"),
+        )
+        .arg(
+            Arg::with_name("component")
+                .long("component")
+                .conflicts_with_all(&["c", "native"])
+                .help("Writes the compiled module as a component, main.component.wasm, with its interface, main.wit, instead of running"),
+        )
+        .arg(
+            Arg::with_name("interpret")
+                .long("interpret")
+                .conflicts_with_all(&["c", "component", "js", "native", "wat"])
+                .help("Runs main.rano by interpreting it rather than compiling it to WASM"),
+        )
+        .arg(
+            Arg::with_name("js")
+                .long("js")
+                .help("Writes the compiled module to main.wasm with its JavaScript glue, main.js and main.d.ts, instead of running"),
+        )
+        .arg(
+            Arg::with_name("native")
+                .long("native")
+                .help("Compiles to a native object file, main.o, with Cranelift instead of running"),
+        )
+        .arg(
+            Arg::with_name("wat")
+                .long("wat")
+                .help("Writes the compiled module to main.wat in the text format"),
+        )
+        .subcommand(SubCommand::with_name("repl").about("Evaluates Rano interactively"))
+        .get_matches();
+    if matches.subcommand_matches("repl").is_some() {
+        return repl::run();
+    }
+
     println!("Reading main.rano");
-    let src = fs::read_to_string(PathBuf::from("main.rano"))?;
+    let bytes = fs::read(PathBuf::from("main.rano"))?;
+    let mut sources = SourceMap::new();
+    let src = match syntax::decode_source(&bytes) {
+        Ok(src) => src,
+        Err(error) => {
+            sources.add("main.rano", error.lossy);
+            report_error(&sources, error.error)?;
+            bail!("Failed to read sources");
+        }
+    };
+    let main = sources.add("main.rano", src);
 
     println!("Parsing main.rano");
-    let tokens = syntax::tokenize(&src);
-    let ast = match syntax::parse(tokens) {
-        Ok(ast) => ast,
-        Err(error) => {
-            report_error(&src, error)?;
-            bail!("Failed to parse sources");
+    let tokens = sources.tokenize(main, TokenizeOptions::default());
+    let (mut ast, errors) = syntax::parse_recovering(tokens);
+    if !errors.is_empty() {
+        for error in errors {
+            report_error(&sources, error)?;
         }
+        bail!("Failed to parse sources");
+    }
+
+    let options = codegen::CompileOptions {
+        cfg: matches
+            .values_of("cfg")
+            .into_iter()
+            .flatten()
+            .fold(Cfg::new(), |cfg, option| match option.split_once('=') {
+                Some((key, value)) => cfg.value(key.trim(), value.trim().trim_matches('"')),
+                None => cfg.flag(option.trim()),
+            }),
+        overflow: match matches.value_of("overflow") {
+            Some(name) => codegen::Overflow::from_name(name).unwrap_or_default(),
+            None if matches.is_present("release") => codegen::Overflow::Wrap,
+            None => codegen::Overflow::Trap,
+        },
+        opt_level: match matches.value_of("opt-level") {
+            Some(name) => codegen::OptLevel::from_name(name).unwrap_or_default(),
+            None if matches.is_present("release") => codegen::OptLevel::O2,
+            None => codegen::OptLevel::O0,
+        },
+        target: matches.values_of("feature").into_iter().flatten().fold(
+            codegen::TargetOptions::default(),
+            |mut target, feature| {
+                target.enable(feature);
+                target
+            },
+        ),
+        dwarf: matches.is_present("debug"),
+        file_names: sources.files().map(|(_, file)| file.name.clone()).collect(),
+        source_map: None,
+        text: matches.is_present("wat"),
+        cache: matches.value_of("cache").map(codegen::Cache::new),
+        bindings: matches.is_present("js"),
+        component: if matches.is_present("component") {
+            Some("main".to_owned())
+        } else {
+            None
+        },
+        max_call_depth: match matches.value_of("max-call-depth") {
+            Some(calls) => Some(
+                calls
+                    .parse()
+                    .map_err(|_| anyhow!("Failed to read the number of calls `{}`", calls))?,
+            ),
+            None => None,
+        },
     };
+    // the lints only look at what is compiled.
+    let errors = configure(&mut ast, &options.cfg);
+    if !errors.is_empty() {
+        for error in errors {
+            report_error(&sources, error)?;
+        }
+        bail!("Failed to configure sources");
+    }
+
+    if matches.is_present("todos") {
+        for warning in semantic::lint_todos(&ast) {
+            report(&sources, warning, Severity::Warning)?;
+        }
+    }
+
+    for warning in semantic::lint_unused(&ast) {
+        report(&sources, warning, Severity::Warning)?;
+    }
+
+    if matches.is_present("interpret") {
+        println!("Interpreting main.rano");
+        let mut interpreter = Interpreter::new();
+        if let Some(calls) = options.max_call_depth {
+            interpreter.set_max_depth(calls as usize);
+        }
+        interpreter.set_options(options);
+        if let Err(errors) = interpreter.load(ast) {
+            for error in errors {
+                report_error(&sources, error)?;
+            }
+            bail!("Failed to compile sources");
+        }
+        interpreter.define_extern("show", |arguments| {
+            for argument in arguments {
+                println!("{}", argument);
+            }
+            Value::Integer(0)
+        });
+        match interpreter.call("main", Vec::new()) {
+            Ok(value) => {
+                dbg!(&value);
+            }
+            Err(error) => {
+                report_error(&sources, error)?;
+                bail!("Failed to run main.rano");
+            }
+        }
+        return Ok(());
+    }
+
+    if matches.is_present("native") {
+        println!("Compiling main.rano natively");
+        let (artifacts, errors) = codegen::compile_with(ast, &options, &codegen::Cranelift::host());
+        if !errors.is_empty() {
+            for error in errors {
+                report_error(&sources, error)?;
+            }
+            bail!("Failed to compile sources");
+        }
+        if let Some(object) = artifacts.object {
+            println!("Writing main.o");
+            fs::write(PathBuf::from("main.o"), object)?;
+        }
+        return Ok(());
+    }
+
+    if matches.is_present("c") {
+        println!("Compiling main.rano to C");
+        let (artifacts, errors) = codegen::compile_with(ast, &options, &codegen::C);
+        if !errors.is_empty() {
+            for error in errors {
+                report_error(&sources, error)?;
+            }
+            bail!("Failed to compile sources");
+        }
+        if let Some(source) = artifacts.c {
+            println!("Writing main.c");
+            fs::write(PathBuf::from("main.c"), source)?;
+        }
+        if let Some(header) = artifacts.c_header {
+            println!("Writing main.h");
+            fs::write(PathBuf::from("main.h"), header)?;
+        }
+        return Ok(());
+    }
 
     println!("Compiling main.rano");
-    let (wasm_bytes, errors) = codegen::compile_wasm(ast);
+    let (artifacts, errors) = codegen::compile_artifacts(ast, &options);
 
     if errors.len() > 0 {
         for error in errors {
-            report_error(&src, error)?;
+            report_error(&sources, error)?;
         }
         bail!("Failed to compile sources");
     }
+    let wasm_bytes = artifacts.wasm;
+    if let Some(text) = artifacts.text {
+        println!("Writing main.wat");
+        fs::write(PathBuf::from("main.wat"), text)?;
+    }
+    if let Some(component) = artifacts.component {
+        println!("Writing main.component.wasm and main.wit");
+        fs::write(PathBuf::from("main.component.wasm"), component.wasm)?;
+        fs::write(PathBuf::from("main.wit"), component.wit)?;
+        if artifacts.bindings.is_none() {
+            return Ok(());
+        }
+    }
+    if let Some(bindings) = artifacts.bindings {
+        println!("Writing main.wasm, main.js and main.d.ts");
+        fs::write(PathBuf::from("main.wasm"), &wasm_bytes)?;
+        fs::write(PathBuf::from("main.js"), bindings.js)?;
+        fs::write(PathBuf::from("main.d.ts"), bindings.dts)?;
+        return Ok(());
+    }
+    if options.target.exception_handling {
+        // wasmer can't catch what the panics are thrown as, nor even load a
+        // module with tags.
+        bail!("Failed to run main.rano: the `exception-handling` target needs `--bindings`");
+    }
 
     println!("Running main.rano");
-    let store = Store::default();
+    let store = match options.target.threads || options.target.simd {
+        // the default engine validates neither the shared memory and the
+        // atomics nor the vectors.
+        true => {
+            let mut features = Features::new();
+            features
+                .threads(options.target.threads)
+                .simd(options.target.simd)
+                .bulk_memory(true);
+            Store::new(&JIT::new(Cranelift::default()).features(features).engine())
+        }
+        false => Store::default(),
+    };
     let module = Module::new(&store, &wasm_bytes)?;
 
-    let import_object = imports! {
-        "extern" => {
-            "show" => Function::new_native(&store, external::show),
-            "Add__i32_i32" => Function::new_native(&store, ops::add_i32_i32),
-            "Subtract__i32_i32" => Function::new_native(&store, ops::subtract_i32_i32),
-            "PartialOrd__i32_i32" => Function::new_native(&store, ops::cmp_i32_i32),
-        },
-    };
+    let memory = thread::shared_memory(&module)?;
+    let import_object = create_imports(&module, memory);
     let instance = Instance::new(&module, &import_object)?;
 
     let main = instance
         .exports
         .get_function("main")
         .map_err(|_| anyhow!("Failed to find main function"))?;
-    let result = main.call(&[])?;
+    let result = main.call(&[]).map_err(|error| {
+        if let Ok(memory) = instance.exports.get_memory("memory") {
+            // nothing runs on the memory anymore once the call trapped.
+            let memory = unsafe { memory.data_unchecked() };
+            if let Some(panic) = codegen::read_panic(memory) {
+                eprintln!("panicked at {}: {}", panic.location, panic.message);
+            }
+        }
+        error
+    })?;
 
     dbg!(&result);
 
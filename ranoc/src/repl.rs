@@ -0,0 +1,148 @@
+use std::io::{self, BufRead, Write};
+
+use libranoc::{
+    core::SourceMap,
+    interpret::{Interpreter, Value},
+    syntax::{
+        self,
+        snippet::{self, ParseStatus, Snippet},
+    },
+};
+
+use crate::report_error;
+
+const HELP: &str = "\
+:trace [n]   show the last n recorded transitions (default 20)
+:back        step one transition into the past
+:forward     step one transition into the future
+:seek <step> jump to the transition numbered <step>
+:clear       forget the recorded transitions
+:quit        leave the REPL
+
+An incomplete input continues on the next line; an empty line ends it.";
+
+fn create_interpreter() -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    // the snippets are checked, so `show` is declared for them to call.
+    let prelude = syntax::parse(syntax::tokenize("extern fn show(value: i32): i32;"))
+        .expect("the prelude parses");
+    interpreter.load(prelude).expect("the prelude checks");
+    interpreter.define_extern("show", |arguments| {
+        for argument in arguments {
+            println!("{}", argument);
+        }
+        Value::Integer(0)
+    });
+    interpreter
+}
+
+fn run_command(interpreter: &mut Interpreter, command: &str) -> bool {
+    let mut words = command.split_whitespace();
+    let trace = interpreter.trace_mut();
+    match words.next() {
+        Some(":quit") | Some(":q") => return false,
+        Some(":trace") => {
+            let count = words
+                .next()
+                .and_then(|count| count.parse().ok())
+                .unwrap_or(20);
+            if trace.evicted() > 0 {
+                println!("({} older transition(s) evicted)", trace.evicted());
+            }
+            let skip = trace.len().saturating_sub(count);
+            for entry in trace.entries().skip(skip) {
+                println!("{}", entry);
+            }
+        }
+        Some(":back") => match trace.step_back() {
+            Some(entry) => println!("{}", entry),
+            None => println!("Already at the oldest transition."),
+        },
+        Some(":forward") => match trace.step_forward() {
+            Some(entry) => println!("{}", entry),
+            None => println!("Already at the latest transition."),
+        },
+        Some(":seek") => match words
+            .next()
+            .and_then(|step| step.parse().ok())
+            .and_then(|step| trace.seek(step))
+        {
+            Some(entry) => println!("{}", entry),
+            None => println!("No such transition."),
+        },
+        Some(":clear") => trace.clear(),
+        _ => println!("{}", HELP),
+    }
+    true
+}
+
+fn evaluate(interpreter: &mut Interpreter, src: &str, status: ParseStatus<Snippet>) {
+    let result = match status {
+        ParseStatus::Complete(snippet) => interpreter.load_snippet(snippet),
+        ParseStatus::Incomplete => snippet::parse_snippet(src)
+            .map_err(|error| vec![error])
+            .and_then(|snippet| interpreter.load_snippet(snippet)),
+        ParseStatus::Invalid(error) => Err(vec![error]),
+    };
+    match result {
+        Ok(values) => {
+            for value in values {
+                println!("{}", value);
+            }
+        }
+        Err(errors) => {
+            let mut sources = SourceMap::new();
+            sources.add("<repl>", src);
+            for error in errors {
+                if let Err(error) = report_error(&sources, error) {
+                    eprintln!("{}", error);
+                }
+            }
+        }
+    }
+}
+
+pub fn run() -> anyhow::Result<()> {
+    let mut interpreter = create_interpreter();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    // the lines of an input which is still incomplete, like a function
+    // whose body has not been closed yet.
+    let mut buffer = String::new();
+    loop {
+        print!("{}", if buffer.is_empty() { "> " } else { ". " });
+        io::stdout().flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        if buffer.is_empty() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with(':') {
+                if !run_command(&mut interpreter, line) {
+                    break;
+                }
+                continue;
+            }
+        } else if line.trim().is_empty() {
+            // an empty line gives up on completing the input.
+            let src = std::mem::take(&mut buffer);
+            evaluate(&mut interpreter, &src, ParseStatus::Incomplete);
+            continue;
+        }
+
+        buffer.push_str(&line);
+        buffer.push('\n');
+        let status = snippet::parse_snippet_status(&buffer);
+        if status.is_incomplete() {
+            continue;
+        }
+        let src = std::mem::take(&mut buffer);
+        evaluate(&mut interpreter, &src, status);
+    }
+
+    Ok(())
+}
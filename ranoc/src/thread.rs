@@ -0,0 +1,109 @@
+use std::{
+    sync::Mutex,
+    thread::{JoinHandle, ThreadId},
+};
+
+use anyhow::anyhow;
+use wasmer::{Instance, Memory, Module, RuntimeError, Val, WasmerEnv};
+
+type Task = JoinHandle<anyhow::Result<i32>>;
+
+/// The tasks spawned, by their ids, with the thread which spawned them,
+/// until they are joined.
+static HANDLES: Mutex<Vec<(ThreadId, Option<Task>)>> = Mutex::new(Vec::new());
+
+/// Lets `thread.spawn` create more instances of the module it lives in,
+/// given the memory it shares with them, if it was compiled for the
+/// `threads` target.
+#[derive(Clone)]
+pub struct ThreadEnv {
+    pub module: Module,
+    pub memory: Option<Memory>,
+}
+
+impl WasmerEnv for ThreadEnv {}
+
+/// The memory a module compiled for the `threads` target imports as
+/// `env.memory`, which its instances on every thread share.
+pub fn shared_memory(module: &Module) -> anyhow::Result<Option<Memory>> {
+    let import = module
+        .imports()
+        .memories()
+        .find(|import| import.module() == "env" && import.name() == "memory");
+    match import {
+        Some(import) => Ok(Some(Memory::new(module.store(), *import.ty())?)),
+        None => Ok(None),
+    }
+}
+
+fn run_task(
+    module: &Module,
+    memory: Option<Memory>,
+    slot: u32,
+    argument: i32,
+) -> anyhow::Result<i32> {
+    let instance = Instance::new(module, &crate::create_imports(module, memory))?;
+    let table = instance.exports.get_table("__indirect_function_table")?;
+    let task = match table.get(slot) {
+        Some(Val::FuncRef(task)) => task,
+        _ => return Err(anyhow!("No task in the table slot {}", slot)),
+    };
+    let result = task.call(&[Val::I32(argument)])?;
+    Ok(result.get(0).and_then(|value| value.i32()).unwrap_or(0))
+}
+
+pub fn spawn(env: &ThreadEnv, slot: i32, argument: i32) -> i32 {
+    let (module, memory) = (env.module.clone(), env.memory.clone());
+    let handle = std::thread::spawn(move || run_task(&module, memory, slot as u32, argument));
+    let mut handles = HANDLES.lock().unwrap();
+    handles.push((std::thread::current().id(), Some(handle)));
+    (handles.len() - 1) as i32
+}
+
+/// The result of the task `id`, which traps the joining thread if the task
+/// failed, or if it is unknown or already joined.
+pub fn join(id: i32) -> Result<i32, RuntimeError> {
+    let handle = HANDLES
+        .lock()
+        .unwrap()
+        .get_mut(id as usize)
+        .and_then(|(_, handle)| handle.take());
+    let handle = handle.ok_or_else(|| {
+        RuntimeError::new(format!("Joined an unknown or already joined task {}", id))
+    })?;
+    finish(id, handle)
+}
+
+/// The number of tasks spawned so far, which `join_scope` is given once the
+/// scope it starts ends.
+pub fn scope() -> i32 {
+    HANDLES.lock().unwrap().len() as i32
+}
+
+/// Joins the tasks the current thread spawned since `scope` gave `start`
+/// which aren't joined yet, trapping if one of them failed.
+pub fn join_scope(start: i32) -> Result<(), RuntimeError> {
+    let current = std::thread::current().id();
+    let handles: Vec<_> = HANDLES
+        .lock()
+        .unwrap()
+        .iter_mut()
+        .enumerate()
+        .skip(start as usize)
+        .filter(|(_, (thread, _))| *thread == current)
+        .filter_map(|(id, (_, handle))| Some((id as i32, handle.take()?)))
+        .collect();
+    // the lock isn't held while joining, as the tasks may spawn more.
+    for (id, handle) in handles {
+        finish(id, handle)?;
+    }
+    Ok(())
+}
+
+fn finish(id: i32, handle: Task) -> Result<i32, RuntimeError> {
+    match handle.join() {
+        Ok(Ok(result)) => Ok(result),
+        Ok(Err(error)) => Err(RuntimeError::new(format!("Task {} failed: {}", id, error))),
+        Err(_) => Err(RuntimeError::new(format!("Task {} panicked", id))),
+    }
+}